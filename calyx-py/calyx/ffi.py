@@ -0,0 +1,146 @@
+"""In-process bindings to the Calyx compiler and interpreter.
+
+Requires the `cffi` package and the shared library built with:
+    cargo rustc --release --lib --features ffi --crate-type cdylib
+The library is loaded from the path in the `CALYX_LIB` environment variable or
+from `target/release` in the repository.
+"""
+import json
+import os
+import sys
+from pathlib import Path
+
+from cffi import FFI
+
+_ffi = FFI()
+_ffi.cdef(
+    """
+    typedef struct CalyxProgram CalyxProgram;
+    typedef struct CalyxSim CalyxSim;
+
+    const char *calyx_last_error(void);
+    CalyxProgram *calyx_parse(const char *src, const char *file,
+                              const char *lib_path);
+    int calyx_compile(CalyxProgram *prog, const char *passes,
+                      const char *disabled, const char *opts);
+    char *calyx_emit(const CalyxProgram *prog, const char *backend);
+    void calyx_free_program(CalyxProgram *prog);
+    void calyx_free_string(char *s);
+
+    CalyxSim *calyx_sim_new(CalyxProgram *prog, const char *data);
+    int calyx_sim_step(CalyxSim *sim);
+    int calyx_sim_run(CalyxSim *sim);
+    int calyx_sim_is_done(const CalyxSim *sim);
+    char *calyx_sim_state(const CalyxSim *sim);
+    void calyx_sim_free(CalyxSim *sim);
+    """
+)
+
+_ROOT = Path(__file__).resolve().parents[2]
+_lib = None
+
+
+def _load():
+    global _lib
+    if _lib is None:
+        path = os.environ.get("CALYX_LIB")
+        if path is None:
+            name = {"darwin": "libfutil.dylib", "win32": "futil.dll"}.get(
+                sys.platform, "libfutil.so"
+            )
+            path = str(_ROOT / "target" / "release" / name)
+        _lib = _ffi.dlopen(path)
+    return _lib
+
+
+class CalyxError(Exception):
+    pass
+
+
+def _check(result, failed):
+    if result == failed:
+        msg = _load().calyx_last_error()
+        raise CalyxError(_ffi.string(msg).decode() if msg else "unknown error")
+    return result
+
+
+def _opt(s):
+    return _ffi.NULL if s is None else s.encode()
+
+
+def _list(items):
+    return _ffi.NULL if items is None else ",".join(items).encode()
+
+
+def _take_string(ptr):
+    _check(ptr, _ffi.NULL)
+    try:
+        return _ffi.string(ptr).decode()
+    finally:
+        _load().calyx_free_string(ptr)
+
+
+class Program:
+    """A Calyx program parsed from source, e.g., the output of
+    `py_ast.Program.emit()`."""
+
+    def __init__(self, src, file=None, lib_path=str(_ROOT)):
+        self._ptr = _ffi.NULL
+        self._ptr = _check(
+            _load().calyx_parse(src.encode(), _opt(file), _opt(lib_path)),
+            _ffi.NULL,
+        )
+
+    def compile(self, passes=None, disabled=None, opts=None):
+        """Run the passes, like `futil -p <passes> -d <disabled> -x <opts>`.
+        Runs the `all` alias when `passes` is None."""
+        _check(
+            _load().calyx_compile(
+                self._ptr, _list(passes), _list(disabled), _list(opts)
+            ),
+            -1,
+        )
+        return self
+
+    def emit(self, backend="calyx"):
+        """The output of the backend, like `futil -b <backend>`."""
+        return _take_string(_load().calyx_emit(self._ptr, backend.encode()))
+
+    def simulate(self, data=None):
+        """Start a simulation of the program. The program cannot be used
+        afterwards. `data` maps memory names to their initial values in the
+        format of the `--data` option of the interpreter."""
+        ptr, self._ptr = self._ptr, _ffi.NULL
+        data = None if data is None else json.dumps(data)
+        sim = _check(_load().calyx_sim_new(ptr, _opt(data)), _ffi.NULL)
+        return Simulation(sim)
+
+    def __del__(self):
+        if _lib is not None and self._ptr != _ffi.NULL:
+            _lib.calyx_free_program(self._ptr)
+
+
+class Simulation:
+    """A simulation of the `main` component of a program."""
+
+    def __init__(self, ptr):
+        self._ptr = ptr
+
+    def step(self):
+        """Simulate one cycle."""
+        _check(_load().calyx_sim_step(self._ptr), -1)
+
+    def run(self):
+        """Simulate until the program is done."""
+        _check(_load().calyx_sim_run(self._ptr), -1)
+
+    def is_done(self):
+        return _check(_load().calyx_sim_is_done(self._ptr), -1) == 1
+
+    def state(self):
+        """The values of the ports and memories of the program."""
+        return json.loads(_take_string(_load().calyx_sim_state(self._ptr)))
+
+    def __del__(self):
+        if _lib is not None:
+            _lib.calyx_sim_free(self._ptr)
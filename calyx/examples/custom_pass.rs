@@ -0,0 +1,85 @@
+//! Example of an out-of-tree pass.
+//!
+//! Defines a pass in a crate that links against `calyx`, registers it with the
+//! default [PassManager], and runs it on a Calyx program:
+//! ```text
+//! cargo run --example custom_pass -- <file> -l <path to primitives>
+//! ```
+use calyx::{
+    errors::CalyxResult,
+    frontend,
+    ir::{
+        self,
+        traversal::{Action, Named, VisResult, Visitor},
+    },
+    pass_manager::PassManager,
+    register_alias,
+};
+use std::path::PathBuf;
+
+/// Reports the number of times each group is enabled in the control program.
+#[derive(Default)]
+struct CountEnables {
+    enables: Vec<(ir::Id, u64)>,
+}
+
+impl Named for CountEnables {
+    fn name() -> &'static str {
+        "count-enables"
+    }
+
+    fn description() -> &'static str {
+        "report the number of times each group is enabled"
+    }
+}
+
+impl Visitor for CountEnables {
+    fn enable(
+        &mut self,
+        s: &mut ir::Enable,
+        _comp: &mut ir::Component,
+        _sigs: &ir::LibrarySignatures,
+    ) -> VisResult {
+        let name = s.group.borrow().name().clone();
+        match self.enables.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, count)) => *count += 1,
+            None => self.enables.push((name, 1)),
+        }
+        Ok(Action::Continue)
+    }
+
+    fn finish(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &ir::LibrarySignatures,
+    ) -> VisResult {
+        for (group, count) in &self.enables {
+            eprintln!("{}.{}: {}", comp.name, group, count);
+        }
+        Ok(Action::Continue)
+    }
+}
+
+fn main() -> CalyxResult<()> {
+    let mut args = std::env::args().skip(1);
+    let file = args.next().map(PathBuf::from);
+    let lib_path = match (args.next().as_deref(), args.next()) {
+        (Some("-l"), Some(path)) => PathBuf::from(path),
+        _ => PathBuf::from("."),
+    };
+
+    // Start with the passes defined by the compiler and add our own.
+    let mut pm = PassManager::default_passes()?;
+    pm.register_pass::<CountEnables>()?;
+    register_alias!(pm, "count-validated", ["validate", CountEnables]);
+
+    let ws = frontend::Workspace::construct(&file, &lib_path)?;
+    let mut ctx = ir::from_ast::ast_to_ir(ws, ir::BackendConf::default())?;
+    pm.execute_plan(&mut ctx, &["count-validated".to_string()], &[])?;
+
+    let out = &mut std::io::stdout();
+    for comp in &ctx.components {
+        ir::IRPrinter::write_component(comp, out)?;
+    }
+    Ok(())
+}
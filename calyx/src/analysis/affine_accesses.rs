@@ -0,0 +1,439 @@
+use crate::ir::{self, CloneName, RRC};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::rc::Rc;
+
+/// An address of the form `base + stride * index` where `index` is the value
+/// of a register.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Affine {
+    /// Register that holds the index. `None` for constant addresses.
+    pub index: Option<ir::Id>,
+    /// Multiple of the index. Zero for constant addresses.
+    pub stride: u64,
+    /// Constant added to the multiple of the index.
+    pub base: i64,
+}
+
+impl Affine {
+    fn constant(val: u64) -> Self {
+        Affine {
+            index: None,
+            stride: 0,
+            base: val as i64,
+        }
+    }
+
+    fn index(reg: ir::Id) -> Self {
+        Affine {
+            index: Some(reg),
+            stride: 1,
+            base: 0,
+        }
+    }
+
+    /// Sum of two affine expressions. Only defined if they use the same index
+    /// or at most one of them uses an index.
+    fn add(self, other: Affine) -> Option<Affine> {
+        let index = match (self.index, other.index) {
+            (None, index) | (index, None) => index,
+            (Some(l), Some(r)) if l == r => Some(l),
+            _ => return None,
+        };
+        Some(Affine {
+            index,
+            stride: self.stride + other.stride,
+            base: self.base + other.base,
+        })
+    }
+
+    /// Multiply the expression by `2^amount`.
+    fn shift(self, amount: u64) -> Option<Affine> {
+        let factor = 1u64.checked_shl(amount as u32)?;
+        Some(Affine {
+            index: self.index,
+            stride: self.stride.checked_mul(factor)?,
+            base: self.base.checked_mul(factor as i64)?,
+        })
+    }
+}
+
+impl Display for Affine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let index = match &self.index {
+            Some(index) => index,
+            None => return write!(f, "{}", self.base),
+        };
+        if self.stride != 1 {
+            write!(f, "{}*", self.stride)?;
+        }
+        write!(f, "{}", index)?;
+        match self.base {
+            0 => Ok(()),
+            base if base > 0 => write!(f, " + {}", base),
+            base => write!(f, " - {}", -base),
+        }
+    }
+}
+
+/// Resolves the values of ports to affine expressions using the assignments
+/// of a component. Guards are ignored, so a port only has an affine value if
+/// all the values written to it are the same affine expression.
+struct Resolver<'a> {
+    /// Assignments of the component.
+    assigns: Vec<&'a ir::Assignment>,
+    /// Value of the outputs of cells that have been visited. `None` marks
+    /// cells whose value is not affine.
+    cells: HashMap<ir::Id, Option<Affine>>,
+}
+
+impl<'a> Resolver<'a> {
+    /// The value written to `cell.port` if all writes have the same value.
+    fn written(&mut self, cell: &ir::Id, port: &str) -> Option<Affine> {
+        let srcs = self
+            .assigns
+            .iter()
+            .filter(|assign| {
+                let dst = assign.dst.borrow();
+                dst.name == port && dst.get_parent_name() == cell
+            })
+            .map(|assign| Rc::clone(&assign.src))
+            .collect::<Vec<_>>();
+        self.all_same(&srcs)
+    }
+
+    /// The value of the ports in `srcs` if they all have the same value.
+    fn all_same(&mut self, srcs: &[RRC<ir::Port>]) -> Option<Affine> {
+        let mut vals = srcs.iter().map(|src| self.port(src));
+        let first = vals.next().flatten()?;
+        vals.all(|val| val.as_ref() == Some(&first))
+            .then_some(first)
+    }
+
+    /// The value of `port` if it is affine.
+    fn port(&mut self, port: &RRC<ir::Port>) -> Option<Affine> {
+        let (cell, name) = {
+            let port = port.borrow();
+            match &port.parent {
+                ir::PortParent::Cell(cell) => {
+                    (cell.upgrade(), port.name.clone())
+                }
+                ir::PortParent::Group(_) => return None,
+            }
+        };
+        let cell = cell.borrow();
+        let cell_name = cell.clone_name();
+        let prim = match &cell.prototype {
+            ir::CellType::Constant { val, .. } => {
+                return Some(Affine::constant(*val))
+            }
+            ir::CellType::Primitive { name: prim, .. } if name == "out" => {
+                prim.clone()
+            }
+            _ => return None,
+        };
+        if let Some(val) = self.cells.get(&cell_name) {
+            return val.clone();
+        }
+        // Values that depend on themselves are not affine.
+        self.cells.insert(cell_name.clone(), None);
+        let val = match prim.as_ref() {
+            "std_reg" => Some(Affine::index(cell_name.clone())),
+            "std_const" => cell.get_parameter("VALUE").map(Affine::constant),
+            "std_wire" | "std_slice" | "std_pad" => {
+                self.written(&cell_name, "in")
+            }
+            "std_add" => {
+                let left = self.written(&cell_name, "left");
+                let right = self.written(&cell_name, "right");
+                left.zip(right).and_then(|(l, r)| l.add(r))
+            }
+            "std_sub" => {
+                let left = self.written(&cell_name, "left");
+                match self.written(&cell_name, "right") {
+                    Some(Affine {
+                        index: None, base, ..
+                    }) => left.map(|l| Affine {
+                        base: l.base - base,
+                        ..l
+                    }),
+                    _ => None,
+                }
+            }
+            "std_lsh" => {
+                let left = self.written(&cell_name, "left");
+                match self.written(&cell_name, "right") {
+                    Some(Affine {
+                        index: None, base, ..
+                    }) if base >= 0 => left.and_then(|l| l.shift(base as u64)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        self.cells.insert(cell_name, val.clone());
+        val
+    }
+}
+
+/// A `while` loop of the control program.
+struct Loop {
+    /// Condition port of the loop.
+    port: (ir::Id, ir::Id),
+    /// Groups enabled in the body of the loop, including nested loops.
+    groups: HashSet<ir::Id>,
+}
+
+/// Record the loops of `con` and the innermost loop that encloses the first
+/// enable of each group.
+fn find_loops(
+    con: &ir::Control,
+    stack: &mut Vec<usize>,
+    loops: &mut Vec<Loop>,
+    enclosing: &mut HashMap<ir::Id, Option<usize>>,
+) {
+    match con {
+        ir::Control::Enable(ir::Enable { group, .. }) => {
+            let name = group.clone_name();
+            for idx in stack.iter() {
+                loops[*idx].groups.insert(name.clone());
+            }
+            enclosing
+                .entry(name)
+                .or_insert_with(|| stack.last().copied());
+        }
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => {
+            for stmt in stmts {
+                find_loops(stmt, stack, loops, enclosing);
+            }
+        }
+        ir::Control::If(ir::If {
+            tbranch, fbranch, ..
+        }) => {
+            find_loops(tbranch, stack, loops, enclosing);
+            find_loops(fbranch, stack, loops, enclosing);
+        }
+        ir::Control::While(ir::While { port, body, .. }) => {
+            loops.push(Loop {
+                port: port.borrow().canonical(),
+                groups: HashSet::new(),
+            });
+            stack.push(loops.len() - 1);
+            find_loops(body, stack, loops, enclosing);
+            stack.pop();
+        }
+        ir::Control::Invoke(_)
+        | ir::Control::Empty(_)
+        | ir::Control::Debug(_) => {}
+    }
+}
+
+/// An access to a memory by a group.
+#[derive(Clone, Debug)]
+pub struct Access {
+    /// The group that accesses the memory.
+    pub group: ir::Id,
+    /// The memory.
+    pub mem: ir::Id,
+    /// The address port driven by the group.
+    pub port: ir::Id,
+    /// The address if it is affine.
+    pub addr: Option<Affine>,
+    /// True if the group writes to the memory.
+    pub write: bool,
+    /// Condition port of the innermost loop that enables the group.
+    pub loop_port: Option<(ir::Id, ir::Id)>,
+    /// Amount by which the loop increments the index of the address in each
+    /// iteration, if it is an induction variable of the loop.
+    pub step: Option<i64>,
+}
+
+impl Access {
+    /// The reason why the access prevents the iterations of its loop from
+    /// running in parallel, if any.
+    pub fn blocker(&self) -> Option<String> {
+        self.loop_port.as_ref()?;
+        match &self.addr {
+            None => Some("the address is not affine".to_string()),
+            Some(Affine {
+                index: Some(index), ..
+            }) if self.step.is_none() => Some(format!(
+                "`{}` is not an induction variable of the loop",
+                index
+            )),
+            Some(Affine { index: None, .. }) if self.write => {
+                Some("every iteration writes to the same address".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "group `{}`: {}.{} = ", self.group, self.mem, self.port)?;
+        match &self.addr {
+            Some(addr) => write!(f, "{}", addr)?,
+            None => write!(f, "?")?,
+        }
+        if let Some((cell, port)) = &self.loop_port {
+            write!(f, " in the loop on `{}.{}`", cell, port)?;
+        }
+        if let (Some(step), Some(Affine { index: Some(i), .. })) =
+            (self.step, &self.addr)
+        {
+            write!(f, " where `{}` steps by {}", i, step)?;
+        }
+        if let Some(reason) = self.blocker() {
+            write!(f, " blocks parallelization: {}", reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds the memory accesses of a component whose addresses are affine
+/// expressions `base + stride * index` of a register, and whether the
+/// register is an induction variable of the loop that performs the access.
+///
+/// Memories are cells with `@read_together` input ports, which are their
+/// address ports. Addresses are resolved through registers, constants, and
+/// `std_add`, `std_sub` by a constant, `std_lsh` by a constant, `std_wire`,
+/// `std_slice`, and `std_pad` cells. An induction variable of a loop is a
+/// register that the groups in the body of the loop only ever increment by
+/// the same constant.
+pub struct AffineAccesses {
+    accesses: Vec<Access>,
+}
+
+impl From<&ir::Component> for AffineAccesses {
+    fn from(comp: &ir::Component) -> Self {
+        let groups = comp.groups.iter().map(|g| g.borrow()).collect::<Vec<_>>();
+        let comb_groups = comp
+            .comb_groups
+            .iter()
+            .map(|g| g.borrow())
+            .collect::<Vec<_>>();
+        let mut resolver = Resolver {
+            assigns: groups
+                .iter()
+                .flat_map(|g| g.assignments.iter())
+                .chain(comb_groups.iter().flat_map(|g| g.assignments.iter()))
+                .chain(comp.continuous_assignments.iter())
+                .collect(),
+            cells: HashMap::new(),
+        };
+
+        let mut loops = vec![];
+        let mut enclosing = HashMap::new();
+        find_loops(
+            &comp.control.borrow(),
+            &mut vec![],
+            &mut loops,
+            &mut enclosing,
+        );
+
+        // Steps of the induction variables of each loop.
+        let steps = loops
+            .iter()
+            .map(|l| {
+                let mut steps: HashMap<ir::Id, Option<i64>> = HashMap::new();
+                for group in
+                    groups.iter().filter(|g| l.groups.contains(g.name()))
+                {
+                    for assign in &group.assignments {
+                        let (reg, port) = assign.dst.borrow().canonical();
+                        let is_reg = comp.find_cell(&reg).is_some_and(|c| {
+                            c.borrow()
+                                .type_name()
+                                .is_some_and(|name| name == "std_reg")
+                        });
+                        if port != "in" || !is_reg {
+                            continue;
+                        }
+                        let step = match resolver.port(&assign.src) {
+                            Some(Affine {
+                                index: Some(index),
+                                stride: 1,
+                                base,
+                            }) if index == reg && base != 0 => Some(base),
+                            _ => None,
+                        };
+                        let entry = steps.entry(reg).or_insert(step);
+                        if *entry != step {
+                            *entry = None;
+                        }
+                    }
+                }
+                steps
+            })
+            .collect::<Vec<_>>();
+
+        let mut accesses = vec![];
+        for group in &groups {
+            // Address ports driven by the group and the memories it writes.
+            let mut addrs: Vec<(_, Vec<RRC<ir::Port>>)> = vec![];
+            let mut writes = HashSet::new();
+            for assign in &group.assignments {
+                let dst = assign.dst.borrow();
+                if !matches!(dst.parent, ir::PortParent::Cell(_)) {
+                    continue;
+                }
+                if dst.attributes.has("go") {
+                    writes.insert(dst.get_parent_name());
+                }
+                if dst.direction != ir::Direction::Input
+                    || !dst.attributes.has("read_together")
+                {
+                    continue;
+                }
+                let key = dst.canonical();
+                match addrs.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, srcs)) => srcs.push(Rc::clone(&assign.src)),
+                    None => addrs.push((key, vec![Rc::clone(&assign.src)])),
+                }
+            }
+            let loop_idx = enclosing.get(group.name()).copied().flatten();
+            for ((mem, port), srcs) in addrs {
+                let addr = resolver.all_same(&srcs);
+                let step = match (&addr, loop_idx) {
+                    (
+                        Some(Affine {
+                            index: Some(index), ..
+                        }),
+                        Some(idx),
+                    ) => steps[idx].get(index).copied().flatten(),
+                    _ => None,
+                };
+                accesses.push(Access {
+                    group: group.clone_name(),
+                    write: writes.contains(&mem),
+                    mem,
+                    port,
+                    addr,
+                    loop_port: loop_idx.map(|idx| loops[idx].port.clone()),
+                    step,
+                });
+            }
+        }
+        AffineAccesses { accesses }
+    }
+}
+
+impl AffineAccesses {
+    /// All the memory accesses of the component, in the order of the groups
+    /// that perform them.
+    pub fn accesses(&self) -> &[Access] {
+        &self.accesses
+    }
+
+    /// The accesses to the memory `mem`.
+    pub fn of_memory<'a>(
+        &'a self,
+        mem: &'a ir::Id,
+    ) -> impl Iterator<Item = &'a Access> + 'a {
+        self.accesses
+            .iter()
+            .filter(move |access| access.mem == *mem)
+    }
+}
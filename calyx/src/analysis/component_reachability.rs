@@ -0,0 +1,44 @@
+use crate::ir::{self, CellType};
+use std::collections::{HashMap, HashSet};
+
+/// Computes the components that are transitively instantiated by the
+/// entrypoint of a program.
+///
+/// A component is reachable if it is the entrypoint or if a reachable
+/// component defines a cell whose prototype is the component.
+pub struct ComponentReachability {
+    reachable: HashSet<ir::Id>,
+}
+
+impl ComponentReachability {
+    /// Find the components reachable from `entrypoint` in `comps`.
+    pub fn new(comps: &[ir::Component], entrypoint: &ir::Id) -> Self {
+        let by_name: HashMap<&ir::Id, &ir::Component> =
+            comps.iter().map(|comp| (&comp.name, comp)).collect();
+
+        let mut reachable = HashSet::new();
+        let mut worklist = vec![entrypoint.clone()];
+        while let Some(name) = worklist.pop() {
+            if !reachable.insert(name.clone()) {
+                continue;
+            }
+            // Names that are not components in the program are ignored.
+            if let Some(comp) = by_name.get(&name) {
+                for cell in comp.cells.iter() {
+                    if let CellType::Component { name } =
+                        &cell.borrow().prototype
+                    {
+                        worklist.push(name.clone());
+                    }
+                }
+            }
+        }
+
+        ComponentReachability { reachable }
+    }
+
+    /// Returns true if the component named `name` is reachable.
+    pub fn is_reachable(&self, name: &ir::Id) -> bool {
+        self.reachable.contains(name)
+    }
+}
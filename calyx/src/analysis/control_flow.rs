@@ -0,0 +1,374 @@
+use crate::ir::{self, RRC};
+use petgraph::{
+    algo::{self, dominators},
+    graph::{DiGraph, NodeIndex},
+    visit::{Dfs, Reversed},
+};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// A node in a [ControlFlowGraph].
+pub enum CfgNode {
+    /// Start of the control program.
+    Entry,
+    /// End of the control program.
+    Exit,
+    /// Enable of a group.
+    Enable(RRC<ir::Group>),
+    /// Invoke of a cell.
+    Invoke(RRC<ir::Cell>),
+    /// A `@debug` statement that reads the given ports.
+    Debug(Vec<RRC<ir::Port>>),
+    /// Evaluation of the condition of an `if`. The successors of this node are
+    /// the first nodes of the branches.
+    Branch {
+        port: RRC<ir::Port>,
+        cond: Option<RRC<ir::CombGroup>>,
+    },
+    /// Evaluation of the condition of a `while`. The successors of this node
+    /// are the first node of the body and the node following the loop.
+    Loop {
+        port: RRC<ir::Port>,
+        cond: Option<RRC<ir::CombGroup>>,
+    },
+    /// Start of a `par`. The successors of this node are the first nodes of
+    /// the par's children.
+    Fork,
+    /// End of a `par`. The last nodes of the par's children are the
+    /// predecessors of this node.
+    Join,
+}
+
+/// Control-flow graph of a control program.
+///
+/// Each node represents a step in the execution of the control program and
+/// an edge `a -> b` means that `b` may execute directly after `a`. Children of
+/// a `par` are placed between a [CfgNode::Fork] and a [CfgNode::Join] node.
+///
+/// The graph supports reachability queries as well as dominator and
+/// post-dominator computations which can be used to reason about which groups
+/// are guaranteed to execute before or after another.
+pub struct ControlFlowGraph {
+    graph: DiGraph<CfgNode, ()>,
+    entry: NodeIndex,
+    exit: NodeIndex,
+}
+
+impl From<&ir::Control> for ControlFlowGraph {
+    fn from(con: &ir::Control) -> Self {
+        let mut graph = DiGraph::new();
+        let entry = graph.add_node(CfgNode::Entry);
+        let exit = graph.add_node(CfgNode::Exit);
+        let mut cfg = ControlFlowGraph { graph, entry, exit };
+        let last = cfg.build(con, vec![entry]);
+        cfg.connect(&last, exit);
+        cfg
+    }
+}
+
+impl ControlFlowGraph {
+    /// The [CfgNode::Entry] node.
+    pub fn entry(&self) -> NodeIndex {
+        self.entry
+    }
+
+    /// The [CfgNode::Exit] node.
+    pub fn exit(&self) -> NodeIndex {
+        self.exit
+    }
+
+    /// Returns the node at `idx`.
+    pub fn node(&self, idx: NodeIndex) -> &CfgNode {
+        &self.graph[idx]
+    }
+
+    /// Iterate over the indices of all the nodes in the graph.
+    pub fn nodes(&self) -> impl Iterator<Item = NodeIndex> + '_ {
+        self.graph.node_indices()
+    }
+
+    /// Nodes that may execute directly after `idx`.
+    pub fn successors(
+        &self,
+        idx: NodeIndex,
+    ) -> impl Iterator<Item = NodeIndex> + '_ {
+        self.graph.neighbors(idx)
+    }
+
+    /// Nodes that may execute directly before `idx`.
+    pub fn predecessors(
+        &self,
+        idx: NodeIndex,
+    ) -> impl Iterator<Item = NodeIndex> + '_ {
+        self.graph
+            .neighbors_directed(idx, petgraph::Direction::Incoming)
+    }
+
+    /// All the nodes that enable the group `name`.
+    pub fn enables_of(&self, name: &ir::Id) -> Vec<NodeIndex> {
+        self.nodes()
+            .filter(|idx| match self.node(*idx) {
+                CfgNode::Enable(group) => group.borrow().name() == name,
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// Returns true if `to` may execute after `from`.
+    pub fn is_reachable(&self, from: NodeIndex, to: NodeIndex) -> bool {
+        algo::has_path_connecting(&self.graph, from, to, None)
+    }
+
+    /// All the nodes that may execute after `from`, including `from` itself.
+    pub fn reachable_from(&self, from: NodeIndex) -> HashSet<NodeIndex> {
+        let mut reachable = HashSet::new();
+        let mut dfs = Dfs::new(&self.graph, from);
+        while let Some(idx) = dfs.next(&self.graph) {
+            reachable.insert(idx);
+        }
+        reachable
+    }
+
+    /// Compute the dominators of the graph. A node `a` dominates `b` if every
+    /// path from the entry to `b` goes through `a`.
+    pub fn dominators(&self) -> Dominators {
+        Dominators(dominators::simple_fast(&self.graph, self.entry))
+    }
+
+    /// Compute the post-dominators of the graph. A node `a` post-dominates
+    /// `b` if every path from `b` to the exit goes through `a`.
+    pub fn post_dominators(&self) -> Dominators {
+        Dominators(dominators::simple_fast(Reversed(&self.graph), self.exit))
+    }
+
+    /// Add edges from all nodes in `preds` to `node`.
+    fn connect(&mut self, preds: &[NodeIndex], node: NodeIndex) {
+        for pred in preds {
+            self.graph.update_edge(*pred, node, ());
+        }
+    }
+
+    /// Add a node with edges from all nodes in `preds`.
+    fn add_node(&mut self, node: CfgNode, preds: &[NodeIndex]) -> NodeIndex {
+        let idx = self.graph.add_node(node);
+        self.connect(preds, idx);
+        idx
+    }
+
+    /// Add the nodes for `con` which executes after the nodes in `preds`.
+    /// Returns the nodes that may execute last in `con`.
+    fn build(
+        &mut self,
+        con: &ir::Control,
+        preds: Vec<NodeIndex>,
+    ) -> Vec<NodeIndex> {
+        match con {
+            ir::Control::Empty(_) => preds,
+            ir::Control::Debug(ir::Debug { ports, .. }) => {
+                vec![self.add_node(CfgNode::Debug(ports.clone()), &preds)]
+            }
+            ir::Control::Enable(ir::Enable { group, .. }) => {
+                vec![self.add_node(CfgNode::Enable(Rc::clone(group)), &preds)]
+            }
+            ir::Control::Invoke(ir::Invoke { comp, .. }) => {
+                vec![self.add_node(CfgNode::Invoke(Rc::clone(comp)), &preds)]
+            }
+            ir::Control::Seq(ir::Seq { stmts, .. }) => stmts
+                .iter()
+                .fold(preds, |preds, stmt| self.build(stmt, preds)),
+            ir::Control::Par(ir::Par { stmts, .. }) => {
+                let fork = self.add_node(CfgNode::Fork, &preds);
+                let last = stmts
+                    .iter()
+                    .flat_map(|stmt| self.build(stmt, vec![fork]))
+                    .collect::<Vec<_>>();
+                vec![self.add_node(CfgNode::Join, &last)]
+            }
+            ir::Control::If(ir::If {
+                port,
+                cond,
+                tbranch,
+                fbranch,
+                ..
+            }) => {
+                let branch = self.add_node(
+                    CfgNode::Branch {
+                        port: Rc::clone(port),
+                        cond: cond.clone(),
+                    },
+                    &preds,
+                );
+                let mut last = self.build(tbranch, vec![branch]);
+                for idx in self.build(fbranch, vec![branch]) {
+                    if !last.contains(&idx) {
+                        last.push(idx);
+                    }
+                }
+                last
+            }
+            ir::Control::While(ir::While {
+                port, cond, body, ..
+            }) => {
+                let head = self.add_node(
+                    CfgNode::Loop {
+                        port: Rc::clone(port),
+                        cond: cond.clone(),
+                    },
+                    &preds,
+                );
+                let last = self.build(body, vec![head]);
+                self.connect(&last, head);
+                vec![head]
+            }
+        }
+    }
+}
+
+/// Dominator information computed by [ControlFlowGraph::dominators] or
+/// [ControlFlowGraph::post_dominators].
+pub struct Dominators(dominators::Dominators<NodeIndex>);
+
+impl Dominators {
+    /// The immediate dominator of `idx`. Returns `None` for the root of the
+    /// computation.
+    pub fn immediate_dominator(&self, idx: NodeIndex) -> Option<NodeIndex> {
+        self.0.immediate_dominator(idx)
+    }
+
+    /// Returns true if `a` dominates `b`. Every node dominates itself.
+    pub fn dominates(&self, a: NodeIndex, b: NodeIndex) -> bool {
+        self.0
+            .dominators(b)
+            .map(|mut doms| doms.any(|idx| idx == a))
+            .unwrap_or(false)
+    }
+}
+
+/// To run the control flow graph tests:
+/// ```bash
+/// cargo test -p calyx control_flow
+/// ```
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{Builder, LibrarySignatures};
+
+    /// Builds the control program returned by `build` for a component with
+    /// the groups `a` to `e` and a 1-bit condition port.
+    fn cfg_of<F>(build: F) -> (ControlFlowGraph, Vec<NodeIndex>)
+    where
+        F: FnOnce(&[RRC<ir::Group>], &RRC<ir::Port>) -> ir::Control,
+    {
+        let sigs = LibrarySignatures::default();
+        let mut comp = ir::Component::new(
+            "main",
+            Vec::<(&str, u64, ir::Direction, ir::Attributes)>::new(),
+        );
+        let mut builder = Builder::new(&mut comp, &sigs);
+        let groups = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|name| builder.add_group(*name))
+            .collect::<Vec<_>>();
+        let port = builder.add_constant(1, 1).borrow().get("out");
+        let cfg = ControlFlowGraph::from(&build(&groups, &port));
+        let nodes = groups
+            .iter()
+            .map(|group| {
+                let enables = cfg.enables_of(group.borrow().name());
+                enables.first().copied().unwrap_or_else(|| cfg.exit())
+            })
+            .collect();
+        (cfg, nodes)
+    }
+
+    fn enable(group: &RRC<ir::Group>) -> ir::Control {
+        ir::Control::enable(Rc::clone(group))
+    }
+
+    #[test]
+    fn if_inside_while() {
+        // seq { a; while p { if p { b; } else { c; } d; } e; }
+        let (cfg, n) = cfg_of(|g, p| {
+            ir::Control::seq(vec![
+                enable(&g[0]),
+                ir::Control::while_(
+                    Rc::clone(p),
+                    None,
+                    Box::new(ir::Control::seq(vec![
+                        ir::Control::if_(
+                            Rc::clone(p),
+                            None,
+                            Box::new(enable(&g[1])),
+                            Box::new(enable(&g[2])),
+                        ),
+                        enable(&g[3]),
+                    ])),
+                ),
+                enable(&g[4]),
+            ])
+        });
+        let (a, b, c, d, e) = (n[0], n[1], n[2], n[3], n[4]);
+        let head = cfg.successors(a).next().unwrap();
+        assert!(matches!(cfg.node(head), CfgNode::Loop { .. }));
+
+        let doms = cfg.dominators();
+        assert!(n.iter().all(|idx| doms.dominates(a, *idx)));
+        assert!(n[1..].iter().all(|idx| doms.dominates(head, *idx)));
+        assert!(!doms.dominates(b, d) && !doms.dominates(c, d));
+        // The loop may not execute its body.
+        assert!(!doms.dominates(d, e));
+        assert_eq!(doms.immediate_dominator(e), Some(head));
+        let branch = doms.immediate_dominator(d).unwrap();
+        assert!(matches!(cfg.node(branch), CfgNode::Branch { .. }));
+        assert_eq!(doms.immediate_dominator(b), Some(branch));
+
+        let post_doms = cfg.post_dominators();
+        assert!(n.iter().all(|idx| post_doms.dominates(e, *idx)));
+        assert!(post_doms.dominates(d, b) && post_doms.dominates(d, c));
+        assert!(!post_doms.dominates(b, branch));
+        assert!(post_doms.dominates(head, d));
+        assert_eq!(post_doms.immediate_dominator(d), Some(head));
+    }
+
+    #[test]
+    fn while_inside_if() {
+        // seq { if p { while p { a; } b; } else { c; } d; }
+        let (cfg, n) = cfg_of(|g, p| {
+            ir::Control::seq(vec![
+                ir::Control::if_(
+                    Rc::clone(p),
+                    None,
+                    Box::new(ir::Control::seq(vec![
+                        ir::Control::while_(
+                            Rc::clone(p),
+                            None,
+                            Box::new(enable(&g[0])),
+                        ),
+                        enable(&g[1]),
+                    ])),
+                    Box::new(enable(&g[2])),
+                ),
+                enable(&g[3]),
+            ])
+        });
+        let (a, b, c, d) = (n[0], n[1], n[2], n[3]);
+        let branch = cfg.successors(cfg.entry()).next().unwrap();
+        let head = cfg.predecessors(b).next().unwrap();
+        assert!(matches!(cfg.node(head), CfgNode::Loop { .. }));
+
+        let doms = cfg.dominators();
+        assert!(doms.dominates(head, a) && doms.dominates(head, b));
+        assert!(!doms.dominates(a, b));
+        assert!(!doms.dominates(head, d) && !doms.dominates(c, d));
+        assert_eq!(doms.immediate_dominator(d), Some(branch));
+
+        let post_doms = cfg.post_dominators();
+        assert!([a, b, c, head, branch]
+            .iter()
+            .all(|idx| post_doms.dominates(d, *idx)));
+        assert!(post_doms.dominates(b, a) && post_doms.dominates(head, a));
+        assert!(!post_doms.dominates(b, branch));
+        assert!(!post_doms.dominates(c, branch));
+        assert_eq!(post_doms.immediate_dominator(branch), Some(d));
+    }
+}
@@ -32,7 +32,9 @@ fn construct(
     used_ports: &mut HashMap<ir::Id, Vec<RRC<ir::Port>>>,
 ) {
     match con {
-        ir::Control::Enable(_) | ir::Control::Empty(_) => {}
+        ir::Control::Enable(_)
+        | ir::Control::Empty(_)
+        | ir::Control::Debug(_) => {}
         ir::Control::Invoke(ir::Invoke {
             comb_group, inputs, ..
         }) => {
@@ -0,0 +1,229 @@
+use crate::ir::{self, CloneName, RRC};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+
+/// How the states of an [Fsm] are encoded in its register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// Every state is a different number.
+    Binary,
+    /// Every state sets a different bit of the register.
+    OneHot,
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Encoding::Binary => write!(f, "binary"),
+            Encoding::OneHot => write!(f, "one-hot"),
+        }
+    }
+}
+
+/// A register that holds the state of a finite-state machine.
+#[derive(Clone, Debug)]
+pub struct Fsm {
+    /// Name of the register.
+    pub reg: ir::Id,
+    /// Width of the register.
+    pub width: u64,
+    /// Number of states, including the reset state `0`.
+    pub states: u64,
+    /// Encoding of the states.
+    pub encoding: Encoding,
+}
+
+impl Display for Fsm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}`: {} states, {} encoding, {}-bit register",
+            self.reg, self.states, self.encoding, self.width
+        )
+    }
+}
+
+/// What is known about the uses of a register.
+#[derive(Default)]
+struct RegUses {
+    /// Constants written to the register or compared with its value.
+    values: BTreeSet<u64>,
+    /// The register is written with its value plus a constant.
+    counter: bool,
+    /// The value of the register is compared with a constant in a guard.
+    compared: bool,
+    /// The register is written with a value that is not a state.
+    other_writes: bool,
+}
+
+/// Finds the registers that implement the finite-state machines generated
+/// by control compilation.
+///
+/// A `std_reg` is a state register if it is only written with constants or
+/// with its own value incremented by a constant, and its value is compared
+/// with constants in guards. The states are the constants that are written
+/// to the register or that its value is compared with. The reset value `0`
+/// is always a state and counters have a state for every value up to the
+/// largest constant.
+pub struct FsmStates {
+    fsms: Vec<Fsm>,
+}
+
+impl FsmStates {
+    /// The state registers in the order in which their cells are defined.
+    pub fn fsms(&self) -> &[Fsm] {
+        &self.fsms
+    }
+}
+
+/// The value of `port` if it is the output of a constant.
+fn constant(port: &RRC<ir::Port>) -> Option<u64> {
+    if let ir::PortParent::Cell(cell) = &port.borrow().parent {
+        if let ir::CellType::Constant { val, .. } =
+            cell.upgrade().borrow().prototype
+        {
+            return Some(val);
+        }
+    }
+    None
+}
+
+/// The comparisons between two ports in `guard`.
+fn comparisons(
+    guard: &ir::Guard,
+    cmps: &mut Vec<(RRC<ir::Port>, RRC<ir::Port>)>,
+) {
+    match guard {
+        ir::Guard::Or(l, r) | ir::Guard::And(l, r) => {
+            comparisons(l, cmps);
+            comparisons(r, cmps);
+        }
+        ir::Guard::Not(g) => comparisons(g, cmps),
+        ir::Guard::Eq(l, r)
+        | ir::Guard::Neq(l, r)
+        | ir::Guard::Gt(l, r)
+        | ir::Guard::Lt(l, r)
+        | ir::Guard::Geq(l, r)
+        | ir::Guard::Leq(l, r) => cmps.push((l.clone(), r.clone())),
+        ir::Guard::True | ir::Guard::Port(_) => {}
+    }
+}
+
+impl From<&ir::Component> for FsmStates {
+    fn from(comp: &ir::Component) -> Self {
+        let assigns = comp
+            .groups
+            .iter()
+            .flat_map(|g| g.borrow().assignments.clone())
+            .chain(
+                comp.comb_groups
+                    .iter()
+                    .flat_map(|g| g.borrow().assignments.clone()),
+            )
+            .chain(comp.continuous_assignments.iter().cloned())
+            .collect::<Vec<_>>();
+
+        // Sources of the inputs of every adder.
+        let mut adder_inputs: HashMap<ir::Id, Vec<RRC<ir::Port>>> =
+            HashMap::new();
+        for assign in &assigns {
+            let dst = assign.dst.borrow();
+            if let ir::PortParent::Cell(cell) = &dst.parent {
+                let cell = cell.upgrade();
+                let cell = cell.borrow();
+                if cell.type_name() == Some(&"std_add".into()) {
+                    adder_inputs
+                        .entry(cell.clone_name())
+                        .or_default()
+                        .push(assign.src.clone());
+                }
+            }
+        }
+
+        let mut uses: HashMap<ir::Id, RegUses> = comp
+            .cells
+            .iter()
+            .filter(|cell| cell.borrow().type_name() == Some(&"std_reg".into()))
+            .map(|cell| (cell.clone_name(), RegUses::default()))
+            .collect();
+
+        for assign in &assigns {
+            // Writes to the state register.
+            let (parent, port) = assign.dst.borrow().canonical();
+            if let Some(reg) = uses.get_mut(&parent).filter(|_| port == "in") {
+                if let Some(val) = constant(&assign.src) {
+                    reg.values.insert(val);
+                } else {
+                    let (src, src_port) = assign.src.borrow().canonical();
+                    let increments = src_port == "out"
+                        && adder_inputs.get(&src).is_some_and(|inputs| {
+                            inputs.iter().any(|p| {
+                                p.borrow().canonical()
+                                    == (parent.clone(), "out".into())
+                            }) && inputs.iter().all(|p| {
+                                constant(p).is_some()
+                                    || p.borrow().get_parent_name() == parent
+                            })
+                        });
+                    if increments {
+                        reg.counter = true;
+                    } else {
+                        reg.other_writes = true;
+                    }
+                }
+            }
+
+            // Comparisons of the state with constants.
+            let mut cmps = vec![];
+            comparisons(&assign.guard, &mut cmps);
+            for (l, r) in cmps {
+                for (state, other) in [(&l, &r), (&r, &l)] {
+                    let (parent, port) = state.borrow().canonical();
+                    match (uses.get_mut(&parent), constant(other)) {
+                        (Some(reg), Some(val)) if port == "out" => {
+                            reg.values.insert(val);
+                            reg.compared = true;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let fsms = comp
+            .cells
+            .iter()
+            .filter_map(|cell| {
+                let cell = cell.borrow();
+                let reg = uses.remove(cell.name())?;
+                if !reg.compared || reg.other_writes {
+                    return None;
+                }
+                let width = cell.get_parameter("WIDTH")?;
+                let mut values = reg.values;
+                values.insert(0);
+                let one_hot = !reg.counter
+                    && values.len() > 3
+                    && values.iter().all(|v| v & v.wrapping_sub(1) == 0)
+                    && values.len() as u64 == width + 1;
+                let states = if reg.counter {
+                    values.iter().max().map_or(1, |max| max + 1)
+                } else {
+                    values.len() as u64
+                };
+                Some(Fsm {
+                    reg: cell.clone_name(),
+                    width,
+                    states,
+                    encoding: if one_hot {
+                        Encoding::OneHot
+                    } else {
+                        Encoding::Binary
+                    },
+                })
+            })
+            .collect();
+
+        FsmStates { fsms }
+    }
+}
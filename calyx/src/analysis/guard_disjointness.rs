@@ -0,0 +1,169 @@
+use crate::ir::{self, RRC};
+
+/// Possible outcomes of comparing two values, stored as a bit set.
+const LT: u8 = 1;
+const EQ: u8 = 2;
+const GT: u8 = 4;
+const ALL: u8 = LT | EQ | GT;
+
+/// Proves that two guards are never true in the same cycle.
+///
+/// The analysis decomposes the guards into comparisons and checks whether
+/// some pair of comparisons cannot hold at the same time:
+/// - `x` and `!x`;
+/// - comparisons of a port with constants that select disjoint ranges of
+///   values, such as `fsm.out == 1` and `fsm.out >= 2`;
+/// - comparisons of the same two ports with disjoint outcomes, such as
+///   `a.out < b.out` and `a.out >= b.out`.
+///
+/// The analysis is conservative: a result of `false` means that the guards
+/// could not be proved disjoint.
+pub struct GuardDisjointness;
+
+impl GuardDisjointness {
+    /// Returns true if `g1` and `g2` are never true in the same cycle.
+    pub fn disjoint(g1: &ir::Guard, g2: &ir::Guard) -> bool {
+        disjoint(g1, true, g2, true)
+    }
+
+    /// Returns true if every pair of guards in `guards` is disjoint.
+    pub fn pairwise_disjoint(guards: &[&ir::Guard]) -> bool {
+        guards.iter().enumerate().all(|(idx, g1)| {
+            guards[idx + 1..].iter().all(|g2| Self::disjoint(g1, g2))
+        })
+    }
+}
+
+/// Returns true if `g1` (negated if `p1` is false) and `g2` (negated if `p2`
+/// is false) are never true at the same time.
+fn disjoint(g1: &ir::Guard, p1: bool, g2: &ir::Guard, p2: bool) -> bool {
+    match (g1, p1) {
+        (ir::Guard::Not(g), _) => return disjoint(g, !p1, g2, p2),
+        // Every disjunct has to be disjoint from the other guard.
+        (ir::Guard::Or(l, r), true) | (ir::Guard::And(l, r), false) => {
+            return disjoint(l, p1, g2, p2) && disjoint(r, p1, g2, p2)
+        }
+        // It is enough for one of the conjuncts to be disjoint.
+        (ir::Guard::And(l, r), true) | (ir::Guard::Or(l, r), false) => {
+            return disjoint(l, p1, g2, p2) || disjoint(r, p1, g2, p2)
+        }
+        // `!true` is never true.
+        (ir::Guard::True, false) => return true,
+        _ => (),
+    }
+    match (g2, p2) {
+        (ir::Guard::Not(_), _)
+        | (ir::Guard::Or(..), _)
+        | (ir::Guard::And(..), _)
+        | (ir::Guard::True, false) => disjoint(g2, p2, g1, p1),
+        _ => match (Atom::from_guard(g1, p1), Atom::from_guard(g2, p2)) {
+            (Some(a1), Some(a2)) => a1.disjoint(&a2),
+            _ => false,
+        },
+    }
+}
+
+/// A (cell, port) pair used as a key.
+type Key = (ir::Id, ir::Id);
+
+/// A comparison that cannot be decomposed further.
+enum Atom {
+    /// The value of the port is in one of the inclusive ranges.
+    Range(Key, Vec<(u64, u64)>),
+    /// Comparing the first port with the second has one of the outcomes.
+    Cmp(Key, Key, u8),
+}
+
+/// Value of `port` if it is a constant.
+fn constant(port: &RRC<ir::Port>) -> Option<u64> {
+    if let ir::PortParent::Cell(cell) = &port.borrow().parent {
+        if let ir::CellType::Constant { val, .. } =
+            cell.upgrade().borrow().prototype
+        {
+            return Some(val);
+        }
+    }
+    None
+}
+
+/// Swap the `LT` and `GT` outcomes of a comparison.
+fn flip(outcomes: u8) -> u8 {
+    (outcomes & EQ) | ((outcomes & LT) << 2) | ((outcomes & GT) >> 2)
+}
+
+impl Atom {
+    /// The comparison performed by `guard`, negated if `pos` is false.
+    fn from_guard(guard: &ir::Guard, pos: bool) -> Option<Self> {
+        let (left, right, outcomes) = match guard {
+            ir::Guard::Port(port) => {
+                // `x` is the same as `x != 0`.
+                let outcomes = if pos { GT } else { EQ };
+                return Some(Self::range(port, 0, outcomes));
+            }
+            ir::Guard::Eq(l, r) => (l, r, EQ),
+            ir::Guard::Neq(l, r) => (l, r, LT | GT),
+            ir::Guard::Lt(l, r) => (l, r, LT),
+            ir::Guard::Leq(l, r) => (l, r, LT | EQ),
+            ir::Guard::Gt(l, r) => (l, r, GT),
+            ir::Guard::Geq(l, r) => (l, r, GT | EQ),
+            ir::Guard::Or(..)
+            | ir::Guard::And(..)
+            | ir::Guard::Not(_)
+            | ir::Guard::True => return None,
+        };
+        let outcomes = if pos { outcomes } else { ALL ^ outcomes };
+        match (constant(left), constant(right)) {
+            (None, Some(val)) => Some(Self::range(left, val, outcomes)),
+            (Some(val), None) => Some(Self::range(right, val, flip(outcomes))),
+            (Some(_), Some(_)) => None,
+            (None, None) => {
+                let l = left.borrow().canonical();
+                let r = right.borrow().canonical();
+                Some(if l <= r {
+                    Atom::Cmp(l, r, outcomes)
+                } else {
+                    Atom::Cmp(r, l, flip(outcomes))
+                })
+            }
+        }
+    }
+
+    /// The values of `port` whose comparison with `val` has one of the
+    /// `outcomes`.
+    fn range(port: &RRC<ir::Port>, val: u64, outcomes: u8) -> Self {
+        let port = port.borrow();
+        let max = if port.width >= 64 {
+            u64::MAX
+        } else {
+            (1 << port.width) - 1
+        };
+        let mut ranges = vec![];
+        if outcomes & LT != 0 && val > 0 {
+            ranges.push((0, (val - 1).min(max)));
+        }
+        if outcomes & EQ != 0 && val <= max {
+            ranges.push((val, val));
+        }
+        if outcomes & GT != 0 && val < max {
+            ranges.push((val + 1, max));
+        }
+        Atom::Range(port.canonical(), ranges)
+    }
+
+    /// Returns true if the comparisons never hold at the same time.
+    fn disjoint(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Atom::Range(p1, r1), Atom::Range(p2, r2)) if p1 == p2 => {
+                r1.iter().all(|(lo1, hi1)| {
+                    r2.iter().all(|(lo2, hi2)| hi1 < lo2 || hi2 < lo1)
+                })
+            }
+            (Atom::Cmp(l1, r1, o1), Atom::Cmp(l2, r2, o2))
+                if l1 == l2 && r1 == r2 =>
+            {
+                o1 & o2 == 0
+            }
+            _ => false,
+        }
+    }
+}
@@ -386,6 +386,16 @@ fn build_live_ranges(
 ) -> (Prop, Prop, Prop) {
     match c {
         ir::Control::Empty(_) => (alive, gens, kills),
+        ir::Control::Debug(ir::Debug { ports, .. }) => {
+            // registers printed by the statement are read by it
+            let mut alive = alive;
+            for port in ports {
+                if let Some(cell) = LiveRangeAnalysis::port_to_cell_name(port) {
+                    alive.insert(cell)
+                }
+            }
+            (alive, gens, kills)
+        }
         ir::Control::Invoke(invoke) => {
             let (reads, writes) =
                 LiveRangeAnalysis::find_gen_kill_invoke(invoke);
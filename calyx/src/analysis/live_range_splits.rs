@@ -0,0 +1,133 @@
+use super::reaching_defns::{GroupOrInvoke, ReachingDefinitionAnalysis};
+use super::ReadWriteSet;
+use crate::ir::{self, CloneName, RRC};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// A cell that is shared by two groups and has to be split before the groups
+/// can run in parallel.
+pub enum Split {
+    /// A combinational primitive used by both groups. The second group can
+    /// use a copy of the primitive instead.
+    Comb(RRC<ir::Cell>),
+    /// A register that is written by the second group to start a new live
+    /// range. The live range can be moved to a new register by renaming the
+    /// register in the given groups.
+    Register(RRC<ir::Cell>, Vec<ir::Id>),
+}
+
+impl Split {
+    /// The cell that has to be split.
+    pub fn cell(&self) -> &RRC<ir::Cell> {
+        match self {
+            Split::Comb(cell) | Split::Register(cell, _) => cell,
+        }
+    }
+}
+
+/// Determines which shared cells prevent two groups that run one after the
+/// other from running in parallel and whether they can be split.
+///
+/// The live ranges of registers are computed using
+/// [ReachingDefinitionAnalysis] and inherit its assumption that every group
+/// is enabled at most once in the control program.
+pub struct LiveRangeSplits {
+    /// The live ranges of each register, given as the groups that define or
+    /// use the values in the live range.
+    live_ranges: BTreeMap<ir::Id, Vec<BTreeSet<(ir::Id, GroupOrInvoke)>>>,
+}
+
+impl From<&ir::Component> for LiveRangeSplits {
+    fn from(comp: &ir::Component) -> Self {
+        let analysis =
+            ReachingDefinitionAnalysis::new(comp, &comp.control.borrow());
+        LiveRangeSplits {
+            live_ranges: analysis
+                .calculate_overlap(&comp.continuous_assignments),
+        }
+    }
+}
+
+/// Names of the cells in `cells`.
+fn names(cells: ir::CellIterator<'_>) -> HashSet<ir::Id> {
+    cells.map(|cell| cell.clone_name()).collect()
+}
+
+impl LiveRangeSplits {
+    /// The groups in the live range of `reg` that `group` defines, if it
+    /// only contains groups.
+    fn live_range(&self, reg: &ir::Id, group: &ir::Id) -> Option<Vec<ir::Id>> {
+        let def = (reg.clone(), GroupOrInvoke::Group(group.clone()));
+        let range = self
+            .live_ranges
+            .get(reg)?
+            .iter()
+            .find(|r| r.contains(&def))?;
+        range
+            .iter()
+            .map(|(_, loc)| match loc {
+                GroupOrInvoke::Group(g) if g != "__continuous" => {
+                    Some(g.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The cells that have to be split to run `first` and `second` in
+    /// parallel. Returns `None` if `second` depends on a value computed by
+    /// `first` or a shared cell cannot be split.
+    pub fn splits(
+        &self,
+        first: &ir::Group,
+        second: &ir::Group,
+    ) -> Option<Vec<Split>> {
+        let first_writes = names(ReadWriteSet::write_set(&first.assignments));
+        let second_writes = names(ReadWriteSet::write_set(&second.assignments));
+        let first_uses = names(ReadWriteSet::uses(&first.assignments));
+        let second_uses = names(ReadWriteSet::uses(&second.assignments));
+        let second_reads_out = ReadWriteSet::port_read_set(&second.assignments)
+            .filter(|port| port.borrow().name == "out")
+            .map(|port| port.borrow().get_parent_name())
+            .collect::<HashSet<_>>();
+
+        let mut shared = first_writes
+            .intersection(&second_uses)
+            .chain(second_writes.intersection(&first_uses))
+            .cloned()
+            .collect::<Vec<_>>();
+        shared.sort();
+        shared.dedup();
+
+        let cells = ReadWriteSet::uses(&second.assignments)
+            .filter(|cell| shared.contains(cell.borrow().name()))
+            .collect::<Vec<_>>();
+        cells
+            .into_iter()
+            .map(|cell| {
+                let name = cell.clone_name();
+                let (prim, is_comb) = match &cell.borrow().prototype {
+                    ir::CellType::Primitive { name, is_comb, .. } => {
+                        (name.clone(), *is_comb)
+                    }
+                    _ => return None,
+                };
+                if is_comb {
+                    return Some(Split::Comb(cell));
+                }
+                // The second group must start a new live range that does not
+                // contain the first group.
+                if prim != "std_reg"
+                    || !second_writes.contains(&name)
+                    || second_reads_out.contains(&name)
+                {
+                    return None;
+                }
+                let groups = self.live_range(&name, second.name())?;
+                if groups.contains(first.name()) {
+                    return None;
+                }
+                Some(Split::Register(cell, groups))
+            })
+            .collect()
+    }
+}
@@ -3,19 +3,35 @@
 //! The analyses construct data-structures that make answering certain queries
 //! about Calyx programs easier.
 
+mod affine_accesses;
+mod component_reachability;
+mod control_flow;
 mod control_ports;
+mod fsm_states;
 mod graph;
 mod graph_coloring;
+mod guard_disjointness;
 mod live_range_analysis;
+mod live_range_splits;
 pub mod reaching_defns;
 mod read_write_set;
 mod schedule_conflicts;
+mod schedule_length;
+mod value_ranges;
 mod variable_detection;
 
+pub use affine_accesses::{Access, Affine, AffineAccesses};
+pub use component_reachability::ComponentReachability;
+pub use control_flow::{CfgNode, ControlFlowGraph, Dominators};
 pub use control_ports::ControlPorts;
+pub use fsm_states::{Encoding, Fsm, FsmStates};
 pub use graph::GraphAnalysis;
 pub use graph_coloring::GraphColoring;
+pub use guard_disjointness::GuardDisjointness;
 pub use live_range_analysis::LiveRangeAnalysis;
+pub use live_range_splits::{LiveRangeSplits, Split};
 pub use read_write_set::ReadWriteSet;
 pub use schedule_conflicts::ScheduleConflicts;
+pub use schedule_length::{Estimate, PathNode, ScheduleLength};
+pub use value_ranges::{Range, ValueRanges};
 pub use variable_detection::VariableDetection;
@@ -453,6 +453,6 @@ fn build_reaching_def(
 
             (cur_reach, killed)
         }
-        ir::Control::Empty(_) => (reach, killed),
+        ir::Control::Empty(_) | ir::Control::Debug(_) => (reach, killed),
     }
 }
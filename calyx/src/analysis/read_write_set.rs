@@ -1,8 +1,9 @@
-use crate::ir::{self, CloneName};
+use crate::ir::{self, CloneName, RRC};
 use itertools::Itertools;
 use std::rc::Rc;
 
-/// Calcuate the reads-from and writes-to set for a given set of assignments.
+/// Calcuate the reads-from and writes-to set for a given set of assignments
+/// or control program.
 pub struct ReadWriteSet;
 
 impl ReadWriteSet {
@@ -33,10 +34,9 @@ impl ReadWriteSet {
         }
     }
 
-    /// Returns [ir::Cell] which are read from in the assignments.
-    /// **Ignores** reads from group holes.
-    pub fn read_set(assigns: &[ir::Assignment]) -> ir::CellIterator<'_> {
-        let iter = Self::port_read_set(assigns)
+    /// The cells that define `ports`, which must not be group holes.
+    fn parent_cells<'a>(ports: ir::PortIterator<'a>) -> ir::CellIterator<'a> {
+        let iter = ports
             .map(|port_ref| {
                 let port = port_ref.borrow();
                 if let ir::PortParent::Cell(cell_wref) = &port.parent {
@@ -52,23 +52,16 @@ impl ReadWriteSet {
         }
     }
 
+    /// Returns [ir::Cell] which are read from in the assignments.
+    /// **Ignores** reads from group holes.
+    pub fn read_set(assigns: &[ir::Assignment]) -> ir::CellIterator<'_> {
+        Self::parent_cells(Self::port_read_set(assigns))
+    }
+
     /// Returns [ir::Cell] which are written to by the assignments.
     /// **Ignores** reads from group holes.
     pub fn write_set(assigns: &[ir::Assignment]) -> ir::CellIterator<'_> {
-        let iter = Self::port_write_set(assigns)
-            .map(|port_ref| {
-                let port = port_ref.borrow();
-                if let ir::PortParent::Cell(cell_wref) = &port.parent {
-                    Rc::clone(&cell_wref.upgrade())
-                } else {
-                    unreachable!()
-                }
-            })
-            .unique_by(|cell| cell.clone_name());
-
-        ir::CellIterator {
-            iter: Box::new(iter),
-        }
+        Self::parent_cells(Self::port_write_set(assigns))
     }
 
     /// Returns the register cells whose out port is read anywhere in the given
@@ -145,4 +138,141 @@ impl ReadWriteSet {
             iter: Box::new(iter),
         }
     }
+
+    /// Adds the ports read and written by `con` to `reads` and `writes`.
+    fn control_ports(
+        con: &ir::Control,
+        reads: &mut Vec<RRC<ir::Port>>,
+        writes: &mut Vec<RRC<ir::Port>>,
+    ) {
+        let comb_group =
+            |group: &Option<RRC<ir::CombGroup>>,
+             reads: &mut Vec<RRC<ir::Port>>,
+             writes: &mut Vec<RRC<ir::Port>>| {
+                if let Some(group) = group {
+                    let group = group.borrow();
+                    reads.extend(Self::port_read_set(&group.assignments));
+                    writes.extend(Self::port_write_set(&group.assignments));
+                }
+            };
+        match con {
+            ir::Control::Empty(_) => {}
+            ir::Control::Debug(debug) => {
+                reads.extend(debug.ports.iter().cloned());
+            }
+            ir::Control::Enable(ir::Enable { group, .. }) => {
+                let group = group.borrow();
+                reads.extend(Self::port_read_set(&group.assignments));
+                writes.extend(Self::port_write_set(&group.assignments));
+            }
+            ir::Control::Seq(ir::Seq { stmts, .. })
+            | ir::Control::Par(ir::Par { stmts, .. }) => {
+                for stmt in stmts {
+                    Self::control_ports(stmt, reads, writes);
+                }
+            }
+            ir::Control::If(ir::If {
+                port,
+                cond,
+                tbranch,
+                fbranch,
+                ..
+            }) => {
+                reads.push(Rc::clone(port));
+                comb_group(cond, reads, writes);
+                Self::control_ports(tbranch, reads, writes);
+                Self::control_ports(fbranch, reads, writes);
+            }
+            ir::Control::While(ir::While {
+                port, cond, body, ..
+            }) => {
+                reads.push(Rc::clone(port));
+                comb_group(cond, reads, writes);
+                Self::control_ports(body, reads, writes);
+            }
+            ir::Control::Invoke(ir::Invoke {
+                comp,
+                inputs,
+                outputs,
+                comb_group: with,
+                interface,
+                ..
+            }) => {
+                let cell = comp.borrow();
+                // The invoke drives the inputs and the `go` port of the
+                // invoked interface of the cell and reads its outputs and the
+                // `done` port of the interface.
+                for (name, port) in inputs {
+                    reads.push(Rc::clone(port));
+                    writes.extend(cell.find(name));
+                }
+                for (name, port) in outputs {
+                    reads.extend(cell.find(name));
+                    writes.push(Rc::clone(port));
+                }
+                if let Some((go, done)) =
+                    cell.find_interface(interface.as_ref())
+                {
+                    writes.push(go);
+                    reads.push(done);
+                }
+                comb_group(with, reads, writes);
+            }
+        }
+    }
+
+    /// Returns [ir::Port] which are read from by the control program. This
+    /// includes the reads of the groups it enables, the `with` groups and
+    /// ports of its conditions, the arguments and outputs of its `invoke`
+    /// statements, and the ports printed by `@debug` statements.
+    /// **Ignores** reads from group holes.
+    pub fn control_port_read_set(con: &ir::Control) -> ir::PortIterator<'_> {
+        let (mut reads, mut writes) = (vec![], vec![]);
+        Self::control_ports(con, &mut reads, &mut writes);
+        let iter = reads.into_iter().filter(|port| {
+            matches!(port.borrow().parent, ir::PortParent::Cell(_))
+        });
+        ir::PortIterator {
+            port_iter: Box::new(iter),
+        }
+    }
+
+    /// Returns [ir::Port] which are written to by the control program. This
+    /// includes the writes of the groups it enables and their `with` groups,
+    /// and the inputs of the cells it invokes along with the ports their
+    /// outputs are connected to.
+    /// **Ignores** writes to group holes.
+    pub fn control_port_write_set(con: &ir::Control) -> ir::PortIterator<'_> {
+        let (mut reads, mut writes) = (vec![], vec![]);
+        Self::control_ports(con, &mut reads, &mut writes);
+        let iter = writes.into_iter().filter(|port| {
+            matches!(port.borrow().parent, ir::PortParent::Cell(_))
+        });
+        ir::PortIterator {
+            port_iter: Box::new(iter),
+        }
+    }
+
+    /// Returns [ir::Cell] which are read from by the control program. The
+    /// reads of a single group are given by [ReadWriteSet::read_set] on its
+    /// assignments.
+    pub fn control_read_set(con: &ir::Control) -> ir::CellIterator<'_> {
+        Self::parent_cells(Self::control_port_read_set(con))
+    }
+
+    /// Returns [ir::Cell] which are written to by the control program.
+    pub fn control_write_set(con: &ir::Control) -> ir::CellIterator<'_> {
+        Self::parent_cells(Self::control_port_write_set(con))
+    }
+
+    /// Returns all uses of cells in the control program.
+    pub fn control_uses(con: &ir::Control) -> ir::CellIterator<'_> {
+        let iter = Self::control_read_set(con)
+            .chain(Self::control_write_set(con))
+            .unique_by(|cell| cell.clone_name());
+
+        ir::CellIterator {
+            iter: Box::new(iter),
+        }
+    }
 }
@@ -1,17 +1,27 @@
 use crate::ir::{self, CloneName};
 use crate::utils::{Idx, WeightGraph};
 use petgraph::visit::IntoEdgeReferences;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Default)]
 /// A conflict graph that describes which groups are being run in parallel
 /// to each other.
+///
+/// Two groups conflict when they are enabled in different children of the
+/// same `par`. The condition groups of `if` and `while` statements conflict
+/// with the groups in the other children of the `par` as well. Groups that
+/// do not conflict are never active in the same cycle which allows passes to
+/// share the resources they use and requires groups that do conflict to not
+/// drive the same ports.
 pub struct ScheduleConflicts {
     graph: WeightGraph<ir::Id>,
     /// Reverse mapping from node indices to Group names.
     /// We can store this because we don't expect nodes or edges to be added
     /// once a conflict graph is constructed.
     rev_map: HashMap<Idx, ir::Id>,
+    /// Groups that conflict with each group. Computed once when the graph is
+    /// constructed so that queries do not traverse the graph.
+    conflicts: HashMap<ir::Id, HashSet<ir::Id>>,
 }
 
 /// Wrapper to iterate over all the conflict edges.
@@ -28,14 +38,22 @@ impl Iterator for ConflictIterator<'_> {
 }
 
 impl ScheduleConflicts {
-    /// Return a vector of all groups that conflict with this group.
+    /// Returns true if the groups `g1` and `g2` may run in parallel.
+    pub fn is_conflicting(&self, g1: &ir::Id, g2: &ir::Id) -> bool {
+        matches!(self.conflicts.get(g1), Some(confs) if confs.contains(g2))
+    }
+
+    /// Return a vector of all groups that conflict with this group, sorted by
+    /// name. Groups that are not enabled in the control program do not
+    /// conflict with any group.
     pub fn conflicts_with(&self, group: &ir::Id) -> Vec<&ir::Id> {
-        self.graph
-            .graph
-            .neighbors(self.graph.index_map[group])
-            .into_iter()
-            .map(|idx| &self.rev_map[&idx])
-            .collect()
+        let mut confs = self
+            .conflicts
+            .get(group)
+            .map(|confs| confs.iter().collect::<Vec<_>>())
+            .unwrap_or_default();
+        confs.sort();
+        confs
     }
 
     /// Returns an iterator containing all conflict edges,
@@ -108,6 +126,7 @@ fn build_conflict_graph(
     match c {
         ir::Control::Empty(_) => (),
         ir::Control::Invoke(_) => (),
+        ir::Control::Debug(_) => (),
         ir::Control::Enable(ir::Enable { group, .. }) => {
             confs.add_node(group.borrow().name());
             all_enables.push(group.clone_name());
@@ -168,6 +187,12 @@ impl From<&ir::Control> for ScheduleConflicts {
         build_conflict_graph(control, &mut confs, &mut vec![]);
         // Build the reverse index
         confs.rev_map = confs.graph.reverse_index();
+        let mut conflicts: HashMap<ir::Id, HashSet<ir::Id>> = HashMap::new();
+        for (g1, g2) in confs.all_conflicts() {
+            conflicts.entry(g1.clone()).or_default().insert(g2.clone());
+            conflicts.entry(g2).or_default().insert(g1);
+        }
+        confs.conflicts = conflicts;
         confs
     }
 }
@@ -0,0 +1,285 @@
+use crate::ir::{self, IRPrinter};
+use std::cmp::Reverse;
+use std::fmt;
+use std::ops::Add;
+
+/// Estimated number of cycles taken by a control statement.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Estimate {
+    /// Number of cycles.
+    pub cycles: u64,
+    /// True if the latency of every part of the statement is known.
+    /// Otherwise, `cycles` is a lower bound.
+    pub exact: bool,
+}
+
+impl Estimate {
+    fn exact(cycles: u64) -> Self {
+        Estimate {
+            cycles,
+            exact: true,
+        }
+    }
+
+    fn lower_bound(cycles: u64) -> Self {
+        Estimate {
+            cycles,
+            exact: false,
+        }
+    }
+
+    /// The longer of the two estimates. Both are used for exactness since
+    /// the shorter one might turn out to be longer.
+    fn max(self, other: Self) -> Self {
+        Estimate {
+            cycles: self.cycles.max(other.cycles),
+            exact: self.exact && other.exact,
+        }
+    }
+
+    fn times(self, n: u64) -> Self {
+        Estimate {
+            cycles: self.cycles * n,
+            exact: self.exact,
+        }
+    }
+}
+
+impl Add for Estimate {
+    type Output = Estimate;
+
+    fn add(self, other: Self) -> Self {
+        Estimate {
+            cycles: self.cycles + other.cycles,
+            exact: self.exact && other.exact,
+        }
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.exact {
+            write!(f, "{}", self.cycles)
+        } else {
+            write!(f, ">={}", self.cycles)
+        }
+    }
+}
+
+/// A control statement on the critical path of a control program.
+pub struct PathNode {
+    /// Description of the statement.
+    pub stmt: String,
+    /// Estimated number of cycles taken by the statement.
+    pub estimate: Estimate,
+    /// Statements within this one that are on the critical path. For `par`
+    /// and `if` statements, this is only the child that takes the longest.
+    pub children: Vec<PathNode>,
+}
+
+/// Estimates the number of cycles taken by a control program and finds its
+/// critical path, i.e., the statements that determine how long the program
+/// takes.
+///
+/// The latency of groups and invokes is taken from their `static`
+/// annotations, which can be inferred using the `infer-static-timing` pass.
+/// The latency of a loop is the latency of its body times its `bound`
+/// annotation.
+/// Groups and invokes without a `static` annotation are assumed to take one
+/// cycle and loops without a `bound` annotation to run once, in which case the
+/// estimate is a lower bound.
+/// Like `infer-static-timing`, evaluating the condition of an `if` takes a
+/// cycle while the conditions of loops are free.
+pub struct ScheduleLength {
+    critical_path: PathNode,
+    /// Loops in the control program along with their estimates.
+    loops: Vec<(String, Estimate)>,
+}
+
+impl From<&ir::Control> for ScheduleLength {
+    fn from(con: &ir::Control) -> Self {
+        let mut loops = vec![];
+        let critical_path = build(con, &mut loops);
+        // Loops that take the longest come first.
+        loops.sort_by_key(|(_, estimate)| Reverse(estimate.cycles));
+        ScheduleLength {
+            critical_path,
+            loops,
+        }
+    }
+}
+
+impl ScheduleLength {
+    /// Estimated number of cycles taken by the control program.
+    pub fn estimate(&self) -> Estimate {
+        self.critical_path.estimate
+    }
+
+    /// The root of the critical path.
+    pub fn critical_path(&self) -> &PathNode {
+        &self.critical_path
+    }
+
+    /// Loops in the control program ordered by their estimated number of
+    /// cycles, longest first.
+    pub fn loops(&self) -> &[(String, Estimate)] {
+        &self.loops
+    }
+}
+
+fn cond_str(
+    port: &ir::RRC<ir::Port>,
+    cond: &Option<ir::RRC<ir::CombGroup>>,
+) -> String {
+    let port = IRPrinter::get_port_access(&port.borrow());
+    match cond {
+        Some(cond) => format!("{} with {}", port, cond.borrow().name()),
+        None => port,
+    }
+}
+
+/// Latency of a statement from its `static` annotation.
+fn static_latency(attrs: &ir::Attributes) -> Estimate {
+    attrs
+        .get("static")
+        .map(|time| Estimate::exact(*time))
+        .unwrap_or_else(|| Estimate::lower_bound(1))
+}
+
+/// The child that takes the longest along with the estimate for all the
+/// children.
+fn longest(children: Vec<PathNode>) -> (Estimate, Vec<PathNode>) {
+    let estimate = children
+        .iter()
+        .map(|node| node.estimate)
+        .fold(Estimate::exact(0), Estimate::max);
+    let longest = children.into_iter().fold(
+        None,
+        |acc: Option<PathNode>, node| match acc {
+            Some(acc) if acc.estimate.cycles >= node.estimate.cycles => {
+                Some(acc)
+            }
+            _ => Some(node),
+        },
+    );
+    (estimate, longest.into_iter().collect())
+}
+
+fn build(con: &ir::Control, loops: &mut Vec<(String, Estimate)>) -> PathNode {
+    let (stmt, estimate, children) = match con {
+        ir::Control::Enable(ir::Enable { group, attributes }) => {
+            let group = group.borrow();
+            let estimate = if attributes.has("static") {
+                static_latency(attributes)
+            } else {
+                static_latency(&group.attributes)
+            };
+            (group.name().to_string(), estimate, vec![])
+        }
+        ir::Control::Invoke(ir::Invoke {
+            comp, attributes, ..
+        }) => (
+            format!("invoke {}", comp.borrow().name()),
+            static_latency(attributes),
+            vec![],
+        ),
+        ir::Control::Seq(ir::Seq { stmts, .. }) => {
+            let children: Vec<_> =
+                stmts.iter().map(|stmt| build(stmt, loops)).collect();
+            let estimate = children
+                .iter()
+                .map(|node| node.estimate)
+                .fold(Estimate::exact(0), Add::add);
+            ("seq".to_string(), estimate, children)
+        }
+        ir::Control::Par(ir::Par { stmts, .. }) => {
+            let (estimate, children) =
+                longest(stmts.iter().map(|stmt| build(stmt, loops)).collect());
+            ("par".to_string(), estimate, children)
+        }
+        ir::Control::If(ir::If {
+            port,
+            cond,
+            tbranch,
+            fbranch,
+            ..
+        }) => {
+            let (estimate, children) =
+                longest(vec![build(tbranch, loops), build(fbranch, loops)]);
+            (
+                format!("if {}", cond_str(port, cond)),
+                Estimate::exact(1) + estimate,
+                children,
+            )
+        }
+        ir::Control::While(ir::While {
+            port,
+            cond,
+            body,
+            attributes,
+        }) => {
+            let body = build(body, loops);
+            let (bound, estimate) = match attributes.get("bound") {
+                Some(bound) => {
+                    (format!("bound {}", bound), body.estimate.times(*bound))
+                }
+                None => (
+                    "unknown bound".to_string(),
+                    Estimate::lower_bound(body.estimate.cycles),
+                ),
+            };
+            let stmt = format!("while {} ({})", cond_str(port, cond), bound);
+            loops.push((stmt.clone(), estimate));
+            (stmt, estimate, vec![body])
+        }
+        ir::Control::Empty(_) => {
+            ("empty".to_string(), Estimate::exact(0), vec![])
+        }
+        ir::Control::Debug(_) => {
+            ("debug".to_string(), Estimate::exact(0), vec![])
+        }
+    };
+    PathNode {
+        stmt,
+        estimate,
+        children,
+    }
+}
+
+impl fmt::Display for ScheduleLength {
+    /// Print the critical path followed by the loops of the program.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_node(
+            node: &PathNode,
+            indent: usize,
+            f: &mut fmt::Formatter<'_>,
+        ) -> fmt::Result {
+            writeln!(
+                f,
+                "{}{}: {}",
+                " ".repeat(indent),
+                node.stmt,
+                node.estimate
+            )?;
+            node.children
+                .iter()
+                .try_for_each(|child| write_node(child, indent + 2, f))
+        }
+
+        writeln!(f, "critical path:")?;
+        write_node(&self.critical_path, 2, f)?;
+        if !self.loops.is_empty() {
+            let total = self.estimate().cycles;
+            writeln!(f, "loops:")?;
+            for (stmt, estimate) in &self.loops {
+                write!(f, "  {}: {}", stmt, estimate)?;
+                if let Some(share) = (estimate.cycles * 100).checked_div(total)
+                {
+                    write!(f, " ({}% of total)", share)?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,868 @@
+use crate::ir::{self, CloneName, RRC};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+/// Number of rounds after which the registers whose values are still
+/// growing are assumed to hold any value of their width.
+const WIDEN_AFTER: usize = 16;
+
+/// Number of rounds that recompute the values of the registers once the
+/// analysis has converged, which recovers the precision lost by widening.
+const NARROW_ROUNDS: usize = 2;
+
+/// Primitives that compare their `left` and `right` inputs.
+const COMPARISONS: [&str; 6] =
+    ["std_lt", "std_le", "std_gt", "std_ge", "std_eq", "std_neq"];
+
+/// The largest value of a `width`-bit port.
+fn max_value(width: u64) -> u64 {
+    if width >= 64 {
+        u64::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+/// The range of a port that can have any value. Ports wider than 64 bits
+/// have no range.
+fn any(width: u64) -> Option<Range> {
+    (width <= 64).then(|| Range::full(width))
+}
+
+/// An inclusive range of unsigned values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Range {
+    pub lo: u64,
+    pub hi: u64,
+}
+
+impl Range {
+    /// The range that only contains `val`.
+    pub fn exact(val: u64) -> Self {
+        Range { lo: val, hi: val }
+    }
+
+    /// All the values of a `width`-bit port.
+    pub fn full(width: u64) -> Self {
+        Range {
+            lo: 0,
+            hi: max_value(width),
+        }
+    }
+
+    /// The smallest range that contains both ranges.
+    pub fn join(self, other: Range) -> Range {
+        Range {
+            lo: self.lo.min(other.lo),
+            hi: self.hi.max(other.hi),
+        }
+    }
+
+    /// The values in both ranges, if there are any.
+    pub fn meet(self, other: Range) -> Option<Range> {
+        let lo = self.lo.max(other.lo);
+        let hi = self.hi.min(other.hi);
+        (lo <= hi).then_some(Range { lo, hi })
+    }
+
+    /// The value of the range if it only contains one.
+    pub fn value(&self) -> Option<u64> {
+        (self.lo == self.hi).then_some(self.lo)
+    }
+
+    /// Number of bits needed to represent every value of the range.
+    pub fn bits(&self) -> u64 {
+        (64 - self.hi.leading_zeros() as u64).max(1)
+    }
+
+    /// The result of comparing the values of the range with the values of
+    /// `other` using the comparison primitive `prim`, such as `std_lt`, if
+    /// it is the same for all of them.
+    pub fn compare(self, prim: &str, other: Range) -> Option<bool> {
+        match prim {
+            "std_lt" if self.hi < other.lo => Some(true),
+            "std_lt" if self.lo >= other.hi => Some(false),
+            "std_gt" => other.compare("std_lt", self),
+            "std_le" => other.compare("std_lt", self).map(|b| !b),
+            "std_ge" => self.compare("std_lt", other).map(|b| !b),
+            "std_eq" if self.value().is_some() && self == other => Some(true),
+            "std_eq" if self.meet(other).is_none() => Some(false),
+            "std_neq" => self.compare("std_eq", other).map(|b| !b),
+            _ => None,
+        }
+    }
+}
+
+/// An assignment to a port.
+struct Source {
+    /// The group or combinational group of the assignment. `None` for
+    /// continuous assignments.
+    group: Option<ir::Id>,
+    /// The port that is read.
+    port: RRC<ir::Port>,
+    /// The assignment has a guard.
+    guarded: bool,
+}
+
+/// Assignments to the ports of the cells of a component.
+type Sources = HashMap<(ir::Id, ir::Id), Vec<Source>>;
+
+/// A value written to a register.
+struct Write {
+    /// The group that writes the value. `None` for writes outside of groups.
+    group: Option<ir::Id>,
+    /// The written value. `None` if it is unknown.
+    src: Option<RRC<ir::Port>>,
+}
+
+/// Values that registers are known to hold at a point of the control
+/// program.
+type Facts = HashMap<ir::Id, Range>;
+
+/// Evaluates the ranges of ports given the values of the registers.
+struct Eval<'a> {
+    sources: &'a Sources,
+    invoked: &'a HashSet<(ir::Id, ir::Id)>,
+    registers: &'a HashMap<ir::Id, Range>,
+    /// Ranges of the cell ports that have been evaluated.
+    memo: HashMap<(ir::Id, ir::Id), Option<Range>>,
+}
+
+impl<'a> Eval<'a> {
+    fn new(
+        sources: &'a Sources,
+        invoked: &'a HashSet<(ir::Id, ir::Id)>,
+        registers: &'a HashMap<ir::Id, Range>,
+    ) -> Self {
+        Eval {
+            sources,
+            invoked,
+            registers,
+            memo: HashMap::new(),
+        }
+    }
+
+    /// The range of `port`, `None` if it is unknown.
+    fn port(&mut self, port: &RRC<ir::Port>) -> Option<Range> {
+        let port = port.borrow();
+        let cell = match &port.parent {
+            ir::PortParent::Cell(cell) => cell.upgrade(),
+            ir::PortParent::Group(_) => return any(port.width),
+        };
+        let cell = cell.borrow();
+        let prim = match &cell.prototype {
+            ir::CellType::Constant { val, .. } => {
+                return Some(Range::exact(*val))
+            }
+            ir::CellType::Primitive { name, .. }
+                if port.direction == ir::Direction::Output =>
+            {
+                name.clone()
+            }
+            _ => return any(port.width),
+        };
+        let key = (cell.clone_name(), port.name.clone());
+        if let Some(range) = self.memo.get(&key) {
+            return *range;
+        }
+        // Ports that depend on themselves can have any value.
+        self.memo.insert(key.clone(), any(port.width));
+        let range = self
+            .primitive(&cell, prim.as_ref(), port.name.as_ref())
+            .filter(|range| range.hi <= max_value(port.width))
+            .or_else(|| any(port.width));
+        self.memo.insert(key, range);
+        range
+    }
+
+    /// The range of the output `port` of `cell`, an instance of `prim`.
+    fn primitive(
+        &mut self,
+        cell: &ir::Cell,
+        prim: &str,
+        port: &str,
+    ) -> Option<Range> {
+        let name = cell.name();
+        match (prim, port) {
+            ("std_reg", "out") => self.registers.get(name).copied(),
+            ("std_const", "out") => {
+                cell.get_parameter("VALUE").map(Range::exact)
+            }
+            ("std_wire" | "std_slice" | "std_pad", "out") => {
+                self.input(name, "in")
+            }
+            ("std_add", "out") => {
+                let (l, r) = self.operands(name)?;
+                Some(Range {
+                    lo: l.lo.checked_add(r.lo)?,
+                    hi: l.hi.checked_add(r.hi)?,
+                })
+            }
+            ("std_sub", "out") => {
+                let (l, r) = self.operands(name)?;
+                (l.lo >= r.hi).then(|| Range {
+                    lo: l.lo - r.hi,
+                    hi: l.hi - r.lo,
+                })
+            }
+            ("std_and", "out") => {
+                let (l, r) = self.operands(name)?;
+                Some(Range {
+                    lo: 0,
+                    hi: l.hi.min(r.hi),
+                })
+            }
+            ("std_or", "out") => {
+                let (l, r) = self.operands(name)?;
+                Some(Range {
+                    lo: l.lo.max(r.lo),
+                    hi: max_value(l.join(r).bits()),
+                })
+            }
+            ("std_rsh", "out") => {
+                let (l, r) = self.operands(name)?;
+                let shr = |val: u64, amount: u64| {
+                    u32::try_from(amount)
+                        .ok()
+                        .and_then(|amount| val.checked_shr(amount))
+                        .unwrap_or(0)
+                };
+                Some(Range {
+                    lo: shr(l.lo, r.hi),
+                    hi: shr(l.hi, r.lo),
+                })
+            }
+            (cmp, "out") if COMPARISONS.contains(&cmp) => {
+                let (l, r) = self.operands(name)?;
+                Some(match l.compare(cmp, r) {
+                    Some(res) => Range::exact(res as u64),
+                    None => Range { lo: 0, hi: 1 },
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// The ranges of the `left` and `right` inputs of `cell`.
+    fn operands(&mut self, cell: &ir::Id) -> Option<(Range, Range)> {
+        Some((self.input(cell, "left")?, self.input(cell, "right")?))
+    }
+
+    /// The range of the values assigned to the input `port` of `cell`.
+    /// Inputs without assignments are zero.
+    fn input(&mut self, cell: &ir::Id, port: &str) -> Option<Range> {
+        let key = (cell.clone(), ir::Id::from(port));
+        if self.invoked.contains(&key) {
+            return None;
+        }
+        let sources = self.sources;
+        match sources.get(&key) {
+            Some(srcs) => srcs
+                .iter()
+                .map(|src| self.port(&src.port))
+                .reduce(|l, r| Some(l?.join(r?)))
+                .flatten(),
+            None => Some(Range::exact(0)),
+        }
+    }
+}
+
+/// An operand of a comparison that is used as the condition of a control
+/// statement.
+#[derive(PartialEq)]
+enum Operand {
+    Register(ir::Id),
+    Constant(u64),
+}
+
+impl Operand {
+    fn from_port(port: &RRC<ir::Port>) -> Option<Self> {
+        let port = port.borrow();
+        let cell = match &port.parent {
+            ir::PortParent::Cell(cell) => cell.upgrade(),
+            ir::PortParent::Group(_) => return None,
+        };
+        let cell = cell.borrow();
+        match &cell.prototype {
+            ir::CellType::Constant { val, .. } => Some(Operand::Constant(*val)),
+            ir::CellType::Primitive { name, .. }
+                if name == "std_reg" && port.name == "out" =>
+            {
+                Some(Operand::Register(cell.clone_name()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The values of `x` for which `x <prim> k` is `holds`, if they form a
+/// range that is smaller than all the values of a `width`-bit port.
+fn constraint(prim: &str, k: u64, holds: bool, width: u64) -> Option<Range> {
+    let max = max_value(width);
+    let at_most = |hi: u64| {
+        Some(Range {
+            lo: 0,
+            hi: hi.min(max),
+        })
+    };
+    let at_least = |lo: u64| (lo <= max).then_some(Range { lo, hi: max });
+    match (prim, holds) {
+        ("std_lt", true) | ("std_ge", false) => at_most(k.checked_sub(1)?),
+        ("std_le", true) | ("std_gt", false) => at_most(k),
+        ("std_gt", true) | ("std_le", false) => at_least(k.checked_add(1)?),
+        ("std_ge", true) | ("std_lt", false) => at_least(k),
+        ("std_eq", true) | ("std_neq", false) => {
+            (k <= max).then_some(Range::exact(k))
+        }
+        _ => None,
+    }
+}
+
+/// The comparison `k <prim> x` written as `x <prim> k`.
+fn flip(prim: &'static str) -> &'static str {
+    match prim {
+        "std_lt" => "std_gt",
+        "std_gt" => "std_lt",
+        "std_le" => "std_ge",
+        "std_ge" => "std_le",
+        _ => prim,
+    }
+}
+
+/// Finds the values registers are known to hold whenever a group runs
+/// using the conditions of the `if` and `while` statements that enclose it.
+struct Conditions<'a> {
+    sources: &'a Sources,
+    /// Widths of the analyzed registers.
+    widths: &'a HashMap<ir::Id, u64>,
+    /// Registers written by each group.
+    group_writes: &'a HashMap<ir::Id, HashSet<ir::Id>>,
+    /// Groups that perform all their writes in the same cycle.
+    one_cycle: &'a HashSet<ir::Id>,
+    /// Facts that hold whenever a group runs.
+    bounds: HashMap<ir::Id, Facts>,
+}
+
+impl Conditions<'_> {
+    /// Registers that may be written by `con`.
+    fn writes(&self, con: &ir::Control) -> HashSet<ir::Id> {
+        match con {
+            ir::Control::Enable(ir::Enable { group, .. }) => self
+                .group_writes
+                .get(group.borrow().name())
+                .cloned()
+                .unwrap_or_default(),
+            ir::Control::Invoke(invoke) => invoke_writes(invoke),
+            ir::Control::Seq(ir::Seq { stmts, .. })
+            | ir::Control::Par(ir::Par { stmts, .. }) => {
+                stmts.iter().flat_map(|stmt| self.writes(stmt)).collect()
+            }
+            ir::Control::If(ir::If {
+                tbranch, fbranch, ..
+            }) => {
+                let mut writes = self.writes(tbranch);
+                writes.extend(self.writes(fbranch));
+                writes
+            }
+            ir::Control::While(ir::While { body, .. }) => self.writes(body),
+            ir::Control::Empty(_) | ir::Control::Debug(_) => HashSet::new(),
+        }
+    }
+
+    /// The register `r`, the comparison `prim`, and the constant `k` if
+    /// `port` is always the result of `r.out <prim> k` while `cond` is
+    /// active.
+    fn condition(
+        &self,
+        port: &RRC<ir::Port>,
+        cond: &Option<RRC<ir::CombGroup>>,
+    ) -> Option<(ir::Id, &'static str, u64)> {
+        let port = port.borrow();
+        let cell = match &port.parent {
+            ir::PortParent::Cell(cell) if port.name == "out" => cell.upgrade(),
+            _ => return None,
+        };
+        let cell = cell.borrow();
+        let prim = match &cell.prototype {
+            ir::CellType::Primitive { name, .. } => {
+                *COMPARISONS.iter().find(|prim| name == **prim)?
+            }
+            _ => return None,
+        };
+        let cond = cond.as_ref().map(|cond| cond.borrow().clone_name());
+        // The inputs of the comparison must be driven whenever the
+        // condition is checked.
+        let operand = |input: &str| {
+            let srcs = self.sources.get(&(cell.clone_name(), input.into()))?;
+            let mut operands = srcs.iter().map(|src| {
+                if !src.guarded && (src.group.is_none() || src.group == cond) {
+                    Operand::from_port(&src.port)
+                } else {
+                    None
+                }
+            });
+            let first = operands.next().flatten()?;
+            operands
+                .all(|op| op.as_ref() == Some(&first))
+                .then_some(first)
+        };
+        match (operand("left")?, operand("right")?) {
+            (Operand::Register(reg), Operand::Constant(k)) => {
+                Some((reg, prim, k))
+            }
+            (Operand::Constant(k), Operand::Register(reg)) => {
+                Some((reg, flip(prim), k))
+            }
+            _ => None,
+        }
+    }
+
+    /// Add the fact that the condition `cond` is `holds` to `facts`.
+    fn assume(
+        &self,
+        facts: &mut Facts,
+        cond: &Option<(ir::Id, &'static str, u64)>,
+        holds: bool,
+        concurrent: &HashSet<ir::Id>,
+    ) {
+        let (reg, prim, k) = match cond {
+            Some(cond) => cond,
+            None => return,
+        };
+        if concurrent.contains(reg) {
+            return;
+        }
+        let constraint = self
+            .widths
+            .get(reg)
+            .and_then(|width| constraint(prim, *k, holds, *width));
+        if let Some(range) = constraint {
+            let range = facts
+                .get(reg)
+                .and_then(|fact| fact.meet(range))
+                .unwrap_or(range);
+            facts.insert(reg.clone(), range);
+        }
+    }
+
+    /// Record the facts that hold when the groups in `con` run given the
+    /// `facts` that hold before it. Registers in `concurrent` may be
+    /// written at any time by statements that run in parallel.
+    fn walk(
+        &mut self,
+        con: &ir::Control,
+        facts: &mut Facts,
+        concurrent: &HashSet<ir::Id>,
+    ) {
+        match con {
+            ir::Control::Enable(ir::Enable { group, .. }) => {
+                let name = group.borrow().clone_name();
+                let writes = self.writes(con);
+                let mut site = facts.clone();
+                // Values written by groups that take multiple cycles may be
+                // read by the group's later writes.
+                if !self.one_cycle.contains(&name) {
+                    site.retain(|reg, _| !writes.contains(reg));
+                }
+                self.bounds
+                    .entry(name)
+                    .and_modify(|bounds| *bounds = join(bounds, &site))
+                    .or_insert(site);
+                facts.retain(|reg, _| !writes.contains(reg));
+            }
+            ir::Control::Invoke(invoke) => {
+                let writes = invoke_writes(invoke);
+                facts.retain(|reg, _| !writes.contains(reg));
+            }
+            ir::Control::Seq(ir::Seq { stmts, .. }) => {
+                for stmt in stmts {
+                    self.walk(stmt, facts, concurrent);
+                }
+            }
+            ir::Control::Par(ir::Par { stmts, .. }) => {
+                let writes =
+                    stmts.iter().map(|s| self.writes(s)).collect::<Vec<_>>();
+                for (idx, stmt) in stmts.iter().enumerate() {
+                    let mut others = concurrent.clone();
+                    for (_, w) in
+                        writes.iter().enumerate().filter(|(i, _)| *i != idx)
+                    {
+                        others.extend(w.iter().cloned());
+                    }
+                    let mut child = facts.clone();
+                    child.retain(|reg, _| !others.contains(reg));
+                    self.walk(stmt, &mut child, &others);
+                }
+                for w in writes {
+                    facts.retain(|reg, _| !w.contains(reg));
+                }
+            }
+            ir::Control::If(ir::If {
+                port,
+                cond,
+                tbranch,
+                fbranch,
+                ..
+            }) => {
+                let cond = self.condition(port, cond);
+                let mut tfacts = facts.clone();
+                self.assume(&mut tfacts, &cond, true, concurrent);
+                self.walk(tbranch, &mut tfacts, concurrent);
+                let mut ffacts = facts.clone();
+                self.assume(&mut ffacts, &cond, false, concurrent);
+                self.walk(fbranch, &mut ffacts, concurrent);
+                *facts = join(&tfacts, &ffacts);
+            }
+            ir::Control::While(ir::While {
+                port, cond, body, ..
+            }) => {
+                // Facts that hold whenever the condition is checked.
+                let writes = self.writes(body);
+                facts.retain(|reg, _| !writes.contains(reg));
+                let cond = self.condition(port, cond);
+                let mut body_facts = facts.clone();
+                self.assume(&mut body_facts, &cond, true, concurrent);
+                self.walk(body, &mut body_facts, concurrent);
+                self.assume(facts, &cond, false, concurrent);
+            }
+            ir::Control::Empty(_) | ir::Control::Debug(_) => {}
+        }
+    }
+}
+
+/// The facts that hold in both `l` and `r`.
+fn join(l: &Facts, r: &Facts) -> Facts {
+    l.iter()
+        .filter_map(|(reg, range)| {
+            r.get(reg).map(|other| (reg.clone(), range.join(*other)))
+        })
+        .collect()
+}
+
+/// Returns true if `cell` is a `std_reg`.
+fn is_register(cell: &ir::Cell) -> bool {
+    matches!(&cell.prototype, ir::CellType::Primitive { name, .. } if name == "std_reg")
+}
+
+/// Returns the register that `port` belongs to, if any.
+fn register(port: &ir::Port) -> Option<ir::Id> {
+    match &port.parent {
+        ir::PortParent::Cell(cell) => {
+            let cell = cell.upgrade();
+            let cell = cell.borrow();
+            is_register(&cell).then(|| cell.clone_name())
+        }
+        ir::PortParent::Group(_) => None,
+    }
+}
+
+/// Registers written by `invoke`.
+fn invoke_writes(invoke: &ir::Invoke) -> HashSet<ir::Id> {
+    let comp = invoke.comp.borrow();
+    let mut writes = invoke
+        .outputs
+        .iter()
+        .filter_map(|(_, dst)| register(&dst.borrow()))
+        .collect::<HashSet<_>>();
+    if is_register(&comp) {
+        writes.insert(comp.clone_name());
+    }
+    writes
+}
+
+/// Calls `f` on every `invoke` statement in `con`.
+fn for_each_invoke<F>(con: &ir::Control, f: &mut F)
+where
+    F: FnMut(&ir::Invoke),
+{
+    match con {
+        ir::Control::Invoke(invoke) => f(invoke),
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => {
+            for stmt in stmts {
+                for_each_invoke(stmt, f);
+            }
+        }
+        ir::Control::If(ir::If {
+            tbranch, fbranch, ..
+        }) => {
+            for_each_invoke(tbranch, f);
+            for_each_invoke(fbranch, f);
+        }
+        ir::Control::While(ir::While { body, .. }) => for_each_invoke(body, f),
+        ir::Control::Enable(_)
+        | ir::Control::Empty(_)
+        | ir::Control::Debug(_) => {}
+    }
+}
+
+/// Computes the range of values of the registers of a component and of the
+/// ports computed from them.
+///
+/// A register holds zero after a reset and every value that is written to
+/// it. Written values are computed through constants, registers, and the
+/// `std_add`, `std_sub`, `std_and`, `std_or`, `std_rsh`, `std_wire`,
+/// `std_slice`, `std_pad`, and comparison primitives; other cells and the
+/// inputs of the component can have any value. The ports of a cell are
+/// assumed to only be read while its inputs are driven.
+///
+/// The conditions of `if` and `while` statements that compare a register
+/// with a constant bound the values of the register in the groups they
+/// enable, as long as the register cannot be written in between. A 32-bit
+/// counter that is incremented by a static group in the body of a loop on
+/// `i.out < 10` only holds values between 0 and 10:
+/// ```calyx
+/// group incr<"static"=1> {
+///   add.left = i.out; add.right = 32'd1;
+///   i.in = add.out; i.write_en = 1'd1; incr[done] = i.done;
+/// }
+/// comb group cond { lt.left = i.out; lt.right = 32'd10; }
+/// ...
+/// while lt.out with cond { incr; }
+/// ```
+/// The bound is only used for the writes of a group that writes the
+/// register itself if the group performs all its writes in one cycle: it is
+/// a static group with a latency of one cycle, or it writes a register
+/// unconditionally and is done when that register is. Registers wider than
+/// 64 bits are not analyzed.
+pub struct ValueRanges {
+    sources: Sources,
+    /// Ports of cells that are written by `invoke` statements.
+    invoked: HashSet<(ir::Id, ir::Id)>,
+    /// Values of the registers.
+    registers: HashMap<ir::Id, Range>,
+}
+
+impl From<&ir::Component> for ValueRanges {
+    fn from(comp: &ir::Component) -> Self {
+        let mut sources: Sources = HashMap::new();
+        let mut writes: HashMap<ir::Id, Vec<Write>> = HashMap::new();
+        let mut group_writes: HashMap<ir::Id, HashSet<ir::Id>> = HashMap::new();
+        // Registers written outside of groups can change at any time.
+        let mut concurrent = HashSet::new();
+        let mut record = |group: Option<&ir::Id>,
+                          is_group: bool,
+                          assign: &ir::Assignment| {
+            let dst = assign.dst.borrow();
+            if let ir::PortParent::Group(_) = dst.parent {
+                return;
+            }
+            sources.entry(dst.canonical()).or_default().push(Source {
+                group: group.cloned(),
+                port: Rc::clone(&assign.src),
+                guarded: !assign.guard.is_true(),
+            });
+            let reg = match register(&dst) {
+                Some(reg) => reg,
+                None => return,
+            };
+            match (dst.name.as_ref(), group) {
+                ("in", _) => writes.entry(reg).or_default().push(Write {
+                    group: group.filter(|_| is_group).cloned(),
+                    src: Some(Rc::clone(&assign.src)),
+                }),
+                ("write_en", Some(group)) if is_group => {
+                    group_writes.entry(group.clone()).or_default().insert(reg);
+                }
+                ("write_en", _) => {
+                    concurrent.insert(reg);
+                }
+                _ => {}
+            }
+        };
+        for group in comp.groups.iter() {
+            let group = group.borrow();
+            for assign in &group.assignments {
+                record(Some(group.name()), true, assign);
+            }
+        }
+        for group in comp.comb_groups.iter() {
+            let group = group.borrow();
+            for assign in &group.assignments {
+                record(Some(group.name()), false, assign);
+            }
+        }
+        for assign in &comp.continuous_assignments {
+            record(None, false, assign);
+        }
+
+        let control = comp.control.borrow();
+        let mut invoked = HashSet::new();
+        for_each_invoke(&control, &mut |invoke| {
+            let cell = invoke.comp.borrow();
+            for (port, src) in &invoke.inputs {
+                invoked.insert((cell.clone_name(), port.clone()));
+                if is_register(&cell) && port == "in" {
+                    writes.entry(cell.clone_name()).or_default().push(Write {
+                        group: None,
+                        src: Some(Rc::clone(src)),
+                    });
+                }
+            }
+            for (_, dst) in &invoke.outputs {
+                let dst = dst.borrow();
+                invoked.insert(dst.canonical());
+                if let Some(reg) = register(&dst) {
+                    writes.entry(reg).or_default().push(Write {
+                        group: None,
+                        src: None,
+                    });
+                }
+            }
+        });
+
+        let widths = comp
+            .cells
+            .iter()
+            .filter_map(|cell| {
+                let cell = cell.borrow();
+                let width = cell.get_parameter("WIDTH")?;
+                (is_register(&cell) && width <= 64)
+                    .then(|| (cell.clone_name(), width))
+            })
+            .collect::<HashMap<_, _>>();
+        let one_cycle = comp
+            .groups
+            .iter()
+            .filter(|group| is_one_cycle(&group.borrow()))
+            .map(|group| group.borrow().clone_name())
+            .collect::<HashSet<_>>();
+        let mut conditions = Conditions {
+            sources: &sources,
+            widths: &widths,
+            group_writes: &group_writes,
+            one_cycle: &one_cycle,
+            bounds: HashMap::new(),
+        };
+        conditions.walk(&control, &mut HashMap::new(), &concurrent);
+        let bounds = conditions.bounds;
+
+        // The values of the registers after one more write.
+        let step = |registers: &HashMap<ir::Id, Range>| {
+            widths
+                .iter()
+                .map(|(reg, width)| {
+                    let mut range = Range::exact(0);
+                    for write in writes.get(reg).into_iter().flatten() {
+                        let mut env = registers.clone();
+                        let facts =
+                            write.group.as_ref().and_then(|g| bounds.get(g));
+                        // Writes of groups that never run with the current
+                        // values have no effect.
+                        let reachable =
+                            facts.into_iter().flatten().all(|(reg, fact)| {
+                                match env.get(reg) {
+                                    Some(val) => match val.meet(*fact) {
+                                        Some(val) => {
+                                            env.insert(reg.clone(), val);
+                                            true
+                                        }
+                                        None => false,
+                                    },
+                                    None => true,
+                                }
+                            });
+                        if !reachable {
+                            continue;
+                        }
+                        let val = write.src.as_ref().and_then(|src| {
+                            Eval::new(&sources, &invoked, &env).port(src)
+                        });
+                        range = range.join(val.unwrap_or(Range::full(*width)));
+                    }
+                    (reg.clone(), range)
+                })
+                .collect::<HashMap<_, _>>()
+        };
+
+        let mut registers = widths
+            .keys()
+            .map(|reg| (reg.clone(), Range::exact(0)))
+            .collect::<HashMap<_, _>>();
+        for round in 0.. {
+            let mut changed = false;
+            for (reg, range) in step(&registers) {
+                let old = registers[&reg];
+                let mut new = old.join(range);
+                if new != old && round >= WIDEN_AFTER {
+                    new = Range::full(widths[&reg]);
+                }
+                if new != old {
+                    changed = true;
+                    registers.insert(reg, new);
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        for _ in 0..NARROW_ROUNDS {
+            registers = step(&registers);
+        }
+
+        ValueRanges {
+            sources,
+            invoked,
+            registers,
+        }
+    }
+}
+
+/// Returns true if `group` performs all its writes in the same cycle: it
+/// takes a single cycle or it is done when a register that it writes
+/// unconditionally is.
+fn is_one_cycle(group: &ir::Group) -> bool {
+    if group.attributes.get("static") == Some(&1) {
+        return true;
+    }
+    let mut dones = group.assignments.iter().filter(|assign| {
+        let dst = assign.dst.borrow();
+        dst.is_hole() && dst.name == "done"
+    });
+    let done = match (dones.next(), dones.next()) {
+        (Some(done), None) if done.guard.is_true() => done,
+        _ => return false,
+    };
+    let reg = match register(&done.src.borrow()) {
+        Some(reg) if done.src.borrow().name == "done" => reg,
+        _ => return false,
+    };
+    group.assignments.iter().any(|assign| {
+        let dst = assign.dst.borrow();
+        dst.name == "write_en"
+            && register(&dst).as_ref() == Some(&reg)
+            && assign.guard.is_true()
+            && assign.src.borrow().is_constant(1, 1)
+    })
+}
+
+impl ValueRanges {
+    /// The values of the register `reg`, if it is analyzed.
+    pub fn register(&self, reg: &ir::Id) -> Option<Range> {
+        self.registers.get(reg).copied()
+    }
+
+    /// The values of `port`, `None` if it is unknown or wider than 64 bits.
+    pub fn port(&self, port: &RRC<ir::Port>) -> Option<Range> {
+        Eval::new(&self.sources, &self.invoked, &self.registers).port(port)
+    }
+
+    /// The values assigned to the input `port` of `cell`, `None` if they
+    /// are unknown.
+    pub fn input(&self, cell: &ir::Id, port: &str) -> Option<Range> {
+        Eval::new(&self.sources, &self.invoked, &self.registers)
+            .input(cell, port)
+    }
+
+    /// The output of the comparison primitive `cell` if it is always the
+    /// same while its inputs are driven.
+    pub fn comparison(&self, cell: &RRC<ir::Cell>) -> Option<bool> {
+        let prim = cell.borrow().type_name()?.clone();
+        if !COMPARISONS.contains(&prim.as_ref()) {
+            return None;
+        }
+        let out = cell.borrow().get("out");
+        self.port(&out)?.value().map(|val| val == 1)
+    }
+}
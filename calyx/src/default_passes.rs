@@ -1,16 +1,19 @@
 //! Defines the default passes available to [PassManager].
 use crate::passes::{
-    ClkInsertion, CollapseControl, CompileEmpty, CompileInvoke,
-    ComponentInterface, DeadCellRemoval, DeadGroupRemoval, Externalize,
-    GoInsertion, GroupToInvoke, GuardCanonical, InferStaticTiming, Inliner,
-    LowerGuards, MergeAssign, MinimizeRegs, Papercut, ParToSeq,
-    RegisterUnsharing, RemoveCombGroups, ResetInsertion, ResourceSharing,
-    SimplifyGuards, SynthesisPapercut, TopDownCompileControl, WellFormed,
-};
-use crate::{
-    errors::CalyxResult, ir::traversal::Named, pass_manager::PassManager,
-    register_alias,
+    AccessPatterns, ClkInsertion, CollapseControl, CompileDebug, CompileEmpty,
+    CompileInvoke, CompileStream, ComponentInterface, DeadAssignmentRemoval,
+    DeadCellRemoval, DeadComponentRemoval, DeadGroupRemoval, DedupComponents,
+    Externalize, FsmReport, GoInsertion, GroupFusion, GroupToInvoke,
+    GuardCanonical, HoistInvariantGroups, InferStaticTiming, InitInsertion,
+    Inliner, LegalizeNames, ListSchedule, LiveRangeSplitting, LowerGuards,
+    LowerStatic, MemToReg, MemoryBanking, MergeAssign, MinimizeRegs, Outline,
+    Papercut, ParToSeq, RaceDetection, RegisterUnsharing, RemoveCombGroups,
+    RemoveDebug, ResetInsertion, ResourceSharing, Retime, SimplifyComparisons,
+    SimplifyGuards, SimplifyWithControl, SpeculateIf, StrengthReduction,
+    SynthesisPapercut, TopDownCompileControl, UnreachableControl, WellFormed,
+    WidthReduction, WrapMain,
 };
+use crate::{errors::CalyxResult, pass_manager::PassManager, register_alias};
 
 impl PassManager {
     pub fn default_passes() -> CalyxResult<Self> {
@@ -18,7 +21,7 @@ impl PassManager {
         let mut pm = PassManager::default();
 
         // Register passes.
-        pm.register_pass::<WellFormed>()?;
+        pm.register_analysis::<WellFormed>()?;
         // pm.register_pass::<StaticTiming>()?;
         // pm.register_pass::<CompileControl>()?;
         pm.register_pass::<CompileInvoke>()?;
@@ -27,32 +30,68 @@ impl PassManager {
         pm.register_pass::<Inliner>()?;
         pm.register_pass::<Externalize>()?;
         pm.register_pass::<CollapseControl>()?;
+        pm.register_pass::<SimplifyWithControl>()?;
         pm.register_pass::<CompileEmpty>()?;
-        pm.register_pass::<Papercut>()?;
+        pm.register_analysis::<Papercut>()?;
         pm.register_pass::<ClkInsertion>()?;
         pm.register_pass::<ResetInsertion>()?;
         pm.register_pass::<ResourceSharing>()?;
         pm.register_pass::<DeadCellRemoval>()?;
+        pm.register_pass::<DeadAssignmentRemoval>()?;
+        pm.register_pass::<DeadComponentRemoval>()?;
+        pm.register_pass::<DedupComponents>()?;
         pm.register_pass::<DeadGroupRemoval>()?;
         pm.register_pass::<MinimizeRegs>()?;
         pm.register_pass::<InferStaticTiming>()?;
+        pm.register_pass::<InitInsertion>()?;
         pm.register_pass::<SimplifyGuards>()?;
         pm.register_pass::<MergeAssign>()?;
         pm.register_pass::<TopDownCompileControl>()?;
         // pm.register_pass::<TopDownStaticTiming>()?;
-        pm.register_pass::<SynthesisPapercut>()?;
+        pm.register_analysis::<SynthesisPapercut>()?;
+        pm.register_analysis::<RaceDetection>()?;
         pm.register_pass::<RegisterUnsharing>()?;
         pm.register_pass::<GuardCanonical>()?;
         pm.register_pass::<LowerGuards>()?;
+        pm.register_pass::<LowerStatic>()?;
         pm.register_pass::<ParToSeq>()?;
         pm.register_pass::<RemoveCombGroups>()?;
         pm.register_pass::<GroupToInvoke>()?;
+        pm.register_pass::<HoistInvariantGroups>()?;
+        pm.register_pass::<GroupFusion>()?;
+        pm.register_pass::<SpeculateIf>()?;
+        pm.register_pass::<StrengthReduction>()?;
+        pm.register_pass::<SimplifyComparisons>()?;
+        pm.register_pass::<WidthReduction>()?;
+        pm.register_pass::<MemoryBanking>()?;
+        pm.register_pass::<MemToReg>()?;
+        pm.register_pass::<LiveRangeSplitting>()?;
+        pm.register_pass::<ListSchedule>()?;
+        pm.register_pass::<Retime>()?;
+        pm.register_pass::<Outline>()?;
+        pm.register_pass::<CompileDebug>()?;
+        pm.register_pass::<CompileStream>()?;
+        pm.register_pass::<RemoveDebug>()?;
+        pm.register_analysis::<UnreachableControl>()?;
+        pm.register_analysis::<AccessPatterns>()?;
+        pm.register_analysis::<FsmReport>()?;
+        pm.register_pass::<WrapMain>()?;
+        pm.register_pass::<LegalizeNames>()?;
 
-        register_alias!(pm, "validate", [WellFormed, Papercut, GuardCanonical]);
+        register_alias!(pm, "lint", [WellFormed, Papercut, UnreachableControl]);
+        register_alias!(
+            pm,
+            "validate",
+            [WellFormed, Papercut, GuardCanonical, UnreachableControl]
+        );
         register_alias!(
             pm,
             "pre-opt",
             [
+                InitInsertion,
+                DeadComponentRemoval,
+                SimplifyWithControl,
+                LowerStatic, // Must run before `infer-static-timing`.
                 RemoveCombGroups, // Must run before `infer-static-timing`.
                 InferStaticTiming,
                 CollapseControl,
@@ -64,6 +103,8 @@ impl PassManager {
             pm,
             "compile",
             [
+                CompileStream,
+                CompileDebug,
                 CompileInvoke,
                 CompileEmpty,
                 // StaticTiming,
@@ -3,6 +3,7 @@
 use crate::frontend::{ast, parser};
 use crate::ir;
 use petgraph::stable_graph::NodeIndex;
+use serde::Serialize;
 use std::rc::Rc;
 
 /// Standard error type for Calyx errors.
@@ -24,8 +25,9 @@ pub enum Error {
     /// Requirement of a pass was not satisfied
     PassAssumption(String, String),
 
-    /// The name has not been bound
-    Undefined(ir::Id, String),
+    /// The name has not been bound. Contains the kind of the name and a
+    /// similar name in scope that was probably meant instead.
+    Undefined(ir::Id, String, Option<String>),
     /// The name has already been bound.
     AlreadyBound(ir::Id, String),
 
@@ -43,7 +45,8 @@ pub enum Error {
     /// Papercut error: signals a commonly made mistake in Calyx program.
     Papercut(String, ir::Id),
 
-    /// Group "static" latency annotation differed from inferred latency.
+    /// A "static" latency annotation on a group or control statement differed
+    /// from the inferred latency.
     ImpossibleLatencyAnnotation(String, u64, u64),
 
     /// Internal compiler error that should never occur.
@@ -56,6 +59,13 @@ pub enum Error {
     InvalidFile(String),
     /// Failed to write the output
     WriteError(String),
+
+    /// An error about a definition generated from the given position in a
+    /// frontend program.
+    FromFrontend(Box<Error>, ir::SourcePos),
+    /// An error about definitions that were created by passes, along with
+    /// notes that describe where they came from.
+    Generated(Box<Error>, Vec<String>),
 }
 
 /// Convience wrapper to represent success or meaningul compiler error.
@@ -87,6 +97,34 @@ impl Span {
         }
     }
 
+    /// The input program that contains the span.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Name of the input file.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Byte offsets of the start and the end of the span in the input.
+    pub fn range(&self) -> (usize, usize) {
+        (self.start, self.end)
+    }
+
+    /// The file, line, and column at which the span starts. Lines and columns
+    /// start at 1.
+    pub fn location(&self) -> Location {
+        let before = &self.input[..self.start];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |idx| idx + 1);
+        Location {
+            file: Some(self.file.to_string()),
+            line,
+            column: column + 1,
+        }
+    }
+
     /// Format this Span with a the error message `err_msg`
     pub fn format(&self, err_msg: &str) -> String {
         let lines = self.input.split('\n');
@@ -113,20 +151,356 @@ impl Span {
     }
 }
 
+/// A position in the input program.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Location {
+    /// Name of the input file if it is known.
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Machine-readable form of an error, used by tools such as editors.
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    /// The error code, such as `E0007`.
+    pub code: &'static str,
+    /// The error message without the source location.
+    pub message: String,
+    /// Where in the input program the error occurs, if it is known.
+    #[serde(flatten)]
+    pub location: Option<Location>,
+    /// Position in the frontend program that generated the input program, if
+    /// it is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frontend: Option<ir::SourcePos>,
+    /// Passes that created the definitions the error is about.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+impl Error {
+    /// Location in the input program that the error points to, if any.
+    pub fn location(&self) -> Option<Location> {
+        use Error::*;
+        match self {
+            ParseError(err) => {
+                let (line, column) = match err.line_col {
+                    pest::error::LineColLocation::Pos(pos)
+                    | pest::error::LineColLocation::Span(pos, _) => pos,
+                };
+                Some(Location {
+                    file: err.path().map(|path| path.to_string()),
+                    line,
+                    column,
+                })
+            }
+            FromFrontend(err, _) | Generated(err, _) => err.location(),
+            _ => self.id()?.span().map(|span| span.location()),
+        }
+    }
+
+    /// The name that the error is about, if any.
+    pub(crate) fn id(&self) -> Option<&ir::Id> {
+        use Error::*;
+        match self {
+            Papercut(_, id)
+            | UnusedGroup(id)
+            | AlreadyBound(id, _)
+            | ReservedName(id)
+            | Undefined(id, ..)
+            | ParamBindingMissing(id, _) => Some(id),
+            MismatchedPortWidths(port, ..) => Some(port.port_name()),
+            Generated(err, _) => err.id(),
+            _ => None,
+        }
+    }
+
+    /// The error message without the source location.
+    pub fn message(&self) -> String {
+        use Error::*;
+        match self {
+            ParseError(err) => err.variant.message().to_string(),
+            Papercut(msg, _) => format!("[Papercut] {}", msg),
+            UnusedGroup(_) => "Group not used in control".to_string(),
+            AlreadyBound(_, bound_by) => {
+                format!("Name already bound by {}", bound_by)
+            }
+            ReservedName(name) => format!("Use of reserved keyword: {}", name),
+            Undefined(name, typ, suggestion) => {
+                let mut msg = format!("Undefined {} name: {}", typ, name);
+                if let Some(sugg) = suggestion {
+                    msg += &format!(", did you mean `{}`?", sugg);
+                }
+                msg
+            }
+            MismatchedPortWidths(port1, w1, port2, w2) => format!(
+                "Port `{}' has width {} which doesn't match the width {} of `{}'",
+                port1.port_name(),
+                w1,
+                w2,
+                port2.port_name()
+            ),
+            ParamBindingMissing(_, param_name) => {
+                format!("Failed to resolve: {}", param_name)
+            }
+            FromFrontend(err, _) | Generated(err, _) => err.message(),
+            _ => {
+                // The other errors do not contain locations so their message
+                // is the formatted error without the code.
+                let msg = format!("{:?}", self);
+                msg.split_once("] ").map_or(msg.clone(), |(_, msg)| {
+                    msg.to_string()
+                })
+            }
+        }
+    }
+
+    /// Machine-readable form of this error.
+    pub fn diagnostic(&self) -> Diagnostic {
+        let (frontend, notes) = match self {
+            Error::FromFrontend(err, pos) => (Some(pos.clone()), err.notes()),
+            Error::Generated(_, notes) => (None, notes.clone()),
+            _ => (None, vec![]),
+        };
+        Diagnostic {
+            code: self.code(),
+            message: self.message(),
+            location: self.location(),
+            frontend,
+            notes,
+        }
+    }
+
+    /// Stable code that identifies the kind of this error. Codes are printed
+    /// with the error message and explained by `futil --explain <code>`.
+    pub fn code(&self) -> &'static str {
+        use Error::*;
+        match self {
+            ParseError(..) => "E0001",
+            ReservedName(..) => "E0002",
+            MalformedControl(..) => "E0003",
+            MalformedStructure(..) => "E0004",
+            MismatchedPortWidths(..) => "E0005",
+            PassAssumption(..) => "E0006",
+            Undefined(..) => "E0007",
+            AlreadyBound(..) => "E0008",
+            UnusedGroup(..) => "E0009",
+            ParamBindingMissing(..) => "E0010",
+            InvalidParamBinding(..) => "E0011",
+            MissingImplementation(..) => "E0012",
+            Papercut(..) => "E0013",
+            ImpossibleLatencyAnnotation(..) => "E0014",
+            Impossible(..) => "E0015",
+            Misc(..) => "E0016",
+            InvalidFile(..) => "E0017",
+            WriteError(..) => "E0018",
+            FromFrontend(err, _) | Generated(err, _) => err.code(),
+        }
+    }
+
+    /// Notes about the passes that created the definitions this error is
+    /// about.
+    fn notes(&self) -> Vec<String> {
+        match self {
+            Error::Generated(_, notes) => notes.clone(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Long-form description of an error code.
+pub struct Explanation {
+    /// The error code.
+    pub code: &'static str,
+    /// One line summary of the error.
+    pub summary: &'static str,
+    /// Why the error is reported.
+    pub description: &'static str,
+    /// A program that triggers the error. Empty if the error cannot be
+    /// triggered by a program.
+    pub example: &'static str,
+    /// How to fix the error.
+    pub fix: &'static str,
+}
+
+/// Explanations of all the error codes reported by the compiler.
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "E0001",
+        summary: "The program could not be parsed",
+        description: "The input does not follow the syntax of Calyx programs. The error message points to the first token that could not be parsed.",
+        example: "cells { r = std_reg(32) }",
+        fix: "Correct the syntax at the reported location. In the example, the cell definition is missing a semicolon: `r = std_reg(32);`.",
+    },
+    Explanation {
+        code: "E0002",
+        summary: "A reserved keyword is used as a name",
+        description: "Keywords of the language, such as `group`, `seq`, or `while`, cannot be used as the names of cells, groups, or components.",
+        example: "cells { seq = std_reg(32); }",
+        fix: "Rename the cell, group, or component.",
+    },
+    Explanation {
+        code: "E0003",
+        summary: "The control program is malformed",
+        description: "A control statement cannot be compiled, for example, an `invoke` uses a combinational group that does not drive any of its inputs.",
+        example: "invoke f(in = x.out)() with cond;  // `cond` does not drive `x.out`",
+        fix: "Follow the suggestion in the error message. In the example, remove the `with` clause.",
+    },
+    Explanation {
+        code: "E0004",
+        summary: "The structure of the program is malformed",
+        description: "The cells, wires, or signature of a component violate a structural requirement, for example, a group is missing an assignment to its `done` hole or an interface port has the wrong width.",
+        example: "group incr { r.in = 32'd1; r.write_en = 1'd1; }",
+        fix: "Follow the suggestion in the error message. In the example, add the assignment `incr[done] = r.done;`.",
+    },
+    Explanation {
+        code: "E0005",
+        summary: "The ports of an assignment have different widths",
+        description: "Both sides of an assignment must have the same bit width.",
+        example: "cells { x = std_reg(32); add = std_add(16); }\nwires { add.left = x.out; }",
+        fix: "Change the width of one of the cells or use `std_slice` or `std_pad` to convert between widths.",
+    },
+    Explanation {
+        code: "E0006",
+        summary: "An assumption of a pass does not hold",
+        description: "Some passes require that the program has been transformed by other passes first.",
+        example: "",
+        fix: "Run the passes mentioned in the error message before the failing pass, or use a pass alias such as `all`.",
+    },
+    Explanation {
+        code: "E0007",
+        summary: "A name is not defined",
+        description: "The program refers to a cell, group, component, or primitive that has not been defined.",
+        example: "wires { r.in = 32'd1; }  // `r` is not in `cells`",
+        fix: "Define the name or correct its spelling. Primitives must be imported from a library, for example, `import \"primitives/core.futil\";`.",
+    },
+    Explanation {
+        code: "E0008",
+        summary: "A name is defined more than once",
+        description: "Cells, groups, and components share a namespace within their scope and must have unique names.",
+        example: "cells { r = std_reg(32); r = std_reg(1); }",
+        fix: "Rename one of the definitions.",
+    },
+    Explanation {
+        code: "E0009",
+        summary: "A group is not used in the control program",
+        description: "Groups that are never enabled are usually a mistake and are rejected by the `well-formed` pass.",
+        example: "wires { group incr { ... } }\ncontrol {}",
+        fix: "Enable the group in the control program or remove it.",
+    },
+    Explanation {
+        code: "E0010",
+        summary: "A parameter of a primitive is not bound",
+        description: "The signature of a primitive uses a parameter that has no value.",
+        example: "extern \"f.sv\" { primitive foo[WIDTH](in: SIZE) -> (); }",
+        fix: "Add the missing parameter to the primitive definition.",
+    },
+    Explanation {
+        code: "E0011",
+        summary: "A primitive is instantiated with the wrong number of parameters",
+        description: "Every parameter of a primitive must be given a value when it is instantiated.",
+        example: "cells { r = std_reg(); }",
+        fix: "Provide one value for each parameter, for example, `r = std_reg(32);`.",
+    },
+    Explanation {
+        code: "E0012",
+        summary: "An implementation is missing",
+        description: "The backend could not find the implementation for a primitive or component.",
+        example: "",
+        fix: "Make sure that the primitive is defined in an `extern` block whose file exists or that the component is defined.",
+    },
+    Explanation {
+        code: "E0013",
+        summary: "The program contains a common mistake",
+        description: "The `papercut` and `synthesis-papercut` passes detect programs that are valid but probably incorrect, for example, a group that writes to a register without driving its `write_en` port.",
+        example: "group save { r.in = 32'd1; save[done] = r.done; }",
+        fix: "Follow the suggestion in the error message. In the example, add `r.write_en = 1'd1;`.",
+    },
+    Explanation {
+        code: "E0014",
+        summary: "A \"static\" annotation does not match the inferred latency",
+        description: "The latency annotated with a `static` attribute must be the same as the latency inferred by `infer-static-timing`.",
+        example: "@static(3) par { @static(1) a; @static(2) b; }",
+        fix: "Correct the annotation or remove it so that the latency is inferred.",
+    },
+    Explanation {
+        code: "E0015",
+        summary: "Internal compiler error",
+        description: "The compiler reached a state that should never occur.",
+        example: "",
+        fix: "This is a bug in the compiler. Please report it along with the program that triggers it.",
+    },
+    Explanation {
+        code: "E0016",
+        summary: "Miscellaneous error",
+        description: "An error that does not fit any of the other categories, such as an invalid combination of command line options.",
+        example: "",
+        fix: "Follow the suggestion in the error message.",
+    },
+    Explanation {
+        code: "E0017",
+        summary: "The input file is invalid",
+        description: "An input file does not exist or could not be read.",
+        example: "",
+        fix: "Check the path to the file and the library path given with `-l`.",
+    },
+    Explanation {
+        code: "E0018",
+        summary: "The output could not be written",
+        description: "Writing the output of the compiler failed.",
+        example: "",
+        fix: "Check that the output path given with `-o` is writable.",
+    },
+];
+
+/// Returns the explanation of the error `code`. Codes are case insensitive.
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS
+        .iter()
+        .find(|expl| expl.code.eq_ignore_ascii_case(code))
+}
+
+impl std::fmt::Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "{}: {}\n", self.code, self.summary)?;
+        writeln!(f, "{}\n", self.description)?;
+        if !self.example.is_empty() {
+            writeln!(f, "Example:\n")?;
+            for line in self.example.lines() {
+                writeln!(f, "    {}", line)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "Fix: {}", self.fix)
+    }
+}
+
 impl std::fmt::Debug for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use Error::*;
+        if let FromFrontend(err, pos) = self {
+            return write!(f, "{:?}\n{}", err, pos);
+        }
+        if let Generated(err, notes) = self {
+            write!(f, "{:?}", err)?;
+            for note in notes {
+                write!(f, "\nnote: {}", note)?;
+            }
+            return Ok(());
+        }
+        write!(f, "[{}] ", self.code())?;
         match self {
             Papercut(msg, id) => {
                 write!(f, "{}", id.fmt_err(&("[Papercut] ".to_string() + msg)))
             }
-            ImpossibleLatencyAnnotation(grp_name, ann_val, inferred_val) => {
+            ImpossibleLatencyAnnotation(name, ann_val, inferred_val) => {
                 let msg1 = format!("Annotated latency: {}", ann_val);
                 let msg2 = format!("Inferred latency: {}", inferred_val);
                 write!(
                     f,
-                    "Impossible \"static\" latency annotation for group {}.\n{}\n{}",
-                    grp_name,
+                    "Impossible \"static\" latency annotation for {}.\n{}\n{}",
+                    name,
                     msg1,
                     msg2
                 )
@@ -146,8 +520,11 @@ impl std::fmt::Debug for Error {
                 let msg = format!("Use of reserved keyword: {}", name.to_string());
                 write!(f, "{}", name.fmt_err(&msg))
             }
-            Undefined(name, typ) => {
-                let msg = format!("Undefined {} name: {}", typ, name.to_string());
+            Undefined(name, typ, suggestion) => {
+                let mut msg = format!("Undefined {} name: {}", typ, name);
+                if let Some(sugg) = suggestion {
+                    msg += &format!(", did you mean `{}`?", sugg);
+                }
                 write!(
                     f,
                     "{}",
@@ -184,6 +561,7 @@ impl std::fmt::Debug for Error {
             Impossible(msg) => write!(f, "Impossible: {}\nThis error should never occur. Report report this as a bug.", msg),
             MissingImplementation(name, id) => write!(f, "Mising {} implementation for `{}`", name, id.to_string()),
             Misc(msg) => write!(f, "{}", msg),
+            FromFrontend(..) | Generated(..) => unreachable!(),
         }
     }
 }
@@ -227,7 +605,9 @@ impl Extract<NodeIndex, NodeIndex> for Option<NodeIndex> {
     fn extract(&self, id: &ir::Id) -> CalyxResult<NodeIndex> {
         match self {
             Some(t) => Ok(*t),
-            None => Err(Error::Undefined(id.clone(), "component".to_string())),
+            None => {
+                Err(Error::Undefined(id.clone(), "component".to_string(), None))
+            }
         }
     }
 }
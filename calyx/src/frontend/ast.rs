@@ -2,6 +2,7 @@
 use std::path::PathBuf;
 
 use atty::Stream;
+use serde::{Deserialize, Serialize};
 
 use crate::errors::{CalyxResult, Error, Span};
 use crate::ir;
@@ -9,14 +10,18 @@ use crate::ir;
 use super::parser;
 
 /// Corresponds to an individual Calyx file.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct NamespaceDef {
+    /// Directories searched for imported files.
+    pub lib_paths: Vec<String>,
     /// Path to extern files.
     pub imports: Vec<String>,
     /// List of component definitions.
     pub components: Vec<ComponentDef>,
     /// Extern statements and any primitive declarations in them.
     pub externs: Vec<(String, Vec<ir::Primitive>)>,
+    /// Entries of the trailing `metadata` block.
+    pub metadata: ir::Metadata,
 }
 
 impl NamespaceDef {
@@ -37,10 +42,12 @@ impl NamespaceDef {
 }
 
 /// AST statement for defining components.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentDef {
     /// Name of the component.
     pub name: ir::Id,
+    /// Parameters used by the signature and cells of the component.
+    pub params: Vec<ir::Id>,
     /// Defines input and output ports along with their attributes.
     pub signature: Vec<ir::PortDef>,
     /// List of instantiated sub-components
@@ -53,11 +60,15 @@ pub struct ComponentDef {
     pub control: Control,
     /// Attributes attached to this component
     pub attributes: ir::Attributes,
+    /// Latency of a `static<n>` component.
+    pub latency: Option<u64>,
+    /// Comments written before this component.
+    pub comments: Vec<String>,
 }
 
 /// Statement that refers to a port on a subcomponent.
 /// This is distinct from a `Portdef` which defines a port.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Port {
     /// Refers to the port named `port` on the subcomponent
     /// `component`.
@@ -89,7 +100,7 @@ impl Port {
 // AST for wire guard expressions
 // ===================================
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NumType {
     Decimal,
     Binary,
@@ -98,17 +109,18 @@ pub enum NumType {
 }
 
 /// Custom bitwidth numbers
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BitNum {
     pub width: u64,
     pub num_type: NumType,
     pub val: u64,
+    #[serde(skip)]
     pub span: Option<Span>,
 }
 
 /// Atomic operations used in guard conditions and RHS of the
 /// guarded assignments.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Atom {
     /// Accessing a particular port on a component.
     Port(Port),
@@ -117,7 +129,7 @@ pub enum Atom {
 }
 
 /// The AST for GuardExprs
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum GuardExpr {
     // Logical operations
     And(Box<GuardExpr>, Box<GuardExpr>),
@@ -135,7 +147,7 @@ pub enum GuardExpr {
 
 /// A guard is a conditions in `guard_conj` which guard the value
 /// represented by `expr`.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Guard {
     pub guard: Option<GuardExpr>,
     pub expr: Atom,
@@ -146,16 +158,19 @@ pub struct Guard {
 // ===================================
 
 /// Prototype of the cell definition
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Proto {
     /// Name of the primitive.
     pub name: ir::Id,
-    /// Parameter binding for primitives
-    pub params: Vec<u64>,
+    /// Parameter binding for the primitive or component. Arguments can refer
+    /// to the parameters of the enclosing component.
+    pub params: Vec<ir::Width>,
+    /// Values of the string parameters of the primitive.
+    pub string_params: Vec<String>,
 }
 
 /// The Cell AST nodes.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cell {
     /// Name of the cell.
     pub name: ir::Id,
@@ -163,6 +178,20 @@ pub struct Cell {
     pub prototype: Proto,
     /// Attributes attached to this cell definition
     pub attributes: ir::Attributes,
+    /// Initial contents of a memory.
+    pub contents: Option<Contents>,
+    /// Comments attached to this cell definition.
+    pub comments: Vec<String>,
+}
+
+/// Initial contents of a memory given by the program.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Contents {
+    /// Values of the elements in row-major order.
+    Values(Vec<u128>),
+    /// Path to a file of whitespace separated hexadecimal values, read when
+    /// the program is compiled.
+    File(String),
 }
 
 /// Methods for constructing the structure AST nodes.
@@ -171,7 +200,8 @@ impl Cell {
     pub fn from(
         name: ir::Id,
         proto: ir::Id,
-        params: Vec<u64>,
+        params: Vec<ir::Width>,
+        string_params: Vec<String>,
         attributes: ir::Attributes,
     ) -> Cell {
         Cell {
@@ -179,32 +209,42 @@ impl Cell {
             prototype: Proto {
                 name: proto,
                 params,
+                string_params,
             },
             attributes,
+            contents: None,
+            comments: vec![],
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Group {
     pub name: ir::Id,
     pub wires: Vec<Wire>,
     pub attributes: ir::Attributes,
     pub is_comb: bool,
+    /// Latency of a `static<n>` group.
+    pub latency: Option<u64>,
+    /// Comments written before this group.
+    pub comments: Vec<String>,
 }
 
 /// Data for the `->` structure statement.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Wire {
     /// Source of the wire.
     pub src: Guard,
 
     /// Guarded destinations of the wire.
     pub dest: Port,
+
+    /// Comments attached to the wire.
+    pub comments: Vec<String>,
 }
 
 /// Control AST nodes.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(clippy::large_enum_variant)]
 pub enum Control {
     /// Represents sequential composition of control statements.
@@ -213,6 +253,8 @@ pub enum Control {
         stmts: Vec<Control>,
         /// Attributes
         attributes: ir::Attributes,
+        /// Comments attached to the statement.
+        comments: Vec<String>,
     },
     /// Represents parallel composition of control statements.
     Par {
@@ -220,6 +262,8 @@ pub enum Control {
         stmts: Vec<Control>,
         /// Attributes
         attributes: ir::Attributes,
+        /// Comments attached to the statement.
+        comments: Vec<String>,
     },
     /// Standard imperative if statement
     If {
@@ -237,6 +281,8 @@ pub enum Control {
 
         /// Attributes
         attributes: ir::Attributes,
+        /// Comments attached to the statement.
+        comments: Vec<String>,
     },
     /// Standard imperative while statement
     While {
@@ -251,6 +297,8 @@ pub enum Control {
 
         /// Attributes
         attributes: ir::Attributes,
+        /// Comments attached to the statement.
+        comments: Vec<String>,
     },
     /// Runs the control for a list of subcomponents.
     Enable {
@@ -258,11 +306,16 @@ pub enum Control {
         comp: ir::Id,
         /// Attributes
         attributes: ir::Attributes,
+        /// Comments attached to the statement.
+        comments: Vec<String>,
     },
     /// Invoke component with input/output assignments.
     Invoke {
         /// Name of the component to be invoked.
         comp: ir::Id,
+        /// Name of the `@go` port of the interface used to invoke the
+        /// component.
+        interface: Option<ir::Id>,
         /// Input assignments
         inputs: Vec<(ir::Id, Atom)>,
         /// Output assignments
@@ -271,7 +324,17 @@ pub enum Control {
         attributes: ir::Attributes,
         /// Combinational group that may execute with this invoke.
         comb_group: Option<ir::Id>,
+        /// Comments attached to the statement.
+        comments: Vec<String>,
     },
     /// Control statement that does nothing.
     Empty {},
+    /// Print the values of ports when executed by a simulator.
+    Debug {
+        /// Format string in which every `{}` is replaced by the value of the
+        /// corresponding port.
+        format: String,
+        /// Ports whose values are printed.
+        ports: Vec<Port>,
+    },
 }
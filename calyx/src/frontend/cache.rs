@@ -0,0 +1,110 @@
+//! On-disk cache of parsed primitive libraries.
+use super::{ast::NamespaceDef, parser};
+use crate::{
+    errors::{CalyxResult, Error},
+    ir,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+/// Changed whenever the format of the cached entries changes so that stale
+/// entries are ignored.
+const CACHE_VERSION: u32 = 1;
+
+/// Signatures defined by a library file that only contains `extern` blocks.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    lib_paths: Vec<String>,
+    imports: Vec<String>,
+    externs: Vec<(String, Vec<ir::Primitive>)>,
+}
+
+/// Caches the primitive signatures defined by library files so that they are
+/// not re-parsed on every compilation.
+///
+/// Entries are keyed by the contents of the library file and only files that
+/// do not define any components are cached. An entry that cannot be read is
+/// treated as missing and a cache that cannot be written to is ignored.
+pub struct LibraryCache {
+    dir: PathBuf,
+}
+
+impl LibraryCache {
+    /// Use `dir` to store the cached entries. The directory is created if it
+    /// does not exist.
+    pub fn new(dir: &Path) -> CalyxResult<Self> {
+        fs::create_dir_all(dir).map_err(|err| {
+            Error::InvalidFile(format!(
+                "Failed to create cache directory `{}`: {}",
+                dir.to_string_lossy(),
+                err
+            ))
+        })?;
+        Ok(Self { dir: dir.into() })
+    }
+
+    /// Location of the entry for a file with the given contents.
+    fn entry_path(&self, content: &[u8]) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        CACHE_VERSION.hash(&mut hasher);
+        content.hash(&mut hasher);
+        self.dir.join(format!("{:016x}.json", hasher.finish()))
+    }
+
+    /// Parse the file at `path`, reusing the cached signatures if its
+    /// contents have been parsed before.
+    pub fn parse_file(&self, path: &Path) -> CalyxResult<NamespaceDef> {
+        let content = fs::read(path).map_err(|err| {
+            Error::InvalidFile(format!(
+                "Failed to read {}: {}",
+                path.to_string_lossy(),
+                err
+            ))
+        })?;
+        let entry_path = self.entry_path(&content);
+        let cached = fs::read(&entry_path)
+            .ok()
+            .and_then(|buf| serde_json::from_slice::<Entry>(&buf).ok());
+        if let Some(Entry {
+            lib_paths,
+            imports,
+            externs,
+        }) = cached
+        {
+            return Ok(NamespaceDef {
+                lib_paths,
+                imports,
+                components: vec![],
+                externs,
+                metadata: ir::Metadata::default(),
+            });
+        }
+
+        let ns = parser::CalyxParser::parse_file(path)?;
+        if ns.components.is_empty() {
+            let entry = Entry {
+                lib_paths: ns.lib_paths,
+                imports: ns.imports,
+                externs: ns.externs,
+            };
+            // Failing to write the entry only means that the file will be
+            // parsed again next time.
+            if let Ok(buf) = serde_json::to_vec(&entry) {
+                let _ = fs::write(&entry_path, buf);
+            }
+            return Ok(NamespaceDef {
+                lib_paths: entry.lib_paths,
+                imports: entry.imports,
+                components: vec![],
+                externs: entry.externs,
+                metadata: ir::Metadata::default(),
+            });
+        }
+        Ok(ns)
+    }
+}
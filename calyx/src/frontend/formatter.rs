@@ -0,0 +1,424 @@
+//! Canonical formatter for Calyx source files.
+//!
+//! The formatter works directly on the tokens of the source text so that
+//! comments and the order of definitions are preserved. The source is parsed
+//! first so that invalid programs are reported instead of being formatted.
+//! The output is such that:
+//! - Every statement and definition is on its own line and blocks are indented
+//!   with two spaces.
+//! - Spacing around operators, attributes, and punctuation is normalized.
+//! - The `=` of consecutive assignments and cell definitions are aligned.
+//! - Runs of blank lines are collapsed into a single blank line.
+
+use super::parser::{block_comment_len, CalyxParser};
+use crate::errors::CalyxResult;
+use std::path::Path;
+
+/// A token in the source text.
+#[derive(Debug, PartialEq)]
+enum Token {
+    /// Identifiers, keywords, and number literals.
+    Word(String),
+    /// String literal including the quotes.
+    Str(String),
+    /// Punctuation and operators.
+    Punct(&'static str),
+    /// Line comment without the trailing newline.
+    LineComment(String),
+    /// Block comment including the delimiters.
+    BlockComment(String),
+}
+
+/// Multi-character punctuation. Must be checked before single characters.
+const LONG_PUNCT: &[&str] = &["->", "==", "!=", "<=", ">="];
+const PUNCT: &[&str] = &[
+    "{", "}", "(", ")", "[", "]", "<", ">", ";", ",", "=", "?", ":", ".", "@",
+    "!", "&", "|",
+];
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '\''
+}
+
+/// A `-` at the start of `rest` is the sign of a number literal when it is
+/// followed by a digit and does not follow an operand.
+fn is_negative_literal(tokens: &[(usize, Token)], rest: &str) -> bool {
+    rest.starts_with('-')
+        && rest[1..].starts_with(|c: char| c.is_ascii_digit())
+        && !matches!(
+            tokens.last(),
+            Some((_, Token::Word(_) | Token::Punct(")" | "]")))
+        )
+}
+
+/// Split `src` into tokens. Each token is paired with the number of newlines
+/// that precede it.
+fn tokenize(src: &str) -> Vec<(usize, Token)> {
+    let mut tokens = vec![];
+    let mut newlines = 0;
+    let mut rest = src;
+    while let Some(c) = rest.chars().next() {
+        let (tok, len) = if c == '\n' {
+            newlines += 1;
+            rest = &rest[1..];
+            continue;
+        } else if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+            continue;
+        } else if rest.starts_with("//") {
+            let len = rest.find('\n').unwrap_or(rest.len());
+            (Token::LineComment(rest[..len].trim_end().to_string()), len)
+        } else if rest.starts_with("/*") {
+            let len = block_comment_len(rest).unwrap_or(rest.len());
+            (Token::BlockComment(rest[..len].to_string()), len)
+        } else if c == '"' {
+            let len = rest[1..].find('"').map(|i| i + 2).unwrap_or(rest.len());
+            (Token::Str(rest[..len].to_string()), len)
+        } else if is_word_char(c) || is_negative_literal(&tokens, rest) {
+            // Identifiers may contain `-` but `->` always starts an arrow.
+            let mut len = 0;
+            for (i, c) in rest.char_indices() {
+                if is_word_char(c) || (c == '-' && !rest[i..].starts_with("->"))
+                {
+                    len = i + c.len_utf8();
+                } else {
+                    break;
+                }
+            }
+            (Token::Word(rest[..len].to_string()), len)
+        } else if let Some(p) = LONG_PUNCT
+            .iter()
+            .chain(PUNCT.iter())
+            .find(|p| rest.starts_with(**p))
+        {
+            (Token::Punct(p), p.len())
+        } else {
+            // Unknown character: keep it as a word so that it is not lost.
+            (Token::Word(c.to_string()), c.len_utf8())
+        };
+        tokens.push((newlines, tok));
+        newlines = 0;
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+/// Renders the tokens of a single statement. Returns the rendered text and
+/// the byte offset of the first `=` outside of an attribute, if any.
+fn render(tokens: &[&Token]) -> (String, Option<usize>) {
+    let mut out = String::new();
+    let mut eq = None;
+    // Depth of `<...>` attribute lists and of parentheses.
+    let mut attr_depth = 0;
+    let mut paren_depth = 0;
+    // The previous token closed an attribute list.
+    let mut after_attr = false;
+    for (i, tok) in tokens.iter().enumerate() {
+        let prev = if i > 0 { Some(tokens[i - 1]) } else { None };
+        let text = match tok {
+            Token::Word(w) | Token::Str(w) => w.as_str(),
+            Token::Punct(p) => p,
+            Token::LineComment(_) | Token::BlockComment(_) => unreachable!(),
+        };
+        // `<` starts an attribute list when it follows the name of a
+        // component, group, or primitive.
+        let opens_attr = *tok == &Token::Punct("<")
+            && matches!(prev, Some(Token::Word(_)))
+            && i >= 2
+            && matches!(
+                tokens[i - 2],
+                Token::Word(w) if w == "group" || w == "component" || w == "primitive"
+            );
+        let closes_attr = attr_depth > 0 && *tok == &Token::Punct(">");
+
+        let space = match (prev, tok) {
+            (None, _) => false,
+            _ if opens_attr || closes_attr => false,
+            (_, Token::Punct("," | ";" | ")" | "]" | "}" | "." | ":")) => false,
+            (Some(Token::Punct("(" | "[" | "{" | "." | "@" | "!")), _) => false,
+            // Parameters, port lists, and invoke arguments.
+            (
+                Some(Token::Word(_) | Token::Punct(")" | "]")),
+                Token::Punct("("),
+            )
+            | (Some(Token::Word(_)), Token::Punct("[")) => false,
+            (_, Token::Punct("(" | "[")) if after_attr => false,
+            (Some(Token::Punct("<")), _) if attr_depth > 0 => false,
+            (_, Token::Punct("=")) | (Some(Token::Punct("=")), _)
+                if attr_depth > 0 =>
+            {
+                false
+            }
+            _ => true,
+        };
+        if space {
+            out.push(' ');
+        }
+        if *tok == &Token::Punct("=")
+            && attr_depth == 0
+            && paren_depth == 0
+            && eq.is_none()
+        {
+            eq = Some(out.len());
+        }
+        out.push_str(text);
+        after_attr = closes_attr;
+        if opens_attr {
+            attr_depth += 1;
+        } else if closes_attr {
+            attr_depth -= 1;
+        } else if *tok == &Token::Punct("(") {
+            paren_depth += 1;
+        } else if *tok == &Token::Punct(")") {
+            paren_depth -= 1;
+        }
+    }
+    (out, eq)
+}
+
+/// A line of formatted output.
+enum Line {
+    Blank,
+    Code {
+        depth: usize,
+        text: String,
+        /// Offset of the `=` in an assignment or cell definition.
+        eq: Option<usize>,
+        /// Comment at the end of the line.
+        comment: Option<String>,
+        /// This line opens a block.
+        opens: bool,
+    },
+    Comment {
+        depth: usize,
+        text: String,
+    },
+}
+
+#[derive(Default)]
+struct Formatter {
+    lines: Vec<Line>,
+    depth: usize,
+}
+
+impl Formatter {
+    fn last_opens(&self) -> bool {
+        matches!(self.lines.last(), Some(Line::Code { opens: true, .. }))
+    }
+
+    /// Add a blank line if the source had one and it would not be at the
+    /// start of a block.
+    fn blank(&mut self, newlines: usize) {
+        if newlines > 1
+            && !self.lines.is_empty()
+            && !self.last_opens()
+            && !matches!(self.lines.last(), Some(Line::Blank))
+        {
+            self.lines.push(Line::Blank)
+        }
+    }
+
+    fn code(&mut self, text: String, eq: Option<usize>, opens: bool) {
+        self.lines.push(Line::Code {
+            depth: self.depth,
+            text,
+            eq,
+            comment: None,
+            opens,
+        })
+    }
+
+    fn comment(&mut self, newlines: usize, text: &str) {
+        if newlines == 0 {
+            if let Some(Line::Code { comment, .. }) = self.lines.last_mut() {
+                if comment.is_none() {
+                    *comment = Some(text.to_string());
+                    return;
+                }
+            }
+        }
+        self.blank(newlines);
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let text = if i > 0 && line.starts_with('*') {
+                format!(" {}", line)
+            } else {
+                line.to_string()
+            };
+            self.lines.push(Line::Comment {
+                depth: self.depth,
+                text,
+            });
+        }
+    }
+
+    fn format(mut self, src: &str) -> String {
+        let tokens = tokenize(src);
+        let mut stmt: Vec<&Token> = vec![];
+        let mut stmt_newlines = 0;
+        // Depth of the `{...}` lists of memory contents in the statement.
+        let mut list_depth = 0;
+        for (newlines, tok) in &tokens {
+            match tok {
+                Token::LineComment(text) | Token::BlockComment(text) => {
+                    self.comment(*newlines, text)
+                }
+                // The contents of a memory, as in `m = std_mem_d1(...) =
+                // {1, 2}`, stay on the line of the cell definition.
+                Token::Punct("{")
+                    if list_depth > 0
+                        || stmt.last() == Some(&&Token::Punct("=")) =>
+                {
+                    list_depth += 1;
+                    stmt.push(tok);
+                }
+                Token::Punct("}") if list_depth > 0 => {
+                    list_depth -= 1;
+                    stmt.push(tok);
+                }
+                Token::Punct("{") => {
+                    let (text, _) = render(&stmt);
+                    let is_else = matches!(stmt.first(), Some(Token::Word(w)) if w == "else");
+                    match self.lines.last_mut() {
+                        Some(Line::Code {
+                            text: last,
+                            comment: None,
+                            opens,
+                            ..
+                        }) if is_else && last == "}" => {
+                            // Keep `} else {` on one line.
+                            *last = format!("}} {} {{", text);
+                            *opens = true;
+                        }
+                        _ => {
+                            self.blank(stmt_newlines);
+                            let text = if text.is_empty() {
+                                "{".to_string()
+                            } else {
+                                format!("{} {{", text)
+                            };
+                            self.code(text, None, true);
+                        }
+                    }
+                    self.depth += 1;
+                    stmt.clear();
+                }
+                Token::Punct("}") => {
+                    if !stmt.is_empty() {
+                        let (text, eq) = render(&stmt);
+                        self.blank(stmt_newlines);
+                        self.code(text, eq, false);
+                        stmt.clear();
+                    }
+                    self.depth = self.depth.saturating_sub(1);
+                    match self.lines.last_mut() {
+                        Some(Line::Code {
+                            text,
+                            opens: opens @ true,
+                            comment: None,
+                            ..
+                        }) => {
+                            text.push('}');
+                            *opens = false;
+                        }
+                        _ => self.code("}".to_string(), None, false),
+                    }
+                }
+                Token::Punct(";") => {
+                    stmt.push(tok);
+                    let (text, eq) = render(&stmt);
+                    self.blank(stmt_newlines);
+                    self.code(text, eq, false);
+                    stmt.clear();
+                }
+                _ => {
+                    if stmt.is_empty() {
+                        stmt_newlines = *newlines;
+                    }
+                    stmt.push(tok);
+                }
+            }
+        }
+        if !stmt.is_empty() {
+            let (text, eq) = render(&stmt);
+            self.code(text, eq, false);
+        }
+        self.align();
+        self.emit()
+    }
+
+    /// Align the `=` of consecutive assignments at the same depth.
+    fn align(&mut self) {
+        let mut start = 0;
+        while start < self.lines.len() {
+            let depth = match &self.lines[start] {
+                Line::Code {
+                    depth, eq: Some(_), ..
+                } => *depth,
+                _ => {
+                    start += 1;
+                    continue;
+                }
+            };
+            let mut end = start;
+            let mut col = 0;
+            while let Some(Line::Code {
+                depth: d,
+                eq: Some(eq),
+                ..
+            }) = self.lines.get(end)
+            {
+                if *d != depth {
+                    break;
+                }
+                col = col.max(*eq);
+                end += 1;
+            }
+            for line in &mut self.lines[start..end] {
+                if let Line::Code {
+                    text, eq: Some(eq), ..
+                } = line
+                {
+                    let pad = " ".repeat(col - *eq);
+                    text.insert_str(*eq, &pad);
+                }
+            }
+            start = end;
+        }
+    }
+
+    fn emit(self) -> String {
+        let mut out = String::new();
+        for line in self.lines {
+            match line {
+                Line::Blank => {}
+                Line::Code {
+                    depth,
+                    text,
+                    comment,
+                    ..
+                } => {
+                    out.push_str(&"  ".repeat(depth));
+                    out.push_str(&text);
+                    if let Some(comment) = comment {
+                        out.push(' ');
+                        out.push_str(&comment);
+                    }
+                }
+                Line::Comment { depth, text } => {
+                    out.push_str(&"  ".repeat(depth));
+                    out.push_str(&text);
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Format the Calyx program in `src`, which is stored at `path`. Returns an
+/// error if `src` does not parse.
+pub fn format(src: &str, path: &Path) -> CalyxResult<String> {
+    CalyxParser::parse_source(src, path)?;
+    Ok(Formatter::default().format(src))
+}
@@ -0,0 +1,55 @@
+//! Compact binary encoding of Calyx programs.
+//!
+//! Programs are stored as a [NamespaceDef] encoded using `bincode` and
+//! prefixed by a header that identifies the format. This allows large
+//! generated designs to be loaded without re-parsing their textual
+//! representation.
+use super::ast::NamespaceDef;
+use crate::errors::{CalyxResult, Error};
+use std::convert::TryInto;
+use std::io::Write;
+
+/// Marks the start of a binary Calyx program.
+const MAGIC: &[u8; 8] = b"CALYXBIN";
+
+/// Changed whenever the encoding of the AST changes so that stale files are
+/// rejected instead of being misinterpreted.
+const VERSION: u32 = 6;
+
+/// Returns true if `buf` starts with the header of a binary Calyx program.
+pub fn is_binary(buf: &[u8]) -> bool {
+    buf.starts_with(MAGIC)
+}
+
+/// Encode `ns` and write it to `w`.
+pub fn write<W: Write>(ns: &NamespaceDef, w: &mut W) -> CalyxResult<()> {
+    let to_err = |err: &dyn std::fmt::Display| {
+        Error::WriteError(format!("Failed to write binary program: {}", err))
+    };
+    w.write_all(MAGIC).map_err(|err| to_err(&err))?;
+    w.write_all(&VERSION.to_le_bytes())
+        .map_err(|err| to_err(&err))?;
+    bincode::serialize_into(w, ns).map_err(|err| to_err(&err))
+}
+
+/// Decode a binary Calyx program. `file` is only used for error messages.
+pub fn read(buf: &[u8], file: &str) -> CalyxResult<NamespaceDef> {
+    let invalid = |msg: String| {
+        Error::InvalidFile(format!("Failed to read {}: {}", file, msg))
+    };
+    let rest = buf
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| invalid("not a binary Calyx program".to_string()))?;
+    if rest.len() < 4 {
+        return Err(invalid("truncated header".to_string()));
+    }
+    let (version, rest) = rest.split_at(4);
+    let version = u32::from_le_bytes(version.try_into().unwrap());
+    if version != VERSION {
+        return Err(invalid(format!(
+            "binary format version {} is not supported (expected {})",
+            version, VERSION
+        )));
+    }
+    bincode::deserialize(rest).map_err(|err| invalid(err.to_string()))
+}
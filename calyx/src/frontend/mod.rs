@@ -5,8 +5,12 @@
 //! in the `ir` module.
 
 pub mod ast;
+mod cache;
+mod formatter;
+pub mod ir_bin;
 pub mod parser;
 mod workspace;
 
 pub use ast::NamespaceDef;
-pub use workspace::Workspace;
+pub use formatter::format;
+pub use workspace::{LibraryConf, Workspace};
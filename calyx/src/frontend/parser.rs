@@ -2,10 +2,12 @@
 
 //! Parser for Calyx programs.
 use super::ast::{self, BitNum, NumType};
+use super::ir_bin;
 use crate::errors::{self, CalyxResult, Span};
 use crate::ir;
 use pest::prec_climber::{Assoc, Operator, PrecClimber};
 use pest_consume::{match_nodes, Error, Parser};
+use std::collections::HashMap;
 use std::fs;
 use std::io::Read;
 use std::path::Path;
@@ -40,6 +42,15 @@ lazy_static::lazy_static! {
             // tighest binding
         ]
     );
+    static ref WIDTH_CLIMBER: PrecClimber<Rule> = PrecClimber::new(
+        vec![
+            // loosest binding
+            Operator::new(Rule::width_add, Assoc::Left)
+                | Operator::new(Rule::width_sub, Assoc::Left),
+            Operator::new(Rule::width_mul, Assoc::Left),
+            // tighest binding
+        ]
+    );
 }
 
 #[derive(Parser)]
@@ -56,29 +67,43 @@ impl CalyxParser {
                 err.to_string()
             ))
         })?;
+        if ir_bin::is_binary(content) {
+            return ir_bin::read(content, &path.to_string_lossy());
+        }
         let string_content = std::str::from_utf8(content)?;
+        CalyxParser::parse_source(string_content, path)
+    }
+
+    /// Parse the source of a Calyx program that is stored at `path`. The
+    /// source does not have to match the contents of the file, which allows
+    /// editors to check unsaved programs.
+    pub fn parse_source(
+        src: &str,
+        path: &Path,
+    ) -> CalyxResult<ast::NamespaceDef> {
         let user_data = UserData {
-            input: Rc::from(string_content),
+            input: Rc::from(src),
             file: Rc::from(path.to_string_lossy()),
         };
-        let inputs = CalyxParser::parse_with_userdata(
-            Rule::file,
-            string_content,
-            user_data,
-        )
-        .map_err(|e| e.with_path(&path.to_string_lossy()))?;
+        let inputs =
+            CalyxParser::parse_with_userdata(Rule::file, src, user_data)
+                .map_err(|e| e.with_path(&path.to_string_lossy()))?;
         let input = inputs.single()?;
         Ok(CalyxParser::file(input)?)
     }
 
     pub fn parse<R: Read>(mut r: R) -> CalyxResult<ast::NamespaceDef> {
-        let mut buf = String::new();
-        r.read_to_string(&mut buf).map_err(|err| {
+        let mut bytes = vec![];
+        r.read_to_end(&mut bytes).map_err(|err| {
             errors::Error::InvalidFile(format!(
                 "Failed to parse buffer: {}",
                 err.to_string()
             ))
         })?;
+        if ir_bin::is_binary(&bytes) {
+            return ir_bin::read(&bytes, "<stdin>");
+        }
+        let buf = String::from_utf8(bytes).map_err(|err| err.utf8_error())?;
         let user_data = UserData {
             input: Rc::from(buf.as_ref()),
             file: Rc::from("<stdin>"),
@@ -90,6 +115,149 @@ impl CalyxParser {
     }
 }
 
+/// Length of the block comment at the start of `src`, including its
+/// delimiters. Block comments nest, so `/* a /* b */ c */` is a single
+/// comment. Returns `None` if the comment is not closed.
+pub(super) fn block_comment_len(src: &str) -> Option<usize> {
+    let bytes = src.as_bytes();
+    let mut depth = 0;
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx..].starts_with(b"/*") {
+            depth += 1;
+            idx += 2;
+        } else if bytes[idx..].starts_with(b"*/") {
+            depth -= 1;
+            idx += 2;
+            if depth == 0 {
+                return Some(idx);
+            }
+        } else {
+            idx += 1;
+        }
+    }
+    None
+}
+
+/// Byte offset of the start of the block comment that ends `src`, taking
+/// nested comments into account.
+fn block_comment_start(src: &str) -> Option<usize> {
+    let bytes = src.as_bytes();
+    let mut depth = 0;
+    let mut idx = bytes.len();
+    while idx >= 2 {
+        if bytes[..idx].ends_with(b"*/") {
+            depth += 1;
+            idx -= 2;
+        } else if bytes[..idx].ends_with(b"/*") {
+            depth -= 1;
+            idx -= 2;
+            if depth == 0 {
+                return Some(idx);
+            }
+        } else {
+            idx -= 1;
+        }
+    }
+    None
+}
+
+/// Full-line comments written directly before the byte offset `start` in
+/// `src`. Blank lines between the comments and `start` are skipped.
+fn leading_comments(src: &str, start: usize) -> Vec<String> {
+    let mut comments = vec![];
+    // Only nodes that start their line can have leading comments.
+    let (mut rest, line) = src[..start]
+        .rsplit_once('\n')
+        .unwrap_or(("", &src[..start]));
+    if !line.trim().is_empty() {
+        return comments;
+    }
+    while !rest.is_empty() {
+        let (before, line) = rest.rsplit_once('\n').unwrap_or(("", rest));
+        let line = line.trim();
+        if line.starts_with("//") {
+            comments.push(line.to_string());
+            rest = before;
+        } else if line.ends_with("*/") {
+            // Block comments may span multiple lines and contain other
+            // block comments.
+            let open = match block_comment_start(rest.trim_end()) {
+                Some(open) => open,
+                None => break,
+            };
+            let line_start = rest[..open].rfind('\n').map_or(0, |i| i + 1);
+            if !rest[line_start..open].trim().is_empty() {
+                break;
+            }
+            comments.push(rest[open..].trim_end().to_string());
+            rest = &rest[..line_start.saturating_sub(1)];
+        } else if line.is_empty() {
+            rest = before;
+        } else {
+            break;
+        }
+    }
+    comments.reverse();
+    comments
+}
+
+/// Comment written on the same line after the byte offset `end` in `src`.
+fn trailing_comment(src: &str, end: usize) -> Option<String> {
+    let rest = &src[end..];
+    let line = rest[..rest.find('\n').unwrap_or(rest.len())].trim();
+    if line.starts_with("//")
+        || (line.starts_with("/*")
+            && block_comment_len(line) == Some(line.len()))
+    {
+        Some(line.to_string())
+    } else {
+        None
+    }
+}
+
+/// Comments that belong to `node`. Comments written after `node` on the same
+/// line are only included when `trailing` is true.
+fn comments(node: &Node, trailing: bool) -> Vec<String> {
+    let src = &node.user_data().input;
+    let span = node.as_span();
+    let mut comments = leading_comments(src, span.start());
+    if trailing {
+        comments.extend(trailing_comment(src, span.end()));
+    }
+    comments
+}
+
+/// A numeric parameter of a primitive along with its default value.
+type NumParam = (ir::Id, Option<u64>);
+
+/// A numeric or string parameter of a primitive.
+enum Param<N, S> {
+    Num(N),
+    Str(S),
+}
+
+/// Split `params` into the numeric and the string parameters. String
+/// parameters must come after all the numeric ones.
+fn split_params<N, S>(
+    params: impl Iterator<Item = Param<N, S>>,
+    span: &Node,
+) -> ParseResult<(Vec<N>, Vec<S>)> {
+    let (mut nums, mut strs) = (vec![], vec![]);
+    for param in params {
+        match param {
+            Param::Num(_) if !strs.is_empty() => {
+                return Err(span.error(
+                    "String parameters must come after numeric parameters",
+                ))
+            }
+            Param::Num(num) => nums.push(num),
+            Param::Str(s) => strs.push(s),
+        }
+    }
+    Ok((nums, strs))
+}
+
 #[allow(clippy::large_enum_variant)]
 enum ExtOrComp {
     Ext((String, Vec<ir::Primitive>)),
@@ -110,6 +278,26 @@ impl CalyxParser {
         Ok(())
     }
 
+    fn component_latency(input: Node) -> ParseResult<Option<u64>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [static_latency(latency)] => Some(latency),
+            [] => None,
+        ))
+    }
+
+    fn static_latency(input: Node) -> ParseResult<u64> {
+        let span = input.clone();
+        let latency = match_nodes!(
+            input.into_children();
+            [bitwidth(latency)] => latency
+        );
+        if latency == 0 {
+            return Err(span.error("The latency of `static` must be positive"));
+        }
+        Ok(latency)
+    }
+
     // ================ Literals =====================
     fn identifier(input: Node) -> ParseResult<ir::Id> {
         let ud = input.user_data();
@@ -156,7 +344,7 @@ impl CalyxParser {
         let ud = input.user_data();
         let input_ref = Rc::clone(&ud.input);
         let file_ref = Rc::clone(&ud.file);
-        let num = match_nodes!(
+        let mut num = match_nodes!(
             input.clone().into_children();
             [bitwidth(width), decimal(val)] => BitNum {
                     width,
@@ -201,6 +389,27 @@ impl CalyxParser {
 
         );
 
+        // Negative literals are lowered to their two's complement and must
+        // be at least the smallest signed number of the given width.
+        if input.as_str().starts_with('-') {
+            if num.width > 64 {
+                return Err(input.error(format!(
+                    "Negative literals wider than 64 bits are not supported, found width {}",
+                    num.width
+                )));
+            }
+            if num.width == 0 || num.val > 1 << (num.width - 1) {
+                let bit_plural = if num.width == 1 { "bit" } else { "bits" };
+                return Err(input.error(format!(
+                    "Cannot represent given literal '{}' in {} {}",
+                    input.as_str(),
+                    num.width,
+                    bit_plural
+                )));
+            }
+            num.val = num.val.wrapping_neg() & (u64::MAX >> (64 - num.width));
+        }
+
         // the below cast is safe since the width must be less than 64 for
         // the given literal to be unrepresentable
         if num.width == 0
@@ -234,10 +443,11 @@ impl CalyxParser {
     }
 
     // ================ Attributes =====================
-    fn attribute(input: Node) -> ParseResult<(String, u64)> {
+    fn attribute(input: Node) -> ParseResult<(String, ir::AttrValue)> {
         Ok(match_nodes!(
             input.into_children();
-            [string_lit(key), bitwidth(num)] => (key, num)
+            [string_lit(key), bitwidth(num)] => (key, num.into()),
+            [string_lit(key), string_lit(val)] => (key, val.into())
         ))
     }
     fn attributes(input: Node) -> ParseResult<ir::Attributes> {
@@ -256,18 +466,19 @@ impl CalyxParser {
         ))
     }
 
-    fn attr_val(input: Node) -> ParseResult<u64> {
+    fn attr_val(input: Node) -> ParseResult<ir::AttrValue> {
         Ok(match_nodes!(
             input.into_children();
-            [bitwidth(num)] => num
+            [bitwidth(num)] => num.into(),
+            [string_lit(val)] => val.into()
         ))
     }
 
-    fn at_attribute(input: Node) -> ParseResult<(String, u64)> {
+    fn at_attribute(input: Node) -> ParseResult<(String, ir::AttrValue)> {
         Ok(match_nodes!(
             input.into_children();
-            [identifier(key), attr_val(num)] => (key.id, num),
-            [identifier(key)] => (key.id, 1)
+            [identifier(key), attr_val(val)] => (key.id, val),
+            [identifier(key)] => (key.id, ir::AttrValue::Num(1))
         ))
     }
 
@@ -279,30 +490,90 @@ impl CalyxParser {
     }
 
     // ================ Signature =====================
-    fn params(input: Node) -> ParseResult<Vec<ir::Id>> {
+    fn string_param(input: Node) -> ParseResult<ir::Id> {
+        Ok(match_nodes!(
+            input.into_children();
+            [identifier(id)] => id
+        ))
+    }
+
+    fn default_param(input: Node) -> ParseResult<(ir::Id, u64)> {
+        Ok(match_nodes!(
+            input.into_children();
+            [identifier(id), bitwidth(val)] => (id, val)
+        ))
+    }
+
+    fn param(input: Node) -> ParseResult<Param<NumParam, ir::Id>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [string_param(id)] => Param::Str(id),
+            [default_param((id, val))] => Param::Num((id, Some(val))),
+            [identifier(id)] => Param::Num((id, None))
+        ))
+    }
+
+    fn params(input: Node) -> ParseResult<(Vec<NumParam>, Vec<ir::Id>)> {
+        let span = input.clone();
+        match_nodes!(
+            input.into_children();
+            [param(params)..] => split_params(params, &span)
+        )
+    }
+
+    #[prec_climb(width_atom, WIDTH_CLIMBER)]
+    fn width(l: ir::Width, op: Node, r: ir::Width) -> ParseResult<ir::Width> {
+        let op = match op.as_rule() {
+            Rule::width_add => ir::WidthOp::Add,
+            Rule::width_sub => ir::WidthOp::Sub,
+            Rule::width_mul => ir::WidthOp::Mul,
+            _ => unreachable!(),
+        };
+        Ok(ir::Width::Expr {
+            op,
+            left: Box::new(l),
+            right: Box::new(r),
+        })
+    }
+
+    fn width_atom(input: Node) -> ParseResult<ir::Width> {
         Ok(match_nodes!(
             input.into_children();
-            [identifier(id)..] => id.collect()
+            [bitwidth(value)] => ir::Width::Const { value },
+            [identifier(value)] => ir::Width::Param { value },
+            [width(width)] => width
         ))
     }
 
-    fn args(input: Node) -> ParseResult<Vec<u64>> {
+    fn arg(input: Node) -> ParseResult<Param<ir::Width, String>> {
         Ok(match_nodes!(
             input.into_children();
-            [bitwidth(bw)..] => bw.collect(),
-            [] => vec![]
+            [width(width)] => Param::Num(width),
+            [string_lit(value)] => Param::Str(value)
         ))
     }
 
+    fn args(input: Node) -> ParseResult<(Vec<ir::Width>, Vec<String>)> {
+        let span = input.clone();
+        match_nodes!(
+            input.into_children();
+            [arg(args)..] => split_params(args, &span),
+        )
+    }
+
+    fn inout(_input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+
     fn io_port(
         input: Node,
-    ) -> ParseResult<(ir::Id, ir::Width, ir::Attributes)> {
+    ) -> ParseResult<(ir::Id, ir::Width, bool, ir::Attributes)> {
         Ok(match_nodes!(
             input.into_children();
-            [at_attributes(attrs), identifier(id), bitwidth(value)] =>
-                (id, ir::Width::Const { value }, attrs),
-            [at_attributes(attrs), identifier(id), identifier(value)] =>
-                (id, ir::Width::Param { value }, attrs)
+            [at_attributes(attrs), identifier(id), width(width)] =>
+                (id, width, false, attrs),
+            [at_attributes(attrs), inout(_), identifier(id), width(width)] =>
+                (id, width, true, attrs)
         ))
     }
 
@@ -310,8 +581,11 @@ impl CalyxParser {
         Ok(match_nodes!(
             input.into_children();
             [io_port(ins)..] => {
-                ins.map(|(name, width, attributes)| ir::PortDef {
-                    name, width, direction: ir::Direction::Input, attributes
+                ins.map(|(name, width, inout, attributes)| ir::PortDef {
+                    name,
+                    width,
+                    direction: if inout { ir::Direction::Inout } else { ir::Direction::Input },
+                    attributes
                 }).collect()
             }
         ))
@@ -321,8 +595,11 @@ impl CalyxParser {
         Ok(match_nodes!(
             input.into_children();
             [io_port(outs)..] => {
-                outs.map(|(name, width, attributes)| ir::PortDef {
-                    name, width, direction: ir::Direction::Output, attributes
+                outs.map(|(name, width, inout, attributes)| ir::PortDef {
+                    name,
+                    width,
+                    direction: if inout { ir::Direction::Inout } else { ir::Direction::Output },
+                    attributes
                 }).collect()
             }
         ))
@@ -343,50 +620,114 @@ impl CalyxParser {
     }
 
     // ==============Primitives=====================
+    #[allow(clippy::type_complexity)]
     fn sig_with_params(
         input: Node,
-    ) -> ParseResult<(Vec<ir::Id>, Vec<ir::PortDef>)> {
+    ) -> ParseResult<(Vec<(ir::Id, Option<u64>)>, Vec<ir::Id>, Vec<ir::PortDef>)>
+    {
         Ok(match_nodes!(
             input.into_children();
-            [params(p), signature(s)] => (p, s),
-            [signature(s)] => (vec![], s),
+            [params((p, sp)), signature(s)] => (p, sp, s),
+            [signature(s)] => (vec![], vec![], s),
         ))
     }
     fn primitive(input: Node) -> ParseResult<ir::Primitive> {
-        Ok(match_nodes!(
-            input.into_children();
-            [name_with_attribute((name, attrs)), sig_with_params((p, s))] => ir::Primitive {
+        let prim = match_nodes!(
+            input.clone().into_children();
+            [name_with_attribute((name, attrs)), sig_with_params((p, sp, s))] => ir::Primitive {
                 name,
-                params: p,
+                params: p.iter().map(|(id, _)| id.clone()).collect(),
+                param_defaults: p.into_iter().map(|(_, val)| val).collect(),
+                string_params: sp,
                 signature: s,
                 attributes: attrs,
                 is_comb: false,
             },
-            [comb(_), name_with_attribute((name, attrs)), sig_with_params((p, s))] => ir::Primitive {
+            [comb(_), name_with_attribute((name, attrs)), sig_with_params((p, sp, s))] => ir::Primitive {
                 name,
-                params: p,
+                params: p.iter().map(|(id, _)| id.clone()).collect(),
+                param_defaults: p.into_iter().map(|(_, val)| val).collect(),
+                string_params: sp,
                 signature: s,
                 attributes: attrs,
                 is_comb: true,
             },
-        ))
+        );
+        // Parameters without defaults cannot be omitted, so they must come
+        // before all the parameters with defaults.
+        let first_default =
+            prim.param_defaults.iter().position(Option::is_some);
+        if let Some(idx) = first_default {
+            if let Some((param, _)) = prim
+                .params
+                .iter()
+                .zip(&prim.param_defaults)
+                .skip(idx)
+                .find(|(_, val)| val.is_none())
+            {
+                return Err(input.error(format!(
+                    "Parameter `{}` of primitive `{}` must have a default value since it comes after parameter `{}`, which has one",
+                    param, prim.name, prim.params[idx]
+                )));
+            }
+        }
+        // Every `@read_together` group describes how to read one output.
+        let mut outputs: HashMap<u64, usize> = HashMap::new();
+        for pd in prim.find_all_with_attr("read_together") {
+            let idx = pd.attributes.get("read_together").copied().unwrap();
+            let count = outputs.entry(idx).or_default();
+            if pd.direction == ir::Direction::Output {
+                *count += 1;
+            }
+        }
+        if outputs.values().any(|count| *count != 1) {
+            return Err(input.error(format!(
+                "Invalid @read_together specification for primitive `{}`. Each specification group must have exactly one output port.",
+                prim.name
+            )));
+        }
+        Ok(prim)
     }
 
     // ================ Cells =====================
+    fn content_value(input: Node) -> ParseResult<u128> {
+        input
+            .as_str()
+            .parse::<u128>()
+            .map_err(|_| input.error("Expected a value that fits in 128 bits"))
+    }
+
+    fn contents(input: Node) -> ParseResult<ast::Contents> {
+        Ok(match_nodes!(
+            input.into_children();
+            [string_lit(file)] => ast::Contents::File(file),
+            [content_value(vals)..] => ast::Contents::Values(vals.collect()),
+        ))
+    }
+
     fn cell_without_semi(input: Node) -> ParseResult<ast::Cell> {
         Ok(match_nodes!(
             input.into_children();
-            [at_attributes(attrs), identifier(id), identifier(prim), args(args)] =>
-            ast::Cell::from(id, prim, args, attrs)
+            [at_attributes(attrs), identifier(id), identifier(prim), args((args, strs))] =>
+            ast::Cell::from(id, prim, args, strs, attrs),
+            [at_attributes(attrs), identifier(id), identifier(prim), args((args, strs)), contents(contents)] => {
+                let mut cell = ast::Cell::from(id, prim, args, strs, attrs);
+                cell.contents = Some(contents);
+                cell
+            }
         ))
     }
 
     fn cell(input: Node) -> ParseResult<ast::Cell> {
+        let comments = comments(&input, true);
         match_nodes!(
             input.clone().into_children();
             [cell_without_semi(_)] =>
                 Err(input.error("Declaration is missing `;`")),
-            [cell_without_semi(node), semi(_)] => Ok(node),
+            [cell_without_semi(mut node), semi(_)] => {
+                node.comments = comments;
+                Ok(node)
+            },
         )
     }
 
@@ -510,33 +851,49 @@ impl CalyxParser {
     }
 
     fn wire(input: Node) -> ParseResult<ast::Wire> {
+        let comments = comments(&input, true);
         Ok(match_nodes!(
             input.into_children();
             [LHS(dest), expr(expr)] => ast::Wire {
                 src: ast::Guard { guard: None, expr },
-                dest
+                dest,
+                comments,
             },
             [LHS(dest), switch_stmt(src)] => ast::Wire {
                 src,
-                dest
+                dest,
+                comments,
             }
         ))
     }
 
     fn group(input: Node) -> ParseResult<ast::Group> {
+        let comments = comments(&input, false);
         Ok(match_nodes!(
             input.into_children();
             [name_with_attribute((name, attrs)), wire(wire)..] => ast::Group {
                 name,
                 attributes: attrs,
+                comments,
                 wires: wire.collect(),
                 is_comb: false,
+                latency: None,
             },
             [comb(_), name_with_attribute((name, attrs)), wire(wire)..] => ast::Group {
                 name,
                 attributes: attrs,
+                comments,
                 wires: wire.collect(),
                 is_comb: true,
+                latency: None,
+            },
+            [static_latency(latency), name_with_attribute((name, attrs)), wire(wire)..] => ast::Group {
+                name,
+                attributes: attrs,
+                comments,
+                wires: wire.collect(),
+                is_comb: false,
+                latency: Some(latency),
             }
         ))
     }
@@ -573,54 +930,83 @@ impl CalyxParser {
         ))
     }
 
+    fn invoke_interface(input: Node) -> ParseResult<Option<ir::Id>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [identifier(go)] => Some(go),
+            [] => None
+        ))
+    }
+
     fn invoke(input: Node) -> ParseResult<ast::Control> {
+        let comments = comments(&input, true);
         Ok(match_nodes!(
             input.into_children();
-            [at_attributes(attrs), identifier(comp), invoke_args(inputs), invoke_args(outputs)] =>
+            [at_attributes(attrs), identifier(comp), invoke_interface(interface), invoke_args(inputs), invoke_args(outputs)] =>
                 ast::Control::Invoke {
                     comp,
+                    interface,
                     inputs,
                     outputs,
                     attributes: attrs,
-                    comb_group: None
+                    comb_group: None,
+                    comments,
                 },
-            [at_attributes(attrs), identifier(comp), invoke_args(inputs), invoke_args(outputs), identifier(group)] =>
+            [at_attributes(attrs), identifier(comp), invoke_interface(interface), invoke_args(inputs), invoke_args(outputs), identifier(group)] =>
                 ast::Control::Invoke {
                     comp,
+                    interface,
                     inputs,
                     outputs,
                     attributes: attrs,
-                    comb_group: Some(group)
+                    comb_group: Some(group),
+                    comments,
                 }
         ))
     }
 
     fn enable(input: Node) -> ParseResult<ast::Control> {
+        let comments = comments(&input, true);
         Ok(match_nodes!(
             input.into_children();
             [at_attributes(attrs), identifier(name)] => ast::Control::Enable {
                 comp: name,
-                attributes: attrs
+                attributes: attrs,
+                comments
+            }
+        ))
+    }
+
+    fn debug(input: Node) -> ParseResult<ast::Control> {
+        Ok(match_nodes!(
+            input.into_children();
+            [string_lit(format), port(ports)..] => ast::Control::Debug {
+                format,
+                ports: ports.collect(),
             }
         ))
     }
 
     fn seq(input: Node) -> ParseResult<ast::Control> {
+        let comments = comments(&input, false);
         Ok(match_nodes!(
             input.into_children();
             [at_attributes(attrs), stmt(stmt)..] => ast::Control::Seq {
                 stmts: stmt.collect(),
                 attributes: attrs,
+                comments,
             }
         ))
     }
 
     fn par(input: Node) -> ParseResult<ast::Control> {
+        let comments = comments(&input, false);
         Ok(match_nodes!(
             input.into_children();
             [at_attributes(attrs), stmt(stmt)..] => ast::Control::Par {
                 stmts: stmt.collect(),
                 attributes: attrs,
+                comments,
             }
         ))
     }
@@ -634,6 +1020,7 @@ impl CalyxParser {
     }
 
     fn if_stmt(input: Node) -> ParseResult<ast::Control> {
+        let comments = comments(&input, false);
         Ok(match_nodes!(
             input.into_children();
             [at_attributes(attrs), port_with((port, cond)), block(stmt)] => ast::Control::If {
@@ -642,6 +1029,7 @@ impl CalyxParser {
                 tbranch: Box::new(stmt),
                 fbranch: Box::new(ast::Control::Empty{}),
                 attributes: attrs,
+                comments,
             },
             [at_attributes(attrs), port_with((port, cond)), block(tbranch), block(fbranch)] =>
                 ast::Control::If {
@@ -650,6 +1038,7 @@ impl CalyxParser {
                     tbranch: Box::new(tbranch),
                     fbranch: Box::new(fbranch),
                     attributes: attrs,
+                    comments,
                 },
             [at_attributes(attrs), port_with((port, cond)), block(tbranch), if_stmt(fbranch)] =>
                 ast::Control::If {
@@ -658,12 +1047,14 @@ impl CalyxParser {
                     tbranch: Box::new(tbranch),
                     fbranch: Box::new(fbranch),
                     attributes: attrs,
+                    comments,
                 },
 
         ))
     }
 
     fn while_stmt(input: Node) -> ParseResult<ast::Control> {
+        let comments = comments(&input, false);
         Ok(match_nodes!(
             input.into_children();
             [at_attributes(attrs), port_with((port, cond)), block(stmt)] => ast::Control::While {
@@ -671,6 +1062,7 @@ impl CalyxParser {
                 cond,
                 body: Box::new(stmt),
                 attributes: attrs,
+                comments,
             }
         ))
     }
@@ -678,15 +1070,29 @@ impl CalyxParser {
     fn stmt(input: Node) -> ParseResult<ast::Control> {
         Ok(match_nodes!(
             input.into_children();
+            [debug(data)] => data,
             [enable(data)] => data,
             [invoke(data)] => data,
             [seq(data)] => data,
             [par(data)] => data,
             [if_stmt(data)] => data,
             [while_stmt(data)] => data,
+            [legacy_stmt(data)] => data,
         ))
     }
 
+    fn legacy_stmt(input: Node) -> ParseResult<ast::Control> {
+        let stmt = input.as_str();
+        let msg = if stmt.starts_with("ifen") {
+            "`ifen` is no longer supported. Use `if <port> with <group> { ... } else { ... }` instead."
+        } else if stmt.starts_with("print") {
+            "`print` is no longer supported. Use `@debug(\"<message>\", <ports>);` to print the values of ports instead."
+        } else {
+            "`disable` is no longer supported. Groups are only active while they are enabled, so remove this statement."
+        };
+        Err(input.error(msg))
+    }
+
     fn block(input: Node) -> ParseResult<ast::Control> {
         Ok(match_nodes!(
             input.into_children();
@@ -712,26 +1118,47 @@ impl CalyxParser {
     }
 
     fn component(input: Node) -> ParseResult<ast::ComponentDef> {
-        Ok(match_nodes!(
+        let comments = comments(&input, false);
+        let span = input.clone();
+        match_nodes!(
             input.into_children();
             [
+                component_latency(latency),
                 name_with_attribute((name, attributes)),
-                signature(sig),
+                sig_with_params((params, string_params, sig)),
                 cells(cells),
                 connections(connections),
                 control(control)
             ] => {
+                if !string_params.is_empty() {
+                    return Err(span.error(format!(
+                        "Component `{}` cannot define string parameters",
+                        name
+                    )));
+                }
+                if let Some((param, _)) =
+                    params.iter().find(|(_, val)| val.is_some())
+                {
+                    return Err(span.error(format!(
+                        "Parameter `{}` of component `{}` cannot have a default value",
+                        param, name
+                    )));
+                }
+                let params = params.into_iter().map(|(id, _)| id).collect();
                 let (continuous_assignments, groups) = connections;
-                ast::ComponentDef {
+                Ok(ast::ComponentDef {
                     name,
+                    params,
                     signature: sig,
                     cells,
                     groups,
                     continuous_assignments,
                     control,
                     attributes,
-                }
-        }))
+                    latency,
+                    comments,
+                })
+        })
     }
 
     fn imports(input: Node) -> ParseResult<Vec<String>> {
@@ -741,6 +1168,13 @@ impl CalyxParser {
         ))
     }
 
+    fn lib_paths(input: Node) -> ParseResult<Vec<String>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [string_lit(path)..] => path.collect()
+        ))
+    }
+
     fn ext(input: Node) -> ParseResult<(String, Vec<ir::Primitive>)> {
         Ok(match_nodes!(
             input.into_children();
@@ -756,15 +1190,42 @@ impl CalyxParser {
         ))
     }
 
+    fn source_pos(input: Node) -> ParseResult<(u64, ir::SourcePos)> {
+        Ok(match_nodes!(
+            input.into_children();
+            [bitwidth(idx), string_lit(file), bitwidth(line)] => {
+                (idx, ir::SourcePos { file, line })
+            }
+        ))
+    }
+
+    fn metadata(input: Node) -> ParseResult<ir::Metadata> {
+        let mut metadata = ir::Metadata::default();
+        for node in input.into_children() {
+            let (idx, pos) = Self::source_pos(node.clone())?;
+            if metadata.insert(idx, pos).is_some() {
+                return Err(node.error(format!(
+                    "Metadata entry {} is defined more than once",
+                    idx
+                )));
+            }
+        }
+        Ok(metadata)
+    }
+
     fn file(input: Node) -> ParseResult<ast::NamespaceDef> {
         Ok(match_nodes!(
             input.into_children();
-            [imports(imports), extern_or_component(mixed).., _EOI] => {
+            // `match_nodes` checks the rules of the patterns after `..` in
+            // reverse order, so the metadata node is matched without a rule.
+            [lib_paths(lib_paths), imports(imports), extern_or_component(mixed).., metadata, _EOI] => {
                 let mut namespace =
                     ast::NamespaceDef {
+                        lib_paths,
                         imports,
                         components: Vec::new(),
                         externs: Vec::new(),
+                        metadata: Self::metadata(metadata)?,
                     };
                 for m in mixed {
                     match m {
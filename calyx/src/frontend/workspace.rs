@@ -5,6 +5,7 @@ use std::{
 
 use super::{
     ast::{ComponentDef, NamespaceDef},
+    cache::LibraryCache,
     parser,
 };
 use crate::{
@@ -35,6 +36,13 @@ use crate::{
 /// relative path to this file. It *does not* look for `core.sv` on the library path.
 ///
 /// Finally, since `core.futil` does not `import` any file, the parsing process is completed.
+///
+/// A file can add directories to search for its imports using `lib_path` statements before its
+/// imports. These are searched after the parent folder and before the library paths:
+/// ```text
+/// lib_path "../primitives";
+/// import "core.futil";
+/// ```
 #[derive(Default)]
 pub struct Workspace {
     /// List of component definitions that need to be compiled.
@@ -47,16 +55,38 @@ pub struct Workspace {
     pub externs: Vec<(PathBuf, Vec<ir::Primitive>)>,
     /// Original import statements present in the top-level file.
     pub original_imports: Vec<String>,
+    /// Original `lib_path` statements present in the top-level file.
+    pub original_lib_paths: Vec<String>,
+    /// The `metadata` block of the top-level file.
+    pub metadata: ir::Metadata,
+}
+
+/// Configuration for finding and loading the libraries imported by a program.
+#[derive(Default)]
+pub struct LibraryConf {
+    /// Directories searched for imported files, in order.
+    pub lib_paths: Vec<PathBuf>,
+    /// Directory used to cache the signatures of parsed library files.
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl From<&Path> for LibraryConf {
+    fn from(lib_path: &Path) -> Self {
+        LibraryConf {
+            lib_paths: vec![lib_path.into()],
+            cache_dir: None,
+        }
+    }
 }
 
 impl Workspace {
     /// Returns the absolute location to an imported file.
-    /// Imports can refer to files either in the library path or in the parent
-    /// folder.
+    /// Imports can refer to files either in the parent folder or in one of the
+    /// library paths.
     fn canonicalize_import<S>(
         import: S,
         parent: &Path,
-        lib_path: &Path,
+        lib_paths: &[PathBuf],
     ) -> CalyxResult<PathBuf>
     where
         S: AsRef<Path> + Clone,
@@ -65,19 +95,32 @@ impl Workspace {
         if parent_path.exists() {
             return Ok(parent_path);
         }
-        let lib = lib_path.join(import.clone());
-        if lib.exists() {
-            return Ok(lib);
+        for lib_path in lib_paths {
+            let lib = lib_path.join(import.clone());
+            if lib.exists() {
+                return Ok(lib);
+            }
         }
 
         Err(Error::InvalidFile(
-            format!("Import path `{}` found neither in the parent ({}) nor library path ({})",
+            format!("Import path `{}` found neither in the parent ({}) nor library paths ({})",
             import.as_ref().to_string_lossy(),
             parent.to_string_lossy(),
-            lib_path.to_string_lossy()
+            lib_paths.iter().map(|p| p.to_string_lossy()).collect::<Vec<_>>().join(", ")
         )))
     }
 
+    /// Returns the absolute location of a directory.
+    fn canonicalize_dir(dir: &Path) -> CalyxResult<PathBuf> {
+        dir.canonicalize().map_err(|err| {
+            Error::InvalidFile(format!(
+                "Failed to canonicalize library path `{}`: {}",
+                dir.to_string_lossy(),
+                err
+            ))
+        })
+    }
+
     // Get the absolute path to an extern. Extern can only exist on paths
     // relative to the parent.
     fn canonicalize_extern<S>(
@@ -104,7 +147,16 @@ impl Workspace {
         file: &Option<PathBuf>,
         lib_path: &Path,
     ) -> CalyxResult<Self> {
-        Self::construct_with_all_deps(file, lib_path, false)
+        Self::construct_with_all_deps(file, &lib_path.into(), false)
+    }
+
+    /// Construct a new workspace from an input stream representing a Calyx
+    /// program and find the imported files using the given configuration.
+    pub fn construct_with_conf(
+        file: &Option<PathBuf>,
+        conf: &LibraryConf,
+    ) -> CalyxResult<Self> {
+        Self::construct_with_all_deps(file, conf, false)
     }
 
     /// Construct the Workspace using the given [NamespaceDef] and ignore all
@@ -113,7 +165,23 @@ impl Workspace {
         file: &Option<PathBuf>,
         lib_path: &Path,
     ) -> CalyxResult<Self> {
-        Self::construct_with_all_deps(file, lib_path, true)
+        Self::construct_with_all_deps(file, &lib_path.into(), true)
+    }
+
+    /// Construct a new workspace from the source of a program stored at
+    /// `file`. The imports of the program are read from the file system.
+    pub fn construct_from_source(
+        src: &str,
+        file: &Path,
+        conf: &LibraryConf,
+    ) -> CalyxResult<Self> {
+        let namespace = parser::CalyxParser::parse_source(src, file)?;
+        Self::construct_from_namespace(
+            namespace,
+            &Self::get_parent(file),
+            conf,
+            false,
+        )
     }
 
     fn get_parent(p: &Path) -> PathBuf {
@@ -134,7 +202,7 @@ impl Workspace {
     /// files.
     fn construct_with_all_deps(
         file: &Option<PathBuf>,
-        lib_path: &Path,
+        conf: &LibraryConf,
         // Parse imported components as declarations
         shallow: bool,
     ) -> CalyxResult<Self> {
@@ -144,23 +212,39 @@ impl Workspace {
             .as_ref()
             .map(|p| Self::get_parent(p))
             .unwrap_or_else(|| PathBuf::from("."));
+        Self::construct_from_namespace(namespace, &parent_path, conf, shallow)
+    }
 
+    /// Construct the Workspace by transitively parsing all the files
+    /// `import`ed by `namespace`, which is defined in `parent_path`.
+    fn construct_from_namespace(
+        namespace: NamespaceDef,
+        parent_path: &Path,
+        conf: &LibraryConf,
+        // Parse imported components as declarations
+        shallow: bool,
+    ) -> CalyxResult<Self> {
         // Set of current dependencies
         let mut dependencies: Vec<PathBuf> = Vec::new();
         // Set of imports that have already been parsed once.
         let mut already_imported: HashSet<PathBuf> = HashSet::new();
 
         let mut workspace = Workspace::default();
-        let abs_lib_path = lib_path.canonicalize().map_err(|err| {
-            Error::InvalidFile(format!(
-                "Failed to canonicalize library path `{}`: {}",
-                lib_path.to_string_lossy(),
-                err
-            ))
-        })?;
+        let abs_lib_paths = conf
+            .lib_paths
+            .iter()
+            .map(|p| Self::canonicalize_dir(p))
+            .collect::<CalyxResult<Vec<_>>>()?;
+        let cache = conf
+            .cache_dir
+            .as_ref()
+            .map(|dir| LibraryCache::new(dir))
+            .transpose()?;
 
         // Add original imports to workspace
         workspace.original_imports = namespace.imports.clone();
+        workspace.original_lib_paths = namespace.lib_paths.clone();
+        workspace.metadata = namespace.metadata.clone();
 
         // Function to merge contents of a namespace into the workspace and
         // return the dependencies that need to be parsed next.
@@ -189,11 +273,19 @@ impl Workspace {
                 workspace.components.extend(&mut ns.components.into_iter());
             }
 
+            // Library paths of this file are searched before the global ones
+            let lib_paths = ns
+                .lib_paths
+                .iter()
+                .map(|p| Self::canonicalize_dir(&parent.join(p)))
+                .chain(abs_lib_paths.iter().cloned().map(Ok))
+                .collect::<CalyxResult<Vec<_>>>()?;
+
             // Return the canonical location of import paths
             let deps = ns
                 .imports
                 .into_iter()
-                .map(|p| Self::canonicalize_import(p, parent, &abs_lib_path))
+                .map(|p| Self::canonicalize_import(p, parent, &lib_paths))
                 .collect::<CalyxResult<_>>()?;
 
             Ok(deps)
@@ -214,7 +306,10 @@ impl Workspace {
             if already_imported.contains(&p) {
                 continue;
             }
-            let ns = parser::CalyxParser::parse_file(&p)?;
+            let ns = match &cache {
+                Some(cache) => cache.parse_file(&p)?,
+                None => parser::CalyxParser::parse_file(&p)?,
+            };
             let parent = Self::get_parent(&p);
 
             let mut deps = merge_into_ws(ns, &parent, shallow)?;
@@ -0,0 +1,411 @@
+//! Generators of random well-formed programs for property-based testing.
+//!
+//! The generators are [proptest] strategies that produce frontend ASTs, so
+//! failing programs are automatically shrunk to small examples. Every
+//! generated program defines a single `main` component whose cells only use
+//! the primitives declared in [PRELUDE]. Use [workspace] to turn a generated
+//! program into a [Workspace] that can be passed to
+//! [ast_to_ir](crate::ir::from_ast::ast_to_ir):
+//! ```ignore
+//! proptest! {
+//!     #[test]
+//!     fn my_pass_preserves_x(ns in fuzz::arb_program()) {
+//!         let ctx = ast_to_ir(fuzz::workspace(ns), BackendConf::default())?;
+//!         ...
+//!     }
+//! }
+//! ```
+//! Only available with the `fuzz` feature.
+use crate::frontend::{ast, parser::CalyxParser, Workspace};
+use crate::ir;
+use proptest::prelude::*;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Declarations of the primitives used by the generated programs. Printed
+/// programs can be parsed again by prepending these declarations.
+pub const PRELUDE: &str = r#"extern "fuzz.sv" {
+  comb primitive std_add[WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH);
+  comb primitive std_lt[WIDTH](left: WIDTH, right: WIDTH) -> (out: 1);
+  primitive std_reg<"static"=1>[WIDTH](
+    @write_together(1) in: WIDTH,
+    @write_together(1) @go write_en: 1,
+    @clk clk: 1,
+    @reset reset: 1
+  ) -> (
+    @stable out: WIDTH,
+    @done done: 1
+  );
+}"#;
+
+/// The primitives declared by [PRELUDE].
+pub fn primitives() -> Vec<ir::Primitive> {
+    CalyxParser::parse_source(PRELUDE, Path::new("fuzz.futil"))
+        .expect("the fuzzing prelude should parse")
+        .externs
+        .into_iter()
+        .flat_map(|(_, prims)| prims)
+        .collect()
+}
+
+/// A workspace with the components of `ns` and the primitives of
+/// [PRELUDE]. The imports and externs of `ns` are ignored.
+pub fn workspace(ns: ast::NamespaceDef) -> Workspace {
+    Workspace {
+        components: ns.components,
+        externs: vec![(PathBuf::from("fuzz.sv"), primitives())],
+        ..Default::default()
+    }
+}
+
+/// The cells available to the generated assignments.
+#[derive(Debug, Clone, Copy)]
+struct Shape {
+    /// Width of the registers and adders.
+    width: u64,
+    /// Number of registers named `r0`, `r1`, ...
+    regs: usize,
+    /// Number of adders named `a0`, `a1`, ...
+    adds: usize,
+}
+
+/// Name of the comparator read by the conditions of `if` and `while`.
+const COND_CELL: &str = "lt";
+/// Name of the combinational group that drives [COND_CELL].
+const COND_GROUP: &str = "cond";
+
+fn port(cell: String, port: &str) -> ast::Port {
+    ast::Port::Comp {
+        component: cell.into(),
+        port: port.into(),
+    }
+}
+
+fn num(width: u64, val: u64) -> ast::Atom {
+    ast::Atom::Num(ast::BitNum {
+        width,
+        num_type: ast::NumType::Decimal,
+        val,
+        span: None,
+    })
+}
+
+fn wire(
+    dest: ast::Port,
+    guard: Option<ast::GuardExpr>,
+    expr: ast::Atom,
+) -> ast::Wire {
+    ast::Wire {
+        src: ast::Guard { guard, expr },
+        dest,
+        comments: vec![],
+    }
+}
+
+/// Values of `shape.width` bits read from registers or constants.
+fn arb_value(shape: Shape) -> BoxedStrategy<ast::Atom> {
+    let width = shape.width;
+    let max = if width >= 64 {
+        u64::MAX
+    } else {
+        (1 << width) - 1
+    };
+    prop_oneof![
+        (0..shape.regs)
+            .prop_map(|r| ast::Atom::Port(port(format!("r{}", r), "out"))),
+        (0..=max).prop_map(move |val| num(width, val)),
+    ]
+    .boxed()
+}
+
+/// One-bit guards over the registers.
+fn arb_guard(shape: Shape) -> BoxedStrategy<ast::GuardExpr> {
+    let leaf = prop_oneof![
+        (0..shape.regs).prop_map(|r| ast::GuardExpr::Atom(ast::Atom::Port(
+            port(format!("r{}", r), "done")
+        ))),
+        (arb_value(shape), arb_value(shape))
+            .prop_map(|(l, r)| ast::GuardExpr::Eq(l, r)),
+        (arb_value(shape), arb_value(shape))
+            .prop_map(|(l, r)| ast::GuardExpr::Neq(l, r)),
+        (arb_value(shape), arb_value(shape))
+            .prop_map(|(l, r)| ast::GuardExpr::Lt(l, r)),
+    ];
+    leaf.prop_recursive(2, 8, 2, |inner| {
+        prop_oneof![
+            (inner.clone(), inner.clone()).prop_map(|(l, r)| {
+                ast::GuardExpr::And(Box::new(l), Box::new(r))
+            }),
+            (inner.clone(), inner.clone()).prop_map(|(l, r)| {
+                ast::GuardExpr::Or(Box::new(l), Box::new(r))
+            }),
+            inner.prop_map(|g| ast::GuardExpr::Not(Box::new(g))),
+        ]
+    })
+    .boxed()
+}
+
+/// The assignments of a group named `name` that writes a value, possibly
+/// computed by an adder, to a register.
+fn arb_group(shape: Shape, name: String) -> BoxedStrategy<ast::Group> {
+    let adder = if shape.adds == 0 {
+        Just(None).boxed()
+    } else {
+        prop::option::of((0..shape.adds, arb_value(shape), arb_value(shape)))
+            .boxed()
+    };
+    (
+        0..shape.regs,
+        arb_value(shape),
+        adder,
+        prop::option::of(arb_guard(shape)),
+    )
+        .prop_map(move |(reg, value, adder, guard)| {
+            let reg = format!("r{}", reg);
+            let mut wires = vec![];
+            let value = match adder {
+                Some((add, left, right)) => {
+                    let add = format!("a{}", add);
+                    wires.push(wire(port(add.clone(), "left"), None, left));
+                    wires.push(wire(port(add.clone(), "right"), None, right));
+                    ast::Atom::Port(port(add, "out"))
+                }
+                None => value,
+            };
+            wires.push(wire(port(reg.clone(), "in"), guard, value));
+            wires.push(wire(port(reg.clone(), "write_en"), None, num(1, 1)));
+            wires.push(wire(
+                ast::Port::Hole {
+                    group: name.as_str().into(),
+                    name: "done".into(),
+                },
+                None,
+                ast::Atom::Port(port(reg, "done")),
+            ));
+            ast::Group {
+                name: name.as_str().into(),
+                wires,
+                attributes: ir::Attributes::default(),
+                is_comb: false,
+                latency: None,
+                comments: vec![],
+            }
+        })
+        .boxed()
+}
+
+/// Attributes with numeric and string values.
+fn arb_attributes() -> BoxedStrategy<ir::Attributes> {
+    prop::collection::vec(
+        prop_oneof![
+            (0..4u64).prop_map(|n| ("fuzz".to_string(), n.into())),
+            "[a-z]{1,4}".prop_map(|s| ("name".to_string(), s.into())),
+        ],
+        0..2,
+    )
+    .prop_map(|attrs: Vec<(String, ir::AttrValue)>| attrs.into())
+    .boxed()
+}
+
+/// Control programs that enable the groups `g0` to `g{groups - 1}`.
+pub fn arb_control(groups: usize) -> BoxedStrategy<ast::Control> {
+    let enable = (0..groups, arb_attributes()).prop_map(|(g, attributes)| {
+        ast::Control::Enable {
+            comp: format!("g{}", g).into(),
+            attributes,
+            comments: vec![],
+        }
+    });
+    let cond_port = || port(COND_CELL.to_string(), "out");
+    enable
+        .prop_recursive(3, 16, 3, move |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 1..4).prop_map(|stmts| {
+                    ast::Control::Seq {
+                        stmts,
+                        attributes: ir::Attributes::default(),
+                        comments: vec![],
+                    }
+                }),
+                prop::collection::vec(inner.clone(), 1..3).prop_map(|stmts| {
+                    ast::Control::Par {
+                        stmts,
+                        attributes: ir::Attributes::default(),
+                        comments: vec![],
+                    }
+                }),
+                (inner.clone(), prop::option::of(inner.clone())).prop_map(
+                    move |(tbranch, fbranch)| ast::Control::If {
+                        port: cond_port(),
+                        cond: Some(COND_GROUP.into()),
+                        tbranch: Box::new(tbranch),
+                        fbranch: Box::new(
+                            fbranch.unwrap_or(ast::Control::Empty {})
+                        ),
+                        attributes: ir::Attributes::default(),
+                        comments: vec![],
+                    }
+                ),
+                (inner, prop::option::of(1..8u64)).prop_map(
+                    move |(body, bound)| {
+                        let mut attributes = ir::Attributes::default();
+                        if let Some(bound) = bound {
+                            attributes.insert("bound", bound);
+                        }
+                        ast::Control::While {
+                            port: cond_port(),
+                            cond: Some(COND_GROUP.into()),
+                            body: Box::new(body),
+                            attributes,
+                            comments: vec![],
+                        }
+                    }
+                ),
+            ]
+        })
+        .boxed()
+}
+
+/// Adds the names of the groups enabled by `control` to `used`. Returns true
+/// if `control` reads the condition of an `if` or `while`.
+fn used_groups(control: &ast::Control, used: &mut HashSet<ir::Id>) -> bool {
+    match control {
+        ast::Control::Seq { stmts, .. } | ast::Control::Par { stmts, .. } => {
+            stmts
+                .iter()
+                .fold(false, |cond, stmt| used_groups(stmt, used) || cond)
+        }
+        ast::Control::If {
+            tbranch, fbranch, ..
+        } => {
+            used_groups(tbranch, used);
+            used_groups(fbranch, used);
+            true
+        }
+        ast::Control::While { body, .. } => {
+            used_groups(body, used);
+            true
+        }
+        ast::Control::Enable { comp, .. } => {
+            used.insert(comp.clone());
+            false
+        }
+        _ => false,
+    }
+}
+
+/// A `main` component with registers, adders, groups that write to the
+/// registers, and a control program over the groups.
+pub fn arb_component() -> BoxedStrategy<ast::ComponentDef> {
+    (
+        prop::sample::select(vec![1u64, 4, 32]),
+        1..4usize,
+        0..3usize,
+        1..5usize,
+    )
+        .prop_flat_map(|(width, regs, adds, groups)| {
+            let shape = Shape { width, regs, adds };
+            let group_strats = (0..groups)
+                .map(|g| arb_group(shape, format!("g{}", g)))
+                .collect::<Vec<_>>();
+            (
+                Just(shape),
+                group_strats,
+                arb_value(shape),
+                prop::collection::vec(arb_attributes(), regs + adds + 1),
+                arb_control(groups),
+            )
+        })
+        .prop_map(|(shape, mut groups, bound, mut attrs, mut control)| {
+            let width = vec![ir::Width::Const { value: shape.width }];
+            let mut cell = |name: String, prim: &str| {
+                ast::Cell::from(
+                    name.into(),
+                    prim.into(),
+                    width.clone(),
+                    vec![],
+                    attrs.pop().unwrap_or_default(),
+                )
+            };
+            let mut cells = (0..shape.regs)
+                .map(|r| cell(format!("r{}", r), "std_reg"))
+                .collect::<Vec<_>>();
+            cells.extend(
+                (0..shape.adds).map(|a| cell(format!("a{}", a), "std_add")),
+            );
+            cells.push(cell(COND_CELL.to_string(), "std_lt"));
+
+            // Groups that are never enabled are rejected by
+            // `well-formed`, so they are run at the end of the program.
+            let mut used = HashSet::new();
+            let uses_cond = used_groups(&control, &mut used);
+            let unused = groups
+                .iter()
+                .filter(|group| !used.contains(&group.name))
+                .map(|group| ast::Control::Enable {
+                    comp: group.name.clone(),
+                    attributes: ir::Attributes::default(),
+                    comments: vec![],
+                })
+                .collect::<Vec<_>>();
+            if !unused.is_empty() {
+                control = ast::Control::Seq {
+                    stmts: vec![
+                        control,
+                        ast::Control::Par {
+                            stmts: unused,
+                            attributes: ir::Attributes::default(),
+                            comments: vec![],
+                        },
+                    ],
+                    attributes: ir::Attributes::default(),
+                    comments: vec![],
+                };
+            }
+
+            let lt = COND_CELL.to_string();
+            if uses_cond {
+                groups.push(ast::Group {
+                    name: COND_GROUP.into(),
+                    wires: vec![
+                        wire(
+                            port(lt.clone(), "left"),
+                            None,
+                            ast::Atom::Port(port("r0".to_string(), "out")),
+                        ),
+                        wire(port(lt, "right"), None, bound),
+                    ],
+                    attributes: ir::Attributes::default(),
+                    is_comb: true,
+                    latency: None,
+                    comments: vec![],
+                });
+            }
+            ast::ComponentDef {
+                name: "main".into(),
+                params: vec![],
+                signature: vec![],
+                cells,
+                groups,
+                continuous_assignments: vec![],
+                control,
+                attributes: ir::Attributes::default(),
+                latency: None,
+                comments: vec![],
+            }
+        })
+        .boxed()
+}
+
+/// A program with a single generated `main` component.
+pub fn arb_program() -> BoxedStrategy<ast::NamespaceDef> {
+    arb_component()
+        .prop_map(|comp| ast::NamespaceDef {
+            lib_paths: vec![],
+            imports: vec![],
+            components: vec![comp],
+            externs: vec![],
+            metadata: ir::Metadata::default(),
+        })
+        .boxed()
+}
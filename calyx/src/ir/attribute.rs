@@ -1,11 +1,53 @@
+use super::{Id, Provenance};
 use linked_hash_map::LinkedHashMap;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 use std::ops::Index;
 
+/// The value of an attribute. Most attributes carry a number
+/// (`@static(1)`) but some carry a string (`@bind("vendor_mult")`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AttrValue {
+    /// A numeric attribute value.
+    Num(u64),
+    /// A string attribute value.
+    Str(String),
+}
+
+impl From<u64> for AttrValue {
+    fn from(n: u64) -> Self {
+        AttrValue::Num(n)
+    }
+}
+
+impl From<String> for AttrValue {
+    fn from(s: String) -> Self {
+        AttrValue::Str(s)
+    }
+}
+
+impl Display for AttrValue {
+    /// Numbers are printed as is and strings are printed with quotes.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttrValue::Num(n) => write!(f, "{}", n),
+            AttrValue::Str(s) => write!(f, "\"{}\"", s),
+        }
+    }
+}
+
 /// Attributes associated with a specific IR structure.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Attributes {
     /// Mapping from the name of the attribute to its value.
-    pub(super) attrs: LinkedHashMap<String, u64>,
+    pub(super) attrs: LinkedHashMap<String, AttrValue>,
+    /// The pass that created the structure, if it was created by a pass.
+    #[serde(skip)]
+    pub(super) provenance: Option<Provenance>,
+    /// Key of the comments of the structure in the comments of its
+    /// component, if it has comments.
+    #[serde(skip)]
+    pub(super) comment_key: Option<usize>,
 }
 
 impl Default for Attributes {
@@ -13,14 +55,18 @@ impl Default for Attributes {
         Attributes {
             // Does not allocate any space.
             attrs: LinkedHashMap::with_capacity(0),
+            provenance: None,
+            comment_key: None,
         }
     }
 }
 
-impl From<Vec<(String, u64)>> for Attributes {
-    fn from(v: Vec<(String, u64)>) -> Self {
+impl From<Vec<(String, AttrValue)>> for Attributes {
+    fn from(v: Vec<(String, AttrValue)>) -> Self {
         Attributes {
             attrs: v.into_iter().collect(),
+            provenance: None,
+            comment_key: None,
         }
     }
 }
@@ -40,11 +86,45 @@ impl Attributes {
     where
         S: ToString + std::hash::Hash,
     {
-        self.attrs.insert(key.to_string(), val);
+        self.attrs.insert(key.to_string(), AttrValue::Num(val));
     }
 
-    /// Get the value associated with an attribute key
+    /// Add a new string-valued attribute
+    pub fn insert_str<S, V>(&mut self, key: S, val: V)
+    where
+        S: ToString + std::hash::Hash,
+        V: ToString,
+    {
+        self.attrs
+            .insert(key.to_string(), AttrValue::Str(val.to_string()));
+    }
+
+    /// Get the numeric value associated with an attribute key. Returns
+    /// `None` if the attribute is not set or carries a string.
     pub fn get<S>(&self, key: S) -> Option<&u64>
+    where
+        S: std::fmt::Display + AsRef<str>,
+    {
+        match self.attrs.get(&key.as_ref().to_string()) {
+            Some(AttrValue::Num(n)) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Get the string value associated with an attribute key. Returns
+    /// `None` if the attribute is not set or carries a number.
+    pub fn get_str<S>(&self, key: S) -> Option<&str>
+    where
+        S: std::fmt::Display + AsRef<str>,
+    {
+        match self.attrs.get(&key.as_ref().to_string()) {
+            Some(AttrValue::Str(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Get the value associated with an attribute key, whatever its kind.
+    pub fn get_value<S>(&self, key: S) -> Option<&AttrValue>
     where
         S: std::fmt::Display + AsRef<str>,
     {
@@ -64,19 +144,79 @@ impl Attributes {
         self.attrs.is_empty()
     }
 
-    /// Remove attribute with the name `key`
+    /// Remove attribute with the name `key`. Returns the removed value if
+    /// it was numeric.
     pub fn remove<S>(&mut self, key: S) -> Option<u64>
     where
         S: ToString,
     {
-        self.attrs.remove(&key.to_string())
+        match self.attrs.remove(&key.to_string()) {
+            Some(AttrValue::Num(n)) => Some(n),
+            _ => None,
+        }
     }
 
+    /// Iterate over the numeric attributes.
     pub fn iter(&self) -> impl Iterator<Item = (&String, &u64)> {
+        self.attrs.iter().filter_map(|(k, v)| match v {
+            AttrValue::Num(n) => Some((k, n)),
+            AttrValue::Str(_) => None,
+        })
+    }
+
+    /// Iterate over all attributes, numeric and string-valued, in the order
+    /// they were added.
+    pub fn iter_values(&self) -> impl Iterator<Item = (&String, &AttrValue)> {
         self.attrs.iter()
     }
+
+    /// The pass that created this structure and the definitions it was
+    /// derived from. Returns `None` for structures from the input program.
+    pub fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_ref()
+    }
+
+    /// Record that this structure was derived from the definitions `names`.
+    /// Has no effect on structures that were not created by a pass.
+    pub fn derive_from<I>(&mut self, names: I)
+    where
+        I: IntoIterator<Item = Id>,
+    {
+        if let Some(prov) = &mut self.provenance {
+            prov.derived_from.extend(names);
+        }
+    }
+
+    /// Mark this structure as created by the pass that is running.
+    pub(super) fn mark_generated(&mut self) {
+        self.provenance = Some(Provenance::default());
+    }
+
+    /// Record `pass` as the creator of this structure if it was created by
+    /// a pass whose name is not known yet.
+    pub(super) fn record_pass(&mut self, pass: &str) {
+        if let Some(prov) = &mut self.provenance {
+            if prov.pass.is_empty() {
+                prov.pass = pass.to_string();
+            }
+        }
+    }
+}
+
+/// Two attribute maps are equal when they bind the same keys to the same
+/// values. Ordering and comments are ignored.
+impl PartialEq for Attributes {
+    fn eq(&self, other: &Self) -> bool {
+        self.attrs.len() == other.attrs.len()
+            && self
+                .attrs
+                .iter()
+                .all(|(k, v)| other.attrs.get(k) == Some(v))
+    }
 }
 
+impl Eq for Attributes {}
+
 impl<S> Index<&S> for Attributes
 where
     S: AsRef<str> + std::fmt::Display,
@@ -9,7 +9,8 @@ use std::rc::Rc;
 /// Uses internal references to the component to construct and validate
 /// constructs when needed.
 /// By default, assumes that the cells are being added by a pass and marks
-/// them with the `@generated` attribute.
+/// them with the `@generated` attribute. Cells and groups added by a pass
+/// also record their [provenance](ir::Provenance).
 ///
 /// In order to disable this behavior, call [[ir::Builder::not_generated()]].
 pub struct Builder<'a> {
@@ -66,6 +67,7 @@ impl<'a> Builder<'a> {
             attributes: ir::Attributes::default(),
             holes: smallvec![],
             assignments: vec![],
+            latency: None,
         }));
 
         // Add default holes to the group.
@@ -80,6 +82,10 @@ impl<'a> Builder<'a> {
             group.borrow_mut().holes.push(hole);
         }
 
+        if self.generated {
+            group.borrow_mut().attributes.mark_generated();
+        }
+
         // Add the group to the component.
         self.component.groups.add(Rc::clone(&group));
 
@@ -100,6 +106,10 @@ impl<'a> Builder<'a> {
             assignments: vec![],
         }));
 
+        if self.generated {
+            group.borrow_mut().attributes.mark_generated();
+        }
+
         // Add the group to the component.
         self.component.comb_groups.add(Rc::clone(&group));
 
@@ -156,6 +166,22 @@ impl<'a> Builder<'a> {
         primitive: Prim,
         param_values: &[u64],
     ) -> RRC<ir::Cell>
+    where
+        Pre: Into<ir::Id> + ToString + Clone,
+        Prim: AsRef<str>,
+    {
+        self.add_primitive_with_strings(prefix, primitive, param_values, &[])
+    }
+
+    /// Like [Builder::add_primitive] but also binds the string parameters of
+    /// the primitive to `string_values`.
+    pub fn add_primitive_with_strings<Pre, Prim>(
+        &mut self,
+        prefix: Pre,
+        primitive: Prim,
+        param_values: &[u64],
+        string_values: &[String],
+    ) -> RRC<ir::Cell>
     where
         Pre: Into<ir::Id> + ToString + Clone,
         Prim: AsRef<str>,
@@ -165,6 +191,9 @@ impl<'a> Builder<'a> {
         let (param_binding, ports) = prim
             .resolve(param_values)
             .expect("Failed to add primitive.");
+        let string_binding = prim
+            .resolve_strings(string_values)
+            .expect("Failed to add primitive.");
 
         let name = self.component.generate_name(prefix);
         let cell = Self::cell_from_signature(
@@ -172,12 +201,60 @@ impl<'a> Builder<'a> {
             ir::CellType::Primitive {
                 name: prim_id,
                 param_binding,
+                string_binding,
                 is_comb: prim.is_comb,
             },
             ports,
         );
         if self.generated {
-            cell.borrow_mut().add_attribute("generated", 1);
+            let mut cell = cell.borrow_mut();
+            cell.add_attribute("generated", 1);
+            cell.attributes.mark_generated();
+        }
+        self.component.cells.add(Rc::clone(&cell));
+        cell
+    }
+
+    /// Construct an instance of `component` and add it to the Component.
+    /// The name of the instance is guaranteed to start with `prefix`.
+    /// Returns a reference to the cell.
+    pub fn add_component<Pre>(
+        &mut self,
+        prefix: Pre,
+        component: &ir::Component,
+    ) -> RRC<ir::Cell>
+    where
+        Pre: Into<ir::Id> + ToString + Clone,
+    {
+        let name = self.component.generate_name(prefix);
+        // Ports of the signature use the directions seen inside the
+        // component.
+        let ports = component
+            .signature
+            .borrow()
+            .ports
+            .iter()
+            .map(|port| {
+                let port = port.borrow();
+                (
+                    port.name.clone(),
+                    port.width,
+                    port.direction.reverse(),
+                    port.attributes.clone(),
+                )
+            })
+            .collect();
+        let cell = Self::cell_from_signature(
+            name,
+            ir::CellType::Component {
+                name: component.name.clone(),
+            },
+            ports,
+        );
+        if self.generated {
+            let mut cell = cell.borrow_mut();
+            cell.add_attribute("generated", 1);
+            cell.attributes.mark_generated();
         }
         self.component.cells.add(Rc::clone(&cell));
         cell
@@ -318,6 +395,7 @@ impl<'a> Builder<'a> {
             // with_capacity(0) does not allocate space.
             // Same as HashMap::with_capacity
             attributes: ir::Attributes::default(),
+            contents: None,
         }));
         ports
             .into_iter()
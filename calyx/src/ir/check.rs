@@ -0,0 +1,269 @@
+//! Structural invariants of the IR that every pass must preserve.
+//!
+//! The frontend only constructs programs that satisfy these invariants, so a
+//! violation after a pass means that the pass produced a malformed program.
+use super::{
+    Assignment, Cell, CombGroup, Component, Context, Control, Guard, Port,
+    PortParent, RRC,
+};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+type CheckResult = Result<(), String>;
+
+/// Checks the invariants of a single component.
+struct Checker<'a> {
+    comp: &'a Component,
+}
+
+impl Checker<'_> {
+    fn has_cell(&self, cell: &RRC<Cell>) -> bool {
+        Rc::ptr_eq(cell, &self.comp.signature)
+            || self.comp.cells.iter().any(|c| Rc::ptr_eq(c, cell))
+    }
+
+    /// `port` must belong to a cell or group of the component.
+    fn port(&self, port: &RRC<Port>) -> CheckResult {
+        let port = port.borrow();
+        match &port.parent {
+            PortParent::Cell(cell) => {
+                let cell = cell.internal.upgrade().ok_or_else(|| {
+                    format!(
+                        "port `{}` belongs to a cell that no longer exists",
+                        port.name
+                    )
+                })?;
+                if !self.has_cell(&cell) {
+                    return Err(format!(
+                        "port `{}.{}` belongs to a cell that is not defined in the component",
+                        cell.borrow().name(),
+                        port.name
+                    ));
+                }
+            }
+            PortParent::Group(group) => {
+                let group = group.internal.upgrade().ok_or_else(|| {
+                    format!(
+                        "hole `{}` belongs to a group that no longer exists",
+                        port.name
+                    )
+                })?;
+                if !self.comp.groups.iter().any(|g| Rc::ptr_eq(g, &group)) {
+                    return Err(format!(
+                        "hole `{}[{}]` belongs to a group that is not defined in the component",
+                        group.borrow().name(),
+                        port.name
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn guard(&self, guard: &Guard) -> CheckResult {
+        match guard {
+            Guard::Or(l, r) | Guard::And(l, r) => {
+                self.guard(l)?;
+                self.guard(r)
+            }
+            Guard::Not(inner) => self.guard(inner),
+            Guard::Eq(l, r)
+            | Guard::Neq(l, r)
+            | Guard::Gt(l, r)
+            | Guard::Lt(l, r)
+            | Guard::Geq(l, r)
+            | Guard::Leq(l, r) => {
+                self.port(l)?;
+                self.port(r)?;
+                let (l, r) = (l.borrow(), r.borrow());
+                if l.width != r.width {
+                    let (l_cell, l_port) = l.canonical();
+                    let (r_cell, r_port) = r.canonical();
+                    return Err(format!(
+                        "guard compares `{}.{}` with width {} to `{}.{}` with width {}",
+                        l_cell, l_port, l.width, r_cell, r_port, r.width
+                    ));
+                }
+                Ok(())
+            }
+            Guard::Port(port) => {
+                self.port(port)?;
+                let port = port.borrow();
+                if port.width != 1 {
+                    let (cell, name) = port.canonical();
+                    return Err(format!(
+                        "guard uses `{}.{}` with width {} as a condition",
+                        cell, name, port.width
+                    ));
+                }
+                Ok(())
+            }
+            Guard::True => Ok(()),
+        }
+    }
+
+    fn assignment(&self, assign: &Assignment) -> CheckResult {
+        self.port(&assign.dst)?;
+        self.port(&assign.src)?;
+        self.guard(&assign.guard)?;
+        let (dst, src) = (assign.dst.borrow(), assign.src.borrow());
+        if dst.width != src.width {
+            let (dst_cell, dst_name) = dst.canonical();
+            let (src_cell, src_name) = src.canonical();
+            return Err(format!(
+                "assignment to `{}.{}` with width {} from `{}.{}` with width {}",
+                dst_cell, dst_name, dst.width, src_cell, src_name, src.width
+            ));
+        }
+        Ok(())
+    }
+
+    fn control(&self, con: &Control) -> CheckResult {
+        match con {
+            Control::Empty(_) => Ok(()),
+            Control::Debug(debug) => {
+                debug.ports.iter().try_for_each(|port| self.port(port))
+            }
+            Control::Enable(enable) => {
+                let group = &enable.group;
+                if !self.comp.groups.iter().any(|g| Rc::ptr_eq(g, group)) {
+                    return Err(format!(
+                        "control enables the group `{}`, which is not defined in the component",
+                        group.borrow().name()
+                    ));
+                }
+                Ok(())
+            }
+            Control::Seq(seq) => {
+                seq.stmts.iter().try_for_each(|stmt| self.control(stmt))
+            }
+            Control::Par(par) => {
+                par.stmts.iter().try_for_each(|stmt| self.control(stmt))
+            }
+            Control::If(con) => {
+                self.port(&con.port)?;
+                self.comb_group(&con.cond)?;
+                self.control(&con.tbranch)?;
+                self.control(&con.fbranch)
+            }
+            Control::While(con) => {
+                self.port(&con.port)?;
+                self.comb_group(&con.cond)?;
+                self.control(&con.body)
+            }
+            Control::Invoke(invoke) => {
+                let name = invoke.comp.borrow().name().clone();
+                if !self.has_cell(&invoke.comp) {
+                    return Err(format!(
+                        "control invokes the cell `{}`, which is not defined in the component",
+                        name
+                    ));
+                }
+                self.comb_group(&invoke.comb_group)?;
+                for (arg, port) in invoke.inputs.iter().chain(&invoke.outputs) {
+                    self.port(port)?;
+                    let cell_port = invoke.comp.borrow().find(arg);
+                    let cell_port = cell_port.ok_or_else(|| {
+                        format!(
+                            "invoke of `{}` connects the port `{}`, which the cell does not have",
+                            name, arg
+                        )
+                    })?;
+                    let (width, arg_width) =
+                        (port.borrow().width, cell_port.borrow().width);
+                    if width != arg_width {
+                        return Err(format!(
+                            "invoke of `{}` connects `{}` with width {} to a port with width {}",
+                            name, arg, arg_width, width
+                        ));
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn comb_group(&self, group: &Option<RRC<CombGroup>>) -> CheckResult {
+        match group {
+            Some(group)
+                if !self
+                    .comp
+                    .comb_groups
+                    .iter()
+                    .any(|g| Rc::ptr_eq(g, group)) =>
+            {
+                Err(format!(
+                    "control uses the combinational group `{}`, which is not defined in the component",
+                    group.borrow().name()
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn names(&self) -> CheckResult {
+        let mut cells = HashSet::new();
+        for cell in self.comp.cells.iter() {
+            let name = cell.borrow().name().clone();
+            if !cells.insert(name.clone()) {
+                return Err(format!("multiple cells are named `{}`", name));
+            }
+        }
+        let mut groups = HashSet::new();
+        let names = self
+            .comp
+            .groups
+            .iter()
+            .map(|group| group.borrow().name().clone())
+            .chain(
+                self.comp
+                    .comb_groups
+                    .iter()
+                    .map(|group| group.borrow().name().clone()),
+            );
+        for name in names {
+            if !groups.insert(name.clone()) {
+                return Err(format!("multiple groups are named `{}`", name));
+            }
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> CheckResult {
+        self.names()?;
+        let comp = self.comp;
+        for assign in &comp.continuous_assignments {
+            self.assignment(assign)?;
+        }
+        for group in comp.groups.iter() {
+            for assign in &group.borrow().assignments {
+                self.assignment(assign)?;
+            }
+        }
+        for group in comp.comb_groups.iter() {
+            for assign in &group.borrow().assignments {
+                self.assignment(assign)?;
+            }
+        }
+        for log in &comp.debug_logs {
+            self.port(&log.enable)?;
+            log.debug
+                .ports
+                .iter()
+                .try_for_each(|port| self.port(port))?;
+        }
+        self.control(&comp.control.borrow())
+    }
+}
+
+/// Checks that every name is defined once in each component, assignments and
+/// guards connect ports of the same width, every port belongs to a cell or
+/// group of its component, and the control program only uses groups and cells
+/// that are defined. Returns a description of the first violation.
+pub fn check_context(ctx: &Context) -> Result<(), String> {
+    ctx.components.iter().try_for_each(|comp| {
+        Checker { comp }
+            .check()
+            .map_err(|err| format!("In component `{}`: {}", comp.name, err))
+    })
+}
@@ -0,0 +1,168 @@
+//! Comments of the source program.
+//!
+//! The parser keeps the comments written on the lines before a component,
+//! cell, group, assignment, or control statement, as well as the comments at
+//! the end of a cell, assignment, enable, or invoke line. They are stored apart
+//! from the IR and identified by the construct they belong to: cells and groups
+//! by their name, assignments by their text, and control statements by a key
+//! stored in their attributes. The pass manager drops the comments of the
+//! constructs a pass removes once it runs; the others are kept even if the
+//! pass moved them.
+use super::{
+    Assignment, Attributes, Component, Control, GetAttributes, IRPrinter, Id,
+    Par, Seq,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Identity of a construct in a component.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CommentPos {
+    /// The definition of the component.
+    Component,
+    /// The cell with the given name.
+    Cell(Id),
+    /// The group or combinational group with the given name.
+    Group(Id),
+    /// An assignment of the given group or, if there is no group, a
+    /// continuous assignment, identified by its text.
+    Assign(Option<Id>, String),
+    /// The control statement with the given comment key. Passes that copy a
+    /// statement copy its key, and the comments are only printed with the
+    /// first copy.
+    Control(usize),
+}
+
+impl CommentPos {
+    /// The identity of the assignment `assign` of `group`.
+    pub fn assign(group: Option<&Id>, assign: &Assignment) -> Self {
+        let text = format!(
+            "{} = {} ? {}",
+            IRPrinter::get_port_access(&assign.dst.borrow()),
+            IRPrinter::guard_str(&assign.guard),
+            IRPrinter::get_port_access(&assign.src.borrow())
+        );
+        CommentPos::Assign(group.cloned(), text)
+    }
+}
+
+/// Call `f` with the comment key of every statement in `ctrl` that has one.
+fn for_each_key<F: FnMut(usize)>(ctrl: &Control, f: &mut F) {
+    if let Some(key) = ctrl.get_attributes().and_then(|a| a.comment_key) {
+        f(key)
+    }
+    match ctrl {
+        Control::Seq(Seq { stmts, .. }) | Control::Par(Par { stmts, .. }) => {
+            stmts.iter().for_each(|stmt| for_each_key(stmt, f))
+        }
+        Control::If(i) => {
+            for_each_key(&i.tbranch, f);
+            for_each_key(&i.fbranch, f);
+        }
+        Control::While(w) => for_each_key(&w.body, f),
+        Control::Invoke(_)
+        | Control::Enable(_)
+        | Control::Debug(_)
+        | Control::Empty(_) => {}
+    }
+}
+
+/// Comments attached to the constructs of a component.
+#[derive(Debug, Default)]
+pub struct ComponentComments {
+    comments: HashMap<CommentPos, Vec<String>>,
+    /// Number of comment keys given to control statements.
+    keys: usize,
+}
+
+impl ComponentComments {
+    /// Attach `comments` to the construct at `pos`. Comments include their
+    /// delimiters (`//` or `/* */`).
+    pub fn add(&mut self, pos: CommentPos, comments: Vec<String>) {
+        if !comments.is_empty() {
+            self.comments.entry(pos).or_default().extend(comments);
+        }
+    }
+
+    /// Attach `comments` to the control statement with the attributes
+    /// `attrs`.
+    pub(super) fn add_control(
+        &mut self,
+        attrs: &mut Attributes,
+        comments: Vec<String>,
+    ) {
+        if !comments.is_empty() {
+            attrs.comment_key = Some(self.keys);
+            self.add(CommentPos::Control(self.keys), comments);
+            self.keys += 1;
+        }
+    }
+
+    /// Comments attached to the construct at `pos` in the order they were
+    /// written.
+    pub fn get(&self, pos: &CommentPos) -> &[String] {
+        self.comments.get(pos).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Drop the comments of the constructs that are not in `comp`.
+    fn retain_defined(&mut self, comp: &Component) {
+        let mut defined = HashSet::new();
+        defined.insert(CommentPos::Component);
+        for cell in comp.cells.iter() {
+            defined.insert(CommentPos::Cell(cell.borrow().name().clone()));
+        }
+        for group in comp.groups.iter() {
+            let group = group.borrow();
+            defined.insert(CommentPos::Group(group.name().clone()));
+            for assign in &group.assignments {
+                defined.insert(CommentPos::assign(Some(group.name()), assign));
+            }
+        }
+        for group in comp.comb_groups.iter() {
+            let group = group.borrow();
+            defined.insert(CommentPos::Group(group.name().clone()));
+            for assign in &group.assignments {
+                defined.insert(CommentPos::assign(Some(group.name()), assign));
+            }
+        }
+        for assign in &comp.continuous_assignments {
+            defined.insert(CommentPos::assign(None, assign));
+        }
+        for_each_key(&comp.control.borrow(), &mut |key| {
+            defined.insert(CommentPos::Control(key));
+        });
+        self.comments.retain(|pos, _| defined.contains(pos));
+    }
+}
+
+/// Comments of a program keyed by component name.
+#[derive(Debug, Default)]
+pub struct Comments {
+    comps: HashMap<Id, ComponentComments>,
+}
+
+impl Comments {
+    /// Comments of the component `comp`, if it has any.
+    pub fn component(&self, comp: &Id) -> Option<&ComponentComments> {
+        self.comps.get(comp)
+    }
+
+    /// Comments of the component `comp`, which can be used to attach new
+    /// comments.
+    pub fn component_mut(&mut self, comp: &Id) -> &mut ComponentComments {
+        self.comps.entry(comp.clone()).or_default()
+    }
+
+    /// Drop the comments of the components that are not in `comps` and of
+    /// the constructs that are no longer part of their component.
+    pub fn retain_defined(&mut self, comps: &[Component]) {
+        self.comps.retain(|name, comments| {
+            match comps.iter().find(|comp| &comp.name == name) {
+                Some(comp) => {
+                    comments.retain_defined(comp);
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+}
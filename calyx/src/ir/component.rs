@@ -1,12 +1,14 @@
 use super::{
     Assignment, Attributes, Builder, Cell, CellType, CloneName, CombGroup,
-    Control, Direction, GetName, Group, Id, RRC,
+    Control, DebugLog, Direction, GetName, Group, Id, Port, PortDef,
+    PortParent, PortSpec, Width, RRC, WRC,
 };
+use crate::errors::{CalyxResult, Error};
 use crate::ir::RESERVED_NAMES;
 use crate::utils;
 use linked_hash_map::LinkedHashMap;
 use std::cell::RefCell;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::iter::Extend;
 use std::rc::Rc;
 
@@ -34,6 +36,10 @@ pub struct Component {
     pub control: RRC<Control>,
     /// Attributes for this component
     pub attributes: Attributes,
+    /// Latency of a `static<n>` component.
+    pub latency: Option<u64>,
+    /// `@debug` statements compiled into the structure of this component.
+    pub debug_logs: Vec<DebugLog>,
 
     ///// Internal structures
     /// Namegenerator that contains the names currently defined in this
@@ -83,6 +89,8 @@ impl Component {
             control: Rc::new(RefCell::new(Control::empty())),
             namegen: utils::NameGenerator::with_prev_defined_names(prev_names),
             attributes: Attributes::default(),
+            latency: None,
+            debug_logs: vec![],
         }
     }
 
@@ -117,12 +125,483 @@ impl Component {
     {
         self.namegen.gen_name(prefix)
     }
+
+    /// Returns the timing behavior of the ports of this component for its
+    /// users, declared by the `@write_together`, `@read_together`, and
+    /// `@stable` attributes of its signature.
+    pub fn port_spec(&self) -> PortSpec {
+        self.signature
+            .borrow()
+            .ports
+            .iter()
+            .map(|port| {
+                let port = port.borrow();
+                PortDef {
+                    name: port.name.clone(),
+                    width: Width::Const { value: port.width },
+                    direction: port.direction.reverse(),
+                    attributes: port.attributes.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Add a port to the signature of this component and return the port
+    /// used inside the component. Like in [Component::new], `direction` is
+    /// the direction of the port for the users of the component.
+    ///
+    /// Cells that instantiate this component are not updated. Use
+    /// [Context::add_port](super::Context::add_port) to also add the port to
+    /// them.
+    ///
+    /// # Panics
+    /// Panics if the signature already has a port named `name`.
+    pub fn add_port(
+        &mut self,
+        name: Id,
+        width: u64,
+        direction: Direction,
+        attributes: Attributes,
+    ) -> RRC<Port> {
+        let port = add_cell_port(
+            &self.signature,
+            name,
+            width,
+            direction.reverse(),
+            attributes,
+        );
+        self.namegen
+            .add_names(std::iter::once(port.borrow().name.to_string()));
+        port
+    }
+
+    /// Remove the port `name` from the signature of this component along
+    /// with the assignments that write to it. Returns an error if the port is
+    /// read by the component.
+    ///
+    /// Cells that instantiate this component are not updated. Use
+    /// [Context::remove_port](super::Context::remove_port) to also remove
+    /// the port from them.
+    pub fn remove_port<S>(&mut self, name: &S) -> CalyxResult<RRC<Port>>
+    where
+        S: AsRef<str> + ?Sized,
+    {
+        let signature = Rc::clone(&self.signature);
+        remove_cell_port(self, &signature, name.as_ref())
+    }
+
+    /// Rename the port `name` of the signature of this component to
+    /// `new_name`. Returns an error if the port does not exist or if the
+    /// signature already has a port named `new_name`.
+    ///
+    /// Cells that instantiate this component are not updated. Use
+    /// [Context::rename_port](super::Context::rename_port) to also rename
+    /// the port in them.
+    pub fn rename_port<S>(&mut self, name: &S, new_name: Id) -> CalyxResult<()>
+    where
+        S: AsRef<str> + ?Sized,
+    {
+        let name = name.as_ref();
+        let sig = self.signature.borrow();
+        let port = sig.find(name).ok_or_else(|| {
+            Error::Undefined(
+                name.into(),
+                format!("port on component `{}'", self.name),
+                None,
+            )
+        })?;
+        if sig.find(&new_name).is_some() {
+            return Err(Error::AlreadyBound(
+                new_name,
+                format!("port on component `{}'", self.name),
+            ));
+        }
+        port.borrow_mut().name = new_name.clone();
+        drop(sig);
+        self.namegen
+            .add_names(std::iter::once(new_name.to_string()));
+        Ok(())
+    }
+
+    /// Rename the cells of this component using `renames`, which maps the old
+    /// names of cells to their new names. Ports and control statements refer
+    /// to the cells directly and do not need to be updated.
+    pub fn rename_cells(&mut self, renames: &HashMap<Id, Id>) {
+        self.cells.rename(renames, |cell, name| cell.name = name);
+        self.namegen
+            .add_names(renames.values().map(|name| name.to_string()));
+    }
+
+    /// Rename the groups of this component using `renames`, which maps the
+    /// old names of groups to their new names.
+    pub fn rename_groups(&mut self, renames: &HashMap<Id, Id>) {
+        self.groups.rename(renames, |group, name| group.name = name);
+        self.namegen
+            .add_names(renames.values().map(|name| name.to_string()));
+    }
+
+    /// Rename the combinational groups of this component using `renames`,
+    /// which maps the old names of groups to their new names.
+    pub fn rename_comb_groups(&mut self, renames: &HashMap<Id, Id>) {
+        self.comb_groups
+            .rename(renames, |group, name| group.name = name);
+        self.namegen
+            .add_names(renames.values().map(|name| name.to_string()));
+    }
+
+    /// Returns true if `port` belongs to the signature or to a cell or group
+    /// that is part of this component.
+    fn is_defined(&self, port: &RRC<Port>) -> bool {
+        match &port.borrow().parent {
+            PortParent::Cell(cell) => {
+                cell.internal.upgrade().is_some_and(|cell| {
+                    Rc::ptr_eq(&cell, &self.signature)
+                        || self.cells.iter().any(|c| Rc::ptr_eq(c, &cell))
+                })
+            }
+            PortParent::Group(group) => {
+                group.internal.upgrade().is_some_and(|group| {
+                    self.groups.iter().any(|g| Rc::ptr_eq(g, &group))
+                })
+            }
+        }
+    }
+
+    /// Removes the assignments that use a port of a cell or group that is no
+    /// longer part of the component, along with the compiled `@debug`
+    /// statements that print such ports. Passes that remove cells or groups
+    /// should call this so that later passes do not see references to them.
+    /// Returns the number of removed assignments.
+    pub fn purge_deleted(&mut self) -> usize {
+        let mut removed = 0;
+        for group in self.groups.iter() {
+            let assigns = &mut group.borrow_mut().assignments;
+            let before = assigns.len();
+            assigns.retain(|assign| self.is_defined_assign(assign));
+            removed += before - assigns.len();
+        }
+        for group in self.comb_groups.iter() {
+            let assigns = &mut group.borrow_mut().assignments;
+            let before = assigns.len();
+            assigns.retain(|assign| self.is_defined_assign(assign));
+            removed += before - assigns.len();
+        }
+        let (live, dead): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut self.continuous_assignments)
+                .into_iter()
+                .partition(|assign| self.is_defined_assign(assign));
+        removed += dead.len();
+        self.continuous_assignments = live;
+        let logs = std::mem::take(&mut self.debug_logs);
+        self.debug_logs = logs
+            .into_iter()
+            .filter(|log| {
+                self.is_defined(&log.enable)
+                    && log.debug.ports.iter().all(|p| self.is_defined(p))
+            })
+            .collect();
+        removed
+    }
+
+    /// Returns true if every port used by `assign` is defined.
+    fn is_defined_assign(&self, assign: &Assignment) -> bool {
+        self.is_defined(&assign.src)
+            && self.is_defined(&assign.dst)
+            && assign.guard.all_ports().iter().all(|p| self.is_defined(p))
+    }
+
+    /// Returns a port used by the component whose cell or group has been
+    /// dropped, if any. Such ports cause a panic as soon as their parent is
+    /// accessed.
+    pub fn dangling_port(&self) -> Option<Id> {
+        let mut ports = vec![];
+        let mut add_assigns = |assigns: &[Assignment]| {
+            for assign in assigns {
+                ports.extend(assign.guard.all_ports());
+                ports.push(Rc::clone(&assign.src));
+                ports.push(Rc::clone(&assign.dst));
+            }
+        };
+        add_assigns(&self.continuous_assignments);
+        for group in self.groups.iter() {
+            add_assigns(&group.borrow().assignments);
+        }
+        for group in self.comb_groups.iter() {
+            add_assigns(&group.borrow().assignments);
+        }
+        for log in &self.debug_logs {
+            ports.push(Rc::clone(&log.enable));
+            ports.extend(log.debug.ports.iter().cloned());
+        }
+        control_ports(&self.control.borrow(), &mut ports);
+        ports.into_iter().find_map(|port| {
+            let port = port.borrow();
+            let is_dropped = match &port.parent {
+                PortParent::Cell(cell) => cell.internal.upgrade().is_none(),
+                PortParent::Group(group) => group.internal.upgrade().is_none(),
+            };
+            if is_dropped {
+                Some(port.name.clone())
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Adds the ports used by `con` to `ports`.
+fn control_ports(con: &Control, ports: &mut Vec<RRC<Port>>) {
+    match con {
+        Control::Seq(super::Seq { stmts, .. })
+        | Control::Par(super::Par { stmts, .. }) => {
+            stmts.iter().for_each(|stmt| control_ports(stmt, ports))
+        }
+        Control::If(super::If {
+            port,
+            tbranch,
+            fbranch,
+            ..
+        }) => {
+            ports.push(Rc::clone(port));
+            control_ports(tbranch, ports);
+            control_ports(fbranch, ports);
+        }
+        Control::While(super::While { port, body, .. }) => {
+            ports.push(Rc::clone(port));
+            control_ports(body, ports);
+        }
+        Control::Invoke(super::Invoke {
+            inputs, outputs, ..
+        }) => ports.extend(
+            inputs
+                .iter()
+                .chain(outputs)
+                .map(|(_, port)| Rc::clone(port)),
+        ),
+        Control::Debug(super::Debug { ports: printed, .. }) => {
+            ports.extend(printed.iter().cloned())
+        }
+        Control::Enable(_) | Control::Empty(_) => {}
+    }
+}
+
+/// Add a port to `cell`. Returns the new port.
+pub(super) fn add_cell_port(
+    cell: &RRC<Cell>,
+    name: Id,
+    width: u64,
+    direction: Direction,
+    attributes: Attributes,
+) -> RRC<Port> {
+    assert!(
+        cell.borrow().find(&name).is_none(),
+        "Cell `{}' already has a port named `{}'",
+        cell.borrow().name(),
+        name
+    );
+    let port = Rc::new(RefCell::new(Port {
+        name,
+        width,
+        direction,
+        parent: PortParent::Cell(WRC::from(cell)),
+        attributes,
+    }));
+    cell.borrow_mut().ports.push(Rc::clone(&port));
+    port
+}
+
+/// Remove the port `name` from `cell`, which is defined in `comp`, along with
+/// the assignments and `invoke` arguments that write to it. Returns an error
+/// if `comp` reads the port.
+pub(super) fn remove_cell_port(
+    comp: &mut Component,
+    cell: &RRC<Cell>,
+    name: &str,
+) -> CalyxResult<RRC<Port>> {
+    let port = cell.borrow().find(name).ok_or_else(|| {
+        Error::Undefined(
+            name.into(),
+            format!("port on `{}'", cell.borrow().name()),
+            None,
+        )
+    })?;
+    if port_is_read(comp, &port) {
+        return Err(read_error(&port, &comp.name));
+    }
+
+    let is_port = |p: &RRC<Port>| Rc::ptr_eq(p, &port);
+    for group in comp.groups.iter() {
+        group.borrow_mut().assignments.retain(|a| !is_port(&a.dst));
+    }
+    for group in comp.comb_groups.iter() {
+        group.borrow_mut().assignments.retain(|a| !is_port(&a.dst));
+    }
+    comp.continuous_assignments.retain(|a| !is_port(&a.dst));
+    remove_invoke_args(&mut comp.control.borrow_mut(), cell, name);
+    cell.borrow_mut().ports.retain(|p| !is_port(p));
+    Ok(port)
+}
+
+/// Rename the port `name` of `cell`, which is defined in `comp`, to
+/// `new_name`, along with the `invoke` arguments and interfaces that refer to
+/// it.
+pub(super) fn rename_cell_port(
+    comp: &Component,
+    cell: &RRC<Cell>,
+    name: &str,
+    new_name: &Id,
+) {
+    if let Some(port) = cell.borrow().find(name) {
+        port.borrow_mut().name = new_name.clone();
+    }
+    rename_invoke_args(&mut comp.control.borrow_mut(), cell, name, new_name);
+}
+
+/// Returns true if `port` is read by an assignment or the control program of
+/// `comp`.
+pub(super) fn port_is_read(comp: &Component, port: &RRC<Port>) -> bool {
+    let is_port = |p: &RRC<Port>| Rc::ptr_eq(p, port);
+    let reads = |assign: &Assignment| {
+        is_port(&assign.src) || assign.guard.all_ports().iter().any(is_port)
+    };
+    comp.groups
+        .iter()
+        .any(|g| g.borrow().assignments.iter().any(reads))
+        || comp
+            .comb_groups
+            .iter()
+            .any(|g| g.borrow().assignments.iter().any(reads))
+        || comp.continuous_assignments.iter().any(reads)
+        || control_reads(&comp.control.borrow(), &is_port)
+}
+
+/// Error for removing `port` while it is still read by the component `comp`.
+pub(super) fn read_error(port: &RRC<Port>, comp: &Id) -> Error {
+    let (cell, name) = port.borrow().canonical();
+    Error::MalformedStructure(format!(
+        "Cannot remove port `{}.{}' since it is still read by component `{}'",
+        cell, name, comp
+    ))
+}
+
+/// Returns true if a port for which `is_port` is true is read by `con`.
+fn control_reads<F>(con: &Control, is_port: &F) -> bool
+where
+    F: Fn(&RRC<Port>) -> bool,
+{
+    match con {
+        Control::Seq(super::Seq { stmts, .. })
+        | Control::Par(super::Par { stmts, .. }) => {
+            stmts.iter().any(|stmt| control_reads(stmt, is_port))
+        }
+        Control::If(super::If {
+            port,
+            tbranch,
+            fbranch,
+            ..
+        }) => {
+            is_port(port)
+                || control_reads(tbranch, is_port)
+                || control_reads(fbranch, is_port)
+        }
+        Control::While(super::While { port, body, .. }) => {
+            is_port(port) || control_reads(body, is_port)
+        }
+        Control::Invoke(super::Invoke {
+            comp,
+            inputs,
+            outputs,
+            ..
+        }) => {
+            inputs.iter().any(|(_, p)| is_port(p))
+                || outputs.iter().any(|(name, _)| {
+                    comp.borrow().find(name).is_some_and(|p| is_port(&p))
+                })
+        }
+        Control::Debug(super::Debug { ports, .. }) => ports.iter().any(is_port),
+        Control::Enable(_) | Control::Empty(_) => false,
+    }
+}
+
+/// Remove the arguments for the port `name` from the `invoke` statements of
+/// `cell` in `con`.
+fn remove_invoke_args(con: &mut Control, cell: &RRC<Cell>, name: &str) {
+    match con {
+        Control::Seq(super::Seq { stmts, .. })
+        | Control::Par(super::Par { stmts, .. }) => stmts
+            .iter_mut()
+            .for_each(|stmt| remove_invoke_args(stmt, cell, name)),
+        Control::If(super::If {
+            tbranch, fbranch, ..
+        }) => {
+            remove_invoke_args(tbranch, cell, name);
+            remove_invoke_args(fbranch, cell, name);
+        }
+        Control::While(super::While { body, .. }) => {
+            remove_invoke_args(body, cell, name)
+        }
+        Control::Invoke(super::Invoke { comp, inputs, .. })
+            if Rc::ptr_eq(comp, cell) =>
+        {
+            inputs.retain(|(port, _)| port != name)
+        }
+        Control::Invoke(_)
+        | Control::Enable(_)
+        | Control::Empty(_)
+        | Control::Debug(_) => {}
+    }
+}
+
+fn rename_invoke_args(
+    con: &mut Control,
+    cell: &RRC<Cell>,
+    name: &str,
+    new_name: &Id,
+) {
+    match con {
+        Control::Seq(super::Seq { stmts, .. })
+        | Control::Par(super::Par { stmts, .. }) => stmts
+            .iter_mut()
+            .for_each(|stmt| rename_invoke_args(stmt, cell, name, new_name)),
+        Control::If(super::If {
+            tbranch, fbranch, ..
+        }) => {
+            rename_invoke_args(tbranch, cell, name, new_name);
+            rename_invoke_args(fbranch, cell, name, new_name);
+        }
+        Control::While(super::While { body, .. }) => {
+            rename_invoke_args(body, cell, name, new_name)
+        }
+        Control::Invoke(super::Invoke {
+            comp,
+            interface,
+            inputs,
+            outputs,
+            ..
+        }) if Rc::ptr_eq(comp, cell) => {
+            for (port, _) in inputs.iter_mut().chain(outputs.iter_mut()) {
+                if *port == *name {
+                    *port = new_name.clone();
+                }
+            }
+            if let Some(interface) = interface {
+                if *interface == *name {
+                    *interface = new_name.clone();
+                }
+            }
+        }
+        Control::Invoke(_)
+        | Control::Enable(_)
+        | Control::Empty(_)
+        | Control::Debug(_) => {}
+    }
 }
 
 /// A wrapper struct exposing an ordered collection of named entities within an
 /// RRC with deterministic iteration and constant-time look-up on names
-/// directly. The struct assumes that the name of an entity cannot change. Doing
-/// so will introduce incorrect results for look-ups.
+/// directly. The struct assumes that the name of an entity does not change
+/// unless it is renamed through the `rename_*` methods of [Component]. Doing
+/// so otherwise will introduce incorrect results for look-ups.
 #[derive(Debug)]
 pub struct IdList<T: GetName>(LinkedHashMap<Id, RRC<T>>);
 
@@ -154,6 +633,11 @@ impl<T: GetName> IdList<T> {
         self.0.is_empty()
     }
 
+    /// Returns the number of elements in the list.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
     /// Keep only the elements in the collection which satisfy the given
     /// predicate
     pub fn retain<F>(&mut self, mut f: F)
@@ -192,6 +676,24 @@ impl<T: GetName> IdList<T> {
         drain.into_iter().map(|(_, cell)| cell)
     }
 
+    /// Rename the elements in `renames`, which maps old names to new names,
+    /// using `set_name`. The order of the elements does not change.
+    fn rename<F>(&mut self, renames: &HashMap<Id, Id>, set_name: F)
+    where
+        F: Fn(&mut T, Id),
+    {
+        self.0 = std::mem::take(&mut self.0)
+            .into_iter()
+            .map(|(name, item)| match renames.get(&name) {
+                Some(new_name) => {
+                    set_name(&mut item.borrow_mut(), new_name.clone());
+                    (new_name.clone(), item)
+                }
+                None => (name, item),
+            })
+            .collect();
+    }
+
     /// Returns the element indicated by the name, if present, otherwise None.
     pub fn find<S>(&self, name: &S) -> Option<RRC<T>>
     where
@@ -4,7 +4,15 @@
 use itertools::Itertools;
 use linked_hash_map::LinkedHashMap;
 
-use super::{Component, Id, Primitive};
+use super::component::{
+    add_cell_port, port_is_read, read_error, remove_cell_port, rename_cell_port,
+};
+use super::{
+    Attributes, CellType, Comments, Component, Direction, Id, Metadata,
+    PassStats, Port, PortSpec, Primitive, RRC,
+};
+use crate::errors::{CalyxResult, Error};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 /// A representation of all the primitive definitions found while parsing
@@ -13,6 +21,8 @@ use std::path::PathBuf;
 pub struct LibrarySignatures {
     /// Direct mapping from name to primitives
     primitive_definitions: Vec<(PathBuf, LinkedHashMap<Id, Primitive>)>,
+    /// Timing behavior of the ports of each primitive.
+    port_specs: HashMap<Id, PortSpec>,
 }
 
 /// Iterator over primitive signatures defined in [LibrarySignatures].
@@ -56,6 +66,14 @@ impl LibrarySignatures {
         })
     }
 
+    /// Return the timing behavior of the ports of the primitive `name`.
+    pub fn port_spec<S>(&self, name: S) -> Option<&PortSpec>
+    where
+        S: AsRef<str>,
+    {
+        self.port_specs.get(&Id::from(name.as_ref()))
+    }
+
     /// Return an iterator over the underlying
     pub fn signatures(&self) -> SigIter<'_> {
         SigIter {
@@ -72,6 +90,16 @@ impl LibrarySignatures {
         self.primitive_definitions
     }
 
+    /// Return the primitives defined by each extern file
+    pub fn extern_defs(
+        &self,
+    ) -> impl Iterator<Item = (&PathBuf, impl Iterator<Item = &Primitive>)>
+    {
+        self.primitive_definitions
+            .iter()
+            .map(|(p, prims)| (p, prims.values()))
+    }
+
     /// Return the paths for the extern defining files
     pub fn extern_paths(&self) -> Vec<&PathBuf> {
         self.primitive_definitions
@@ -87,6 +115,9 @@ impl From<Vec<(PathBuf, Vec<Primitive>)>> for LibrarySignatures {
         for (path, prims) in externs {
             let map: LinkedHashMap<_, _> =
                 prims.into_iter().map(|p| (p.name.clone(), p)).collect();
+            for prim in map.values() {
+                lib.port_specs.insert(prim.name.clone(), prim.port_spec());
+            }
             lib.primitive_definitions.push((path, map));
         }
         lib
@@ -102,6 +133,15 @@ pub struct BackendConf {
     pub enable_verification: bool,
     /// Generate initial assignments for input ports
     pub initialize_inputs: bool,
+    /// File mapping primitives to vendor modules.
+    pub bindings: Option<PathBuf>,
+    /// File to write the C++ Verilator driver for the design to.
+    pub verilator_harness: Option<PathBuf>,
+    /// Directory to write one Verilog file per component to.
+    pub per_module_dir: Option<PathBuf>,
+    /// Component to use as the entrypoint of the program instead of the one
+    /// marked with the "toplevel" attribute or named `main`.
+    pub toplevel: Option<Id>,
 }
 
 /// The IR Context
@@ -117,4 +157,157 @@ pub struct Context {
     /// Extra options provided to the command line.
     /// Interperted by individual passes
     pub extra_opts: Vec<String>,
+    /// Statistics recorded by the passes that ran on the program.
+    pub stats: PassStats,
+    /// Positions in the frontend program that generated this program.
+    pub metadata: Metadata,
+    /// Comments of the source program.
+    pub comments: Comments,
+}
+
+impl Context {
+    /// Add a port to the signature of the component `comp` and to every cell
+    /// that instantiates it. `direction` is the direction of the port for the
+    /// users of the component. Returns the port used inside `comp`.
+    ///
+    /// The new port is unconnected in the components that instantiate
+    /// `comp`, which means that `invoke` statements leave new inputs
+    /// undriven.
+    pub fn add_port(
+        &mut self,
+        comp: &Id,
+        name: Id,
+        width: u64,
+        direction: Direction,
+        attributes: Attributes,
+    ) -> CalyxResult<RRC<Port>> {
+        let port = self.find_component_mut(comp)?.add_port(
+            name.clone(),
+            width,
+            direction.clone(),
+            attributes,
+        );
+        for user in &self.components {
+            for cell in user.cells.iter().filter(|c| instantiates(c, comp)) {
+                let attributes = port.borrow().attributes.clone();
+                add_cell_port(
+                    cell,
+                    name.clone(),
+                    width,
+                    direction.clone(),
+                    attributes,
+                );
+            }
+        }
+        Ok(port)
+    }
+
+    /// Remove the port `name` from the signature of the component `comp` and
+    /// from every cell that instantiates it. Assignments and `invoke`
+    /// arguments that write to the port are removed. Returns an error if the
+    /// port is read by `comp` or by any component that instantiates it, in
+    /// which case nothing is changed.
+    pub fn remove_port<S>(&mut self, comp: &Id, name: &S) -> CalyxResult<()>
+    where
+        S: AsRef<str> + ?Sized,
+    {
+        // Check all the uses before anything is removed.
+        let name = name.as_ref();
+        let this = self.find_component_mut(comp)?;
+        let port = this.signature.borrow().find(name).ok_or_else(|| {
+            Error::Undefined(
+                name.into(),
+                format!("port on component `{}'", comp),
+                None,
+            )
+        })?;
+        if port_is_read(this, &port) {
+            return Err(read_error(&port, &this.name));
+        }
+        for user in &self.components {
+            for cell in user.cells.iter().filter(|c| instantiates(c, comp)) {
+                if let Some(port) = cell.borrow().find(name) {
+                    if port_is_read(user, &port) {
+                        return Err(read_error(&port, &user.name));
+                    }
+                }
+            }
+        }
+
+        self.find_component_mut(comp)?.remove_port(name)?;
+        for user in &mut self.components {
+            let cells = user
+                .cells
+                .iter()
+                .filter(|c| instantiates(c, comp))
+                .cloned()
+                .collect::<Vec<_>>();
+            for cell in cells {
+                remove_cell_port(user, &cell, name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rename the port `name` of the component `comp` to `new_name` in its
+    /// signature and in every cell that instantiates it, along with the
+    /// `invoke` statements that refer to the port.
+    pub fn rename_port<S>(
+        &mut self,
+        comp: &Id,
+        name: &S,
+        new_name: Id,
+    ) -> CalyxResult<()>
+    where
+        S: AsRef<str> + ?Sized,
+    {
+        let name = name.as_ref();
+        self.find_component_mut(comp)?
+            .rename_port(name, new_name.clone())?;
+        for user in &self.components {
+            for cell in user.cells.iter().filter(|c| instantiates(c, comp)) {
+                rename_cell_port(user, cell, name, &new_name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Rename the component `comp` to `new_name`, along with the cells that
+    /// instantiate it and the entrypoint of the program.
+    pub fn rename_component(
+        &mut self,
+        comp: &Id,
+        new_name: Id,
+    ) -> CalyxResult<()> {
+        self.find_component_mut(comp)?.name = new_name.clone();
+        for user in &self.components {
+            for cell in user.cells.iter() {
+                if let CellType::Component { name } =
+                    &mut cell.borrow_mut().prototype
+                {
+                    if *name == *comp {
+                        *name = new_name.clone();
+                    }
+                }
+            }
+        }
+        if self.entrypoint == comp {
+            self.entrypoint = new_name;
+        }
+        Ok(())
+    }
+
+    fn find_component_mut(&mut self, name: &Id) -> CalyxResult<&mut Component> {
+        self.components
+            .iter_mut()
+            .find(|comp| comp.name == name)
+            .ok_or_else(|| {
+                Error::Undefined(name.clone(), "component".to_string(), None)
+            })
+    }
+}
+
+/// Returns true if `cell` is an instance of the component `comp`.
+fn instantiates(cell: &&RRC<super::Cell>, comp: &Id) -> bool {
+    matches!(&cell.borrow().prototype, CellType::Component { name } if name == comp)
 }
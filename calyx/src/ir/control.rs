@@ -70,6 +70,9 @@ type PortMap = Vec<(Id, RRC<Port>)>;
 pub struct Invoke {
     /// Cell that is being invoked.
     pub comp: RRC<Cell>,
+    /// Name of the `@go` port of the interface used to invoke `comp`.
+    /// If `None`, the canonical `go`/`done` interface is used.
+    pub interface: Option<Id>,
     /// Mapping from name of input ports in `comp` to the port connected to it.
     pub inputs: PortMap,
     /// Mapping from name of output ports in `comp` to the port connected to it.
@@ -84,6 +87,37 @@ pub struct Invoke {
 #[derive(Debug)]
 pub struct Empty {}
 
+/// Data for the `@debug` control statement.
+#[derive(Debug)]
+pub struct Debug {
+    /// Format string in which every `{}` is replaced by the value of the
+    /// corresponding port.
+    pub format: String,
+    /// Ports whose values are printed.
+    pub ports: Vec<RRC<Port>>,
+}
+
+impl Debug {
+    /// Placeholder for the value of a port in the format string.
+    pub const PLACEHOLDER: &'static str = "{}";
+
+    /// The parts of the format string between the placeholders. There is
+    /// always one more part than there are placeholders.
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.format.split(Self::PLACEHOLDER)
+    }
+}
+
+/// A `@debug` statement that has been compiled into structure. The values of
+/// the ports are printed in every cycle in which `enable` is high.
+#[derive(Debug)]
+pub struct DebugLog {
+    /// Port that is high while the statement executes.
+    pub enable: RRC<Port>,
+    /// The compiled statement.
+    pub debug: Debug,
+}
+
 /// Control AST nodes.
 #[derive(Debug)]
 pub enum Control {
@@ -101,6 +135,8 @@ pub enum Control {
     Enable(Enable),
     /// Control statement that does nothing.
     Empty(Empty),
+    /// Print the values of ports when executed by a simulator.
+    Debug(Debug),
 }
 
 impl GetAttributes for Control {
@@ -112,7 +148,7 @@ impl GetAttributes for Control {
             | Self::While(While { attributes, .. })
             | Self::Invoke(Invoke { attributes, .. })
             | Self::Enable(Enable { attributes, .. }) => Some(attributes),
-            Self::Empty(..) => None,
+            Self::Empty(..) | Self::Debug(..) => None,
         }
     }
 
@@ -124,7 +160,7 @@ impl GetAttributes for Control {
             | Self::While(While { attributes, .. })
             | Self::Invoke(Invoke { attributes, .. })
             | Self::Enable(Enable { attributes, .. }) => Some(attributes),
-            Self::Empty(..) => None,
+            Self::Empty(..) | Self::Debug(..) => None,
         }
     }
 }
@@ -136,6 +172,11 @@ impl Control {
         Control::Empty(Empty {})
     }
 
+    /// Convience constructor for debug.
+    pub fn debug(format: String, ports: Vec<RRC<Port>>) -> Self {
+        Control::Debug(Debug { format, ports })
+    }
+
     /// Convience constructor for seq.
     pub fn seq(stmts: Vec<Control>) -> Self {
         Control::Seq(Seq {
@@ -164,6 +205,7 @@ impl Control {
     pub fn invoke(comp: RRC<Cell>, inputs: PortMap, outputs: PortMap) -> Self {
         Control::Invoke(Invoke {
             comp,
+            interface: None,
             inputs,
             outputs,
             attributes: Attributes::default(),
@@ -243,12 +285,14 @@ impl Control {
             }),
             Control::Invoke(Invoke {
                 comp,
+                interface,
                 inputs,
                 outputs,
                 attributes,
                 comb_group,
             }) => Control::Invoke(Invoke {
                 comp: Rc::clone(comp),
+                interface: interface.clone(),
                 inputs: inputs
                     .iter()
                     .map(|(name, port)| (name.clone(), Rc::clone(port)))
@@ -267,6 +311,9 @@ impl Control {
                 })
             }
             Control::Empty(_) => Control::empty(),
+            Control::Debug(Debug { format, ports }) => {
+                Control::debug(format.clone(), ports.clone())
+            }
         }
     }
 }
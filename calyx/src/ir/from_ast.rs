@@ -1,16 +1,17 @@
 use super::{
-    Assignment, Attributes, BackendConf, Builder, CellType, Component, Context,
-    Control, Direction, GetAttributes, Guard, Id, Invoke, LibrarySignatures,
-    Port, PortDef, Width, RRC,
+    Assignment, Attributes, BackendConf, Builder, Cell, CellType, CombGroup,
+    CommentPos, Comments, Component, ComponentComments, Context, Control,
+    Debug, Direction, GetAttributes, Guard, Id, Invoke, LibrarySignatures,
+    PassStats, Port, PortDef, Width, RRC,
 };
 use crate::{
     errors::{CalyxResult, Error},
     frontend::{self, ast},
-    utils::NameGenerator,
+    utils::{self, NameGenerator},
 };
 use linked_hash_map::LinkedHashMap;
 use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::rc::Rc;
 
 /// Context to store the signature information for all defined primitives and
@@ -52,13 +53,46 @@ fn check_signature(sig: &[PortDef]) -> CalyxResult<()> {
                 }
             }
             Direction::Inout => {
-                panic!("Components shouldn't have inout ports.")
+                if !inputs.contains(&pd.name) && !outputs.contains(&pd.name) {
+                    inputs.insert(&pd.name);
+                    outputs.insert(&pd.name);
+                } else {
+                    return Err(Error::AlreadyBound(
+                        pd.name.clone(),
+                        "component".to_string(),
+                    ));
+                }
             }
         }
     }
     Ok(())
 }
 
+/// Checks that `inout` ports are only used in the signature of the toplevel
+/// component and are marked with `@external`. `inout` ports are connected to
+/// bidirectional pins of the device and cannot be connected to other cells.
+fn check_inout_ports(
+    kind: &str,
+    name: &Id,
+    sig: &[PortDef],
+    is_toplevel: bool,
+) -> CalyxResult<()> {
+    let inout = sig.iter().find(|pd| pd.direction == Direction::Inout);
+    match inout {
+        Some(pd) if !is_toplevel => Err(Error::MalformedStructure(format!(
+            "Port `{}` of {} `{}` is an `inout` port. Only the toplevel component can define `inout` ports.",
+            pd.name, kind, name
+        ))),
+        Some(pd) if !pd.attributes.has("external") => {
+            Err(Error::MalformedStructure(format!(
+                "`inout` port `{}` of component `{}` must be marked with `@external`.",
+                pd.name, name
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
 /// Definition of special interface ports.
 const INTERFACE_PORTS: [(&str, u64, Direction); 4] = [
     ("go", 1, Direction::Input),
@@ -67,15 +101,116 @@ const INTERFACE_PORTS: [(&str, u64, Direction); 4] = [
     ("done", 1, Direction::Output),
 ];
 
+/// Check that `pd` can be used as the interface port `name`.
+fn check_interface_port(
+    comp: &Id,
+    pd: &PortDef,
+    (name, width, direction): &(&str, u64, Direction),
+) -> CalyxResult<()> {
+    let width_ok =
+        matches!(pd.width, Width::Const { value } if value == *width);
+    if width_ok && pd.direction == *direction {
+        return Ok(());
+    }
+    let dir = match direction {
+        Direction::Input => "input",
+        Direction::Output => "output",
+        Direction::Inout => "inout",
+    };
+    let msg = if pd.attributes.has(name) {
+        format!(
+            "Port `{}` of component `{}` is marked with `@{}` but is not a {}-bit {} port.",
+            pd.name, comp, name, width, dir
+        )
+    } else {
+        format!(
+            "Port `{}` of component `{}` is inferred to be the `@{}` port but is not a {}-bit {} port. Mark a {}-bit {} port with `@{}` or rename `{}`.",
+            pd.name, comp, name, width, dir, width, dir, name, pd.name
+        )
+    };
+    Err(Error::MalformedStructure(msg))
+}
+
+/// Check that the additional `go`/`done` interfaces of a component are
+/// well-formed.
+///
+/// A component may have several interfaces. The ports of an interface are
+/// marked with `@go(n)` and `@done(n)` for the same value `n`. The canonical
+/// interface, used to run the component's control program, has the value `1`.
+fn check_entry_interfaces(comp: &Id, sig: &[PortDef]) -> CalyxResult<()> {
+    let values = |name: &str| -> CalyxResult<HashSet<u64>> {
+        let iface = INTERFACE_PORTS.iter().find(|(n, ..)| *n == name).unwrap();
+        let mut values = HashSet::new();
+        for pd in sig {
+            if let Some(val) = pd.attributes.get(name) {
+                check_interface_port(comp, pd, iface)?;
+                if !values.insert(*val) {
+                    return Err(Error::MalformedStructure(format!(
+                        "Component `{}` has multiple ports marked with `@{}({})`",
+                        comp, name, val
+                    )));
+                }
+            }
+        }
+        Ok(values)
+    };
+    let go = values("go")?;
+    let done = values("done")?;
+    if let Some(val) = go.symmetric_difference(&done).next() {
+        let (has, missing) = if go.contains(val) {
+            ("go", "done")
+        } else {
+            ("done", "go")
+        };
+        return Err(Error::MalformedStructure(format!(
+            "Component `{}` has a port marked with `@{}({})` but no port marked with `@{}({})`",
+            comp, has, val, missing, val
+        )));
+    }
+    Ok(())
+}
+
 /// Extend the signature with magical ports.
-fn extend_signature(sig: &mut Vec<PortDef>) {
+///
+/// An interface port is the port marked with the interface attribute. If no
+/// port is marked, a port with the same name as the interface port is marked
+/// with the attribute. Otherwise, a new port is added to the signature.
+/// For the `go` and `done` ports, only ports of the canonical interface,
+/// marked with `@go(1)` and `@done(1)`, are considered.
+fn extend_signature(comp: &Id, sig: &mut Vec<PortDef>) -> CalyxResult<()> {
     let port_names: HashSet<_> =
         sig.iter().map(|pd| pd.name.to_string()).collect();
     let mut namegen = NameGenerator::with_prev_defined_names(port_names);
-    for (name, width, direction) in INTERFACE_PORTS.iter() {
-        // Check if there is already another interface port defined for the
-        // component
-        if !sig.iter().any(|pd| pd.attributes.has(name)) {
+    for iface @ (name, width, direction) in INTERFACE_PORTS.iter() {
+        let is_entry = *name == "go" || *name == "done";
+        let marked = sig
+            .iter()
+            .filter(|pd| match pd.attributes.get(name) {
+                Some(val) => !is_entry || *val == 1,
+                None => false,
+            })
+            .collect::<Vec<_>>();
+        if marked.len() > 1 {
+            return Err(Error::MalformedStructure(format!(
+                "Component `{}` has multiple ports marked with `@{}`: {}",
+                comp,
+                name,
+                marked
+                    .iter()
+                    .map(|pd| format!("`{}`", pd.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+        if let Some(pd) = marked.first() {
+            check_interface_port(comp, pd, iface)?;
+        } else if let Some(pd) = sig
+            .iter_mut()
+            .find(|pd| pd.name == name && !pd.attributes.has(name))
+        {
+            check_interface_port(comp, pd, iface)?;
+            pd.attributes.insert(name, 1);
+        } else {
             let mut attributes = Attributes::default();
             attributes.insert(name, 1);
             sig.push(PortDef {
@@ -86,6 +221,73 @@ fn extend_signature(sig: &mut Vec<PortDef>) {
             });
         }
     }
+    check_entry_interfaces(comp, sig)
+}
+
+/// Checks that the widths in the signature of a primitive or component and
+/// the arguments of the cells of a component only use the parameters
+/// `params`. Components are checked even if they are never instantiated.
+fn check_width_params<'a, I>(params: &[Id], widths: I) -> CalyxResult<()>
+where
+    I: IntoIterator<Item = (&'a Id, &'a Width)>,
+{
+    for (name, width) in widths {
+        if let Some(param) =
+            width.params().into_iter().find(|p| !params.contains(p))
+        {
+            return Err(Error::ParamBindingMissing(
+                name.clone(),
+                param.clone(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Find the entrypoint of the program. It is the component named `toplevel`
+/// if given, otherwise the component with the "toplevel" attribute or the
+/// component named `main`.
+fn find_entrypoint(
+    comps: &[ast::ComponentDef],
+    toplevel: Option<&Id>,
+) -> CalyxResult<Id> {
+    if let Some(name) = toplevel {
+        return comps
+            .iter()
+            .find(|c| c.name == *name)
+            .map(|c| c.name.clone())
+            .ok_or_else(|| {
+                Error::Undefined(
+                    name.clone(),
+                    "toplevel component".to_string(),
+                    None,
+                )
+            });
+    }
+    let candidates: Vec<_> = comps
+        .iter()
+        .filter(|c| c.attributes.has("toplevel"))
+        .map(|c| c.name.clone())
+        .collect();
+    match candidates.as_slice() {
+        [] => comps
+            .iter()
+            .find(|c| c.name == "main")
+            .map(|c| c.name.clone())
+            .ok_or_else(|| Error::Misc("No entry point for the program. Program needs to be either mark a component with the \"toplevel\" attribute or define a component named `main`".to_string())),
+        [name] => Ok(name.clone()),
+        names => {
+            let names = names
+                .iter()
+                .map(|name| format!("`{}`", name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Err(Error::Misc(format!(
+                "Components {} are all marked with the \"toplevel\" attribute. Select the entry point using --toplevel",
+                names
+            )))
+        }
+    }
 }
 
 /// Construct an IR representation using a parsed AST and command line options.
@@ -93,6 +295,25 @@ pub fn ast_to_ir(
     mut workspace: frontend::Workspace,
     bc: BackendConf,
 ) -> CalyxResult<Context> {
+    for prim in workspace.externs.iter().flat_map(|(_, prims)| prims) {
+        let sig = prim.signature.iter().map(|pd| (&pd.name, &pd.width));
+        check_width_params(&prim.params, sig)?;
+    }
+    for comp in &workspace.components {
+        let sig = comp.signature.iter().map(|pd| (&pd.name, &pd.width));
+        let args = comp.cells.iter().flat_map(|cell| {
+            cell.prototype
+                .params
+                .iter()
+                .map(move |arg| (&cell.name, arg))
+        });
+        check_width_params(&comp.params, sig.chain(args))?;
+    }
+    let entrypoint =
+        find_entrypoint(&workspace.components, bc.toplevel.as_ref())?;
+    workspace.components =
+        instantiate_components(workspace.components, &entrypoint)?;
+
     let mut all_names: HashSet<&Id> = HashSet::with_capacity(
         workspace.components.len() + workspace.externs.len(),
     );
@@ -113,6 +334,10 @@ pub fn ast_to_ir(
         all_names.insert(bound);
     }
 
+    for prim in workspace.externs.iter().flat_map(|(_, prims)| prims) {
+        check_inout_ports("primitive", &prim.name, &prim.signature, false)?;
+    }
+
     // Build the signature context
     let mut sig_ctx = SigCtx {
         lib: workspace.externs.into(),
@@ -122,8 +347,9 @@ pub fn ast_to_ir(
     // Add declarations to context
     for comp in &mut workspace.declarations {
         check_signature(&comp.signature)?;
+        check_inout_ports("component", &comp.name, &comp.signature, false)?;
         // extend the signature
-        extend_signature(&mut comp.signature);
+        extend_signature(&comp.name, &mut comp.signature)?;
         sig_ctx
             .comp_sigs
             .insert(comp.name.clone(), comp.signature.clone());
@@ -132,32 +358,37 @@ pub fn ast_to_ir(
     // Add components to context
     for comp in &mut workspace.components {
         check_signature(&comp.signature)?;
+        check_inout_ports(
+            "component",
+            &comp.name,
+            &comp.signature,
+            comp.name == entrypoint,
+        )?;
         // extend the signature
-        extend_signature(&mut comp.signature);
+        extend_signature(&comp.name, &mut comp.signature)?;
         sig_ctx
             .comp_sigs
             .insert(comp.name.clone(), comp.signature.clone());
     }
+    let mut comments = Comments::default();
     let comps: Vec<Component> = workspace
         .components
         .into_iter()
-        .map(|comp| build_component(comp, &sig_ctx))
+        .map(|comp| {
+            let comp_comments = comments.component_mut(&comp.name);
+            build_component(comp, &sig_ctx, comp_comments)
+        })
         .collect::<Result<_, _>>()?;
 
-    // Find the entrypoint for the program.
-    let entrypoint = comps
-        .iter()
-        .find(|c| c.attributes.get("toplevel").is_some())
-        .or_else(|| comps.iter().find(|c| c.name == "main"))
-        .map(|c| c.name.clone())
-        .ok_or_else(|| Error::Misc("No entry point for the program. Program needs to be either mark a component with the \"toplevel\" attribute or define a component named `main`".to_string()))?;
-
     Ok(Context {
         components: comps,
         lib: sig_ctx.lib,
         bc,
         entrypoint,
         extra_opts: vec![],
+        stats: PassStats::default(),
+        metadata: workspace.metadata,
+        comments,
     })
 }
 
@@ -179,13 +410,28 @@ fn validate_component(
 
         let proto_name = &cell.prototype.name;
 
-        if sig_ctx.lib.find_primitive(&proto_name).is_none()
-            && !sig_ctx.comp_sigs.contains_key(proto_name)
-        {
+        if let Some(prim) = sig_ctx.lib.find_primitive(proto_name) {
+            prim.resolve_strings(&cell.prototype.string_params)?;
+        } else if !sig_ctx.comp_sigs.contains_key(proto_name) {
+            let mut names = sig_ctx
+                .lib
+                .signatures()
+                .map(|prim| prim.name.to_string())
+                .chain(sig_ctx.comp_sigs.keys().map(Id::to_string))
+                .collect::<Vec<_>>();
+            names.sort();
             return Err(Error::Undefined(
                 proto_name.clone(),
                 "primitive or component".to_string(),
+                utils::closest_name(proto_name.as_ref(), names),
             ));
+        } else if !cell.prototype.string_params.is_empty() {
+            return Err(Error::MalformedStructure(format!(
+                "Component `{}` does not define any string parameters but cell `{}` provides {}",
+                proto_name,
+                cell.name,
+                cell.prototype.string_params.len()
+            )));
         }
     }
 
@@ -207,6 +453,7 @@ fn validate_component(
 fn build_component(
     comp: ast::ComponentDef,
     sig_ctx: &SigCtx,
+    comments: &mut ComponentComments,
 ) -> CalyxResult<Component> {
     // Validate the component before building it.
     validate_component(&comp, sig_ctx)?;
@@ -230,36 +477,252 @@ fn build_component(
     // required information.
     comp.cells
         .into_iter()
-        .for_each(|cell| add_cell(cell, sig_ctx, &mut builder));
+        .try_for_each(|cell| add_cell(cell, sig_ctx, comments, &mut builder))?;
 
     comp.groups
         .into_iter()
-        .try_for_each(|g| add_group(g, &mut builder))?;
+        .try_for_each(|g| add_group(g, comments, &mut builder))?;
 
-    let continuous_assignments = comp
-        .continuous_assignments
-        .into_iter()
-        .map(|w| build_assignment(w, &mut builder))
-        .collect::<CalyxResult<Vec<_>>>()?;
+    let continuous_assignments = build_assignments(
+        comp.continuous_assignments,
+        None,
+        comments,
+        &mut builder,
+    )?;
     builder.component.continuous_assignments = continuous_assignments;
 
     // Build the Control ast using ast::Control.
-    let control =
-        Rc::new(RefCell::new(build_control(comp.control, &mut builder)?));
-    builder.component.control = control;
+    let control = build_control(comp.control, comments, &mut builder)?;
+    builder.component.control = Rc::new(RefCell::new(control));
 
     ir_component.attributes = comp.attributes;
+    ir_component.latency = comp.latency;
+    comments.add(CommentPos::Component, comp.comments);
 
     Ok(ir_component)
 }
 
+///////////////// Component Instantiation /////////////////////////
+
+/// Tracks the instances of parameterized components used by the program.
+struct Instances<'a> {
+    /// Parameterized components defined by the program.
+    generics: &'a HashMap<Id, ast::ComponentDef>,
+    /// Names of all the components defined by the program.
+    comp_names: HashSet<Id>,
+    /// Instances that have already been requested.
+    created: HashSet<Id>,
+    /// Instances that still need to be built along with their arguments.
+    pending: VecDeque<(Id, Vec<u64>)>,
+}
+
+impl Instances<'_> {
+    /// Name of the instance of `name` with the parameter values `args`.
+    fn instance_name(name: &Id, args: &[u64]) -> Id {
+        let args: Vec<_> = args.iter().map(|arg| arg.to_string()).collect();
+        format!("{}_{}", name, args.join("_")).into()
+    }
+
+    /// Resolve the arguments of the cells in `comp` using `binding` and
+    /// replace uses of parameterized components with their instances.
+    fn resolve_cells(
+        &mut self,
+        comp: &mut ast::ComponentDef,
+        binding: &LinkedHashMap<Id, u64>,
+    ) -> CalyxResult<()> {
+        for cell in &mut comp.cells {
+            let args = cell
+                .prototype
+                .params
+                .iter()
+                .map(|arg| arg.resolve(&cell.name, binding))
+                .collect::<CalyxResult<Vec<_>>>()?;
+
+            let proto = &cell.prototype.name;
+            if let Some(generic) = self.generics.get(proto) {
+                if generic.params.len() != args.len() {
+                    return Err(Error::MalformedStructure(format!(
+                        "Component `{}` requires {} parameters but cell `{}` provides {}",
+                        proto,
+                        generic.params.len(),
+                        cell.name,
+                        args.len()
+                    )));
+                }
+                let name = Self::instance_name(proto, &args);
+                if self.created.insert(name.clone()) {
+                    self.pending.push_back((proto.clone(), args));
+                }
+                cell.prototype.name = name;
+                cell.prototype.params = vec![];
+            } else if self.comp_names.contains(proto) && !args.is_empty() {
+                return Err(Error::MalformedStructure(format!(
+                    "Component `{}` does not define any parameters but cell `{}` provides {}",
+                    proto,
+                    cell.name,
+                    args.len()
+                )));
+            } else {
+                cell.prototype.params = args
+                    .into_iter()
+                    .map(|value| Width::Const { value })
+                    .collect();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Monomorphize the parameterized components used by the program.
+///
+/// A cell `c = comp(32)` of a component `comp[WIDTH]` is replaced by a cell of
+/// the component `comp_32`, which is a copy of `comp` with `WIDTH` replaced by
+/// `32`. Parameterized components that are never instantiated are removed and
+/// every other component is replaced by its instances. The `entrypoint` cannot
+/// be parameterized.
+fn instantiate_components(
+    comps: Vec<ast::ComponentDef>,
+    entrypoint: &Id,
+) -> CalyxResult<Vec<ast::ComponentDef>> {
+    let generics: HashMap<Id, ast::ComponentDef> = comps
+        .iter()
+        .filter(|comp| !comp.params.is_empty())
+        .map(|comp| (comp.name.clone(), comp.clone()))
+        .collect();
+    if let Some(comp) = generics.get(entrypoint) {
+        return Err(Error::MalformedStructure(format!(
+            "Toplevel component `{}` cannot define parameters",
+            comp.name
+        )));
+    }
+
+    let mut instances = Instances {
+        generics: &generics,
+        comp_names: comps.iter().map(|comp| comp.name.clone()).collect(),
+        created: HashSet::new(),
+        pending: VecDeque::new(),
+    };
+    let no_params = LinkedHashMap::with_capacity(0);
+    let mut comps = comps;
+    for comp in comps.iter_mut().filter(|comp| comp.params.is_empty()) {
+        instances.resolve_cells(comp, &no_params)?;
+    }
+
+    let mut built: HashMap<Id, Vec<ast::ComponentDef>> = HashMap::new();
+    while let Some((name, args)) = instances.pending.pop_front() {
+        let generic = &generics[&name];
+        let binding: LinkedHashMap<Id, u64> =
+            generic.params.iter().cloned().zip(args.clone()).collect();
+        let mut comp = generic.clone();
+        comp.name = Instances::instance_name(&name, &args);
+        comp.params = vec![];
+        comp.signature = generic
+            .signature
+            .iter()
+            .map(|pd| {
+                pd.resolve(&binding)
+                    .map(|(name, value, attributes)| PortDef {
+                        name,
+                        width: Width::Const { value },
+                        direction: pd.direction.clone(),
+                        attributes,
+                    })
+            })
+            .collect::<CalyxResult<_>>()?;
+        instances.resolve_cells(&mut comp, &binding)?;
+        built.entry(name).or_default().push(comp);
+    }
+
+    // Instances are defined in place of their parameterized component.
+    Ok(comps
+        .into_iter()
+        .flat_map(|comp| {
+            if comp.params.is_empty() {
+                vec![comp]
+            } else {
+                built.remove(&comp.name).unwrap_or_default()
+            }
+        })
+        .collect())
+}
+
 ///////////////// Cell Construction /////////////////////////
 
-fn add_cell(cell: ast::Cell, sig_ctx: &SigCtx, builder: &mut Builder) {
+/// The values of every element of the memory `cell` given by `contents`.
+/// Elements without a value are zero.
+fn memory_contents(
+    cell: &Cell,
+    contents: ast::Contents,
+) -> CalyxResult<Vec<u128>> {
+    let dims = cell.memory_dimensions().ok_or_else(|| {
+        Error::MalformedStructure(format!(
+            "Cell `{}` is given initial contents but only memories (`std_mem_d1` to `std_mem_d4`) can be initialized",
+            cell.name()
+        ))
+    })?;
+    if cell.attributes.has("external") {
+        return Err(Error::MalformedStructure(format!(
+            "Memory `{}` is @external, so its contents come from the data file and cannot be given in the program",
+            cell.name()
+        )));
+    }
+    let width = cell.get_parameter("WIDTH").unwrap();
+    let size = dims.iter().product::<u64>();
+    match contents {
+        ast::Contents::File(path) => {
+            utils::read_memory_file(&path, width, size)
+        }
+        ast::Contents::Values(mut vals) => {
+            if vals.len() as u64 > size {
+                return Err(Error::MalformedStructure(format!(
+                    "Memory `{}` is given {} values but has {} elements",
+                    cell.name(),
+                    vals.len(),
+                    size
+                )));
+            }
+            if let Some(val) =
+                vals.iter().find(|val| width < 128 && **val >> width != 0)
+            {
+                return Err(Error::MalformedStructure(format!(
+                    "Memory `{}` is given the value {}, which does not fit in {} bits",
+                    cell.name(),
+                    val,
+                    width
+                )));
+            }
+            vals.resize(size as usize, 0);
+            Ok(vals)
+        }
+    }
+}
+
+fn add_cell(
+    cell: ast::Cell,
+    sig_ctx: &SigCtx,
+    comments: &mut ComponentComments,
+    builder: &mut Builder,
+) -> CalyxResult<()> {
     let proto_name = &cell.prototype.name;
 
     let res = if sig_ctx.lib.find_primitive(proto_name).is_some() {
-        builder.add_primitive(cell.name, proto_name, &cell.prototype.params)
+        // Component instantiation resolves all the arguments.
+        let params: Vec<_> = cell
+            .prototype
+            .params
+            .iter()
+            .map(|arg| {
+                arg.as_const().unwrap_or_else(|| {
+                    unreachable!("Unresolved parameter `{}`", arg)
+                })
+            })
+            .collect();
+        builder.add_primitive_with_strings(
+            cell.name,
+            proto_name,
+            &params,
+            &cell.prototype.string_params,
+        )
     } else {
         // Validator ensures that if the protoype is not a primitive, it
         // is a component.
@@ -287,34 +750,43 @@ fn add_cell(cell: ast::Cell, sig_ctx: &SigCtx, builder: &mut Builder) {
     };
 
     // Add attributes to the built cell
-    res.borrow_mut().attributes = cell.attributes;
+    let mut res = res.borrow_mut();
+    res.attributes = cell.attributes;
+    comments.add(CommentPos::Cell(res.name().clone()), cell.comments);
+    if let Some(contents) = cell.contents {
+        res.contents = Some(memory_contents(&res, contents)?);
+    }
+    Ok(())
 }
 
 ///////////////// Group Construction /////////////////////////
 
 /// Build an [ir::Group] from an [ast::Group] and attach it to the [ir::Compoennt]
 /// associated with the [ir::Builder]
-fn add_group(group: ast::Group, builder: &mut Builder) -> CalyxResult<()> {
+fn add_group(
+    group: ast::Group,
+    comments: &mut ComponentComments,
+    builder: &mut Builder,
+) -> CalyxResult<()> {
     if group.is_comb {
         let ir_group = builder.add_comb_group(group.name);
-        let assigns = group
-            .wires
-            .into_iter()
-            .map(|assign| build_assignment(assign, builder))
-            .collect::<CalyxResult<Vec<_>>>()?;
+        let name = ir_group.borrow().name().clone();
+        let assigns =
+            build_assignments(group.wires, Some(&name), comments, builder)?;
 
         ir_group.borrow_mut().attributes = group.attributes;
         ir_group.borrow_mut().assignments = assigns;
+        comments.add(CommentPos::Group(name), group.comments);
     } else {
         let ir_group = builder.add_group(group.name);
-        let assigns = group
-            .wires
-            .into_iter()
-            .map(|assign| build_assignment(assign, builder))
-            .collect::<CalyxResult<Vec<_>>>()?;
+        let name = ir_group.borrow().name().clone();
+        let assigns =
+            build_assignments(group.wires, Some(&name), comments, builder)?;
 
         ir_group.borrow_mut().attributes = group.attributes;
         ir_group.borrow_mut().assignments = assigns;
+        ir_group.borrow_mut().latency = group.latency;
+        comments.add(CommentPos::Group(name), group.comments);
     };
 
     Ok(())
@@ -322,28 +794,82 @@ fn add_group(group: ast::Group, builder: &mut Builder) -> CalyxResult<()> {
 
 ///////////////// Assignment Construction /////////////////////////
 
+/// Error for an undefined name of kind `typ`. Suggests the name in `in_scope`
+/// that is most similar to `name`.
+fn undefined<I>(name: Id, typ: &str, in_scope: I) -> Error
+where
+    I: IntoIterator<Item = Id>,
+{
+    let suggestion = utils::closest_name(name.as_ref(), in_scope);
+    Error::Undefined(name, typ.to_string(), suggestion)
+}
+
+/// Error for the undefined cell `cell` used in the port `cell.port`. If a
+/// similar cell has the port, the suggestion includes the port.
+fn undefined_cell(cell: Id, port: Option<&Id>, comp: &Component) -> Error {
+    let names = comp.cells.iter().map(|c| c.borrow().name().clone());
+    let suggestion =
+        utils::closest_name(cell.as_ref(), names).map(|similar| match port {
+            Some(port)
+                if comp
+                    .find_cell(&similar)
+                    .map(|c| c.borrow().find(port).is_some())
+                    .unwrap_or_default() =>
+            {
+                format!("{}.{}", similar, port)
+            }
+            _ => similar,
+        });
+    Error::Undefined(cell, "cell".to_string(), suggestion)
+}
+
+/// Error for the undefined port `port` on `cell`. The suggestion contains the
+/// name of the cell.
+fn undefined_port(
+    port: Id,
+    typ: &str,
+    cell: &Id,
+    ports: &[RRC<Port>],
+) -> Error {
+    let names = ports.iter().map(|p| p.borrow().name.clone());
+    let suggestion = utils::closest_name(port.as_ref(), names)
+        .map(|similar| format!("{}.{}", cell, similar));
+    Error::Undefined(port, typ.to_string(), suggestion)
+}
+
 /// Get the pointer to the Port represented by `port`.
 fn get_port_ref(port: ast::Port, comp: &Component) -> CalyxResult<RRC<Port>> {
     match port {
-        ast::Port::Comp { component, port } => comp
-            .find_cell(&component)
-            .ok_or_else(|| {
-                Error::Undefined(component.clone(), "cell".to_string())
-            })?
-            .borrow()
-            .find(&port)
-            .ok_or_else(|| Error::Undefined(port, "port".to_string())),
+        ast::Port::Comp { component, port } => {
+            let cell = comp
+                .find_cell(&component)
+                .ok_or_else(|| undefined_cell(component, Some(&port), comp))?;
+            let cell = cell.borrow();
+            cell.find(&port).ok_or_else(|| {
+                undefined_port(port, "port", cell.name(), &cell.ports)
+            })
+        }
         ast::Port::This { port } => {
-            comp.signature.borrow().find(&port).ok_or_else(|| {
-                Error::Undefined(port, "component port".to_string())
+            let sig = comp.signature.borrow();
+            sig.find(&port).ok_or_else(|| {
+                let names = sig.ports.iter().map(|p| p.borrow().name.clone());
+                undefined(port, "component port", names)
+            })
+        }
+        ast::Port::Hole { group, name: port } => {
+            let group_ref = comp.find_group(&group).ok_or_else(|| {
+                let names =
+                    comp.groups.iter().map(|g| g.borrow().name().clone());
+                undefined(group, "group", names)
+            })?;
+            let group = group_ref.borrow();
+            group.find(&port).ok_or_else(|| {
+                let names = group.holes.iter().map(|h| h.borrow().name.clone());
+                let suggestion = utils::closest_name(port.as_ref(), names)
+                    .map(|similar| format!("{}[{}]", group.name(), similar));
+                Error::Undefined(port, "hole".to_string(), suggestion)
             })
         }
-        ast::Port::Hole { group, name: port } => comp
-            .find_group(&group)
-            .ok_or_else(|| Error::Undefined(group, "group".to_string()))?
-            .borrow()
-            .find(&port)
-            .ok_or_else(|| Error::Undefined(port, "hole".to_string())),
     }
 }
 
@@ -380,6 +906,25 @@ fn build_assignment(
     Ok(builder.build_assignment(dst_port, src_port, guard))
 }
 
+/// Build the assignments of the group `group`, or the continuous assignments
+/// if there is no group, and record their comments.
+fn build_assignments(
+    wires: Vec<ast::Wire>,
+    group: Option<&Id>,
+    comments: &mut ComponentComments,
+    builder: &mut Builder,
+) -> CalyxResult<Vec<Assignment>> {
+    wires
+        .into_iter()
+        .map(|mut wire| {
+            let wire_comments = std::mem::take(&mut wire.comments);
+            let assign = build_assignment(wire, builder)?;
+            comments.add(CommentPos::assign(group, &assign), wire_comments);
+            Ok(assign)
+        })
+        .collect()
+}
+
 /// Transform an ast::GuardExpr to an ir::Guard.
 fn build_guard(guard: ast::GuardExpr, bd: &mut Builder) -> CalyxResult<Guard> {
     use ast::GuardExpr as GE;
@@ -404,36 +949,62 @@ fn build_guard(guard: ast::GuardExpr, bd: &mut Builder) -> CalyxResult<Guard> {
 
 ///////////////// Control Construction /////////////////////////
 
-/// Transform ast::Control to ir::Control.
+/// Get the combinational group named `name`.
+fn find_comb_group(name: Id, comp: &Component) -> CalyxResult<RRC<CombGroup>> {
+    comp.find_comb_group(&name).ok_or_else(|| {
+        let names = comp.comb_groups.iter().map(|g| g.borrow().name().clone());
+        undefined(name, "combinational group", names)
+    })
+}
+
+/// Transform ast::Control to ir::Control and record the comments of its
+/// statements in `comments`.
 fn build_control(
     control: ast::Control,
+    comments: &mut ComponentComments,
     builder: &mut Builder,
 ) -> CalyxResult<Control> {
     Ok(match control {
         ast::Control::Enable {
             comp: component,
             attributes,
+            comments: stmt_comments,
         } => {
+            let comp = &builder.component;
             let mut en = Control::enable(Rc::clone(
-                &builder.component.find_group(&component).ok_or_else(|| {
-                    Error::Undefined(component.clone(), "group".to_string())
+                &comp.find_group(&component).ok_or_else(|| {
+                    let names =
+                        comp.groups.iter().map(|g| g.borrow().name().clone());
+                    undefined(component, "group", names)
                 })?,
             ));
             *(en.get_mut_attributes().unwrap()) = attributes;
+            comments
+                .add_control(en.get_mut_attributes().unwrap(), stmt_comments);
             en
         }
         ast::Control::Invoke {
             comp: component,
+            interface,
             inputs,
             outputs,
             attributes,
             comb_group,
+            comments: stmt_comments,
         } => {
             let cell = Rc::clone(
                 &builder.component.find_cell(&component).ok_or_else(|| {
-                    Error::Undefined(component.clone(), "cell".to_string())
+                    undefined_cell(component.clone(), None, builder.component)
                 })?,
             );
+            if let Some(go) = &interface {
+                if cell.borrow().find_interface(Some(go)).is_none() {
+                    return Err(Error::MalformedControl(format!(
+                        "`{}` is not the `@go` port of an interface of `{}`",
+                        go, component
+                    )));
+                }
+            }
             let inputs = inputs
                 .into_iter()
                 .map(|(id, port)| atom_to_port(port, builder).map(|p| (id, p)))
@@ -444,43 +1015,49 @@ fn build_control(
                 .collect::<Result<_, _>>()?;
             let mut inv = Invoke {
                 comp: cell,
+                interface,
                 inputs,
                 outputs,
                 attributes,
                 comb_group: None,
             };
             if let Some(cg) = comb_group {
-                let cg_ref = builder
-                    .component
-                    .find_comb_group(&cg)
-                    .ok_or_else(|| {
-                        Error::Undefined(
-                            cg.clone(),
-                            "combinational group".to_string(),
-                        )
-                    })?;
+                let cg_ref = find_comb_group(cg, builder.component)?;
                 inv.comb_group = Some(cg_ref);
             }
+            comments.add_control(&mut inv.attributes, stmt_comments);
             Control::Invoke(inv)
         }
-        ast::Control::Seq { stmts, attributes } => {
+        ast::Control::Seq {
+            stmts,
+            attributes,
+            comments: stmt_comments,
+        } => {
             let mut s = Control::seq(
                 stmts
                     .into_iter()
-                    .map(|c| build_control(c, builder))
+                    .map(|c| build_control(c, comments, builder))
                     .collect::<CalyxResult<Vec<_>>>()?,
             );
             *(s.get_mut_attributes().unwrap()) = attributes;
+            comments
+                .add_control(s.get_mut_attributes().unwrap(), stmt_comments);
             s
         }
-        ast::Control::Par { stmts, attributes } => {
+        ast::Control::Par {
+            stmts,
+            attributes,
+            comments: stmt_comments,
+        } => {
             let mut p = Control::par(
                 stmts
                     .into_iter()
-                    .map(|c| build_control(c, builder))
+                    .map(|c| build_control(c, comments, builder))
                     .collect::<CalyxResult<Vec<_>>>()?,
             );
             *(p.get_mut_attributes().unwrap()) = attributes;
+            comments
+                .add_control(p.get_mut_attributes().unwrap(), stmt_comments);
             p
         }
         ast::Control::If {
@@ -489,24 +1066,20 @@ fn build_control(
             tbranch,
             fbranch,
             attributes,
+            comments: stmt_comments,
         } => {
             let group = maybe_cond
-                .map(|cond| {
-                    builder.component.find_comb_group(&cond).ok_or_else(|| {
-                        Error::Undefined(
-                            cond.clone(),
-                            "combinational group".to_string(),
-                        )
-                    })
-                })
+                .map(|cond| find_comb_group(cond, builder.component))
                 .transpose()?;
             let mut con = Control::if_(
                 get_port_ref(port, builder.component)?,
                 group,
-                Box::new(build_control(*tbranch, builder)?),
-                Box::new(build_control(*fbranch, builder)?),
+                Box::new(build_control(*tbranch, comments, builder)?),
+                Box::new(build_control(*fbranch, comments, builder)?),
             );
             *(con.get_mut_attributes().unwrap()) = attributes;
+            comments
+                .add_control(con.get_mut_attributes().unwrap(), stmt_comments);
             con
         }
         ast::Control::While {
@@ -514,25 +1087,39 @@ fn build_control(
             cond: maybe_cond,
             body,
             attributes,
+            comments: stmt_comments,
         } => {
             let group = maybe_cond
-                .map(|cond| {
-                    builder.component.find_comb_group(&cond).ok_or_else(|| {
-                        Error::Undefined(
-                            cond.clone(),
-                            "combinational group".to_string(),
-                        )
-                    })
-                })
+                .map(|cond| find_comb_group(cond, builder.component))
                 .transpose()?;
             let mut con = Control::while_(
                 get_port_ref(port, builder.component)?,
                 group,
-                Box::new(build_control(*body, builder)?),
+                Box::new(build_control(*body, comments, builder)?),
             );
             *(con.get_mut_attributes().unwrap()) = attributes;
+            comments
+                .add_control(con.get_mut_attributes().unwrap(), stmt_comments);
             con
         }
         ast::Control::Empty { .. } => Control::empty(),
+        ast::Control::Debug { format, ports } => {
+            let placeholders = format.matches(Debug::PLACEHOLDER).count();
+            if placeholders != ports.len() {
+                return Err(Error::MalformedControl(format!(
+                    "`@debug` with format string \"{}\" expects {} ports but got {}",
+                    format,
+                    placeholders,
+                    ports.len()
+                )));
+            }
+            Control::debug(
+                format,
+                ports
+                    .into_iter()
+                    .map(|port| get_port_ref(port, builder.component))
+                    .collect::<CalyxResult<_>>()?,
+            )
+        }
     })
 }
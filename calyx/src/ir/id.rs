@@ -22,6 +22,11 @@ impl Id {
         }
     }
 
+    /// The span of the input program that defines this name, if any.
+    pub fn span(&self) -> Option<&Span> {
+        self.span.as_ref()
+    }
+
     pub fn fmt_err(&self, err_msg: &str) -> String {
         match &self.span {
             Some(span) => span.format(err_msg),
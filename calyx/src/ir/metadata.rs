@@ -0,0 +1,123 @@
+//! Positions in the source of the frontend program that generated a Calyx
+//! program.
+//!
+//! Frontends, such as the Dahlia compiler, can add a trailing `metadata`
+//! block to the programs they generate:
+//! ```text
+//! metadata {
+//!   0: "gemm.fuse" line 12;
+//! }
+//! ```
+//! Components, groups, and cells refer to an entry of the block using the
+//! `pos` attribute, e.g., `group incr<"pos"=0>` or `@pos(0) r = std_reg(32)`.
+//! Errors about these definitions then point to the original position.
+use super::{Attributes, Component, Id};
+use crate::errors::{Error, Span};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Attribute that refers to an entry of the metadata block.
+pub const POS_ATTR: &str = "pos";
+
+/// A line in a file of the frontend program.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourcePos {
+    pub file: String,
+    pub line: u64,
+}
+
+impl std::fmt::Display for SourcePos {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "from frontend file {} line {}", self.file, self.line)
+    }
+}
+
+/// Entries of the `metadata` block of a program.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    positions: BTreeMap<u64, SourcePos>,
+}
+
+impl Metadata {
+    /// Add the entry `idx`. Returns the position it previously had, if any.
+    pub fn insert(&mut self, idx: u64, pos: SourcePos) -> Option<SourcePos> {
+        self.positions.insert(idx, pos)
+    }
+
+    /// The position of the entry `idx`.
+    pub fn get(&self, idx: u64) -> Option<&SourcePos> {
+        self.positions.get(&idx)
+    }
+
+    /// Iterate over the entries in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = (&u64, &SourcePos)> {
+        self.positions.iter()
+    }
+
+    /// Returns true if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Build the map from the definitions in `comps` that have a `pos`
+    /// attribute to their position in the frontend program.
+    pub fn source_map<'a, I>(&self, comps: I) -> SourceMap
+    where
+        I: IntoIterator<Item = &'a Component>,
+    {
+        let mut map = SourceMap::default();
+        if self.is_empty() {
+            return map;
+        }
+        let mut add = |name: &Id, attrs: &Attributes| {
+            let pos = attrs.get(POS_ATTR).and_then(|idx| self.get(*idx));
+            if let (Some(span), Some(pos)) = (name.span(), pos) {
+                map.positions.push((span.clone(), pos.clone()));
+            }
+        };
+        for comp in comps {
+            add(&comp.name, &comp.attributes);
+            for group in comp.groups.iter() {
+                let group = group.borrow();
+                add(group.name(), &group.attributes);
+            }
+            for group in comp.comb_groups.iter() {
+                let group = group.borrow();
+                add(group.name(), &group.attributes);
+            }
+            for cell in comp.cells.iter() {
+                let cell = cell.borrow();
+                add(cell.name(), &cell.attributes);
+            }
+        }
+        map
+    }
+}
+
+/// Map from the definitions of a program to their position in the frontend
+/// program. Built using [Metadata::source_map].
+#[derive(Default)]
+pub struct SourceMap {
+    /// Spans of the names of the definitions and their positions.
+    positions: Vec<(Span, SourcePos)>,
+}
+
+impl SourceMap {
+    /// Position in the frontend program of the definition named by `id`.
+    pub fn lookup(&self, id: &Id) -> Option<&SourcePos> {
+        let span = id.span()?;
+        self.positions
+            .iter()
+            .find(|(def, _)| def == span)
+            .map(|(_, pos)| pos)
+    }
+
+    /// Attach the position in the frontend program of the definition that
+    /// `err` is about, if it is known.
+    pub fn annotate(&self, err: Error) -> Error {
+        match err.id().and_then(|id| self.lookup(id)).cloned() {
+            Some(pos) => Error::FromFrontend(Box::new(err), pos),
+            None => err,
+        }
+    }
+}
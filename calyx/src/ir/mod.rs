@@ -9,39 +9,56 @@
 // Modules defining internal structures.
 mod attribute;
 mod builder;
+mod comments;
 mod common;
 mod component;
 mod context;
 mod control;
 mod guard;
 mod id;
+mod metadata;
 mod primitives;
 mod printer;
+mod provenance;
 mod reserved_names;
+mod stats;
 mod structure;
 
 // Re-export types at the module level.
-pub use attribute::{Attributes, GetAttributes};
+pub use attribute::{AttrValue, Attributes, GetAttributes};
 pub use builder::Builder;
+pub use comments::{CommentPos, Comments, ComponentComments};
 pub use common::{RRC, WRC};
 pub use component::{Component, IdList};
 pub use context::{BackendConf, Context, LibrarySignatures};
-pub use control::{Control, Empty, Enable, If, Invoke, Par, Seq, While};
+pub use control::{
+    Control, Debug, DebugLog, Empty, Enable, If, Invoke, Par, Seq, While,
+};
 pub use guard::Guard;
 pub use id::Id;
-pub use primitives::{PortDef, Primitive, Width};
+pub use metadata::{Metadata, SourceMap, SourcePos, POS_ATTR};
+pub use primitives::{PortDef, PortSpec, Primitive, Width, WidthOp};
 pub use printer::IRPrinter;
+pub(crate) use provenance::record_pass;
+pub use provenance::{Provenance, ProvenanceMap};
 pub use reserved_names::RESERVED_NAMES;
+pub use stats::{ComponentStats, PassStats};
 pub use structure::{
     Assignment, Binding, Cell, CellIterator, CellType, CloneName, CombGroup,
-    Direction, GetName, Group, Port, PortIterator, PortParent,
+    Direction, GetName, Group, Port, PortIterator, PortParent, StringBinding,
 };
 
 /// Visitor to traverse a control program.
 pub mod traversal;
 
+/// Structural invariants of the IR that passes must preserve.
+pub mod check;
+
 /// Module to transform AST programs into IR.
 pub mod from_ast;
 
+/// Module to transform IR programs back into the AST.
+pub mod to_ast;
+
 /// Convinience macros for constructing IR nodes.
 mod macros;
@@ -1,7 +1,9 @@
 use super::{Attributes, Direction, Id};
 use crate::errors::{CalyxResult, Error};
 use linked_hash_map::LinkedHashMap;
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
+use std::iter::FromIterator;
 
 /// Representation of a external primitive definition.
 ///
@@ -14,14 +16,23 @@ use smallvec::SmallVec;
 /// );
 /// ```
 ///
+/// Numeric parameters can have default values, such as `[WIDTH, INIT=0]`,
+/// which are used when an instantiation omits them.
+///
 /// The signature of a port is represented using [PortDef] which also specify
 /// the direction of the port.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Primitive {
     /// Name of this primitive.
     pub name: Id,
     /// Paramters for this primitive.
     pub params: Vec<Id>,
+    /// Default values of the parameters. Parameters with defaults come after
+    /// all the parameters without one and can be omitted by instantiations.
+    #[serde(default)]
+    pub param_defaults: Vec<Option<u64>>,
+    /// String parameters for this primitive.
+    pub string_params: Vec<Id>,
     /// The input/output signature for this primitive.
     pub signature: Vec<PortDef>,
     /// Key-value attributes for this primitive.
@@ -41,7 +52,7 @@ impl Primitive {
         SmallVec<[(Id, u64); 5]>,
         Vec<(Id, u64, Direction, Attributes)>,
     )> {
-        if self.params.len() != parameters.len() {
+        if parameters.len() > self.params.len() {
             return Err(Error::InvalidParamBinding(
                 self.name.clone(),
                 self.params.len(),
@@ -51,9 +62,23 @@ impl Primitive {
         let bindings = self
             .params
             .iter()
-            .cloned()
-            .zip(parameters.iter().cloned())
-            .collect::<LinkedHashMap<Id, u64>>();
+            .enumerate()
+            .map(|(idx, param)| {
+                let val = parameters.get(idx).copied().or_else(|| {
+                    self.param_defaults.get(idx).copied().flatten()
+                });
+                match val {
+                    Some(val) => Ok((param.clone(), val)),
+                    None => Err(Error::MalformedStructure(format!(
+                        "Primitive `{}` requires a value for parameter `{}`, which has no default. Provided {} of its {} parameters.",
+                        self.name,
+                        param,
+                        parameters.len(),
+                        self.params.len()
+                    ))),
+                }
+            })
+            .collect::<CalyxResult<LinkedHashMap<Id, u64>>>()?;
 
         let ports = self
             .signature
@@ -68,6 +93,33 @@ impl Primitive {
         Ok((bindings.into_iter().collect(), ports))
     }
 
+    /// Returns the bindings for all the string parameters.
+    pub fn resolve_strings(
+        &self,
+        values: &[String],
+    ) -> CalyxResult<Vec<(Id, String)>> {
+        if self.string_params.len() != values.len() {
+            return Err(Error::MalformedStructure(format!(
+                "Primitive `{}` requires {} string parameters but provided with {}",
+                self.name,
+                self.string_params.len(),
+                values.len()
+            )));
+        }
+        Ok(self
+            .string_params
+            .iter()
+            .cloned()
+            .zip(values.iter().cloned())
+            .collect())
+    }
+
+    /// Returns the timing behavior of the ports declared by the
+    /// `@write_together`, `@read_together`, and `@stable` attributes.
+    pub fn port_spec(&self) -> PortSpec {
+        self.signature.iter().cloned().collect()
+    }
+
     /// Return all ports that have the attribute `attr`.
     pub fn find_all_with_attr<S>(&self, attr: S) -> Vec<&PortDef>
     where
@@ -80,13 +132,99 @@ impl Primitive {
     }
 }
 
+/// Timing behavior of the ports of a [Primitive].
+///
+/// # Example
+/// ```calyx
+/// primitive std_mem_d1[WIDTH, SIZE, IDX_SIZE](
+///   @read_together(1) @write_together(2) addr0: IDX_SIZE,
+///   @write_together(2) write_data: WIDTH,
+///   @write_together(2) @go write_en: 1,
+///   ...
+/// ) -> (
+///   @read_together(1) read_data: WIDTH,
+///   ...
+/// );
+/// ```
+/// requires `addr0` to be driven when `read_data` is read and `addr0`,
+/// `write_data`, and `write_en` to be driven together.
+#[derive(Clone, Debug, Default)]
+pub struct PortSpec {
+    /// Sets of inputs that must be driven together when any of them is
+    /// driven, given by the `@write_together(n)` attribute.
+    pub write_together: Vec<Vec<Id>>,
+    /// Outputs along with the inputs that must be driven when they are
+    /// read, given by the `@read_together(n)` attribute.
+    pub read_together: Vec<(Id, Vec<Id>)>,
+    /// Outputs that hold their value until the primitive is written to
+    /// again, given by the `@stable` attribute.
+    pub stable: Vec<Id>,
+}
+
+impl FromIterator<PortDef> for PortSpec {
+    fn from_iter<I: IntoIterator<Item = PortDef>>(ports: I) -> Self {
+        let mut write_together: LinkedHashMap<u64, Vec<Id>> =
+            LinkedHashMap::new();
+        let mut read_together: LinkedHashMap<u64, (Vec<Id>, Vec<Id>)> =
+            LinkedHashMap::new();
+        let mut stable = vec![];
+        for pd in ports {
+            if let Some(idx) = pd.attributes.get("write_together") {
+                write_together
+                    .entry(*idx)
+                    .or_default()
+                    .push(pd.name.clone());
+            }
+            if let Some(idx) = pd.attributes.get("read_together") {
+                let (outputs, inputs) = read_together.entry(*idx).or_default();
+                match pd.direction {
+                    Direction::Output => outputs.push(pd.name.clone()),
+                    _ => inputs.push(pd.name.clone()),
+                }
+            }
+            if pd.attributes.has("stable") {
+                stable.push(pd.name);
+            }
+        }
+        PortSpec {
+            write_together: write_together
+                .into_iter()
+                .map(|(_, ports)| ports)
+                .collect(),
+            read_together: read_together
+                .into_iter()
+                .flat_map(|(_, (outputs, inputs))| {
+                    outputs.into_iter().map(move |out| (out, inputs.clone()))
+                })
+                .collect(),
+            stable,
+        }
+    }
+}
+
+impl PortSpec {
+    /// Returns true if reading the primitive requires driving an address,
+    /// which is the case for memories.
+    pub fn is_addressed(&self) -> bool {
+        self.read_together
+            .iter()
+            .any(|(_, inputs)| !inputs.is_empty())
+    }
+
+    /// Returns the inputs that must be driven to read any output.
+    pub fn read_inputs(&self) -> impl Iterator<Item = &Id> {
+        self.read_together.iter().flat_map(|(_, inputs)| inputs)
+    }
+}
+
 /// Definition of a port.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PortDef {
     /// The name of the port.
     pub name: Id,
-    /// The width of the port. Can be either a number ([`Width::Const`]) or
-    /// a parameter ([`Width::Param`]).
+    /// The width of the port. Can be either a number ([`Width::Const`]), a
+    /// parameter ([`Width::Param`]), or an expression over both
+    /// ([`Width::Expr`]).
     pub width: Width,
     /// The direction of the port. Only allowed to be [`Direction::Input`]
     /// or [`Direction::Output`].
@@ -107,12 +245,98 @@ impl From<(Id, u64, Direction)> for PortDef {
 }
 
 /// Represents an abstract width of a primitive signature.
-#[derive(Clone, Debug)]
+///
+/// Widths that depend on the parameters of a primitive or component stay
+/// symbolic until the parameters are bound, which happens when a cell
+/// instantiates the primitive or when a parameterized component is
+/// elaborated.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Width {
     /// The width is a constant.
     Const { value: u64 },
     /// The width is a parameter.
     Param { value: Id },
+    /// The width is an arithmetic expression over constants and parameters,
+    /// such as `WIDTH + 1`.
+    Expr {
+        op: WidthOp,
+        left: Box<Width>,
+        right: Box<Width>,
+    },
+}
+
+/// Operators of a [`Width::Expr`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WidthOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+impl std::fmt::Display for WidthOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WidthOp::Add => write!(f, "+"),
+            WidthOp::Sub => write!(f, "-"),
+            WidthOp::Mul => write!(f, "*"),
+        }
+    }
+}
+
+impl Width {
+    /// The value of the width if it does not depend on any parameter.
+    pub fn as_const(&self) -> Option<u64> {
+        match self {
+            Width::Const { value } => Some(*value),
+            Width::Param { .. } | Width::Expr { .. } => None,
+        }
+    }
+
+    /// The parameters that the width depends on.
+    pub fn params(&self) -> Vec<&Id> {
+        match self {
+            Width::Const { .. } => vec![],
+            Width::Param { value } => vec![value],
+            Width::Expr { left, right, .. } => {
+                let mut params = left.params();
+                params.extend(right.params());
+                params
+            }
+        }
+    }
+
+    /// The value of the width given the values of the parameters in
+    /// `binding`. `name` is the definition the width belongs to and is only
+    /// used in errors.
+    pub fn resolve(
+        &self,
+        name: &Id,
+        binding: &LinkedHashMap<Id, u64>,
+    ) -> CalyxResult<u64> {
+        match self {
+            Width::Const { value } => Ok(*value),
+            Width::Param { value } => {
+                binding.get(value).copied().ok_or_else(|| {
+                    Error::ParamBindingMissing(name.clone(), value.clone())
+                })
+            }
+            Width::Expr { op, left, right } => {
+                let l = left.resolve(name, binding)?;
+                let r = right.resolve(name, binding)?;
+                let value = match op {
+                    WidthOp::Add => l.checked_add(r),
+                    WidthOp::Sub => l.checked_sub(r),
+                    WidthOp::Mul => l.checked_mul(r),
+                };
+                value.ok_or_else(|| {
+                    Error::MalformedStructure(format!(
+                        "Width `{}` of `{}` evaluates to `{} {} {}`, which is not a valid width",
+                        self, name, l, op, r
+                    ))
+                })
+            }
+        }
+    }
 }
 
 impl std::fmt::Display for Width {
@@ -120,6 +344,30 @@ impl std::fmt::Display for Width {
         match self {
             Width::Const { value } => write!(f, "{}", value),
             Width::Param { value } => write!(f, "{}", value),
+            Width::Expr { op, left, right } => {
+                // Operands are parenthesized when they bind less tightly
+                // than the operator, or as tightly on the right of `-`.
+                let nested = |w: &Width, right: bool| match w {
+                    Width::Expr { op: inner, .. } => {
+                        (*op == WidthOp::Mul && *inner != WidthOp::Mul)
+                            || (right
+                                && *op == WidthOp::Sub
+                                && *inner != WidthOp::Mul)
+                    }
+                    Width::Const { .. } | Width::Param { .. } => false,
+                };
+                if nested(left, false) {
+                    write!(f, "({})", left)?;
+                } else {
+                    write!(f, "{}", left)?;
+                }
+                write!(f, " {} ", op)?;
+                if nested(right, true) {
+                    write!(f, "({})", right)
+                } else {
+                    write!(f, "{}", right)
+                }
+            }
         }
     }
 }
@@ -132,19 +380,7 @@ impl PortDef {
         &self,
         binding: &LinkedHashMap<Id, u64>,
     ) -> CalyxResult<(Id, u64, Attributes)> {
-        match &self.width {
-            Width::Const { value } => {
-                Ok((self.name.clone(), *value, self.attributes.clone()))
-            }
-            Width::Param { value } => match binding.get(value) {
-                Some(width) => {
-                    Ok((self.name.clone(), *width, self.attributes.clone()))
-                }
-                None => Err(Error::ParamBindingMissing(
-                    self.name.clone(),
-                    value.clone(),
-                )),
-            },
-        }
+        let width = self.width.resolve(&self.name, binding)?;
+        Ok((self.name.clone(), width, self.attributes.clone()))
     }
 }
@@ -3,7 +3,8 @@
 //! to the Component.
 use itertools::Itertools;
 
-use crate::ir::{self, RRC};
+use crate::ir::{self, CommentPos, GetAttributes, RRC};
+use std::collections::HashSet;
 use std::io;
 use std::path::Path;
 use std::rc::Rc;
@@ -12,14 +13,14 @@ use std::rc::Rc;
 pub struct IRPrinter;
 
 impl IRPrinter {
-    /// Format attributes of the form `@static(1)`.
+    /// Format attributes of the form `@static(1)` or `@bind("name")`.
     /// Returns the empty string if the `attrs` is empty.
     fn format_at_attributes(attrs: &ir::Attributes) -> String {
         attrs
             .attrs
             .iter()
             .map(|(k, v)| {
-                if *v == 1 {
+                if *v == ir::AttrValue::Num(1) {
                     format!("@{}", k)
                 } else {
                     format!("@{}({})", k, v)
@@ -47,13 +48,25 @@ impl IRPrinter {
         }
     }
 
+    /// Write `comments`, one per line.
+    fn write_comments<F: io::Write>(
+        comments: &[String],
+        indent_level: usize,
+        f: &mut F,
+    ) -> io::Result<()> {
+        for comment in comments {
+            writeln!(f, "{}{}", " ".repeat(indent_level), comment)?;
+        }
+        Ok(())
+    }
+
     /// Formats port definitions in signatures
     fn format_ports(ports: &[RRC<ir::Port>]) -> String {
         ports
             .iter()
             .map(|p| {
                 format!(
-                    "{}{}: {}",
+                    "{}{}{}: {}",
                     if !p.borrow().attributes.is_empty() {
                         format!(
                             "{} ",
@@ -62,6 +75,11 @@ impl IRPrinter {
                     } else {
                         "".to_string()
                     },
+                    if p.borrow().direction == ir::Direction::Inout {
+                        "inout "
+                    } else {
+                        ""
+                    },
                     p.borrow().name.id.to_string(),
                     p.borrow().width
                 )
@@ -82,6 +100,22 @@ impl IRPrinter {
         writeln!(f, "}}")
     }
 
+    /// Formats and writes the `metadata` block. Nothing is written if there
+    /// are no entries.
+    pub fn write_metadata<F: io::Write>(
+        metadata: &ir::Metadata,
+        f: &mut F,
+    ) -> io::Result<()> {
+        if metadata.is_empty() {
+            return Ok(());
+        }
+        writeln!(f, "metadata {{")?;
+        for (idx, pos) in metadata.iter() {
+            writeln!(f, "  {}: \"{}\" line {};", idx, pos.file, pos.line)?;
+        }
+        writeln!(f, "}}")
+    }
+
     fn format_port_def(port_defs: &[&ir::PortDef]) -> String {
         port_defs
             .iter()
@@ -119,13 +153,26 @@ impl IRPrinter {
             prim.name,
             Self::format_attributes(&prim.attributes)
         )?;
-        if !prim.params.is_empty() {
+        if !prim.params.is_empty() || !prim.string_params.is_empty() {
             write!(
                 f,
                 "[{}]",
                 prim.params
                     .iter()
-                    .map(|p| p.to_string())
+                    .zip(
+                        prim.param_defaults
+                            .iter()
+                            .chain(std::iter::repeat(&None))
+                    )
+                    .map(|(p, val)| match val {
+                        Some(val) => format!("{}={}", p, val),
+                        None => p.to_string(),
+                    })
+                    .chain(
+                        prim.string_params
+                            .iter()
+                            .map(|p| format!("{}: string", p))
+                    )
                     .collect_vec()
                     .join(", ")
             )?
@@ -151,13 +198,33 @@ impl IRPrinter {
         comp: &ir::Component,
         f: &mut F,
     ) -> io::Result<()> {
+        Self::write_component_with_comments(comp, &ir::Comments::default(), f)
+    }
+
+    /// Formats and writes the Component along with the comments attached to
+    /// it in `comments`.
+    pub fn write_component_with_comments<F: io::Write>(
+        comp: &ir::Component,
+        comments: &ir::Comments,
+        f: &mut F,
+    ) -> io::Result<()> {
+        let empty = ir::ComponentComments::default();
+        let comments = comments.component(&comp.name).unwrap_or(&empty);
         let sig = comp.signature.borrow();
         let (inputs, outputs): (Vec<_>, Vec<_>) =
             sig.ports.iter().map(|p| Rc::clone(p)).partition(|p| {
                 // Cell signature stores the ports in reversed direction.
-                matches!(p.borrow().direction, ir::Direction::Output)
+                // `inout` ports are printed with the inputs.
+                matches!(
+                    p.borrow().direction,
+                    ir::Direction::Output | ir::Direction::Inout
+                )
             });
 
+        Self::write_comments(comments.get(&CommentPos::Component), 0, f)?;
+        if let Some(latency) = comp.latency {
+            write!(f, "static<{}> ", latency)?;
+        }
         writeln!(
             f,
             "component {}{}({}) -> ({}) {{",
@@ -170,22 +237,32 @@ impl IRPrinter {
         // Add the cells
         writeln!(f, "  cells {{")?;
         for cell in comp.cells.iter() {
-            Self::write_cell(&cell.borrow(), 4, f)?;
+            let cell = cell.borrow();
+            let pos = CommentPos::Cell(cell.name().clone());
+            Self::write_comments(comments.get(&pos), 4, f)?;
+            Self::write_cell(&cell, 4, f)?;
         }
         writeln!(f, "  }}")?;
 
         // Add the wires
         writeln!(f, "  wires {{")?;
         for group in comp.groups.iter() {
-            Self::write_group(&group.borrow(), 4, f)?;
+            Self::write_commented_group(&group.borrow(), 4, comments, f)?;
             writeln!(f)?;
         }
         for comb_group in comp.comb_groups.iter() {
-            Self::write_comb_group(&comb_group.borrow(), 4, f)?;
+            Self::write_commented_comb_group(
+                &comb_group.borrow(),
+                4,
+                comments,
+                f,
+            )?;
             writeln!(f)?;
         }
         // Write the continuous assignments
         for assign in &comp.continuous_assignments {
+            let pos = CommentPos::assign(None, assign);
+            Self::write_comments(comments.get(&pos), 4, f)?;
             Self::write_assignment(assign, 4, f)?;
             writeln!(f)?;
         }
@@ -196,7 +273,13 @@ impl IRPrinter {
             writeln!(f, "  control {{}}")?;
         } else {
             writeln!(f, "  control {{")?;
-            Self::write_control(&comp.control.borrow(), 4, f)?;
+            Self::write_commented_control(
+                &comp.control.borrow(),
+                4,
+                comments,
+                &mut HashSet::new(),
+                f,
+            )?;
             writeln!(f, "  }}")?;
         }
 
@@ -213,6 +296,7 @@ impl IRPrinter {
             ir::CellType::Primitive {
                 name,
                 param_binding,
+                string_binding,
                 ..
             } => {
                 write!(f, "{}", " ".repeat(indent_level))?;
@@ -224,16 +308,29 @@ impl IRPrinter {
                     )?
                 }
                 write!(f, "{} = ", cell.name().id)?;
-                writeln!(
+                write!(
                     f,
-                    "{}({});",
+                    "{}({})",
                     name.id,
                     param_binding
                         .iter()
                         .map(|(_, v)| v.to_string())
+                        .chain(
+                            string_binding
+                                .iter()
+                                .map(|(_, v)| format!("\"{}\"", v))
+                        )
                         .collect::<Vec<_>>()
                         .join(", ")
-                )
+                )?;
+                if let Some(contents) = &cell.contents {
+                    write!(
+                        f,
+                        " = {{{}}}",
+                        contents.iter().map(|v| v.to_string()).join(", ")
+                    )?;
+                }
+                writeln!(f, ";")
             }
             ir::CellType::Component { name } => {
                 write!(f, "{}", " ".repeat(indent_level))?;
@@ -271,6 +368,20 @@ impl IRPrinter {
         indent_level: usize,
         f: &mut F,
     ) -> io::Result<()> {
+        let comments = ir::ComponentComments::default();
+        Self::write_commented_comb_group(group, indent_level, &comments, f)
+    }
+
+    /// Format and write a combinational group along with the comments
+    /// attached to it and its assignments.
+    fn write_commented_comb_group<F: io::Write>(
+        group: &ir::CombGroup,
+        indent_level: usize,
+        comments: &ir::ComponentComments,
+        f: &mut F,
+    ) -> io::Result<()> {
+        let pos = CommentPos::Group(group.name().clone());
+        Self::write_comments(comments.get(&pos), indent_level, f)?;
         write!(f, "{}", " ".repeat(indent_level))?;
         write!(f, "comb group {}", group.name().id)?;
         if !group.attributes.is_empty() {
@@ -279,6 +390,8 @@ impl IRPrinter {
         writeln!(f, " {{")?;
 
         for assign in &group.assignments {
+            let pos = CommentPos::assign(Some(group.name()), assign);
+            Self::write_comments(comments.get(&pos), indent_level + 2, f)?;
             Self::write_assignment(assign, indent_level + 2, f)?;
             writeln!(f)?;
         }
@@ -291,7 +404,24 @@ impl IRPrinter {
         indent_level: usize,
         f: &mut F,
     ) -> io::Result<()> {
+        let comments = ir::ComponentComments::default();
+        Self::write_commented_group(group, indent_level, &comments, f)
+    }
+
+    /// Format and write a group along with the comments attached to it and
+    /// its assignments.
+    fn write_commented_group<F: io::Write>(
+        group: &ir::Group,
+        indent_level: usize,
+        comments: &ir::ComponentComments,
+        f: &mut F,
+    ) -> io::Result<()> {
+        let pos = CommentPos::Group(group.name().clone());
+        Self::write_comments(comments.get(&pos), indent_level, f)?;
         write!(f, "{}", " ".repeat(indent_level))?;
+        if let Some(latency) = group.latency {
+            write!(f, "static<{}> ", latency)?;
+        }
         write!(f, "group {}", group.name().id)?;
         if !group.attributes.is_empty() {
             write!(f, "{}", Self::format_attributes(&group.attributes))?;
@@ -299,6 +429,8 @@ impl IRPrinter {
         writeln!(f, " {{")?;
 
         for assign in &group.assignments {
+            let pos = CommentPos::assign(Some(group.name()), assign);
+            Self::write_comments(comments.get(&pos), indent_level + 2, f)?;
             Self::write_assignment(assign, indent_level + 2, f)?;
             writeln!(f)?;
         }
@@ -311,6 +443,43 @@ impl IRPrinter {
         indent_level: usize,
         f: &mut F,
     ) -> io::Result<()> {
+        let comments = ir::ComponentComments::default();
+        Self::write_commented_control(
+            control,
+            indent_level,
+            &comments,
+            &mut HashSet::new(),
+            f,
+        )
+    }
+
+    /// Format and write a control program along with the comments attached
+    /// to its statements. Statements copied by passes share their comments,
+    /// which are only written with the first copy; `written` are the keys of
+    /// the comments written so far.
+    fn write_commented_control<F: io::Write>(
+        control: &ir::Control,
+        indent_level: usize,
+        comments: &ir::ComponentComments,
+        written: &mut HashSet<usize>,
+        f: &mut F,
+    ) -> io::Result<()> {
+        let key = control.get_attributes().and_then(|a| a.comment_key);
+        if let Some(key) = key.filter(|key| written.insert(*key)) {
+            let pos = CommentPos::Control(key);
+            Self::write_comments(comments.get(&pos), indent_level, f)?;
+        }
+        // Write the child statement `stmt`.
+        let mut write_child =
+            |stmt: &ir::Control, f: &mut F| -> io::Result<()> {
+                Self::write_commented_control(
+                    stmt,
+                    indent_level + 2,
+                    comments,
+                    written,
+                    f,
+                )
+            };
         write!(f, "{}", " ".repeat(indent_level))?;
         match control {
             ir::Control::Enable(ir::Enable { group, attributes }) => {
@@ -321,6 +490,7 @@ impl IRPrinter {
             }
             ir::Control::Invoke(ir::Invoke {
                 comp,
+                interface,
                 inputs,
                 outputs,
                 attributes,
@@ -329,7 +499,11 @@ impl IRPrinter {
                 if !attributes.is_empty() {
                     write!(f, "{} ", Self::format_at_attributes(attributes))?
                 }
-                write!(f, "invoke {}(", comp.borrow().name())?;
+                write!(f, "invoke {}", comp.borrow().name())?;
+                if let Some(go) = interface {
+                    write!(f, "[{}]", go)?;
+                }
+                write!(f, "(")?;
                 for (i, (arg, port)) in inputs.iter().enumerate() {
                     write!(
                         f,
@@ -372,7 +546,7 @@ impl IRPrinter {
                 }
                 writeln!(f, "seq {{")?;
                 for stmt in stmts {
-                    Self::write_control(stmt, indent_level + 2, f)?;
+                    write_child(stmt, f)?;
                 }
                 writeln!(f, "{}}}", " ".repeat(indent_level))
             }
@@ -382,7 +556,7 @@ impl IRPrinter {
                 }
                 writeln!(f, "par {{")?;
                 for stmt in stmts {
-                    Self::write_control(stmt, indent_level + 2, f)?;
+                    write_child(stmt, f)?;
                 }
                 writeln!(f, "{}}}", " ".repeat(indent_level))
             }
@@ -401,13 +575,13 @@ impl IRPrinter {
                     write!(f, "with {} ", c.borrow().name.id)?;
                 }
                 writeln!(f, "{{")?;
-                Self::write_control(tbranch, indent_level + 2, f)?;
+                write_child(tbranch, f)?;
                 write!(f, "{}}}", " ".repeat(indent_level))?;
                 if let ir::Control::Empty(_) = **fbranch {
                     writeln!(f)
                 } else {
                     writeln!(f, " else {{")?;
-                    Self::write_control(fbranch, indent_level + 2, f)?;
+                    write_child(fbranch, f)?;
                     writeln!(f, "{}}}", " ".repeat(indent_level))
                 }
             }
@@ -425,10 +599,17 @@ impl IRPrinter {
                     write!(f, "with {} ", c.borrow().name.id)?;
                 }
                 writeln!(f, "{{")?;
-                Self::write_control(body, indent_level + 2, f)?;
+                write_child(body, f)?;
                 writeln!(f, "{}}}", " ".repeat(indent_level))
             }
             ir::Control::Empty(_) => writeln!(f),
+            ir::Control::Debug(ir::Debug { format, ports }) => {
+                write!(f, "@debug(\"{}\"", format)?;
+                for port in ports {
+                    write!(f, ", {}", Self::get_port_access(&port.borrow()))?;
+                }
+                writeln!(f, ");")
+            }
         }
     }
 
@@ -477,7 +658,7 @@ impl IRPrinter {
     }
 
     /// Get the port access expression.
-    fn get_port_access(port: &ir::Port) -> String {
+    pub fn get_port_access(port: &ir::Port) -> String {
         match &port.parent {
             ir::PortParent::Cell(cell_wref) => {
                 let cell_ref =
@@ -0,0 +1,128 @@
+//! Provenance of the cells and groups that passes add to a program.
+//!
+//! Cells and groups created using a [Builder](super::Builder) remember which
+//! pass created them and the definitions they were derived from. The name of
+//! the pass is filled in by the pass manager once the pass finishes so that
+//! passes only need to record the definitions they derive from using
+//! [Attributes::derive_from](super::Attributes::derive_from). Errors about
+//! generated definitions are annotated with their provenance using a
+//! [ProvenanceMap].
+use super::{Attributes, Component, Context, Id};
+use crate::errors::Error;
+use std::fmt::Display;
+
+/// The pass that created a cell or group and the definitions it was derived
+/// from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    /// Name of the pass that created the definition.
+    pub pass: String,
+    /// Names of the definitions in the same component that the definition
+    /// was derived from.
+    pub derived_from: Vec<Id>,
+}
+
+impl Display for Provenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "created by the `{}` pass", self.pass)?;
+        if !self.derived_from.is_empty() {
+            let names = self
+                .derived_from
+                .iter()
+                .map(|name| format!("`{}`", name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            write!(f, " from {}", names)?;
+        }
+        Ok(())
+    }
+}
+
+/// Record `pass` as the creator of the definitions in `ctx` that were created
+/// since the last pass finished.
+pub(crate) fn record_pass(ctx: &mut Context, pass: &str) {
+    for comp in &ctx.components {
+        for cell in comp.cells.iter() {
+            cell.borrow_mut().attributes.record_pass(pass);
+        }
+        for group in comp.groups.iter() {
+            group.borrow_mut().attributes.record_pass(pass);
+        }
+        for group in comp.comb_groups.iter() {
+            group.borrow_mut().attributes.record_pass(pass);
+        }
+    }
+}
+
+/// Map from the names of the generated definitions of a program to their
+/// provenance.
+#[derive(Default)]
+pub struct ProvenanceMap {
+    /// Kind, name, and provenance of every generated definition.
+    defs: Vec<(&'static str, Id, Provenance)>,
+}
+
+impl ProvenanceMap {
+    /// Build the map from the generated definitions in `comps`.
+    pub fn new<'a, I>(comps: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Component>,
+    {
+        let mut map = ProvenanceMap::default();
+        let mut add = |kind, name: &Id, attrs: &Attributes| {
+            let prov = attrs.provenance().filter(|prov| !prov.pass.is_empty());
+            if let Some(prov) = prov {
+                map.defs.push((kind, name.clone(), prov.clone()));
+            }
+        };
+        for comp in comps {
+            for cell in comp.cells.iter() {
+                let cell = cell.borrow();
+                add("cell", cell.name(), &cell.attributes);
+            }
+            for group in comp.groups.iter() {
+                let group = group.borrow();
+                add("group", group.name(), &group.attributes);
+            }
+            for group in comp.comb_groups.iter() {
+                let group = group.borrow();
+                add("combinational group", group.name(), &group.attributes);
+            }
+        }
+        map
+    }
+
+    /// Kind and provenance of the generated definition called `name`.
+    pub fn lookup(&self, name: &str) -> Option<(&'static str, &Provenance)> {
+        self.defs
+            .iter()
+            .find(|(_, def, _)| def == name)
+            .map(|(kind, _, prov)| (*kind, prov))
+    }
+
+    /// Attach the provenance of the generated definitions that the message
+    /// of `err` quotes, if any.
+    pub fn annotate(&self, err: Error) -> Error {
+        if self.defs.is_empty() || matches!(err, Error::Generated(..)) {
+            return err;
+        }
+        let mut notes: Vec<String> = vec![];
+        let msg = err.message();
+        // Quoted names are between every other pair of backticks. Ports and
+        // holes are quoted along with their parent, like `r.in` or `g[go]`.
+        for quoted in msg.split('`').skip(1).step_by(2) {
+            let name = quoted.split(['.', '[']).next().unwrap();
+            if let Some((kind, prov)) = self.lookup(name) {
+                let note = format!("{} `{}` was {}", kind, name, prov);
+                if !notes.contains(&note) {
+                    notes.push(note);
+                }
+            }
+        }
+        if notes.is_empty() {
+            err
+        } else {
+            Error::Generated(Box::new(err), notes)
+        }
+    }
+}
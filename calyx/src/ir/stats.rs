@@ -0,0 +1,56 @@
+//! Statistics about the changes made by passes.
+use super::Id;
+use linked_hash_map::LinkedHashMap;
+use serde::Serialize;
+
+/// Counts recorded for a component by a pass, such as the number of cells it
+/// shared, keyed by the name of the statistic.
+pub type ComponentStats = LinkedHashMap<String, u64>;
+
+/// Statistics recorded by the passes that ran on a program.
+///
+/// The statistics are organized by pass and component in the order in which
+/// the passes ran. When a pass runs more than once, its counts are added up.
+/// The serialized form maps the name of each pass to the counts of each
+/// component:
+/// ```json
+/// { "resource-sharing": { "main": { "cells-shared": 2 } } }
+/// ```
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct PassStats {
+    passes: LinkedHashMap<String, LinkedHashMap<String, ComponentStats>>,
+    /// Pass that is currently running.
+    #[serde(skip)]
+    current: Option<String>,
+}
+
+impl PassStats {
+    /// Record the statistics of the following calls to [PassStats::record]
+    /// under `pass`.
+    pub fn start_pass<S: ToString>(&mut self, pass: S) {
+        self.current = Some(pass.to_string());
+    }
+
+    /// Add `count` to the statistic `stat` of the component `comp` for the
+    /// pass that is currently running. Does nothing if no pass is running.
+    pub fn record<S: ToString>(&mut self, comp: &Id, stat: S, count: u64) {
+        let pass = match &self.current {
+            Some(pass) => pass.clone(),
+            None => return,
+        };
+        *self
+            .passes
+            .entry(pass)
+            .or_default()
+            .entry(comp.to_string())
+            .or_default()
+            .entry(stat.to_string())
+            .or_insert(0) += count;
+    }
+
+    /// Returns true if no statistics were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+}
@@ -1,11 +1,22 @@
 //! Representation for structure (wires and cells) in a Calyx program.
 use super::{Attributes, GetAttributes, Guard, Id, RRC, WRC};
+use crate::utils;
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use std::hash::Hash;
 use std::rc::Rc;
 
+/// Suggest the port in `ports` with a name similar to `name` for panic
+/// messages.
+fn suggestion(name: &str, ports: &[RRC<Port>]) -> String {
+    let names = ports.iter().map(|p| p.borrow().name.clone());
+    utils::closest_name(name, names)
+        .map(|similar| format!(", did you mean `{}'?", similar))
+        .unwrap_or_default()
+}
+
 /// Direction of a port on a cell.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Direction {
     /// Input port.
     Input,
@@ -117,6 +128,9 @@ impl Iterator for PortIterator<'_> {
 /// Alias for bindings
 pub type Binding = SmallVec<[(Id, u64); 5]>;
 
+/// Alias for bindings of string parameters
+pub type StringBinding = Vec<(Id, String)>;
+
 /// The type for a Cell
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum CellType {
@@ -126,6 +140,8 @@ pub enum CellType {
         name: Id,
         /// Bindings for the parameters. Uses Vec to retain the input order.
         param_binding: Binding,
+        /// Bindings for the string parameters.
+        string_binding: StringBinding,
         /// True iff this is a combinational primitive
         is_comb: bool,
     },
@@ -156,6 +172,19 @@ pub struct Cell {
     pub prototype: CellType,
     /// Attributes for this group.
     pub attributes: Attributes,
+    /// Initial contents of a memory in row-major order. Every element of the
+    /// memory is given a value.
+    pub contents: Option<Vec<u128>>,
+}
+
+impl GetAttributes for Port {
+    fn get_attributes(&self) -> Option<&Attributes> {
+        Some(&self.attributes)
+    }
+
+    fn get_mut_attributes(&mut self) -> Option<&mut Attributes> {
+        Some(&mut self.attributes)
+    }
 }
 
 impl GetAttributes for Cell {
@@ -191,6 +220,37 @@ impl Cell {
             .map(|r| Rc::clone(r))
     }
 
+    /// Get a reference to the first port that has the attribute `attr` set to
+    /// `val`.
+    pub fn find_with_attr_val<S>(&self, attr: S, val: u64) -> Option<RRC<Port>>
+    where
+        S: AsRef<str>,
+    {
+        self.ports
+            .iter()
+            .find(|&g| g.borrow().attributes.get(attr.as_ref()) == Some(&val))
+            .map(Rc::clone)
+    }
+
+    /// Get the `go` and `done` ports of an interface of this cell.
+    /// The interface is named by its `@go` port and is paired with the `@done`
+    /// port that has the same attribute value. If `go` is `None`, the
+    /// canonical interface marked with `@go(1)` and `@done(1)` is returned.
+    pub fn find_interface(
+        &self,
+        go: Option<&Id>,
+    ) -> Option<(RRC<Port>, RRC<Port>)> {
+        let go_port = match go {
+            Some(name) => self
+                .find(name)
+                .filter(|port| port.borrow().attributes.has("go"))?,
+            None => self.find_with_attr_val("go", 1)?,
+        };
+        let val = *go_port.borrow().attributes.get("go").unwrap();
+        let done_port = self.find_with_attr_val("done", val)?;
+        Some((go_port, done_port))
+    }
+
     /// Get a reference to the named port and throw an error if it doesn't
     /// exist.
     pub fn get<S>(&self, name: S) -> RRC<Port>
@@ -199,9 +259,10 @@ impl Cell {
     {
         self.find(&name).unwrap_or_else(|| {
             panic!(
-                "Port `{}' not found on cell `{}'",
+                "Port `{}' not found on cell `{}'{}",
                 name.to_string(),
-                self.name.to_string()
+                self.name,
+                suggestion(name.as_ref(), &self.ports)
             )
         })
     }
@@ -248,6 +309,43 @@ impl Cell {
         }
     }
 
+    /// Get the binding of a string parameter from the prototype used to
+    /// build this cell.
+    pub fn get_string_parameter<S>(&self, param: S) -> Option<&str>
+    where
+        S: std::fmt::Display + Clone + AsRef<str>,
+    {
+        match &self.prototype {
+            CellType::Primitive { string_binding, .. } => string_binding
+                .iter()
+                .find(|(key, _)| *key == param)
+                .map(|(_, val)| val.as_str()),
+            CellType::Component { .. } => None,
+            CellType::ThisComponent => None,
+            CellType::Constant { .. } => None,
+        }
+    }
+
+    /// The sizes of the dimensions of a `std_mem_d*` primitive, if this cell
+    /// is one.
+    pub fn memory_dimensions(&self) -> Option<Vec<u64>> {
+        let dims = match &self.prototype {
+            CellType::Primitive { name, .. } => match name.as_ref() {
+                "std_mem_d1" => vec!["SIZE"],
+                "std_mem_d2" => vec!["D0_SIZE", "D1_SIZE"],
+                "std_mem_d3" => vec!["D0_SIZE", "D1_SIZE", "D2_SIZE"],
+                "std_mem_d4" => {
+                    vec!["D0_SIZE", "D1_SIZE", "D2_SIZE", "D3_SIZE"]
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        dims.into_iter()
+            .map(|dim| self.get_parameter(dim))
+            .collect()
+    }
+
     /// Return the canonical name for the cell generated to represent this
     /// (val, width) constant.
     pub fn constant_name(val: u64, width: u64) -> Id {
@@ -321,6 +419,9 @@ pub struct Group {
 
     /// Attributes for this group.
     pub attributes: Attributes,
+
+    /// Latency of a `static<n>` group which does not have a `done` condition.
+    pub latency: Option<u64>,
 }
 impl Group {
     /// Get a reference to the named hole if it exists.
@@ -341,9 +442,10 @@ impl Group {
     {
         self.find(&name).unwrap_or_else(|| {
             panic!(
-                "Hole `{}' not found on group `{}'",
+                "Hole `{}' not found on group `{}'{}",
                 name.to_string(),
-                self.name.to_string()
+                self.name,
+                suggestion(name.as_ref(), &self.holes)
             )
         })
     }
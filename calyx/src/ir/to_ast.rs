@@ -0,0 +1,297 @@
+//! Transform an [Context] back into the frontend AST.
+//!
+//! The generated [ast::NamespaceDef] does not import any files. Instead, it
+//! contains the `extern` definitions of all the primitives used by the
+//! program so that it can be rebuilt without access to the original sources.
+use super::{
+    Assignment, Cell, CellType, CombGroup, Component, Context, Control, Guard,
+    Id, Port, PortDef, PortParent, Width, RRC,
+};
+use crate::{
+    errors::{CalyxResult, Error},
+    frontend::ast,
+};
+use std::fs;
+
+/// Transform the program represented by `ctx` into a [ast::NamespaceDef].
+pub fn context_to_ast(ctx: &Context) -> CalyxResult<ast::NamespaceDef> {
+    let externs = ctx
+        .lib
+        .extern_defs()
+        .map(|(path, prims)| {
+            // Extern paths are resolved relative to the file being parsed so
+            // make them absolute.
+            let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+            (path.to_string_lossy().to_string(), prims.cloned().collect())
+        })
+        .collect();
+    let components = ctx
+        .components
+        .iter()
+        .map(component_to_ast)
+        .collect::<CalyxResult<_>>()?;
+    Ok(ast::NamespaceDef {
+        lib_paths: vec![],
+        imports: vec![],
+        components,
+        externs,
+        metadata: ctx.metadata.clone(),
+    })
+}
+
+fn component_to_ast(comp: &Component) -> CalyxResult<ast::ComponentDef> {
+    let signature = comp
+        .signature
+        .borrow()
+        .ports
+        .iter()
+        .map(|port| {
+            let port = port.borrow();
+            PortDef {
+                name: port.name.clone(),
+                width: Width::Const { value: port.width },
+                // The signature cell stores the ports in reversed direction.
+                direction: port.direction.reverse(),
+                attributes: port.attributes.clone(),
+            }
+        })
+        .collect();
+
+    let cells = comp
+        .cells
+        .iter()
+        .filter_map(|cell| cell_to_ast(&cell.borrow()))
+        .collect();
+
+    let groups = comp
+        .groups
+        .iter()
+        .map(|group| {
+            let group = group.borrow();
+            Ok(ast::Group {
+                name: group.name().clone(),
+                wires: assignments_to_ast(&group.assignments)?,
+                attributes: group.attributes.clone(),
+                is_comb: false,
+                latency: group.latency,
+                comments: vec![],
+            })
+        })
+        .chain(comp.comb_groups.iter().map(|group| {
+            let group = group.borrow();
+            Ok(ast::Group {
+                name: group.name().clone(),
+                wires: assignments_to_ast(&group.assignments)?,
+                attributes: group.attributes.clone(),
+                is_comb: true,
+                latency: None,
+                comments: vec![],
+            })
+        }))
+        .collect::<CalyxResult<_>>()?;
+
+    Ok(ast::ComponentDef {
+        name: comp.name.clone(),
+        params: vec![],
+        signature,
+        cells,
+        groups,
+        continuous_assignments: assignments_to_ast(
+            &comp.continuous_assignments,
+        )?,
+        control: control_to_ast(&comp.control.borrow())?,
+        attributes: comp.attributes.clone(),
+        latency: comp.latency,
+        comments: vec![],
+    })
+}
+
+/// Returns `None` for constants since they are defined by their uses.
+fn cell_to_ast(cell: &Cell) -> Option<ast::Cell> {
+    let (proto, params, string_params) = match &cell.prototype {
+        CellType::Primitive {
+            name,
+            param_binding,
+            string_binding,
+            ..
+        } => (
+            name.clone(),
+            param_binding
+                .iter()
+                .map(|(_, v)| Width::Const { value: *v })
+                .collect(),
+            string_binding.iter().map(|(_, v)| v.clone()).collect(),
+        ),
+        CellType::Component { name } => (name.clone(), vec![], vec![]),
+        CellType::ThisComponent | CellType::Constant { .. } => return None,
+    };
+    let mut ast_cell = ast::Cell::from(
+        cell.name().clone(),
+        proto,
+        params,
+        string_params,
+        cell.attributes.clone(),
+    );
+    ast_cell.contents = cell.contents.clone().map(ast::Contents::Values);
+    Some(ast_cell)
+}
+
+fn assignments_to_ast(assigns: &[Assignment]) -> CalyxResult<Vec<ast::Wire>> {
+    assigns
+        .iter()
+        .map(|assign| {
+            let guard = match &*assign.guard {
+                Guard::True => None,
+                guard => Some(guard_to_ast(guard)),
+            };
+            Ok(ast::Wire {
+                src: ast::Guard {
+                    guard,
+                    expr: port_to_atom(&assign.src),
+                },
+                dest: port_to_ast(&assign.dst)?,
+                comments: vec![],
+            })
+        })
+        .collect()
+}
+
+fn guard_to_ast(guard: &Guard) -> ast::GuardExpr {
+    use ast::GuardExpr as GE;
+
+    let boxed = |g: &Guard| Box::new(guard_to_ast(g));
+    match guard {
+        // Only nested `true` guards reach here.
+        Guard::True => GE::Atom(ast::Atom::Num(ast::BitNum {
+            width: 1,
+            num_type: ast::NumType::Decimal,
+            val: 1,
+            span: None,
+        })),
+        Guard::Or(l, r) => GE::Or(boxed(l), boxed(r)),
+        Guard::And(l, r) => GE::And(boxed(l), boxed(r)),
+        Guard::Not(g) => GE::Not(boxed(g)),
+        Guard::Eq(l, r) => GE::Eq(port_to_atom(l), port_to_atom(r)),
+        Guard::Neq(l, r) => GE::Neq(port_to_atom(l), port_to_atom(r)),
+        Guard::Gt(l, r) => GE::Gt(port_to_atom(l), port_to_atom(r)),
+        Guard::Lt(l, r) => GE::Lt(port_to_atom(l), port_to_atom(r)),
+        Guard::Geq(l, r) => GE::Geq(port_to_atom(l), port_to_atom(r)),
+        Guard::Leq(l, r) => GE::Leq(port_to_atom(l), port_to_atom(r)),
+        Guard::Port(p) => GE::Atom(port_to_atom(p)),
+    }
+}
+
+/// Ports on constant cells are turned into numbers.
+fn port_to_atom(port: &RRC<Port>) -> ast::Atom {
+    let port = port.borrow();
+    if let PortParent::Cell(cell) = &port.parent {
+        if let CellType::Constant { val, width } =
+            cell.upgrade().borrow().prototype
+        {
+            return ast::Atom::Num(ast::BitNum {
+                width,
+                num_type: ast::NumType::Decimal,
+                val,
+                span: None,
+            });
+        }
+    }
+    ast::Atom::Port(port_ref(&port))
+}
+
+fn port_to_ast(port: &RRC<Port>) -> CalyxResult<ast::Port> {
+    match port_to_atom(port) {
+        ast::Atom::Port(port) => Ok(port),
+        ast::Atom::Num(_) => Err(Error::MalformedStructure(format!(
+            "Constant `{}` used as a port",
+            port.borrow().get_parent_name()
+        ))),
+    }
+}
+
+fn port_ref(port: &Port) -> ast::Port {
+    match &port.parent {
+        PortParent::Cell(cell) => {
+            let cell = cell.upgrade();
+            let cell = cell.borrow();
+            match cell.prototype {
+                CellType::ThisComponent => ast::Port::This {
+                    port: port.name.clone(),
+                },
+                _ => ast::Port::Comp {
+                    component: cell.name().clone(),
+                    port: port.name.clone(),
+                },
+            }
+        }
+        PortParent::Group(group) => ast::Port::Hole {
+            group: group.upgrade().borrow().name().clone(),
+            name: port.name.clone(),
+        },
+    }
+}
+
+fn control_to_ast(control: &Control) -> CalyxResult<ast::Control> {
+    let stmts_to_ast = |stmts: &[Control]| -> CalyxResult<Vec<ast::Control>> {
+        stmts.iter().map(control_to_ast).collect()
+    };
+    let cond_name = |cond: &Option<RRC<CombGroup>>| -> Option<Id> {
+        cond.as_ref().map(|cg| cg.borrow().name().clone())
+    };
+    let port_map = |ports: &[(Id, RRC<Port>)]| -> Vec<(Id, ast::Atom)> {
+        ports
+            .iter()
+            .map(|(id, port)| (id.clone(), port_to_atom(port)))
+            .collect()
+    };
+    Ok(match control {
+        Control::Seq(seq) => ast::Control::Seq {
+            stmts: stmts_to_ast(&seq.stmts)?,
+            attributes: seq.attributes.clone(),
+            comments: vec![],
+        },
+        Control::Par(par) => ast::Control::Par {
+            stmts: stmts_to_ast(&par.stmts)?,
+            attributes: par.attributes.clone(),
+            comments: vec![],
+        },
+        Control::If(if_) => ast::Control::If {
+            port: port_to_ast(&if_.port)?,
+            cond: cond_name(&if_.cond),
+            tbranch: Box::new(control_to_ast(&if_.tbranch)?),
+            fbranch: Box::new(control_to_ast(&if_.fbranch)?),
+            attributes: if_.attributes.clone(),
+            comments: vec![],
+        },
+        Control::While(wh) => ast::Control::While {
+            port: port_to_ast(&wh.port)?,
+            cond: cond_name(&wh.cond),
+            body: Box::new(control_to_ast(&wh.body)?),
+            attributes: wh.attributes.clone(),
+            comments: vec![],
+        },
+        Control::Enable(en) => ast::Control::Enable {
+            comp: en.group.borrow().name().clone(),
+            attributes: en.attributes.clone(),
+            comments: vec![],
+        },
+        Control::Invoke(inv) => ast::Control::Invoke {
+            comp: inv.comp.borrow().name().clone(),
+            interface: inv.interface.clone(),
+            inputs: port_map(&inv.inputs),
+            outputs: port_map(&inv.outputs),
+            attributes: inv.attributes.clone(),
+            comb_group: cond_name(&inv.comb_group),
+            comments: vec![],
+        },
+        Control::Empty(_) => ast::Control::Empty {},
+        Control::Debug(debug) => ast::Control::Debug {
+            format: debug.format.clone(),
+            ports: debug
+                .ports
+                .iter()
+                .map(port_to_ast)
+                .collect::<CalyxResult<_>>()?,
+        },
+    })
+}
@@ -6,6 +6,7 @@ use crate::ir::Control;
 pub type VisResult = CalyxResult<Action>;
 
 /// Action performed at the end of visiting a control statement.
+#[allow(clippy::large_enum_variant)]
 pub enum Action {
     /// Continue traversal of control program.
     Continue,
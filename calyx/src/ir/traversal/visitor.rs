@@ -82,6 +82,13 @@ impl<T: Default + Sized + Visitor> ConstructVisitor for T {
 /// 1. start_<node>: Called when visiting <node> top-down.
 /// 2. finish_<node>: Called when visiting <node> bottow-up.
 ///
+/// Leaf nodes also have start_<node> and finish_<node> functions. By
+/// default, the start_<node> functions of leaves call [Visitor::enable],
+/// [Visitor::invoke], [Visitor::empty], and [Visitor::debug].
+/// If [Visitor::visit_assignments] returns true, [Visitor::visit_assignment]
+/// is called on every assignment in the component before the control
+/// program is traversed.
+///
 /// A pass will usually override one or more function and rely on the default
 /// visitors to automatically visit the children.
 pub trait Visitor {
@@ -97,6 +104,16 @@ pub trait Visitor {
         false
     }
 
+    /// Returns true if [Visitor::visit_assignment] should be called on every
+    /// assignment of the components.
+    #[inline(always)]
+    fn visit_assignments() -> bool
+    where
+        Self: Sized,
+    {
+        false
+    }
+
     /// Define the traversal over a component.
     /// Calls [Visitor::start], visits each control node, and finally calls
     /// [Visitor::finish].
@@ -109,6 +126,12 @@ pub trait Visitor {
         Self: Sized,
     {
         self.start(comp, signatures)?
+            .and_then(|| {
+                if Self::visit_assignments() {
+                    self.traverse_assignments(comp, signatures)?;
+                }
+                Ok(Action::Continue)
+            })?
             .and_then(|| {
                 // Create a clone of the reference to the Control
                 // program.
@@ -126,12 +149,47 @@ pub trait Visitor {
         Ok(())
     }
 
+    /// Calls [Visitor::visit_assignment] on the assignments in every group,
+    /// combinational group, and the continuous assignments of a component.
+    fn traverse_assignments(
+        &mut self,
+        comp: &mut ir::Component,
+        signatures: &LibrarySignatures,
+    ) -> CalyxResult<()>
+    where
+        Self: Sized,
+    {
+        let groups = comp.groups.iter().map(Rc::clone).collect::<Vec<_>>();
+        for group in groups {
+            for assign in group.borrow_mut().assignments.iter_mut() {
+                self.visit_assignment(assign, comp, signatures)?;
+            }
+        }
+        let comb_groups =
+            comp.comb_groups.iter().map(Rc::clone).collect::<Vec<_>>();
+        for comb_group in comb_groups {
+            for assign in comb_group.borrow_mut().assignments.iter_mut() {
+                self.visit_assignment(assign, comp, signatures)?;
+            }
+        }
+        // Take the continuous assignments out of the component so that the
+        // visitor can mutably borrow it.
+        let mut assigns = std::mem::take(&mut comp.continuous_assignments);
+        let res = assigns.iter_mut().try_for_each(|assign| {
+            self.visit_assignment(assign, comp, signatures)
+        });
+        // Keep any continuous assignments added by the visitor.
+        assigns.append(&mut comp.continuous_assignments);
+        comp.continuous_assignments = assigns;
+        res
+    }
+
     /// Run the visitor on a given program [`ir::Context`](crate::ir::Context).
     /// The function mutably borrows the [`control`](crate::ir::Component::control)
     /// program in each component and traverses it.
     ///
-    /// After visiting a component, it called [ConstructVisitor::clear_data] to
-    /// reset the struct.
+    /// After visiting a component, it records the [Visitor::stats] of the
+    /// component and calls [ConstructVisitor::clear_data] to reset the struct.
     ///
     /// # Panics
     /// Panics if the pass attempts to use the control program mutably.
@@ -140,17 +198,24 @@ pub trait Visitor {
         Self: Sized + ConstructVisitor,
     {
         let signatures = &context.lib;
+        let stats = &mut context.stats;
 
         if Self::require_postorder() {
             // Temporarily take ownership of components from context.
             let comps = context.components.drain(..).collect();
             let mut po = PostOrder::new(comps);
-            po.apply_update(|comp| {
+            let result = po.apply_update(|comp| {
                 self.traverse_component(comp, signatures)?;
+                for (stat, count) in self.stats() {
+                    stats.record(&comp.name, stat, count);
+                }
                 self.clear_data();
                 Ok(())
-            })?;
+            });
+            // Give the components back even if the pass failed so that the
+            // error can refer to them.
             context.components = po.take();
+            result?;
         } else {
             context
                 .components
@@ -158,6 +223,9 @@ pub trait Visitor {
                 .iter_mut()
                 .try_for_each(|comp| {
                     self.traverse_component(comp, signatures)?;
+                    for (stat, count) in self.stats() {
+                        stats.record(&comp.name, stat, count);
+                    }
                     self.clear_data();
                     Ok(()) as CalyxResult<_>
                 })?;
@@ -178,6 +246,14 @@ pub trait Visitor {
         Ok(visitor)
     }
 
+    /// Statistics about the changes made to the component that was just
+    /// traversed, such as the number of cells that were removed. They are
+    /// recorded in [Context::stats](crate::ir::Context::stats) before
+    /// [ConstructVisitor::clear_data] is called.
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![]
+    }
+
     /// Executed before the traversal begins.
     fn start(
         &mut self,
@@ -198,6 +274,20 @@ pub trait Visitor {
         Ok(Action::Continue)
     }
 
+    /// Executed on every assignment in the component before the control
+    /// program is traversed if [Visitor::visit_assignments] returns true.
+    /// Not executed if [Visitor::start] does not return [Action::Continue].
+    /// The group containing the assignment is mutably borrowed while this is
+    /// called.
+    fn visit_assignment(
+        &mut self,
+        _assign: &mut ir::Assignment,
+        _comp: &mut Component,
+        _sigs: &LibrarySignatures,
+    ) -> CalyxResult<()> {
+        Ok(())
+    }
+
     /// Executed before visiting the children of a [ir::Seq] node.
     fn start_seq(
         &mut self,
@@ -278,6 +368,17 @@ pub trait Visitor {
         Ok(Action::Continue)
     }
 
+    /// Executed before [Visitor::finish_enable] at an [ir::Enable] node. Calls
+    /// [Visitor::enable] by default.
+    fn start_enable(
+        &mut self,
+        s: &mut ir::Enable,
+        comp: &mut Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.enable(s, comp, sigs)
+    }
+
     /// Executed at an [ir::Enable] node.
     fn enable(
         &mut self,
@@ -288,6 +389,27 @@ pub trait Visitor {
         Ok(Action::Continue)
     }
 
+    /// Executed after [Visitor::start_enable] at an [ir::Enable] node.
+    fn finish_enable(
+        &mut self,
+        _s: &mut ir::Enable,
+        _comp: &mut Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        Ok(Action::Continue)
+    }
+
+    /// Executed before [Visitor::finish_invoke] at an [ir::Invoke] node. Calls
+    /// [Visitor::invoke] by default.
+    fn start_invoke(
+        &mut self,
+        s: &mut ir::Invoke,
+        comp: &mut Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.invoke(s, comp, sigs)
+    }
+
     /// Executed at an [ir::Invoke] node.
     fn invoke(
         &mut self,
@@ -298,6 +420,27 @@ pub trait Visitor {
         Ok(Action::Continue)
     }
 
+    /// Executed after [Visitor::start_invoke] at an [ir::Invoke] node.
+    fn finish_invoke(
+        &mut self,
+        _s: &mut ir::Invoke,
+        _comp: &mut Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        Ok(Action::Continue)
+    }
+
+    /// Executed before [Visitor::finish_empty] at an [ir::Empty] node. Calls
+    /// [Visitor::empty] by default.
+    fn start_empty(
+        &mut self,
+        s: &mut ir::Empty,
+        comp: &mut Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.empty(s, comp, sigs)
+    }
+
     /// Executed at an [ir::Empty] node.
     fn empty(
         &mut self,
@@ -307,6 +450,47 @@ pub trait Visitor {
     ) -> VisResult {
         Ok(Action::Continue)
     }
+
+    /// Executed after [Visitor::start_empty] at an [ir::Empty] node.
+    fn finish_empty(
+        &mut self,
+        _s: &mut ir::Empty,
+        _comp: &mut Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        Ok(Action::Continue)
+    }
+
+    /// Executed before [Visitor::finish_debug] at an [ir::Debug] node. Calls
+    /// [Visitor::debug] by default.
+    fn start_debug(
+        &mut self,
+        s: &mut ir::Debug,
+        comp: &mut Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.debug(s, comp, sigs)
+    }
+
+    /// Executed at an [ir::Debug] node.
+    fn debug(
+        &mut self,
+        _s: &mut ir::Debug,
+        _comp: &mut Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        Ok(Action::Continue)
+    }
+
+    /// Executed after [Visitor::start_debug] at an [ir::Debug] node.
+    fn finish_debug(
+        &mut self,
+        _s: &mut ir::Debug,
+        _comp: &mut Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        Ok(Action::Continue)
+    }
 }
 
 /// Describes types that can be visited by things implementing [Visitor].
@@ -353,9 +537,22 @@ impl Visitable for Control {
                 .and_then(|| ctrl.body.visit(visitor, component, sigs))?
                 .pop()
                 .and_then(|| visitor.finish_while(ctrl, component, sigs))?,
-            Control::Enable(ctrl) => visitor.enable(ctrl, component, sigs)?,
-            Control::Empty(ctrl) => visitor.empty(ctrl, component, sigs)?,
-            Control::Invoke(data) => visitor.invoke(data, component, sigs)?,
+            Control::Enable(ctrl) => visitor
+                .start_enable(ctrl, component, sigs)?
+                .pop()
+                .and_then(|| visitor.finish_enable(ctrl, component, sigs))?,
+            Control::Empty(ctrl) => visitor
+                .start_empty(ctrl, component, sigs)?
+                .pop()
+                .and_then(|| visitor.finish_empty(ctrl, component, sigs))?,
+            Control::Debug(ctrl) => visitor
+                .start_debug(ctrl, component, sigs)?
+                .pop()
+                .and_then(|| visitor.finish_debug(ctrl, component, sigs))?,
+            Control::Invoke(data) => visitor
+                .start_invoke(data, component, sigs)?
+                .pop()
+                .and_then(|| visitor.finish_invoke(data, component, sigs))?,
         };
         Ok(res.apply_change(self))
     }
@@ -30,6 +30,8 @@ pub mod analysis;
 pub mod default_passes;
 pub mod errors;
 pub mod frontend;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
 pub mod ir;
 pub mod pass_manager;
 pub mod passes;
@@ -18,11 +18,20 @@ pub struct PassManager {
 
     /// Tracks alias for groups of passes that run together.
     aliases: HashMap<String, Vec<String>>,
+
+    /// Passes that only analyze the program and never change it.
+    analyses: HashSet<String>,
+
+    /// Check the invariants of the IR after every pass.
+    check_ir: bool,
 }
 
 impl PassManager {
     /// Register a new Calyx pass and return an error if another pass with the
     /// same name has already been registered.
+    /// Downstream crates can use this to add their own passes to a
+    /// [PassManager] constructed using [PassManager::default_passes]. See
+    /// `calyx/examples/custom_pass.rs` for a complete example.
     ///
     /// ## Example
     /// ```rust
@@ -49,6 +58,25 @@ impl PassManager {
         Ok(())
     }
 
+    /// Check the structural invariants of the IR after every pass and report
+    /// the first pass that breaks them. See [ir::check::check_context].
+    pub fn enable_ir_checks(&mut self) {
+        self.check_ir = true;
+    }
+
+    /// Register a pass that only analyzes the program, such as a validation
+    /// pass or a lint. Analysis passes can be run by
+    /// [PassManager::execute_analyses].
+    pub fn register_analysis<Pass>(&mut self) -> CalyxResult<()>
+    where
+        Pass:
+            traversal::Visitor + traversal::ConstructVisitor + traversal::Named,
+    {
+        self.register_pass::<Pass>()?;
+        self.analyses.insert(Pass::name().to_string());
+        Ok(())
+    }
+
     /// Adds a new alias for groups of passes. An alias is a list of strings
     /// that represent valid pass names OR an alias.
     /// The passes and aliases are executed in the order of specification.
@@ -159,24 +187,85 @@ impl PassManager {
         excl: &[String],
     ) -> CalyxResult<()> {
         let (passes, excl_set) = self.create_plan(incl, excl)?;
+        // A pass can only be blamed for breaking the invariants of the IR if
+        // they held before it ran.
+        if self.check_ir {
+            ir::check::check_context(ctx).map_err(|err| {
+                Error::MalformedStructure(format!(
+                    "The input program violates the invariants of the IR, so passes cannot be checked. {}",
+                    err
+                ))
+            })?;
+        }
         for name in passes {
             // Pass is known to exist because create_plan validates the
             // names of passes.
             let pass = &self.passes[&name];
             if !excl_set.contains(&name) {
-                pass(ctx)?;
+                ctx.stats.start_pass(&name);
+                let result = pass(ctx);
+                // Comments of the constructs removed by the pass have
+                // nothing left to be printed with.
+                ctx.comments.retain_defined(&ctx.components);
+                // Definitions created by the pass remember its name so that
+                // errors about them can be traced back to it.
+                ir::record_pass(ctx, &name);
+                result.map_err(|err| {
+                    ir::ProvenanceMap::new(&ctx.components).annotate(err)
+                })?;
+                if cfg!(debug_assertions) {
+                    for comp in &ctx.components {
+                        if let Some(port) = comp.dangling_port() {
+                            return Err(Error::Impossible(format!(
+                                "Pass `{}` removed the cell or group of the port `{}` in component `{}` without removing its uses. Call `Component::purge_deleted` after removing cells.",
+                                name, port, comp.name
+                            )));
+                        }
+                    }
+                }
+                if self.check_ir {
+                    ir::check::check_context(ctx).map_err(|err| {
+                        ir::ProvenanceMap::new(&ctx.components).annotate(
+                            Error::MalformedStructure(format!(
+                                "Pass `{}` produced an invalid program. {}",
+                                name, err
+                            )),
+                        )
+                    })?;
+                }
             }
         }
 
         Ok(())
     }
+
+    /// Executes a plan that only contains analysis passes. Returns an error
+    /// without running any pass if the plan contains a pass that may change
+    /// the program.
+    pub fn execute_analyses(
+        &self,
+        ctx: &mut ir::Context,
+        incl: &[String],
+        excl: &[String],
+    ) -> CalyxResult<()> {
+        let (passes, excl_set) = self.create_plan(incl, excl)?;
+        if let Some(pass) = passes.iter().find(|pass| {
+            !excl_set.contains(*pass) && !self.analyses.contains(*pass)
+        }) {
+            return Err(Error::Misc(format!(
+                "Pass `{}` changes the program and cannot be run as an analysis.",
+                pass
+            )));
+        }
+        self.execute_plan(ctx, incl, excl)
+    }
 }
 
 /// Simple macro to register an alias with a pass manager.
 #[macro_export]
 macro_rules! register_alias {
     (@unwrap_name $pass:ident) => {
-        $pass::name().to_string()
+        <$pass as $crate::ir::traversal::Named>::name().to_string()
     };
 
     (@unwrap_name $pass:literal) => {
@@ -185,7 +274,7 @@ macro_rules! register_alias {
 
     ($manager:expr, $alias:literal, [ $($pass:tt),* $(,)? ]) => {
         $manager.add_alias($alias.to_string(), vec![
-            $(register_alias!(@unwrap_name $pass)),*
+            $($crate::register_alias!(@unwrap_name $pass)),*
         ])?;
     };
 }
@@ -0,0 +1,65 @@
+use crate::analysis::AffineAccesses;
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, LibrarySignatures};
+
+#[derive(Default)]
+/// Reports the address of each memory access as an affine expression
+/// `stride*i + base` of a register `i`, along with the innermost `while`
+/// loop that performs the access and how much `i` changes in each of its
+/// iterations.
+///
+/// Accesses that prevent the iterations of their loop from running in
+/// parallel, or the memory from being banked, are marked with the reason:
+/// - the address is not affine;
+/// - the index is not an induction variable of the loop;
+/// - the loop writes to the same constant address in every iteration.
+///
+/// The report is printed to stderr and the program is not changed.
+pub struct AccessPatterns {
+    /// Number of accesses with affine addresses.
+    affine: u64,
+    /// Number of accesses that block parallelization.
+    blocking: u64,
+}
+
+impl Named for AccessPatterns {
+    fn name() -> &'static str {
+        "access-patterns"
+    }
+
+    fn description() -> &'static str {
+        "report the affine access patterns of memories in loops"
+    }
+}
+
+impl Visitor for AccessPatterns {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("affine-accesses", self.affine),
+            ("blocking-accesses", self.blocking),
+        ]
+    }
+
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let analysis = AffineAccesses::from(&*comp);
+        if !analysis.accesses().is_empty() {
+            eprintln!("Access patterns in `{}`:", comp.name);
+        }
+        for access in analysis.accesses() {
+            eprintln!("  {}", access);
+            if access.addr.is_some() {
+                self.affine += 1;
+            }
+            if access.blocker().is_some() {
+                self.blocking += 1;
+            }
+        }
+
+        // This pass doesn't modify any control.
+        Ok(Action::Stop)
+    }
+}
@@ -1,5 +1,5 @@
 use crate::ir::traversal::{Action, Named, VisResult, Visitor};
-use crate::ir::{self, LibrarySignatures};
+use crate::ir::{self, GetAttributes, LibrarySignatures};
 
 #[derive(Default)]
 /// Collapses and de-nests control constructs.
@@ -42,6 +42,23 @@ impl Named for CollapseControl {
     }
 }
 
+/// Replace a `seq` or `par` with a single statement with that statement.
+/// A "static" annotation on the removed node is moved to the statement if the
+/// statement does not have one.
+fn collapse_single(
+    mut stmt: ir::Control,
+    attributes: &ir::Attributes,
+) -> ir::Control {
+    if let (Some(time), Some(attrs)) =
+        (attributes.get("static"), stmt.get_mut_attributes())
+    {
+        if !attrs.has("static") {
+            attrs.insert("static", *time);
+        }
+    }
+    stmt
+}
+
 impl Visitor for CollapseControl {
     /// Collapse seq { seq { A }; B } into seq { A; B }.
     fn finish_seq(
@@ -54,7 +71,8 @@ impl Visitor for CollapseControl {
             return Ok(Action::Change(ir::Control::empty()));
         }
         if s.stmts.len() == 1 {
-            return Ok(Action::Change(s.stmts.pop().unwrap()));
+            let stmt = s.stmts.pop().unwrap();
+            return Ok(Action::Change(collapse_single(stmt, &s.attributes)));
         }
         let mut seqs: Vec<ir::Control> = vec![];
         for con in s.stmts.drain(..) {
@@ -80,7 +98,8 @@ impl Visitor for CollapseControl {
             return Ok(Action::Change(ir::Control::empty()));
         }
         if s.stmts.len() == 1 {
-            return Ok(Action::Change(s.stmts.pop().unwrap()));
+            let stmt = s.stmts.pop().unwrap();
+            return Ok(Action::Change(collapse_single(stmt, &s.attributes)));
         }
         let mut pars: Vec<ir::Control> = vec![];
         for con in s.stmts.drain(..) {
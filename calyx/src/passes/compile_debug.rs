@@ -0,0 +1,91 @@
+use crate::errors::Error;
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, Component, Control, LibrarySignatures};
+use crate::{build_assignments, structure};
+use std::rc::Rc;
+
+#[derive(Default)]
+/// Compiles all [`ir::Debug`](crate::ir::Debug) statements into an
+/// [`ir::Enable`](crate::ir::Enable) and records them in the
+/// [`debug_logs`](crate::ir::Component::debug_logs) of the component so that
+/// backends can print the values.
+///
+/// The statement `@debug("i = {}", i.out);` enables the group:
+/// ```calyx
+/// cells {
+///     debug_en = std_wire(1);
+///     debug_arg = std_wire(32);
+/// }
+///
+/// group debug {
+///     debug_en.in = 1'd1;
+///     debug_arg.in = i.out;
+///     debug[done] = 1'd1;
+/// }
+/// ```
+/// and the values of `debug_arg.out` are printed whenever `debug_en.out` is
+/// high.
+pub struct CompileDebug;
+
+impl Named for CompileDebug {
+    fn name() -> &'static str {
+        "compile-debug"
+    }
+
+    fn description() -> &'static str {
+        "Rewrites @debug statements into groups that drive the printed values"
+    }
+}
+
+impl Visitor for CompileDebug {
+    fn debug(
+        &mut self,
+        s: &mut ir::Debug,
+        comp: &mut Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        if sigs.find_primitive("std_wire").is_none() {
+            return Err(Error::MalformedStructure(format!(
+                "`{}` requires the `std_wire` primitive. Import `primitives/core.futil`.",
+                Self::name()
+            )));
+        }
+
+        let mut builder = ir::Builder::new(comp, sigs);
+        let group = builder.add_group("debug");
+        group.borrow_mut().attributes.insert("static", 1);
+
+        let en = builder.add_primitive("debug_en", "std_wire", &[1]);
+        structure!(builder;
+            let signal_on = constant(1, 1);
+        );
+        let mut assigns: Vec<_> = build_assignments!(builder;
+            en["in"] = ? signal_on["out"];
+        );
+        let mut ports = Vec::with_capacity(s.ports.len());
+        for port in &s.ports {
+            let width = port.borrow().width;
+            let arg = builder.add_primitive("debug_arg", "std_wire", &[width]);
+            assigns.push(builder.build_assignment(
+                arg.borrow().get("in"),
+                Rc::clone(port),
+                ir::Guard::True,
+            ));
+            ports.push(arg.borrow().get("out"));
+        }
+        assigns.extend(build_assignments!(builder;
+            group["done"] = ? signal_on["out"];
+        ));
+        group.borrow_mut().assignments.append(&mut assigns);
+
+        comp.debug_logs.push(ir::DebugLog {
+            enable: en.borrow().get("out"),
+            debug: ir::Debug {
+                format: std::mem::take(&mut s.format),
+                ports,
+            },
+        });
+
+        Ok(Action::Change(Control::enable(group)))
+    }
+}
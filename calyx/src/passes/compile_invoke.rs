@@ -1,10 +1,12 @@
 use crate::errors::Error;
 use crate::ir::traversal::{Action, Named, VisResult, Visitor};
-use crate::ir::{self, Attributes, LibrarySignatures};
+use crate::ir::{self, Attributes, CloneName, LibrarySignatures};
 use crate::structure;
 
 /// Compiles [`ir::Invoke`](crate::ir::Invoke) statements into an [`ir::Enable`](crate::ir::Enable)
 /// that runs the invoked component.
+/// The assignments of the combinational group attached using `with` are
+/// copied into the generated group.
 #[derive(Default)]
 pub struct CompileInvoke;
 
@@ -28,6 +30,10 @@ impl Visitor for CompileInvoke {
         let mut builder = ir::Builder::new(comp, ctx);
 
         let invoke_group = builder.add_group("invoke");
+        invoke_group
+            .borrow_mut()
+            .attributes
+            .derive_from([s.comp.borrow().clone_name()]);
 
         // comp.go = 1'd1;
         // invoke[done] = comp.done;
@@ -35,12 +41,14 @@ impl Visitor for CompileInvoke {
             let one = constant(1, 1);
         );
 
+        // Use the interface named in the invoke or the canonical one.
         let cell = s.comp.borrow();
-        let go_port = cell
-            .find_with_attr("go")
-            .ok_or_else(|| Error::MalformedControl(format!("Invoked component `{}` does not have a port with attribute @go", cell.name())))?;
-        let done_port = cell.find_with_attr("done")
-            .ok_or_else(|| Error::MalformedControl(format!("Invoked component `{}` does not have a port with attribute @done", cell.name())))?;
+        let (go_port, done_port) = cell
+            .find_interface(s.interface.as_ref())
+            .ok_or_else(|| match &s.interface {
+                Some(go) => Error::MalformedControl(format!("Invoked component `{}` does not have an interface with @go port `{}`", cell.name(), go)),
+                None => Error::MalformedControl(format!("Invoked component `{}` does not have ports with attributes @go and @done", cell.name())),
+            })?;
         let go_assign = builder.build_assignment(
             go_port,
             one.borrow().get("out"),
@@ -66,7 +74,15 @@ impl Visitor for CompileInvoke {
                 builder.build_assignment(p, cell.get(out), ir::Guard::True)
             }))
             .chain(enable_assignments.drain(..))
-            .collect();
+            .collect::<Vec<_>>();
+        // The combinational group of an `invoke ... with` is active while the
+        // invoke runs so its assignments become part of the invoke group.
+        let with_assigns = s
+            .comb_group
+            .take()
+            .map(|cg| cg.borrow().assignments.clone())
+            .unwrap_or_default();
+        let assigns = with_assigns.into_iter().chain(assigns).collect();
         invoke_group.borrow_mut().assignments = assigns;
 
         // Copy "static" annotation from the `invoke` statement if present
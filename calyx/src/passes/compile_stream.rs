@@ -0,0 +1,213 @@
+use crate::errors::{CalyxResult, Error};
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, LibrarySignatures, RRC};
+use std::rc::Rc;
+
+/// Name of the primitive that buffers the values sent over a stream.
+const BUFFER: &str = "std_elastic_buffer";
+
+#[derive(Default)]
+/// Compiles connections between stream ports into ready/valid handshakes
+/// through an elastic buffer.
+///
+/// A port marked with `@stream` carries the values of a stream. It must be
+/// paired with a 1-bit `<port>_valid` port in the same direction, which is
+/// high when the port holds a value, and a 1-bit `<port>_ready` port in the
+/// opposite direction, which is high when the receiver accepts the value.
+/// An unguarded continuous assignment between two stream ports, like
+/// `cons.in = prod.out`, connects the streams:
+/// ```calyx
+/// cells {
+///     stream_buf = std_elastic_buffer(32);
+/// }
+/// wires {
+///     stream_buf.in = prod.out;
+///     stream_buf.in_valid = prod.out_valid;
+///     prod.out_ready = stream_buf.in_ready;
+///     cons.in = stream_buf.out;
+///     cons.in_valid = stream_buf.out_valid;
+///     stream_buf.out_ready = cons.in_ready;
+/// }
+/// ```
+/// The buffer holds up to two values so that neither side waits on the
+/// other in the same cycle. The handshake ports of connected streams cannot
+/// be driven by any other assignment and every stream can only be connected
+/// to a single receiver.
+pub struct CompileStream;
+
+impl Named for CompileStream {
+    fn name() -> &'static str {
+        "compile-stream"
+    }
+
+    fn description() -> &'static str {
+        "connects @stream ports using ready/valid handshakes and elastic buffers"
+    }
+}
+
+/// Returns true if `port` carries the values of a stream.
+fn is_stream(port: &RRC<ir::Port>) -> bool {
+    port.borrow().attributes.has("stream")
+}
+
+/// Name of `port` as written in the component that uses it.
+fn port_name(port: &RRC<ir::Port>) -> String {
+    let port = port.borrow();
+    match &port.parent {
+        ir::PortParent::Cell(cell) => {
+            let cell = cell.upgrade();
+            let cell = cell.borrow();
+            match cell.prototype {
+                ir::CellType::ThisComponent => port.name.to_string(),
+                _ => format!("{}.{}", cell.name(), port.name),
+            }
+        }
+        ir::PortParent::Group(group) => {
+            format!("{}[{}]", group.upgrade().borrow().name(), port.name)
+        }
+    }
+}
+
+/// The `valid` and `ready` ports of the stream port `port`.
+fn handshake(
+    port: &RRC<ir::Port>,
+) -> CalyxResult<(RRC<ir::Port>, RRC<ir::Port>)> {
+    let p = port.borrow();
+    let find = |suffix: &str, direction: ir::Direction| {
+        let cell = match &p.parent {
+            ir::PortParent::Cell(cell) => cell.upgrade(),
+            ir::PortParent::Group(_) => return None,
+        };
+        let found = cell.borrow().find(format!("{}_{}", p.name, suffix));
+        found.filter(|hs| {
+            let hs = hs.borrow();
+            hs.width == 1 && hs.direction == direction
+        })
+    };
+    match (
+        find("valid", p.direction.clone()),
+        find("ready", p.direction.reverse()),
+    ) {
+        (Some(valid), Some(ready)) => Ok((valid, ready)),
+        _ => Err(Error::MalformedStructure(format!(
+            "Stream port `{}` requires a 1-bit `{}_valid` port in the same direction and a 1-bit `{}_ready` port in the opposite direction",
+            port_name(port),
+            p.name,
+            p.name
+        ))),
+    }
+}
+
+impl Visitor for CompileStream {
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        for port in comp.signature.borrow().ports.iter() {
+            if is_stream(port) {
+                handshake(port)?;
+            }
+        }
+
+        let connects = |assign: &ir::Assignment| {
+            is_stream(&assign.dst) && is_stream(&assign.src)
+        };
+        for group in comp.groups.iter() {
+            if let Some(assign) =
+                group.borrow().assignments.iter().find(|a| connects(a))
+            {
+                return Err(Error::MalformedStructure(format!(
+                    "Group `{}` connects the stream ports `{}` and `{}`. Streams can only be connected using continuous assignments",
+                    group.borrow().name(),
+                    port_name(&assign.dst),
+                    port_name(&assign.src)
+                )));
+            }
+        }
+        let (streams, rest): (Vec<_>, Vec<_>) = comp
+            .continuous_assignments
+            .drain(..)
+            .partition(|a| connects(a));
+        comp.continuous_assignments = rest;
+        if streams.is_empty() {
+            return Ok(Action::Stop);
+        }
+        if sigs.find_primitive(BUFFER).is_none() {
+            return Err(Error::MalformedStructure(format!(
+                "`{}` requires the `{}` primitive. Import `primitives/stream.futil`.",
+                Self::name(),
+                BUFFER
+            )));
+        }
+
+        // Handshake ports driven by the connections.
+        let mut driven: Vec<RRC<ir::Port>> = vec![];
+        for assign in &streams {
+            if !assign.guard.is_true() {
+                return Err(Error::MalformedStructure(format!(
+                    "The connection of the stream ports `{}` and `{}` cannot be guarded",
+                    port_name(&assign.dst),
+                    port_name(&assign.src)
+                )));
+            }
+            let (_, src_ready) = handshake(&assign.src)?;
+            let (dst_valid, _) = handshake(&assign.dst)?;
+            for port in [&assign.src, &src_ready, &dst_valid] {
+                if driven.iter().any(|p| Rc::ptr_eq(p, port)) {
+                    return Err(Error::MalformedStructure(format!(
+                        "Stream port `{}` is connected to more than one receiver",
+                        port_name(&assign.src)
+                    )));
+                }
+            }
+            driven.extend([Rc::clone(&assign.src), src_ready, dst_valid]);
+        }
+        let assigns = comp
+            .groups
+            .iter()
+            .flat_map(|g| g.borrow().assignments.clone())
+            .chain(
+                comp.comb_groups
+                    .iter()
+                    .flat_map(|g| g.borrow().assignments.clone()),
+            )
+            .chain(comp.continuous_assignments.iter().cloned());
+        for assign in assigns {
+            if driven.iter().any(|p| Rc::ptr_eq(p, &assign.dst)) {
+                return Err(Error::MalformedStructure(format!(
+                    "Handshake port `{}` of a connected stream cannot be driven by other assignments",
+                    port_name(&assign.dst)
+                )));
+            }
+        }
+
+        let mut builder = ir::Builder::new(comp, sigs);
+        let mut assigns = Vec::with_capacity(streams.len() * 6);
+        for stream in streams {
+            let (src_valid, src_ready) = handshake(&stream.src)?;
+            let (dst_valid, dst_ready) = handshake(&stream.dst)?;
+            let width = stream.src.borrow().width;
+            let buf = builder.add_primitive("stream_buf", BUFFER, &[width]);
+            let buf = buf.borrow();
+            let conns = [
+                (buf.get("in"), stream.src),
+                (buf.get("in_valid"), src_valid),
+                (src_ready, buf.get("in_ready")),
+                (stream.dst, buf.get("out")),
+                (dst_valid, buf.get("out_valid")),
+                (buf.get("out_ready"), dst_ready),
+            ];
+            for (dst, src) in conns {
+                assigns.push(builder.build_assignment(
+                    dst,
+                    src,
+                    ir::Guard::True,
+                ));
+            }
+        }
+        comp.continuous_assignments.append(&mut assigns);
+
+        Ok(Action::Stop)
+    }
+}
@@ -0,0 +1,245 @@
+use crate::analysis::GuardDisjointness;
+use crate::ir::{
+    self,
+    traversal::{Action, Named, VisResult, Visitor},
+    CloneName, LibrarySignatures, RRC,
+};
+use std::collections::HashSet;
+
+/// Removes assignments that can never have an effect:
+/// 1. Assignments whose guard is never true. A guard is never true if it
+///    evaluates to false once its constant ports are replaced with their
+///    values, like `1'd0 & x`, or if it is disjoint with itself, like
+///    `fsm.out == 2'd1 & fsm.out == 2'd2`.
+/// 2. Assignments to the ports of cells whose values are never read. The
+///    values of a cell are read if its ports are used by the control
+///    program, or by the source or guard of an assignment to the signature
+///    of the component, to a hole, or to a cell whose values are read.
+///
+/// Instances of components and `@external` cells are always read since
+/// writing to them can have effects outside the component. Assignments to
+/// holes are never removed.
+///
+/// The cells whose assignments are removed are left in place and can be
+/// removed using `dead-cell-removal`.
+#[derive(Default)]
+pub struct DeadAssignmentRemoval {
+    /// Cells whose ports are read by the control program.
+    control_reads: HashSet<ir::Id>,
+    /// Number of assignments that were removed.
+    removed: u64,
+}
+
+impl Named for DeadAssignmentRemoval {
+    fn name() -> &'static str {
+        "dead-assignment-removal"
+    }
+
+    fn description() -> &'static str {
+        "removes assignments with false guards or to cells that are never read"
+    }
+}
+
+/// Value of `port` if it is a constant.
+fn constant(port: &RRC<ir::Port>) -> Option<u64> {
+    if let ir::PortParent::Cell(cell) = &port.borrow().parent {
+        if let ir::CellType::Constant { val, .. } =
+            cell.upgrade().borrow().prototype
+        {
+            return Some(val);
+        }
+    }
+    None
+}
+
+/// Value of `guard` if it only depends on constants.
+fn eval(guard: &ir::Guard) -> Option<bool> {
+    let cmp = |l: &RRC<ir::Port>, r: &RRC<ir::Port>| {
+        constant(l).and_then(|l| constant(r).map(|r| (l, r)))
+    };
+    match guard {
+        ir::Guard::True => Some(true),
+        ir::Guard::Not(g) => eval(g).map(|b| !b),
+        ir::Guard::And(l, r) => match (eval(l), eval(r)) {
+            (Some(false), _) | (_, Some(false)) => Some(false),
+            (Some(true), Some(true)) => Some(true),
+            _ => None,
+        },
+        ir::Guard::Or(l, r) => match (eval(l), eval(r)) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        },
+        ir::Guard::Port(p) => constant(p).map(|val| val != 0),
+        ir::Guard::Eq(l, r) => cmp(l, r).map(|(l, r)| l == r),
+        ir::Guard::Neq(l, r) => cmp(l, r).map(|(l, r)| l != r),
+        ir::Guard::Gt(l, r) => cmp(l, r).map(|(l, r)| l > r),
+        ir::Guard::Lt(l, r) => cmp(l, r).map(|(l, r)| l < r),
+        ir::Guard::Geq(l, r) => cmp(l, r).map(|(l, r)| l >= r),
+        ir::Guard::Leq(l, r) => cmp(l, r).map(|(l, r)| l <= r),
+    }
+}
+
+/// Returns true if `guard` is never true.
+fn never_true(guard: &ir::Guard) -> bool {
+    eval(guard) == Some(false) || GuardDisjointness::disjoint(guard, guard)
+}
+
+/// The cell that `port` belongs to, if it is not a hole or a port of the
+/// signature of the component.
+fn written_cell(port: &RRC<ir::Port>) -> Option<ir::Id> {
+    let port = port.borrow();
+    match &port.parent {
+        ir::PortParent::Cell(cell) => {
+            let cell = cell.upgrade();
+            let cell = cell.borrow();
+            match cell.prototype {
+                ir::CellType::ThisComponent => None,
+                _ => Some(cell.clone_name()),
+            }
+        }
+        ir::PortParent::Group(_) => None,
+    }
+}
+
+impl DeadAssignmentRemoval {
+    /// Cells whose values are read by the control program or, transitively,
+    /// by the assignments in `assigns`.
+    fn read_cells(
+        &self,
+        comp: &ir::Component,
+        assigns: &[ir::Assignment],
+    ) -> HashSet<ir::Id> {
+        let mut read: HashSet<ir::Id> = comp
+            .cells
+            .iter()
+            .filter(|cell| {
+                let cell = cell.borrow();
+                cell.attributes.has("external")
+                    || matches!(cell.prototype, ir::CellType::Component { .. })
+            })
+            .map(|cell| cell.clone_name())
+            .chain(self.control_reads.iter().cloned())
+            .collect();
+        for log in &comp.debug_logs {
+            read.insert(log.enable.borrow().get_parent_name());
+            read.extend(
+                log.debug.ports.iter().map(|p| p.borrow().get_parent_name()),
+            );
+        }
+        loop {
+            let before = read.len();
+            for assign in assigns {
+                let live = written_cell(&assign.dst)
+                    .is_none_or(|cell| read.contains(&cell));
+                if live {
+                    read.insert(assign.src.borrow().get_parent_name());
+                    read.extend(
+                        assign
+                            .guard
+                            .all_ports()
+                            .iter()
+                            .map(|p| p.borrow().get_parent_name()),
+                    );
+                }
+            }
+            if read.len() == before {
+                return read;
+            }
+        }
+    }
+}
+
+impl Visitor for DeadAssignmentRemoval {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![("assignments-removed", self.removed)]
+    }
+
+    fn finish_if(
+        &mut self,
+        s: &mut ir::If,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.control_reads.insert(s.port.borrow().get_parent_name());
+        Ok(Action::Continue)
+    }
+
+    fn finish_while(
+        &mut self,
+        s: &mut ir::While,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.control_reads.insert(s.port.borrow().get_parent_name());
+        Ok(Action::Continue)
+    }
+
+    fn invoke(
+        &mut self,
+        s: &mut ir::Invoke,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.control_reads.insert(s.comp.clone_name());
+        self.control_reads.extend(
+            s.inputs
+                .iter()
+                .map(|(_, port)| port.borrow().get_parent_name()),
+        );
+        Ok(Action::Continue)
+    }
+
+    fn debug(
+        &mut self,
+        s: &mut ir::Debug,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.control_reads
+            .extend(s.ports.iter().map(|port| port.borrow().get_parent_name()));
+        Ok(Action::Continue)
+    }
+
+    fn finish(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let assigns = comp
+            .groups
+            .iter()
+            .flat_map(|g| g.borrow().assignments.clone())
+            .chain(
+                comp.comb_groups
+                    .iter()
+                    .flat_map(|g| g.borrow().assignments.clone()),
+            )
+            .chain(comp.continuous_assignments.iter().cloned())
+            .collect::<Vec<_>>();
+        let read = self.read_cells(comp, &assigns);
+
+        let removed = &mut self.removed;
+        let mut keep = |assign: &ir::Assignment| {
+            let dead = match written_cell(&assign.dst) {
+                Some(cell) => {
+                    !read.contains(&cell) || never_true(&assign.guard)
+                }
+                None => false,
+            };
+            if dead {
+                *removed += 1;
+            }
+            !dead
+        };
+        for group in comp.groups.iter() {
+            group.borrow_mut().assignments.retain(&mut keep);
+        }
+        for group in comp.comb_groups.iter() {
+            group.borrow_mut().assignments.retain(&mut keep);
+        }
+        comp.continuous_assignments.retain(&mut keep);
+
+        Ok(Action::Stop)
+    }
+}
@@ -10,6 +10,8 @@ use std::collections::HashSet;
 #[derive(Default)]
 pub struct DeadCellRemoval {
     used_cells: HashSet<ir::Id>,
+    /// Number of cells that were removed.
+    removed: u64,
 }
 
 impl Named for DeadCellRemoval {
@@ -23,6 +25,10 @@ impl Named for DeadCellRemoval {
 }
 
 impl Visitor for DeadCellRemoval {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![("cells-removed", self.removed)]
+    }
+
     fn invoke(
         &mut self,
         s: &mut ir::Invoke,
@@ -46,6 +52,37 @@ impl Visitor for DeadCellRemoval {
         Ok(Action::Continue)
     }
 
+    fn start_if(
+        &mut self,
+        s: &mut ir::If,
+        _comp: &mut ir::Component,
+        _sigs: &ir::LibrarySignatures,
+    ) -> VisResult {
+        self.used_cells.insert(s.port.borrow().get_parent_name());
+        Ok(Action::Continue)
+    }
+
+    fn start_while(
+        &mut self,
+        s: &mut ir::While,
+        _comp: &mut ir::Component,
+        _sigs: &ir::LibrarySignatures,
+    ) -> VisResult {
+        self.used_cells.insert(s.port.borrow().get_parent_name());
+        Ok(Action::Continue)
+    }
+
+    fn debug(
+        &mut self,
+        s: &mut ir::Debug,
+        _comp: &mut ir::Component,
+        _sigs: &ir::LibrarySignatures,
+    ) -> VisResult {
+        self.used_cells
+            .extend(s.ports.iter().map(|port| port.borrow().get_parent_name()));
+        Ok(Action::Continue)
+    }
+
     fn finish(
         &mut self,
         comp: &mut ir::Component,
@@ -72,11 +109,16 @@ impl Visitor for DeadCellRemoval {
         );
 
         // Remove cells that are not used.
+        let before = comp.cells.len();
         comp.cells.retain(|c| {
             let cell = c.borrow();
             cell.attributes.has("external")
                 || self.used_cells.contains(cell.name())
         });
+        self.removed = (before - comp.cells.len()) as u64;
+        // Drop what still refers to the removed cells, such as the compiled
+        // `@debug` statements that print them.
+        comp.purge_deleted();
 
         Ok(Action::Stop)
     }
@@ -0,0 +1,49 @@
+use crate::analysis::ComponentReachability;
+use crate::errors::CalyxResult;
+use crate::ir::{
+    self,
+    traversal::{ConstructVisitor, Named, Visitor},
+};
+
+/// Removes components that are not transitively instantiated by the
+/// entrypoint of the program.
+///
+/// Generated programs often import large libraries of components and only use
+/// a few of them. Removing the unused components before the other passes run
+/// avoids compiling them.
+#[derive(Default)]
+pub struct DeadComponentRemoval;
+
+impl Named for DeadComponentRemoval {
+    fn name() -> &'static str {
+        "dead-component-removal"
+    }
+
+    fn description() -> &'static str {
+        "removes components not instantiated by the entrypoint"
+    }
+}
+
+impl Visitor for DeadComponentRemoval {
+    /// Works on the whole program instead of traversing each component.
+    fn do_pass(&mut self, context: &mut ir::Context) -> CalyxResult<()>
+    where
+        Self: Sized + ConstructVisitor,
+    {
+        let reachable = ComponentReachability::new(
+            &context.components,
+            &context.entrypoint,
+        );
+        let before = context.components.len();
+        context
+            .components
+            .retain(|comp| reachable.is_reachable(&comp.name));
+        let removed = before - context.components.len();
+        context.stats.record(
+            &context.entrypoint,
+            "components-removed",
+            removed as u64,
+        );
+        Ok(())
+    }
+}
@@ -1,18 +1,32 @@
+use super::unreachable_control::constant_condition;
 use crate::ir::{
     self,
-    traversal::{Action, Named, VisResult, Visitor},
+    traversal::{Action, Named, VisResult, Visitable, Visitor},
     CloneName, LibrarySignatures,
 };
 use std::collections::HashSet;
+use std::mem;
 
 /// Removes unused groups and combinational groups from components.
-/// A group is considered in use when it shows up in an [ir::Enable].
+/// A group is considered in use when it shows up in an [ir::Enable] or when
+/// one of its holes is used by a continuous assignment or by an assignment in
+/// another group that is in use.
 /// A combinational group is considered in use when it is a part of an
-/// [ir::If] or [ir::While].
+/// [ir::If], [ir::While], or [ir::Invoke].
+///
+/// The pass also prunes control statements whose condition is a constant:
+/// `if 1'd1 { A } else { B }` is replaced with `A`, `if 1'd0 { A } else { B }`
+/// is replaced with `B`, and `while 1'd0 { A }` is replaced with `empty`.
+/// Conditions that read a `std_const` or a `std_wire` only driven by a
+/// constant are treated the same way.
+/// Groups and combinational groups only used by the pruned statements are
+/// then removed.
 #[derive(Default)]
 pub struct DeadGroupRemoval {
     used_groups: HashSet<ir::Id>,
     used_comb_groups: HashSet<ir::Id>,
+    /// Number of groups and combinational groups that were removed.
+    removed: u64,
 }
 
 impl Named for DeadGroupRemoval {
@@ -21,11 +35,45 @@ impl Named for DeadGroupRemoval {
     }
 
     fn description() -> &'static str {
-        "removes unsed groups from components"
+        "removes unused groups and constant control branches from components"
+    }
+}
+
+impl DeadGroupRemoval {
+    /// Mark every group whose hole is used by `assigns` as used.
+    /// Uses of the holes of `owner` do not count. Returns true if a group
+    /// was newly marked.
+    fn mark_hole_uses(
+        &mut self,
+        assigns: &[ir::Assignment],
+        owner: Option<&ir::Id>,
+    ) -> bool {
+        let mut changed = false;
+        for assign in assigns {
+            let ports = assign
+                .guard
+                .all_ports()
+                .into_iter()
+                .chain(vec![assign.dst.clone(), assign.src.clone()]);
+            for port in ports {
+                let port = port.borrow();
+                if port.is_hole() {
+                    let group = port.get_parent_name();
+                    if Some(&group) != owner {
+                        changed |= self.used_groups.insert(group);
+                    }
+                }
+            }
+        }
+        changed
     }
 }
 
 impl Visitor for DeadGroupRemoval {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![("groups-removed", self.removed)]
+    }
+
     fn enable(
         &mut self,
         s: &mut ir::Enable,
@@ -36,6 +84,40 @@ impl Visitor for DeadGroupRemoval {
         Ok(Action::Continue)
     }
 
+    fn invoke(
+        &mut self,
+        s: &mut ir::Invoke,
+        _comp: &mut ir::Component,
+        _sigs: &ir::LibrarySignatures,
+    ) -> VisResult {
+        if let Some(cg) = &s.comb_group {
+            self.used_comb_groups.insert(cg.borrow().clone_name());
+        }
+        Ok(Action::Continue)
+    }
+
+    /// Replace an `if` with a constant condition with the branch that is
+    /// always taken. The other branch is never visited so groups only used by
+    /// it are removed.
+    fn start_if(
+        &mut self,
+        s: &mut ir::If,
+        comp: &mut ir::Component,
+        sigs: &ir::LibrarySignatures,
+    ) -> VisResult {
+        if let Some(val) = constant_condition(comp, &s.port, &s.cond) {
+            let branch = if val != 0 {
+                &mut s.tbranch
+            } else {
+                &mut s.fbranch
+            };
+            let mut live = mem::replace(branch, Box::new(ir::Control::empty()));
+            live.visit(self, comp, sigs)?;
+            return Ok(Action::Change(*live));
+        }
+        Ok(Action::Continue)
+    }
+
     fn finish_if(
         &mut self,
         s: &mut ir::If,
@@ -48,6 +130,19 @@ impl Visitor for DeadGroupRemoval {
         Ok(Action::Continue)
     }
 
+    /// Remove `while` loops whose condition is always false.
+    fn start_while(
+        &mut self,
+        s: &mut ir::While,
+        comp: &mut ir::Component,
+        _sigs: &ir::LibrarySignatures,
+    ) -> VisResult {
+        if let Some(0) = constant_condition(comp, &s.port, &s.cond) {
+            return Ok(Action::Change(ir::Control::empty()));
+        }
+        Ok(Action::Continue)
+    }
+
     fn finish_while(
         &mut self,
         s: &mut ir::While,
@@ -65,22 +160,36 @@ impl Visitor for DeadGroupRemoval {
         comp: &mut ir::Component,
         _sigs: &LibrarySignatures,
     ) -> VisResult {
-        // Groups that are driven by their `go` signals should not be
-        // removed.
-        for group in comp.groups.iter() {
-            for assign in &group.borrow().assignments {
-                let dst = assign.dst.borrow();
-                if dst.is_hole() && dst.name == "go" {
-                    self.used_groups.insert(dst.get_parent_name().clone());
+        // Groups whose holes are driven or read by continuous assignments or
+        // by groups in use should not be removed. Marking a group can make
+        // the groups whose holes it uses live, so iterate to a fixpoint.
+        self.mark_hole_uses(&comp.continuous_assignments, None);
+        for cg in comp.comb_groups.iter() {
+            let cg = cg.borrow();
+            if self.used_comb_groups.contains(cg.name()) {
+                self.mark_hole_uses(&cg.assignments, None);
+            }
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for group in comp.groups.iter() {
+                let group = group.borrow();
+                if self.used_groups.contains(group.name()) {
+                    changed |= self
+                        .mark_hole_uses(&group.assignments, Some(group.name()));
                 }
             }
         }
 
         // Remove Groups that are not used
+        let before = comp.groups.len() + comp.comb_groups.len();
         comp.groups
             .retain(|g| self.used_groups.contains(g.borrow().name()));
         comp.comb_groups
             .retain(|cg| self.used_comb_groups.contains(cg.borrow().name()));
+        self.removed =
+            (before - comp.groups.len() - comp.comb_groups.len()) as u64;
 
         Ok(Action::Stop)
     }
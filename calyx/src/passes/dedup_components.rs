@@ -0,0 +1,113 @@
+use crate::errors::CalyxResult;
+use crate::ir::{
+    self,
+    traversal::{ConstructVisitor, Named, Visitor},
+};
+use itertools::Itertools;
+use std::collections::{hash_map::Entry, HashMap};
+
+/// Merges components that are structurally identical.
+///
+/// Frontends that monomorphize their designs often generate many copies of
+/// the same component under different names. Two components are identical
+/// if they only differ in their names and comments. The first of them is
+/// kept and the cells instantiating the others are changed to instantiate
+/// it. Since merging components can make the components that instantiate
+/// them identical, the pass runs until no more components are merged.
+///
+/// The entrypoint of the program is never merged.
+#[derive(Default)]
+pub struct DedupComponents;
+
+impl Named for DedupComponents {
+    fn name() -> &'static str {
+        "dedup-components"
+    }
+
+    fn description() -> &'static str {
+        "merges structurally identical components"
+    }
+}
+
+impl DedupComponents {
+    /// The program text of `comp` without its name and comments. Identical
+    /// components have the same key.
+    fn key(comp: &ir::Component) -> CalyxResult<String> {
+        let mut buf = vec![];
+        ir::IRPrinter::write_component(comp, &mut buf)?;
+        let header = format!("component {}", comp.name);
+        let key = std::str::from_utf8(&buf)?
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("//"))
+            .enumerate()
+            .map(|(idx, line)| {
+                if idx == 0 {
+                    line.replacen(&header, "component", 1)
+                } else {
+                    line.to_string()
+                }
+            })
+            .join("\n");
+        Ok(key)
+    }
+
+    /// Maps every component that is identical to an earlier one to the name
+    /// of the earlier component.
+    fn duplicates(
+        context: &ir::Context,
+    ) -> CalyxResult<HashMap<ir::Id, ir::Id>> {
+        let mut kept: HashMap<String, ir::Id> = HashMap::new();
+        let mut merged = HashMap::new();
+        for comp in &context.components {
+            if comp.name == context.entrypoint {
+                continue;
+            }
+            match kept.entry(Self::key(comp)?) {
+                Entry::Occupied(first) => {
+                    merged.insert(comp.name.clone(), first.get().clone());
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(comp.name.clone());
+                }
+            }
+        }
+        Ok(merged)
+    }
+}
+
+impl Visitor for DedupComponents {
+    /// Works on the whole program instead of traversing each component.
+    fn do_pass(&mut self, context: &mut ir::Context) -> CalyxResult<()>
+    where
+        Self: Sized + ConstructVisitor,
+    {
+        let before = context.components.len();
+        loop {
+            let merged = Self::duplicates(context)?;
+            if merged.is_empty() {
+                break;
+            }
+            context
+                .components
+                .retain(|comp| !merged.contains_key(&comp.name));
+            for comp in &context.components {
+                for cell in comp.cells.iter() {
+                    if let ir::CellType::Component { name } =
+                        &mut cell.borrow_mut().prototype
+                    {
+                        if let Some(first) = merged.get(name) {
+                            *name = first.clone();
+                        }
+                    }
+                }
+            }
+        }
+        let removed = before - context.components.len();
+        context.stats.record(
+            &context.entrypoint,
+            "components-merged",
+            removed as u64,
+        );
+        Ok(())
+    }
+}
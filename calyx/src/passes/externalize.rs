@@ -1,5 +1,7 @@
-use crate::ir::traversal::{Action, Named, VisResult, Visitor};
-use crate::ir::{self, CloneName, LibrarySignatures, WRC};
+use crate::errors::CalyxResult;
+use crate::ir::traversal::{ConstructVisitor, Named, Visitor};
+use crate::ir::{self, CloneName, RRC, WRC};
+use std::rc::Rc;
 
 #[derive(Default)]
 /// Externalize input/output ports for cells marked with the `@external(1)` attribute.
@@ -52,45 +54,95 @@ fn format_port_name(comp: &ir::Id, port: &ir::Id) -> ir::Id {
     format!("{}_{}", comp.id, port.id).into()
 }
 
-impl Visitor for Externalize {
-    fn start(
-        &mut self,
-        comp: &mut ir::Component,
-        _ctx: &LibrarySignatures,
-    ) -> VisResult {
-        // Extract external cells.
-        let (ext_cells, cells): (Vec<_>, Vec<_>) =
-            comp.cells.drain().partition(|cr| {
-                let cell = cr.borrow();
-                cell.get_attribute("external") == Some(&1)
-            });
+/// Externalize the `@external(1)` cells of the component `comp_name`. Returns
+/// the name of each externalized cell and port along with the port added to
+/// the signature of the component.
+pub(super) fn externalize_cells(
+    context: &mut ir::Context,
+    comp_name: &ir::Id,
+) -> CalyxResult<Vec<(ir::Id, ir::Id, RRC<ir::Port>)>> {
+    let comp = context
+        .components
+        .iter_mut()
+        .find(|comp| comp.name == comp_name)
+        .unwrap();
+    // Extract external cells.
+    let (ext_cells, cells): (Vec<_>, Vec<_>) =
+        comp.cells.drain().partition(|cr| {
+            let cell = cr.borrow();
+            cell.get_attribute("external") == Some(&1)
+        });
 
-        // Re-add non-external cells.
-        comp.cells.extend(cells);
+    // Re-add non-external cells.
+    comp.cells.extend(cells);
 
-        // Detach the port from the component's cell and attach it to the
-        // component's signature.
-        // By doing this, we don't need to change the assignments since they
-        // refer to this port. All we have done is change the port's parent
-        // which automatically changes the assignments.
-        for cell_ref in ext_cells {
-            let mut cell = cell_ref.borrow_mut();
-            let name = cell.clone_name();
-            for port_ref in cell.ports.drain(..) {
-                let port_name = port_ref.borrow().name.clone();
-                // Change the name and the parent of this port.
-                port_ref.borrow_mut().name =
-                    format_port_name(&name, &port_name);
-                // Point to the signature cell as its parent
-                port_ref.borrow_mut().parent =
-                    ir::PortParent::Cell(WRC::from(&comp.signature));
-                // Remove any attributes from this cell port.
-                port_ref.borrow_mut().attributes = ir::Attributes::default();
-                comp.signature.borrow_mut().ports.push(port_ref);
+    let mut externalized = vec![];
+    for cell_ref in ext_cells {
+        let mut cell = cell_ref.borrow_mut();
+        let name = cell.clone_name();
+        for port_ref in cell.ports.drain(..) {
+            let (orig_name, width, direction) = {
+                let port = port_ref.borrow();
+                (port.name.clone(), port.width, port.direction.clone())
+            };
+            // Avoid ports of the component that already use the name.
+            let port_name = context
+                .components
+                .iter_mut()
+                .find(|comp| comp.name == comp_name)
+                .unwrap()
+                .generate_name(format_port_name(&name, &orig_name));
+            // The port of the cell is an input of the component if
+            // it is an output of the cell.
+            let sig_port = context.add_port(
+                comp_name,
+                port_name.clone(),
+                width,
+                direction.reverse(),
+                ir::Attributes::default(),
+            )?;
+            // Reuse the port of the cell in the signature. By doing
+            // this, we don't need to change the assignments since
+            // they refer to this port.
+            let comp = context
+                .components
+                .iter()
+                .find(|comp| comp.name == comp_name)
+                .unwrap();
+            {
+                let mut port = port_ref.borrow_mut();
+                port.name = port_name;
+                port.parent = ir::PortParent::Cell(WRC::from(&comp.signature));
+                port.attributes = ir::Attributes::default();
             }
+            let mut sig = comp.signature.borrow_mut();
+            let idx = sig
+                .ports
+                .iter()
+                .position(|p| Rc::ptr_eq(p, &sig_port))
+                .unwrap();
+            sig.ports[idx] = Rc::clone(&port_ref);
+            externalized.push((name.clone(), orig_name, port_ref));
         }
+    }
+    Ok(externalized)
+}
 
-        // Stop traversal, we don't need to traverse over control ast
-        Ok(Action::Stop)
+impl Visitor for Externalize {
+    /// Works on the whole program so that the cells that instantiate a
+    /// component get the new ports of its signature.
+    fn do_pass(&mut self, context: &mut ir::Context) -> CalyxResult<()>
+    where
+        Self: Sized + ConstructVisitor,
+    {
+        let names = context
+            .components
+            .iter()
+            .map(|comp| comp.name.clone())
+            .collect::<Vec<_>>();
+        for comp_name in names {
+            externalize_cells(context, &comp_name)?;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,68 @@
+use crate::analysis::{Encoding, FsmStates};
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, LibrarySignatures};
+
+#[derive(Default)]
+/// Reports the finite-state machines of each component after control
+/// compilation, along with their number of states, the encoding of the
+/// states, and the width of their state register. Components whose control
+/// logic dominates their area have many or wide FSMs.
+///
+/// The report is printed to stderr and the program is not changed. The
+/// statistics count the FSMs of each component, the total number of states,
+/// the width of the widest state register, and the number of FSMs that use
+/// a one-hot encoding.
+pub struct FsmReport {
+    /// Number of FSMs.
+    fsms: u64,
+    /// Total number of states.
+    states: u64,
+    /// Width of the widest state register.
+    widest: u64,
+    /// Number of FSMs with one-hot encoded states.
+    one_hot: u64,
+}
+
+impl Named for FsmReport {
+    fn name() -> &'static str {
+        "fsm-report"
+    }
+
+    fn description() -> &'static str {
+        "report the states and encoding of the FSMs of each component"
+    }
+}
+
+impl Visitor for FsmReport {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("fsms", self.fsms),
+            ("fsm-states", self.states),
+            ("widest-fsm", self.widest),
+            ("one-hot-fsms", self.one_hot),
+        ]
+    }
+
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let analysis = FsmStates::from(&*comp);
+        if !analysis.fsms().is_empty() {
+            eprintln!("FSMs in `{}`:", comp.name);
+        }
+        for fsm in analysis.fsms() {
+            eprintln!("  {}", fsm);
+            self.fsms += 1;
+            self.states += fsm.states;
+            self.widest = self.widest.max(fsm.width);
+            if fsm.encoding == Encoding::OneHot {
+                self.one_hot += 1;
+            }
+        }
+
+        // This pass doesn't modify any control.
+        Ok(Action::Stop)
+    }
+}
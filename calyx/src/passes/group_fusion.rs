@@ -0,0 +1,219 @@
+use super::math_utilities::get_bit_width_from;
+use crate::analysis::ReadWriteSet;
+use crate::ir::{
+    self,
+    traversal::{Action, Named, VisResult, Visitor},
+    CloneName, LibrarySignatures, RRC,
+};
+use crate::{build_assignments, guard, structure};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Fuses consecutive static groups in a `seq` into a single group.
+///
+/// A run of two or more consecutive group enables in a `seq` is fused when:
+/// 1. Every group has a `static` attribute of at least one cycle.
+/// 2. No group reads or writes a hole other than its own `done` hole.
+/// 3. No cell is written to by more than one group in the run.
+///
+/// The fused group uses a counter to activate the assignments of each group
+/// for exactly `static` cycles and is done once all the groups have run.
+/// The `done` conditions of the original groups are not used.
+///
+/// # Example
+/// ```calyx
+/// seq { @static(1) incr_x; @static(1) incr_y; }
+/// ```
+/// becomes
+/// ```calyx
+/// seq { @static(2) fused; }
+/// ```
+/// where `fused` runs the assignments of `incr_x` when its counter is `0` and
+/// the assignments of `incr_y` when its counter is `1`. Fusing removes the
+/// cycle needed by the control FSM to transition between the groups.
+#[derive(Default)]
+pub struct GroupFusion {
+    /// Number of groups that were fused.
+    fused: u64,
+}
+
+impl Named for GroupFusion {
+    fn name() -> &'static str {
+        "group-fusion"
+    }
+
+    fn description() -> &'static str {
+        "fuse consecutive static groups in a seq into a single group"
+    }
+}
+
+/// Returns the latency of the group if it is static and does not use any
+/// holes other than its own `done` hole. The assignments of such a group can
+/// be moved into another group that runs them for `static` cycles.
+pub(super) fn static_latency(group: &RRC<ir::Group>) -> Option<u64> {
+    let group = group.borrow();
+    let latency = *group.attributes.get("static").filter(|l| **l > 0)?;
+    let is_hole = |port: &RRC<ir::Port>| port.borrow().is_hole();
+    let uses_holes = group.assignments.iter().any(|assign| {
+        let own_done = is_hole(&assign.dst)
+            && assign.dst.borrow().get_parent_name() == group.name()
+            && assign.dst.borrow().name == "done";
+        (is_hole(&assign.dst) && !own_done)
+            || is_hole(&assign.src)
+            || assign.guard.all_ports().iter().any(is_hole)
+    });
+    if uses_holes {
+        None
+    } else {
+        Some(latency)
+    }
+}
+
+/// Build a group that runs each of the `groups` in sequence.
+fn fuse(
+    groups: &[(RRC<ir::Group>, u64)],
+    builder: &mut ir::Builder,
+) -> RRC<ir::Group> {
+    let total: u64 = groups.iter().map(|(_, latency)| latency).sum();
+    let fsm_size = get_bit_width_from(total + 1);
+    let fused = builder.add_group("fused");
+    fused
+        .borrow_mut()
+        .attributes
+        .derive_from(groups.iter().map(|(group, _)| group.clone_name()));
+
+    structure!(builder;
+        let fsm = prim std_reg(fsm_size);
+        let signal_on = constant(1, 1);
+        let incr = prim std_add(fsm_size);
+        let one = constant(1, fsm_size);
+        let last = constant(total, fsm_size);
+        let reset_val = constant(0, fsm_size);
+    );
+
+    let mut cur_cycle = 0;
+    for (group, latency) in groups {
+        structure!(builder;
+            let start_st = constant(cur_cycle, fsm_size);
+            let end_st = constant(cur_cycle + latency, fsm_size);
+        );
+        // Do not generate `fsm.out >= 0` since it is always true.
+        let active = if *latency == 1 {
+            guard!(fsm["out"]).eq(guard!(start_st["out"]))
+        } else if cur_cycle == 0 {
+            guard!(fsm["out"]).lt(guard!(end_st["out"]))
+        } else {
+            guard!(fsm["out"]).ge(guard!(start_st["out"]))
+                & guard!(fsm["out"]).lt(guard!(end_st["out"]))
+        };
+
+        let group = group.borrow();
+        let mut assigns = group
+            .assignments
+            .iter()
+            .filter(|assign| !assign.dst.borrow().is_hole())
+            .map(|assign| {
+                let mut assign = assign.clone();
+                assign.guard = Box::new(active.clone().and(*assign.guard));
+                assign
+            })
+            .collect();
+        fused.borrow_mut().assignments.append(&mut assigns);
+        cur_cycle += latency;
+    }
+
+    let done_guard = guard!(fsm["out"]).eq(guard!(last["out"]));
+    let not_done_guard = !done_guard.clone();
+    let mut assigns = build_assignments!(builder;
+        incr["left"] = ? one["out"];
+        incr["right"] = ? fsm["out"];
+        fsm["in"] = not_done_guard ? incr["out"];
+        fsm["write_en"] = not_done_guard ? signal_on["out"];
+        fused["done"] = done_guard ? signal_on["out"];
+    );
+    fused.borrow_mut().assignments.append(&mut assigns);
+    fused.borrow_mut().attributes.insert("static", total);
+
+    // CLEANUP: Reset the counter once the group is done.
+    let mut cleanup = build_assignments!(builder;
+        fsm["in"] = done_guard ? reset_val["out"];
+        fsm["write_en"] = done_guard ? signal_on["out"];
+    );
+    builder
+        .component
+        .continuous_assignments
+        .append(&mut cleanup);
+
+    fused
+}
+
+/// A statement in a `seq` along with the group it enables, if the group can
+/// be fused.
+type Stmt = (ir::Control, Option<(RRC<ir::Group>, u64)>);
+
+impl Visitor for GroupFusion {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![("groups-fused", self.fused)]
+    }
+
+    fn finish_seq(
+        &mut self,
+        s: &mut ir::Seq,
+        comp: &mut ir::Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        // Split the statements into runs of groups that can be fused.
+        let mut runs: Vec<Vec<Stmt>> = vec![];
+        let mut written: HashSet<ir::Id> = HashSet::new();
+        for stmt in s.stmts.drain(..) {
+            let fused = match &stmt {
+                ir::Control::Enable(ir::Enable { group, .. }) => {
+                    static_latency(group)
+                        .map(|latency| (Rc::clone(group), latency))
+                }
+                _ => None,
+            };
+            let writes = match &fused {
+                Some((group, _)) => {
+                    ReadWriteSet::write_set(&group.borrow().assignments)
+                        .map(|cell| cell.clone_name())
+                        .collect::<HashSet<_>>()
+                }
+                None => HashSet::new(),
+            };
+            let extends_run = fused.is_some()
+                && matches!(runs.last(), Some(run) if run[0].1.is_some())
+                && written.is_disjoint(&writes);
+            if extends_run {
+                runs.last_mut().unwrap().push((stmt, fused));
+                written.extend(writes);
+            } else {
+                runs.push(vec![(stmt, fused)]);
+                written = writes;
+            }
+        }
+
+        // Replace each run of two or more groups with a fused group.
+        let mut builder = ir::Builder::new(comp, sigs);
+        s.stmts = runs
+            .into_iter()
+            .map(|mut run| {
+                if run.len() == 1 {
+                    run.pop().unwrap().0
+                } else {
+                    let groups = run
+                        .into_iter()
+                        .map(|(_, fused)| fused.unwrap())
+                        .collect::<Vec<_>>();
+                    self.fused += groups.len() as u64;
+                    ir::Control::enable(fuse(&groups, &mut builder))
+                }
+            })
+            .collect();
+
+        if s.stmts.len() == 1 && matches!(s.stmts[0], ir::Control::Enable(_)) {
+            return Ok(Action::Change(s.stmts.pop().unwrap()));
+        }
+        Ok(Action::Continue)
+    }
+}
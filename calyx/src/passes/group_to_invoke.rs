@@ -38,6 +38,7 @@ fn construct_invoke(
 ) -> ir::Control {
     let mut inputs = Vec::new();
     let mut outputs = Vec::new();
+    let (go_port, done_port) = comp.borrow().find_interface(None).unwrap();
 
     let cell_is_parent = |port: &ir::Port| -> bool {
         if let ir::PortParent::Cell(cell_wref) = &port.parent {
@@ -50,17 +51,13 @@ fn construct_invoke(
     for assign in assigns {
         // If the cell's port is being used as a source, add the dst to
         // outputs
-        if cell_is_parent(&assign.src.borrow())
-            && assign.src != comp.borrow().get_with_attr("done")
-        {
+        if cell_is_parent(&assign.src.borrow()) && assign.src != done_port {
             let name = assign.src.borrow().name.clone();
             outputs.push((name, Rc::clone(&assign.dst)));
         }
         // If the cell's port is being used as a dest, add the source to
         // inputs
-        if cell_is_parent(&assign.dst.borrow())
-            && assign.dst != comp.borrow().get_with_attr("go")
-        {
+        if cell_is_parent(&assign.dst.borrow()) && assign.dst != go_port {
             let name = assign.dst.borrow().name.clone();
             inputs.push((name, Rc::clone(&assign.src)));
         }
@@ -88,15 +85,12 @@ impl Visitor for GroupToInvoke {
 
         // Component must define a @go/@done interface
         let cell = writes.pop().unwrap();
-        let maybe_go_port = cell.borrow().find_with_attr("go");
-        let maybe_done_port = cell.borrow().find_with_attr("done");
-        if maybe_go_port.is_none() || maybe_done_port.is_none() {
-            return Ok(Action::Continue);
-        }
-
-        let go_port = maybe_go_port.unwrap();
+        let maybe_interface = cell.borrow().find_interface(None);
+        let (go_port, done_port) = match maybe_interface {
+            Some(interface) => interface,
+            None => return Ok(Action::Continue),
+        };
         let mut go_multi_write = false;
-        let done_port = maybe_done_port.unwrap();
         let mut done_multi_write = false;
         for assign in &group.assignments {
             // All assignments should be unguaraded.
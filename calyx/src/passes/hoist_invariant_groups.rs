@@ -0,0 +1,364 @@
+use crate::analysis::{CfgNode, ControlFlowGraph, ReadWriteSet};
+use crate::ir::{
+    self,
+    traversal::{Action, Named, VisResult, Visitor},
+    CloneName, GetAttributes, LibrarySignatures, RRC,
+};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// Hoists loop-invariant group enables out of `while` loops.
+///
+/// A group enabled in the body of a `while` loop is hoisted before the loop
+/// when:
+/// 1. It is enabled exactly once in the body and runs in every iteration.
+/// 2. None of the stateful cells it reads are written in the loop.
+/// 3. None of the stateful cells it writes are read or written by anything
+///    else in the loop.
+///
+/// Since the hoisted group always runs once, loops are only transformed when
+/// their `@bound` attribute guarantees that the body runs at least once.
+/// Loops that contain `invoke` statements are not transformed.
+///
+/// # Example
+/// ```calyx
+/// @bound(4) while lt.out with cond {
+///   seq { init_x; incr_i; }
+/// }
+/// ```
+/// where `init_x` writes a constant into the register `x` which is not used
+/// in the loop becomes:
+/// ```calyx
+/// seq {
+///   init_x;
+///   @bound(4) while lt.out with cond { seq { incr_i; } }
+/// }
+/// ```
+///
+/// The `static` attributes of the transformed loops, the statements that
+/// contain them, and the component are removed since their latency changed.
+#[derive(Default)]
+pub struct HoistInvariantGroups {
+    /// Number of loops transformed in the component so far.
+    hoisted: usize,
+    /// Value of `hoisted` when each enclosing control statement was entered.
+    entered: Vec<usize>,
+}
+
+impl Named for HoistInvariantGroups {
+    fn name() -> &'static str {
+        "hoist-invariant-groups"
+    }
+
+    fn description() -> &'static str {
+        "hoist loop-invariant group enables out of while loops"
+    }
+}
+
+/// Stateful cells read and written by a set of assignments.
+#[derive(Default)]
+struct Uses {
+    reads: HashSet<ir::Id>,
+    writes: HashSet<ir::Id>,
+}
+
+/// Returns true if the cell keeps state across cycles or is visible outside
+/// the component. Values on combinational cells are recomputed by each group
+/// that uses them.
+fn is_stateful(cell: &RRC<ir::Cell>) -> bool {
+    !matches!(
+        cell.borrow().prototype,
+        ir::CellType::Constant { .. }
+            | ir::CellType::Primitive { is_comb: true, .. }
+    )
+}
+
+/// The cell that `port` belongs to.
+fn parent_cell(port: &RRC<ir::Port>) -> Option<RRC<ir::Cell>> {
+    match &port.borrow().parent {
+        ir::PortParent::Cell(cell) => Some(cell.upgrade()),
+        ir::PortParent::Group(_) => None,
+    }
+}
+
+impl Uses {
+    fn from_assigns(assigns: &[ir::Assignment]) -> Self {
+        // Reading the done signal of a cell only tells the group when it is
+        // finished.
+        let reads = ReadWriteSet::port_read_set(assigns)
+            .filter(|port| !port.borrow().attributes.has("done"))
+            .filter_map(|port| parent_cell(&port))
+            .filter(is_stateful)
+            .map(|cell| cell.clone_name())
+            .collect();
+        let writes = ReadWriteSet::write_set(assigns)
+            .filter(is_stateful)
+            .map(|cell| cell.clone_name())
+            .collect();
+        Uses { reads, writes }
+    }
+
+    fn extend(&mut self, other: &Uses) {
+        self.reads.extend(other.reads.iter().cloned());
+        self.writes.extend(other.writes.iter().cloned());
+    }
+
+    /// Returns true if `self` does not observe or overwrite anything `other`
+    /// writes and vice versa.
+    fn independent(&self, other: &Uses) -> bool {
+        self.reads.is_disjoint(&other.writes)
+            && self.writes.is_disjoint(&other.reads)
+            && self.writes.is_disjoint(&other.writes)
+    }
+}
+
+/// Uses of a condition port and its combinational group.
+fn cond_uses(port: &RRC<ir::Port>, cond: &Option<RRC<ir::CombGroup>>) -> Uses {
+    let mut uses = cond
+        .as_ref()
+        .map(|cg| Uses::from_assigns(&cg.borrow().assignments))
+        .unwrap_or_default();
+    if let Some(cell) = parent_cell(port).filter(is_stateful) {
+        uses.reads.insert(cell.clone_name());
+    }
+    uses
+}
+
+/// Remove the enables of groups in `hoisted` from `con`. Returns true if
+/// an enable was removed, in which case the `static` attributes of the
+/// statements that contained it are removed since their latency changed.
+fn remove_enables(con: &mut ir::Control, hoisted: &HashSet<ir::Id>) -> bool {
+    let is_hoisted = |con: &ir::Control| match con {
+        ir::Control::Enable(ir::Enable { group, .. }) => {
+            hoisted.contains(group.borrow().name())
+        }
+        _ => false,
+    };
+    let changed = match con {
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => {
+            let len = stmts.len();
+            stmts.retain(|stmt| !is_hoisted(stmt));
+            let removed = stmts.len() != len;
+            stmts.iter_mut().fold(removed, |changed, stmt| {
+                remove_enables(stmt, hoisted) || changed
+            })
+        }
+        ir::Control::If(ir::If {
+            tbranch, fbranch, ..
+        }) => {
+            let changed = remove_enables(tbranch, hoisted);
+            remove_enables(fbranch, hoisted) || changed
+        }
+        ir::Control::While(ir::While { body, .. }) => {
+            remove_enables(body, hoisted)
+        }
+        ir::Control::Enable(_) => {
+            let hoist = is_hoisted(con);
+            if hoist {
+                *con = ir::Control::empty();
+            }
+            hoist
+        }
+        ir::Control::Invoke(_)
+        | ir::Control::Empty(_)
+        | ir::Control::Debug(_) => false,
+    };
+    if changed {
+        if let Some(attrs) = con.get_mut_attributes() {
+            attrs.remove("static");
+        }
+    }
+    changed
+}
+
+impl HoistInvariantGroups {
+    /// Record that the traversal entered a control statement.
+    fn enter(&mut self) -> VisResult {
+        self.entered.push(self.hoisted);
+        Ok(Action::Continue)
+    }
+
+    /// Record that the traversal left a control statement with the
+    /// attributes `attrs`. If a loop in the statement was transformed, the
+    /// latency of the statement changed and its `static` attribute is
+    /// removed.
+    fn leave(&mut self, attrs: &mut ir::Attributes) {
+        if self.entered.pop() != Some(self.hoisted) {
+            attrs.remove("static");
+        }
+    }
+}
+
+impl Visitor for HoistInvariantGroups {
+    fn finish(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        if self.hoisted > 0 {
+            comp.attributes.remove("static");
+        }
+        Ok(Action::Continue)
+    }
+
+    fn start_seq(
+        &mut self,
+        _s: &mut ir::Seq,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.enter()
+    }
+
+    fn finish_seq(
+        &mut self,
+        s: &mut ir::Seq,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.leave(&mut s.attributes);
+        Ok(Action::Continue)
+    }
+
+    fn start_par(
+        &mut self,
+        _s: &mut ir::Par,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.enter()
+    }
+
+    fn finish_par(
+        &mut self,
+        s: &mut ir::Par,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.leave(&mut s.attributes);
+        Ok(Action::Continue)
+    }
+
+    fn start_if(
+        &mut self,
+        _s: &mut ir::If,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.enter()
+    }
+
+    fn finish_if(
+        &mut self,
+        s: &mut ir::If,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.leave(&mut s.attributes);
+        Ok(Action::Continue)
+    }
+
+    fn start_while(
+        &mut self,
+        _s: &mut ir::While,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.enter()
+    }
+
+    fn finish_while(
+        &mut self,
+        s: &mut ir::While,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.leave(&mut s.attributes);
+        if !matches!(s.attributes.get("bound"), Some(bound) if *bound > 0) {
+            return Ok(Action::Continue);
+        }
+
+        let cfg = ControlFlowGraph::from(&*s.body);
+        let dominators = cfg.dominators();
+
+        // Cells used by everything in the loop other than group enables.
+        let mut loop_uses = cond_uses(&s.port, &s.cond);
+        loop_uses.extend(&Uses::from_assigns(&comp.continuous_assignments));
+
+        let mut enables: HashMap<ir::Id, Vec<_>> = HashMap::new();
+        let mut groups = vec![];
+        for idx in cfg.nodes() {
+            match cfg.node(idx) {
+                CfgNode::Enable(group) => {
+                    let name = group.clone_name();
+                    if !enables.contains_key(&name) {
+                        groups.push((idx, Rc::clone(group)));
+                    }
+                    enables.entry(name).or_default().push(idx);
+                }
+                CfgNode::Branch { port, cond }
+                | CfgNode::Loop { port, cond } => {
+                    loop_uses.extend(&cond_uses(port, cond))
+                }
+                CfgNode::Debug(ports) => loop_uses.reads.extend(
+                    ports
+                        .iter()
+                        .filter_map(parent_cell)
+                        .filter(is_stateful)
+                        .map(|cell| cell.clone_name()),
+                ),
+                CfgNode::Invoke(_) => return Ok(Action::Continue),
+                CfgNode::Entry
+                | CfgNode::Exit
+                | CfgNode::Fork
+                | CfgNode::Join => {}
+            }
+        }
+        let group_uses = groups
+            .iter()
+            .map(|(_, group)| Uses::from_assigns(&group.borrow().assignments))
+            .collect::<Vec<_>>();
+
+        let mut hoisted = vec![];
+        for (i, (idx, group)) in groups.iter().enumerate() {
+            let uses = &group_uses[i];
+            let invariant = enables[group.borrow().name()].len() == 1
+                && dominators.dominates(*idx, cfg.exit())
+                && uses.reads.is_disjoint(&uses.writes)
+                && uses.independent(&loop_uses)
+                && group_uses
+                    .iter()
+                    .enumerate()
+                    .all(|(j, other)| i == j || uses.independent(other));
+            if invariant {
+                hoisted.push(Rc::clone(group));
+            }
+        }
+
+        if hoisted.is_empty() {
+            return Ok(Action::Continue);
+        }
+
+        self.hoisted += 1;
+        let names = hoisted.iter().map(|g| g.clone_name()).collect();
+        let mut body = std::mem::replace(&mut *s.body, ir::Control::empty());
+        remove_enables(&mut body, &names);
+        let mut while_ = ir::Control::while_(
+            Rc::clone(&s.port),
+            s.cond.clone(),
+            Box::new(body),
+        );
+        if let Some(attrs) = while_.get_mut_attributes() {
+            *attrs = std::mem::take(&mut s.attributes);
+            // The latency of the body has changed.
+            attrs.remove("static");
+        }
+        let mut stmts = hoisted
+            .into_iter()
+            .map(ir::Control::enable)
+            .collect::<Vec<_>>();
+        stmts.push(while_);
+        Ok(Action::Change(ir::Control::seq(stmts)))
+    }
+}
@@ -6,7 +6,7 @@ use crate::ir::traversal::{
     Action, ConstructVisitor, Named, VisResult, Visitor,
 };
 use crate::ir::{self, LibrarySignatures};
-use crate::ir::{GetAttributes, RRC};
+use crate::ir::{GetAttributes, IRPrinter, RRC};
 use itertools::Itertools;
 use std::{cmp, ops::Add, rc::Rc};
 
@@ -19,6 +19,10 @@ use std::{cmp, ops::Add, rc::Rc};
 /// annotation in a group that differs from an inferred value, this
 /// pass will throw an error. If a group's `done` signal relies on signals
 /// that are not only `done` signals, this pass will ignore that group.
+///
+/// Control statements are annotated using the latencies of their children.
+/// Existing annotations on control statements are checked against the
+/// inferred latency in the same way.
 pub struct InferStaticTiming {
     /// primitive name -> (go signal, done signal, latency)
     latency_data: HashMap<ir::Id, (ir::Id, ir::Id, u64)>,
@@ -83,6 +87,28 @@ where
         .fold_options(start, acc)
 }
 
+/// Add a "static" annotation with the inferred `latency` to the control
+/// statement `stmt` of `comp`. If the statement already has a "static"
+/// annotation, it must match the inferred latency.
+fn annotate_static(
+    stmt: &str,
+    comp: &ir::Component,
+    attributes: &mut ir::Attributes,
+    latency: u64,
+) -> CalyxResult<()> {
+    if let Some(curr_lat) = attributes.get("static") {
+        if *curr_lat != latency {
+            return Err(Error::ImpossibleLatencyAnnotation(
+                format!("`{}` in component `{}`", stmt, comp.name),
+                *curr_lat,
+                latency,
+            ));
+        }
+    }
+    attributes.insert("static", latency);
+    Ok(())
+}
+
 impl Named for InferStaticTiming {
     fn name() -> &'static str {
         "infer-static-timing"
@@ -381,7 +407,11 @@ impl Visitor for InferStaticTiming {
                 if let Some(curr_lat) = grp.attributes.get("static") {
                     if *curr_lat != latency {
                         return Err(Error::ImpossibleLatencyAnnotation(
-                            grp.name().to_string(),
+                            format!(
+                                "group `{}` in component `{}`",
+                                grp.name(),
+                                comp.name
+                            ),
                             *curr_lat,
                             latency,
                         ));
@@ -405,14 +435,18 @@ impl Visitor for InferStaticTiming {
     fn finish_while(
         &mut self,
         s: &mut ir::While,
-        _comp: &mut ir::Component,
+        comp: &mut ir::Component,
         _sigs: &LibrarySignatures,
     ) -> VisResult {
         if let (Some(bound), Some(body_time)) = (
             s.attributes.get("bound").cloned(),
             s.body.get_attributes().and_then(|attr| attr.get("static")),
         ) {
-            s.attributes.insert("static", bound * body_time);
+            let stmt = format!(
+                "while {}",
+                IRPrinter::get_port_access(&s.port.borrow())
+            );
+            annotate_static(&stmt, comp, &mut s.attributes, bound * body_time)?;
         }
         Ok(Action::Continue)
     }
@@ -420,7 +454,7 @@ impl Visitor for InferStaticTiming {
     fn finish_if(
         &mut self,
         s: &mut ir::If,
-        _comp: &mut ir::Component,
+        comp: &mut ir::Component,
         _sigs: &LibrarySignatures,
     ) -> VisResult {
         if let (Some(ttime), Some(ftime)) = (
@@ -431,7 +465,14 @@ impl Visitor for InferStaticTiming {
                 .get_attributes()
                 .and_then(|attr| attr.get("static")),
         ) {
-            s.attributes.insert("static", 1 + cmp::max(ttime, ftime));
+            let stmt =
+                format!("if {}", IRPrinter::get_port_access(&s.port.borrow()));
+            annotate_static(
+                &stmt,
+                comp,
+                &mut s.attributes,
+                1 + cmp::max(ttime, ftime),
+            )?;
         }
 
         Ok(Action::Continue)
@@ -440,11 +481,11 @@ impl Visitor for InferStaticTiming {
     fn finish_par(
         &mut self,
         s: &mut ir::Par,
-        _comp: &mut ir::Component,
+        comp: &mut ir::Component,
         _sigs: &LibrarySignatures,
     ) -> VisResult {
         if let Some(time) = accumulate_static_time(&s.stmts, 0, cmp::max) {
-            s.attributes.insert("static", time);
+            annotate_static("par", comp, &mut s.attributes, time)?;
         }
         Ok(Action::Continue)
     }
@@ -452,11 +493,11 @@ impl Visitor for InferStaticTiming {
     fn finish_seq(
         &mut self,
         s: &mut ir::Seq,
-        _comp: &mut ir::Component,
+        comp: &mut ir::Component,
         _sigs: &LibrarySignatures,
     ) -> VisResult {
         if let Some(time) = accumulate_static_time(&s.stmts, 0, Add::add) {
-            s.attributes.insert("static", time);
+            annotate_static("seq", comp, &mut s.attributes, time)?;
         }
         Ok(Action::Continue)
     }
@@ -464,11 +505,12 @@ impl Visitor for InferStaticTiming {
     fn enable(
         &mut self,
         s: &mut ir::Enable,
-        _comp: &mut ir::Component,
+        comp: &mut ir::Component,
         _sigs: &LibrarySignatures,
     ) -> VisResult {
         if let Some(time) = s.group.borrow().attributes.get("static") {
-            s.attributes.insert("static", *time);
+            let stmt = format!("{};", s.group.borrow().name());
+            annotate_static(&stmt, comp, &mut s.attributes, *time)?;
         }
 
         Ok(Action::Continue)
@@ -477,18 +519,23 @@ impl Visitor for InferStaticTiming {
     fn invoke(
         &mut self,
         s: &mut ir::Invoke,
-        _comp: &mut ir::Component,
+        comp: &mut ir::Component,
         _sigs: &LibrarySignatures,
     ) -> VisResult {
         // If we've found static timing for the invoked component, add
-        // this information to invoke.
+        // this information to invoke. The latency of a component only
+        // describes its canonical interface.
+        if s.interface.is_some() {
+            return Ok(Action::Continue);
+        }
         if let Some(time) = &s
             .comp
             .borrow()
             .type_name()
             .and_then(|name| self.comp_latency.get(name))
         {
-            s.attributes.insert("static", **time);
+            let stmt = format!("invoke {}", s.comp.borrow().name());
+            annotate_static(&stmt, comp, &mut s.attributes, **time)?;
         }
         Ok(Action::Continue)
     }
@@ -498,15 +545,33 @@ impl Visitor for InferStaticTiming {
         comp: &mut ir::Component,
         _lib: &LibrarySignatures,
     ) -> VisResult {
-        if let Some(time) = comp
+        let inferred = comp
             .control
             .borrow()
             .get_attributes()
             .and_then(|attrs| attrs.get("static"))
-        {
-            comp.attributes.insert("static", *time);
-            self.comp_latency.insert(comp.name.clone(), *time);
-        }
+            .copied();
+        let declared = comp.attributes.get("static").copied();
+        let time = match (declared, inferred) {
+            (Some(declared), Some(inferred)) if declared != inferred => {
+                return Err(Error::ImpossibleLatencyAnnotation(
+                    format!("component `{}`", comp.name),
+                    declared,
+                    inferred,
+                ));
+            }
+            (_, Some(time)) => time,
+            // The latency of `static` components without control is
+            // guaranteed by `lower-static`.
+            (Some(time), None)
+                if matches!(*comp.control.borrow(), ir::Control::Empty(_)) =>
+            {
+                time
+            }
+            _ => return Ok(Action::Continue),
+        };
+        comp.attributes.insert("static", time);
+        self.comp_latency.insert(comp.name.clone(), time);
         Ok(Action::Continue)
     }
 }
@@ -0,0 +1,287 @@
+use crate::analysis::ReadWriteSet;
+use crate::errors::{CalyxResult, Error};
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, CloneName, GetAttributes, LibrarySignatures, RRC};
+use crate::{build_assignments, structure};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Default)]
+/// Initializes registers with the `@init(value)` attribute when the loop that
+/// updates them is entered.
+///
+/// A register is initialized before the innermost `while` loop that contains
+/// all of its writes so that accumulators are reset for every iteration of
+/// the surrounding loops. Registers that are not written inside a loop are
+/// initialized at the start of the control program.
+///
+/// # Example
+/// ```calyx
+/// cells { @init(0) acc = std_reg(32); }
+/// control {
+///   while lt.out with cond { seq { upd_acc; incr; } }
+/// }
+/// ```
+/// becomes:
+/// ```calyx
+/// cells { acc = std_reg(32); }
+/// wires {
+///   group init_acc<"static"=1> {
+///     acc.in = 32'd0;
+///     acc.write_en = 1'd1;
+///     init_acc[done] = acc.done;
+///   }
+/// }
+/// control {
+///   seq {
+///     init_acc;
+///     while lt.out with cond { seq { upd_acc; incr; } }
+///   }
+/// }
+/// ```
+pub struct InitInsertion;
+
+impl Named for InitInsertion {
+    fn name() -> &'static str {
+        "init-insertion"
+    }
+
+    fn description() -> &'static str {
+        "initialize registers with an @init attribute when their loop is entered"
+    }
+}
+
+/// Registers with an `@init` attribute and their initial values.
+fn init_registers(
+    comp: &ir::Component,
+) -> CalyxResult<Vec<(RRC<ir::Cell>, u64)>> {
+    let mut regs = vec![];
+    for cell_ref in comp.cells.iter() {
+        let cell = cell_ref.borrow();
+        let val = match cell.get_attribute("init") {
+            Some(val) => *val,
+            None => continue,
+        };
+        let is_reg =
+            matches!(cell.type_name(), Some(name) if name == "std_reg");
+        if !is_reg {
+            return Err(Error::MalformedStructure(format!(
+                "`@init` is only supported on `std_reg` cells but `{}` is not a register",
+                cell.name()
+            )));
+        }
+        let width = cell.get_parameter("WIDTH").unwrap();
+        if width < 64 && val >= 1 << width {
+            return Err(Error::MalformedStructure(format!(
+                "`@init({})` does not fit in the {}-bit register `{}`",
+                val,
+                width,
+                cell.name()
+            )));
+        }
+        regs.push((Rc::clone(cell_ref), val));
+    }
+    Ok(regs)
+}
+
+/// Records a write to `cell` inside of `loops`.
+fn record_write(
+    homes: &mut HashMap<ir::Id, Option<Vec<usize>>>,
+    cell: ir::Id,
+    loops: &[usize],
+) {
+    if let Some(home) = homes.get_mut(&cell) {
+        match home {
+            // Only keep the loops that contain all the writes.
+            Some(common) => {
+                let len = common
+                    .iter()
+                    .zip(loops)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                common.truncate(len);
+            }
+            None => *home = Some(loops.to_vec()),
+        }
+    }
+}
+
+/// Computes the loops that contain all the writes to each register. Loops
+/// are numbered in pre-order and `loops` contains the loops surrounding `con`.
+fn loops_of_writes(
+    con: &ir::Control,
+    loops: &mut Vec<usize>,
+    next_loop: &mut usize,
+    homes: &mut HashMap<ir::Id, Option<Vec<usize>>>,
+) {
+    match con {
+        ir::Control::Enable(ir::Enable { group, .. }) => {
+            for cell in ReadWriteSet::write_set(&group.borrow().assignments) {
+                record_write(homes, cell.clone_name(), loops);
+            }
+        }
+        ir::Control::Invoke(ir::Invoke { comp, .. }) => {
+            record_write(homes, comp.clone_name(), loops)
+        }
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => {
+            for stmt in stmts {
+                loops_of_writes(stmt, loops, next_loop, homes);
+            }
+        }
+        ir::Control::If(ir::If {
+            tbranch, fbranch, ..
+        }) => {
+            loops_of_writes(tbranch, loops, next_loop, homes);
+            loops_of_writes(fbranch, loops, next_loop, homes);
+        }
+        ir::Control::While(ir::While { body, .. }) => {
+            loops.push(*next_loop);
+            *next_loop += 1;
+            loops_of_writes(body, loops, next_loop, homes);
+            loops.pop();
+        }
+        ir::Control::Empty(_) | ir::Control::Debug(_) => {}
+    }
+}
+
+/// Enables the groups in `inits` before the loops they belong to. Loops are
+/// numbered in the same order as [loops_of_writes]. Returns true if `con`
+/// was changed.
+fn insert_inits(
+    con: &mut ir::Control,
+    next_loop: &mut usize,
+    inits: &mut HashMap<usize, Vec<RRC<ir::Group>>>,
+) -> bool {
+    let changed = match con {
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => {
+            let mut changed = false;
+            for stmt in stmts {
+                changed |= insert_inits(stmt, next_loop, inits);
+            }
+            changed
+        }
+        ir::Control::If(ir::If {
+            tbranch, fbranch, ..
+        }) => {
+            let t = insert_inits(tbranch, next_loop, inits);
+            insert_inits(fbranch, next_loop, inits) || t
+        }
+        ir::Control::While(ir::While { body, .. }) => {
+            let idx = *next_loop;
+            *next_loop += 1;
+            let changed = insert_inits(body, next_loop, inits);
+            if let Some(groups) = inits.remove(&idx) {
+                let mut while_ = std::mem::replace(con, ir::Control::empty());
+                if changed {
+                    remove_latency(&mut while_);
+                }
+                let mut stmts = groups
+                    .into_iter()
+                    .map(ir::Control::enable)
+                    .collect::<Vec<_>>();
+                stmts.push(while_);
+                *con = ir::Control::seq(stmts);
+                return true;
+            }
+            changed
+        }
+        ir::Control::Enable(_)
+        | ir::Control::Invoke(_)
+        | ir::Control::Empty(_)
+        | ir::Control::Debug(_) => false,
+    };
+    if changed {
+        remove_latency(con);
+    }
+    changed
+}
+
+/// The latency of `con` has changed.
+fn remove_latency(con: &mut ir::Control) {
+    if let Some(attrs) = con.get_mut_attributes() {
+        attrs.remove("static");
+    }
+}
+
+impl Visitor for InitInsertion {
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let regs = init_registers(comp)?;
+        if regs.is_empty()
+            || matches!(*comp.control.borrow(), ir::Control::Empty(_))
+        {
+            return Ok(Action::Stop);
+        }
+
+        for cell in ReadWriteSet::write_set(&comp.continuous_assignments) {
+            if cell.borrow().get_attribute("init").is_some() {
+                return Err(Error::MalformedStructure(format!(
+                    "Register `{}` has an `@init` attribute but is written by continuous assignments",
+                    cell.borrow().name()
+                )));
+            }
+        }
+
+        let mut homes = regs
+            .iter()
+            .map(|(cell, _)| (cell.clone_name(), None))
+            .collect();
+        loops_of_writes(
+            &comp.control.borrow(),
+            &mut vec![],
+            &mut 0,
+            &mut homes,
+        );
+
+        let mut builder = ir::Builder::new(comp, sigs);
+        // Initialization groups for each loop. Registers that are not written
+        // in a loop are initialized at the start of the control program.
+        let mut inits: HashMap<usize, Vec<_>> = HashMap::new();
+        let mut top_level = vec![];
+        for (cell, val) in regs {
+            cell.borrow_mut().attributes.remove("init");
+            let name = cell.clone_name();
+            let width = cell.borrow().get_parameter("WIDTH").unwrap();
+            let group = builder.add_group(format!("init_{}", name));
+            group.borrow_mut().attributes.insert("static", 1);
+            group.borrow_mut().attributes.derive_from([name.clone()]);
+            structure!(builder;
+                let signal_on = constant(1, 1);
+                let init = constant(val, width);
+            );
+            let mut assigns: Vec<_> = build_assignments!(builder;
+                cell["in"] = ? init["out"];
+                cell["write_en"] = ? signal_on["out"];
+                group["done"] = ? cell["done"];
+            );
+            group.borrow_mut().assignments.append(&mut assigns);
+
+            match homes[&name].as_ref().and_then(|loops| loops.last()) {
+                Some(idx) => inits.entry(*idx).or_default().push(group),
+                None => top_level.push(group),
+            }
+        }
+
+        // The latency of the component has changed.
+        comp.attributes.remove("static");
+        let mut con = comp.control.borrow_mut();
+        insert_inits(&mut con, &mut 0, &mut inits);
+        if !top_level.is_empty() {
+            let body = std::mem::replace(&mut *con, ir::Control::empty());
+            let mut stmts = top_level
+                .into_iter()
+                .map(ir::Control::enable)
+                .collect::<Vec<_>>();
+            stmts.push(body);
+            *con = ir::Control::seq(stmts);
+        }
+
+        // This pass doesn't visit the control program.
+        Ok(Action::Stop)
+    }
+}
@@ -120,17 +120,19 @@ impl Visitor for Inliner {
         };
 
         let this_comp = Rc::clone(&comp.signature);
+        let (this_go, this_done) =
+            this_comp.borrow().find_interface(None).unwrap();
         let mut builder = ir::Builder::new(comp, sigs);
 
         // add top_level[go] = this.go
         let mut asgns = vec![
             builder.build_assignment(
                 top_level.borrow().get("go"),
-                this_comp.borrow().get_with_attr("go"),
+                this_go,
                 ir::Guard::True,
             ),
             builder.build_assignment(
-                this_comp.borrow().get_with_attr("done"),
+                this_done,
                 top_level.borrow().get("done"),
                 ir::Guard::True,
             ),
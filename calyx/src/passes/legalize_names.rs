@@ -0,0 +1,336 @@
+use crate::errors::{CalyxResult, Error};
+use crate::ir::traversal::{ConstructVisitor, Named, Visitor};
+use crate::ir::{self, CloneName};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+/// Reserved words of Verilog and SystemVerilog separated by whitespace.
+const VERILOG_KEYWORDS: &str = "
+    alias always always_comb always_ff always_latch and assert assign
+    assume automatic before begin bind bins binsof bit break buf bufif0
+    bufif1 byte case casex casez cell chandle class clocking cmos config
+    const constraint context continue cover covergroup coverpoint cross
+    deassign default defparam design disable dist do edge else end
+    endcase endclass endclocking endconfig endfunction endgenerate
+    endgroup endinterface endmodule endpackage endprimitive endprogram
+    endproperty endsequence endspecify endtable endtask enum event
+    expect export extends extern final first_match for force foreach
+    forever fork forkjoin function generate genvar highz0 highz1 if iff
+    ifnone ignore_bins illegal_bins import incdir include initial inout
+    input inside instance int integer interface intersect join join_any
+    join_none large liblist library local localparam logic longint
+    macromodule matches medium modport module nand negedge new nmos nor
+    noshowcancelled not notif0 notif1 null or output package packed
+    parameter pmos posedge primitive priority program property protected
+    pull0 pull1 pulldown pullup pulsestyle_ondetect pulsestyle_onevent
+    pure rand randc randcase randsequence rcmos real realtime ref reg
+    release repeat return rnmos rpmos rtran rtranif0 rtranif1 scalared
+    sequence shortint shortreal showcancelled signed small solve specify
+    specparam static string strong0 strong1 struct super supply0 supply1
+    table tagged task this throughout time timeprecision timeunit tran
+    tranif0 tranif1 tri tri0 tri1 triand trior trireg type typedef union
+    unique unsigned use uwire var vectored virtual void wait wait_order
+    wand weak0 weak1 while wildcard wire with within wor xnor xor
+";
+
+/// Reserved words of VHDL separated by whitespace. VHDL identifiers are
+/// case-insensitive.
+const VHDL_KEYWORDS: &str = "
+    abs access after alias all and architecture array assert assume
+    assume_guarantee attribute begin block body buffer bus case
+    component configuration constant context cover default disconnect
+    downto else elsif end entity exit fairness file for force function
+    generate generic group guarded if impure in inertial inout is label
+    library linkage literal loop map mod nand new next nor not null of
+    on open or others out package parameter port postponed procedure
+    process property protected pure range record register reject release
+    rem report restrict return rol ror select sequence severity shared
+    signal sla sll sra srl strong subtype then to transport type
+    unaffected units until use variable vmode vprop vunit wait when
+    while with xnor xor
+";
+
+/// Renames the components, ports, cells, and groups of a program whose
+/// names are not legal identifiers in the generated hardware description.
+///
+/// Calyx names may contain `-` and `'`, which are replaced with `_`, and
+/// names that are reserved words of the target language get a suffix. A
+/// counter is appended to a new name if it is already used by another name
+/// in the same scope: components share a scope with the primitives, which
+/// are never renamed, while the signature ports, cells, and groups of a
+/// component share another one. Names that are already legal are kept.
+///
+/// The pass is configured using `-x legalize-names:<key>=<value>`:
+/// - `target`: `verilog` (the default) or `vhdl`. VHDL identifiers also
+///   have to start with a letter, cannot contain repeated or trailing
+///   underscores, and are compared without case.
+/// - `map`: file to write the mapping from the new names to the original
+///   names to. It is a JSON object with an entry for every component with
+///   renamed names:
+///   ```json
+///   { "main": { "name": "main", "cells": { "a_b": "a-b" } } }
+///   ```
+pub struct LegalizeNames {
+    /// Apply the rules of VHDL identifiers.
+    vhdl: bool,
+    /// File to write the mapping of names to.
+    map: Option<PathBuf>,
+}
+
+impl Named for LegalizeNames {
+    fn name() -> &'static str {
+        "legalize-names"
+    }
+
+    fn description() -> &'static str {
+        "rename identifiers that are illegal in Verilog or VHDL"
+    }
+}
+
+impl ConstructVisitor for LegalizeNames {
+    fn from(ctx: &ir::Context) -> CalyxResult<Self>
+    where
+        Self: Sized + Named,
+    {
+        let mut pass = LegalizeNames {
+            vhdl: false,
+            map: None,
+        };
+        for opt in &ctx.extra_opts {
+            let mut splits = opt.splitn(2, ':');
+            if splits.next() != Some(Self::name()) {
+                continue;
+            }
+            let (key, value) = splits
+                .next()
+                .and_then(|opt| opt.split_once('='))
+                .ok_or_else(|| {
+                    Error::Misc(format!(
+                        "Malformed option `{}`. Options of `{}` have the form `{}:<key>=<value>`",
+                        opt,
+                        Self::name(),
+                        Self::name()
+                    ))
+                })?;
+            match (key, value) {
+                ("target", "verilog") => pass.vhdl = false,
+                ("target", "vhdl") => pass.vhdl = true,
+                ("target", _) => {
+                    return Err(Error::Misc(format!(
+                        "Unknown target `{}` for `{}`. Valid targets are `verilog` and `vhdl`",
+                        value,
+                        Self::name()
+                    )))
+                }
+                ("map", _) => pass.map = Some(value.into()),
+                _ => {
+                    return Err(Error::Misc(format!(
+                        "Unknown option `{}` for `{}`. Valid options are `target` and `map`",
+                        key,
+                        Self::name()
+                    )))
+                }
+            }
+        }
+        Ok(pass)
+    }
+
+    fn clear_data(&mut self) {
+        /* All data can be transferred between components */
+    }
+}
+
+/// Names defined in a scope.
+struct Scope {
+    vhdl: bool,
+    /// Names in the scope. Lowercase for VHDL.
+    names: HashSet<String>,
+}
+
+impl Scope {
+    fn new(vhdl: bool) -> Self {
+        Scope {
+            vhdl,
+            names: HashSet::new(),
+        }
+    }
+
+    fn key(&self, name: &str) -> String {
+        if self.vhdl {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        }
+    }
+
+    fn is_keyword(&self, name: &str) -> bool {
+        let lower = name.to_lowercase();
+        VERILOG_KEYWORDS.split_whitespace().any(|kw| kw == name)
+            || (self.vhdl
+                && VHDL_KEYWORDS.split_whitespace().any(|kw| kw == lower))
+    }
+
+    /// The name closest to `name` that is a legal identifier.
+    fn sanitize(&self, name: &str) -> String {
+        let name: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if !self.vhdl {
+            return name;
+        }
+        let name = name
+            .split('_')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("_");
+        if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            name
+        } else {
+            format!("n{}", name)
+        }
+    }
+
+    /// Add `name` to the scope if it is legal and not defined yet.
+    fn reserve(&mut self, name: &str) -> bool {
+        self.sanitize(name) == name
+            && !self.is_keyword(name)
+            && self.names.insert(self.key(name))
+    }
+
+    /// Add a new legal name based on `name` to the scope.
+    fn fresh(&mut self, name: &str) -> ir::Id {
+        let base = self.sanitize(name);
+        let mut name = base.clone();
+        let mut idx = 0;
+        while self.is_keyword(&name) || self.names.contains(&self.key(&name)) {
+            name = format!("{}_{}", base, idx);
+            idx += 1;
+        }
+        self.names.insert(self.key(&name));
+        name.into()
+    }
+
+    /// Add `names` to the scope. Returns the new names of those that are not
+    /// legal or already defined.
+    fn legalize<I>(&mut self, names: I) -> HashMap<ir::Id, ir::Id>
+    where
+        I: IntoIterator<Item = ir::Id>,
+    {
+        let illegal = names
+            .into_iter()
+            .filter(|name| !self.reserve(name.as_ref()))
+            .collect::<Vec<_>>();
+        illegal
+            .into_iter()
+            .map(|name| {
+                let new_name = self.fresh(name.as_ref());
+                (name, new_name)
+            })
+            .collect()
+    }
+}
+
+/// Original names of the renamed names of a component, keyed by their new
+/// names.
+#[derive(Serialize)]
+struct ComponentMap {
+    /// Original name of the component.
+    name: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    ports: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    cells: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    groups: BTreeMap<String, String>,
+}
+
+/// Mapping from new names to original names.
+fn inverse(renames: &HashMap<ir::Id, ir::Id>) -> BTreeMap<String, String> {
+    renames
+        .iter()
+        .map(|(old, new)| (new.to_string(), old.to_string()))
+        .collect()
+}
+
+impl Visitor for LegalizeNames {
+    /// Works on the whole program since ports and components are renamed in
+    /// every component that uses them.
+    fn do_pass(&mut self, context: &mut ir::Context) -> CalyxResult<()>
+    where
+        Self: Sized + ConstructVisitor,
+    {
+        let mut globals = Scope::new(self.vhdl);
+        for prim in context.lib.signatures() {
+            let key = globals.key(prim.name.as_ref());
+            globals.names.insert(key);
+        }
+        let components = globals
+            .legalize(context.components.iter().map(|comp| comp.name.clone()));
+
+        let mut maps = BTreeMap::new();
+        let mut port_renames = vec![];
+        for comp in &mut context.components {
+            let mut scope = Scope::new(self.vhdl);
+            let ports = scope.legalize(
+                comp.signature
+                    .borrow()
+                    .ports
+                    .iter()
+                    .map(|port| port.borrow().name.clone())
+                    .collect::<Vec<_>>(),
+            );
+            let cells =
+                scope.legalize(comp.cells.iter().map(|c| c.clone_name()));
+            let groups =
+                scope.legalize(comp.groups.iter().map(|g| g.clone_name()));
+            let comb_groups =
+                scope.legalize(comp.comb_groups.iter().map(|g| g.clone_name()));
+            comp.rename_cells(&cells);
+            comp.rename_groups(&groups);
+            comp.rename_comb_groups(&comb_groups);
+
+            let renamed =
+                ports.len() + cells.len() + groups.len() + comb_groups.len();
+            let name = components.get(&comp.name).unwrap_or(&comp.name);
+            context.stats.record(name, "names-renamed", renamed as u64);
+            if renamed > 0 || components.contains_key(&comp.name) {
+                let mut group_map = inverse(&groups);
+                group_map.extend(inverse(&comb_groups));
+                maps.insert(
+                    name.to_string(),
+                    ComponentMap {
+                        name: comp.name.to_string(),
+                        ports: inverse(&ports),
+                        cells: inverse(&cells),
+                        groups: group_map,
+                    },
+                );
+            }
+            port_renames.push((comp.name.clone(), ports));
+        }
+
+        for (comp, ports) in port_renames {
+            for (name, new_name) in ports {
+                context.rename_port(&comp, &name, new_name)?;
+            }
+        }
+        for (name, new_name) in components {
+            context.rename_component(&name, new_name)?;
+        }
+
+        if let Some(path) = &self.map {
+            let json = serde_json::to_string_pretty(&maps).unwrap();
+            fs::write(path, json).map_err(|err| {
+                Error::Misc(format!(
+                    "Failed to write {}: {}",
+                    path.to_string_lossy(),
+                    err
+                ))
+            })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use crate::analysis::ReadWriteSet;
+use crate::errors::{CalyxResult, Error};
+use crate::ir::{
+    self,
+    traversal::{Action, ConstructVisitor, Named, VisResult, Visitor},
+    LibrarySignatures,
+};
+use std::collections::HashMap;
+
+/// Schedules the groups enabled by a `par` block so that they use at most a
+/// given number of cells of each primitive at the same time.
+///
+/// The budget is given as a list of `<primitive>=<n>` pairs, such as
+/// `-x list-schedule:std_mult_pipe=2,std_div_pipe=1`. A `par` block whose
+/// statements are all group enables and that uses more cells of a primitive
+/// than its budget is split into a `seq` of `par` blocks. The groups are
+/// placed in order of decreasing `static` latency into the first block that
+/// still has room for the cells they use, so that groups with similar
+/// latencies run together and the latency of the schedule stays low. Groups
+/// without a `static` latency are assumed to take a single cycle.
+///
+/// Once the groups no longer run at the same time, `resource-sharing` can
+/// share the cells between them.
+///
+/// # Example
+/// With `-x list-schedule:std_mult_pipe=2`:
+/// ```calyx
+/// par { mul0; mul1; mul2; add; }
+/// ```
+/// where the `mul` groups each use a `std_mult_pipe` becomes:
+/// ```calyx
+/// seq { par { mul0; mul1; add; } mul2; }
+/// ```
+pub struct ListSchedule {
+    /// Maximum number of cells of each primitive used at the same time.
+    budget: HashMap<String, u64>,
+    /// Number of `par` blocks that were scheduled.
+    scheduled: u64,
+}
+
+impl Named for ListSchedule {
+    fn name() -> &'static str {
+        "list-schedule"
+    }
+
+    fn description() -> &'static str {
+        "serialize the groups of par blocks to respect a resource budget"
+    }
+}
+
+impl ConstructVisitor for ListSchedule {
+    fn from(ctx: &ir::Context) -> CalyxResult<Self>
+    where
+        Self: Sized + Named,
+    {
+        let mut budget = HashMap::new();
+        for opt in &ctx.extra_opts {
+            let mut splits = opt.splitn(2, ':');
+            if splits.next() != Some(Self::name()) {
+                continue;
+            }
+            let malformed = || {
+                Error::Misc(format!(
+                    "Malformed option `{}`. `{}` expects `{}:<primitive>=<n>,...`",
+                    opt,
+                    Self::name(),
+                    Self::name()
+                ))
+            };
+            for limit in splits.next().ok_or_else(malformed)?.split(',') {
+                let (prim, count) =
+                    limit.split_once('=').ok_or_else(malformed)?;
+                let count = count.parse().map_err(|_| malformed())?;
+                budget.insert(prim.to_string(), count);
+            }
+        }
+        Ok(ListSchedule {
+            budget,
+            scheduled: 0,
+        })
+    }
+
+    fn clear_data(&mut self) {
+        self.scheduled = 0;
+    }
+}
+
+/// A group enable along with the resources it needs.
+struct Task {
+    stmt: ir::Control,
+    latency: u64,
+    /// Number of cells of each budgeted primitive used by the group.
+    uses: HashMap<String, u64>,
+}
+
+/// Statements that run in parallel along with the resources they use.
+#[derive(Default)]
+struct Step {
+    stmts: Vec<ir::Control>,
+    uses: HashMap<String, u64>,
+}
+
+impl ListSchedule {
+    /// Number of cells of each budgeted primitive used by `group`.
+    fn uses(&self, group: &ir::Group) -> HashMap<String, u64> {
+        let mut uses = HashMap::new();
+        for cell in ReadWriteSet::uses(&group.assignments) {
+            if let Some(prim) = cell.borrow().type_name() {
+                if self.budget.contains_key(prim.as_ref()) {
+                    *uses.entry(prim.to_string()).or_default() += 1;
+                }
+            }
+        }
+        uses
+    }
+
+    /// Returns true if the statements of `step` can run along with `task`
+    /// without exceeding the budget.
+    fn fits(&self, step: &Step, task: &Task) -> bool {
+        task.uses.iter().all(|(prim, n)| {
+            step.uses.get(prim).copied().unwrap_or_default() + n
+                <= self.budget[prim]
+        })
+    }
+}
+
+impl Visitor for ListSchedule {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![("scheduled", self.scheduled)]
+    }
+
+    fn start(
+        &mut self,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        if self.budget.is_empty() {
+            return Ok(Action::Stop);
+        }
+        Ok(Action::Continue)
+    }
+
+    fn finish_par(
+        &mut self,
+        s: &mut ir::Par,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        if !s
+            .stmts
+            .iter()
+            .all(|stmt| matches!(stmt, ir::Control::Enable(_)))
+        {
+            return Ok(Action::Continue);
+        }
+        let needs = s
+            .stmts
+            .iter()
+            .map(|stmt| {
+                let group = match stmt {
+                    ir::Control::Enable(en) => en.group.borrow(),
+                    _ => unreachable!(),
+                };
+                let latency =
+                    group.attributes.get("static").copied().unwrap_or(1);
+                (latency, self.uses(&group))
+            })
+            .collect::<Vec<_>>();
+
+        let mut total: HashMap<&String, u64> = HashMap::new();
+        for (prim, n) in needs.iter().flat_map(|(_, uses)| uses) {
+            *total.entry(prim).or_default() += n;
+        }
+        if total.iter().all(|(prim, n)| *n <= self.budget[*prim]) {
+            return Ok(Action::Continue);
+        }
+
+        let mut tasks = s
+            .stmts
+            .drain(..)
+            .zip(needs)
+            .map(|(stmt, (latency, uses))| Task {
+                stmt,
+                latency,
+                uses,
+            })
+            .collect::<Vec<_>>();
+
+        // First fit in order of decreasing latency. The sort is stable so
+        // groups with the same latency keep their order.
+        tasks.sort_by_key(|task| std::cmp::Reverse(task.latency));
+        let mut steps: Vec<Step> = vec![];
+        for task in tasks {
+            let idx = match steps.iter().position(|step| self.fits(step, &task))
+            {
+                Some(idx) => idx,
+                None => {
+                    steps.push(Step::default());
+                    steps.len() - 1
+                }
+            };
+            let step = &mut steps[idx];
+            for (prim, n) in task.uses {
+                *step.uses.entry(prim).or_default() += n;
+            }
+            step.stmts.push(task.stmt);
+        }
+
+        self.scheduled += 1;
+        let seq = steps
+            .into_iter()
+            .map(|mut step| {
+                if step.stmts.len() == 1 {
+                    step.stmts.pop().unwrap()
+                } else {
+                    ir::Control::par(step.stmts)
+                }
+            })
+            .collect();
+        Ok(Action::Change(ir::Control::seq(seq)))
+    }
+}
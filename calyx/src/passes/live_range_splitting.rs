@@ -0,0 +1,251 @@
+use crate::analysis::{LiveRangeSplits, ScheduleLength, Split};
+use crate::errors::{CalyxResult, Error};
+use crate::ir::{
+    self,
+    traversal::{Action, ConstructVisitor, Named, VisResult, Visitor},
+    CloneName, GetAttributes, LibrarySignatures, RRC,
+};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Number of cycles that splitting a cell must save when no cost is given.
+const DEFAULT_CYCLES_PER_CELL: u64 = 1;
+
+/// Undoes the sharing of cells when the sharing forces groups on the critical
+/// path of a component to run one after the other.
+///
+/// For each pair of consecutive enables in a `seq` where at least one group
+/// is on the critical path computed by [ScheduleLength], the pass uses
+/// [LiveRangeSplits] to find the shared cells that prevent the groups from
+/// running in parallel. A shared combinational primitive is split by giving
+/// the second group its own copy. A shared register is split by moving the
+/// live range that starts in the second group to a new register and renaming
+/// the register in all the groups of the live range. The groups are then run
+/// in a `par` block.
+///
+/// Running the groups in parallel saves as many cycles as the shorter group
+/// takes, which is only known for groups with `static` latencies. A pair is
+/// transformed when it saves at least `cycles-per-cell` cycles for every cell
+/// that is added, which can be set using
+/// `-x live-range-splitting:cycles-per-cell=<n>`.
+///
+/// Since the pass changes the latency of `seq` statements, it removes their
+/// `static` attributes and should be followed by `infer-static-timing`.
+///
+/// # Example
+/// ```calyx
+/// seq { @static(2) write_a; @static(2) write_b; }
+/// ```
+/// where both groups compute their value using the adder `add` becomes
+/// ```calyx
+/// seq { @static(2) par { write_a; write_b; } }
+/// ```
+/// where `write_b` uses a copy of `add`.
+pub struct LiveRangeSplitting {
+    /// Minimum number of cycles that must be saved per cell that is added.
+    cycles_per_cell: u64,
+    /// Shared cells of the current component.
+    splits: Option<LiveRangeSplits>,
+    /// Groups on the critical path of the current component.
+    critical: HashSet<ir::Id>,
+    /// Groups that were renamed and are no longer described by `splits`.
+    renamed: HashSet<ir::Id>,
+    /// Number of cells that were split.
+    split_cells: u64,
+}
+
+impl Named for LiveRangeSplitting {
+    fn name() -> &'static str {
+        "live-range-splitting"
+    }
+
+    fn description() -> &'static str {
+        "split shared cells that serialize groups on the critical path"
+    }
+}
+
+impl ConstructVisitor for LiveRangeSplitting {
+    fn from(ctx: &ir::Context) -> CalyxResult<Self>
+    where
+        Self: Sized + Named,
+    {
+        let mut cycles_per_cell = DEFAULT_CYCLES_PER_CELL;
+        for opt in &ctx.extra_opts {
+            let mut splits = opt.splitn(2, ':');
+            if splits.next() != Some(Self::name()) {
+                continue;
+            }
+            cycles_per_cell = splits
+                .next()
+                .and_then(|opt| opt.strip_prefix("cycles-per-cell="))
+                .and_then(|val| val.parse().ok())
+                .ok_or_else(|| {
+                    Error::Misc(format!(
+                        "Malformed option `{}`. `{}` expects `{}:cycles-per-cell=<n>`",
+                        opt,
+                        Self::name(),
+                        Self::name()
+                    ))
+                })?;
+        }
+        Ok(LiveRangeSplitting {
+            cycles_per_cell,
+            splits: None,
+            critical: HashSet::new(),
+            renamed: HashSet::new(),
+            split_cells: 0,
+        })
+    }
+
+    fn clear_data(&mut self) {
+        self.splits = None;
+        self.critical.clear();
+        self.renamed.clear();
+        self.split_cells = 0;
+    }
+}
+
+/// Names of the statements on the critical path rooted at `node`.
+fn critical_path(
+    node: &crate::analysis::PathNode,
+    names: &mut HashSet<ir::Id>,
+) {
+    names.insert(node.stmt.as_str().into());
+    node.children
+        .iter()
+        .for_each(|child| critical_path(child, names));
+}
+
+/// The group enabled by `ctrl` and its latency, if it is known.
+fn static_enable(ctrl: &ir::Control) -> Option<(RRC<ir::Group>, u64)> {
+    match ctrl {
+        ir::Control::Enable(ir::Enable { group, .. }) => {
+            let estimate = ScheduleLength::from(ctrl).estimate();
+            estimate.exact.then(|| (Rc::clone(group), estimate.cycles))
+        }
+        _ => None,
+    }
+}
+
+impl LiveRangeSplitting {
+    /// Split the cells so that `second` can run in parallel with the group
+    /// before it.
+    fn split(
+        &mut self,
+        splits: Vec<Split>,
+        second: &RRC<ir::Group>,
+        builder: &mut ir::Builder,
+    ) {
+        for split in splits {
+            let cell = Rc::clone(split.cell());
+            let (prim, params) = match &cell.borrow().prototype {
+                ir::CellType::Primitive {
+                    name,
+                    param_binding,
+                    ..
+                } => (
+                    name.clone(),
+                    param_binding.iter().map(|(_, v)| *v).collect::<Vec<_>>(),
+                ),
+                _ => unreachable!("Only primitives can be split"),
+            };
+            let copy = builder.add_primitive(cell.clone_name(), prim, &params);
+            let groups = match split {
+                Split::Comb(_) => vec![Rc::clone(second)],
+                Split::Register(_, groups) => groups
+                    .iter()
+                    .map(|g| builder.component.find_group(g).unwrap())
+                    .collect(),
+            };
+            for group in groups {
+                let mut group = group.borrow_mut();
+                builder.rename_port_uses(
+                    &[(Rc::clone(&cell), Rc::clone(&copy))],
+                    &mut group.assignments,
+                );
+                self.renamed.insert(group.clone_name());
+            }
+            self.split_cells += 1;
+        }
+    }
+}
+
+impl Visitor for LiveRangeSplitting {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![("split", self.split_cells)]
+    }
+
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let schedule = ScheduleLength::from(&*comp.control.borrow());
+        critical_path(schedule.critical_path(), &mut self.critical);
+        self.splits = Some(LiveRangeSplits::from(&*comp));
+        Ok(Action::Continue)
+    }
+
+    fn finish_seq(
+        &mut self,
+        s: &mut ir::Seq,
+        comp: &mut ir::Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let mut builder = ir::Builder::new(comp, sigs);
+        let mut stmts: Vec<ir::Control> = Vec::with_capacity(s.stmts.len());
+        let mut changed = false;
+        for stmt in s.stmts.drain(..) {
+            let candidate = stmts
+                .last()
+                .and_then(static_enable)
+                .zip(static_enable(&stmt))
+                .filter(|((first, _), (second, _))| {
+                    let (first, second) = (first.borrow(), second.borrow());
+                    (self.critical.contains(first.name())
+                        || self.critical.contains(second.name()))
+                        && !self.renamed.contains(first.name())
+                        && !self.renamed.contains(second.name())
+                });
+            let ((first, first_time), (second, second_time)) = match candidate {
+                Some(pair) => pair,
+                None => {
+                    stmts.push(stmt);
+                    continue;
+                }
+            };
+            let splits = self
+                .splits
+                .as_ref()
+                .unwrap()
+                .splits(&first.borrow(), &second.borrow());
+            let splits = match splits {
+                Some(splits)
+                    if !splits.is_empty()
+                        && first_time.min(second_time)
+                            >= self.cycles_per_cell * splits.len() as u64 =>
+                {
+                    splits
+                }
+                _ => {
+                    stmts.push(stmt);
+                    continue;
+                }
+            };
+            self.renamed.insert(first.clone_name());
+            self.split(splits, &second, &mut builder);
+            let prev = stmts.pop().unwrap();
+            let mut par = ir::Control::par(vec![prev, stmt]);
+            par.get_mut_attributes()
+                .unwrap()
+                .insert("static", first_time.max(second_time));
+            stmts.push(par);
+            changed = true;
+        }
+        s.stmts = stmts;
+        if changed {
+            s.attributes.remove("static");
+        }
+        Ok(Action::Continue)
+    }
+}
@@ -3,9 +3,17 @@ use crate::ir::{
     traversal::{Action, Named, VisResult, Visitor},
     RRC,
 };
+use std::collections::HashMap;
+use std::rc::Rc;
 
 /// Lowers guards into a purely structural representation. After this pass,
 /// all guards are guaranteed to be either [ir::Guard::True] or [ir::Guard::Port].
+///
+/// Each guard expression is computed by a network of `std_and`, `std_or`,
+/// `std_not`, and comparison cells, which lets backends that cannot express
+/// arbitrary guards inline (such as the MLIR backend) emit the program.
+/// Guard expressions that occur multiple times in the same group share their
+/// cells.
 #[derive(Default)]
 pub struct LowerGuards;
 
@@ -36,16 +44,39 @@ fn guard_to_prim(guard: &ir::Guard) -> Option<String> {
     Some(var_name.to_string())
 }
 
+/// Maps guard expressions to the ports that compute them.
+type GuardCache = HashMap<ir::Guard, RRC<ir::Port>>;
+
 fn lower_guard(
     guard: ir::Guard,
     assigns: &mut Vec<ir::Assignment>,
     builder: &mut ir::Builder,
+    cache: &mut GuardCache,
+) -> RRC<ir::Port> {
+    if let ir::Guard::Port(p) = guard {
+        return p;
+    }
+    if let Some(port) = cache.get(&guard) {
+        return Rc::clone(port);
+    }
+    let key = guard.clone();
+    let port = lower_guard_expr(guard, assigns, builder, cache);
+    cache.insert(key, Rc::clone(&port));
+    port
+}
+
+/// Instantiates the cells that compute `guard` and returns their output.
+fn lower_guard_expr(
+    guard: ir::Guard,
+    assigns: &mut Vec<ir::Assignment>,
+    builder: &mut ir::Builder,
+    cache: &mut GuardCache,
 ) -> RRC<ir::Port> {
     let maybe_prim = guard_to_prim(&guard);
     match guard {
         ir::Guard::And(l, r) | ir::Guard::Or(l, r) => {
-            let l_low = lower_guard(*l, assigns, builder);
-            let r_low = lower_guard(*r, assigns, builder);
+            let l_low = lower_guard(*l, assigns, builder, cache);
+            let r_low = lower_guard(*r, assigns, builder, cache);
 
             let prim = maybe_prim.unwrap();
             let prim_name = format!("std_{}", prim);
@@ -91,7 +122,7 @@ fn lower_guard(
             prim.get("out")
         }
         ir::Guard::Not(g) => {
-            let g_low = lower_guard(*g, assigns, builder);
+            let g_low = lower_guard(*g, assigns, builder, cache);
             let not_prim = builder.add_primitive(
                 "not",
                 "std_not",
@@ -115,10 +146,11 @@ fn lower_assigns(
     builder: &mut ir::Builder,
 ) -> Vec<ir::Assignment> {
     let mut new_assigns = Vec::with_capacity(assigns.len() * 2);
+    let mut cache = GuardCache::new();
     for mut assign in assigns {
         let g = std::mem::take(&mut assign.guard);
         let mut assigns = vec![];
-        let port = lower_guard(*g, &mut assigns, builder);
+        let port = lower_guard(*g, &mut assigns, builder, &mut cache);
         assign.guard = Box::new(port.into());
         new_assigns.extend(assigns);
         new_assigns.push(assign);
@@ -0,0 +1,181 @@
+use super::math_utilities::get_bit_width_from;
+use crate::ir::{
+    self,
+    traversal::{Action, Named, VisResult, Visitor},
+    LibrarySignatures, RRC,
+};
+use crate::{build_assignments, guard, structure};
+use std::rc::Rc;
+
+/// Lowers `static` groups and components to the `go`/`done` interface used by
+/// the rest of the compiler.
+///
+/// A `static<n>` group runs its assignments for exactly `n` cycles and does
+/// not have a done condition. The pass adds a counter to the group that
+/// enables the assignments while it is below `n` and signals that the group
+/// is done when it reaches `n`:
+/// ```calyx
+/// static<2> group g { r.in = 32'd1; r.write_en = 1'd1; }
+/// ```
+/// becomes
+/// ```calyx
+/// group g<"static"=2> {
+///   r.in = fsm.out < 2'd2 ? 32'd1;
+///   r.write_en = fsm.out < 2'd2 ? 1'd1;
+///   fsm.in = fsm.out != 2'd2 ? incr.out;
+///   ...
+///   g[done] = fsm.out == 2'd2 ? 1'd1;
+/// }
+/// ```
+/// along with continuous assignments that reset the counter once the group
+/// is done.
+///
+/// A `static<n>` component has the `"static"=n` attribute attached to it. If
+/// the component does not have a control program, the pass also drives its
+/// `done` port with a counter that reaches `n` cycles after `go` is raised.
+/// The latency of components with control programs is checked by
+/// `infer-static-timing`.
+#[derive(Default)]
+pub struct LowerStatic {
+    /// Number of static groups and components that were lowered.
+    lowered: u64,
+}
+
+impl Named for LowerStatic {
+    fn name() -> &'static str {
+        "lower-static"
+    }
+
+    fn description() -> &'static str {
+        "lower static groups and components to the go-done interface"
+    }
+}
+
+/// Counter that counts up to `latency` while `run` is true and is reset once
+/// it reaches `latency`. Returns the counter along with the guard that is
+/// true when it has reached `latency`.
+fn counter(
+    latency: u64,
+    run: ir::Guard,
+    builder: &mut ir::Builder,
+) -> (RRC<ir::Cell>, ir::Guard, Vec<ir::Assignment>) {
+    let fsm_size = get_bit_width_from(latency + 1);
+    structure!(builder;
+        let fsm = prim std_reg(fsm_size);
+        let signal_on = constant(1, 1);
+        let incr = prim std_add(fsm_size);
+        let one = constant(1, fsm_size);
+        let last = constant(latency, fsm_size);
+        let reset_val = constant(0, fsm_size);
+    );
+    let done_guard = guard!(fsm["out"]).eq(guard!(last["out"]));
+    let count_guard = run & !done_guard.clone();
+    let assigns = build_assignments!(builder;
+        incr["left"] = ? one["out"];
+        incr["right"] = ? fsm["out"];
+        fsm["in"] = count_guard ? incr["out"];
+        fsm["write_en"] = count_guard ? signal_on["out"];
+    );
+
+    // CLEANUP: Reset the counter once it reaches the latency.
+    let mut cleanup = build_assignments!(builder;
+        fsm["in"] = done_guard ? reset_val["out"];
+        fsm["write_en"] = done_guard ? signal_on["out"];
+    );
+    builder
+        .component
+        .continuous_assignments
+        .append(&mut cleanup);
+    (fsm, done_guard, assigns)
+}
+
+impl LowerStatic {
+    fn lower_group(
+        &mut self,
+        group: &RRC<ir::Group>,
+        latency: u64,
+        builder: &mut ir::Builder,
+    ) {
+        let (fsm, done_guard, mut assigns) =
+            counter(latency, ir::Guard::True, builder);
+        let fsm_size = fsm.borrow().get("out").borrow().width;
+        structure!(builder;
+            let signal_on = constant(1, 1);
+            let last = constant(latency, fsm_size);
+        );
+        let active = guard!(fsm["out"]).lt(guard!(last["out"]));
+
+        let mut group = group.borrow_mut();
+        for assign in &mut group.assignments {
+            *assign.guard = active.clone().and(*assign.guard.clone());
+        }
+        assigns.push(builder.build_assignment(
+            group.get("done"),
+            signal_on.borrow().get("out"),
+            done_guard,
+        ));
+        group.assignments.append(&mut assigns);
+        group.attributes.insert("static", latency);
+        group.latency = None;
+        self.lowered += 1;
+    }
+}
+
+impl Visitor for LowerStatic {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![("lowered", self.lowered)]
+    }
+
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let groups = comp
+            .groups
+            .iter()
+            .filter_map(|group| {
+                let latency = group.borrow().latency?;
+                Some((Rc::clone(group), latency))
+            })
+            .collect::<Vec<_>>();
+        let comp_latency = comp.latency.take();
+        let empty_control =
+            matches!(*comp.control.borrow(), ir::Control::Empty(_));
+
+        let mut builder = ir::Builder::new(comp, sigs);
+        for (group, latency) in groups {
+            self.lower_group(&group, latency, &mut builder);
+        }
+
+        if let Some(latency) = comp_latency {
+            if empty_control {
+                let (go, done) = builder
+                    .component
+                    .signature
+                    .borrow()
+                    .find_interface(None)
+                    .unwrap();
+                let (_, done_guard, mut assigns) =
+                    counter(latency, ir::Guard::port(go), &mut builder);
+                structure!(builder;
+                    let signal_on = constant(1, 1);
+                );
+                assigns.push(builder.build_assignment(
+                    done,
+                    signal_on.borrow().get("out"),
+                    done_guard,
+                ));
+                builder
+                    .component
+                    .continuous_assignments
+                    .append(&mut assigns);
+            }
+            builder.component.attributes.insert("static", latency);
+            self.lowered += 1;
+        }
+
+        // This pass doesn't modify any control.
+        Ok(Action::Stop)
+    }
+}
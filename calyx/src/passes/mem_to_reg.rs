@@ -0,0 +1,268 @@
+use super::memory_banking::{control_uses, uses_cell};
+use crate::errors::{CalyxResult, Error};
+use crate::ir::{
+    self,
+    traversal::{Action, ConstructVisitor, Named, VisResult, Visitor},
+    CloneName, LibrarySignatures, RRC,
+};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Memories with at most this many elements are promoted when no size is
+/// given.
+const DEFAULT_SIZE: u64 = 4;
+
+/// Replaces small memories that are only accessed at constant addresses with
+/// one register per element.
+///
+/// A `std_mem_d1` is promoted when:
+/// 1. It has at most `size` elements, is not `@external`, and has no initial
+///    contents since registers are reset to zero.
+/// 2. Every group that uses it drives `addr0` only with constants.
+/// 3. It is not used by the control program, continuous assignments,
+///    combinational groups, or guards.
+///
+/// Writes are redirected to the register selected by the address and reads
+/// of `read_data` and `done` select between the registers using the guards
+/// of the address assignments.
+///
+/// # Example
+/// With `-x mem-to-reg:size=2`:
+/// ```calyx
+/// mem = std_mem_d1(32, 2, 1);
+/// group write { mem.addr0 = 1'd1; mem.write_data = x.out; mem.write_en = 1'd1; write[done] = mem.done; }
+/// ```
+/// becomes:
+/// ```calyx
+/// mem0 = std_reg(32); mem1 = std_reg(32);
+/// group write { mem1.in = x.out; mem1.write_en = 1'd1; write[done] = mem1.done; }
+/// ```
+pub struct MemToReg {
+    /// Maximum number of elements in a promoted memory.
+    size: u64,
+    /// Number of memories that were promoted.
+    promoted: u64,
+}
+
+impl Named for MemToReg {
+    fn name() -> &'static str {
+        "mem-to-reg"
+    }
+
+    fn description() -> &'static str {
+        "replace small memories accessed at constant addresses with registers"
+    }
+}
+
+impl ConstructVisitor for MemToReg {
+    fn from(ctx: &ir::Context) -> CalyxResult<Self>
+    where
+        Self: Sized + Named,
+    {
+        let mut size = DEFAULT_SIZE;
+        for opt in &ctx.extra_opts {
+            let mut splits = opt.splitn(2, ':');
+            if splits.next() != Some(Self::name()) {
+                continue;
+            }
+            size = splits
+                .next()
+                .and_then(|opt| opt.strip_prefix("size="))
+                .and_then(|val| val.parse().ok())
+                .ok_or_else(|| {
+                    Error::Misc(format!(
+                        "Malformed option `{}`. `{}` expects `{}:size=<n>`",
+                        opt,
+                        Self::name(),
+                        Self::name()
+                    ))
+                })?;
+        }
+        Ok(MemToReg { size, promoted: 0 })
+    }
+
+    fn clear_data(&mut self) {
+        self.promoted = 0;
+    }
+}
+
+/// Returns true if `port` belongs to the cell named `cell`.
+fn is_port_of(port: &RRC<ir::Port>, cell: &ir::Id) -> bool {
+    let port = port.borrow();
+    !port.is_hole() && port.get_parent_name() == cell
+}
+
+/// Returns the guard under which `group` accesses each element of `mem`, or
+/// `None` if an address is not a constant smaller than `size` or the group does
+/// not drive the address.
+fn selectors(
+    group: &ir::Group,
+    mem: &ir::Id,
+    size: u64,
+) -> Option<Vec<Option<ir::Guard>>> {
+    let mut sels: Vec<Option<ir::Guard>> = vec![None; size as usize];
+    for assign in &group.assignments {
+        if assign
+            .guard
+            .all_ports()
+            .iter()
+            .any(|port| is_port_of(port, mem))
+        {
+            return None;
+        }
+        if !(is_port_of(&assign.dst, mem)
+            && assign.dst.borrow().name == "addr0")
+        {
+            continue;
+        }
+        let addr = match &assign.src.borrow().parent {
+            ir::PortParent::Cell(cell) => {
+                match cell.upgrade().borrow().prototype {
+                    ir::CellType::Constant { val, .. } if val < size => val,
+                    _ => return None,
+                }
+            }
+            ir::PortParent::Group(_) => return None,
+        };
+        let sel = &mut sels[addr as usize];
+        let guard = *assign.guard.clone();
+        *sel = Some(match sel.take() {
+            Some(prev) => prev.or(guard),
+            None => guard,
+        });
+    }
+    // Reads without an address depend on the address driven by another group.
+    sels.iter().any(Option::is_some).then_some(sels)
+}
+
+impl MemToReg {
+    /// Returns true if `cell` is a memory that is small enough to promote.
+    fn candidate(&self, cell: &ir::Cell) -> bool {
+        cell.type_name().map(|n| n == "std_mem_d1").unwrap_or(false)
+            && cell.get_attribute("external").is_none()
+            && cell.contents.is_none()
+            && cell.get_parameter("SIZE").unwrap() <= self.size
+    }
+
+    /// Promote `mem` if all its accesses use constant addresses.
+    fn promote(
+        &mut self,
+        mem: &RRC<ir::Cell>,
+        builder: &mut ir::Builder,
+        control_cells: &HashSet<ir::Id>,
+    ) {
+        let name = mem.clone_name();
+        let size = mem.borrow().get_parameter("SIZE").unwrap();
+        let width = mem.borrow().get_parameter("WIDTH").unwrap();
+        let comp = &builder.component;
+        if control_cells.contains(&name)
+            || uses_cell(&comp.continuous_assignments, &name)
+            || comp
+                .comb_groups
+                .iter()
+                .any(|cg| uses_cell(&cg.borrow().assignments, &name))
+        {
+            return;
+        }
+        let groups = comp
+            .groups
+            .iter()
+            .filter(|g| uses_cell(&g.borrow().assignments, &name))
+            .map(|g| {
+                let sels = selectors(&g.borrow(), &name, size)?;
+                Some((Rc::clone(g), sels))
+            })
+            .collect::<Option<Vec<_>>>();
+        let groups = match groups {
+            Some(groups) => groups,
+            None => return,
+        };
+
+        let regs = (0..size)
+            .map(|idx| {
+                builder.add_primitive(
+                    format!("{}{}", name, idx),
+                    "std_reg",
+                    &[width],
+                )
+            })
+            .collect::<Vec<_>>();
+        for (group, sels) in groups {
+            let mut group = group.borrow_mut();
+            let assigns = std::mem::take(&mut group.assignments);
+            for assign in assigns {
+                let (dst, src) = (
+                    is_port_of(&assign.dst, &name),
+                    is_port_of(&assign.src, &name),
+                );
+                if !dst && !src {
+                    group.assignments.push(assign);
+                    continue;
+                }
+                let port = if dst { &assign.dst } else { &assign.src };
+                let port = match port.borrow().name.as_ref() {
+                    "write_data" => "in",
+                    "write_en" => "write_en",
+                    "read_data" => "out",
+                    "done" => "done",
+                    // The address selects the register.
+                    _ => continue,
+                };
+                for (reg, sel) in regs.iter().zip(&sels) {
+                    let sel = match sel {
+                        Some(sel) => sel.clone(),
+                        None => continue,
+                    };
+                    let reg_port = reg.borrow().get(port);
+                    let (dst, src) = if dst {
+                        (reg_port, Rc::clone(&assign.src))
+                    } else {
+                        (Rc::clone(&assign.dst), reg_port)
+                    };
+                    group.assignments.push(builder.build_assignment(
+                        dst,
+                        src,
+                        sel.and(*assign.guard.clone()),
+                    ));
+                }
+            }
+        }
+        builder
+            .component
+            .cells
+            .retain(|cell| !Rc::ptr_eq(cell, mem));
+        self.promoted += 1;
+    }
+}
+
+impl Visitor for MemToReg {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![("promoted", self.promoted)]
+    }
+
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let mems = comp
+            .cells
+            .iter()
+            .filter(|cell| self.candidate(&cell.borrow()))
+            .cloned()
+            .collect::<Vec<_>>();
+        if mems.is_empty() {
+            return Ok(Action::Stop);
+        }
+
+        let mut control_cells = HashSet::new();
+        control_uses(&comp.control.borrow(), &mut control_cells);
+        let mut builder = ir::Builder::new(comp, sigs);
+        for mem in mems {
+            self.promote(&mem, &mut builder, &control_cells);
+        }
+
+        // This pass doesn't modify any control.
+        Ok(Action::Stop)
+    }
+}
@@ -0,0 +1,426 @@
+use crate::analysis::ReadWriteSet;
+use crate::errors::{CalyxResult, Error};
+use crate::ir::{
+    self,
+    traversal::{Action, Named, VisResult, Visitor},
+    CloneName, LibrarySignatures, RRC,
+};
+use crate::{build_assignments, structure};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// Splits memories marked with the `@bank(n)` attribute into `n` banks.
+///
+/// A `std_mem_d1` with `SIZE` elements is cyclically partitioned into `n`
+/// memories with `SIZE / n` elements each: element `i` is stored at index
+/// `i / n` of bank `i % n`. The factor `n` must be a power of two that divides
+/// `SIZE`.
+///
+/// A memory is only banked if every group that uses it drives its address
+/// with values whose remainder modulo `n` is known at compile time. This is
+/// the case for constants, registers that are only ever written with
+/// multiples of `n`, such as the index of a loop that is incremented by `n`,
+/// and the sums of such values. The accesses of each group are redirected to
+/// the bank selected by the remainder and the address is divided by `n`:
+/// ```calyx
+/// group read1 { mem.addr0 = add.out; ... } // add.out = i.out + 3'd1
+/// ```
+/// becomes
+/// ```calyx
+/// group read1 {
+///   rsh.left = add.out; rsh.right = 3'd1; slice.in = rsh.out;
+///   mem_bank1.addr0 = slice.out; ...
+/// }
+/// ```
+/// Memories that are `@external`, used by the control program, continuous
+/// assignments, or combinational groups are not banked. The initial contents
+/// of a memory are split between its banks.
+///
+/// Once the accesses are split, consecutive enables in a `seq` of groups that
+/// access a banked memory are run in `par` blocks if none of the groups
+/// writes to a cell used by another group in the block.
+#[derive(Default)]
+pub struct MemoryBanking {
+    /// Groups whose accesses were redirected to banks.
+    banked_groups: HashSet<ir::Id>,
+}
+
+impl Named for MemoryBanking {
+    fn name() -> &'static str {
+        "memory-banking"
+    }
+
+    fn description() -> &'static str {
+        "split memories marked with @bank(n) into n banks"
+    }
+}
+
+/// Remainders of the values of ports modulo the banking factor.
+struct Remainders<'a> {
+    /// Assignments of the component.
+    assigns: Vec<&'a ir::Assignment>,
+    /// Banking factor.
+    factor: u64,
+    /// Remainder of the outputs of cells that have been visited. `Some(None)`
+    /// marks a cell whose remainder is unknown.
+    cells: HashMap<ir::Id, Option<u64>>,
+}
+
+impl<'a> Remainders<'a> {
+    /// Remainder of the values written to `cell.port`. Requires all writes to
+    /// have the same remainder.
+    fn written(&mut self, cell: &ir::Id, port: &str) -> Option<u64> {
+        let srcs = self
+            .assigns
+            .iter()
+            .filter(|assign| {
+                let dst = assign.dst.borrow();
+                dst.name == port && dst.get_parent_name() == cell
+            })
+            .map(|assign| Rc::clone(&assign.src))
+            .collect::<Vec<_>>();
+        let mut rems = srcs.iter().map(|src| self.port(src));
+        let first = rems.next().flatten()?;
+        rems.all(|rem| rem == Some(first)).then_some(first)
+    }
+
+    /// Remainder of the value of `port`, if it is known.
+    fn port(&mut self, port: &RRC<ir::Port>) -> Option<u64> {
+        let (cell, name) = {
+            let port = port.borrow();
+            match &port.parent {
+                ir::PortParent::Cell(cell) => {
+                    (cell.upgrade(), port.name.clone())
+                }
+                ir::PortParent::Group(_) => return None,
+            }
+        };
+        let cell = cell.borrow();
+        let cell_name = cell.clone_name();
+        match &cell.prototype {
+            ir::CellType::Constant { val, .. } => Some(val % self.factor),
+            ir::CellType::Primitive { name: prim, .. } if name == "out" => {
+                if let Some(rem) = self.cells.get(&cell_name) {
+                    return *rem;
+                }
+                match prim.as_ref() {
+                    "std_reg" => {
+                        // Registers start out as zero. Assume that the
+                        // register only stores multiples of the factor while
+                        // checking the values written to it.
+                        self.cells.insert(cell_name.clone(), Some(0));
+                        let rem =
+                            self.written(&cell_name, "in").filter(|r| *r == 0);
+                        self.cells.insert(cell_name, rem);
+                        rem
+                    }
+                    "std_add" => {
+                        self.cells.insert(cell_name.clone(), None);
+                        let rem = self
+                            .written(&cell_name, "left")
+                            .zip(self.written(&cell_name, "right"))
+                            .map(|(l, r)| (l + r) % self.factor);
+                        self.cells.insert(cell_name, rem);
+                        rem
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Names of the cells directly used by the control program.
+pub(super) fn control_uses(con: &ir::Control, uses: &mut HashSet<ir::Id>) {
+    match con {
+        ir::Control::Enable(_) | ir::Control::Empty(_) => {}
+        ir::Control::Debug(ir::Debug { ports, .. }) => uses
+            .extend(ports.iter().map(|port| port.borrow().get_parent_name())),
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => {
+            stmts.iter().for_each(|stmt| control_uses(stmt, uses))
+        }
+        ir::Control::If(ir::If {
+            port,
+            tbranch,
+            fbranch,
+            ..
+        }) => {
+            uses.insert(port.borrow().get_parent_name());
+            control_uses(tbranch, uses);
+            control_uses(fbranch, uses);
+        }
+        ir::Control::While(ir::While { port, body, .. }) => {
+            uses.insert(port.borrow().get_parent_name());
+            control_uses(body, uses);
+        }
+        ir::Control::Invoke(ir::Invoke {
+            comp,
+            inputs,
+            outputs,
+            ..
+        }) => {
+            uses.insert(comp.clone_name());
+            uses.extend(
+                inputs
+                    .iter()
+                    .chain(outputs.iter())
+                    .map(|(_, port)| port.borrow().get_parent_name()),
+            );
+        }
+    }
+}
+
+/// Returns true if any of the assignments uses `cell`.
+pub(super) fn uses_cell(assigns: &[ir::Assignment], cell: &ir::Id) -> bool {
+    ReadWriteSet::uses(assigns).any(|c| c.borrow().name() == cell)
+}
+
+impl MemoryBanking {
+    /// Returns the banking factor of `cell` if it is a memory marked with
+    /// `@bank(n)`.
+    fn factor(cell: &ir::Cell) -> CalyxResult<Option<u64>> {
+        let factor = match cell.get_attribute("bank") {
+            Some(factor) => *factor,
+            None => return Ok(None),
+        };
+        let size = cell.get_parameter("SIZE");
+        let idx_size = cell.get_parameter("IDX_SIZE");
+        match (cell.type_name().map(|n| n.as_ref()), size, idx_size) {
+            (Some("std_mem_d1"), Some(size), Some(idx_size))
+                if factor.is_power_of_two()
+                    && factor > 1
+                    && size % factor == 0
+                    && idx_size > factor.trailing_zeros() as u64 =>
+            {
+                Ok(Some(factor))
+            }
+            _ => Err(Error::MalformedStructure(cell.name().fmt_err(&format!(
+                "Cannot split `{}' into {} banks: only std_mem_d1 memories can be banked and the number of banks must be a power of two that divides their size",
+                cell.name(),
+                factor
+            )))),
+        }
+    }
+
+    /// Bank `mem` by `factor` if all its accesses can be resolved to a bank.
+    fn bank(
+        &mut self,
+        mem: &RRC<ir::Cell>,
+        factor: u64,
+        builder: &mut ir::Builder,
+        control_cells: &HashSet<ir::Id>,
+    ) {
+        let name = mem.clone_name();
+        let comp = &builder.component;
+        if mem.borrow().get_attribute("external").is_some()
+            || control_cells.contains(&name)
+            || uses_cell(&comp.continuous_assignments, &name)
+            || comp
+                .comb_groups
+                .iter()
+                .any(|cg| uses_cell(&cg.borrow().assignments, &name))
+        {
+            return;
+        }
+
+        // Find the bank accessed by each group that uses the memory.
+        let groups = comp
+            .groups
+            .iter()
+            .filter(|g| uses_cell(&g.borrow().assignments, &name))
+            .cloned()
+            .collect::<Vec<_>>();
+        let all_groups =
+            comp.groups.iter().map(|g| g.borrow()).collect::<Vec<_>>();
+        let mut rems = Remainders {
+            assigns: all_groups
+                .iter()
+                .flat_map(|g| g.assignments.iter())
+                .chain(comp.continuous_assignments.iter())
+                .collect(),
+            factor,
+            cells: HashMap::new(),
+        };
+        let banks = groups
+            .iter()
+            .map(|group| {
+                let group = group.borrow();
+                let mut addrs = group.assignments.iter().filter(|assign| {
+                    let dst = assign.dst.borrow();
+                    dst.name == "addr0" && dst.get_parent_name() == name
+                });
+                let first = rems.port(&addrs.next()?.src)?;
+                addrs
+                    .all(|assign| rems.port(&assign.src) == Some(first))
+                    .then_some(first)
+            })
+            .collect::<Option<Vec<_>>>();
+        drop(all_groups);
+        let banks = match banks {
+            Some(banks) => banks,
+            None => return,
+        };
+
+        let (width, size, idx_size) = {
+            let mem = mem.borrow();
+            (
+                mem.get_parameter("WIDTH").unwrap(),
+                mem.get_parameter("SIZE").unwrap(),
+                mem.get_parameter("IDX_SIZE").unwrap(),
+            )
+        };
+        let shift = factor.trailing_zeros() as u64;
+        let contents = mem.borrow().contents.clone();
+        let bank_cells = (0..factor)
+            .map(|bank| {
+                let cell = builder.add_primitive(
+                    format!("{}_bank{}", name, bank),
+                    "std_mem_d1",
+                    &[width, size / factor, idx_size - shift],
+                );
+                // Element `i` of the memory is stored in bank `i % factor`.
+                cell.borrow_mut().contents = contents.as_ref().map(|vals| {
+                    vals.iter()
+                        .skip(bank as usize)
+                        .step_by(factor as usize)
+                        .copied()
+                        .collect()
+                });
+                cell
+            })
+            .collect::<Vec<_>>();
+
+        for (group, bank) in groups.iter().zip(banks) {
+            structure!(builder;
+                let rsh = prim std_rsh(idx_size);
+                let slice = prim std_slice(idx_size, idx_size - shift);
+                let amount = constant(shift, idx_size);
+            );
+            let mut group = group.borrow_mut();
+            let mut assigns = build_assignments!(builder;
+                rsh["right"] = ? amount["out"];
+                slice["in"] = ? rsh["out"];
+            );
+            // Divide the address by the number of banks.
+            for assign in group.assignments.iter_mut().filter(|assign| {
+                let dst = assign.dst.borrow();
+                dst.name == "addr0" && dst.get_parent_name() == name
+            }) {
+                assigns.push(builder.build_assignment(
+                    rsh.borrow().get("left"),
+                    Rc::clone(&assign.src),
+                    *assign.guard.clone(),
+                ));
+                assign.src = slice.borrow().get("out");
+            }
+            group.assignments.append(&mut assigns);
+            builder.rename_port_uses(
+                &[(Rc::clone(mem), Rc::clone(&bank_cells[bank as usize]))],
+                &mut group.assignments,
+            );
+            self.banked_groups.insert(group.clone_name());
+        }
+        builder
+            .component
+            .cells
+            .retain(|cell| !Rc::ptr_eq(cell, mem));
+    }
+}
+
+impl Visitor for MemoryBanking {
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.banked_groups.clear();
+        let mut mems = vec![];
+        for cell in comp.cells.iter() {
+            if let Some(factor) = Self::factor(&cell.borrow())? {
+                mems.push((Rc::clone(cell), factor));
+            }
+        }
+        if mems.is_empty() {
+            return Ok(Action::Stop);
+        }
+
+        let mut control_cells = HashSet::new();
+        control_uses(&comp.control.borrow(), &mut control_cells);
+        let mut builder = ir::Builder::new(comp, sigs);
+        for (mem, factor) in mems {
+            self.bank(&mem, factor, &mut builder, &control_cells);
+        }
+        Ok(Action::Continue)
+    }
+
+    fn finish_seq(
+        &mut self,
+        s: &mut ir::Seq,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        // Cells written and used by a group that accesses a banked memory.
+        let banked = |ctrl: &ir::Control| match ctrl {
+            ir::Control::Enable(ir::Enable { group, .. })
+                if self.banked_groups.contains(group.borrow().name()) =>
+            {
+                let group = group.borrow();
+                let names = |cells: ir::CellIterator<'_>| {
+                    cells.map(|c| c.clone_name()).collect::<HashSet<_>>()
+                };
+                Some((
+                    names(ReadWriteSet::write_set(&group.assignments)),
+                    names(ReadWriteSet::uses(&group.assignments)),
+                ))
+            }
+            _ => None,
+        };
+
+        // Greedily group consecutive independent enables into `par` blocks.
+        let mut stmts: Vec<ir::Control> = vec![];
+        let mut block: Vec<ir::Control> = vec![];
+        let mut block_writes = HashSet::new();
+        let mut block_uses = HashSet::new();
+        let mut changed = false;
+        let mut flush = |block: &mut Vec<ir::Control>,
+                         stmts: &mut Vec<ir::Control>| {
+            if block.len() > 1 {
+                changed = true;
+                stmts.push(ir::Control::par(std::mem::take(block)));
+            } else {
+                stmts.append(block);
+            }
+        };
+        for stmt in s.stmts.drain(..) {
+            match banked(&stmt) {
+                Some((writes, uses)) => {
+                    if !writes.is_disjoint(&block_uses)
+                        || !block_writes.is_disjoint(&uses)
+                    {
+                        flush(&mut block, &mut stmts);
+                        block_writes.clear();
+                        block_uses.clear();
+                    }
+                    block_writes.extend(writes);
+                    block_uses.extend(uses);
+                    block.push(stmt);
+                }
+                None => {
+                    flush(&mut block, &mut stmts);
+                    block_writes.clear();
+                    block_uses.clear();
+                    stmts.push(stmt);
+                }
+            }
+        }
+        flush(&mut block, &mut stmts);
+        s.stmts = stmts;
+        if changed {
+            // The latency of the `seq` changed.
+            s.attributes.remove("static");
+        }
+        Ok(Action::Continue)
+    }
+}
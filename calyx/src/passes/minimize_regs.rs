@@ -15,8 +15,7 @@ use crate::{
 /// A greedy graph coloring algorithm on the interference graph
 /// is used to assign each register a name.
 ///
-/// This pass only renames uses of registers. `DeadCellRemoval` should be run after this
-/// to actually remove the register definitions.
+/// The registers whose uses are all renamed are removed from the component.
 #[derive(Default)]
 pub struct MinimizeRegs {
     live: LiveRangeAnalysis,
@@ -73,4 +72,8 @@ impl ShareComponents for MinimizeRegs {
     fn get_rewrites(&self) -> &[(ir::RRC<ir::Cell>, ir::RRC<ir::Cell>)] {
         &self.rewrites
     }
+
+    fn remove_replaced(&self) -> bool {
+        true
+    }
 }
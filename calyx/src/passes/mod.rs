@@ -1,56 +1,110 @@
 //! Passes for the Calyx compiler.
+mod access_patterns;
 mod clk_insertion;
 mod collapse_control;
+mod compile_debug;
 mod compile_empty;
 mod compile_invoke;
+mod compile_stream;
 mod component_interface;
+mod dead_assignment_removal;
 mod dead_cell_removal;
+mod dead_component_removal;
 mod dead_group_removal;
+mod dedup_components;
 mod externalize;
+mod fsm_report;
 mod go_insertion;
+mod group_fusion;
 mod group_to_invoke;
 mod guard_canonical;
+mod hoist_invariant_groups;
 mod infer_static_timing;
+mod init_insertion;
 mod inliner;
+mod legalize_names;
+mod list_schedule;
+mod live_range_splitting;
 mod lower_guards;
+mod lower_static;
 mod math_utilities;
+mod mem_to_reg;
+mod memory_banking;
 mod merge_assign;
 mod minimize_regs;
+mod outline;
 mod papercut;
 mod par_to_seq;
+mod race_detection;
 mod register_unsharing;
 mod remove_comb_groups;
+mod remove_debug;
 mod reset_insertion;
 mod resource_sharing;
+mod retime;
 mod sharing_components;
+mod simplify_comparisons;
 mod simplify_guards;
+mod simplify_with_control;
+mod speculate_if;
+mod strength_reduction;
 mod synthesis_papercut;
 mod top_down_compile_control;
+mod unreachable_control;
 mod well_formed;
+mod width_reduction;
+mod wrap_main;
 
+pub use access_patterns::AccessPatterns;
 pub use clk_insertion::ClkInsertion;
 pub use collapse_control::CollapseControl;
+pub use compile_debug::CompileDebug;
 pub use compile_empty::CompileEmpty;
 pub use compile_invoke::CompileInvoke;
+pub use compile_stream::CompileStream;
 pub use component_interface::ComponentInterface;
+pub use dead_assignment_removal::DeadAssignmentRemoval;
 pub use dead_cell_removal::DeadCellRemoval;
+pub use dead_component_removal::DeadComponentRemoval;
 pub use dead_group_removal::DeadGroupRemoval;
+pub use dedup_components::DedupComponents;
 pub use externalize::Externalize;
+pub use fsm_report::FsmReport;
 pub use go_insertion::GoInsertion;
+pub use group_fusion::GroupFusion;
 pub use group_to_invoke::GroupToInvoke;
 pub use guard_canonical::GuardCanonical;
+pub use hoist_invariant_groups::HoistInvariantGroups;
 pub use infer_static_timing::InferStaticTiming;
+pub use init_insertion::InitInsertion;
 pub use inliner::Inliner;
+pub use legalize_names::LegalizeNames;
+pub use list_schedule::ListSchedule;
+pub use live_range_splitting::LiveRangeSplitting;
 pub use lower_guards::LowerGuards;
+pub use lower_static::LowerStatic;
+pub use mem_to_reg::MemToReg;
+pub use memory_banking::MemoryBanking;
 pub use merge_assign::MergeAssign;
 pub use minimize_regs::MinimizeRegs;
+pub use outline::Outline;
 pub use papercut::Papercut;
 pub use par_to_seq::ParToSeq;
+pub use race_detection::RaceDetection;
 pub use register_unsharing::RegisterUnsharing;
 pub use remove_comb_groups::RemoveCombGroups;
+pub use remove_debug::RemoveDebug;
 pub use reset_insertion::ResetInsertion;
 pub use resource_sharing::ResourceSharing;
+pub use retime::Retime;
+pub use simplify_comparisons::SimplifyComparisons;
 pub use simplify_guards::SimplifyGuards;
+pub use simplify_with_control::SimplifyWithControl;
+pub use speculate_if::SpeculateIf;
+pub use strength_reduction::StrengthReduction;
 pub use synthesis_papercut::SynthesisPapercut;
 pub use top_down_compile_control::TopDownCompileControl;
+pub use unreachable_control::UnreachableControl;
 pub use well_formed::WellFormed;
+pub use width_reduction::WidthReduction;
+pub use wrap_main::WrapMain;
@@ -0,0 +1,630 @@
+use super::memory_banking::control_uses;
+use crate::analysis::ScheduleConflicts;
+use crate::errors::{CalyxResult, Error};
+use crate::ir::{
+    self,
+    traversal::{ConstructVisitor, Named, Visitor},
+    CloneName, LibrarySignatures, RRC,
+};
+use crate::utils::NameGenerator;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// Groups are outlined when at least this many of them are identical and no
+/// count is given.
+const DEFAULT_REPEATS: u64 = 2;
+
+/// Factors groups that are identical up to the renaming of cells into a new
+/// component and replaces their enables with `invoke` statements. This is the
+/// inverse of inlining and trades latency for area in generated code, such as
+/// unrolled loops, that repeats the same group many times.
+///
+/// Two groups are identical if they have the same assignments after the cells
+/// they use are numbered in the order of their first use. A cell is moved into
+/// the new component if it is a combinational primitive that is only used by
+/// the group; the instances of such cells must have the same parameters in
+/// every group. The other cells are connected to the ports of the component
+/// through the arguments of the `invoke`.
+///
+/// A group is outlined when:
+/// 1. It only writes its own `done` hole and reads no holes.
+/// 2. No other group or assignment uses its holes.
+/// 3. It reads only output ports and writes only input ports of the cells
+///    that are not moved into the new component.
+///
+/// Groups that run in parallel invoke different instances of the component.
+///
+/// The number of identical groups that are outlined is configured with
+/// `-x outline:min-repeats=<n>`, which defaults to `2`.
+///
+/// # Example
+/// ```calyx
+/// group upd0 { add0.left = x.out; add0.right = 32'd1; x.in = add0.out; x.write_en = 1'd1; upd0[done] = x.done; }
+/// group upd1 { add1.left = y.out; add1.right = 32'd1; y.in = add1.out; y.write_en = 1'd1; upd1[done] = y.done; }
+/// control { seq { upd0; upd1; } }
+/// ```
+/// becomes:
+/// ```calyx
+/// component outlined(x_out: 32, x_done: 1) -> (x_in: 32, x_write_en: 1) {
+///   cells { add0 = std_add(32); }
+///   wires { group upd0 { add0.left = x_out; ...; upd0[done] = x_done; } }
+///   control { upd0; }
+/// }
+/// ...
+/// control {
+///   seq {
+///     invoke outlined(x_out = x.out, x_done = x.done)(x_in = x.in, x_write_en = x.write_en);
+///     invoke outlined(x_out = y.out, x_done = y.done)(x_in = y.in, x_write_en = y.write_en);
+///   }
+/// }
+/// ```
+pub struct Outline {
+    /// Minimum number of identical groups that are outlined.
+    min_repeats: u64,
+}
+
+impl Named for Outline {
+    fn name() -> &'static str {
+        "outline"
+    }
+
+    fn description() -> &'static str {
+        "factor identical groups into a component that is invoked in their place"
+    }
+}
+
+impl ConstructVisitor for Outline {
+    fn from(ctx: &ir::Context) -> CalyxResult<Self>
+    where
+        Self: Sized + Named,
+    {
+        let mut min_repeats = DEFAULT_REPEATS;
+        for opt in &ctx.extra_opts {
+            let mut splits = opt.splitn(2, ':');
+            if splits.next() != Some(Self::name()) {
+                continue;
+            }
+            min_repeats = splits
+                .next()
+                .and_then(|opt| opt.strip_prefix("min-repeats="))
+                .and_then(|val| val.parse().ok())
+                .filter(|val| *val >= 2)
+                .ok_or_else(|| {
+                    Error::Misc(format!(
+                        "Malformed option `{}`. `{}` expects `{}:min-repeats=<n>` with `n` at least 2",
+                        opt,
+                        Self::name(),
+                        Self::name()
+                    ))
+                })?;
+        }
+        Ok(Outline { min_repeats })
+    }
+
+    fn clear_data(&mut self) {
+        /* All data can be transferred between components */
+    }
+}
+
+/// A group and the cells it uses, numbered in the order of their first use.
+/// Groups with the same key are identical up to the renaming of the cells.
+struct Shape {
+    group: RRC<ir::Group>,
+    cells: Vec<RRC<ir::Cell>>,
+    key: String,
+}
+
+impl Shape {
+    /// Key of a port of a cell. Returns `None` for holes and ports of cells
+    /// that are not moved into the component and cannot be connected to it.
+    fn port(
+        &mut self,
+        port: &RRC<ir::Port>,
+        local: &HashSet<ir::Id>,
+        is_dst: bool,
+    ) -> Option<String> {
+        let port = port.borrow();
+        let cell = match &port.parent {
+            ir::PortParent::Cell(cell) => cell.upgrade(),
+            ir::PortParent::Group(_) => return None,
+        };
+        let cell_ref = cell.borrow();
+        if let ir::CellType::Constant { val, width } = &cell_ref.prototype {
+            return Some(format!("{}'d{}", width, val));
+        }
+        let dir = if is_dst {
+            ir::Direction::Input
+        } else {
+            ir::Direction::Output
+        };
+        if !local.contains(cell_ref.name()) && port.direction != dir {
+            return None;
+        }
+        let idx = match self.cells.iter().position(|c| Rc::ptr_eq(c, &cell)) {
+            Some(idx) => idx,
+            None => {
+                self.cells.push(Rc::clone(&cell));
+                self.cells.len() - 1
+            }
+        };
+        Some(format!("c{}.{}:{}", idx, port.name, port.width))
+    }
+
+    /// Key of a guard.
+    fn guard(
+        &mut self,
+        guard: &ir::Guard,
+        local: &HashSet<ir::Id>,
+    ) -> Option<String> {
+        Some(match guard {
+            ir::Guard::True => "1".to_string(),
+            ir::Guard::Port(port) => self.port(port, local, false)?,
+            ir::Guard::Not(inner) => {
+                format!("!({})", self.guard(inner, local)?)
+            }
+            ir::Guard::And(l, r) | ir::Guard::Or(l, r) => format!(
+                "({} {} {})",
+                self.guard(l, local)?,
+                guard.op_str(),
+                self.guard(r, local)?
+            ),
+            ir::Guard::Eq(l, r)
+            | ir::Guard::Neq(l, r)
+            | ir::Guard::Gt(l, r)
+            | ir::Guard::Lt(l, r)
+            | ir::Guard::Geq(l, r)
+            | ir::Guard::Leq(l, r) => format!(
+                "({} {} {})",
+                self.port(l, local, false)?,
+                guard.op_str(),
+                self.port(r, local, false)?
+            ),
+        })
+    }
+
+    /// Build the shape of `group`. `local` are the cells that are moved into
+    /// the component if the group is outlined.
+    fn new(group: &RRC<ir::Group>, local: &HashSet<ir::Id>) -> Option<Self> {
+        let mut shape = Shape {
+            group: Rc::clone(group),
+            cells: vec![],
+            key: String::new(),
+        };
+        let group = group.borrow();
+        let mut done = false;
+        for assign in &group.assignments {
+            let dst = if assign.dst.borrow().is_hole() {
+                let (parent, name) = assign.dst.borrow().canonical();
+                if parent != group.name() || name != "done" {
+                    return None;
+                }
+                done = true;
+                "done".to_string()
+            } else {
+                shape.port(&assign.dst, local, true)?
+            };
+            let src = shape.port(&assign.src, local, false)?;
+            let guard = shape.guard(&assign.guard, local)?;
+            shape.key += &format!("{} = {} ? {};", dst, src, guard);
+        }
+        if !done {
+            return None;
+        }
+        if let Some(latency) = group.attributes.get("static") {
+            shape.key += &format!(" static={}", latency);
+        }
+        for cell in &shape.cells {
+            let cell = cell.borrow();
+            shape.key += &match &cell.prototype {
+                ir::CellType::Primitive {
+                    name,
+                    param_binding,
+                    string_binding,
+                    ..
+                } if local.contains(cell.name()) => {
+                    format!(" {}{:?}{:?}", name, param_binding, string_binding)
+                }
+                _ => " _".to_string(),
+            };
+        }
+        Some(shape)
+    }
+}
+
+/// The arguments of the `invoke` statements that replace a group.
+type Invocation = (
+    RRC<ir::Cell>,
+    Vec<(ir::Id, RRC<ir::Port>)>,
+    Vec<(ir::Id, RRC<ir::Port>)>,
+);
+
+/// Replace the enables of the groups in `invokes` with `invoke` statements.
+fn replace_enables(
+    con: &mut ir::Control,
+    invokes: &HashMap<ir::Id, Invocation>,
+) {
+    let invoke = match con {
+        ir::Control::Enable(ir::Enable { group, .. }) => {
+            invokes.get(&group.clone_name())
+        }
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => {
+            for stmt in stmts {
+                replace_enables(stmt, invokes);
+            }
+            None
+        }
+        ir::Control::If(ir::If {
+            tbranch, fbranch, ..
+        }) => {
+            replace_enables(tbranch, invokes);
+            replace_enables(fbranch, invokes);
+            None
+        }
+        ir::Control::While(ir::While { body, .. }) => {
+            replace_enables(body, invokes);
+            None
+        }
+        ir::Control::Invoke(_)
+        | ir::Control::Empty(_)
+        | ir::Control::Debug(_) => None,
+    };
+    if let Some((cell, inputs, outputs)) = invoke {
+        *con = ir::Control::invoke(
+            Rc::clone(cell),
+            inputs.clone(),
+            outputs.clone(),
+        );
+    }
+}
+
+/// Cells that can be moved into the component that outlines a group of
+/// `comp`: combinational primitives that are used by exactly one group.
+fn local_cells(comp: &ir::Component) -> HashSet<ir::Id> {
+    let mut pinned = HashSet::new();
+    control_uses(&comp.control.borrow(), &mut pinned);
+    let assign_cells = |assigns: &[ir::Assignment]| {
+        assigns
+            .iter()
+            .flat_map(|assign| {
+                let mut ports = assign.guard.all_ports();
+                ports.push(Rc::clone(&assign.src));
+                ports.push(Rc::clone(&assign.dst));
+                ports
+            })
+            .filter(|port| !port.borrow().is_hole())
+            .map(|port| port.borrow().get_parent_name())
+            .collect::<HashSet<_>>()
+    };
+    pinned.extend(assign_cells(&comp.continuous_assignments));
+    for group in comp.comb_groups.iter() {
+        pinned.extend(assign_cells(&group.borrow().assignments));
+    }
+    let mut users: HashMap<ir::Id, u64> = HashMap::new();
+    for group in comp.groups.iter() {
+        for cell in assign_cells(&group.borrow().assignments) {
+            *users.entry(cell).or_default() += 1;
+        }
+    }
+    comp.cells
+        .iter()
+        .filter(|cell| {
+            let cell = cell.borrow();
+            matches!(
+                cell.prototype,
+                ir::CellType::Primitive { is_comb: true, .. }
+            ) && !pinned.contains(cell.name())
+                && users.get(cell.name()) == Some(&1)
+        })
+        .map(|cell| cell.clone_name())
+        .collect()
+}
+
+/// Groups of `comp` whose holes are used outside of the group itself.
+fn hole_users(comp: &ir::Component) -> HashSet<ir::Id> {
+    let mut used = HashSet::new();
+    let mut add = |assigns: &[ir::Assignment], owner: Option<&ir::Id>| {
+        for assign in assigns {
+            let mut ports = assign.guard.all_ports();
+            ports.push(Rc::clone(&assign.src));
+            ports.push(Rc::clone(&assign.dst));
+            for port in ports {
+                let port = port.borrow();
+                if port.is_hole() && Some(&port.get_parent_name()) != owner {
+                    used.insert(port.get_parent_name());
+                }
+            }
+        }
+    };
+    add(&comp.continuous_assignments, None);
+    for group in comp.comb_groups.iter() {
+        add(&group.borrow().assignments, None);
+    }
+    for group in comp.groups.iter() {
+        let group = group.borrow();
+        add(&group.assignments, Some(group.name()));
+    }
+    used
+}
+
+impl Outline {
+    /// Build the component that runs the assignments of `rep`. Returns the
+    /// component and the ports of the cells of `rep` that are connected to
+    /// it as `(cell index, cell port, component port, is_input)`.
+    fn build_component(
+        name: &ir::Id,
+        rep: &Shape,
+        local: &HashSet<ir::Id>,
+        lib: &LibrarySignatures,
+    ) -> (ir::Component, Vec<(usize, ir::Id, ir::Id, bool)>) {
+        let group = rep.group.borrow();
+        let interface = [
+            ("go", ir::Direction::Input),
+            ("clk", ir::Direction::Input),
+            ("reset", ir::Direction::Input),
+            ("done", ir::Direction::Output),
+        ];
+        let mut namegen = NameGenerator::with_prev_defined_names(
+            interface.iter().map(|(name, _)| name.to_string()).collect(),
+        );
+        let mut ports = interface
+            .iter()
+            .map(|(name, dir)| {
+                let mut attrs = ir::Attributes::default();
+                attrs.insert(*name, 1);
+                (ir::Id::from(*name), 1, dir.clone(), attrs)
+            })
+            .collect::<Vec<_>>();
+
+        // Ports of the cells that are not moved into the component.
+        let mut exposed: Vec<(usize, ir::Id, ir::Id, bool)> = vec![];
+        let mut port_map: HashMap<(ir::Id, ir::Id), ir::Id> = HashMap::new();
+        for assign in &group.assignments {
+            let mut used = assign.guard.all_ports();
+            used.push(Rc::clone(&assign.src));
+            used.push(Rc::clone(&assign.dst));
+            for port in used {
+                let port = port.borrow();
+                if port.is_hole() {
+                    continue;
+                }
+                let (cell, name) = port.canonical();
+                let idx = match rep
+                    .cells
+                    .iter()
+                    .position(|c| *c.borrow().name() == cell)
+                {
+                    Some(idx) if !local.contains(&cell) => idx,
+                    _ => continue,
+                };
+                if port_map.contains_key(&(cell.clone(), name.clone())) {
+                    continue;
+                }
+                let comp_port = namegen.gen_name(format!("{}_{}", cell, name));
+                let is_input = port.direction == ir::Direction::Output;
+                let dir = port.direction.reverse();
+                ports.push((
+                    comp_port.clone(),
+                    port.width,
+                    dir,
+                    ir::Attributes::default(),
+                ));
+                port_map.insert((cell, name.clone()), comp_port.clone());
+                exposed.push((idx, name, comp_port, is_input));
+            }
+        }
+
+        let mut comp = ir::Component::new(name, ports);
+        let mut builder = ir::Builder::new(&mut comp, lib);
+        let new_group = builder.add_group(group.name().clone());
+        new_group.borrow_mut().attributes = group.attributes.clone();
+
+        // Ports of the component that replace the ports used by the group.
+        let mut ports: HashMap<(ir::Id, ir::Id), RRC<ir::Port>> =
+            HashMap::new();
+        ports.insert(
+            (group.name().clone(), "done".into()),
+            new_group.borrow().get("done"),
+        );
+        for ((cell, name), comp_port) in &port_map {
+            let port = builder.component.signature.borrow().get(comp_port);
+            ports.insert((cell.clone(), name.clone()), port);
+        }
+        for cell in &rep.cells {
+            let cell = cell.borrow();
+            let new_cell = match &cell.prototype {
+                ir::CellType::Primitive {
+                    name,
+                    param_binding,
+                    string_binding,
+                    ..
+                } if local.contains(cell.name()) => {
+                    let params = param_binding
+                        .iter()
+                        .map(|(_, val)| *val)
+                        .collect::<Vec<_>>();
+                    let strings = string_binding
+                        .iter()
+                        .map(|(_, val)| val.clone())
+                        .collect::<Vec<_>>();
+                    builder.add_primitive_with_strings(
+                        cell.name().clone(),
+                        name,
+                        &params,
+                        &strings,
+                    )
+                }
+                _ => continue,
+            };
+            for port in &cell.ports {
+                let name = port.borrow().name.clone();
+                let new_port = new_cell.borrow().get(&name);
+                ports.insert((cell.name().clone(), name), new_port);
+            }
+        }
+        // Constants are not part of the cells of the shape.
+        for assign in &group.assignments {
+            let mut used = assign.guard.all_ports();
+            used.push(Rc::clone(&assign.src));
+            for port in used {
+                let port = port.borrow();
+                if let ir::PortParent::Cell(cell) = &port.parent {
+                    let cell = cell.upgrade();
+                    let cell = cell.borrow();
+                    if let ir::CellType::Constant { val, width } =
+                        &cell.prototype
+                    {
+                        let constant = builder.add_constant(*val, *width);
+                        let out = constant.borrow().get("out");
+                        ports.insert(port.canonical(), out);
+                    }
+                }
+            }
+        }
+
+        let assigns = group
+            .assignments
+            .iter()
+            .map(|assign| {
+                let lookup = |port: &RRC<ir::Port>| {
+                    Rc::clone(&ports[&port.borrow().canonical()])
+                };
+                let mut guard = assign.guard.clone();
+                guard.for_each(&|port| Some(ir::Guard::Port(lookup(&port))));
+                builder.build_assignment(
+                    lookup(&assign.dst),
+                    lookup(&assign.src),
+                    *guard,
+                )
+            })
+            .collect();
+        new_group.borrow_mut().assignments = assigns;
+        *comp.control.borrow_mut() = ir::Control::enable(new_group);
+        (comp, exposed)
+    }
+
+    /// Outline the identical groups of `comp`. Returns the new components
+    /// and the number of groups that were outlined.
+    fn outline(
+        &self,
+        comp: &mut ir::Component,
+        lib: &LibrarySignatures,
+        namegen: &mut NameGenerator,
+    ) -> (Vec<ir::Component>, u64) {
+        let local = local_cells(comp);
+        let holes = hole_users(comp);
+
+        // Identical groups in the order of their first definition.
+        let mut classes: Vec<Vec<Shape>> = vec![];
+        for group in comp.groups.iter() {
+            if holes.contains(group.borrow().name()) {
+                continue;
+            }
+            let shape = match Shape::new(group, &local) {
+                Some(shape) => shape,
+                None => continue,
+            };
+            match classes.iter_mut().find(|class| class[0].key == shape.key) {
+                Some(class) => class.push(shape),
+                None => classes.push(vec![shape]),
+            }
+        }
+
+        let conflicts = ScheduleConflicts::from(&*comp.control.borrow());
+        let mut components = vec![];
+        let mut invokes: HashMap<ir::Id, Invocation> = HashMap::new();
+        let mut moved: HashSet<ir::Id> = HashSet::new();
+        for class in classes {
+            if (class.len() as u64) < self.min_repeats {
+                continue;
+            }
+            let name = namegen.gen_name("outlined");
+            let (outlined, exposed) =
+                Self::build_component(&name, &class[0], &local, lib);
+
+            // Groups that run in parallel use different instances.
+            let mut instances: Vec<(RRC<ir::Cell>, Vec<ir::Id>)> = vec![];
+            let mut builder = ir::Builder::new(comp, lib);
+            for shape in &class {
+                let group = shape.group.clone_name();
+                let idx = instances.iter().position(|(_, groups)| {
+                    groups.iter().all(|g| !conflicts.is_conflicting(g, &group))
+                });
+                let idx = match idx {
+                    Some(idx) => idx,
+                    None => {
+                        let cell =
+                            builder.add_component(name.clone(), &outlined);
+                        instances.push((cell, vec![]));
+                        instances.len() - 1
+                    }
+                };
+                instances[idx].1.push(group.clone());
+
+                let (mut inputs, mut outputs) = (vec![], vec![]);
+                for (cell_idx, port, comp_port, is_input) in &exposed {
+                    let port = shape.cells[*cell_idx].borrow().get(port);
+                    if *is_input {
+                        inputs.push((comp_port.clone(), port));
+                    } else {
+                        outputs.push((comp_port.clone(), port));
+                    }
+                }
+                let cell = Rc::clone(&instances[idx].0);
+                invokes.insert(group, (cell, inputs, outputs));
+                moved.extend(
+                    shape
+                        .cells
+                        .iter()
+                        .map(|cell| cell.clone_name())
+                        .filter(|cell| local.contains(cell)),
+                );
+            }
+            components.push(outlined);
+        }
+
+        let count = invokes.len() as u64;
+        if count > 0 {
+            replace_enables(&mut comp.control.borrow_mut(), &invokes);
+            comp.groups
+                .retain(|group| !invokes.contains_key(group.borrow().name()));
+            comp.cells
+                .retain(|cell| !moved.contains(cell.borrow().name()));
+        }
+        (components, count)
+    }
+}
+
+impl Visitor for Outline {
+    /// Works on the whole program since it adds components.
+    fn do_pass(&mut self, context: &mut ir::Context) -> CalyxResult<()>
+    where
+        Self: Sized + ConstructVisitor,
+    {
+        let mut namegen = NameGenerator::with_prev_defined_names(
+            context
+                .components
+                .iter()
+                .map(|comp| comp.name.to_string())
+                .chain(context.lib.signatures().map(|p| p.name.to_string()))
+                .collect(),
+        );
+        let mut idx = 0;
+        while idx < context.components.len() {
+            let comp = &mut context.components[idx];
+            let name = comp.name.clone();
+            let (outlined, count) =
+                self.outline(comp, &context.lib, &mut namegen);
+            if count > 0 {
+                context.stats.record(&name, "groups-outlined", count);
+            }
+            // Define the new components before the component that uses them.
+            let added = outlined.len();
+            for (offset, new) in outlined.into_iter().enumerate() {
+                context.components.insert(idx + offset, new);
+            }
+            idx += added + 1;
+        }
+        Ok(())
+    }
+}
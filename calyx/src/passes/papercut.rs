@@ -7,98 +7,115 @@ use crate::ir::{self, CloneName, LibrarySignatures};
 use itertools::Itertools;
 use std::collections::{HashMap, HashSet};
 
-/// Tuple containing (port, set of ports).
-/// When the first port is read from, all of the ports in the set must be written to.
-type ReadTogether = (ir::Id, HashSet<ir::Id>);
-
-/// Pass to check for common errors such as missing assignments to `done` holes
-/// of groups.
-pub struct Papercut {
-    /// Map from (primitive name) -> Vec<(set of ports)>
-    /// When any of the ports in a set is driven, all ports in that set must
-    /// be driven.
-    /// For example, when driving the `in` port of a register, the `write_en`
-    /// signal must also be driven.
-    write_together: HashMap<ir::Id, Vec<HashSet<ir::Id>>>,
-
-    /// Map from (primitive name) -> Vec<(port, set of ports)>
-    /// When the `port` in the tuple is being read from, all the ports in the
-    /// set must be driven.
-    read_together: HashMap<ir::Id, Vec<ReadTogether>>,
+/// A requirement on the ports of a cell that a group drives, declared by
+/// the attributes of the ports of the primitive or component it instantiates.
+enum Rule {
+    /// When any of the ports is written to, all of them must be written to.
+    /// Declared by `@write_together(n)`.
+    WriteTogether(HashSet<ir::Id>),
+    /// When the port is read from, all the ports in the set must be written
+    /// to. Declared by `@read_together(n)`.
+    ReadTogether(ir::Id, HashSet<ir::Id>),
 }
 
-/// Construct @write_together specs from the primitive definitions.
-fn write_together_specs<'a>(
-    primitives: impl Iterator<Item = &'a ir::Primitive>,
-) -> HashMap<ir::Id, Vec<HashSet<ir::Id>>> {
-    let mut write_together = HashMap::new();
-    for prim in primitives {
-        let writes: Vec<HashSet<ir::Id>> = prim
-            .find_all_with_attr("write_together")
-            .into_iter()
-            .map(|pd| {
-                (
-                    pd.attributes.get("write_together").unwrap(),
-                    pd.name.clone(),
-                )
-            })
-            .into_group_map()
-            .into_values()
-            .map(|writes| writes.into_iter().collect::<HashSet<_>>())
-            .collect();
-        if !writes.is_empty() {
-            write_together.insert(prim.name.clone(), writes);
-        }
+impl Rule {
+    /// Returns the rules declared by `spec`. Rules for reads come first so
+    /// that a missing address is reported before a partial write.
+    fn from_spec(spec: &ir::PortSpec) -> Vec<Rule> {
+        let reads = spec.read_together.iter().map(|(read, ports)| {
+            Rule::ReadTogether(read.clone(), ports.iter().cloned().collect())
+        });
+        let writes = spec
+            .write_together
+            .iter()
+            .map(|ports| Rule::WriteTogether(ports.iter().cloned().collect()));
+        reads.chain(writes).collect()
     }
-    write_together
-}
 
-/// Construct @read_together specs from the primitive definitions.
-fn read_together_specs<'a>(
-    primitives: impl Iterator<Item = &'a ir::Primitive>,
-) -> CalyxResult<HashMap<ir::Id, Vec<ReadTogether>>> {
-    let mut read_together = HashMap::new();
-    for prim in primitives {
-        let reads: Vec<ReadTogether> = prim
-                .find_all_with_attr("read_together")
-                .into_iter()
-                .map(|pd| (pd.attributes.get("read_together").unwrap(), pd))
-                .into_group_map()
-                .into_values()
-                .map(|ports| {
-                    let (outputs, inputs): (Vec<_>, Vec<_>) =
-                        ports.into_iter().partition(|&port| {
-                            matches!(port.direction, ir::Direction::Output)
-                        });
-                    // There should only be one port in the read_together specification.
-                    if outputs.len() != 1 {
-                        return Err(Error::Papercut(format!("Invalid @read_together specification for primitive `{}`. Each specification groups is only allowed to have one output port specified.", prim.name), prim.name.clone()))
-                    }
-                    assert!(outputs.len() == 1);
-                    Ok((
-                        outputs[0].name.clone(),
-                        inputs
-                            .into_iter()
-                            .map(|port| port.name.clone())
-                            .collect::<HashSet<_>>(),
-                    ))
-                })
-                .collect::<CalyxResult<_>>()?;
-        if !reads.is_empty() {
-            read_together.insert(prim.name.clone(), reads);
+    /// Check the rule against the ports of the cell `inst` that a group
+    /// reads from and writes to. Returns the reason it is violated, if any.
+    fn check(
+        &self,
+        inst: &ir::Id,
+        reads: &HashSet<ir::Id>,
+        writes: &HashSet<ir::Id>,
+    ) -> Option<String> {
+        let missing = |required: &HashSet<ir::Id>| {
+            required
+                .difference(writes)
+                .sorted()
+                .map(|port| format!("{}.{}", inst, port))
+                .join(", ")
+        };
+        match self {
+            Rule::ReadTogether(read, required) => {
+                if !reads.contains(read) || required.is_subset(writes) {
+                    return None;
+                }
+                Some(format!(
+                    "When read the port `{}.{}', the ports [{}] must be written to.",
+                    inst,
+                    read,
+                    missing(required)
+                ))
+            }
+            Rule::WriteTogether(required) => {
+                // It should either be the case that:
+                // 1. `writes` contains no writes that overlap with `required`.
+                // 2. `writes` contains all of `required`.
+                let first = required.intersection(writes).sorted().next()?;
+                if required.is_subset(writes) {
+                    return None;
+                }
+                Some(format!(
+                    "When writing to the port `{}.{}', the ports [{}] must also be written to.",
+                    inst,
+                    first,
+                    missing(required)
+                ))
+            }
         }
     }
-    Ok(read_together)
+}
+
+/// The rules of a primitive or component.
+struct Rules {
+    /// Description of the kind of type used in error messages.
+    kind: &'static str,
+    rules: Vec<Rule>,
+}
+
+/// Pass to check for common errors such as missing assignments to `done` holes
+/// of groups.
+///
+/// Groups must also drive the ports of the cells they use as required by the
+/// `@write_together` and `@read_together` attributes of the primitive or
+/// component the cells instantiate. For example, when driving the `in` port
+/// of a register, the `write_en` signal must also be driven.
+pub struct Papercut {
+    /// Map from the name of a primitive or component to its rules.
+    rules: HashMap<ir::Id, Rules>,
 }
 
 impl ConstructVisitor for Papercut {
     fn from(ctx: &ir::Context) -> CalyxResult<Self> {
-        let write_together = write_together_specs(ctx.lib.signatures());
-        let read_together = read_together_specs(ctx.lib.signatures())?;
-        Ok(Papercut {
-            write_together,
-            read_together,
-        })
+        let prims = ctx.lib.signatures().map(|prim| {
+            let spec = ctx.lib.port_spec(&prim.name).unwrap();
+            (prim.name.clone(), "primitive type", Rule::from_spec(spec))
+        });
+        let comps = ctx.components.iter().map(|comp| {
+            (
+                comp.name.clone(),
+                "component",
+                Rule::from_spec(&comp.port_spec()),
+            )
+        });
+        let rules = prims
+            .chain(comps)
+            .filter(|(_, _, rules)| !rules.is_empty())
+            .map(|(name, kind, rules)| (name, Rules { kind, rules }))
+            .collect();
+        Ok(Papercut { rules })
     }
 
     fn clear_data(&mut self) {
@@ -124,7 +141,7 @@ fn port_information(
     if let ir::PortParent::Cell(cell_wref) = &port.parent {
         let cell_ref = cell_wref.upgrade();
         let cell = cell_ref.borrow();
-        if let ir::CellType::Primitive { name, .. } = &cell.prototype {
+        if let Some(name) = cell.type_name() {
             return Some((
                 (cell.name().clone(), name.clone()),
                 port.name.clone(),
@@ -141,8 +158,11 @@ impl Visitor for Papercut {
         _ctx: &LibrarySignatures,
     ) -> VisResult {
         // If the control program is empty, check that the `done` signal
-        // has been assigned to.
-        if let ir::Control::Empty(..) = *comp.control.borrow() {
+        // has been assigned to. The `done` signal of static components is
+        // driven by `lower-static`.
+        if let (ir::Control::Empty(..), None) =
+            (&*comp.control.borrow(), comp.latency)
+        {
             let done_use =
                 comp.continuous_assignments.iter().find(|assign_ref| {
                     let assign = assign_ref.dst.borrow();
@@ -162,8 +182,8 @@ impl Visitor for Papercut {
         // `.write_en' port need to be driven.
         for group_ref in comp.groups.iter() {
             let group = group_ref.borrow();
-            // Build a map from (instance name, primitive name) to the signals being
-            // read from and written to.
+            // Build a map from (instance name, type name) to the signals
+            // being read from and written to.
             let all_writes =
                 analysis::ReadWriteSet::port_write_set(&group.assignments)
                     .filter_map(port_information)
@@ -175,72 +195,26 @@ impl Visitor for Papercut {
                     .into_grouping_map()
                     .collect::<HashSet<_>>();
 
-            for ((inst, comp_type), reads) in all_reads {
-                if let Some(spec) = self.read_together.get(&comp_type) {
-                    let empty = HashSet::new();
-                    let writes = all_writes
-                        .get(&(inst.clone(), comp_type.clone()))
-                        .unwrap_or(&empty);
-                    for (read, required) in spec {
-                        if reads.contains(read)
-                            && matches!(
-                                required.difference(writes).next(),
-                                Some(_)
-                            )
-                        {
-                            let missing = required
-                                .difference(writes)
-                                .sorted()
-                                .map(|port| {
-                                    format!("{}.{}", inst.clone(), port)
-                                })
-                                .join(", ");
-                            let msg =
-                                format!("Required signal not driven inside the group.\
-                                        \nWhen read the port `{}.{}', the ports [{}] must be written to.\
-                                        \nThe primitive type `{}' requires this invariant.",
-                                        inst,
-                                        read,
-                                        missing,
-                                        comp_type);
-                            return Err(Error::Papercut(
-                                msg,
-                                group.clone_name(),
-                            ));
-                        }
-                    }
-                }
-            }
-            // Check if this matches the `write_together' and `read_together' specification.
-            for ((inst, comp_type), writes) in all_writes {
-                if let Some(spec) = self.write_together.get(&comp_type) {
-                    for required in spec {
-                        // It should either be the case that:
-                        // 1. `writes` contains no writes that overlap with `required`
-                        //     In which case `required - writes` == `required`.
-                        // 2. `writes` contains writes that overlap with `required`
-                        //     In which case `required - writes == {}`
-                        let mut diff = required - &writes;
-                        if !diff.is_empty() && diff != *required {
-                            let first = writes.iter().sorted().next().unwrap();
-                            let missing = diff
-                                .drain()
-                                .sorted()
-                                .map(|port| format!("{}.{}", inst, port))
-                                .join(", ");
-                            let msg =
-                                format!("Required signal not driven inside the group.\
-                                        \nWhen writing to the port `{}.{}', the ports [{}] must also be written to.\
-                                        \nThe primitive type `{}' requires this invariant.",
-                                        inst,
-                                        first,
-                                        missing,
-                                        comp_type);
-                            return Err(Error::Papercut(
-                                msg,
-                                group.clone_name(),
-                            ));
-                        }
+            let empty = HashSet::new();
+            let cells =
+                all_reads.keys().chain(all_writes.keys()).unique().sorted();
+            for key in cells {
+                let (inst, comp_type) = key;
+                let spec = match self.rules.get(comp_type) {
+                    Some(spec) => spec,
+                    None => continue,
+                };
+                let reads = all_reads.get(key).unwrap_or(&empty);
+                let writes = all_writes.get(key).unwrap_or(&empty);
+                for rule in &spec.rules {
+                    if let Some(reason) = rule.check(inst, reads, writes) {
+                        let msg = format!(
+                            "Required signal not driven inside the group.\
+                             \n{}\
+                             \nThe {} `{}' requires this invariant.",
+                            reason, spec.kind, comp_type
+                        );
+                        return Err(Error::Papercut(msg, group.clone_name()));
                     }
                 }
             }
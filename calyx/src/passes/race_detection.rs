@@ -0,0 +1,94 @@
+use crate::analysis::{GuardDisjointness, ScheduleConflicts};
+use crate::errors::Error;
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, LibrarySignatures};
+use std::collections::HashMap;
+
+#[derive(Default)]
+/// Reports groups that may run in parallel and write to the same port. The
+/// value of such a port is undefined in the cycles where both groups are
+/// active.
+///
+/// Groups whose assignments to the port use guards that are proved disjoint
+/// by [GuardDisjointness] are not reported. Other guards that are never true
+/// in the same cycle are still reported, so this pass is not part of the
+/// `validate` alias.
+pub struct RaceDetection;
+
+impl Named for RaceDetection {
+    fn name() -> &'static str {
+        "race-detection"
+    }
+
+    fn description() -> &'static str {
+        "Detect groups that may run in parallel and write to the same port"
+    }
+}
+
+/// The ports written to by the group or combinational group `name` along
+/// with the guards of the assignments to them.
+fn group_writes(
+    comp: &ir::Component,
+    name: &ir::Id,
+) -> HashMap<(ir::Id, ir::Id), Vec<ir::Guard>> {
+    let writes = |assigns: &[ir::Assignment]| {
+        let mut writes: HashMap<_, Vec<_>> = HashMap::new();
+        for assign in assigns {
+            let dst = assign.dst.borrow();
+            if !dst.is_hole() {
+                writes
+                    .entry(dst.canonical())
+                    .or_default()
+                    .push((*assign.guard).clone());
+            }
+        }
+        writes
+    };
+    if let Some(group) = comp.find_group(name) {
+        writes(&group.borrow().assignments)
+    } else if let Some(group) = comp.find_comb_group(name) {
+        writes(&group.borrow().assignments)
+    } else {
+        HashMap::new()
+    }
+}
+
+impl Visitor for RaceDetection {
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let conflicts = ScheduleConflicts::from(&*comp.control.borrow());
+        for (g1, g2) in conflicts.all_conflicts() {
+            // A condition group shared by the children of a `par` conflicts
+            // with itself but always drives the same values.
+            if g1 == g2 {
+                continue;
+            }
+            let (g1, g2) = if g1 < g2 { (g1, g2) } else { (g2, g1) };
+            let writes1 = group_writes(comp, &g1);
+            let writes2 = group_writes(comp, &g2);
+            let race = writes1
+                .iter()
+                .filter(|(port, guards1)| {
+                    writes2.get(*port).is_some_and(|guards2| {
+                        !guards1.iter().all(|g1| {
+                            guards2
+                                .iter()
+                                .all(|g2| GuardDisjointness::disjoint(g1, g2))
+                        })
+                    })
+                })
+                .map(|(port, _)| port)
+                .min();
+            if let Some((cell, port)) = race {
+                let msg = format!("Groups `{}' and `{}' may run in parallel and both write to the port `{}.{}'. The value of the port is undefined when both groups are active.", g1, g2, cell, port);
+                return Err(Error::Papercut(msg, g2));
+            }
+        }
+
+        // This pass doesn't modify any control.
+        Ok(Action::Stop)
+    }
+}
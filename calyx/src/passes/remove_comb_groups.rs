@@ -312,12 +312,15 @@ impl Visitor for RemoveCombGroups {
                 std::mem::replace(s.tbranch.as_mut(), ir::Control::empty());
             let fbranch =
                 std::mem::replace(s.fbranch.as_mut(), ir::Control::empty());
-            let if_ = ir::Control::if_(
+            let mut if_ = ir::Control::if_(
                 Rc::clone(port_ref),
                 None,
                 Box::new(tbranch),
                 Box::new(fbranch),
             );
+            if let Some(attrs) = if_.get_mut_attributes() {
+                *attrs = std::mem::take(&mut s.attributes);
+            }
             let cond = ir::Control::enable(Rc::clone(cond_ref));
             Ok(Action::Change(ir::Control::seq(vec![cond, if_])))
         } else {
@@ -0,0 +1,28 @@
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, Component, Control, LibrarySignatures};
+
+#[derive(Default)]
+/// Removes all [`ir::Debug`](crate::ir::Debug) statements so that the values
+/// are only printed by the interpreter.
+pub struct RemoveDebug;
+
+impl Named for RemoveDebug {
+    fn name() -> &'static str {
+        "remove-debug"
+    }
+
+    fn description() -> &'static str {
+        "Removes @debug statements from the control program"
+    }
+}
+
+impl Visitor for RemoveDebug {
+    fn debug(
+        &mut self,
+        _s: &mut ir::Debug,
+        _comp: &mut Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        Ok(Action::Change(Control::empty()))
+    }
+}
@@ -0,0 +1,496 @@
+use super::group_fusion::static_latency;
+use super::math_utilities::get_bit_width_from;
+use crate::errors::{CalyxResult, Error};
+use crate::ir::traversal::{
+    Action, ConstructVisitor, Named, VisResult, Visitor,
+};
+use crate::ir::{self, CloneName, GetAttributes, LibrarySignatures, RRC};
+use crate::{build_assignments, guard, structure};
+use linked_hash_map::LinkedHashMap;
+use std::collections::HashMap;
+use std::iter;
+use std::rc::Rc;
+
+/// Maximum number of combinational primitives on a path when no depth is
+/// given.
+const DEFAULT_DEPTH: u64 = 4;
+
+/// Inserts pipeline registers into static groups whose combinational paths
+/// go through more than `depth` primitives.
+///
+/// A group is transformed when:
+/// 1. It is `static` for one cycle and does not use holes other than its
+///    `done` hole.
+/// 2. Its `done` condition is `r.done` for a `std_reg` named `r` that is
+///    written with `r.write_en = 1'd1`.
+/// 3. Every other assignment writes to `r.in` or to an input of a
+///    combinational primitive.
+///
+/// The inputs of the paths are then stable while the group runs, so a
+/// register placed on a path only delays the value on the path. The depth of
+/// a primitive is the number of combinational primitives on the longest path
+/// from a register or the start of the path to its output. An output whose
+/// depth is `depth` is registered when it is read by another combinational
+/// primitive. Paths through continuous assignments are not considered.
+///
+/// The pipeline registers are written in every cycle of the group and `r` is
+/// written once the values have propagated through all of them, which is
+/// tracked by a counter. The `static` attributes of the group and of the
+/// control statements and components that use it are updated.
+///
+/// # Example
+/// With `-x retime:depth=1`:
+/// ```calyx
+/// group incr<"static"=1> {
+///   add0.left = x.out; add0.right = 32'd1;
+///   add1.left = add0.out; add1.right = 32'd1;
+///   r.in = add1.out; r.write_en = 1'd1; incr[done] = r.done;
+/// }
+/// ```
+/// becomes:
+/// ```calyx
+/// group incr<"static"=2> {
+///   add0.left = x.out; add0.right = 32'd1;
+///   pipe.in = add0.out; pipe.write_en = 1'd1;
+///   add1.left = pipe.out; add1.right = 32'd1;
+///   r.in = add1.out; r.write_en = fsm.out == 1'd1 ? 1'd1; incr[done] = r.done;
+///   ...
+/// }
+/// ```
+/// where `fsm` counts the cycles of the group.
+pub struct Retime {
+    /// Maximum number of combinational primitives on a path.
+    depth: u64,
+    /// Latencies of the components whose latency changed.
+    latencies: HashMap<ir::Id, u64>,
+    /// Set when the latency of a group or an invoke in the current component
+    /// changed.
+    changed: bool,
+    /// Number of groups that were pipelined.
+    groups: u64,
+    /// Number of pipeline registers that were inserted.
+    registers: u64,
+}
+
+impl Named for Retime {
+    fn name() -> &'static str {
+        "retime"
+    }
+
+    fn description() -> &'static str {
+        "insert pipeline registers on long combinational paths in static groups"
+    }
+}
+
+impl ConstructVisitor for Retime {
+    fn from(ctx: &ir::Context) -> CalyxResult<Self>
+    where
+        Self: Sized + Named,
+    {
+        let mut depth = DEFAULT_DEPTH;
+        for opt in &ctx.extra_opts {
+            let mut splits = opt.splitn(2, ':');
+            if splits.next() != Some(Self::name()) {
+                continue;
+            }
+            depth = splits
+                .next()
+                .and_then(|opt| opt.strip_prefix("depth="))
+                .and_then(|val| val.parse().ok())
+                .filter(|depth| *depth > 0)
+                .ok_or_else(|| {
+                    Error::Misc(format!(
+                        "Malformed option `{}`. `{}` expects `{}:depth=<n>` where `n` is at least 1",
+                        opt,
+                        Self::name(),
+                        Self::name()
+                    ))
+                })?;
+        }
+        Ok(Retime {
+            depth,
+            latencies: HashMap::new(),
+            changed: false,
+            groups: 0,
+            registers: 0,
+        })
+    }
+
+    fn clear_data(&mut self) {
+        // The latencies of the components are used by their invokes.
+        self.changed = false;
+        self.groups = 0;
+        self.registers = 0;
+    }
+}
+
+/// The combinational primitive that `port` belongs to.
+fn comb_cell(port: &RRC<ir::Port>) -> Option<ir::Id> {
+    if let ir::PortParent::Cell(cell) = &port.borrow().parent {
+        let cell = cell.upgrade();
+        let cell = cell.borrow();
+        if let ir::CellType::Primitive { is_comb: true, .. } = cell.prototype {
+            return Some(cell.clone_name());
+        }
+    }
+    None
+}
+
+/// The register whose `done` signal is the `done` condition of `group` if
+/// the group can be pipelined.
+fn sink(group: &RRC<ir::Group>) -> Option<RRC<ir::Cell>> {
+    if static_latency(group)? != 1 {
+        return None;
+    }
+    let group = group.borrow();
+    let mut dones = group.assignments.iter().filter(|assign| {
+        let dst = assign.dst.borrow();
+        dst.is_hole() && dst.name == "done"
+    });
+    let done = dones.next()?;
+    if dones.next().is_some() || !done.guard.is_true() {
+        return None;
+    }
+    let reg = match &done.src.borrow().parent {
+        ir::PortParent::Cell(cell) => cell.upgrade(),
+        ir::PortParent::Group(_) => return None,
+    };
+    let is_reg = matches!(
+        &reg.borrow().prototype,
+        ir::CellType::Primitive { name, .. } if name == "std_reg"
+    );
+    if done.src.borrow().name != "done" || !is_reg {
+        return None;
+    }
+    let name = reg.clone_name();
+    let mut write_ens = 0;
+    for assign in &group.assignments {
+        let dst = assign.dst.borrow();
+        if dst.is_hole() {
+            continue;
+        }
+        if dst.get_parent_name() == name {
+            match dst.name.as_ref() {
+                "in" => (),
+                "write_en"
+                    if assign.guard.is_true()
+                        && assign.src.borrow().is_constant(1, 1) =>
+                {
+                    write_ens += 1
+                }
+                _ => return None,
+            }
+        } else if comb_cell(&assign.dst).is_none() {
+            return None;
+        }
+    }
+    (write_ens == 1).then_some(reg)
+}
+
+/// Chooses the ports that are registered in a group.
+struct Pipeline<'a> {
+    assigns: &'a [ir::Assignment],
+    depth: u64,
+    /// The depth of each visited primitive and the number of registers on
+    /// the longest path to its output. `None` while the primitive is being
+    /// visited.
+    cells: HashMap<ir::Id, Option<(u64, u64)>>,
+    /// The registered ports.
+    cuts: LinkedHashMap<(ir::Id, ir::Id), RRC<ir::Port>>,
+}
+
+impl Pipeline<'_> {
+    /// Ports read by the assignments to the ports of the primitive `cell`.
+    fn reads(&self, cell: &ir::Id) -> Vec<RRC<ir::Port>> {
+        self.assigns
+            .iter()
+            .filter(|assign| assign.dst.borrow().get_parent_name() == cell)
+            .flat_map(|assign| {
+                iter::once(Rc::clone(&assign.src))
+                    .chain(assign.guard.all_ports())
+            })
+            .collect()
+    }
+
+    /// The depth of the primitive and the number of registers on the
+    /// longest path to its output. Returns `None` if the primitive is part of
+    /// a combinational loop.
+    fn visit(&mut self, cell: &ir::Id) -> Option<(u64, u64)> {
+        match self.cells.get(cell) {
+            Some(visited) => return *visited,
+            None => self.cells.insert(cell.clone(), None),
+        };
+        let (mut depth, mut stages) = (0, 0);
+        for port in self.reads(cell) {
+            let pred = match comb_cell(&port) {
+                Some(pred) => pred,
+                None => continue,
+            };
+            let (pred_depth, pred_stages) = self.visit(&pred)?;
+            if pred_depth >= self.depth {
+                self.cuts
+                    .entry(port.borrow().canonical())
+                    .or_insert_with(|| Rc::clone(&port));
+                stages = stages.max(pred_stages + 1);
+            } else {
+                depth = depth.max(pred_depth);
+                stages = stages.max(pred_stages);
+            }
+        }
+        self.cells.insert(cell.clone(), Some((depth + 1, stages)));
+        Some((depth + 1, stages))
+    }
+
+    /// The number of registers on the longest path to `reg.in`.
+    fn stages(&mut self, reg: &ir::Id) -> Option<u64> {
+        let mut stages = 0;
+        for port in self.reads(reg) {
+            if let Some(pred) = comb_cell(&port) {
+                stages = stages.max(self.visit(&pred)?.1);
+            }
+        }
+        Some(stages)
+    }
+}
+
+impl Retime {
+    /// Pipeline `group` and return its new latency.
+    fn retime_group(
+        &mut self,
+        group: &RRC<ir::Group>,
+        builder: &mut ir::Builder,
+    ) -> Option<u64> {
+        let reg = sink(group)?;
+        let grp = group.borrow();
+        let mut pipeline = Pipeline {
+            assigns: &grp.assignments,
+            depth: self.depth,
+            cells: HashMap::new(),
+            cuts: LinkedHashMap::new(),
+        };
+        let stages = pipeline.stages(reg.borrow().name())?;
+        if stages == 0 {
+            return None;
+        }
+        let cuts = pipeline.cuts;
+        drop(grp);
+
+        // Register the ports and read the registers instead.
+        let mut regs = HashMap::new();
+        let mut assigns = vec![];
+        structure!(builder;
+            let signal_on = constant(1, 1);
+        );
+        for (key, port) in cuts {
+            let width = port.borrow().width;
+            let pipe = builder.add_primitive("pipe", "std_reg", &[width]);
+            assigns.push(builder.build_assignment(
+                pipe.borrow().get("in"),
+                port,
+                ir::Guard::True,
+            ));
+            assigns.extend(build_assignments!(builder;
+                pipe["write_en"] = ? signal_on["out"];
+            ));
+            regs.insert(key, pipe.borrow().get("out"));
+        }
+        self.registers += regs.len() as u64;
+        let rewrite = |port: &RRC<ir::Port>| {
+            regs.get(&port.borrow().canonical()).map(Rc::clone)
+        };
+        for assign in group.borrow_mut().assignments.iter_mut() {
+            if comb_cell(&assign.dst).is_none() {
+                continue;
+            }
+            if let Some(port) = rewrite(&assign.src) {
+                assign.src = port;
+            }
+            assign
+                .guard
+                .for_each(&|port| rewrite(&port).map(ir::Guard::port));
+        }
+
+        // Write to the register once the values have propagated.
+        let fsm_size = get_bit_width_from(stages + 1);
+        structure!(builder;
+            let fsm = prim std_reg(fsm_size);
+            let incr = prim std_add(fsm_size);
+            let one = constant(1, fsm_size);
+            let last = constant(stages, fsm_size);
+            let reset_val = constant(0, fsm_size);
+        );
+        let done_guard = guard!(fsm["out"]).eq(guard!(last["out"]));
+        let not_done_guard = !done_guard.clone();
+        let write_en = reg.borrow().get("write_en");
+        for assign in group.borrow_mut().assignments.iter_mut() {
+            if Rc::ptr_eq(&assign.dst, &write_en) {
+                *assign.guard = done_guard.clone();
+            }
+        }
+        assigns.extend(build_assignments!(builder;
+            incr["left"] = ? one["out"];
+            incr["right"] = ? fsm["out"];
+            fsm["in"] = not_done_guard ? incr["out"];
+            fsm["write_en"] = not_done_guard ? signal_on["out"];
+        ));
+        let latency = stages + 1;
+        let mut group = group.borrow_mut();
+        group.assignments.append(&mut assigns);
+        group.attributes.insert("static", latency);
+
+        // CLEANUP: Reset the counter once the register is written.
+        let mut cleanup = build_assignments!(builder;
+            fsm["in"] = done_guard ? reset_val["out"];
+            fsm["write_en"] = done_guard ? signal_on["out"];
+        );
+        builder
+            .component
+            .continuous_assignments
+            .append(&mut cleanup);
+
+        self.groups += 1;
+        Some(latency)
+    }
+
+    /// Replace the latency of the statement if it has one.
+    fn update(&self, attributes: &mut ir::Attributes, latency: Option<u64>) {
+        if let Some(latency) = latency.filter(|_| self.changed) {
+            if attributes.has("static") {
+                attributes.insert("static", latency);
+            }
+        }
+    }
+}
+
+fn latency(con: &ir::Control) -> Option<u64> {
+    con.get_attributes()
+        .and_then(|attrs| attrs.get("static"))
+        .copied()
+}
+
+impl Visitor for Retime {
+    // The latencies of components are needed by the invokes of their users.
+    fn require_postorder() -> bool {
+        true
+    }
+
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("groups-pipelined", self.groups),
+            ("registers-inserted", self.registers),
+        ]
+    }
+
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let groups = comp.groups.iter().cloned().collect::<Vec<_>>();
+        let mut builder = ir::Builder::new(comp, sigs);
+        for group in &groups {
+            if self.retime_group(group, &mut builder).is_some() {
+                self.changed = true;
+            }
+        }
+        Ok(Action::Continue)
+    }
+
+    fn enable(
+        &mut self,
+        s: &mut ir::Enable,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let latency = s.group.borrow().attributes.get("static").copied();
+        self.update(&mut s.attributes, latency);
+        Ok(Action::Continue)
+    }
+
+    fn invoke(
+        &mut self,
+        s: &mut ir::Invoke,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let latency = s
+            .comp
+            .borrow()
+            .type_name()
+            .and_then(|name| self.latencies.get(name))
+            .copied();
+        if latency.is_some() && s.interface.is_none() {
+            self.changed = true;
+            self.update(&mut s.attributes, latency);
+        }
+        Ok(Action::Continue)
+    }
+
+    fn finish_seq(
+        &mut self,
+        s: &mut ir::Seq,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let latency = s.stmts.iter().map(latency).sum();
+        self.update(&mut s.attributes, latency);
+        Ok(Action::Continue)
+    }
+
+    fn finish_par(
+        &mut self,
+        s: &mut ir::Par,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let latency = s
+            .stmts
+            .iter()
+            .map(latency)
+            .try_fold(0, |acc, l| l.map(|l| acc.max(l)));
+        self.update(&mut s.attributes, latency);
+        Ok(Action::Continue)
+    }
+
+    fn finish_if(
+        &mut self,
+        s: &mut ir::If,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let latency = latency(&s.tbranch)
+            .zip(latency(&s.fbranch))
+            .map(|(t, f)| 1 + t.max(f));
+        self.update(&mut s.attributes, latency);
+        Ok(Action::Continue)
+    }
+
+    fn finish_while(
+        &mut self,
+        s: &mut ir::While,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let latency = s
+            .attributes
+            .get("bound")
+            .zip(latency(&s.body))
+            .map(|(bound, body)| bound * body);
+        self.update(&mut s.attributes, latency);
+        Ok(Action::Continue)
+    }
+
+    fn finish(
+        &mut self,
+        comp: &mut ir::Component,
+        _lib: &LibrarySignatures,
+    ) -> VisResult {
+        if self.changed && comp.attributes.has("static") {
+            if let Some(time) = latency(&comp.control.borrow()) {
+                comp.attributes.insert("static", time);
+                self.latencies.insert(comp.name.clone(), time);
+            }
+        }
+        Ok(Action::Continue)
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    analysis::{GraphColoring, ScheduleConflicts},
+    analysis::{GraphColoring, ReadWriteSet, ScheduleConflicts},
     ir,
 };
 use ir::{
@@ -7,7 +7,10 @@ use ir::{
     CloneName, RRC,
 };
 use itertools::Itertools;
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 
 /// A trait for implementing passes that want to share components
 /// by building a conflict graph and performing graph coloring
@@ -31,6 +34,7 @@ use std::{collections::HashMap, rc::Rc};
 ///  - call `custom_conflicts` to insert pass specific conflict edges
 ///  - perform graph coloring using `self.ordering` to define the order of the greedy coloring
 ///  - use coloring to rewrite group assignments, continuous assignments, and conditional ports.
+///  - if `remove_replaced` holds, remove the replaced cells that are no longer used.
 pub trait ShareComponents {
     /// Initialize the structure using `&ir::Component` and `&ir::LibrarySignatures`.
     /// This function is called at the very beginning of the traversal
@@ -74,9 +78,24 @@ pub trait ShareComponents {
 
     /// Get the list of rewrites.
     fn get_rewrites(&self) -> &[(RRC<ir::Cell>, RRC<ir::Cell>)];
+
+    /// Whether the cells that were replaced by another cell are removed
+    /// once they are no longer used. The default implementation keeps them.
+    fn remove_replaced(&self) -> bool {
+        false
+    }
 }
 
 impl<T: ShareComponents> Visitor for T {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        let shared = self
+            .get_rewrites()
+            .iter()
+            .filter(|(old, new)| !Rc::ptr_eq(old, new))
+            .count();
+        vec![("cells-shared", shared as u64)]
+    }
+
     fn start(
         &mut self,
         comp: &mut ir::Component,
@@ -108,40 +127,24 @@ impl<T: ShareComponents> Visitor for T {
                 })
                 .collect();
 
+        // Cells used by groups that may run in parallel conflict with each
+        // other.
         let par_conflicts = ScheduleConflicts::from(&*comp.control.borrow());
-        let group_conflicts = par_conflicts
-            .all_conflicts()
-            .into_grouping_map_by(|(g1, _)| g1.clone())
-            .fold(
-                HashMap::new(),
-                |mut acc: HashMap<ir::CellType, Vec<ir::Id>>,
-                 _,
-                 (_, conflicted_group)| {
-                    for conflict in
-                        self.lookup_group_conflicts(&conflicted_group)
-                    {
-                        acc.entry(id_to_type[&conflict].clone())
-                            .and_modify(|v| v.push(conflict.clone()))
-                            .or_insert_with(|| vec![conflict]);
-                    }
-                    acc
-                },
-            );
-
-        group_conflicts
-            .into_iter()
-            .for_each(|(group, conflict_group_b)| {
-                for a in self.lookup_group_conflicts(&group) {
-                    let g = graphs_by_type.get_mut(&id_to_type[&a]).unwrap();
-                    if let Some(confs) = conflict_group_b.get(&id_to_type[&a]) {
-                        for b in confs {
-                            if a != b {
-                                g.insert_conflict(&a, b);
-                            }
-                        }
+        for (g1, g2) in par_conflicts.all_conflicts() {
+            let confs2 = self.lookup_group_conflicts(&g2);
+            for a in self.lookup_group_conflicts(&g1) {
+                for b in
+                    confs2.iter().filter(|b| id_to_type[&a] == id_to_type[*b])
+                {
+                    if a != *b {
+                        graphs_by_type
+                            .get_mut(&id_to_type[&a])
+                            .unwrap()
+                            .insert_conflict(&a, b);
                     }
                 }
-            });
+            }
+        }
 
         // add custom conflicts
         self.custom_conflicts(comp, |confs: Vec<ir::Id>| {
@@ -307,4 +310,46 @@ impl<T: ShareComponents> Visitor for T {
 
         Ok(Action::Continue)
     }
+
+    fn finish(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &ir::LibrarySignatures,
+    ) -> VisResult {
+        if !self.remove_replaced() {
+            return Ok(Action::Continue);
+        }
+        let replaced: HashSet<ir::Id> = self
+            .get_rewrites()
+            .iter()
+            .filter(|(old, new)| !Rc::ptr_eq(old, new))
+            .map(|(old, _)| old.clone_name())
+            .collect();
+        let mut used: HashSet<ir::Id> =
+            ReadWriteSet::control_uses(&comp.control.borrow())
+                .map(|cell| cell.clone_name())
+                .collect();
+        for group in comp.groups.iter() {
+            used.extend(
+                ReadWriteSet::uses(&group.borrow().assignments)
+                    .map(|cell| cell.clone_name()),
+            );
+        }
+        for group in comp.comb_groups.iter() {
+            used.extend(
+                ReadWriteSet::uses(&group.borrow().assignments)
+                    .map(|cell| cell.clone_name()),
+            );
+        }
+        used.extend(
+            ReadWriteSet::uses(&comp.continuous_assignments)
+                .map(|cell| cell.clone_name()),
+        );
+        comp.cells.retain(|cell| {
+            let name = cell.clone_name();
+            !replaced.contains(&name) || used.contains(&name)
+        });
+        comp.purge_deleted();
+        Ok(Action::Continue)
+    }
 }
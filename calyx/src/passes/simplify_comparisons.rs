@@ -0,0 +1,284 @@
+use crate::analysis::{Range, ValueRanges};
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, CloneName, LibrarySignatures, RRC};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+#[derive(Default)]
+/// Replaces comparisons whose result is always the same with constants,
+/// using the ranges of values computed by [ValueRanges]:
+/// - Comparisons in guards between registers and constants, such as
+///   `i.out < 32'd16` when `i` never holds more than 10, become `1'b1` or
+///   `!1'b1`.
+/// - Comparison primitives (`std_lt`, `std_le`, `std_gt`, `std_ge`,
+///   `std_eq`, and `std_neq`) are removed and their output is replaced with
+///   `1'd1` or `1'd0`. This is only done if the inputs of the cell are only
+///   written by unguarded assignments from registers and constants, which
+///   happen in every group and combinational group that reads its output,
+///   and the output is not used by the control program.
+///
+/// The assignments whose guards become false are removed by
+/// `dead-assignment-removal`.
+pub struct SimplifyComparisons {
+    /// Outputs of the removed comparisons and their values.
+    rewrites: HashMap<(ir::Id, ir::Id), (bool, RRC<ir::Port>)>,
+    /// Number of comparisons that were replaced.
+    simplified: u64,
+}
+
+impl Named for SimplifyComparisons {
+    fn name() -> &'static str {
+        "simplify-comparisons"
+    }
+
+    fn description() -> &'static str {
+        "replace comparisons that always have the same result with constants"
+    }
+}
+
+/// The constant guard with value `val`.
+fn constant(val: bool) -> ir::Guard {
+    if val {
+        ir::Guard::True
+    } else {
+        !ir::Guard::True
+    }
+}
+
+/// The range of `port` if it is a register or a constant, whose values are
+/// known in every cycle.
+fn stable_range(ranges: &ValueRanges, port: &RRC<ir::Port>) -> Option<Range> {
+    let is_stable = match &port.borrow().parent {
+        ir::PortParent::Cell(cell) => {
+            match &cell.upgrade().borrow().prototype {
+                ir::CellType::Constant { .. } => true,
+                ir::CellType::Primitive { name, .. } => name == "std_reg",
+                _ => false,
+            }
+        }
+        ir::PortParent::Group(_) => false,
+    };
+    if is_stable {
+        ranges.port(port)
+    } else {
+        None
+    }
+}
+
+/// Records the ports read by `con`.
+fn control_reads(con: &ir::Control, reads: &mut HashSet<(ir::Id, ir::Id)>) {
+    match con {
+        ir::Control::Invoke(ir::Invoke { inputs, .. }) => {
+            reads.extend(inputs.iter().map(|(_, src)| src.borrow().canonical()))
+        }
+        ir::Control::Debug(ir::Debug { ports, .. }) => {
+            reads.extend(ports.iter().map(|port| port.borrow().canonical()))
+        }
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => {
+            for stmt in stmts {
+                control_reads(stmt, reads);
+            }
+        }
+        ir::Control::If(ir::If {
+            port,
+            tbranch,
+            fbranch,
+            ..
+        }) => {
+            reads.insert(port.borrow().canonical());
+            control_reads(tbranch, reads);
+            control_reads(fbranch, reads);
+        }
+        ir::Control::While(ir::While { port, body, .. }) => {
+            reads.insert(port.borrow().canonical());
+            control_reads(body, reads);
+        }
+        ir::Control::Enable(_) | ir::Control::Empty(_) => {}
+    }
+}
+
+impl SimplifyComparisons {
+    /// Replace the comparisons in `guard` that are always true or false.
+    fn simplify(&mut self, guard: &mut ir::Guard, ranges: &ValueRanges) {
+        let (prim, l, r) = match guard {
+            ir::Guard::And(l, r) | ir::Guard::Or(l, r) => {
+                self.simplify(l, ranges);
+                self.simplify(r, ranges);
+                return;
+            }
+            ir::Guard::Not(g) => {
+                self.simplify(g, ranges);
+                return;
+            }
+            ir::Guard::Port(port) => {
+                let val = self
+                    .rewrites
+                    .get(&port.borrow().canonical())
+                    .map(|(val, _)| *val);
+                if let Some(val) = val {
+                    *guard = constant(val);
+                }
+                return;
+            }
+            ir::Guard::True => return,
+            ir::Guard::Eq(l, r) => ("std_eq", l, r),
+            ir::Guard::Neq(l, r) => ("std_neq", l, r),
+            ir::Guard::Lt(l, r) => ("std_lt", l, r),
+            ir::Guard::Gt(l, r) => ("std_gt", l, r),
+            ir::Guard::Leq(l, r) => ("std_le", l, r),
+            ir::Guard::Geq(l, r) => ("std_ge", l, r),
+        };
+        let val = stable_range(ranges, l)
+            .zip(stable_range(ranges, r))
+            .and_then(|(l, r)| l.compare(prim, r));
+        for port in [l, r] {
+            let new_port = self
+                .rewrites
+                .get(&port.borrow().canonical())
+                .map(|(_, new_port)| Rc::clone(new_port));
+            if let Some(new_port) = new_port {
+                *port = new_port;
+            }
+        }
+        if let Some(val) = val {
+            *guard = constant(val);
+            self.simplified += 1;
+        }
+    }
+
+    /// Rewrite the reads of the removed comparisons in `assigns` and
+    /// simplify their guards.
+    fn rewrite_assigns(
+        &mut self,
+        assigns: &mut [ir::Assignment],
+        ranges: &ValueRanges,
+    ) {
+        for assign in assigns {
+            let port = self
+                .rewrites
+                .get(&assign.src.borrow().canonical())
+                .map(|(_, port)| Rc::clone(port));
+            if let Some(port) = port {
+                assign.src = port;
+            }
+            self.simplify(&mut assign.guard, ranges);
+        }
+    }
+}
+
+impl Visitor for SimplifyComparisons {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![("comparisons-simplified", self.simplified)]
+    }
+
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let ranges = ValueRanges::from(&*comp);
+
+        // Groups in which each port is read, and groups in which each port
+        // is written by an unguarded assignment from a register or a
+        // constant. `None` stands for the continuous assignments.
+        let mut reads: HashMap<_, HashSet<Option<ir::Id>>> = HashMap::new();
+        let mut driven: HashMap<_, HashSet<Option<ir::Id>>> = HashMap::new();
+        // Ports written by any other assignment.
+        let mut unstable = HashSet::new();
+        let mut visit = |group: Option<&ir::Id>, assign: &ir::Assignment| {
+            for port in assign.guard.all_ports().iter().chain([&assign.src]) {
+                reads
+                    .entry(port.borrow().canonical())
+                    .or_default()
+                    .insert(group.cloned());
+            }
+            let dst = assign.dst.borrow().canonical();
+            if assign.guard.is_true()
+                && stable_range(&ranges, &assign.src).is_some()
+            {
+                driven.entry(dst).or_default().insert(group.cloned());
+            } else {
+                unstable.insert(dst);
+            }
+        };
+        for group in comp.groups.iter() {
+            let group = group.borrow();
+            for assign in &group.assignments {
+                visit(Some(group.name()), assign);
+            }
+        }
+        for group in comp.comb_groups.iter() {
+            let group = group.borrow();
+            for assign in &group.assignments {
+                visit(Some(group.name()), assign);
+            }
+        }
+        for assign in &comp.continuous_assignments {
+            visit(None, assign);
+        }
+        let mut used_by_control = HashSet::new();
+        control_reads(&comp.control.borrow(), &mut used_by_control);
+
+        let is_driven = |cell: &ir::Id, group: &Option<ir::Id>| {
+            ["left", "right"].iter().all(|input| {
+                let key = (cell.clone(), ir::Id::from(*input));
+                !unstable.contains(&key)
+                    && driven.get(&key).is_some_and(|groups| {
+                        groups.contains(&None) || groups.contains(group)
+                    })
+            })
+        };
+        let removed = comp
+            .cells
+            .iter()
+            .filter_map(|cell| {
+                let val = ranges.comparison(cell)?;
+                let out = (cell.clone_name(), ir::Id::from("out"));
+                let groups = reads.get(&out)?;
+                (!used_by_control.contains(&out)
+                    && groups
+                        .iter()
+                        .all(|group| is_driven(cell.borrow().name(), group)))
+                .then_some((out, val))
+            })
+            .collect::<Vec<_>>();
+
+        let mut builder = ir::Builder::new(comp, sigs);
+        for (out, val) in removed {
+            let port = builder.add_constant(val as u64, 1).borrow().get("out");
+            self.rewrites.insert(out, (val, port));
+        }
+        self.simplified += self.rewrites.len() as u64;
+
+        // The assignments are taken out of the groups because rewriting
+        // them borrows the groups of the holes.
+        for group in comp.groups.iter() {
+            let mut assigns =
+                std::mem::take(&mut group.borrow_mut().assignments);
+            self.rewrite_assigns(&mut assigns, &ranges);
+            group.borrow_mut().assignments = assigns;
+        }
+        for group in comp.comb_groups.iter() {
+            let mut assigns =
+                std::mem::take(&mut group.borrow_mut().assignments);
+            self.rewrite_assigns(&mut assigns, &ranges);
+            group.borrow_mut().assignments = assigns;
+        }
+        let mut assigns = std::mem::take(&mut comp.continuous_assignments);
+        self.rewrite_assigns(&mut assigns, &ranges);
+        comp.continuous_assignments = assigns;
+
+        let rewrites = &self.rewrites;
+        comp.cells.retain(|cell| {
+            !rewrites
+                .keys()
+                .any(|(name, _)| cell.borrow().name() == name)
+        });
+        // The assignments to the inputs of the removed cells are dropped.
+        comp.purge_deleted();
+        self.rewrites.clear();
+
+        Ok(Action::Stop)
+    }
+}
@@ -0,0 +1,149 @@
+use crate::ir::{
+    self,
+    traversal::{Action, Named, VisResult, Visitor},
+    LibrarySignatures, RRC,
+};
+use std::rc::Rc;
+
+/// Peephole simplifications of the control program that reduce the number of
+/// states produced when it is compiled.
+///
+/// The pass performs the following rewrites:
+/// 1. `empty` statements are removed from `seq` and `par` blocks, and a block
+///    without statements is replaced by `empty`. Blocks with a single
+///    statement are collapsed by `collapse-control`.
+/// 2. An `if` whose branches are both `empty` is replaced by `empty` since
+///    the condition and its `with` group have no side effects.
+/// 3. An `if` with an `empty` true branch tests the negated condition instead
+///    if the condition already drives a `std_not` in its `with` group or in
+///    the continuous assignments, so that `empty` branches only appear as
+///    false branches, like `if` statements written without an `else`. No
+///    hardware is added to negate other conditions.
+///
+/// # Example
+/// ```calyx
+/// comb group cond { lt.left = r.out; lt.right = 32'd2; not0.in = lt.out; }
+/// ...
+/// if lt.out with cond { empty } else { A; }
+/// ```
+/// becomes
+/// ```calyx
+/// if not0.out with cond { A; }
+/// ```
+#[derive(Default)]
+pub struct SimplifyWithControl {
+    /// Number of control statements that were removed or rewritten.
+    simplified: u64,
+}
+
+impl Named for SimplifyWithControl {
+    fn name() -> &'static str {
+        "simplify-with-control"
+    }
+
+    fn description() -> &'static str {
+        "remove empty statements and empty true branches"
+    }
+}
+
+/// Returns true if `con` is an `empty` statement.
+fn is_empty(con: &ir::Control) -> bool {
+    matches!(con, ir::Control::Empty(_))
+}
+
+/// The output of a `std_not` whose input is driven by `port` without a guard
+/// in `assigns`.
+fn find_negation(
+    assigns: &[ir::Assignment],
+    port: &RRC<ir::Port>,
+) -> Option<RRC<ir::Port>> {
+    assigns.iter().find_map(|assign| {
+        if !assign.guard.is_true() || !Rc::ptr_eq(&assign.src, port) {
+            return None;
+        }
+        let dst = assign.dst.borrow();
+        let cell = match &dst.parent {
+            ir::PortParent::Cell(cell) => cell.upgrade(),
+            ir::PortParent::Group(_) => return None,
+        };
+        let cell = cell.borrow();
+        (dst.name == "in" && cell.type_name()? == "std_not")
+            .then(|| cell.get("out"))
+    })
+}
+
+impl SimplifyWithControl {
+    /// Remove the `empty` statements of a `seq` or `par` block. Returns
+    /// `empty` if the block has no statements left.
+    fn simplify_block(
+        &mut self,
+        stmts: &mut Vec<ir::Control>,
+    ) -> Option<ir::Control> {
+        let len = stmts.len();
+        stmts.retain(|stmt| !is_empty(stmt));
+        self.simplified += (len - stmts.len()) as u64;
+        if stmts.is_empty() {
+            self.simplified += 1;
+            Some(ir::Control::empty())
+        } else {
+            None
+        }
+    }
+}
+
+impl Visitor for SimplifyWithControl {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![("statements-simplified", self.simplified)]
+    }
+
+    fn finish_seq(
+        &mut self,
+        s: &mut ir::Seq,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        Ok(self
+            .simplify_block(&mut s.stmts)
+            .map_or(Action::Continue, Action::Change))
+    }
+
+    fn finish_par(
+        &mut self,
+        s: &mut ir::Par,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        Ok(self
+            .simplify_block(&mut s.stmts)
+            .map_or(Action::Continue, Action::Change))
+    }
+
+    fn finish_if(
+        &mut self,
+        s: &mut ir::If,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        if !is_empty(&s.tbranch) {
+            return Ok(Action::Continue);
+        }
+        if is_empty(&s.fbranch) {
+            self.simplified += 1;
+            return Ok(Action::Change(ir::Control::empty()));
+        }
+
+        // Only reuse a negation of the condition that is already driven
+        // whenever the condition is tested.
+        let negation = s
+            .cond
+            .as_ref()
+            .and_then(|cond| find_negation(&cond.borrow().assignments, &s.port))
+            .or_else(|| find_negation(&comp.continuous_assignments, &s.port));
+        if let Some(port) = negation {
+            self.simplified += 1;
+            s.port = port;
+            std::mem::swap(&mut s.tbranch, &mut s.fbranch);
+        }
+        Ok(Action::Continue)
+    }
+}
@@ -0,0 +1,247 @@
+use super::group_fusion::static_latency;
+use super::math_utilities::get_bit_width_from;
+use crate::analysis::ReadWriteSet;
+use crate::ir::{
+    self,
+    traversal::{Action, Named, VisResult, Visitor},
+    CloneName, GetAttributes, LibrarySignatures, RRC,
+};
+use crate::{build_assignments, guard, structure};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Branches that take more cycles than this are not speculated.
+const MAX_LATENCY: u64 = 8;
+
+/// Speculatively executes both branches of `if` statements marked with the
+/// `@speculate` attribute.
+///
+/// An `if` statement is transformed when:
+/// 1. Each branch is an enable of a group that is `static` for at most
+///    [MAX_LATENCY] cycles and does not use holes other than its `done` hole.
+/// 2. Neither branch writes a cell that is used by the other branch or by the
+///    condition.
+/// 3. Neither branch writes to the ports of a sub-component.
+///
+/// Both branches are run in a `par` block and their results are selected
+/// using the condition: the assignments that commit a result, i.e., writes to
+/// `write_en` ports and to the ports of the component, are guarded by the
+/// condition for the true branch and by its negation for the false branch.
+/// This removes the cycles needed to evaluate the condition and to transition
+/// into a branch at the cost of running the untaken branch.
+///
+/// # Example
+/// ```calyx
+/// @speculate if lt.out with cond { @static(2) then_g; } else { @static(3) else_g; }
+/// ```
+/// becomes
+/// ```calyx
+/// @static(3) par { spec_then_g; spec_else_g; }
+/// ```
+/// where `spec_then_g` only writes to `write_en` ports when `lt.out` is true
+/// and `spec_else_g` only does so when it is false. Both groups take 3 cycles
+/// and the assignments of `cond` are added to the longest branch so that the
+/// condition is valid for the duration of the `par` block.
+#[derive(Default)]
+pub struct SpeculateIf;
+
+impl Named for SpeculateIf {
+    fn name() -> &'static str {
+        "speculate-if"
+    }
+
+    fn description() -> &'static str {
+        "run both branches of short static if statements in parallel"
+    }
+}
+
+/// A branch that can be speculated along with its latency.
+type Branch = (RRC<ir::Group>, u64);
+
+/// Returns the group enabled by `ctrl` and its latency if it can be
+/// speculated.
+fn branch(ctrl: &ir::Control) -> Option<Branch> {
+    let group = match ctrl {
+        ir::Control::Enable(ir::Enable { group, .. }) => group,
+        _ => return None,
+    };
+    let latency = static_latency(group).filter(|l| *l <= MAX_LATENCY)?;
+    let writes_component = ReadWriteSet::write_set(&group.borrow().assignments)
+        .any(|cell| {
+            matches!(cell.borrow().prototype, ir::CellType::Component { .. })
+        });
+    if writes_component {
+        None
+    } else {
+        Some((Rc::clone(group), latency))
+    }
+}
+
+/// Names of the cells in `cells`.
+fn names(cells: ir::CellIterator<'_>) -> HashSet<ir::Id> {
+    cells.map(|cell| cell.clone_name()).collect()
+}
+
+/// Returns true if an assignment to `port` commits the result of a group.
+fn commits(port: &RRC<ir::Port>) -> bool {
+    let port = port.borrow();
+    port.name == "write_en"
+        || matches!(&port.parent, ir::PortParent::Cell(cell)
+            if matches!(cell.upgrade().borrow().prototype, ir::CellType::ThisComponent))
+}
+
+/// Build a group that runs the assignments of `group` for `latency` cycles and
+/// only commits their results when `commit` is true. The group is done after
+/// `total` cycles so that all the branches of a `par` block finish together.
+/// `cond` are assignments that are active for the duration of the group.
+fn speculate(
+    (group, latency): &Branch,
+    total: u64,
+    commit: ir::Guard,
+    cond: &[ir::Assignment],
+    builder: &mut ir::Builder,
+) -> RRC<ir::Group> {
+    let fsm_size = get_bit_width_from(total + 1);
+    let spec = builder.add_group(format!("spec_{}", group.borrow().name()));
+    spec.borrow_mut()
+        .attributes
+        .derive_from([group.clone_name()]);
+
+    structure!(builder;
+        let fsm = prim std_reg(fsm_size);
+        let signal_on = constant(1, 1);
+        let incr = prim std_add(fsm_size);
+        let one = constant(1, fsm_size);
+        let stop = constant(*latency, fsm_size);
+        let last = constant(total, fsm_size);
+        let reset_val = constant(0, fsm_size);
+    );
+
+    let active = guard!(fsm["out"]).lt(guard!(stop["out"]));
+    let mut assigns = group
+        .borrow()
+        .assignments
+        .iter()
+        .filter(|assign| !assign.dst.borrow().is_hole())
+        .map(|assign| {
+            let mut assign = assign.clone();
+            let guard = if commits(&assign.dst) {
+                commit.clone() & active.clone()
+            } else {
+                active.clone()
+            };
+            assign.guard = Box::new(guard.and(*assign.guard));
+            assign
+        })
+        .chain(cond.iter().cloned())
+        .collect();
+    spec.borrow_mut().assignments.append(&mut assigns);
+
+    let done_guard = guard!(fsm["out"]).eq(guard!(last["out"]));
+    let not_done_guard = !done_guard.clone();
+    let mut assigns = build_assignments!(builder;
+        incr["left"] = ? one["out"];
+        incr["right"] = ? fsm["out"];
+        fsm["in"] = not_done_guard ? incr["out"];
+        fsm["write_en"] = not_done_guard ? signal_on["out"];
+        spec["done"] = done_guard ? signal_on["out"];
+    );
+    spec.borrow_mut().assignments.append(&mut assigns);
+    spec.borrow_mut().attributes.insert("static", total);
+
+    // CLEANUP: Reset the counter once the group is done.
+    let mut cleanup = build_assignments!(builder;
+        fsm["in"] = done_guard ? reset_val["out"];
+        fsm["write_en"] = done_guard ? signal_on["out"];
+    );
+    builder
+        .component
+        .continuous_assignments
+        .append(&mut cleanup);
+
+    spec
+}
+
+impl Visitor for SpeculateIf {
+    fn finish_if(
+        &mut self,
+        s: &mut ir::If,
+        comp: &mut ir::Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        if !s.attributes.has("speculate") {
+            return Ok(Action::Continue);
+        }
+        let tbranch = match branch(&s.tbranch) {
+            Some(b) => b,
+            None => return Ok(Action::Continue),
+        };
+        let fbranch = match &*s.fbranch {
+            ir::Control::Empty(_) => None,
+            ctrl => match branch(ctrl) {
+                Some(b) => Some(b),
+                None => return Ok(Action::Continue),
+            },
+        };
+
+        // The branches must not interfere with each other or the condition.
+        let cond_assigns = s
+            .cond
+            .as_ref()
+            .map(|cond| cond.borrow().assignments.clone())
+            .unwrap_or_default();
+        let mut cond_uses = names(ReadWriteSet::uses(&cond_assigns));
+        cond_uses.insert(s.port.borrow().get_parent_name());
+        let branches =
+            std::iter::once(tbranch).chain(fbranch).collect::<Vec<_>>();
+        let uses = branches
+            .iter()
+            .map(|(group, _)| {
+                let group = group.borrow();
+                (
+                    names(ReadWriteSet::write_set(&group.assignments)),
+                    names(ReadWriteSet::uses(&group.assignments)),
+                )
+            })
+            .collect::<Vec<_>>();
+        let disjoint = uses.iter().enumerate().all(|(idx, (writes, _))| {
+            writes.is_disjoint(&cond_uses)
+                && uses.iter().enumerate().all(|(other, (_, used))| {
+                    idx == other || writes.is_disjoint(used)
+                })
+        });
+        if !disjoint {
+            return Ok(Action::Continue);
+        }
+
+        // The longest branch drives the condition for the whole `par` block.
+        let latency = branches.iter().map(|(_, l)| *l).max().unwrap();
+        let longest = branches.iter().position(|(_, l)| *l == latency).unwrap();
+        let cond_guard = ir::Guard::port(Rc::clone(&s.port));
+        let guards = vec![cond_guard.clone(), !cond_guard];
+        let mut builder = ir::Builder::new(comp, sigs);
+        let stmts = branches
+            .iter()
+            .zip(guards)
+            .enumerate()
+            .map(|(idx, (branch, commit))| {
+                let cond = if idx == longest {
+                    &cond_assigns[..]
+                } else {
+                    &[]
+                };
+                ir::Control::enable(speculate(
+                    branch,
+                    latency,
+                    commit,
+                    cond,
+                    &mut builder,
+                ))
+            })
+            .collect();
+
+        let mut par = ir::Control::par(stmts);
+        par.get_mut_attributes().unwrap().insert("static", latency);
+        Ok(Action::Change(par))
+    }
+}
@@ -0,0 +1,406 @@
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, CloneName, LibrarySignatures, RRC};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+#[derive(Default)]
+/// Replaces operators with a constant operand by cheaper primitives:
+/// - Multiplications by a power of two (`std_unsyn_mult`, `std_unsyn_smult`)
+///   become left shifts (`std_lsh`).
+/// - Unsigned divisions by a power of two (`std_unsyn_div`) become right
+///   shifts (`std_rsh`).
+/// - Comparisons against zero (`x == 0`, `x != 0`, `x > 0` and `0 < x`)
+///   become OR-reductions (`std_orr`). The result is negated using a
+///   `std_not` for `x == 0`.
+///
+/// An operand is constant if all the assignments to it are unguarded and
+/// read the same constant. The pipelined operators are not rewritten since
+/// the groups that use them wait for their `done` signal.
+///
+/// # Example
+/// ```calyx
+/// cells { mul = std_unsyn_mult(32); }
+/// wires { mul.left = x.out; mul.right = 32'd8; r.in = mul.out; }
+/// ```
+/// becomes:
+/// ```calyx
+/// cells { lsh0 = std_lsh(32); }
+/// wires { lsh0.left = x.out; lsh0.right = 32'd3; r.in = lsh0.out; }
+/// ```
+pub struct StrengthReduction {
+    /// Ports of the replaced cells and the ports that replace them.
+    rewrites: HashMap<(ir::Id, ir::Id), RRC<ir::Port>>,
+    /// Names of the replaced cells.
+    removed: HashSet<ir::Id>,
+}
+
+impl Named for StrengthReduction {
+    fn name() -> &'static str {
+        "strength-reduction"
+    }
+
+    fn description() -> &'static str {
+        "replace operators with constant operands by cheaper primitives"
+    }
+}
+
+/// The cheaper operation that replaces a cell.
+enum Reduction {
+    /// Shift the `operand` port by `amount` using `prim`.
+    Shift {
+        prim: &'static str,
+        operand: &'static str,
+        constant: &'static str,
+        amount: u64,
+    },
+    /// OR-reduce the `operand` port and negate the result if `negate` is set.
+    OrReduce {
+        operand: &'static str,
+        constant: &'static str,
+        negate: bool,
+    },
+}
+
+impl Reduction {
+    /// Primitives used by this reduction.
+    fn primitives(&self) -> Vec<&'static str> {
+        match self {
+            Reduction::Shift { prim, .. } => vec![prim],
+            Reduction::OrReduce { negate: false, .. } => vec!["std_orr"],
+            Reduction::OrReduce { negate: true, .. } => {
+                vec!["std_orr", "std_not"]
+            }
+        }
+    }
+}
+
+/// The reduction for the primitive `prim` given the constant values of its
+/// `left` and `right` ports.
+fn reduction(
+    prim: &str,
+    left: Option<u64>,
+    right: Option<u64>,
+) -> Option<Reduction> {
+    let log2 = |val: Option<u64>| {
+        val.filter(|v| v.is_power_of_two())
+            .map(|v| v.trailing_zeros() as u64)
+    };
+    let shift = |prim, operand, constant, amount| Reduction::Shift {
+        prim,
+        operand,
+        constant,
+        amount,
+    };
+    let or_reduce = |operand, constant, negate| Reduction::OrReduce {
+        operand,
+        constant,
+        negate,
+    };
+    match prim {
+        "std_unsyn_mult" | "std_unsyn_smult" => log2(right)
+            .map(|amount| shift("std_lsh", "left", "right", amount))
+            .or_else(|| {
+                log2(left)
+                    .map(|amount| shift("std_lsh", "right", "left", amount))
+            }),
+        "std_unsyn_div" => {
+            log2(right).map(|amount| shift("std_rsh", "left", "right", amount))
+        }
+        "std_eq" | "std_neq" => {
+            let negate = prim == "std_eq";
+            if right == Some(0) {
+                Some(or_reduce("left", "right", negate))
+            } else if left == Some(0) {
+                Some(or_reduce("right", "left", negate))
+            } else {
+                None
+            }
+        }
+        "std_gt" if right == Some(0) => Some(or_reduce("left", "right", false)),
+        "std_lt" if left == Some(0) => Some(or_reduce("right", "left", false)),
+        _ => None,
+    }
+}
+
+/// Records the ports written by the outputs of `invoke` statements.
+fn invoke_writes(con: &ir::Control, writes: &mut HashSet<(ir::Id, ir::Id)>) {
+    match con {
+        ir::Control::Invoke(ir::Invoke { outputs, .. }) => {
+            writes.extend(outputs.iter().map(|(_, p)| p.borrow().canonical()))
+        }
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => {
+            for stmt in stmts {
+                invoke_writes(stmt, writes);
+            }
+        }
+        ir::Control::If(ir::If {
+            tbranch, fbranch, ..
+        }) => {
+            invoke_writes(tbranch, writes);
+            invoke_writes(fbranch, writes);
+        }
+        ir::Control::While(ir::While { body, .. }) => {
+            invoke_writes(body, writes)
+        }
+        ir::Control::Enable(_)
+        | ir::Control::Empty(_)
+        | ir::Control::Debug(_) => {}
+    }
+}
+
+/// Constant values of the cell ports that are only written by unguarded
+/// assignments from the same constant. Ports written with anything else
+/// are mapped to `None`.
+fn constant_ports(
+    comp: &ir::Component,
+) -> HashMap<(ir::Id, ir::Id), Option<u64>> {
+    let mut constants: HashMap<_, Option<u64>> = HashMap::new();
+    let mut record = |assign: &ir::Assignment| {
+        let src = assign.src.borrow();
+        let val = match &src.parent {
+            ir::PortParent::Cell(cell) if assign.guard.is_true() => {
+                match cell.upgrade().borrow().prototype {
+                    ir::CellType::Constant { val, .. } => Some(val),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let entry = constants
+            .entry(assign.dst.borrow().canonical())
+            .or_insert(val);
+        if *entry != val {
+            *entry = None;
+        }
+    };
+    for group in comp.groups.iter() {
+        group.borrow().assignments.iter().for_each(&mut record);
+    }
+    for group in comp.comb_groups.iter() {
+        group.borrow().assignments.iter().for_each(&mut record);
+    }
+    comp.continuous_assignments.iter().for_each(&mut record);
+
+    let mut writes = HashSet::new();
+    invoke_writes(&comp.control.borrow(), &mut writes);
+    for port in writes {
+        constants.insert(port, None);
+    }
+    constants
+}
+
+impl StrengthReduction {
+    /// The port that replaces `port`, if any.
+    fn rewrite(&self, port: &RRC<ir::Port>) -> Option<RRC<ir::Port>> {
+        self.rewrites.get(&port.borrow().canonical()).map(Rc::clone)
+    }
+
+    /// Rewrite the uses of the replaced cells in `assigns`. Assignments to
+    /// the ports in `constants` read the new constant or are removed if
+    /// there isn't one.
+    fn rewrite_assigns(
+        &self,
+        assigns: &mut Vec<ir::Assignment>,
+        constants: &HashMap<(ir::Id, ir::Id), Option<RRC<ir::Port>>>,
+    ) {
+        assigns.retain_mut(|assign| {
+            if let Some(constant) =
+                constants.get(&assign.dst.borrow().canonical())
+            {
+                match constant {
+                    Some(port) => assign.src = Rc::clone(port),
+                    None => return false,
+                }
+            }
+            if let Some(port) = self.rewrite(&assign.dst) {
+                assign.dst = port;
+            }
+            if let Some(port) = self.rewrite(&assign.src) {
+                assign.src = port;
+            }
+            assign
+                .guard
+                .for_each(&|port| self.rewrite(&port).map(ir::Guard::port));
+            true
+        });
+    }
+}
+
+impl Visitor for StrengthReduction {
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.rewrites.clear();
+        self.removed.clear();
+        let values = constant_ports(comp);
+        let reductions = comp
+            .cells
+            .iter()
+            .filter_map(|cell_ref| {
+                let cell = cell_ref.borrow();
+                let prim = match &cell.prototype {
+                    ir::CellType::Primitive { name, .. } => name,
+                    _ => return None,
+                };
+                let value = |port: &str| {
+                    values
+                        .get(&(cell.clone_name(), port.into()))
+                        .copied()
+                        .flatten()
+                };
+                reduction(prim.as_ref(), value("left"), value("right"))
+                    .filter(|red| {
+                        red.primitives()
+                            .iter()
+                            .all(|p| sigs.find_primitive(p).is_some())
+                    })
+                    .map(|red| (Rc::clone(cell_ref), red))
+            })
+            .collect::<Vec<_>>();
+        if reductions.is_empty() {
+            return Ok(Action::Stop);
+        }
+
+        let mut builder = ir::Builder::new(comp, sigs);
+        // Replacement values for the constant operands of the removed cells.
+        let mut constants = HashMap::new();
+        let mut continuous = vec![];
+        for (cell, red) in &reductions {
+            let name = cell.clone_name();
+            let width = cell.borrow().get_parameter("WIDTH").unwrap();
+            let port = |port: &str| (name.clone(), ir::Id::from(port));
+            match *red {
+                Reduction::Shift {
+                    prim,
+                    operand,
+                    constant,
+                    amount,
+                } => {
+                    let prefix = prim.trim_start_matches("std_");
+                    let shift = builder.add_primitive(prefix, prim, &[width]);
+                    let amount = builder.add_constant(amount, width);
+                    let shift = shift.borrow();
+                    self.rewrites.insert(port(operand), shift.get("left"));
+                    self.rewrites.insert(port(constant), shift.get("right"));
+                    self.rewrites.insert(port("out"), shift.get("out"));
+                    constants.insert(
+                        port(constant),
+                        Some(amount.borrow().get("out")),
+                    );
+                }
+                Reduction::OrReduce {
+                    operand,
+                    constant,
+                    negate,
+                } => {
+                    let orr = builder.add_primitive("orr", "std_orr", &[width]);
+                    self.rewrites.insert(port(operand), orr.borrow().get("in"));
+                    constants.insert(port(constant), None);
+                    if negate {
+                        let not = builder.add_primitive("not", "std_not", &[1]);
+                        continuous.push(builder.build_assignment(
+                            not.borrow().get("in"),
+                            orr.borrow().get("out"),
+                            ir::Guard::True,
+                        ));
+                        self.rewrites
+                            .insert(port("out"), not.borrow().get("out"));
+                    } else {
+                        self.rewrites
+                            .insert(port("out"), orr.borrow().get("out"));
+                    }
+                }
+            }
+        }
+
+        // The assignments are taken out of the groups because rewriting
+        // them borrows the groups of the holes.
+        for group in comp.groups.iter() {
+            let mut assigns =
+                std::mem::take(&mut group.borrow_mut().assignments);
+            self.rewrite_assigns(&mut assigns, &constants);
+            group.borrow_mut().assignments = assigns;
+        }
+        for group in comp.comb_groups.iter() {
+            let mut assigns =
+                std::mem::take(&mut group.borrow_mut().assignments);
+            self.rewrite_assigns(&mut assigns, &constants);
+            group.borrow_mut().assignments = assigns;
+        }
+        self.rewrite_assigns(&mut comp.continuous_assignments, &constants);
+        comp.continuous_assignments.append(&mut continuous);
+
+        // The cells are removed once the ports used by the control program
+        // have been rewritten.
+        self.removed = reductions
+            .iter()
+            .map(|(cell, _)| cell.clone_name())
+            .collect();
+        Ok(Action::Continue)
+    }
+
+    fn finish(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        comp.cells
+            .retain(|cell| !self.removed.contains(cell.borrow().name()));
+        Ok(Action::Continue)
+    }
+
+    fn start_if(
+        &mut self,
+        s: &mut ir::If,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        if let Some(port) = self.rewrite(&s.port) {
+            s.port = port;
+        }
+        Ok(Action::Continue)
+    }
+
+    fn start_while(
+        &mut self,
+        s: &mut ir::While,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        if let Some(port) = self.rewrite(&s.port) {
+            s.port = port;
+        }
+        Ok(Action::Continue)
+    }
+
+    fn invoke(
+        &mut self,
+        s: &mut ir::Invoke,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        for (_, port) in s.inputs.iter_mut().chain(s.outputs.iter_mut()) {
+            if let Some(new_port) = self.rewrite(port) {
+                *port = new_port;
+            }
+        }
+        Ok(Action::Continue)
+    }
+
+    fn debug(
+        &mut self,
+        s: &mut ir::Debug,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        for port in s.ports.iter_mut() {
+            if let Some(new_port) = self.rewrite(port) {
+                *port = new_port;
+            }
+        }
+        Ok(Action::Continue)
+    }
+}
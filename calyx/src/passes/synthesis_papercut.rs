@@ -1,6 +1,8 @@
 use crate::analysis::GraphAnalysis;
-use crate::errors::Error;
-use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::errors::{CalyxResult, Error};
+use crate::ir::traversal::{
+    Action, ConstructVisitor, Named, VisResult, Visitor,
+};
 use crate::ir::{self, CloneName, LibrarySignatures};
 use std::collections::HashSet;
 
@@ -15,13 +17,28 @@ pub struct SynthesisPapercut {
     memories: HashSet<ir::Id>,
 }
 
-impl Default for SynthesisPapercut {
-    fn default() -> Self {
-        let memories = ["std_mem_d1", "std_mem_d2", "std_mem_d3", "std_mem_d4"]
-            .iter()
-            .map(|&mem| mem.into())
+impl ConstructVisitor for SynthesisPapercut {
+    fn from(ctx: &ir::Context) -> CalyxResult<Self> {
+        // Memories are the primitives that must be given an address to be
+        // read from.
+        let memories = ctx
+            .lib
+            .signatures()
+            .filter(|prim| {
+                let has_port = |name: &str| {
+                    prim.signature.iter().any(|pd| pd.name == name)
+                };
+                has_port(READ_PORT)
+                    && has_port(WRITE_PORT)
+                    && ctx.lib.port_spec(&prim.name).unwrap().is_addressed()
+            })
+            .map(|prim| prim.name.clone())
             .collect();
-        SynthesisPapercut { memories }
+        Ok(SynthesisPapercut { memories })
+    }
+
+    fn clear_data(&mut self) {
+        /* All data is shared between components. */
     }
 }
 
@@ -76,6 +76,7 @@ fn control_exits(
         ),
         ir::Control::Invoke(_) => unreachable!("`invoke` statements should have been compiled away. Run `{}` before this pass.", passes::CompileInvoke::name()),
         ir::Control::Empty(_) => unreachable!("`empty` statements should have been compiled away. Run `{}` before this pass.", passes::CompileEmpty::name()),
+        ir::Control::Debug(_) => unreachable!("`@debug` statements should have been compiled away. Run `{}` before this pass.", passes::CompileDebug::name()),
         ir::Control::Par(_) => unreachable!(),
     }
 }
@@ -151,6 +152,7 @@ fn compute_unique_ids(con: &mut ir::Control, cur_state: u64) -> u64 {
         }
         ir::Control::Invoke(_) => unreachable!("`invoke` statements should have been compiled away. Run `{}` before this pass.", passes::CompileInvoke::name()),
         ir::Control::Empty(_) => unreachable!("`empty` statements should have been compiled away. Run `{}` before this pass.", passes::CompileEmpty::name()),
+        ir::Control::Debug(_) => unreachable!("`@debug` statements should have been compiled away. Run `{}` before this pass.", passes::CompileDebug::name()),
     }
 }
 
@@ -466,6 +468,7 @@ fn calculate_states_recur(
         ir::Control::Par(_) => unreachable!(),
         ir::Control::Invoke(_) => unreachable!("`invoke` statements should have been compiled away. Run `{}` before this pass.", passes::CompileInvoke::name()),
         ir::Control::Empty(_) => unreachable!("`empty` statements should have been compiled away. Run `{}` before this pass.", passes::CompileEmpty::name()),
+        ir::Control::Debug(_) => unreachable!("`@debug` statements should have been compiled away. Run `{}` before this pass.", passes::CompileDebug::name()),
     }
 }
 
@@ -0,0 +1,239 @@
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, CloneName, LibrarySignatures, RRC};
+
+#[derive(Default)]
+/// Warns about control statements that can never execute:
+/// - statements of a `seq` that follow a statement that never finishes, such
+///   as the enable of a group whose `done` hole is always 0 or a `while` loop
+///   whose condition is always 1;
+/// - the branch of an `if` that is not selected by a constant condition;
+/// - the body of a `while` loop whose condition is always 0.
+///
+/// A condition is constant if it reads a constant, a `std_const`, or a
+/// `std_wire` whose input is only driven by a constant.
+///
+/// The warnings point to the first group or cell used by the unreachable
+/// statement. The program is not changed.
+pub struct UnreachableControl;
+
+impl Named for UnreachableControl {
+    fn name() -> &'static str {
+        "unreachable-control"
+    }
+
+    fn description() -> &'static str {
+        "warn about control statements that can never execute"
+    }
+}
+
+/// Value of `port` if it is the same in every cycle. `assigns` are the
+/// assignments active whenever `port` is read.
+fn constant_value(
+    port: &RRC<ir::Port>,
+    assigns: &[&ir::Assignment],
+) -> Option<u64> {
+    let cell = match &port.borrow().parent {
+        ir::PortParent::Cell(cell) => cell.upgrade(),
+        ir::PortParent::Group(_) => return None,
+    };
+    let cell = cell.borrow();
+    match &cell.prototype {
+        ir::CellType::Constant { val, .. } => Some(*val),
+        ir::CellType::Primitive { name, .. } if name == "std_const" => {
+            cell.get_parameter("VALUE")
+        }
+        ir::CellType::Primitive { name, .. } if name == "std_wire" => {
+            let input = cell.get("in");
+            let mut drivers = assigns.iter().filter(|asgn| {
+                asgn.dst.borrow().canonical() == input.borrow().canonical()
+            });
+            match (drivers.next(), drivers.next()) {
+                (Some(asgn), None) if asgn.guard.is_true() => {
+                    constant_value(&asgn.src, assigns)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Value of the condition of an `if` or `while` if it is constant.
+pub(super) fn constant_condition(
+    comp: &ir::Component,
+    port: &RRC<ir::Port>,
+    cond: &Option<RRC<ir::CombGroup>>,
+) -> Option<u64> {
+    let cond = cond.as_ref().map(|cond| cond.borrow());
+    let assigns = comp
+        .continuous_assignments
+        .iter()
+        .chain(cond.iter().flat_map(|cond| cond.assignments.iter()))
+        .collect::<Vec<_>>();
+    constant_value(port, &assigns)
+}
+
+/// Returns true if the `done` hole of `group` is always 0.
+fn never_done(group: &ir::Group) -> bool {
+    let done = group.get("done");
+    let assigns = group.assignments.iter().collect::<Vec<_>>();
+    let mut drivers = group
+        .assignments
+        .iter()
+        .filter(|asgn| {
+            asgn.dst.borrow().canonical() == done.borrow().canonical()
+        })
+        .peekable();
+    drivers.peek().is_some()
+        && drivers.all(|asgn| constant_value(&asgn.src, &assigns) == Some(0))
+}
+
+/// Name of the first group or cell used by `con`, which is used to point to
+/// the statement in warnings.
+fn first_name(con: &ir::Control) -> Option<ir::Id> {
+    match con {
+        ir::Control::Enable(ir::Enable { group, .. }) => {
+            Some(group.clone_name())
+        }
+        ir::Control::Invoke(ir::Invoke { comp, .. }) => Some(comp.clone_name()),
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => {
+            stmts.iter().find_map(first_name)
+        }
+        ir::Control::If(ir::If {
+            tbranch, fbranch, ..
+        }) => first_name(tbranch).or_else(|| first_name(fbranch)),
+        ir::Control::While(ir::While { body, .. }) => first_name(body),
+        ir::Control::Empty(_) | ir::Control::Debug(_) => None,
+    }
+}
+
+/// Print a warning about the unreachable statement `con`.
+fn warn(comp: &ir::Component, con: &ir::Control, reason: &str) {
+    if matches!(con, ir::Control::Empty(_)) {
+        return;
+    }
+    let msg = format!("Unreachable control in `{}`: {}", comp.name, reason);
+    match first_name(con) {
+        Some(name) => eprintln!("Warning: {}", name.fmt_err(&msg)),
+        None => eprintln!("Warning: {}", msg),
+    }
+}
+
+/// Warn about the unreachable statements in `con`. Returns the reason why
+/// `con` never finishes, if it does not.
+fn check(comp: &ir::Component, con: &ir::Control) -> Option<String> {
+    match con {
+        ir::Control::Enable(ir::Enable { group, .. }) => {
+            if never_done(&group.borrow()) {
+                Some(format!("group `{}` never finishes", group.clone_name()))
+            } else {
+                None
+            }
+        }
+        ir::Control::Seq(ir::Seq { stmts, .. }) => {
+            for (idx, stmt) in stmts.iter().enumerate() {
+                if let Some(reason) = check(comp, stmt) {
+                    if let Some(next) = stmts[idx + 1..]
+                        .iter()
+                        .find(|stmt| !matches!(stmt, ir::Control::Empty(_)))
+                    {
+                        warn(comp, next, &format!("runs after {}", reason));
+                    }
+                    return Some(reason);
+                }
+            }
+            None
+        }
+        ir::Control::Par(ir::Par { stmts, .. }) => stmts
+            .iter()
+            .map(|stmt| check(comp, stmt))
+            .fold(None, |acc, reason| acc.or(reason)),
+        ir::Control::If(ir::If {
+            port,
+            cond,
+            tbranch,
+            fbranch,
+            ..
+        }) => {
+            let port_name = {
+                let (cell, name) = port.borrow().canonical();
+                format!("`{}.{}`", cell, name)
+            };
+            match constant_condition(comp, port, cond) {
+                Some(0) => {
+                    warn(
+                        comp,
+                        tbranch,
+                        &format!(
+                            "true branch runs when {} is 1 but it is always 0",
+                            port_name
+                        ),
+                    );
+                    check(comp, fbranch)
+                }
+                Some(_) => {
+                    warn(
+                        comp,
+                        fbranch,
+                        &format!(
+                            "false branch runs when {} is 0 but it is always 1",
+                            port_name
+                        ),
+                    );
+                    check(comp, tbranch)
+                }
+                None => {
+                    let t = check(comp, tbranch);
+                    let f = check(comp, fbranch);
+                    t.and(f)
+                }
+            }
+        }
+        ir::Control::While(ir::While {
+            port, cond, body, ..
+        }) => {
+            let port_name = {
+                let (cell, name) = port.borrow().canonical();
+                format!("`{}.{}`", cell, name)
+            };
+            match constant_condition(comp, port, cond) {
+                Some(0) => {
+                    warn(
+                        comp,
+                        body,
+                        &format!(
+                            "loop body runs when {} is 1 but it is always 0",
+                            port_name
+                        ),
+                    );
+                    None
+                }
+                Some(_) => {
+                    check(comp, body);
+                    Some(format!("the loop on {} never exits", port_name))
+                }
+                None => {
+                    check(comp, body);
+                    None
+                }
+            }
+        }
+        ir::Control::Invoke(_)
+        | ir::Control::Empty(_)
+        | ir::Control::Debug(_) => None,
+    }
+}
+
+impl Visitor for UnreachableControl {
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        check(comp, &comp.control.borrow());
+
+        // This pass doesn't modify any control.
+        Ok(Action::Stop)
+    }
+}
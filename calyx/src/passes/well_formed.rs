@@ -2,6 +2,7 @@ use crate::errors::{CalyxResult, Error};
 use crate::ir::traversal::{Action, Named, VisResult, Visitor};
 use crate::ir::{self, CloneName, Component, LibrarySignatures};
 use std::collections::HashSet;
+use std::rc::Rc;
 
 /// Pass to check if the program is well-formed.
 ///
@@ -9,6 +10,9 @@ use std::collections::HashSet;
 /// 1. Programs that don't use a defined group or combinational group.
 /// 2. Groups that don't write to their done signal.
 /// 3. Groups that write to another group's done signal.
+/// 4. `static` groups that write to their done signal.
+/// 5. `static` components whose `@interval` cannot be met by their control
+///    program or that drive their own done signal without one.
 #[derive(Default)]
 pub struct WellFormed {
     /// Names of the groups that have been used in the control.
@@ -27,12 +31,64 @@ impl Named for WellFormed {
     }
 }
 
+impl WellFormed {
+    /// Check the timing contract of a `static<latency>` component. The
+    /// interval of the component, given by the `@interval` attribute on its
+    /// `go` port, defaults to its latency.
+    fn check_static_component(
+        comp: &Component,
+        latency: u64,
+    ) -> CalyxResult<()> {
+        let (go, done) = comp.signature.borrow().find_interface(None).unwrap();
+        let interval = go.borrow().attributes.get("interval").copied();
+        let has_control =
+            !matches!(*comp.control.borrow(), ir::Control::Empty(_));
+        match interval {
+            Some(0) => Err(Error::MalformedStructure(comp.name.fmt_err(
+                &format!(
+                    "Static component `{}' must have a positive interval",
+                    comp.name
+                ),
+            ))),
+            // The control program cannot accept new inputs before it is done.
+            Some(interval) if has_control && interval < latency => {
+                Err(Error::MalformedStructure(comp.name.fmt_err(&format!(
+                    "Static component `{}' has interval {} which is shorter than its latency {}. Only components without control can be pipelined.",
+                    comp.name, interval, latency
+                ))))
+            }
+            _ => Ok(()),
+        }?;
+
+        // The done signal of a component without control is driven by
+        // `lower-static`.
+        if !has_control
+            && comp
+                .continuous_assignments
+                .iter()
+                .any(|assign| Rc::ptr_eq(&assign.dst, &done))
+        {
+            return Err(Error::MalformedStructure(comp.name.fmt_err(
+                &format!(
+                    "Static component `{}' without control cannot drive its done signal",
+                    comp.name
+                ),
+            )));
+        }
+        Ok(())
+    }
+}
+
 impl Visitor for WellFormed {
     fn start(
         &mut self,
         comp: &mut Component,
         _ctx: &LibrarySignatures,
     ) -> VisResult {
+        if let Some(latency) = comp.latency {
+            Self::check_static_component(comp, latency)?;
+        }
+
         // For each non-combinational group, check if there is at least one write to the done
         // signal of that group and that the write is to the group's done signal.
         comp.groups.iter().try_for_each(|group_ref| {
@@ -54,7 +110,16 @@ impl Visitor for WellFormed {
                     Ok(())
                 }
             }).collect::<CalyxResult<Vec<_>>>()?;
-            if done.is_empty() {
+            if group.latency.is_some() {
+                if done.is_empty() {
+                    Ok(())
+                } else {
+                    Err(Error::MalformedStructure(gname.fmt_err(&format!(
+                        "Static group `{}' cannot write to its `done' hole",
+                        gname
+                    ))))
+                }
+            } else if done.is_empty() {
                 Err(Error::MalformedStructure(gname.fmt_err(&format!(
                     "No writes to the `done' hole for group `{}'",
                     gname.to_string()
@@ -0,0 +1,494 @@
+use crate::analysis::ValueRanges;
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, CloneName, LibrarySignatures, RRC};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// Primitives whose `out` port is computed from their `left` and `right`
+/// inputs and has the same width.
+const OPERATORS: [&str; 5] =
+    ["std_add", "std_sub", "std_and", "std_or", "std_rsh"];
+
+/// Primitives that compare their `left` and `right` inputs.
+const COMPARISONS: [&str; 6] =
+    ["std_lt", "std_le", "std_gt", "std_ge", "std_eq", "std_neq"];
+
+#[derive(Default)]
+/// Narrows registers, operators, and comparisons to the number of bits
+/// needed by the values they can hold, which are computed by [ValueRanges].
+///
+/// A narrowed cell is replaced by a smaller instance of the same primitive.
+/// When all the assignments to one of its inputs agree, the input is written
+/// with narrow constants, such as `4'd1` instead of `32'd1`, or directly by
+/// the outputs of other cells narrowed to the same width. Otherwise, the
+/// values are truncated using a `std_slice`. Outputs that are read by cells
+/// that are not narrowed, guards, or the control program are extended back
+/// to the original width using a `std_pad`. For example, a 32-bit loop
+/// counter `i` that only holds values up to 10 and is an output of the
+/// component becomes:
+/// ```calyx
+/// cells {
+///   i0 = std_reg(4);
+///   add0 = std_add(4);
+///   pad = std_pad(4, 32);
+/// }
+/// wires {
+///   group incr {
+///     add0.left = i0.out;
+///     add0.right = 4'd1;
+///     i0.in = add0.out;
+///     ...
+///   }
+///   pad.in = i0.out;
+///   out = pad.out;
+/// }
+/// ```
+/// Cells that are invoked or marked with `@external` are not narrowed.
+pub struct WidthReduction {
+    /// Ports of the narrowed cells and the ports that replace them in
+    /// assignments that use the original width.
+    rewrites: HashMap<(ir::Id, ir::Id), RRC<ir::Port>>,
+    /// Inputs of the narrowed cells that are written with narrow values,
+    /// outputs of the narrowed cells, and the ports that replace them.
+    narrow: HashMap<(ir::Id, ir::Id), RRC<ir::Port>>,
+    /// Names of the narrowed cells.
+    removed: HashSet<ir::Id>,
+    /// Number of narrowed registers.
+    registers: u64,
+    /// Number of narrowed operators and comparisons.
+    operators: u64,
+    /// Number of bits removed from registers.
+    bits: u64,
+}
+
+impl Named for WidthReduction {
+    fn name() -> &'static str {
+        "width-reduction"
+    }
+
+    fn description() -> &'static str {
+        "narrow registers and operators to the bits needed by their values"
+    }
+}
+
+/// A cell that is replaced by a narrower one.
+struct Narrowed {
+    cell: RRC<ir::Cell>,
+    prim: ir::Id,
+    width: u64,
+    bits: u64,
+}
+
+impl Narrowed {
+    /// The inputs of the cell whose width is reduced.
+    fn inputs(&self) -> &'static [&'static str] {
+        if self.prim == "std_reg" {
+            &["in"]
+        } else {
+            &["left", "right"]
+        }
+    }
+
+    /// The output of the cell whose width is reduced, if any.
+    fn output(&self) -> Option<&'static str> {
+        (!COMPARISONS.contains(&self.prim.as_ref())).then_some("out")
+    }
+}
+
+/// Number of bits needed by the values of the inputs and outputs of `cell`,
+/// an instance of `prim`, if it can be narrowed.
+fn needed_bits(
+    cell: &ir::Cell,
+    prim: &str,
+    ranges: &ValueRanges,
+) -> Option<u64> {
+    let name = cell.name();
+    if prim == "std_reg" {
+        return ranges.register(name).map(|range| range.bits());
+    }
+    let is_operator = OPERATORS.contains(&prim);
+    if !is_operator && !COMPARISONS.contains(&prim) {
+        return None;
+    }
+    let left = ranges.input(name, "left")?;
+    let right = ranges.input(name, "right")?;
+    let bits = left.bits().max(right.bits());
+    if is_operator {
+        Some(bits.max(ranges.port(&cell.get("out"))?.bits()))
+    } else {
+        Some(bits)
+    }
+}
+
+/// The value of `port` if it is a constant.
+fn constant(port: &RRC<ir::Port>) -> Option<u64> {
+    match &port.borrow().parent {
+        ir::PortParent::Cell(cell) => match cell.upgrade().borrow().prototype {
+            ir::CellType::Constant { val, .. } => Some(val),
+            _ => None,
+        },
+        ir::PortParent::Group(_) => None,
+    }
+}
+
+/// The lowest `bits` bits of `val`.
+fn truncate(val: u64, bits: u64) -> u64 {
+    val & ((1 << bits) - 1)
+}
+
+/// Records the cells invoked by `con` and the ports that it reads or
+/// writes.
+fn control_uses(
+    con: &ir::Control,
+    invoked: &mut HashSet<ir::Id>,
+    ports: &mut HashSet<(ir::Id, ir::Id)>,
+) {
+    match con {
+        ir::Control::Invoke(ir::Invoke {
+            comp,
+            inputs,
+            outputs,
+            ..
+        }) => {
+            invoked.insert(comp.clone_name());
+            ports.extend(
+                inputs
+                    .iter()
+                    .chain(outputs.iter())
+                    .map(|(_, port)| port.borrow().canonical()),
+            );
+        }
+        ir::Control::Debug(ir::Debug { ports: debug, .. }) => {
+            ports.extend(debug.iter().map(|port| port.borrow().canonical()))
+        }
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => {
+            for stmt in stmts {
+                control_uses(stmt, invoked, ports);
+            }
+        }
+        ir::Control::If(ir::If {
+            port,
+            tbranch,
+            fbranch,
+            ..
+        }) => {
+            ports.insert(port.borrow().canonical());
+            control_uses(tbranch, invoked, ports);
+            control_uses(fbranch, invoked, ports);
+        }
+        ir::Control::While(ir::While { port, body, .. }) => {
+            ports.insert(port.borrow().canonical());
+            control_uses(body, invoked, ports);
+        }
+        ir::Control::Enable(_) | ir::Control::Empty(_) => {}
+    }
+}
+
+/// Calls `f` on every assignment of `comp`.
+fn for_each_assign<F>(comp: &ir::Component, mut f: F)
+where
+    F: FnMut(&ir::Assignment),
+{
+    for group in comp.groups.iter() {
+        group.borrow().assignments.iter().for_each(&mut f);
+    }
+    for group in comp.comb_groups.iter() {
+        group.borrow().assignments.iter().for_each(&mut f);
+    }
+    comp.continuous_assignments.iter().for_each(f);
+}
+
+/// The name of the cell and of `port`, `None` if it is a hole.
+fn cell_port(port: &RRC<ir::Port>) -> Option<(ir::Id, ir::Id)> {
+    let port = port.borrow();
+    if port.is_hole() {
+        None
+    } else {
+        Some(port.canonical())
+    }
+}
+
+impl WidthReduction {
+    /// The port that replaces `port` if it uses the original width.
+    fn rewrite(&self, port: &RRC<ir::Port>) -> Option<RRC<ir::Port>> {
+        self.rewrites.get(&port.borrow().canonical()).map(Rc::clone)
+    }
+
+    /// Rewrite the uses of the narrowed cells in `assigns`. `constants` are
+    /// the narrow constants written to the inputs of the narrowed cells.
+    fn rewrite_assigns(
+        &self,
+        assigns: &mut [ir::Assignment],
+        constants: &HashMap<(u64, u64), RRC<ir::Port>>,
+    ) {
+        for assign in assigns {
+            let dst = assign.dst.borrow().canonical();
+            if let Some(port) = self.narrow.get(&dst) {
+                let bits = port.borrow().width;
+                let src = match constant(&assign.src) {
+                    Some(val) => &constants[&(truncate(val, bits), bits)],
+                    None => &self.narrow[&assign.src.borrow().canonical()],
+                };
+                assign.dst = Rc::clone(port);
+                assign.src = Rc::clone(src);
+            } else {
+                if let Some(port) = self.rewrite(&assign.dst) {
+                    assign.dst = port;
+                }
+                if let Some(port) = self.rewrite(&assign.src) {
+                    assign.src = port;
+                }
+            }
+            assign
+                .guard
+                .for_each(&|port| self.rewrite(&port).map(ir::Guard::port));
+        }
+    }
+}
+
+impl Visitor for WidthReduction {
+    fn stats(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("registers-narrowed", self.registers),
+            ("operators-narrowed", self.operators),
+            ("register-bits-saved", self.bits),
+        ]
+    }
+
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        if sigs.find_primitive("std_slice").is_none()
+            || sigs.find_primitive("std_pad").is_none()
+        {
+            return Ok(Action::Stop);
+        }
+        let ranges = ValueRanges::from(&*comp);
+        let mut invoked = HashSet::new();
+        let mut control_ports = HashSet::new();
+        control_uses(&comp.control.borrow(), &mut invoked, &mut control_ports);
+        let narrowed = comp
+            .cells
+            .iter()
+            .filter_map(|cell_ref| {
+                let cell = cell_ref.borrow();
+                let prim = cell.type_name()?.clone();
+                if cell.attributes.has("external")
+                    || invoked.contains(cell.name())
+                {
+                    return None;
+                }
+                let width = cell.get_parameter("WIDTH")?;
+                let bits = needed_bits(&cell, prim.as_ref(), &ranges)?;
+                (bits < width).then(|| Narrowed {
+                    cell: Rc::clone(cell_ref),
+                    prim,
+                    width,
+                    bits,
+                })
+            })
+            .collect::<Vec<_>>();
+        if narrowed.is_empty() {
+            return Ok(Action::Stop);
+        }
+        let find_input = |(cell, port): &(ir::Id, ir::Id)| {
+            narrowed.iter().find(|n| {
+                n.cell.borrow().name() == cell
+                    && n.inputs().contains(&port.as_ref())
+            })
+        };
+        let find_output = |(cell, port): &(ir::Id, ir::Id)| {
+            narrowed.iter().find(|n| {
+                n.cell.borrow().name() == cell
+                    && n.output() == Some(port.as_ref())
+            })
+        };
+
+        // Inputs that are written with values of the original width, which
+        // are truncated by a `std_slice`. Ports used by the control program
+        // always have the original width.
+        let mut wide_inputs = control_ports.clone();
+        for_each_assign(comp, |assign| {
+            let dst = match cell_port(&assign.dst) {
+                Some(dst) => dst,
+                None => return,
+            };
+            if let Some(n) = find_input(&dst) {
+                let is_narrow = constant(&assign.src).is_some()
+                    || cell_port(&assign.src)
+                        .and_then(|src| find_output(&src))
+                        .is_some_and(|src| src.bits == n.bits);
+                if !is_narrow {
+                    wide_inputs.insert(dst);
+                }
+            }
+        });
+        // Outputs that are read with the original width, which are extended
+        // by a `std_pad`, and the narrow constants written to inputs.
+        let mut wide_outputs = control_ports;
+        let mut constants = HashSet::new();
+        for_each_assign(comp, |assign| {
+            let input = cell_port(&assign.dst)
+                .filter(|dst| !wide_inputs.contains(dst))
+                .and_then(|dst| find_input(&dst));
+            match (input, constant(&assign.src)) {
+                (Some(n), Some(val)) => {
+                    constants.insert((truncate(val, n.bits), n.bits));
+                }
+                (Some(_), None) => {}
+                (None, _) => wide_outputs.extend(cell_port(&assign.src)),
+            }
+            for port in assign.guard.all_ports() {
+                wide_outputs.extend(cell_port(&port));
+            }
+        });
+
+        let mut builder = ir::Builder::new(comp, sigs);
+        let mut continuous = vec![];
+        for n in &narrowed {
+            let name = n.cell.clone_name();
+            let cell =
+                builder.add_primitive(name.clone(), n.prim.clone(), &[n.bits]);
+            let cell = cell.borrow();
+            for port in n.cell.borrow().ports.iter() {
+                let port = port.borrow().name.clone();
+                let key = (name.clone(), port.clone());
+                if n.inputs().contains(&port.as_ref()) {
+                    if wide_inputs.contains(&key) {
+                        let slice = builder.add_primitive(
+                            "slice",
+                            "std_slice",
+                            &[n.width, n.bits],
+                        );
+                        let slice = slice.borrow();
+                        continuous.push(builder.build_assignment(
+                            cell.get(&port),
+                            slice.get("out"),
+                            ir::Guard::True,
+                        ));
+                        self.rewrites.insert(key, slice.get("in"));
+                    } else {
+                        self.narrow.insert(key, cell.get(&port));
+                    }
+                } else if n.output() == Some(port.as_ref()) {
+                    if wide_outputs.contains(&key) {
+                        let pad = builder.add_primitive(
+                            "pad",
+                            "std_pad",
+                            &[n.bits, n.width],
+                        );
+                        let pad = pad.borrow();
+                        continuous.push(builder.build_assignment(
+                            pad.get("in"),
+                            cell.get(&port),
+                            ir::Guard::True,
+                        ));
+                        self.rewrites.insert(key.clone(), pad.get("out"));
+                    }
+                    self.narrow.insert(key, cell.get(&port));
+                } else {
+                    self.rewrites.insert(key, cell.get(&port));
+                }
+            }
+            if n.prim == "std_reg" {
+                self.registers += 1;
+                self.bits += n.width - n.bits;
+            } else {
+                self.operators += 1;
+            }
+            self.removed.insert(name);
+        }
+        let constants = constants
+            .into_iter()
+            .map(|(val, bits)| {
+                let port = builder.add_constant(val, bits).borrow().get("out");
+                ((val, bits), port)
+            })
+            .collect::<HashMap<_, _>>();
+
+        // The assignments are taken out of the groups because rewriting
+        // them borrows the groups of the holes.
+        for group in comp.groups.iter() {
+            let mut assigns =
+                std::mem::take(&mut group.borrow_mut().assignments);
+            self.rewrite_assigns(&mut assigns, &constants);
+            group.borrow_mut().assignments = assigns;
+        }
+        for group in comp.comb_groups.iter() {
+            let mut assigns =
+                std::mem::take(&mut group.borrow_mut().assignments);
+            self.rewrite_assigns(&mut assigns, &constants);
+            group.borrow_mut().assignments = assigns;
+        }
+        self.rewrite_assigns(&mut comp.continuous_assignments, &constants);
+        comp.continuous_assignments.append(&mut continuous);
+
+        Ok(Action::Continue)
+    }
+
+    fn finish(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        comp.cells
+            .retain(|cell| !self.removed.contains(cell.borrow().name()));
+        comp.purge_deleted();
+        Ok(Action::Continue)
+    }
+
+    fn start_if(
+        &mut self,
+        s: &mut ir::If,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        if let Some(port) = self.rewrite(&s.port) {
+            s.port = port;
+        }
+        Ok(Action::Continue)
+    }
+
+    fn start_while(
+        &mut self,
+        s: &mut ir::While,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        if let Some(port) = self.rewrite(&s.port) {
+            s.port = port;
+        }
+        Ok(Action::Continue)
+    }
+
+    fn invoke(
+        &mut self,
+        s: &mut ir::Invoke,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        for (_, port) in s.inputs.iter_mut().chain(s.outputs.iter_mut()) {
+            if let Some(new_port) = self.rewrite(port) {
+                *port = new_port;
+            }
+        }
+        Ok(Action::Continue)
+    }
+
+    fn debug(
+        &mut self,
+        s: &mut ir::Debug,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        for port in s.ports.iter_mut() {
+            if let Some(new_port) = self.rewrite(port) {
+                *port = new_port;
+            }
+        }
+        Ok(Action::Continue)
+    }
+}
@@ -0,0 +1,278 @@
+use super::externalize::externalize_cells;
+use crate::errors::{CalyxResult, Error};
+use crate::ir::traversal::{ConstructVisitor, Named, Visitor};
+use crate::ir::{self, RRC};
+use crate::utils::NameGenerator;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Wraps the toplevel component in a generated component that exposes a
+/// standard interface so that host harnesses can drive any program without
+/// editing it.
+///
+/// The generated component has the ports `go`, `clk`, `reset` and `done`, an
+/// input and output port for each port of the `@external(1)` cells of the
+/// toplevel component, and the remaining ports of the toplevel component
+/// under their original names. The wrapper becomes the entrypoint of the
+/// program.
+///
+/// The pass is configured using `-x wrap-main:<key>=<value>`:
+/// - `top`: component to wrap. Defaults to the entrypoint of the program.
+/// - `name`: name of the generated component. Defaults to `main`. If the
+///   wrapped component already uses this name, it is renamed to
+///   `<name>_inner`.
+/// - `go`, `done`, `clk`, `reset`: names of the interface ports.
+/// - `memory-ports`: name of the port exposing the port `{port}` of the
+///   external cell `{mem}`. Defaults to `{mem}_{port}`.
+///
+/// For example, with `-x wrap-main:go=start -x wrap-main:memory-ports={port}_{mem}`:
+/// ```text
+/// component main() -> () {
+///   cells { @external(1) m = std_mem_d1(32, 4, 2); }
+///   ...
+/// }
+/// ```
+/// becomes:
+/// ```text
+/// component main_inner(m_read_data: 32, ...) -> (m_addr0: 2, ...) { ... }
+/// component main(start: 1, read_data_m: 32, ...) -> (done: 1, addr0_m: 2, ...) {
+///   cells { main_inner = main_inner(); }
+///   wires {
+///     main_inner.go = start;
+///     done = main_inner.done;
+///     addr0_m = main_inner.m_addr0;
+///     main_inner.m_read_data = read_data_m;
+///     ...
+///   }
+///   control {}
+/// }
+/// ```
+pub struct WrapMain {
+    /// Component to wrap.
+    top: Option<ir::Id>,
+    /// Name of the generated component.
+    name: ir::Id,
+    go: ir::Id,
+    done: ir::Id,
+    clk: ir::Id,
+    reset: ir::Id,
+    /// Format of the names of the ports of external cells.
+    memory_ports: String,
+}
+
+impl Named for WrapMain {
+    fn name() -> &'static str {
+        "wrap-main"
+    }
+
+    fn description() -> &'static str {
+        "wrap the toplevel component in a component with a standard interface"
+    }
+}
+
+impl ConstructVisitor for WrapMain {
+    fn from(ctx: &ir::Context) -> CalyxResult<Self>
+    where
+        Self: Sized + Named,
+    {
+        let mut pass = WrapMain {
+            top: None,
+            name: "main".into(),
+            go: "go".into(),
+            done: "done".into(),
+            clk: "clk".into(),
+            reset: "reset".into(),
+            memory_ports: "{mem}_{port}".to_string(),
+        };
+        for opt in &ctx.extra_opts {
+            let mut splits = opt.splitn(2, ':');
+            if splits.next() != Some(Self::name()) {
+                continue;
+            }
+            let (key, value) = splits
+                .next()
+                .and_then(|opt| opt.split_once('='))
+                .ok_or_else(|| {
+                    Error::Misc(format!(
+                        "Malformed option `{}`. Options of `{}` have the form `{}:<key>=<value>`",
+                        opt,
+                        Self::name(),
+                        Self::name()
+                    ))
+                })?;
+            match key {
+                "top" => pass.top = Some(value.into()),
+                "name" => pass.name = value.into(),
+                "go" => pass.go = value.into(),
+                "done" => pass.done = value.into(),
+                "clk" => pass.clk = value.into(),
+                "reset" => pass.reset = value.into(),
+                "memory-ports" => pass.memory_ports = value.to_string(),
+                _ => {
+                    return Err(Error::Misc(format!(
+                        "Unknown option `{}` for `{}`. Valid options are `top`, `name`, `go`, `done`, `clk`, `reset` and `memory-ports`",
+                        key,
+                        Self::name()
+                    )))
+                }
+            }
+        }
+        Ok(pass)
+    }
+
+    fn clear_data(&mut self) {
+        /* All data can be transferred between components */
+    }
+}
+
+impl WrapMain {
+    /// Name of the port of the wrapper that exposes `port` of the external
+    /// cell `mem`.
+    fn memory_port(&self, mem: &ir::Id, port: &ir::Id) -> ir::Id {
+        self.memory_ports
+            .replace("{mem}", mem.as_ref())
+            .replace("{port}", port.as_ref())
+            .into()
+    }
+
+    /// Name of the port of the wrapper connected to the interface port `port`
+    /// of the wrapped component, if it is one.
+    fn interface_port(&self, port: &ir::Port) -> Option<&ir::Id> {
+        let attrs = &port.attributes;
+        if attrs.get("go") == Some(&1) {
+            Some(&self.go)
+        } else if attrs.get("done") == Some(&1) {
+            Some(&self.done)
+        } else if attrs.has("clk") {
+            Some(&self.clk)
+        } else if attrs.has("reset") {
+            Some(&self.reset)
+        } else {
+            None
+        }
+    }
+}
+
+impl Visitor for WrapMain {
+    /// Works on the whole program since it adds a component.
+    fn do_pass(&mut self, context: &mut ir::Context) -> CalyxResult<()>
+    where
+        Self: Sized + ConstructVisitor,
+    {
+        let mut top = self
+            .top
+            .clone()
+            .unwrap_or_else(|| context.entrypoint.clone());
+        if !context.components.iter().any(|comp| comp.name == top) {
+            return Err(Error::Undefined(top, "component".to_string(), None));
+        }
+
+        // Free the name of the wrapper.
+        if top == self.name {
+            let mut namegen = NameGenerator::with_prev_defined_names(
+                context
+                    .components
+                    .iter()
+                    .map(|comp| comp.name.to_string())
+                    .chain(context.lib.signatures().map(|p| p.name.to_string()))
+                    .collect(),
+            );
+            let inner = namegen.gen_name(format!("{}_inner", top));
+            context.rename_component(&top, inner.clone())?;
+            top = inner;
+        } else if context.components.iter().any(|comp| comp.name == self.name)
+            || context.lib.find_primitive(&self.name).is_some()
+        {
+            return Err(Error::AlreadyBound(
+                self.name.clone(),
+                "component or primitive".to_string(),
+            ));
+        }
+
+        // Ports of the wrapped component that are exposed by the wrapper.
+        let memories = externalize_cells(context, &top)?
+            .into_iter()
+            .map(|(mem, port, sig_port)| {
+                (sig_port, self.memory_port(&mem, &port))
+            })
+            .collect::<Vec<_>>();
+        let top_comp = context
+            .components
+            .iter()
+            .find(|comp| comp.name == top)
+            .unwrap();
+        let mut exposed: Vec<(RRC<ir::Port>, ir::Id)> = vec![];
+        for port in top_comp.signature.borrow().ports.iter() {
+            let name = if let Some(name) = self.interface_port(&port.borrow()) {
+                name.clone()
+            } else if let Some((_, name)) =
+                memories.iter().find(|(p, _)| Rc::ptr_eq(p, port))
+            {
+                name.clone()
+            } else {
+                port.borrow().name.clone()
+            };
+            exposed.push((Rc::clone(port), name));
+        }
+
+        // Signature of the wrapper.
+        let mut names = HashSet::new();
+        let mut ports = vec![];
+        for (port, name) in &exposed {
+            if !names.insert(name.clone()) {
+                return Err(Error::AlreadyBound(
+                    name.clone(),
+                    format!("port of component `{}`", self.name),
+                ));
+            }
+            let port = port.borrow();
+            let mut attributes = ir::Attributes::default();
+            for attr in ["go", "done", "clk", "reset"] {
+                if let Some(val) = port.attributes.get(attr) {
+                    attributes.insert(attr, *val);
+                }
+            }
+            ports.push((
+                name.clone(),
+                port.width,
+                port.direction.reverse(),
+                attributes,
+            ));
+        }
+
+        let mut wrapper = ir::Component::new(&self.name, ports);
+        let mut builder = ir::Builder::new(&mut wrapper, &context.lib);
+        let cell = builder.add_component(top.clone(), top_comp);
+        let mut assigns = vec![];
+        for (port, name) in &exposed {
+            let port = port.borrow();
+            // The clock and reset are connected by `clk-insertion` and
+            // `reset-insertion`.
+            if port.attributes.has("clk") || port.attributes.has("reset") {
+                continue;
+            }
+            let inner = cell.borrow().get(&port.name);
+            let outer = builder.component.signature.borrow().get(name);
+            let assign = if inner.borrow().direction == ir::Direction::Input {
+                builder.build_assignment(inner, outer, ir::Guard::True)
+            } else {
+                builder.build_assignment(outer, inner, ir::Guard::True)
+            };
+            assigns.push(assign);
+        }
+        wrapper.continuous_assignments = assigns;
+
+        // The wrapper replaces the wrapped component as the entrypoint.
+        let top_comp = context
+            .components
+            .iter_mut()
+            .find(|comp| comp.name == top)
+            .unwrap();
+        if top_comp.attributes.remove("toplevel").is_some() {
+            wrapper.attributes.insert("toplevel", 1);
+        }
+        context.entrypoint = wrapper.name.clone();
+        context.components.push(wrapper);
+        Ok(())
+    }
+}
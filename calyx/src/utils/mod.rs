@@ -3,8 +3,12 @@ pub mod math;
 pub(crate) mod measure_time;
 mod namegenerator;
 mod out_file;
+mod rom;
+mod suggest;
 mod weight_graph;
 
 pub use namegenerator::NameGenerator;
 pub use out_file::OutputFile;
+pub use rom::{read_memory_file, read_rom_file};
+pub use suggest::closest_name;
 pub use weight_graph::{BoolIdx, Idx, WeightGraph};
@@ -19,29 +19,44 @@ impl NameGenerator {
         }
     }
 
-    /// Returns a new String that starts with `prefix`.
+    /// Mark `names` as defined so that they are never generated.
+    pub fn add_names<I>(&mut self, names: I)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.generated_names.extend(names)
+    }
+
+    /// Returns a new String that starts with `prefix`. The prefix itself is
+    /// used if it is not defined. Otherwise, the smallest counter that gives
+    /// an undefined name is appended to it.
     /// For example:
     /// ```
+    /// namegen.gen_name("seq");  // Generates "seq"
     /// namegen.gen_name("seq");  // Generates "seq0"
     /// namegen.gen_name("seq");  // Generates "seq1"
     /// ```
+    /// Since names defined by the program are never generated, the names
+    /// stay fresh when a printed program is parsed again.
     pub fn gen_name<S>(&mut self, prefix: S) -> ir::Id
     where
         S: Into<ir::Id> + ToString + Clone,
     {
-        let mut cur_prefix: ir::Id = prefix.into();
+        let prefix: ir::Id = prefix.into();
         loop {
             // Insert default value for this prefix if there is no entry.
             let count = self
                 .name_hash
-                .entry(cur_prefix.to_string())
+                .entry(prefix.to_string())
                 .and_modify(|v| *v += 1)
                 .or_insert(-1);
 
+            // Return the prefix itself to keep the position information of
+            // names defined by the program.
             let name = if *count == -1 {
-                cur_prefix
+                prefix.clone()
             } else {
-                ir::Id::from(cur_prefix.to_string() + &count.to_string())
+                ir::Id::from(prefix.to_string() + &count.to_string())
             };
 
             // If we've not generated this name before, return it.
@@ -49,9 +64,6 @@ impl NameGenerator {
                 self.generated_names.insert(name.to_string());
                 return name;
             }
-
-            // If the name was generated before, use the current name as the prefix.
-            cur_prefix = name;
         }
     }
 }
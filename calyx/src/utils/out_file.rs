@@ -1,4 +1,4 @@
-use std::{io::Write, path::PathBuf, str::FromStr};
+use std::{cell::RefCell, io::Write, path::PathBuf, rc::Rc, str::FromStr};
 
 /// Possible choices for output streams.
 /// Used by the `-o` option to the compiler.
@@ -6,6 +6,8 @@ use std::{io::Write, path::PathBuf, str::FromStr};
 pub enum OutputFile {
     Stdout,
     File(PathBuf),
+    /// In-memory buffer used when the compiler is called as a library.
+    Buffer(Rc<RefCell<Vec<u8>>>),
 }
 
 impl OutputFile {
@@ -13,6 +15,7 @@ impl OutputFile {
         match self {
             OutputFile::Stdout => "<stdout>".to_string(),
             OutputFile::File(path) => path.to_string_lossy().to_string(),
+            OutputFile::Buffer(_) => "<buffer>".to_string(),
         }
     }
 }
@@ -32,6 +35,7 @@ impl ToString for OutputFile {
         match self {
             OutputFile::Stdout => "-".to_string(),
             OutputFile::File(p) => p.to_str().unwrap().to_string(),
+            OutputFile::Buffer(_) => "<buffer>".to_string(),
         }
     }
 }
@@ -46,7 +50,7 @@ impl OutputFile {
     pub fn isatty(&self) -> bool {
         match self {
             OutputFile::Stdout => atty::is(atty::Stream::Stdout),
-            OutputFile::File(_) => false,
+            OutputFile::File(_) | OutputFile::Buffer(_) => false,
         }
     }
 
@@ -56,6 +60,20 @@ impl OutputFile {
             OutputFile::File(path) => {
                 Box::new(std::fs::File::create(path).unwrap())
             }
+            OutputFile::Buffer(buf) => Box::new(BufferWriter(Rc::clone(buf))),
         }
     }
 }
+
+/// Appends everything written to it to a shared buffer.
+struct BufferWriter(Rc<RefCell<Vec<u8>>>);
+
+impl Write for BufferWriter {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(bytes)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+//! Contents of memories read from files.
+use crate::errors::{CalyxResult, Error};
+use std::fs;
+
+/// Read the contents of a `std_rom` with `size` elements of `width` bits
+/// from `path`. The file contains whitespace separated hexadecimal values,
+/// like the files read by `$readmemh`, and `//` starts a comment. Elements
+/// that are not given a value in the file are zero.
+pub fn read_rom_file(
+    path: &str,
+    width: u64,
+    size: u64,
+) -> CalyxResult<Vec<u128>> {
+    read_contents(path, width, size, "ROM")
+}
+
+/// Read the initial contents of a memory with `size` elements of `width` bits
+/// from `path`. The file uses the same format as [read_rom_file].
+pub fn read_memory_file(
+    path: &str,
+    width: u64,
+    size: u64,
+) -> CalyxResult<Vec<u128>> {
+    read_contents(path, width, size, "memory")
+}
+
+fn read_contents(
+    path: &str,
+    width: u64,
+    size: u64,
+    kind: &str,
+) -> CalyxResult<Vec<u128>> {
+    let read_err = |msg: String| {
+        Error::InvalidFile(format!(
+            "Failed to read {} contents from {}: {}",
+            kind, path, msg
+        ))
+    };
+    if width > 128 {
+        return Err(read_err(format!(
+            "{} elements wider than 128 bits are not supported but the width is {}",
+            kind, width
+        )));
+    }
+    let contents =
+        fs::read_to_string(path).map_err(|err| read_err(err.to_string()))?;
+
+    let mut data = vec![];
+    for word in contents
+        .lines()
+        .map(|line| line.split("//").next().unwrap())
+        .flat_map(str::split_whitespace)
+    {
+        let val =
+            u128::from_str_radix(&word.replace('_', ""), 16).map_err(|_| {
+                read_err(format!("`{}` is not a hexadecimal number", word))
+            })?;
+        if width < 128 && val >> width != 0 {
+            return Err(read_err(format!(
+                "`{}` does not fit in {} bits",
+                word, width
+            )));
+        }
+        data.push(val);
+    }
+    if data.len() as u64 > size {
+        return Err(read_err(format!(
+            "found {} values but the {} has {} elements",
+            data.len(),
+            kind,
+            size
+        )));
+    }
+    data.resize(size as usize, 0);
+    Ok(data)
+}
@@ -0,0 +1,39 @@
+//! Suggestions for misspelled names.
+
+/// Edit distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let subst = prev[j] + usize::from(ca != *cb);
+            cur[j + 1] = subst.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+/// The name in `candidates` that is most similar to `name`, if any of them
+/// is similar enough to be a likely misspelling. Ties are broken in favor of
+/// the earlier candidate.
+pub fn closest_name<I, S>(name: &str, candidates: I) -> Option<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let max_dist = std::cmp::max(1, name.chars().count() / 3);
+    let mut best: Option<(usize, String)> = None;
+    for cand in candidates {
+        let cand = cand.as_ref();
+        let dist = edit_distance(name, cand);
+        if dist == 0 || dist > max_dist {
+            continue;
+        }
+        if !matches!(&best, Some((d, _)) if *d <= dist) {
+            best = Some((dist, cand.to_string()));
+        }
+    }
+    best.map(|(_, cand)| cand)
+}
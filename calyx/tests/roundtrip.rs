@@ -0,0 +1,54 @@
+//! Property-based tests over randomly generated programs. Run with
+//! `cargo test -p calyx --features fuzz`.
+use calyx::{
+    errors::CalyxResult, frontend, fuzz, ir, pass_manager::PassManager,
+};
+use proptest::prelude::*;
+use std::path::Path;
+
+/// Prints the `main` component of `ctx`.
+fn print(ctx: &ir::Context) -> String {
+    let mut buf = vec![];
+    for comp in &ctx.components {
+        ir::IRPrinter::write_component(comp, &mut buf).unwrap();
+    }
+    String::from_utf8(buf).unwrap()
+}
+
+/// Parses a program printed by [print].
+fn reparse(printed: &str) -> CalyxResult<ir::Context> {
+    let src = format!("{}\n{}", fuzz::PRELUDE, printed);
+    let ns = frontend::parser::CalyxParser::parse_source(
+        &src,
+        Path::new("roundtrip.futil"),
+    )?;
+    ir::from_ast::ast_to_ir(fuzz::workspace(ns), ir::BackendConf::default())
+}
+
+proptest! {
+    #[test]
+    fn print_parse_roundtrip(ns in fuzz::arb_program()) {
+        let ctx = ir::from_ast::ast_to_ir(
+            fuzz::workspace(ns),
+            ir::BackendConf::default(),
+        )
+        .unwrap();
+        let printed = print(&ctx);
+        let ctx = reparse(&printed).unwrap_or_else(|err| {
+            panic!("failed to parse:\n{}\n{:?}", printed, err)
+        });
+        prop_assert_eq!(printed, print(&ctx));
+    }
+
+    #[test]
+    fn validation_does_not_panic(ns in fuzz::arb_program()) {
+        let mut ctx = ir::from_ast::ast_to_ir(
+            fuzz::workspace(ns),
+            ir::BackendConf::default(),
+        )
+        .unwrap();
+        let pm = PassManager::default_passes().unwrap();
+        // Validation may reject a program but must not panic on it.
+        let _ = pm.execute_plan(&mut ctx, &["validate".to_string()], &[]);
+    }
+}
@@ -25,6 +25,8 @@ DEFAULT_CONFIGURATION = {
             "exec": "./target/debug/futil",
             "file_extensions": [".futil"],
             "flags": None,
+            "lib_paths": None,
+            "lib_cache": None,
         },
         "interpreter": {
             "exec": "./target/debug/interp",
@@ -1,3 +1,5 @@
+import os
+
 from fud.stages import SourceType, Stage
 
 from ..utils import shell, unwrap_or
@@ -22,12 +24,29 @@ class FutilStage(Stage):
     def defaults():
         return {}
 
+    def _lib_flags(self):
+        """
+        Flags for the library paths and the library cache in the configuration.
+        `lib_paths` is either a list or a string of paths separated by `:`.
+        """
+        lib_paths = unwrap_or(
+            self.config.get(["stages", self.name, "lib_paths"]), []
+        )
+        if isinstance(lib_paths, str):
+            lib_paths = lib_paths.split(os.pathsep)
+        flags = [f"-l {path}" for path in lib_paths if path]
+        lib_cache = self.config.get(["stages", self.name, "lib_cache"])
+        if lib_cache is not None:
+            flags.append(f"--lib-cache {lib_cache}")
+        return " ".join(flags)
+
     def _define_steps(self, input_data):
         cmd = " ".join(
             [
                 self.cmd,
                 "-l",
                 self.config["global", "futil_directory"],
+                self._lib_flags(),
                 self.flags,
                 unwrap_or(self.config["stages", self.name, "flags"], ""),
             ]
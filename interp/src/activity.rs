@@ -0,0 +1,189 @@
+//! Switching activity of the ports during a run of the interpreter.
+//!
+//! When activity tracking is enabled, every change to the value of a port
+//! adds the number of bits that flipped to the toggle count of the port. The
+//! counts can be used for early power estimation. Ports are identified by the
+//! instance of the component they belong to, so every instance of a
+//! component has its own counts.
+use crate::interpreter_ir as iir;
+use crate::names::{ComponentQIN, InstanceName};
+use crate::values::Value;
+use calyx::ir;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+/// Switching activity collected by a simulation. It is shared by the
+/// environments of the simulation.
+pub type SharedActivity = Rc<RefCell<Activity>>;
+
+/// Toggle counts of the ports of a program.
+#[derive(Default)]
+pub struct Activity {
+    /// Toggle counts of the ports of each instance keyed by their address.
+    toggles: HashMap<ComponentQIN, HashMap<usize, u64>>,
+}
+
+impl Activity {
+    /// Activity of a new simulation.
+    pub fn new() -> SharedActivity {
+        Rc::new(RefCell::new(Self::default()))
+    }
+
+    /// Record that the value of `port` of the instance `qin` changed from
+    /// `old` to `new`.
+    pub(crate) fn record(
+        &mut self,
+        qin: &ComponentQIN,
+        port: *const ir::Port,
+        old: &Value,
+        new: &Value,
+    ) {
+        let flips = old
+            .iter()
+            .zip(new.iter())
+            .filter(|(old, new)| old != new)
+            .count() as u64;
+        if flips > 0 {
+            *self
+                .toggles
+                .entry(qin.clone())
+                .or_default()
+                .entry(port as usize)
+                .or_default() += flips;
+        }
+    }
+
+    fn lookup(&self, qin: &ComponentQIN, port: &ir::RRC<ir::Port>) -> u64 {
+        let addr = port.as_ptr() as *const ir::Port as usize;
+        self.toggles
+            .get(qin)
+            .and_then(|ports| ports.get(&addr))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Activity of a single cell.
+#[derive(Serialize)]
+struct CellActivity {
+    /// Name of the primitive or component the cell instantiates.
+    #[serde(rename = "type")]
+    ty: String,
+    /// Toggle count of each port.
+    ports: BTreeMap<String, u64>,
+}
+
+/// Activity of all the cells of the same primitive.
+#[derive(Serialize, Default)]
+struct PrimitiveActivity {
+    /// Number of cells of the primitive.
+    cells: u64,
+    /// Total width of the ports of the cells.
+    bits: u64,
+    /// Total toggle count of the ports of the cells.
+    toggles: u64,
+}
+
+/// Activity of every port in a program.
+#[derive(Serialize)]
+pub struct ActivityReport {
+    /// Number of cycles the program ran for.
+    cycles: u64,
+    /// Activity of the cells keyed by the hierarchical name of the instance
+    /// of their component, e.g., `main.dot0`, and by cell name.
+    instances: BTreeMap<String, BTreeMap<String, CellActivity>>,
+    /// Activity aggregated by primitive. Cells of components are not
+    /// included since their activity is already counted in the cells of the
+    /// component.
+    summary: BTreeMap<String, PrimitiveActivity>,
+}
+
+impl ActivityReport {
+    /// Build the report for the instances of the components in `ctx`,
+    /// starting from `main`, from the counts collected so far.
+    pub fn new(
+        ctx: &iir::ComponentCtx,
+        main: &Rc<iir::Component>,
+        activity: &Activity,
+        cycles: u64,
+    ) -> Self {
+        let mut report = Self {
+            cycles,
+            instances: BTreeMap::new(),
+            summary: BTreeMap::new(),
+        };
+        let qin = ComponentQIN::new_single(main, &main.name);
+        report.add_instance(ctx, main, &qin, activity);
+        report
+    }
+
+    /// Add the activity of the instance `qin` of `comp` and of the instances
+    /// it contains.
+    fn add_instance(
+        &mut self,
+        ctx: &iir::ComponentCtx,
+        comp: &Rc<iir::Component>,
+        qin: &ComponentQIN,
+        activity: &Activity,
+    ) {
+        let mut cells = BTreeMap::new();
+        for cell in comp.cells.iter() {
+            let cell = cell.borrow();
+            if let Some(act) = self.cell_activity(&cell, qin, activity) {
+                cells.insert(cell.name().to_string(), act);
+            }
+            if let ir::CellType::Component { name } = &cell.prototype {
+                let inner = ctx.iter().find(|c| c.name == name).unwrap();
+                let inner_qin =
+                    qin.new_extend(InstanceName::new(inner, cell.name()));
+                self.add_instance(ctx, inner, &inner_qin, activity);
+            }
+        }
+        self.instances.insert(qin.to_string(), cells);
+    }
+
+    /// Activity of the ports of `cell`. Constants are skipped since their
+    /// values never change.
+    fn cell_activity(
+        &mut self,
+        cell: &ir::Cell,
+        qin: &ComponentQIN,
+        activity: &Activity,
+    ) -> Option<CellActivity> {
+        let (ty, is_prim) = match &cell.prototype {
+            ir::CellType::Primitive { name, .. } => (name, true),
+            ir::CellType::Component { name } => (name, false),
+            ir::CellType::ThisComponent | ir::CellType::Constant { .. } => {
+                return None
+            }
+        };
+        let ports: BTreeMap<_, _> = cell
+            .ports
+            .iter()
+            .map(|port| {
+                (port.borrow().name.to_string(), activity.lookup(qin, port))
+            })
+            .collect();
+        if is_prim {
+            let summary = self.summary.entry(ty.to_string()).or_default();
+            summary.cells += 1;
+            summary.bits += cell
+                .ports
+                .iter()
+                .map(|port| port.borrow().width)
+                .sum::<u64>();
+            summary.toggles += ports.values().sum::<u64>();
+        }
+        Some(CellActivity {
+            ty: ty.to_string(),
+            ports,
+        })
+    }
+
+    /// The report as a JSON object.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
@@ -0,0 +1,227 @@
+//! Checkpoints of a simulation.
+//!
+//! A checkpoint records the clock, the values of all ports, and the state of
+//! all stateful cells of a program along with the position of the main
+//! component in its control program. A long simulation can then be resumed
+//! from the checkpoint instead of being run from the start.
+//!
+//! Checkpoints are only saved right before a group is enabled or a component
+//! is invoked by the control program of the main component outside of a
+//! `par`, since the state of the program is fully described by its cells and
+//! ports at these points. The checkpoint is saved at the first such point at
+//! or after the requested cycle.
+use crate::environment::InterpreterState;
+use crate::errors::{InterpreterError, InterpreterResult};
+use crate::interpreter_ir as iir;
+use crate::values::Value;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::iter::once;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// A step into a control statement.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Step {
+    /// The statement of a `seq` with the given index.
+    Seq(usize),
+    /// The body of a `while` loop.
+    Body,
+    /// The branch of an `if` statement taken when the condition has the
+    /// given value.
+    Branch(bool),
+}
+
+/// The state of an instance of a component.
+#[derive(Serialize, Deserialize)]
+pub struct ComponentState {
+    /// Clock count
+    pub clk: u64,
+    /// Values of the ports keyed by their name.
+    pub ports: BTreeMap<String, Value>,
+    /// State of the stateful cells keyed by their name. Sub-components store
+    /// their own [ComponentState].
+    pub cells: BTreeMap<String, serde_json::Value>,
+}
+
+/// A checkpoint of a simulation.
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Position of the next statement to execute in the control program of
+    /// the main component.
+    position: Vec<Step>,
+    /// State of the main component.
+    state: ComponentState,
+}
+
+impl Checkpoint {
+    /// Read a checkpoint saved by a previous simulation.
+    pub fn read(path: &Path) -> InterpreterResult<Self> {
+        let buf = fs::read(path).map_err(|err| {
+            InterpreterError::CheckpointError(format!(
+                "failed to read {}: {}",
+                path.to_string_lossy(),
+                err
+            ))
+        })?;
+        serde_json::from_slice(&buf).map_err(|err| {
+            InterpreterError::CheckpointError(format!(
+                "failed to read {}: {}",
+                path.to_string_lossy(),
+                err
+            ))
+        })
+    }
+
+    fn write(&self, path: &Path) -> InterpreterResult<()> {
+        let to_err = |err: &dyn std::fmt::Display| {
+            InterpreterError::CheckpointError(format!(
+                "failed to write {}: {}",
+                path.to_string_lossy(),
+                err
+            ))
+        };
+        let buf = serde_json::to_vec(self).map_err(|err| to_err(&err))?;
+        fs::write(path, buf).map_err(|err| to_err(&err))
+    }
+
+    /// The cycle in which the checkpoint was saved.
+    pub fn cycle(&self) -> u64 {
+        self.state.clk
+    }
+
+    /// Restore the state of the main component into `env`.
+    pub fn restore(self, env: &mut InterpreterState) -> InterpreterResult<()> {
+        env.restore_state(self.state)
+    }
+
+    /// The part of `control` that remains to be executed after resuming from
+    /// this checkpoint. `control` must be the control program of the main
+    /// component.
+    pub fn remaining_control(
+        &self,
+        control: &iir::Control,
+    ) -> InterpreterResult<iir::Control> {
+        remaining_control(control, &self.position)
+    }
+}
+
+fn remaining_control(
+    control: &iir::Control,
+    position: &[Step],
+) -> InterpreterResult<iir::Control> {
+    let (step, rest) = match position.split_first() {
+        Some(split) => split,
+        None => return Ok(control.clone()),
+    };
+    match (control, step) {
+        (iir::Control::Seq(seq), Step::Seq(idx)) if *idx < seq.stmts.len() => {
+            let stmts = once(remaining_control(&seq.stmts[*idx], rest)?)
+                .chain(seq.stmts[idx + 1..].iter().cloned())
+                .collect();
+            Ok(iir::Control::Seq(Rc::new(iir::Seq {
+                stmts,
+                attributes: Default::default(),
+            })))
+        }
+        // Finish the current iteration and then run the loop again.
+        (iir::Control::While(wh), Step::Body) => {
+            Ok(iir::Control::Seq(Rc::new(iir::Seq {
+                stmts: vec![remaining_control(&wh.body, rest)?, control.clone()],
+                attributes: Default::default(),
+            })))
+        }
+        (iir::Control::If(if_), Step::Branch(cond)) => {
+            let branch = if *cond { &if_.tbranch } else { &if_.fbranch };
+            remaining_control(branch, rest)
+        }
+        _ => Err(InterpreterError::CheckpointError(
+            "the saved position does not exist in the control program of the main component".to_string(),
+        )),
+    }
+}
+
+/// Checkpoint requested from a simulation. It is shared by the environments
+/// of the simulation.
+pub type SharedRequest = Rc<RefCell<Request>>;
+
+/// A checkpoint to save once the simulation reaches a cycle.
+pub struct Request {
+    cycle: u64,
+    path: PathBuf,
+    /// True once the checkpoint was saved.
+    saved: bool,
+    /// Current position in the control program of the main component.
+    position: Vec<Step>,
+}
+
+impl Request {
+    /// Save a checkpoint to `path` once the simulation reaches `cycle`.
+    pub fn new(cycle: u64, path: PathBuf) -> SharedRequest {
+        Rc::new(RefCell::new(Self {
+            cycle,
+            path,
+            saved: false,
+            position: vec![],
+        }))
+    }
+
+    /// Returns true if the checkpoint has not been saved yet.
+    pub fn is_pending(&self) -> bool {
+        !self.saved
+    }
+}
+
+/// The request of the simulation of `env` if it is pending and `env` is the
+/// instance of the main component, whose position is saved.
+fn pending(env: &InterpreterState) -> Option<&SharedRequest> {
+    env.instruments
+        .checkpoint
+        .as_ref()
+        .filter(|req| env.qin.len() == 1 && req.borrow().is_pending())
+}
+
+/// Tracks a step into a control statement until it is dropped.
+pub(crate) struct StepGuard {
+    request: Option<SharedRequest>,
+}
+
+impl Drop for StepGuard {
+    fn drop(&mut self) {
+        if let Some(req) = &self.request {
+            req.borrow_mut().position.pop();
+        }
+    }
+}
+
+/// Record that the main component of `env` entered a control statement.
+/// The position is only tracked while a checkpoint is pending.
+#[must_use]
+pub(crate) fn enter(env: &InterpreterState, step: Step) -> StepGuard {
+    let request = pending(env).cloned();
+    if let Some(req) = &request {
+        req.borrow_mut().position.push(step);
+    }
+    StepGuard { request }
+}
+
+/// Save the requested checkpoint if the simulation has reached its cycle.
+/// Must only be called right before the main component enables a group or
+/// invokes a component.
+pub(crate) fn save_if_due(env: &InterpreterState) -> InterpreterResult<()> {
+    if let Some(req) = pending(env) {
+        let mut req = req.borrow_mut();
+        if env.clk >= req.cycle {
+            let checkpoint = Checkpoint {
+                position: req.position.clone(),
+                state: env.save_state()?,
+            };
+            checkpoint.write(&req.path)?;
+            req.saved = true;
+        }
+    }
+    Ok(())
+}
@@ -11,8 +11,14 @@ pub struct Config {
     /// enables/disables "sloppy" interpretation which returns 0 for invalid indicies
     /// rather than erroring
     pub allow_invalid_memory_access: bool,
+    /// checks every index of a memory access against its dimension rather
+    /// than only checking the flattened address
+    pub strict_memory_access: bool,
     /// upgrades overflow/underflow warnings into errors
     pub error_on_overflow: bool,
+    /// errors when an adder, subtractor, or multiplier produces a result
+    /// that does not fit its output width
+    pub check_overflow: bool,
     /// permits "sloppy" interpretation with parallel blocks
     pub allow_par_conflicts: bool,
 }
@@ -20,7 +26,9 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             allow_invalid_memory_access: false,
+            strict_memory_access: false,
             error_on_overflow: false,
+            check_overflow: false,
             allow_par_conflicts: false,
         }
     }
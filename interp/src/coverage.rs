@@ -0,0 +1,377 @@
+//! Coverage information for a run of the interpreter.
+//!
+//! When coverage is enabled, the interpreter counts how many times each
+//! control statement is executed and, for `if` and `while` statements, how
+//! many times the condition was true and false. Statements are identified by
+//! their component and their position in its control program, so the counts
+//! are shared by every instance of a component.
+use crate::environment::InterpreterState;
+use crate::interpreter_ir as iir;
+use calyx::ir;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+/// Execution counts for a single control statement.
+#[derive(Default, Clone, Copy)]
+struct Counts {
+    /// Number of times the statement was executed.
+    count: u64,
+    /// Number of times the condition was true.
+    taken: u64,
+    /// Number of times the condition was false.
+    not_taken: u64,
+}
+
+/// A control statement identified by the name of its component and its
+/// position in the control program of the component.
+type StmtId = (ir::Id, usize);
+
+/// Coverage information collected by a simulation. It is shared by the
+/// environments of the simulation.
+pub type SharedCoverage = Rc<RefCell<Coverage>>;
+
+/// Execution counts of the control statements of a program.
+#[derive(Default)]
+pub struct Coverage {
+    /// Ids of the statements keyed by their address. The control programs
+    /// are shared through `Rc`s, so the addresses do not change while the
+    /// program is simulated.
+    ids: HashMap<usize, StmtId>,
+    counts: HashMap<StmtId, Counts>,
+}
+
+impl Coverage {
+    /// Coverage information for the components in `ctx`.
+    pub fn new(ctx: &iir::ComponentCtx) -> SharedCoverage {
+        let mut cov = Self::default();
+        for comp in ctx.iter() {
+            cov.number(&comp.name, &comp.control, &mut 0);
+        }
+        Rc::new(RefCell::new(cov))
+    }
+
+    /// Assign ids to the statements of `ctrl` in the order in which they
+    /// appear in the report.
+    fn number(&mut self, comp: &ir::Id, ctrl: &iir::Control, next: &mut usize) {
+        if !matches!(ctrl, iir::Control::Empty(_)) {
+            self.ids.insert(addr(ctrl), (comp.clone(), *next));
+            *next += 1;
+        }
+        match ctrl {
+            iir::Control::Seq(s) => {
+                for stmt in &s.stmts {
+                    self.number(comp, stmt, next);
+                }
+            }
+            iir::Control::Par(p) => {
+                for stmt in &p.stmts {
+                    self.number(comp, stmt, next);
+                }
+            }
+            iir::Control::If(i) => {
+                self.number(comp, &i.tbranch, next);
+                self.number(comp, &i.fbranch, next);
+            }
+            iir::Control::While(w) => self.number(comp, &w.body, next),
+            iir::Control::Invoke(_)
+            | iir::Control::Enable(_)
+            | iir::Control::Empty(_)
+            | iir::Control::Debug(_) => {}
+        }
+    }
+
+    /// Update the counts of the statement at `addr`. Statements that are not
+    /// part of the program, such as the ones built when resuming from a
+    /// checkpoint, are not counted.
+    fn update<F: FnOnce(&mut Counts)>(&mut self, addr: usize, f: F) {
+        if let Some(id) = self.ids.get(&addr) {
+            f(self.counts.entry(id.clone()).or_default())
+        }
+    }
+
+    fn lookup(&self, comp: &ir::Id, idx: usize) -> Counts {
+        self.counts
+            .get(&(comp.clone(), idx))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Address of the node wrapped by a control statement.
+fn addr(ctrl: &iir::Control) -> usize {
+    match ctrl {
+        iir::Control::Seq(s) => Rc::as_ptr(s) as usize,
+        iir::Control::Par(p) => Rc::as_ptr(p) as usize,
+        iir::Control::If(i) => Rc::as_ptr(i) as usize,
+        iir::Control::While(w) => Rc::as_ptr(w) as usize,
+        iir::Control::Invoke(i) => Rc::as_ptr(i) as usize,
+        iir::Control::Enable(e) => Rc::as_ptr(e) as usize,
+        iir::Control::Empty(e) => Rc::as_ptr(e) as usize,
+        iir::Control::Debug(d) => Rc::as_ptr(d) as usize,
+    }
+}
+
+/// Record an execution of the control statement in the coverage of the
+/// simulation of `env`, if it is collected.
+pub fn record(env: &InterpreterState, ctrl: &iir::Control) {
+    if let Some(cov) = &env.instruments.coverage {
+        cov.borrow_mut().update(addr(ctrl), |c| c.count += 1)
+    }
+}
+
+/// Record the value of the condition of an `if` or `while` statement.
+pub fn record_branch<T>(env: &InterpreterState, node: &T, taken: bool) {
+    if let Some(cov) = &env.instruments.coverage {
+        cov.borrow_mut().update(node as *const T as usize, |c| {
+            if taken {
+                c.taken += 1
+            } else {
+                c.not_taken += 1
+            }
+        })
+    }
+}
+
+fn port_name(port: &ir::RRC<ir::Port>) -> String {
+    let port = port.borrow();
+    format!("{}.{}", port.get_parent_name(), port.name)
+}
+
+fn cond_str(
+    port: &ir::RRC<ir::Port>,
+    cond: &Option<ir::RRC<ir::CombGroup>>,
+) -> String {
+    match cond {
+        Some(cond) => {
+            format!("{} with {}", port_name(port), cond.borrow().name())
+        }
+        None => port_name(port),
+    }
+}
+
+/// Coverage of a single control statement.
+#[derive(Serialize)]
+struct ControlCoverage {
+    /// Position of the statement in the annotated listing of the component.
+    id: usize,
+    statement: String,
+    count: u64,
+    #[serde(rename = "true", skip_serializing_if = "Option::is_none")]
+    taken: Option<u64>,
+    #[serde(rename = "false", skip_serializing_if = "Option::is_none")]
+    not_taken: Option<u64>,
+}
+
+/// Coverage of the groups and control statements of a component.
+#[derive(Serialize)]
+struct ComponentCoverage {
+    /// Number of times each group and combinational group was run.
+    groups: BTreeMap<String, u64>,
+    control: Vec<ControlCoverage>,
+    /// Lines of the annotated control program along with their counts.
+    #[serde(skip)]
+    listing: Vec<(Option<u64>, String)>,
+}
+
+impl ComponentCoverage {
+    fn new(comp: &iir::Component, cov: &Coverage) -> Self {
+        let groups = comp
+            .groups
+            .iter()
+            .map(|g| g.borrow().name().to_string())
+            .chain(
+                comp.comb_groups
+                    .iter()
+                    .map(|g| g.borrow().name().to_string()),
+            )
+            .map(|name| (name, 0))
+            .collect();
+        let mut comp_cov = Self {
+            groups,
+            control: vec![],
+            listing: vec![],
+        };
+        comp_cov.walk(&comp.name, cov, &comp.control, 0);
+        comp_cov
+    }
+
+    fn add_group(&mut self, group: &ir::Id, count: u64) {
+        *self.groups.entry(group.to_string()).or_default() += count;
+    }
+
+    /// Add a statement along with its line in the listing.
+    fn add(
+        &mut self,
+        counts: Counts,
+        stmt: String,
+        depth: usize,
+        branch: bool,
+    ) {
+        let (taken, not_taken) = if branch {
+            (Some(counts.taken), Some(counts.not_taken))
+        } else {
+            (None, None)
+        };
+        let mut line = format!("{}{}", "  ".repeat(depth), stmt);
+        if branch {
+            line += &format!(
+                "  [true: {}, false: {}]",
+                counts.taken, counts.not_taken
+            );
+        }
+        self.control.push(ControlCoverage {
+            id: self.control.len(),
+            statement: stmt.trim_end_matches(" {").to_string(),
+            count: counts.count,
+            taken,
+            not_taken,
+        });
+        self.listing.push((Some(counts.count), line));
+    }
+
+    /// Add a line to the listing that is not a statement.
+    fn add_line(&mut self, line: &str, depth: usize) {
+        self.listing
+            .push((None, format!("{}{}", "  ".repeat(depth), line)));
+    }
+
+    fn walk(
+        &mut self,
+        comp: &ir::Id,
+        cov: &Coverage,
+        ctrl: &iir::Control,
+        depth: usize,
+    ) {
+        // Statements are numbered in the order in which they are added.
+        let counts = cov.lookup(comp, self.control.len());
+        match ctrl {
+            iir::Control::Seq(s) => {
+                self.add(counts, "seq {".to_string(), depth, false);
+                for stmt in &s.stmts {
+                    self.walk(comp, cov, stmt, depth + 1);
+                }
+                self.add_line("}", depth);
+            }
+            iir::Control::Par(p) => {
+                self.add(counts, "par {".to_string(), depth, false);
+                for stmt in &p.stmts {
+                    self.walk(comp, cov, stmt, depth + 1);
+                }
+                self.add_line("}", depth);
+            }
+            iir::Control::If(i) => {
+                let stmt = format!("if {} {{", cond_str(&i.port, &i.cond));
+                self.add(counts, stmt, depth, true);
+                if let Some(cond) = &i.cond {
+                    let evals = counts.taken + counts.not_taken;
+                    self.add_group(cond.borrow().name(), evals);
+                }
+                self.walk(comp, cov, &i.tbranch, depth + 1);
+                if !matches!(i.fbranch, iir::Control::Empty(_)) {
+                    self.add_line("} else {", depth);
+                    self.walk(comp, cov, &i.fbranch, depth + 1);
+                }
+                self.add_line("}", depth);
+            }
+            iir::Control::While(w) => {
+                let stmt = format!("while {} {{", cond_str(&w.port, &w.cond));
+                self.add(counts, stmt, depth, true);
+                if let Some(cond) = &w.cond {
+                    let evals = counts.taken + counts.not_taken;
+                    self.add_group(cond.borrow().name(), evals);
+                }
+                self.walk(comp, cov, &w.body, depth + 1);
+                self.add_line("}", depth);
+            }
+            iir::Control::Invoke(i) => {
+                let ports = |map: &[(ir::Id, ir::RRC<ir::Port>)]| {
+                    map.iter()
+                        .map(|(name, port)| {
+                            format!("{}={}", name, port_name(port))
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                let mut stmt = format!(
+                    "invoke {}({})({})",
+                    i.comp.borrow().name(),
+                    ports(&i.inputs),
+                    ports(&i.outputs)
+                );
+                if let Some(cond) = &i.comb_group {
+                    stmt += &format!(" with {}", cond.borrow().name());
+                    self.add_group(cond.borrow().name(), counts.count);
+                }
+                stmt += ";";
+                self.add(counts, stmt, depth, false);
+            }
+            iir::Control::Enable(e) => {
+                let group = e.group.borrow().name().clone();
+                self.add(counts, format!("{};", group), depth, false);
+                self.add_group(&group, counts.count);
+            }
+            iir::Control::Debug(d) => {
+                let ports = d
+                    .ports
+                    .iter()
+                    .map(|port| format!(", {}", port_name(port)))
+                    .collect::<String>();
+                let stmt = format!("@debug(\"{}\"{});", d.format, ports);
+                self.add(counts, stmt, depth, false);
+            }
+            iir::Control::Empty(_) => {}
+        }
+    }
+}
+
+/// Coverage information for all the components in a program.
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct CoverageReport(BTreeMap<String, ComponentCoverage>);
+
+impl CoverageReport {
+    /// Build the report for the components from the counts collected so far.
+    pub fn new(components: &iir::ComponentCtx, cov: &Coverage) -> Self {
+        Self(
+            components
+                .iter()
+                .map(|comp| {
+                    (comp.name.to_string(), ComponentCoverage::new(comp, cov))
+                })
+                .collect(),
+        )
+    }
+
+    /// The report as a JSON object keyed by component name.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+
+    /// The groups and control program of each component annotated with
+    /// their execution counts. Statements that were never executed are
+    /// marked with `#####`.
+    pub fn listing(&self) -> String {
+        let count_str = |count: Option<u64>| match count {
+            Some(0) => "#####".to_string(),
+            Some(n) => n.to_string(),
+            None => String::new(),
+        };
+        let mut out = String::new();
+        for (name, comp) in &self.0 {
+            out += &format!("component {}:\n", name);
+            for (group, count) in &comp.groups {
+                out += &format!(
+                    "{:>9} | group {}\n",
+                    count_str(Some(*count)),
+                    group
+                );
+            }
+            for (count, line) in &comp.listing {
+                out += &format!("{:>9} | {}\n", count_str(*count), line);
+            }
+        }
+        out
+    }
+}
@@ -4,6 +4,7 @@ use std::rc::Rc;
 
 use super::commands::{Command, PrintCode};
 use super::context::DebuggingContext;
+use super::history::{History, DEFAULT_WINDOW};
 use super::io_utils::Input;
 use crate::environment::{InterpreterState, PrimitiveMap, StateView};
 use crate::errors::{InterpreterError, InterpreterResult};
@@ -22,6 +23,10 @@ pub struct Debugger {
     _context: iir::ComponentCtx,
     main_component: Rc<iir::Component>,
     debugging_ctx: DebuggingContext,
+    /// Control program to run instead of the one of the main component.
+    control: Option<iir::Control>,
+    /// Number of steps that can be undone with `reverse-step`.
+    history_window: usize,
 }
 
 impl Debugger {
@@ -33,9 +38,22 @@ impl Debugger {
             _context: Rc::clone(context),
             main_component: Rc::clone(main_component),
             debugging_ctx: DebuggingContext::new(context, &main_component.name),
+            control: None,
+            history_window: DEFAULT_WINDOW,
         }
     }
 
+    /// Run `control` instead of the control program of the main component,
+    /// e.g., the part of it that remains after resuming from a checkpoint.
+    pub fn set_control(&mut self, control: iir::Control) {
+        self.control = Some(control);
+    }
+
+    /// Keep the deltas of the last `window` steps for `reverse-step`.
+    pub fn set_history_window(&mut self, window: usize) {
+        self.history_window = window;
+    }
+
     pub fn main_loop(
         &mut self,
         env: InterpreterState,
@@ -50,6 +68,9 @@ impl Debugger {
             env,
             qin,
         );
+        if let Some(control) = self.control.take() {
+            component_interpreter = component_interpreter.with_control(control);
+        }
         component_interpreter.set_go_high();
 
         if pass_through {
@@ -57,6 +78,8 @@ impl Debugger {
             return component_interpreter.deconstruct();
         }
 
+        let mut history = History::new(self.history_window);
+        history.start(&component_interpreter);
         let mut input_stream = Input::default();
         println!("== Calyx Interactive Debugger ==");
         loop {
@@ -76,9 +99,28 @@ impl Debugger {
 
             match comm {
                 Command::Step => {
-                    component_interpreter.step()?;
+                    if history.is_behind() {
+                        history.replay(&mut component_interpreter);
+                    } else {
+                        component_interpreter.step()?;
+                        history.record(&component_interpreter);
+                    }
+                }
+                Command::ReverseStep(steps) => {
+                    let undone = history
+                        .rewind(&mut component_interpreter, steps as usize);
+                    if undone < steps as usize {
+                        println!(
+                            "Reached the start of the history after {} steps",
+                            undone
+                        );
+                    }
                 }
                 Command::Continue => {
+                    // Return to the present before executing again.
+                    while history.is_behind() {
+                        history.replay(&mut component_interpreter);
+                    }
                     let mut breakpoints = self.debugging_ctx.hit_breakpoints(
                         component_interpreter.currently_executing_group(),
                     );
@@ -87,6 +129,7 @@ impl Debugger {
                         && !component_interpreter.is_done()
                     {
                         component_interpreter.step()?;
+                        history.record(&component_interpreter);
                         breakpoints = self.debugging_ctx.hit_breakpoints(
                             component_interpreter.currently_executing_group(),
                         );
@@ -117,6 +160,13 @@ impl Debugger {
                         if self.main_component.name == print_list[0] {
                             print_list.remove(0);
                         }
+                        // Hierarchical name of the target, which tells
+                        // instances of the same component apart.
+                        let path = std::iter::once(&self.main_component.name)
+                            .chain(print_list.iter())
+                            .map(|s| s.id.clone())
+                            .collect::<Vec<_>>()
+                            .join(".");
 
                         let mut current_target =
                             CurrentTarget::Env(&component_interpreter);
@@ -130,7 +180,12 @@ impl Debugger {
                                 // first look for cell
                                 let cell = current_env.get_cell(target);
                                 if let Some(cell) = cell {
-                                    print_cell(&cell, &current_env, &code)
+                                    print_cell(
+                                        &cell,
+                                        &path,
+                                        &current_env,
+                                        &code,
+                                    )
                                 } else if idx != 0 {
                                     let prior = &print_list[idx - 1];
 
@@ -145,8 +200,8 @@ impl Debugger {
                                         if let Some(port) = pt {
                                             print_port(
                                                 port,
+                                                &path,
                                                 &current_env,
-                                                None,
                                                 &code,
                                             )
                                         } else {
@@ -164,8 +219,8 @@ impl Debugger {
                                     {
                                         print_port(
                                             &port,
+                                            &path,
                                             &current_env,
-                                            Some(print_list[idx - 1].clone()),
                                             &code,
                                         );
                                     } else {
@@ -268,13 +323,15 @@ impl Debugger {
     }
 }
 
+/// Print the ports of the cell `target` whose hierarchical name is `path`.
 fn print_cell(
     target: &RRC<ir::Cell>,
+    path: &str,
     state: &StateView,
     code: &Option<PrintCode>,
 ) {
     let cell_ref = target.borrow();
-    println!("{}{}", SPACING, cell_ref.name());
+    println!("{}{}", SPACING, path);
     for port in cell_ref.ports.iter() {
         let v = state.lookup(port.as_raw());
         println!(
@@ -295,26 +352,20 @@ fn print_cell(
     }
 }
 
+/// Print the value of the port `target` whose hierarchical name is `path`.
 fn print_port(
     target: &RRC<ir::Port>,
+    path: &str,
     state: &StateView,
-    prior_name: Option<ir::Id>,
     code: &Option<PrintCode>,
 ) {
     let port_ref = target.borrow();
-    let parent_name = if let Some(prior) = prior_name {
-        prior
-    } else {
-        port_ref.get_parent_name()
-    };
-
     let v = state.lookup(port_ref.as_raw());
 
     println!(
-        "{}{}.{} = {}",
+        "{}{} = {}",
         SPACING,
-        parent_name,
-        port_ref.name,
+        path,
         if let Some(code) = code {
             match code {
                 PrintCode::Unsigned => format!("{}", v.as_unsigned()),
@@ -35,8 +35,9 @@ pub enum PrintCode {
     SFixed(usize),
 }
 // This is used internally to print out the help message but otherwise is not used for anything
-const HELP_LIST: [Command; 10] = [
+const HELP_LIST: [Command; 11] = [
     Command::Step,
+    Command::ReverseStep(1),
     Command::Continue,
     Command::Display,
     Command::Print(None, None),
@@ -49,9 +50,10 @@ const HELP_LIST: [Command; 10] = [
 ];
 pub enum Command {
     Step,                                                      // Step execution
-    Continue, // Execute until breakpoint
-    Empty,    // Empty command, does nothing
-    Display,  // Display full environment contents
+    ReverseStep(u64), // Undo the given number of steps
+    Continue,         // Execute until breakpoint
+    Empty,            // Empty command, does nothing
+    Display,          // Display full environment contents
     Print(Option<Vec<Vec<calyx::ir::Id>>>, Option<PrintCode>), // Print something
     Break(Vec<GroupName>), // Create a breakpoint
     Help,                  // Help message
@@ -77,6 +79,7 @@ impl Command {
     fn help_string(&self) -> (Vec<&str>, &str) {
         match self {
             Command::Step => (vec!["Step", "S"], "Advance the execution by a step"),
+            Command::ReverseStep(_) => (vec!["Reverse-step", "RS"], "Undo the given number of steps (default 1)"),
             Command::Continue => ( vec!["Continue", "C"], "Continue until the program finishes executing or hits a breakpoint"),
             Command::Display => (vec!["Display"], "Display the full state"),
             Command::Print(_, _) => (vec!["Print", "P"], "Print target value"),
@@ -197,12 +197,17 @@ impl DebuggingContext {
         }
     }
 
+    /// Returns the running instances of the groups whose breakpoints were
+    /// hit, sorted by their hierarchical names.
     pub fn hit_breakpoints(
         &mut self,
         current_executing: HashSet<GroupQIN>,
-    ) -> Vec<CompGroupName> {
-        let current: HashSet<CompGroupName> =
-            current_executing.into_iter().map(|x| x.into()).collect();
+    ) -> Vec<GroupQIN> {
+        let current: HashSet<CompGroupName> = current_executing
+            .iter()
+            .cloned()
+            .map(|x| x.into())
+            .collect();
 
         let sleeping = std::mem::take(&mut self.sleeping_breakpoints);
 
@@ -219,7 +224,7 @@ impl DebuggingContext {
             })
             .collect();
 
-        current
+        let hit: HashSet<CompGroupName> = current
             .into_iter()
             .filter(|x| {
                 if let Some(brk) = self.breakpoints.get_mut(x) {
@@ -231,7 +236,15 @@ impl DebuggingContext {
                 }
                 false
             })
-            .collect()
+            .collect();
+
+        // Every instance of a group shares its breakpoint.
+        let mut instances = current_executing
+            .into_iter()
+            .filter(|x| hit.contains(&CompGroupName::from(x.clone())))
+            .collect::<Vec<_>>();
+        instances.sort_by_key(|x| x.to_string());
+        instances
     }
 
     pub fn print_breakpoints(&self) {
@@ -0,0 +1,188 @@
+//! History of the states the debugger stepped through.
+//!
+//! After every step, the values of the ports and the state of the stateful
+//! cells of the main component are compared with the ones before the step
+//! and the bindings that changed are recorded. `reverse-step` undoes these
+//! deltas to move the state backwards without re-running the program, and
+//! stepping forward again replays them until the present is reached. Only the
+//! most recent steps, up to the size of the history window, are kept.
+//!
+//! The control program is not rewound, so the state of instances of
+//! components that are running cannot be restored and is left as it is.
+use crate::environment::StateView;
+use crate::interpreter::{ComponentInterpreter, ConstCell, Interpreter};
+use crate::utils::AsRaw;
+use crate::values::Value;
+use calyx::ir;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+type ConstPort = *const ir::Port;
+
+/// Number of steps kept when no window is given.
+pub const DEFAULT_WINDOW: usize = 100;
+
+/// Values of the ports and states of the cells of the main component.
+#[derive(Default)]
+struct Snapshot {
+    ports: HashMap<ConstPort, Value>,
+    cells: HashMap<ConstCell, serde_json::Value>,
+}
+
+impl Snapshot {
+    fn take(env: &StateView) -> Self {
+        let comp = env.get_comp();
+        let sig = comp.signature.borrow();
+        let holes = comp.groups.iter().flat_map(|group| {
+            group.borrow().holes.iter().cloned().collect::<Vec<_>>()
+        });
+        let cell_ports = comp
+            .cells
+            .iter()
+            .filter(|cell| {
+                !matches!(
+                    cell.borrow().prototype,
+                    ir::CellType::Constant { .. }
+                )
+            })
+            .flat_map(|cell| cell.borrow().ports.clone());
+        let ports = sig
+            .ports
+            .iter()
+            .cloned()
+            .chain(holes)
+            .chain(cell_ports)
+            .map(|port| (port.as_raw(), env.lookup(port.as_raw()).clone()))
+            .collect();
+
+        // Instances of components that are running cannot be saved.
+        let cell_map = env.get_cell_map().borrow();
+        let cells = comp
+            .cells
+            .iter()
+            .filter_map(|cell| {
+                let state = cell_map.get(&cell.as_raw())?.save_state();
+                Some((cell.as_raw(), state.ok()??))
+            })
+            .collect();
+        Self { ports, cells }
+    }
+}
+
+/// Bindings changed by a step along with their values before and after it.
+#[derive(Default)]
+struct Delta {
+    ports: Vec<(ConstPort, Value, Value)>,
+    cells: Vec<(ConstCell, serde_json::Value, serde_json::Value)>,
+}
+
+impl Delta {
+    fn between(before: &Snapshot, after: &Snapshot) -> Self {
+        let mut delta = Delta::default();
+        for (port, new) in &after.ports {
+            match before.ports.get(port) {
+                Some(old) if old != new => {
+                    delta.ports.push((*port, old.clone(), new.clone()))
+                }
+                _ => (),
+            }
+        }
+        for (cell, new) in &after.cells {
+            match before.cells.get(cell) {
+                Some(old) if old != new => {
+                    delta.cells.push((*cell, old.clone(), new.clone()))
+                }
+                _ => (),
+            }
+        }
+        delta
+    }
+
+    /// Set the bindings to their values before the step if `undo` is true and
+    /// to their values after it otherwise.
+    fn apply(&self, interp: &mut ComponentInterpreter, undo: bool) {
+        let cell_map = Rc::clone(interp.get_env().get_cell_map());
+        for (cell, old, new) in &self.cells {
+            if let Some(prim) = cell_map.borrow_mut().get_mut(cell) {
+                let state = if undo { old } else { new };
+                // Instances of components that are running are left as they
+                // are.
+                let _ = prim.restore_state(state.clone());
+            }
+        }
+        let mut env = interp.get_mut_env();
+        for (port, old, new) in &self.ports {
+            let value = if undo { old } else { new };
+            env.insert(*port, value.clone());
+        }
+    }
+}
+
+/// The deltas of the most recent steps of the debugger.
+pub struct History {
+    /// Maximum number of steps kept.
+    window: usize,
+    /// Deltas of the steps, oldest first.
+    deltas: VecDeque<Delta>,
+    /// Number of steps the current state is behind the present.
+    behind: usize,
+    /// State after the last recorded step.
+    last: Snapshot,
+}
+
+impl History {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window,
+            deltas: VecDeque::new(),
+            behind: 0,
+            last: Snapshot::default(),
+        }
+    }
+
+    /// Record the state of `interp` before the first step.
+    pub fn start(&mut self, interp: &ComponentInterpreter) {
+        self.last = Snapshot::take(&interp.get_env());
+    }
+
+    /// Record the bindings changed by the step `interp` just executed.
+    pub fn record(&mut self, interp: &ComponentInterpreter) {
+        if self.window == 0 {
+            return;
+        }
+        let now = Snapshot::take(&interp.get_env());
+        if self.deltas.len() == self.window {
+            self.deltas.pop_front();
+        }
+        self.deltas.push_back(Delta::between(&self.last, &now));
+        self.last = now;
+    }
+
+    /// Returns true if the state of the interpreter is behind the present.
+    pub fn is_behind(&self) -> bool {
+        self.behind > 0
+    }
+
+    /// Move the state of `interp` back by up to `steps` steps. Returns the
+    /// number of steps it was moved back by.
+    pub fn rewind(
+        &mut self,
+        interp: &mut ComponentInterpreter,
+        steps: usize,
+    ) -> usize {
+        let steps = steps.min(self.deltas.len() - self.behind);
+        for _ in 0..steps {
+            let idx = self.deltas.len() - self.behind - 1;
+            self.deltas[idx].apply(interp, true);
+            self.behind += 1;
+        }
+        steps
+    }
+
+    /// Move the state of `interp` forward by one step towards the present.
+    pub fn replay(&mut self, interp: &mut ComponentInterpreter) {
+        let idx = self.deltas.len() - self.behind;
+        self.deltas[idx].apply(interp, false);
+        self.behind -= 1;
+    }
+}
@@ -1,6 +1,7 @@
 mod cidr;
 mod commands;
 mod context;
+mod history;
 mod io_utils;
 pub(crate) mod parser;
 
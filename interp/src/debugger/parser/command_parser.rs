@@ -34,6 +34,13 @@ impl CommandParser {
         Ok(Command::Step)
     }
 
+    fn reverse_step(input: Node) -> ParseResult<Command> {
+        Ok(match_nodes!(input.into_children();
+            [num(n)] => Command::ReverseStep(n),
+            [] => Command::ReverseStep(1),
+        ))
+    }
+
     fn display(_input: Node) -> ParseResult<Command> {
         Ok(Command::Display)
     }
@@ -153,6 +160,7 @@ impl CommandParser {
             [print(p), EOI(_)] => p,
             [print_fail(_), EOI(_)] => Command::Print(None, None),
             [step(s), EOI(_)] => s,
+            [reverse_step(rs), EOI(_)] => rs,
             [cont(c), EOI(_)] => c,
             [help(h), EOI(_)] => h,
             [display(disp), EOI(_)] => disp,
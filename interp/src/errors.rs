@@ -1,3 +1,4 @@
+use crate::structures::names::ComponentQIN;
 use crate::utils::assignment_to_string;
 use crate::values::Value;
 use calyx::errors::Error;
@@ -70,6 +71,23 @@ pub enum InterpreterError {
         v1: Value,
         v2: Value,
     },
+    /// Two branches of a par drive the same port in the same cycle
+    #[error(
+        "par branches write to {parent_id}.{port_id} in the same cycle
+{}
+{}",
+        group1.fmt_err("first write in this group"),
+        group2.fmt_err("conflicting write in this group")
+    )]
+    ParRace {
+        port_id: Id,
+        parent_id: Id,
+        group1: Id,
+        group2: Id,
+    },
+    /// The values on a combinational loop never settle
+    #[error("combinational loop does not converge: {}", .0.join(", "))]
+    CombinationalLoop(Vec<String>),
     #[error("invalid internal seq state. This should never happen, please report it")]
     InvalidSeqState,
     #[error(
@@ -90,6 +108,13 @@ pub enum InterpreterError {
 
     #[error("unknown primitive - \"{0}\"")]
     UnknownPrimitive(String),
+    /// A primitive defined by an extern file has no model in the interpreter
+    #[error("cannot simulate \"{cell}\" because primitive \"{primitive}\" from \"{file}\" has no model in the interpreter. Register a handler for it using `interp::ffi::register`")]
+    MissingModel {
+        primitive: String,
+        cell: Id,
+        file: String,
+    },
     #[error("program evaluated the truth value of a wire \"{}.{}\" which is not one bit. Wire is {} bits wide.", 0.0, 0.1, 1)]
     InvalidBoolCast((Id, Id), u64),
     #[error("the interpreter attempted to exit the group \"{0}\" before it finished. This should never happen, please report it.")]
@@ -100,9 +125,60 @@ pub enum InterpreterError {
     #[error("invalid memory access. Given index ({}) but memory has dimension ({})", access.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", "), dims.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", "))]
     InvalidMemoryAccess { access: Vec<u64>, dims: Vec<u64> },
 
+    #[error("invalid access to memory \"{}\" in cycle {} by {}. Given index ({}) but memory has dimension ({})", cell, cycle, driver, access.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", "), dims.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", "))]
+    MemoryAccessOutOfBounds {
+        cell: Id,
+        access: Vec<u64>,
+        dims: Vec<u64>,
+        cycle: u64,
+        driver: String,
+    },
+
     // TODO (Griffin): Make this error message better please
     #[error("Computation has under/overflowed its bounds")]
     OverflowError(),
+
+    #[error(
+        "computation in \"{}\" has under/overflowed its bounds in cycle {}",
+        cell,
+        cycle
+    )]
+    CellOverflow { cell: Id, cycle: u64 },
+
+    /// A checkpoint could not be saved or does not match the program
+    #[error("checkpoint error - {0}")]
+    CheckpointError(String),
+
+    /// The stall configuration could not be read
+    #[error("stall configuration error - {0}")]
+    StallConfigError(String),
+
+    /// The stimulus file could not be read or does not match the program
+    #[error("stimulus error - {0}")]
+    StimulusError(String),
+
+    /// A handler of a foreign primitive failed or the recording of its
+    /// values could not be read or replayed
+    #[error("foreign function error - {0}")]
+    FfiError(String),
+
+    /// The profiling options are invalid
+    #[error("profile error - {0}")]
+    ProfileError(String),
+
+    /// The taint configuration could not be read or does not match the
+    /// program
+    #[error("taint configuration error - {0}")]
+    TaintConfigError(String),
+
+    /// The trace filter could not be parsed
+    #[error("trace filter error - {0}")]
+    TraceError(String),
+
+    /// An error about a definition generated from the given position in a
+    /// frontend program
+    #[error("{0}\n{1}")]
+    FromFrontend(Box<InterpreterError>, ir::SourcePos),
 }
 
 impl InterpreterError {
@@ -119,6 +195,53 @@ impl InterpreterError {
             a2: assignment_to_string(a2),
         }
     }
+
+    /// Qualify the names of the cells and groups in the error with the
+    /// instance `qin` that raised it, e.g., `add` becomes `main.dot0.add`.
+    /// Names that were already qualified by a nested instance are kept.
+    pub fn in_instance(mut self, qin: &ComponentQIN) -> Self {
+        let qualify = |id: &mut Id| {
+            if !id.id.contains('.') {
+                *id = qin.qualify(id);
+            }
+        };
+        match &mut self {
+            Self::ConflictingAssignments { parent_id: id, .. }
+            | Self::ParOverlap { parent_id: id, .. }
+            | Self::MemoryAccessOutOfBounds { cell: id, .. }
+            | Self::CellOverflow { cell: id, .. }
+            | Self::InvalidGroupExitNamed(id)
+            | Self::InvalidBoolCast((id, _), _) => qualify(id),
+            Self::ParRace { group1, group2, .. } => {
+                qualify(group1);
+                qualify(group2);
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// Attach the position in the frontend program of the cell or group
+    /// that the error is about, if it is known.
+    pub fn annotate(self, source_map: &ir::SourceMap) -> Self {
+        if let Self::CompilerError(err) = self {
+            return Self::CompilerError(Box::new(source_map.annotate(*err)));
+        }
+        let id = match &self {
+            Self::ConflictingAssignments { parent_id: id, .. }
+            | Self::ParOverlap { parent_id: id, .. }
+            | Self::ParRace { group1: id, .. }
+            | Self::MemoryAccessOutOfBounds { cell: id, .. }
+            | Self::CellOverflow { cell: id, .. }
+            | Self::InvalidGroupExitNamed(id)
+            | Self::InvalidBoolCast((id, _), _) => id,
+            _ => return self,
+        };
+        match source_map.lookup(id).cloned() {
+            Some(pos) => Self::FromFrontend(Box::new(self), pos),
+            None => self,
+        }
+    }
 }
 
 // this is silly but needed to make the program print something sensible when returning
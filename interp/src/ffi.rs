@@ -0,0 +1,353 @@
+//! Handlers that model the interaction of a design with its host.
+//!
+//! Primitives like `std_syscall` have no behavior of their own. Instead, the
+//! program running the interpreter registers a Rust closure for the
+//! primitive using [register], which is called whenever a cell of the
+//! primitive is enabled. The closure receives the name of the cell, its
+//! string parameters, and the values of its inputs, and returns the value of
+//! the `out` port of the cell, which can model reading a sensor or writing to
+//! a log.
+//!
+//! The values returned by the handlers can be recorded and replayed in a
+//! later run that does not call the handlers, which makes simulations that
+//! depend on the host deterministic. Calls are recorded in the order in which
+//! they happen and replayed to each cell, identified by its hierarchical name,
+//! in the order in which they were recorded for it.
+//!
+//! Primitives that a program declares in its own `extern` blocks usually have
+//! no model in the interpreter. Simulating one without a registered handler
+//! is an error that names the file defining the primitive.
+use crate::errors::{InterpreterError, InterpreterResult};
+use crate::primitives::Primitive;
+use crate::values::Value;
+use calyx::ir;
+use lazy_static::*;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Mutex;
+
+/// Name of the primitive that models a call to the host. Its cells are
+/// always handled by the registered handler or the replayed values.
+pub const SYSCALL: &str = "std_syscall";
+
+/// A call of a handler by a cell.
+pub struct HostCall<'a> {
+    /// Hierarchical name of the cell, e.g., `main.sensor`.
+    pub cell: &'a ir::Id,
+    /// String parameters of the cell, like the `CALL` of `std_syscall`.
+    pub strings: &'a ir::StringBinding,
+    /// Values of the inputs of the cell other than `go`, `clk`, and `reset`.
+    pub args: &'a [(ir::Id, Value)],
+}
+
+impl HostCall<'_> {
+    /// Value of the string parameter `name` of the cell.
+    pub fn string(&self, name: &str) -> Option<&str> {
+        self.strings
+            .iter()
+            .find(|(id, _)| id == name)
+            .map(|(_, val)| val.as_str())
+    }
+
+    /// Value of the input `name` of the cell.
+    pub fn arg(&self, name: &str) -> Option<&Value> {
+        self.args
+            .iter()
+            .find(|(id, _)| id == name)
+            .map(|(_, val)| val)
+    }
+}
+
+/// Handler for the cells of a primitive. Returns the value of the `out` port
+/// of the cell, which is truncated to the width of the port.
+pub type Handler = Box<dyn FnMut(&HostCall) -> InterpreterResult<u64> + Send>;
+
+/// Value returned to a cell.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    cell: String,
+    value: u64,
+}
+
+lazy_static! {
+    /// Handlers keyed by the name of their primitive.
+    static ref HANDLERS: Mutex<HashMap<String, Handler>> =
+        Mutex::new(HashMap::new());
+    /// Files that define the primitives of the program, keyed by the name of
+    /// the primitive.
+    static ref EXTERNS: Mutex<HashMap<String, PathBuf>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Call `handler` whenever a cell of `primitive` is enabled. Replaces the
+/// previous handler of the primitive.
+pub fn register<F>(primitive: &str, handler: F)
+where
+    F: FnMut(&HostCall) -> InterpreterResult<u64> + Send + 'static,
+{
+    HANDLERS
+        .lock()
+        .unwrap()
+        .insert(primitive.to_string(), Box::new(handler));
+}
+
+/// Returns true if the cells of `primitive` are handled by a handler.
+pub fn is_foreign(primitive: &str) -> bool {
+    primitive == SYSCALL || HANDLERS.lock().unwrap().contains_key(primitive)
+}
+
+/// Remember the files that define the primitives of `lib`, which are named
+/// by the errors about primitives that cannot be simulated.
+pub fn declare_externs(lib: &ir::LibrarySignatures) {
+    let mut externs = EXTERNS.lock().unwrap();
+    for (path, prims) in lib.extern_defs() {
+        for prim in prims {
+            externs.insert(prim.name.to_string(), path.clone());
+        }
+    }
+}
+
+/// The error for the cell `cell` of `primitive`, which has neither a model
+/// in the interpreter nor a handler.
+pub(crate) fn missing_model(
+    primitive: String,
+    cell: ir::Id,
+) -> InterpreterError {
+    match EXTERNS.lock().unwrap().get(&primitive) {
+        Some(path) => InterpreterError::MissingModel {
+            primitive,
+            cell,
+            file: path.file_name().map_or_else(
+                || path.to_string_lossy().to_string(),
+                |name| name.to_string_lossy().to_string(),
+            ),
+        },
+        None => InterpreterError::UnknownPrimitive(primitive),
+    }
+}
+
+/// Calls recorded or replayed by a simulation. It is shared by the
+/// environments of the simulation.
+pub type SharedCalls = Rc<RefCell<Calls>>;
+
+/// Values returned to the cells of foreign primitives in a simulation.
+#[derive(Default)]
+pub struct Calls {
+    /// Values returned so far, if they are being recorded.
+    recording: Option<Vec<Record>>,
+    /// Values that remain to be replayed to each cell keyed by its
+    /// hierarchical name, if a recording is being replayed.
+    replay: Option<HashMap<String, VecDeque<u64>>>,
+}
+
+impl Calls {
+    /// Record the values returned to the cells if `record` is true, and
+    /// return the values recorded in `replay` instead of calling the
+    /// handlers if it is given.
+    pub fn new(
+        record: bool,
+        replay: Option<&Path>,
+    ) -> InterpreterResult<SharedCalls> {
+        let mut calls = Self::default();
+        if record {
+            calls.recording = Some(vec![]);
+        }
+        if let Some(path) = replay {
+            let to_err = |err: &dyn std::fmt::Display| {
+                InterpreterError::FfiError(format!(
+                    "failed to read {}: {}",
+                    path.to_string_lossy(),
+                    err
+                ))
+            };
+            let buf = fs::read(path).map_err(|err| to_err(&err))?;
+            let records: Vec<Record> =
+                serde_json::from_slice(&buf).map_err(|err| to_err(&err))?;
+            let mut replay: HashMap<String, VecDeque<u64>> = HashMap::new();
+            for rec in records {
+                replay.entry(rec.cell).or_default().push_back(rec.value);
+            }
+            calls.replay = Some(replay);
+        }
+        Ok(Rc::new(RefCell::new(calls)))
+    }
+
+    /// Write the values recorded so far to `path` as JSON.
+    pub fn save_recording(&self, path: &Path) -> InterpreterResult<()> {
+        let json = serde_json::to_string_pretty(
+            self.recording.as_deref().unwrap_or_default(),
+        )
+        .unwrap();
+        fs::write(path, json).map_err(|err| {
+            InterpreterError::FfiError(format!(
+                "failed to write {}: {}",
+                path.to_string_lossy(),
+                err
+            ))
+        })
+    }
+}
+
+/// The value returned to `call` by a cell of `primitive`.
+fn call(
+    primitive: &str,
+    call: &HostCall,
+    calls: &Option<SharedCalls>,
+) -> InterpreterResult<u64> {
+    let mut calls = calls.as_ref().map(|calls| calls.borrow_mut());
+    let replay = calls.as_mut().and_then(|calls| calls.replay.as_mut());
+    let value = match replay {
+        Some(replay) => replay
+            .get_mut(call.cell.as_ref())
+            .and_then(VecDeque::pop_front)
+            .ok_or_else(|| {
+                InterpreterError::FfiError(format!(
+                    "the recording has no value for the call from `{}`",
+                    call.cell
+                ))
+            })?,
+        None => {
+            let mut handlers = HANDLERS.lock().unwrap();
+            let handler = handlers.get_mut(primitive).ok_or_else(|| {
+                InterpreterError::FfiError(format!(
+                    "no handler is registered for `{}`, which is used by `{}`",
+                    primitive, call.cell
+                ))
+            })?;
+            handler(call)?
+        }
+    };
+    if let Some(records) =
+        calls.as_mut().and_then(|calls| calls.recording.as_mut())
+    {
+        records.push(Record {
+            cell: call.cell.to_string(),
+            value,
+        });
+    }
+    Ok(value)
+}
+
+/// A cell whose behavior is defined by the handler of its primitive. The
+/// handler is called at the end of every cycle in which `go` is high and its
+/// result is available on `out` in the next cycle, along with a high `done`.
+pub(crate) struct ForeignPrimitive {
+    primitive: String,
+    /// Hierarchical name of the cell.
+    cell: ir::Id,
+    strings: ir::StringBinding,
+    width: u64,
+    /// Inputs of the call made in the current cycle, if `go` is high.
+    pending: Option<Vec<(ir::Id, Value)>>,
+    out: Value,
+    /// Calls recorded or replayed by the simulation.
+    calls: Option<SharedCalls>,
+}
+
+impl ForeignPrimitive {
+    /// The primitive for `cell`, whose hierarchical name is `name`, in a
+    /// simulation that records or replays `calls`.
+    pub(crate) fn new(
+        cell: &ir::Cell,
+        name: ir::Id,
+        calls: &Option<SharedCalls>,
+    ) -> InterpreterResult<Self> {
+        let (primitive, strings) = match &cell.prototype {
+            ir::CellType::Primitive {
+                name,
+                string_binding,
+                ..
+            } => (name.to_string(), string_binding.clone()),
+            _ => unreachable!("`{}` is not a primitive", name),
+        };
+        let width = match (cell.find("go"), cell.find("done"), cell.find("out"))
+        {
+            (Some(_), Some(_), Some(out)) => out.borrow().width,
+            _ => {
+                return Err(InterpreterError::FfiError(format!(
+                    "`{}` must have `go`, `done`, and `out` ports to be handled by a handler",
+                    primitive
+                )))
+            }
+        };
+        Ok(Self {
+            primitive,
+            cell: name,
+            strings,
+            width,
+            pending: None,
+            out: Value::zeroes(width),
+            calls: calls.clone(),
+        })
+    }
+}
+
+impl Primitive for ForeignPrimitive {
+    fn do_tick(&mut self) -> InterpreterResult<Vec<(ir::Id, Value)>> {
+        let done = match self.pending.take() {
+            Some(args) => {
+                let value = call(
+                    &self.primitive,
+                    &HostCall {
+                        cell: &self.cell,
+                        strings: &self.strings,
+                        args: &args,
+                    },
+                    &self.calls,
+                )?;
+                self.out = Value::from(value, self.width);
+                Value::bit_high()
+            }
+            None => Value::bit_low(),
+        };
+        Ok(vec![
+            (ir::Id::from("out"), self.out.clone()),
+            (ir::Id::from("done"), done),
+        ])
+    }
+
+    fn is_comb(&self) -> bool {
+        false
+    }
+
+    fn validate(&self, _inputs: &[(ir::Id, &Value)]) {}
+
+    fn execute(
+        &mut self,
+        inputs: &[(ir::Id, &Value)],
+    ) -> InterpreterResult<Vec<(ir::Id, Value)>> {
+        let go = inputs
+            .iter()
+            .find(|(id, _)| id == "go")
+            .is_some_and(|(_, go)| go.as_bool());
+        self.pending = if go {
+            Some(
+                inputs
+                    .iter()
+                    .filter(|(id, _)| {
+                        !matches!(id.as_ref(), "go" | "clk" | "reset")
+                    })
+                    .map(|(id, val)| (id.clone(), (*val).clone()))
+                    .collect(),
+            )
+        } else {
+            None
+        };
+        Ok(vec![])
+    }
+
+    fn reset(
+        &mut self,
+        _inputs: &[(ir::Id, &Value)],
+    ) -> InterpreterResult<Vec<(ir::Id, Value)>> {
+        self.pending = None;
+        Ok(vec![
+            (ir::Id::from("out"), self.out.clone()),
+            (ir::Id::from("done"), Value::bit_low()),
+        ])
+    }
+}
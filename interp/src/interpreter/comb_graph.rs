@@ -0,0 +1,309 @@
+//! Evaluates the combinational logic of a set of assignments in dependency
+//! order instead of repeatedly evaluating every assignment until the values
+//! stop changing.
+use super::interpret_group::eval_prims;
+use super::utils::{ConstCell, ConstPort};
+use crate::environment::InterpreterState;
+use crate::errors::{InterpreterError, InterpreterResult};
+use crate::taint;
+use crate::utils::AsRaw;
+use crate::values::Value;
+use calyx::ir::{self, RRC};
+use petgraph::{
+    algo,
+    graph::{DiGraph, NodeIndex},
+};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A node in a [CombGraph].
+enum CombNode {
+    /// A port driven by the assignments at the given indices.
+    Port(RRC<ir::Port>, Vec<usize>),
+    /// A cell driven by the assignments.
+    Cell { cell: RRC<ir::Cell>, is_comb: bool },
+}
+
+/// A strongly connected component of the graph.
+struct Scc {
+    nodes: Vec<CombNode>,
+    /// The nodes form a combinational loop and are evaluated until they
+    /// converge.
+    cyclic: bool,
+}
+
+/// Dependency graph between the ports driven by a set of assignments and the
+/// cells that the assignments drive.
+///
+/// A driven port depends on the ports read by its assignments and a cell
+/// depends on its driven input ports. The outputs of combinational cells
+/// depend on their inputs while the outputs of stateful cells are treated as
+/// inputs to the graph. The strongly connected components of the graph are
+/// evaluated in topological order so that each port is computed once per
+/// evaluation unless it is part of a combinational loop.
+pub(super) struct CombGraph {
+    sccs: Vec<Scc>,
+}
+
+impl CombGraph {
+    /// Construct the graph for `assigns` which drive the given `cells`.
+    pub fn new<'a, I>(
+        assigns: I,
+        cells: &[RRC<ir::Cell>],
+        state: &InterpreterState,
+    ) -> Self
+    where
+        I: Iterator<Item = &'a ir::Assignment>,
+    {
+        let assigns = assigns.collect::<Vec<_>>();
+        let mut graph: DiGraph<CombNode, ()> = DiGraph::new();
+
+        // Add a node for every driven port.
+        let mut port_nodes: HashMap<ConstPort, NodeIndex> = HashMap::new();
+        let mut ports = vec![];
+        for (idx, assign) in assigns.iter().enumerate() {
+            let dst = assign.dst.as_raw();
+            if let Some(node) = port_nodes.get(&dst) {
+                if let CombNode::Port(_, writers) = &mut graph[*node] {
+                    writers.push(idx);
+                }
+            } else {
+                let node = graph.add_node(CombNode::Port(
+                    Rc::clone(&assign.dst),
+                    vec![idx],
+                ));
+                port_nodes.insert(dst, node);
+                ports.push(node);
+            }
+        }
+
+        // Add a node for every cell which depends on its driven inputs.
+        let prim_map = state.cell_map.borrow();
+        let mut comb_cells: HashMap<ConstCell, NodeIndex> = HashMap::new();
+        for cell in cells {
+            let is_comb = matches!(
+                prim_map.get(&cell.as_raw()),
+                Some(prim) if prim.is_comb()
+            );
+            let node = graph.add_node(CombNode::Cell {
+                cell: Rc::clone(cell),
+                is_comb,
+            });
+            if is_comb {
+                comb_cells.insert(cell.as_raw(), node);
+            }
+            for port in &cell.borrow().ports {
+                if let Some(src) = port_nodes.get(&port.as_raw()) {
+                    graph.add_edge(*src, node, ());
+                }
+            }
+        }
+
+        // Driven ports depend on the ports read by their assignments.
+        for node in ports {
+            let writers = match &graph[node] {
+                CombNode::Port(_, writers) => writers.clone(),
+                CombNode::Cell { .. } => unreachable!(),
+            };
+            for idx in writers {
+                let assign = assigns[idx];
+                let mut reads = assign.guard.all_ports();
+                reads.push(Rc::clone(&assign.src));
+                for port in reads {
+                    if let Some(src) = port_nodes.get(&port.as_raw()) {
+                        graph.update_edge(*src, node, ());
+                    } else if let ir::PortParent::Cell(cell) =
+                        &port.borrow().parent
+                    {
+                        let cell = cell.upgrade();
+                        if let Some(src) = comb_cells.get(&cell.as_raw()) {
+                            graph.update_edge(*src, node, ());
+                        }
+                    }
+                }
+            }
+        }
+
+        // Tarjan's algorithm returns the components in reverse topological
+        // order.
+        let mut sccs = algo::tarjan_scc(&graph);
+        sccs.reverse();
+        let cyclic = sccs
+            .iter()
+            .map(|scc| scc.len() > 1 || graph.contains_edge(scc[0], scc[0]))
+            .collect::<Vec<_>>();
+        let mut nodes = graph
+            .into_nodes_edges()
+            .0
+            .into_iter()
+            .map(|node| Some(node.weight))
+            .collect::<Vec<_>>();
+        let sccs = sccs
+            .into_iter()
+            .zip(cyclic)
+            .map(|(scc, cyclic)| Scc {
+                nodes: scc
+                    .into_iter()
+                    .map(|idx| nodes[idx.index()].take().unwrap())
+                    .collect(),
+                cyclic,
+            })
+            .collect();
+        CombGraph { sccs }
+    }
+
+    /// Evaluate the graph until all the values converge. `assigns` must be
+    /// the assignments used to construct the graph. Returns an error if the
+    /// outputs of the stateful cells oscillate.
+    pub fn eval(
+        &self,
+        state: &mut InterpreterState,
+        assigns: &[&ir::Assignment],
+    ) -> InterpreterResult<()> {
+        // Each pass propagates the changes through at least one more
+        // stateful cell, so the values converge after a pass per stateful
+        // cell unless the stateful cells form a loop.
+        let stateful = self
+            .sccs
+            .iter()
+            .flat_map(|scc| &scc.nodes)
+            .filter(|node| {
+                matches!(node, CombNode::Cell { is_comb: false, .. })
+            })
+            .count();
+        let mut changed_cells = vec![];
+        for _ in 0..=stateful {
+            // Stateful cells, such as memories, may change their outputs
+            // when their inputs change. Their readers are evaluated again.
+            changed_cells.clear();
+            for scc in &self.sccs {
+                if scc.cyclic {
+                    Self::eval_loop(scc, state, assigns)?;
+                    continue;
+                }
+                for node in &scc.nodes {
+                    let changed = Self::eval_node(node, state, assigns)?;
+                    if !changed {
+                        continue;
+                    }
+                    if let CombNode::Cell {
+                        cell,
+                        is_comb: false,
+                    } = node
+                    {
+                        changed_cells.push(cell);
+                    }
+                }
+            }
+            if changed_cells.is_empty() {
+                return Ok(());
+            }
+        }
+        Err(InterpreterError::CombinationalLoop(
+            changed_cells
+                .iter()
+                .flat_map(|cell| {
+                    let cell = cell.borrow();
+                    cell.ports
+                        .iter()
+                        .filter(|port| {
+                            port.borrow().direction == ir::Direction::Output
+                        })
+                        .map(|port| {
+                            format!("{}.{}", cell.name(), port.borrow().name)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+        ))
+    }
+
+    /// Evaluate a combinational loop until it converges. Returns an error if
+    /// the values on the loop oscillate.
+    fn eval_loop(
+        scc: &Scc,
+        state: &mut InterpreterState,
+        assigns: &[&ir::Assignment],
+    ) -> InterpreterResult<()> {
+        let ports = scc
+            .nodes
+            .iter()
+            .filter_map(|node| match node {
+                CombNode::Port(port, _) => Some(port),
+                CombNode::Cell { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        let mut seen: Vec<Vec<Value>> = vec![];
+        loop {
+            let mut changed = false;
+            for node in &scc.nodes {
+                changed |= Self::eval_node(node, state, assigns)?;
+            }
+            if !changed {
+                return Ok(());
+            }
+            let values = ports
+                .iter()
+                .map(|port| state.get_from_port(*port).clone())
+                .collect::<Vec<_>>();
+            if seen.contains(&values) {
+                return Err(InterpreterError::CombinationalLoop(
+                    ports
+                        .iter()
+                        .map(|port| {
+                            let port = port.borrow();
+                            format!("{}.{}", port.get_parent_name(), port.name)
+                        })
+                        .collect(),
+                ));
+            }
+            seen.push(values);
+        }
+    }
+
+    /// Evaluate a single node. Returns true if any value changed.
+    fn eval_node(
+        node: &CombNode,
+        state: &mut InterpreterState,
+        assigns: &[&ir::Assignment],
+    ) -> InterpreterResult<bool> {
+        match node {
+            CombNode::Port(port, writers) => {
+                let mut active: Option<&ir::Assignment> = None;
+                for idx in writers {
+                    let assign = assigns[*idx];
+                    if !state.eval_guard(&assign.guard)? {
+                        continue;
+                    }
+                    if let Some(prior) = active {
+                        let dst = port.borrow();
+                        return Err(InterpreterError::conflicting_assignments(
+                            dst.name.clone(),
+                            dst.get_parent_name(),
+                            prior,
+                            assign,
+                        ));
+                    }
+                    active = Some(assign);
+                }
+                taint::assign(state, port, active);
+                // Ports without an active assignment are set to zero.
+                let new_val = match active {
+                    Some(assign) => {
+                        state.get_from_port(assign.src.borrow()).clone()
+                    }
+                    None => Value::zeroes(port.borrow().width as usize),
+                };
+                if *state.get_from_port(port) == new_val {
+                    Ok(false)
+                } else {
+                    state.insert(port, new_val);
+                    Ok(true)
+                }
+            }
+            CombNode::Cell { cell, .. } => {
+                eval_prims(state, std::iter::once(cell), false)
+            }
+        }
+    }
+}
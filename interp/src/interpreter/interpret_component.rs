@@ -25,3 +25,13 @@ pub fn interpret_component(
         )
     }
 }
+
+/// Interpret `control` instead of the control program of the component, e.g.,
+/// the part of it that remains after resuming from a checkpoint.
+pub fn interpret_component_control(
+    comp: &Rc<iir::Component>,
+    control: &iir::Control,
+    env: InterpreterState,
+) -> InterpreterResult<InterpreterState> {
+    interpret_control(control, &comp.continuous_assignments, env, comp)
+}
@@ -7,8 +7,15 @@ use super::interpret_group::{
     finish_comb_group_interpretation, finish_group_interpretation,
     interpret_comb_group, interpret_group, interpret_invoke,
 };
+use super::steppers::{Interpreter, ParInterpreter};
+use super::utils::format_debug;
+use crate::checkpoint::{self, Step};
+use crate::coverage;
 use crate::environment::InterpreterState;
 use crate::errors::InterpreterResult;
+use crate::structures::names::ComponentQIN;
+use crate::utils::AsRaw;
+use calyx::ir;
 
 use crate::interpreter_ir as iir;
 
@@ -17,8 +24,9 @@ pub fn interpret_control(
     ctrl: &iir::Control,
     continuous_assignments: &iir::ContinuousAssignments,
     env: InterpreterState,
-    comp: &iir::Component,
+    comp: &Rc<iir::Component>,
 ) -> InterpreterResult<InterpreterState> {
+    coverage::record(&env, ctrl);
     match ctrl {
         iir::Control::Seq(s) => eval_seq(s, continuous_assignments, env, comp),
         iir::Control::Par(p) => eval_par(p, continuous_assignments, env, comp),
@@ -31,6 +39,7 @@ pub fn interpret_control(
         iir::Control::Empty(e) => {
             eval_empty(e, continuous_assignments, env, comp)
         }
+        iir::Control::Debug(d) => eval_debug(d, env),
     }
 }
 
@@ -39,58 +48,48 @@ fn eval_seq(
     s: &iir::Seq,
     continuous_assignments: &iir::ContinuousAssignments,
     mut env: InterpreterState,
-    comp: &iir::Component,
+    comp: &Rc<iir::Component>,
 ) -> InterpreterResult<InterpreterState> {
-    for stmt in &s.stmts {
+    for (idx, stmt) in s.stmts.iter().enumerate() {
+        let _step = checkpoint::enter(&env, Step::Seq(idx));
         env = interpret_control(stmt, continuous_assignments, env, comp)?;
     }
     Ok(env)
 }
 
 /// Interpret Par
-
+/// The branches are stepped in lockstep so that branches which drive the same
+/// port in the same cycle are reported.
 fn eval_par(
-    p: &iir::Par,
+    p: &Rc<iir::Par>,
     continuous_assignments: &iir::ContinuousAssignments,
-    mut env: InterpreterState,
-    comp: &iir::Component,
+    env: InterpreterState,
+    comp: &Rc<iir::Component>,
 ) -> InterpreterResult<InterpreterState> {
-    //vector to keep track of all updated states
-    let mut states = Vec::new();
-
-    // evaluate each expression within the starter environment by forking from it
-    for st in &p.stmts {
-        states.push(interpret_control(
-            st,
-            continuous_assignments,
-            env.fork(),
-            comp,
-        )?);
-    }
-
-    // states = &p.stmts.into_iter().map(|ctr| {
-    //     interpret_control(ctr, continuous_assignments, env.fork(), comp)?
-    // });
-
-    //clock updates
-    let mut tl = 0;
-
-    //vector of smooshers from the states
-    let mut smooshers = Vec::new();
-
-    //i do this using loops for clock updates
-    for is in states {
-        if is.clk > tl {
-            tl = is.clk;
-        }
-
-        smooshers.push(is.port_map);
-    }
-
-    env.port_map = env.port_map.merge_many(smooshers, &HashSet::new())?;
-    env.clk = tl;
-
-    Ok(env)
+    let qin = ComponentQIN::new_single(comp, &comp.name);
+    let sig = comp.signature.borrow();
+    // The inputs of the component, which are outputs of its signature, and
+    // its `done` port are not checked for races.
+    let interface_ports = sig
+        .ports
+        .iter()
+        .filter(|port| {
+            let port = port.borrow();
+            port.direction == ir::Direction::Output
+                || port.attributes.has("done")
+        })
+        .map(|port| port.as_raw())
+        .collect::<HashSet<_>>();
+
+    let mut interp = ParInterpreter::new(
+        p,
+        env,
+        continuous_assignments,
+        Rc::new(interface_ports),
+        &qin,
+    );
+    interp.run()?;
+    interp.deconstruct()
 }
 
 /// Interpret If
@@ -98,7 +97,7 @@ fn eval_if(
     i: &iir::If,
     continuous_assignments: &iir::ContinuousAssignments,
     mut env: InterpreterState,
-    comp: &iir::Component,
+    comp: &Rc<iir::Component>,
 ) -> InterpreterResult<InterpreterState> {
     if let Some(comb) = &i.cond {
         env =
@@ -106,6 +105,7 @@ fn eval_if(
     }
 
     let cond_flag = env.get_from_port(&i.port.borrow()).as_bool();
+    coverage::record_branch(&env, i, cond_flag);
     if let Some(comb) = &i.cond {
         env = finish_comb_group_interpretation(
             &comb.borrow(),
@@ -116,6 +116,7 @@ fn eval_if(
 
     let target = if !cond_flag { &i.fbranch } else { &i.tbranch };
 
+    let _step = checkpoint::enter(&env, Step::Branch(cond_flag));
     interpret_control(target, continuous_assignments, env, comp)
 }
 
@@ -128,7 +129,7 @@ fn eval_while(
     w: &iir::While,
     continuous_assignments: &iir::ContinuousAssignments,
     mut env: InterpreterState,
-    comp: &iir::Component,
+    comp: &Rc<iir::Component>,
 ) -> InterpreterResult<InterpreterState> {
     loop {
         if let Some(comb) = &w.cond {
@@ -140,6 +141,7 @@ fn eval_while(
         }
 
         let cond_val = env.get_from_port(&w.port.borrow()).as_bool();
+        coverage::record_branch(&env, w, cond_val);
 
         if let Some(comb) = &w.cond {
             env = finish_comb_group_interpretation(
@@ -153,6 +155,7 @@ fn eval_while(
             break;
         }
 
+        let _step = checkpoint::enter(&env, Step::Body);
         env = interpret_control(&w.body, continuous_assignments, env, comp)?;
     }
 
@@ -167,6 +170,7 @@ fn eval_invoke(
     continuous_assignments: &iir::ContinuousAssignments,
     env: InterpreterState,
 ) -> InterpreterResult<InterpreterState> {
+    checkpoint::save_if_due(&env)?;
     interpret_invoke(inv, continuous_assignments, env)
 }
 
@@ -176,6 +180,7 @@ fn eval_enable(
     continuous_assignments: &iir::ContinuousAssignments,
     mut env: InterpreterState,
 ) -> InterpreterResult<InterpreterState> {
+    checkpoint::save_if_due(&env)?;
     env = interpret_group(Rc::clone(&e.group), continuous_assignments, env)?;
     finish_group_interpretation(&e.group.borrow(), continuous_assignments, env)
 }
@@ -186,7 +191,17 @@ fn eval_empty(
     _e: &iir::Empty,
     _continuous_assignments: &iir::ContinuousAssignments,
     env: InterpreterState,
-    _comp: &iir::Component,
+    _comp: &Rc<iir::Component>,
+) -> InterpreterResult<InterpreterState> {
+    Ok(env)
+}
+
+/// Interpret Debug
+/// Prints the message to stderr without taking any cycles.
+fn eval_debug(
+    d: &iir::Debug,
+    env: InterpreterState,
 ) -> InterpreterResult<InterpreterState> {
+    eprintln!("{}", format_debug(d, &env));
     Ok(env)
 }
@@ -14,7 +14,7 @@ use crate::values::Value;
 use calyx::ir::{self, RRC};
 
 use super::steppers::{AssignmentInterpreter, InvokeInterpreter};
-use crate::errors::InterpreterResult;
+use crate::errors::{InterpreterError, InterpreterResult};
 
 use crate::interpreter_ir as iir;
 
@@ -191,7 +191,16 @@ pub(crate) fn eval_prims<'a, 'b, I: Iterator<Item = &'b RRC<ir::Cell>>>(
                 prim.reset(&inputs)
             } else {
                 prim.execute(&inputs)
-            };
+            }
+            .map_err(|err| match err {
+                InterpreterError::OverflowError() => {
+                    InterpreterError::CellOverflow {
+                        cell: cell.borrow().name().clone(),
+                        cycle: env.clk,
+                    }
+                }
+                err => err,
+            });
 
             for (port, val) in new_vals? {
                 let port_ref = cell.borrow().find(port).unwrap();
@@ -1,9 +1,12 @@
+mod comb_graph;
 mod interpret_component;
 mod interpret_control;
 mod interpret_group;
 mod steppers;
 mod utils;
 
-pub use interpret_component::interpret_component;
+pub use interpret_component::{
+    interpret_component, interpret_component_control,
+};
 pub use steppers::{ComponentInterpreter, Interpreter};
 pub use utils::{ConstCell, ConstPort};
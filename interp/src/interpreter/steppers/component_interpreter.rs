@@ -5,7 +5,7 @@ use super::control_interpreter::{
     ControlInterpreter, Interpreter, StructuralInterpreter,
 };
 use crate::environment::{InterpreterState, MutStateView, StateView};
-use crate::errors::InterpreterResult;
+use crate::errors::{InterpreterError, InterpreterResult};
 use crate::interpreter_ir as iir;
 use crate::primitives::Primitive;
 use crate::structures::names::{ComponentQIN, GroupQIN};
@@ -83,13 +83,13 @@ impl ComponentInterpreter {
 
         let go_port = inputs
             .iter()
-            .find(|x| x.borrow().attributes.has("go"))
+            .find(|x| x.borrow().attributes.get("go") == Some(&1))
             .unwrap()
             .clone();
 
         let done_port = outputs
             .iter()
-            .find(|x| x.borrow().attributes.has("done"))
+            .find(|x| x.borrow().attributes.get("done") == Some(&1))
             .unwrap()
             .clone();
 
@@ -119,6 +119,24 @@ impl ComponentInterpreter {
         }
     }
 
+    /// Run `control` instead of the control program of the component, e.g.,
+    /// the part of it that remains after resuming from a checkpoint.
+    pub fn with_control(mut self, control: iir::Control) -> Self {
+        self.control_ref = control;
+        self
+    }
+
+    /// Qualify the names in an error raised by a nested instance with the
+    /// hierarchical name of the instance. Errors of the main component keep
+    /// the names of its cells and groups.
+    fn qualify_err(&self, err: InterpreterError) -> InterpreterError {
+        if self.qual_name.len() > 1 {
+            err.in_instance(&self.qual_name)
+        } else {
+            err
+        }
+    }
+
     fn look_up_outputs(&self) -> Vec<(ir::Id, crate::values::Value)> {
         let env = self.get_env();
         self.output_ports
@@ -278,7 +296,7 @@ impl Primitive for ComponentInterpreter {
         if currently_done {
             self.reset(&[])?;
         } else {
-            self.step()?;
+            self.step().map_err(|err| self.qualify_err(err))?;
         }
 
         // just became done for an imperative component
@@ -338,7 +356,7 @@ impl Primitive for ComponentInterpreter {
         for (port, value) in input_vec {
             env.insert(port, value);
         }
-        self.converge().unwrap();
+        self.converge().map_err(|err| self.qualify_err(err))?;
 
         Ok(self.look_up_outputs())
     }
@@ -358,7 +376,7 @@ impl Primitive for ComponentInterpreter {
 
         let new = match interp {
             StructuralOrControl::Structural(mut s) => {
-                s.step()?;
+                s.step().map_err(|err| self.qualify_err(err))?;
                 StructuralOrControl::Structural(s)
             }
             StructuralOrControl::Control(control) => {
@@ -389,4 +407,40 @@ impl Primitive for ComponentInterpreter {
     fn get_comp_interpreter(&self) -> Option<&ComponentInterpreter> {
         Some(self)
     }
+
+    fn save_state(&self) -> InterpreterResult<Option<serde_json::Value>> {
+        let env = match (&self.interp, self.get_env()) {
+            (
+                StructuralOrControl::Structural(_)
+                | StructuralOrControl::Env(_),
+                StateView::SingleView(env),
+            ) => env,
+            _ => {
+                return Err(InterpreterError::CheckpointError(format!(
+                    "cannot save the state of an instance of `{}` while it is running",
+                    self.comp_ref.name
+                )))
+            }
+        };
+        let state = env.save_state()?;
+        serde_json::to_value(state)
+            .map(Some)
+            .map_err(|err| InterpreterError::CheckpointError(err.to_string()))
+    }
+
+    fn restore_state(
+        &mut self,
+        state: serde_json::Value,
+    ) -> InterpreterResult<()> {
+        let state = serde_json::from_value(state).map_err(|err| {
+            InterpreterError::CheckpointError(err.to_string())
+        })?;
+        match (self.interp.is_control(), self.get_mut_env()) {
+            (false, MutStateView::Single(env)) => env.restore_state(state),
+            _ => Err(InterpreterError::CheckpointError(format!(
+                "cannot restore the state of an instance of `{}` while it is running",
+                self.comp_ref.name
+            ))),
+        }
+    }
 }
@@ -1,5 +1,6 @@
-use super::super::utils::{get_done_port, get_go_port};
+use super::super::utils::{format_debug, get_done_port, get_go_port};
 use super::AssignmentInterpreter;
+use crate::coverage;
 use crate::errors::InterpreterError;
 use crate::interpreter::interpret_group::finish_interpretation;
 use crate::interpreter_ir as iir;
@@ -15,7 +16,7 @@ use crate::{
     values::Value,
 };
 use calyx::ir::{self, Assignment, Guard, RRC};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 // this almost certainly doesn't need to exist but it can't be a trait fn with a
@@ -50,6 +51,14 @@ pub trait Interpreter {
     fn get_mut_env(&mut self) -> MutStateView<'_>;
 }
 
+/// A port driven by an active assignment in the current cycle.
+pub struct DrivenPort {
+    /// Name of the group (or invoked cell) the assignment belongs to.
+    group: ir::Id,
+    port: RRC<ir::Port>,
+    value: Value,
+}
+
 pub struct EmptyInterpreter {
     pub(super) env: InterpreterState,
 }
@@ -58,6 +67,10 @@ impl EmptyInterpreter {
     pub fn new(env: InterpreterState) -> Self {
         Self { env }
     }
+
+    fn driven_ports(&self) -> InterpreterResult<Vec<DrivenPort>> {
+        Ok(vec![])
+    }
 }
 
 impl Interpreter for EmptyInterpreter {
@@ -200,6 +213,23 @@ impl EnableInterpreter {
     fn get<P: AsRaw<ir::Port>>(&self, port: P) -> &Value {
         self.interp.get(port)
     }
+
+    fn driven_ports(&self) -> InterpreterResult<Vec<DrivenPort>> {
+        let group = match &self.group_name {
+            Some(name) => name,
+            None => return Ok(vec![]),
+        };
+        Ok(self
+            .interp
+            .driven_ports()?
+            .into_iter()
+            .map(|(port, value)| DrivenPort {
+                group: group.clone(),
+                port,
+                value,
+            })
+            .collect())
+    }
 }
 
 impl Interpreter for EnableInterpreter {
@@ -269,6 +299,13 @@ impl SeqInterpreter {
             qin: qin.clone(),
         }
     }
+
+    fn driven_ports(&self) -> InterpreterResult<Vec<DrivenPort>> {
+        match &self.current_interpreter {
+            Some(cur) => cur.driven_ports(),
+            None => Ok(vec![]),
+        }
+    }
 }
 
 impl Interpreter for SeqInterpreter {
@@ -357,6 +394,9 @@ pub struct ParInterpreter {
     interpreters: Vec<ControlInterpreter>,
     in_state: InterpreterState,
     input_ports: Rc<HashSet<*const ir::Port>>,
+    /// The branches converged since they last started a statement and since
+    /// the environment was last changed.
+    converged: bool,
     _qin: ComponentQIN,
 }
 
@@ -387,17 +427,83 @@ impl ParInterpreter {
             interpreters,
             in_state: env,
             input_ports,
+            converged: false,
             _par: Rc::clone(par),
             _qin: qin.clone(),
         }
     }
+
+    /// The clock and completion of every branch.
+    fn branch_progress(&self) -> Vec<(u64, bool)> {
+        self.interpreters
+            .iter()
+            .map(|i| (i.get_env().clk(), i.is_done()))
+            .collect()
+    }
+
+    fn driven_ports(&self) -> InterpreterResult<Vec<DrivenPort>> {
+        let mut driven = vec![];
+        for i in &self.interpreters {
+            driven.extend(i.driven_ports()?);
+        }
+        Ok(driven)
+    }
+
+    /// Checks that no two branches of the par drive the same port in the
+    /// current cycle. Holes and the input ports of the component are exempt.
+    fn check_races(&self) -> InterpreterResult<()> {
+        let allow_conflicts =
+            crate::SETTINGS.read().unwrap().allow_par_conflicts;
+        let mut drivers: HashMap<*const ir::Port, DrivenPort> = HashMap::new();
+
+        for i in &self.interpreters {
+            let mut branch_drivers = HashMap::new();
+            for driven in i.driven_ports()? {
+                let raw = driven.port.as_raw();
+                if driven.port.borrow().is_hole()
+                    || self.input_ports.contains(&raw)
+                {
+                    continue;
+                }
+                if let Some(prev) = drivers.get(&raw) {
+                    if allow_conflicts && prev.value == driven.value {
+                        log::warn!("Allowing parallel conflict");
+                        continue;
+                    }
+                    let port = driven.port.borrow();
+                    return Err(InterpreterError::ParRace {
+                        port_id: port.name.clone(),
+                        parent_id: port.get_parent_name(),
+                        group1: prev.group.clone(),
+                        group2: driven.group.clone(),
+                    });
+                }
+                branch_drivers.insert(raw, driven);
+            }
+            drivers.extend(branch_drivers);
+        }
+        Ok(())
+    }
 }
 
 impl Interpreter for ParInterpreter {
     fn step(&mut self) -> InterpreterResult<()> {
+        // Converge the branches so that the race check sees the assignments
+        // that are active when the clock ticks. Executing a cycle converges
+        // a branch, so this is only needed after a branch started a new
+        // statement or finished instead of executing a cycle, or after the
+        // environment was changed.
+        if !self.converged {
+            self.converge()?;
+        }
+        self.check_races()?;
+        let before = self.branch_progress();
         for i in &mut self.interpreters {
             i.step()?;
         }
+        self.converged = self.branch_progress().into_iter().zip(before).all(
+            |((clk, _), (prev_clk, prev_done))| prev_done || clk > prev_clk,
+        );
         Ok(())
     }
 
@@ -432,6 +538,7 @@ impl Interpreter for ParInterpreter {
     }
 
     fn get_mut_env(&mut self) -> MutStateView<'_> {
+        self.converged = false;
         MutCompositeView::new(
             &mut self.in_state,
             self.interpreters
@@ -453,14 +560,14 @@ impl Interpreter for ParInterpreter {
                 return err;
             }
         }
+        self.converged = true;
         Ok(())
     }
 }
 pub struct IfInterpreter {
     port: ConstPort,
     cond: Option<EnableInterpreter>,
-    tbranch: iir::Control,
-    fbranch: iir::Control,
+    ctrl_if: Rc<iir::If>,
     branch_interp: Option<ControlInterpreter>,
     continuous_assignments: iir::ContinuousAssignments,
     input_ports: Rc<HashSet<*const ir::Port>>,
@@ -489,7 +596,9 @@ impl IfInterpreter {
                 None,
             )
         } else {
-            let grp = if is_signal_high(env.get_from_port(cond_port)) {
+            let cond_flag = is_signal_high(env.get_from_port(cond_port));
+            coverage::record_branch(&env, &**ctrl_if, cond_flag);
+            let grp = if cond_flag {
                 &ctrl_if.tbranch
             } else {
                 &ctrl_if.fbranch
@@ -509,14 +618,23 @@ impl IfInterpreter {
         Self {
             port: cond_port,
             cond,
-            tbranch: ctrl_if.tbranch.clone(),
-            fbranch: ctrl_if.fbranch.clone(),
+            ctrl_if: Rc::clone(ctrl_if),
             branch_interp,
             continuous_assignments: Rc::clone(continuous_assigns),
             input_ports,
             qin: qin.clone(),
         }
     }
+
+    fn driven_ports(&self) -> InterpreterResult<Vec<DrivenPort>> {
+        if let Some(cond) = &self.cond {
+            cond.driven_ports()
+        } else if let Some(branch) = &self.branch_interp {
+            branch.driven_ports()
+        } else {
+            Ok(vec![])
+        }
+    }
 }
 
 impl Interpreter for IfInterpreter {
@@ -524,27 +642,20 @@ impl Interpreter for IfInterpreter {
         if let Some(i) = &mut self.cond {
             if i.is_done() {
                 let i = self.cond.take().unwrap();
-                let branch;
-                #[allow(clippy::branches_sharing_code)]
-                if is_signal_high(i.get(self.port)) {
-                    let env = i.deconstruct()?;
-                    branch = ControlInterpreter::new(
-                        &self.tbranch,
-                        env,
-                        &self.continuous_assignments,
-                        Rc::clone(&self.input_ports),
-                        &self.qin,
-                    );
-                } else {
-                    let env = i.deconstruct()?;
-                    branch = ControlInterpreter::new(
-                        &self.fbranch,
-                        env,
-                        &self.continuous_assignments,
-                        Rc::clone(&self.input_ports),
-                        &self.qin,
-                    );
-                }
+                let cond_flag = is_signal_high(i.get(self.port));
+                let env = i.deconstruct()?;
+                coverage::record_branch(&env, &*self.ctrl_if, cond_flag);
+                let branch = ControlInterpreter::new(
+                    if cond_flag {
+                        &self.ctrl_if.tbranch
+                    } else {
+                        &self.ctrl_if.fbranch
+                    },
+                    env,
+                    &self.continuous_assignments,
+                    Rc::clone(&self.input_ports),
+                    &self.qin,
+                );
 
                 self.branch_interp = Some(branch);
                 Ok(())
@@ -641,6 +752,7 @@ impl WhileInterpreter {
             terminal_env = None;
             body_interp = None;
         } else if is_signal_high(env.get_from_port(port)) {
+            coverage::record_branch(&env, &**ctrl_while, true);
             body_interp = Some(ControlInterpreter::new(
                 &ctrl_while.body,
                 env,
@@ -651,6 +763,7 @@ impl WhileInterpreter {
             terminal_env = None;
             cond_interp = None;
         } else {
+            coverage::record_branch(&env, &**ctrl_while, false);
             terminal_env = Some(env);
             body_interp = None;
             cond_interp = None;
@@ -667,6 +780,16 @@ impl WhileInterpreter {
             qin: qin.clone(),
         }
     }
+
+    fn driven_ports(&self) -> InterpreterResult<Vec<DrivenPort>> {
+        if let Some(cond) = &self.cond_interp {
+            cond.driven_ports()
+        } else if let Some(body) = &self.body_interp {
+            body.driven_ports()
+        } else {
+            Ok(vec![])
+        }
+    }
 }
 
 impl Interpreter for WhileInterpreter {
@@ -674,17 +797,20 @@ impl Interpreter for WhileInterpreter {
         if let Some(ci) = &mut self.cond_interp {
             if ci.is_done() {
                 let ci = self.cond_interp.take().unwrap();
-                if is_signal_high(ci.get(self.port)) {
+                let cond_val = is_signal_high(ci.get(self.port));
+                let env = ci.deconstruct()?;
+                coverage::record_branch(&env, &*self.wh, cond_val);
+                if cond_val {
                     let body_interp = ControlInterpreter::new(
                         &self.wh.body,
-                        ci.deconstruct()?,
+                        env,
                         &self.continuous_assignments,
                         Rc::clone(&self.input_ports),
                         &self.qin,
                     );
                     self.body_interp = Some(body_interp)
                 } else {
-                    self.terminal_env = Some(ci.deconstruct()?)
+                    self.terminal_env = Some(env)
                 }
             } else {
                 ci.step()?
@@ -706,6 +832,7 @@ impl Interpreter for WhileInterpreter {
                     );
                     self.cond_interp = Some(cond_interp)
                 } else if is_signal_high(env.get_from_port(self.port)) {
+                    coverage::record_branch(&env, &*self.wh, true);
                     self.body_interp = Some(ControlInterpreter::new(
                         &self.wh.body,
                         env,
@@ -714,6 +841,7 @@ impl Interpreter for WhileInterpreter {
                         &self.qin,
                     ));
                 } else {
+                    coverage::record_branch(&env, &*self.wh, false);
                     self.terminal_env = Some(env);
                 }
             }
@@ -819,12 +947,12 @@ impl InvokeInterpreter {
             assignment_vec.extend(w_ref.assignments.iter().cloned());
         }
 
-        let go_port = comp_cell.get_with_attr("go");
+        let (go_port, comp_done_port) =
+            comp_cell.find_interface(invoke.interface.as_ref()).unwrap();
         // insert one into the go_port
         // should probably replace with an actual assignment from a constant one
         env.insert(go_port, Value::bit_high());
 
-        let comp_done_port = comp_cell.get_with_attr("done");
         let interp = AssignmentInterpreter::new(
             env,
             comp_done_port.into(),
@@ -837,6 +965,20 @@ impl InvokeInterpreter {
             assign_interp: interp,
         }
     }
+
+    fn driven_ports(&self) -> InterpreterResult<Vec<DrivenPort>> {
+        let group = self.invoke.comp.borrow().name().clone();
+        Ok(self
+            .assign_interp
+            .driven_ports()?
+            .into_iter()
+            .map(|(port, value)| DrivenPort {
+                group: group.clone(),
+                port,
+                value,
+            })
+            .collect())
+    }
 }
 
 impl Interpreter for InvokeInterpreter {
@@ -852,7 +994,12 @@ impl Interpreter for InvokeInterpreter {
         let mut env = self.assign_interp.reset()?;
 
         // set go low
-        let go_port = self.invoke.comp.borrow().get_with_attr("go");
+        let (go_port, _) = self
+            .invoke
+            .comp
+            .borrow()
+            .find_interface(self.invoke.interface.as_ref())
+            .unwrap();
         // insert one into the go_port
         // should probably replace with an actual assignment from a constant one
         env.insert(go_port, Value::bit_low());
@@ -915,6 +1062,7 @@ impl ControlInterpreter {
         input_ports: Rc<HashSet<*const ir::Port>>,
         qin: &ComponentQIN,
     ) -> Self {
+        coverage::record(&env, control);
         match control {
             iir::Control::Seq(s) => Self::Seq(Box::new(SeqInterpreter::new(
                 s,
@@ -961,8 +1109,20 @@ impl ControlInterpreter {
             iir::Control::Empty(_) => {
                 Self::Empty(Box::new(EmptyInterpreter::new(env)))
             }
+            // The message is printed when the statement starts and it
+            // finishes immediately.
+            iir::Control::Debug(d) => {
+                eprintln!("{}", format_debug(d, &env));
+                Self::Empty(Box::new(EmptyInterpreter::new(env)))
+            }
         }
     }
+
+    /// Returns the ports driven by the active assignments of this control
+    /// program in the current cycle.
+    pub fn driven_ports(&self) -> InterpreterResult<Vec<DrivenPort>> {
+        control_match!(self, i, i.driven_ports())
+    }
 }
 
 impl Interpreter for ControlInterpreter {
@@ -1011,7 +1171,7 @@ impl StructuralInterpreter {
         env: InterpreterState,
     ) -> Self {
         let comp_sig = comp.signature.borrow();
-        let done_port = comp_sig.get_with_attr("done");
+        let (_, done_port) = comp_sig.find_interface(None).unwrap();
         let done_raw = done_port.as_raw();
         let continuous = Rc::clone(&comp.continuous_assignments);
         let assigns: Vec<ir::Assignment> = vec![];
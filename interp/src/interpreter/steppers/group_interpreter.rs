@@ -1,12 +1,13 @@
-use super::super::interpret_group::{eval_prims, finish_interpretation};
+use super::super::comb_graph::CombGraph;
+use super::super::interpret_group::finish_interpretation;
 use super::super::utils::{self, ConstCell, ConstPort};
 use crate::environment::InterpreterState;
 use crate::errors::{InterpreterError, InterpreterResult};
-use crate::utils::{AsRaw, PortAssignment};
+use crate::stimulus;
+use crate::utils::AsRaw;
 use crate::values::Value;
 use calyx::ir::{self, Assignment, Cell, RRC};
 use std::cell::Ref;
-use std::collections::HashSet;
 use std::rc::Rc;
 
 use super::control_interpreter::EnableHolder;
@@ -99,6 +100,7 @@ pub struct AssignmentInterpreter {
     assigns: AssignmentHolder,
     cont_assigns: iir::ContinuousAssignments,
     cells: Vec<RRC<Cell>>,
+    graph: CombGraph,
     val_changed: Option<bool>,
 }
 
@@ -117,6 +119,11 @@ impl AssignmentInterpreter {
             assigns.get_ref().iter().chain(cont_assigns.iter()),
             done_signal,
         );
+        let graph = CombGraph::new(
+            assigns.get_ref().iter().chain(cont_assigns.iter()),
+            &cells,
+            &state,
+        );
 
         Self {
             state,
@@ -124,6 +131,7 @@ impl AssignmentInterpreter {
             assigns,
             cont_assigns: Rc::clone(cont_assigns),
             cells,
+            graph,
             val_changed: None,
         }
     }
@@ -141,6 +149,16 @@ impl AssignmentInterpreter {
         if self.val_changed.unwrap_or(true) {
             self.step_convergence()?;
         }
+        stimulus::step(&mut self.state)?;
+        if let Some(profile) = &self.state.instruments.profile {
+            if let AssignmentHolder::Group(group) = &self.assigns {
+                profile.borrow_mut().record(
+                    &self.state.qin,
+                    group,
+                    self.state.clk,
+                );
+            }
+        }
 
         let mut update_list: Vec<(RRC<ir::Port>, Value)> = vec![];
 
@@ -151,7 +169,18 @@ impl AssignmentInterpreter {
                 .borrow_mut()
                 .get_mut(&(&cell.borrow() as &Cell as ConstCell))
             {
-                let new_vals = x.do_tick();
+                let new_vals = x.do_tick().map_err(|err| match err {
+                    InterpreterError::InvalidMemoryAccess { access, dims } => {
+                        InterpreterError::MemoryAccessOutOfBounds {
+                            cell: cell.borrow().name().clone(),
+                            access,
+                            dims,
+                            cycle: self.state.clk,
+                            driver: self.address_driver(cell),
+                        }
+                    }
+                    err => err,
+                });
                 for (port, val) in new_vals? {
                     let port_ref =
                         cell.borrow().find(&port).unwrap_or_else(|| {
@@ -170,113 +199,47 @@ impl AssignmentInterpreter {
         for (port, val) in update_list {
             self.state.insert(port, val);
         }
+        self.state.clk += 1;
         self.val_changed = None;
 
         Ok(())
     }
 
+    /// Describes the assignments driving the address ports of the given cell
+    /// for use in error messages.
+    fn address_driver(&self, cell: &RRC<Cell>) -> String {
+        let name = cell.borrow().name().clone();
+        let drives_addr = |assign: &ir::Assignment| {
+            let dst = assign.dst.borrow();
+            dst.get_parent_name() == name
+                && dst.name.as_ref().starts_with("addr")
+        };
+        if self.assigns.get_ref().iter().any(drives_addr) {
+            match self.assigns.get_name() {
+                Some(group) => format!("group \"{}\"", group),
+                None => "an invoke".to_string(),
+            }
+        } else if self.cont_assigns.iter().any(drives_addr) {
+            "continuous assignments".to_string()
+        } else {
+            "unknown assignments".to_string()
+        }
+    }
+
     /// Continue interpreting the assignments until the combinational portions
     /// converge
     pub fn step_convergence(&mut self) -> InterpreterResult<()> {
-        self.val_changed = Some(true); // always run convergence if called
-
-        let possible_ports: HashSet<*const ir::Port> = self
-            .assigns
-            .get_ref()
+        let assign_ref = self.assigns.get_ref();
+        let assigns = assign_ref
             .iter()
             .chain(self.cont_assigns.iter())
-            .map(|a| a.dst.as_raw())
-            .collect();
-
-        // this unwrap is safe
-        while self.val_changed.unwrap() {
-            let mut assigned_ports: HashSet<PortAssignment> = HashSet::new();
-            self.val_changed = Some(false);
-
-            let mut updates_list = vec![];
-
-            let assign_ref = self.assigns.get_ref();
-            // compute all updates from the assignments
-            for assignment in assign_ref.iter().chain(self.cont_assigns.iter())
-            {
-                if self.state.eval_guard(&assignment.guard)? {
-                    let pa = PortAssignment::new(assignment);
-                    //first check nothing has been assigned to this destination yet
-                    if let Some(prior_assign) = assigned_ports.get(&pa) {
-                        let s_orig = prior_assign.get_assignment();
-                        let s_conf = pa.get_assignment();
-
-                        let dst = assignment.dst.borrow();
-
-                        return Err(InterpreterError::conflicting_assignments(
-                            dst.name.clone(),
-                            dst.get_parent_name(),
-                            s_orig,
-                            s_conf,
-                        ));
-                    }
-                    //now add to the HS, because we are assigning
-                    //regardless of whether value has changed this is still a
-                    //value driving the port
-                    assigned_ports.insert(pa);
-                    //ok now proceed
-                    //the below (get) attempts to get from working_env HM first, then
-                    //backing_env Smoosher. What does it mean for the value to be in HM?
-                    //That it's a locked value?
-                    let old_val =
-                        self.state.get_from_port(&assignment.dst.borrow());
-                    let new_val_ref =
-                        self.state.get_from_port(&assignment.src.borrow());
-                    // no need to make updates if the value has not changed
-                    let port = assignment.dst.clone(); // Rc clone
-                    let new_val = new_val_ref.clone();
-
-                    if old_val != new_val_ref {
-                        updates_list.push((port, new_val)); //no point in rewriting same value to this list
-                        self.val_changed = Some(true);
-                    }
-                }
-            }
-
-            let assigned_const_ports: HashSet<_> = assigned_ports
-                .iter()
-                .map(PortAssignment::get_port)
-                .collect();
-
-            //now assign rest to 0
-            //first get all that need to be 0
-            for port in &possible_ports - &assigned_const_ports {
-                //need to set to zero, because unassigned
-                //ok now proceed
-
-                //need to find appropriate-sized 0, so just read
-                //width of old_val
-
-                let old_val = self.state.get_from_port(port);
-                let old_val_width = old_val.width(); //&assignment.dst.borrow().width()
-                let new_val = Value::from(0, old_val_width);
-
-                if old_val.as_unsigned() != 0_u32.into() {
-                    self.val_changed = Some(true);
-                }
-
-                //update directly
-                self.state.insert(port, new_val);
-            }
-
-            // perform all the updates
-            for (port, value) in updates_list {
-                self.state.insert(port, value);
-            }
-
-            let changed =
-                eval_prims(&mut self.state, self.cells.iter(), false)?;
-            if changed {
-                self.val_changed = Some(true);
-            }
-        }
+            .collect::<Vec<_>>();
+        self.graph.eval(&mut self.state, &assigns)?;
+        stimulus::observe(&mut self.state)?;
+        self.val_changed = Some(false);
         Ok(())
     }
+
     /// Advance the interpreter by a cycle, if possible
     pub fn step(&mut self) -> InterpreterResult<()> {
         self.step_cycle()?;
@@ -345,6 +308,21 @@ impl AssignmentInterpreter {
         self.state.get_from_port(port)
     }
 
+    /// Returns the destination ports of the (non-continuous) assignments whose
+    /// guards are currently true, along with the value driven onto them.
+    pub fn driven_ports(
+        &self,
+    ) -> InterpreterResult<Vec<(RRC<ir::Port>, Value)>> {
+        let mut driven = vec![];
+        for assign in self.assigns.get_ref().iter() {
+            if self.state.eval_guard(&assign.guard)? {
+                let val = self.state.get_from_port(assign.src.borrow());
+                driven.push((Rc::clone(&assign.dst), val.clone()));
+            }
+        }
+        Ok(driven)
+    }
+
     // This is not currenty relevant for anything, but may be needed later
     // pending adjustments to the primitive contract as we will need the ability
     // to pass new inputs to components
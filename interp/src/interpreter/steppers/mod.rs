@@ -3,5 +3,5 @@ mod control_interpreter;
 mod group_interpreter;
 
 pub use component_interpreter::ComponentInterpreter;
-pub use control_interpreter::{Interpreter, InvokeInterpreter};
+pub use control_interpreter::{Interpreter, InvokeInterpreter, ParInterpreter};
 pub use group_interpreter::AssignmentInterpreter;
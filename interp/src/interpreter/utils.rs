@@ -1,3 +1,4 @@
+use crate::environment::InterpreterState;
 use crate::values::Value;
 use calyx::ir;
 use calyx::ir::RRC;
@@ -77,9 +78,29 @@ pub fn control_is_empty(control: &iir::Control) -> bool {
         iir::Control::Invoke(_) => false,
         iir::Control::Enable(_) => false,
         iir::Control::Empty(_) => true,
+        iir::Control::Debug(_) => false,
     }
 }
 
+/// The message printed by a `@debug` statement. Values are printed as
+/// unsigned decimal numbers.
+pub fn format_debug(debug: &iir::Debug, env: &InterpreterState) -> String {
+    let mut out = String::new();
+    let mut values = debug
+        .ports
+        .iter()
+        .map(|port| env.get_from_port(port.borrow()).as_unsigned());
+    for (idx, segment) in debug.segments().enumerate() {
+        if idx > 0 {
+            if let Some(value) = values.next() {
+                out.push_str(&value.to_string());
+            }
+        }
+        out.push_str(segment);
+    }
+    out
+}
+
 pub enum ReferenceHolder<'a, T> {
     Ref(Ref<'a, T>),
     Borrow(&'a T),
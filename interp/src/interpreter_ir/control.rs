@@ -4,7 +4,7 @@ use calyx::ir::{self, Attributes, CombGroup, Port, RRC};
 use std::rc::Rc;
 
 // These IR constructs are unchanged but are here re-exported for consistency
-pub use calyx::ir::{Empty, Enable, Invoke};
+pub use calyx::ir::{Debug, Empty, Enable, Invoke};
 
 /// Data for the `seq` control statement.
 #[derive(Debug)]
@@ -69,6 +69,8 @@ pub enum Control {
     Enable(Rc<Enable>),
     /// Control statement that does nothing.
     Empty(Rc<Empty>),
+    /// Print the values of ports.
+    Debug(Rc<Debug>),
 }
 
 impl From<CalyxControl> for Control {
@@ -81,6 +83,7 @@ impl From<CalyxControl> for Control {
             CalyxControl::Invoke(invoke) => Control::Invoke(Rc::new(invoke)),
             CalyxControl::Enable(enable) => Control::Enable(Rc::new(enable)),
             CalyxControl::Empty(empty) => Control::Empty(Rc::new(empty)),
+            CalyxControl::Debug(debug) => Control::Debug(Rc::new(debug)),
         }
     }
 }
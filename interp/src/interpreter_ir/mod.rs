@@ -12,7 +12,7 @@ mod component;
 mod control;
 
 pub use component::Component;
-pub use control::{Control, Empty, Enable, If, Invoke, Par, Seq, While};
+pub use control::{Control, Debug, Empty, Enable, If, Invoke, Par, Seq, While};
 
 use std::rc::Rc;
 pub type ComponentCtx = Rc<Vec<Rc<component::Component>>>;
@@ -1,7 +1,16 @@
+pub mod activity;
+pub mod checkpoint;
+pub mod ffi;
 pub mod interpreter;
 pub mod primitives;
+pub mod profile;
+pub mod stalls;
+pub mod stimulus;
+pub mod taint;
+pub mod trace;
 pub use utils::MemoryMap;
 mod configuration;
+pub mod coverage;
 
 pub mod debugger;
 pub mod errors;
@@ -9,7 +18,7 @@ pub mod interpreter_ir;
 mod macros;
 mod structures;
 
-pub use structures::{environment, stk_env, values};
+pub use structures::{environment, names, stk_env, values};
 
 mod tests;
 mod utils;
@@ -1,12 +1,24 @@
 use crate::environment::InterpreterState;
 use argh::FromArgs;
-use calyx::{frontend, ir, pass_manager::PassManager, utils::OutputFile};
+use calyx::{
+    errors::Error, frontend, ir, pass_manager::PassManager, utils::OutputFile,
+};
+use interp::activity;
+use interp::checkpoint::{self, Checkpoint};
+use interp::coverage;
 use interp::debugger::Debugger;
 use interp::environment;
 use interp::errors::{InterpreterError, InterpreterResult};
-use interp::interpreter::interpret_component;
+use interp::ffi;
+use interp::interpreter::{interpret_component, interpret_component_control};
 use interp::interpreter_ir as iir;
+use interp::profile;
+use interp::stalls;
+use interp::stimulus;
+use interp::taint;
+use interp::trace;
 use log::warn;
+use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
 use std::rc::Rc;
@@ -45,6 +57,11 @@ pub struct Opts {
     /// rather than erroring
     allow_invalid_memory_access: bool,
 
+    #[argh(switch, long = "strict-memory-access")]
+    /// errors when any index of a memory access exceeds its dimension, even
+    /// if the flattened address is in bounds
+    strict_memory_access: bool,
+
     #[argh(switch, long = "allow-par-conflicts")]
     /// enables "sloppy" par simulation which allows parallel overlap when values agree
     allow_par_conflicts: bool,
@@ -52,6 +69,84 @@ pub struct Opts {
     /// upgrades [over | under]flow warnings to errors
     error_on_overflow: bool,
 
+    #[argh(switch, long = "check-overflow")]
+    /// errors when std_add, std_sub, or std_mult_pipe (or their signed
+    /// versions) produce a result that does not fit their output width
+    check_overflow: bool,
+
+    #[argh(option, long = "coverage")]
+    /// write the number of times each group, control statement, and branch
+    /// was executed to the given file as JSON
+    coverage: Option<PathBuf>,
+
+    #[argh(option, long = "coverage-listing")]
+    /// write the groups and control program annotated with the number of
+    /// times they were executed to the given file
+    coverage_listing: Option<PathBuf>,
+
+    #[argh(option, long = "activity")]
+    /// write the number of bits that toggled on each port of each cell,
+    /// along with totals for each primitive, to the given file as JSON
+    activity: Option<PathBuf>,
+
+    #[argh(option, long = "profile")]
+    /// write the number of cycles each group was active for, sampled every
+    /// --profile-sample cycles, to the given file as JSON
+    profile: Option<PathBuf>,
+
+    #[argh(option, long = "profile-sample")]
+    /// number of cycles between two samples of the profile given by
+    /// --profile (default 1)
+    profile_sample: Option<u64>,
+
+    #[argh(option, long = "checkpoint")]
+    /// save the state of the simulation to the given file once it reaches
+    /// the cycle given by --checkpoint-at
+    checkpoint: Option<PathBuf>,
+
+    #[argh(option, long = "checkpoint-at")]
+    /// cycle at which to save the checkpoint given by --checkpoint
+    checkpoint_at: Option<u64>,
+
+    #[argh(option, long = "resume")]
+    /// resume the simulation from a checkpoint saved with --checkpoint
+    resume: Option<PathBuf>,
+
+    #[argh(option, long = "stalls")]
+    /// delay the writes to the @external memories named in the given JSON
+    /// file by the wait states of their stall model
+    stalls: Option<PathBuf>,
+
+    #[argh(option, long = "record-calls")]
+    /// write the values returned to the cells of `std_syscall` and other
+    /// foreign primitives to the given file as JSON
+    record_calls: Option<PathBuf>,
+
+    #[argh(option, long = "replay-calls")]
+    /// return the values recorded with --record-calls in the given file
+    /// instead of calling the handlers of foreign primitives
+    replay_calls: Option<PathBuf>,
+
+    #[argh(option, long = "stimulus")]
+    /// drive the inputs of the main component over time using the commands
+    /// in the given stimulus file
+    stimulus: Option<PathBuf>,
+
+    #[argh(option, long = "taint")]
+    /// track which inputs of the main component flow into its outputs,
+    /// starting from the memories and ports labeled in the given JSON file
+    taint: Option<PathBuf>,
+
+    #[argh(option, long = "taint-report")]
+    /// write the labels of the outputs and stateful cells of the main
+    /// component to the given file as JSON. Defaults to stderr
+    taint_report: Option<PathBuf>,
+
+    #[argh(option, long = "trace")]
+    /// print the changes to the groups and cells selected by the given
+    /// filter in every cycle, such as "group=main::do_add,cell=acc"
+    trace: Option<String>,
+
     #[argh(subcommand)]
     comm: Option<Command>,
 }
@@ -78,6 +173,10 @@ struct CommandDebug {
     #[argh(switch, short = 'p', long = "pass-through")]
     /// flag which runs the program to completion through the debugger
     pass_through: bool,
+
+    #[argh(option, long = "history")]
+    /// number of steps that can be undone with `reverse-step` (default 100)
+    history: Option<usize>,
 }
 
 #[inline]
@@ -114,9 +213,15 @@ fn main() -> InterpreterResult<()> {
         if opts.allow_invalid_memory_access {
             write_lock.allow_invalid_memory_access = true;
         }
+        if opts.strict_memory_access {
+            write_lock.strict_memory_access = true;
+        }
         if opts.error_on_overflow {
             write_lock.error_on_overflow = true;
         }
+        if opts.check_overflow {
+            write_lock.check_overflow = true;
+        }
         if opts.allow_par_conflicts {
             write_lock.allow_par_conflicts = true;
             warn!("You have enabled Par conflicts. This is not recommended and is usually a bad idea")
@@ -124,14 +229,77 @@ fn main() -> InterpreterResult<()> {
         // release lock
     }
 
+    let profile = match (&opts.profile, opts.profile_sample) {
+        (Some(_), Some(0)) => {
+            return Err(InterpreterError::ProfileError(
+                "--profile-sample must be at least 1".to_string(),
+            ))
+        }
+        (Some(_), interval) => {
+            Some(profile::Profile::new(interval.unwrap_or(1)))
+        }
+        (None, Some(_)) => {
+            return Err(InterpreterError::ProfileError(
+                "--profile-sample must be used with --profile".to_string(),
+            ))
+        }
+        (None, None) => None,
+    };
+
+    // `std_syscall` cells with `CALL = "log"` print their argument to
+    // stderr. Programs embedding the interpreter register their own handlers.
+    ffi::register(ffi::SYSCALL, |call| match call.string("CALL") {
+        Some("log") => {
+            let arg = call.arg("arg").map_or(0, |arg| arg.as_u64());
+            eprintln!("{}: {}", call.cell, arg);
+            Ok(0)
+        }
+        name => Err(InterpreterError::FfiError(format!(
+            "`{}` makes the unknown call `{}`",
+            call.cell,
+            name.unwrap_or_default()
+        ))),
+    });
+
+    let checkpoint = match (opts.checkpoint.clone(), opts.checkpoint_at) {
+        (Some(path), Some(cycle)) => {
+            if opts.resume.is_some() {
+                return Err(InterpreterError::CheckpointError(
+                    "--checkpoint cannot be used with --resume".to_string(),
+                ));
+            }
+            if matches!(opts.comm, Some(Command::Debug(_))) {
+                return Err(InterpreterError::CheckpointError(
+                    "--checkpoint is not supported by the debugger".to_string(),
+                ));
+            }
+            Some(checkpoint::Request::new(cycle, path))
+        }
+        (None, None) => None,
+        _ => {
+            return Err(InterpreterError::CheckpointError(
+                "--checkpoint and --checkpoint-at must be used together"
+                    .to_string(),
+            ))
+        }
+    };
+
     // Construct IR
     let ws = frontend::Workspace::construct(&opts.file, &opts.lib_path)?;
     let mut ctx = ir::from_ast::ast_to_ir(ws, ir::BackendConf::default())?;
+    ffi::declare_externs(&ctx.lib);
     let pm = PassManager::default_passes()?;
+    // Errors point to the frontend program when the cells and groups they
+    // are about have positions.
+    let source_map = ctx.metadata.source_map(&ctx.components);
 
     if !opts.skip_verification {
-        pm.execute_plan(&mut ctx, &["validate".to_string()], &[])?;
+        pm.execute_plan(&mut ctx, &["validate".to_string()], &[])
+            .map_err(|err| source_map.annotate(err))?;
     }
+    // Registers with an `@init` attribute are initialized by the control
+    // program.
+    pm.execute_plan(&mut ctx, &["init-insertion".to_string()], &[])?;
 
     let entry_point = ctx.entrypoint;
 
@@ -149,19 +317,141 @@ fn main() -> InterpreterResult<()> {
 
     let mems = interp::MemoryMap::inflate_map(&opts.data_file)?;
 
-    let env = environment::InterpreterState::init_top_level(
+    let instruments = environment::Instruments {
+        coverage: (opts.coverage.is_some() || opts.coverage_listing.is_some())
+            .then(|| coverage::Coverage::new(&components)),
+        activity: opts.activity.is_some().then(activity::Activity::new),
+        taint: opts
+            .taint
+            .as_ref()
+            .map(|path| taint::TaintState::read(path))
+            .transpose()?,
+        trace: opts
+            .trace
+            .as_ref()
+            .map(|spec| trace::Trace::new(spec, &components))
+            .transpose()?,
+        profile,
+        stalls: opts
+            .stalls
+            .as_ref()
+            .map(|path| stalls::Stalls::read(path))
+            .transpose()?,
+        stimulus: opts
+            .stimulus
+            .as_ref()
+            .map(|path| stimulus::Stimulus::read(path))
+            .transpose()?,
+        calls: if opts.record_calls.is_some() || opts.replay_calls.is_some() {
+            Some(ffi::Calls::new(
+                opts.record_calls.is_some(),
+                opts.replay_calls.as_deref(),
+            )?)
+        } else {
+            None
+        },
+        checkpoint,
+    };
+
+    let mut env = environment::InterpreterState::init_top_level(
         &components,
         main_component,
         &mems,
-    );
+        &instruments,
+    )?;
+
+    // The part of the control program that remains to be executed when
+    // resuming from a checkpoint.
+    let control = match &opts.resume {
+        Some(path) => {
+            let checkpoint = Checkpoint::read(path)?;
+            let control =
+                checkpoint.remaining_control(&main_component.control)?;
+            checkpoint.restore(&mut env)?;
+            Some(control)
+        }
+        None => None,
+    };
+    stimulus::init(&mut env)?;
+    taint::init(&env)?;
+
     let res = match opts.comm.unwrap_or(Command::Interpret(CommandInterpret {}))
     {
-        Command::Interpret(_) => interpret_component(main_component, env?),
-        Command::Debug(CommandDebug { pass_through }) => {
+        Command::Interpret(_) => match &control {
+            Some(control) => {
+                interpret_component_control(main_component, control, env)
+            }
+            None => interpret_component(main_component, env),
+        },
+        Command::Debug(CommandDebug {
+            pass_through,
+            history,
+        }) => {
             let mut cidb = Debugger::new(&components, main_component);
-            cidb.main_loop(env?, pass_through)
+            if let Some(control) = control {
+                cidb.set_control(control);
+            }
+            if let Some(window) = history {
+                cidb.set_history_window(window);
+            }
+            cidb.main_loop(env, pass_through)
         }
     };
 
-    print_res(res)
+    if let Some(req) = &instruments.checkpoint {
+        if req.borrow().is_pending() {
+            warn!(
+                "The simulation ended before cycle {}, no checkpoint was saved",
+                opts.checkpoint_at.unwrap_or_default()
+            );
+        }
+    }
+
+    if let Some(cov) = &instruments.coverage {
+        let report = coverage::CoverageReport::new(&components, &cov.borrow());
+        if let Some(path) = &opts.coverage {
+            fs::write(path, report.to_json()).map_err(Error::from)?;
+        }
+        if let Some(path) = &opts.coverage_listing {
+            fs::write(path, report.listing()).map_err(Error::from)?;
+        }
+    }
+
+    if let (Some(path), Some(activity)) =
+        (&opts.activity, &instruments.activity)
+    {
+        let cycles = res.as_ref().map_or(0, |env| env.clk);
+        let report = activity::ActivityReport::new(
+            &components,
+            main_component,
+            &activity.borrow(),
+            cycles,
+        );
+        fs::write(path, report.to_json()).map_err(Error::from)?;
+    }
+
+    if let (Some(path), Some(profile)) = (&opts.profile, &instruments.profile) {
+        let report = profile::ProfileReport::new(
+            &components,
+            main_component,
+            &profile.borrow(),
+        );
+        fs::write(path, report.to_json()).map_err(Error::from)?;
+    }
+
+    if let (Some(path), Some(calls)) = (&opts.record_calls, &instruments.calls)
+    {
+        calls.borrow().save_recording(path)?;
+    }
+
+    if let Some(taint) = &instruments.taint {
+        let report =
+            taint::TaintReport::new(main_component, &taint.borrow()).to_json();
+        match &opts.taint_report {
+            Some(path) => fs::write(path, report).map_err(Error::from)?,
+            None => eprintln!("{}", report),
+        }
+    }
+
+    print_res(res.map_err(|err| err.annotate(&source_map)))
 }
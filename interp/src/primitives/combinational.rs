@@ -1,12 +1,12 @@
 use super::{
-    super::errors::InterpreterResult,
+    super::errors::{InterpreterError, InterpreterResult},
     prim_utils::{get_input_unwrap, get_param},
     Primitive,
 };
 use crate::comb_primitive;
 use crate::values::Value;
 use bitvec::vec::BitVec;
-use calyx::ir;
+use calyx::{ir, utils};
 use std::ops::Not;
 
 /// A constant.
@@ -120,14 +120,20 @@ impl Primitive for StdMux {
 comb_primitive!(StdNot[WIDTH](r#in: WIDTH) -> (out: WIDTH) {
     Ok(r#in.clone_bit_vec().not().into())
 });
+comb_primitive!(StdOrr[WIDTH](r#in: WIDTH) -> (out: WIDTH) {
+    Ok(if r#in.iter().any(|bit| bit) {
+        Value::bit_high()
+    } else {
+        Value::bit_low()
+    })
+});
 
-// ===================== Unsigned binary operations ======================
-comb_primitive!(StdAdd[WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
-    let a_iter = left.iter();
-    let b_iter = right.iter();
+/// Adds two values of the same width, returning the sum truncated to their
+/// width along with the carry out of the most significant bit.
+fn add_bits(left: &Value, right: &Value) -> (Value, bool) {
     let mut c_in = false;
     let mut sum = BitVec::new();
-    for (ai, bi) in a_iter.zip(b_iter) {
+    for (ai, bi) in left.iter().zip(right.iter()) {
         sum.push(
             c_in & !ai & !bi
                 || bi & !c_in & !ai
@@ -139,63 +145,61 @@ comb_primitive!(StdAdd[WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
     let tr: Value = sum.into();
     //as a sanity check, check tr has same width as left
     assert_eq!(tr.width(), left.width());
-    Ok(tr)
-});
-comb_primitive!(StdSub[WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
+    (tr, c_in)
+}
+
+/// Subtracts `right` from `left` by adding the two's complement of `right`.
+fn sub_bits(left: &Value, right: &Value) -> Value {
     //first turn right into ~right + 1
-    let new_right = !right.clone_bit_vec();
-    let mut adder = StdAdd::from_constants(WIDTH);
-    let (_,new_right) = adder
-        .execute(
-            &[("left".into(), &Value::from_bv(new_right)),
-            ("right".into(), &Value::from(1, WIDTH))],
-        )?
-        .into_iter()
-        .next()
-        .unwrap();
+    let (new_right, _) = add_bits(
+        &Value::from_bv(!right.clone_bit_vec()),
+        &Value::from(1, right.width()),
+    );
     //then add left and new_right
-    Ok(adder.execute(&[("left".into(), left),
-    ("right".into(), &new_right)])?.into_iter().next().map(|(_, v)| v).unwrap())
+    add_bits(left, &new_right).0
+}
+
+/// Errors if an operation over- or underflowed and overflow checking is
+/// enabled.
+fn check_overflow(overflow: bool) -> InterpreterResult<()> {
+    if overflow && crate::SETTINGS.read().unwrap().check_overflow {
+        Err(InterpreterError::OverflowError())
+    } else {
+        Ok(())
+    }
+}
+
+// ===================== Unsigned binary operations ======================
+comb_primitive!(StdAdd[WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
+    let (sum, carry) = add_bits(left, right);
+    check_overflow(carry)?;
+    Ok(sum)
+});
+comb_primitive!(StdSub[WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
+    check_overflow(left.as_unsigned() < right.as_unsigned())?;
+    Ok(sub_bits(left, right))
+});
+
+// ===================== Signed binary operations ======================
+comb_primitive!(StdSadd[WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
+    let (_, overflow) =
+        Value::from_checked(left.as_signed() + right.as_signed(), WIDTH);
+    check_overflow(overflow)?;
+    Ok(add_bits(left, right).0)
+});
+comb_primitive!(StdSsub[WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
+    let (_, overflow) =
+        Value::from_checked(left.as_signed() - right.as_signed(), WIDTH);
+    check_overflow(overflow)?;
+    Ok(sub_bits(left, right))
 });
 
-// TODO (Griffin): Make these wrappers around the normal add
+// ===================== FP binary operations ======================
 comb_primitive!(StdFpAdd[WIDTH, INT_WIDTH, FRAC_WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
-    let a_iter = left.iter();
-    let b_iter = right.iter();
-    let mut c_in = false;
-    let mut sum = BitVec::new();
-    for (ai, bi) in a_iter.zip(b_iter) {
-        sum.push(
-            c_in & !ai & !bi
-                || bi & !c_in & !ai
-                || ai & !c_in & !bi
-                || ai & bi & c_in,
-        );
-        c_in = bi & c_in || ai & c_in || ai & bi || ai & c_in & bi;
-    }
-    let tr = Value::from_bv(sum);
-    //as a sanity check, check tr has same width as left
-    assert_eq!(tr.width(), left.width());
-    Ok(tr)
+    Ok(add_bits(left, right).0)
 });
 comb_primitive!(StdFpSub[WIDTH, INT_WIDTH, FRAC_WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
-    //first turn right into ~right + 1
-    let new_right = !right.clone_bit_vec();
-    let mut adder = StdAdd::from_constants(WIDTH);
-    let new_right = adder
-        .execute(
-            &[("left".into(), &Value::from_bv(new_right)),
-            ("right".into(), &Value::from(1, WIDTH))],
-        )?
-        .into_iter()
-        .next()
-        .map(|(_, v)| v)
-        .unwrap();
-    //then add left and new_right
-    let out = adder.execute(&[("left".into(), left),
-    ("right".into(), &new_right)])?.into_iter().next().map(|(_, v)| v).unwrap();
-
-    Ok(out)
+    Ok(sub_bits(left, right))
 });
 
 // ===================== Shift Operations ======================
@@ -284,14 +288,20 @@ comb_primitive!(StdRsh[WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
 // ===================== Signed Shift Operations ======================
 comb_primitive!(StdSlsh[WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
     let shift_amount = right.as_usize();
+    if shift_amount >= WIDTH as usize {
+        return Ok(Value::zeroes(WIDTH as usize));
+    }
     let mut val = left.clone_bit_vec();
     val.shift_right(shift_amount);
     Ok(val.into())
-
 });
 comb_primitive!(StdSrsh[WIDTH](left: WIDTH, right: WIDTH) -> (out: WIDTH) {
     let shift_amount = right.as_usize();
     let sign: bool = left[left.len()-1]; //msb
+    // Shifting out every bit leaves only copies of the sign bit.
+    if shift_amount >= WIDTH as usize {
+        return Ok(Value::from_bv(BitVec::repeat(sign, WIDTH as usize)));
+    }
     let mut val = left.clone_bit_vec();
     val.shift_left(shift_amount);
     if sign {
@@ -489,3 +499,85 @@ comb_primitive!(StdSlice[IN_WIDTH, OUT_WIDTH](r#in: IN_WIDTH) -> (out: OUT_WIDTH
 comb_primitive!(StdPad[IN_WIDTH, OUT_WIDTH](r#in: IN_WIDTH) -> (out: OUT_WIDTH) {
     Ok(r#in.ext(OUT_WIDTH as usize))
 });
+comb_primitive!(StdWire[WIDTH](r#in: WIDTH) -> (out: WIDTH) {
+    Ok(r#in.clone())
+});
+
+// ===================== Read-only memories ======================
+/// A read-only memory whose contents are preloaded from the file named by the
+/// `INIT_FILE` parameter using [utils::read_rom_file].
+#[derive(Debug)]
+pub struct StdRom {
+    width: u64,
+    idx_size: u64,
+    data: Vec<Value>,
+}
+
+impl StdRom {
+    pub fn new(
+        params: &ir::Binding,
+        string_params: &ir::StringBinding,
+    ) -> InterpreterResult<Self> {
+        let width = get_param(params, "WIDTH")
+            .expect("Missing width param for std_rom");
+        let size =
+            get_param(params, "SIZE").expect("Missing size param for std_rom");
+        let idx_size = get_param(params, "IDX_SIZE")
+            .expect("Missing idx_size param for std_rom");
+        let file = string_params
+            .iter()
+            .find(|(id, _)| id == "INIT_FILE")
+            .map(|(_, file)| file)
+            .expect("Missing init_file param for std_rom");
+
+        let data = utils::read_rom_file(file, width, size)?
+            .into_iter()
+            .map(|val| Value::from(val, width))
+            .collect();
+
+        Ok(Self {
+            width,
+            idx_size,
+            data,
+        })
+    }
+}
+
+impl Primitive for StdRom {
+    fn do_tick(&mut self) -> InterpreterResult<Vec<(ir::Id, Value)>> {
+        Ok(vec![])
+    }
+
+    fn is_comb(&self) -> bool {
+        true
+    }
+
+    fn validate(&self, inputs: &[(ir::Id, &Value)]) {
+        for (id, v) in inputs {
+            match id.as_ref() {
+                "addr0" => assert_eq!(v.len() as u64, self.idx_size),
+                p => unreachable!("Unknown port: {}", p),
+            }
+        }
+    }
+
+    fn execute(
+        &mut self,
+        inputs: &[(ir::Id, &Value)],
+    ) -> InterpreterResult<Vec<(ir::Id, Value)>> {
+        let addr0 = get_input_unwrap(inputs, "addr0").as_u64();
+        let out = self
+            .data
+            .get(addr0 as usize)
+            .cloned()
+            .unwrap_or_else(|| Value::zeroes(self.width));
+        Ok(vec![("read_data".into(), out)])
+    }
+
+    fn reset(
+        &mut self,
+        inputs: &[(ir::Id, &Value)],
+    ) -> InterpreterResult<Vec<(ir::Id, Value)>> {
+        self.execute(inputs)
+    }
+}
@@ -1,5 +1,7 @@
+use crate::errors::{InterpreterError, InterpreterResult};
 use crate::values::Value;
 use calyx::ir;
+use serde::{de::DeserializeOwned, Serialize};
 
 pub(super) fn get_param<S>(params: &ir::Binding, target: S) -> Option<u64>
 where
@@ -36,3 +38,22 @@ where
 {
     get_input(inputs, target).unwrap()
 }
+
+/// Save the state of a stateful primitive by serializing the whole primitive.
+pub(super) fn save_state<P: Serialize>(
+    prim: &P,
+) -> InterpreterResult<Option<serde_json::Value>> {
+    serde_json::to_value(prim)
+        .map(Some)
+        .map_err(|err| InterpreterError::CheckpointError(err.to_string()))
+}
+
+/// Restore the state of a primitive saved by [save_state].
+pub(super) fn restore_state<P: DeserializeOwned>(
+    prim: &mut P,
+    state: serde_json::Value,
+) -> InterpreterResult<()> {
+    *prim = serde_json::from_value(state)
+        .map_err(|err| InterpreterError::CheckpointError(err.to_string()))?;
+    Ok(())
+}
@@ -58,6 +58,20 @@ pub trait Primitive {
     fn get_comp_interpreter(&self) -> Option<&ComponentInterpreter> {
         None
     }
+
+    /// Save the internal state of this primitive for a checkpoint. Returns
+    /// `None` for primitives without state.
+    fn save_state(&self) -> InterpreterResult<Option<serde_json::Value>> {
+        Ok(None)
+    }
+
+    /// Restore the internal state of this primitive from a checkpoint.
+    fn restore_state(
+        &mut self,
+        _state: serde_json::Value,
+    ) -> InterpreterResult<()> {
+        Ok(())
+    }
 }
 
 /// An enum wrapping over a tuple representing the shape of a multi-dimensional
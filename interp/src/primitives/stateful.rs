@@ -1,4 +1,6 @@
-use super::prim_utils::{get_input_unwrap, get_param};
+use super::prim_utils::{
+    get_input_unwrap, get_param, restore_state, save_state,
+};
 use super::{Primitive, Serializeable};
 use crate::errors::{InterpreterError, InterpreterResult};
 use crate::utils::construct_bindings;
@@ -7,6 +9,7 @@ use crate::values::Value;
 use calyx::ir;
 use ibig::ops::RemEuclid;
 use log::warn;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
 /// Pipelined Multiplication (3 cycles)
@@ -17,6 +20,7 @@ use std::collections::VecDeque;
 /// The product associated with a given input will be output on the third [do_tick()].
 /// Note: Calling [Primitive::execute] multiple times before [Primitive::do_tick] has no effect; only the last
 /// set of inputs prior to the [Primitve::do_tick] will be saved.
+#[derive(Serialize, Deserialize)]
 pub struct StdMultPipe<const SIGNED: bool> {
     pub width: u64,
     pub product: Value,
@@ -78,6 +82,17 @@ impl<const SIGNED: bool> Primitive for StdMultPipe<SIGNED> {
         false
     }
 
+    fn save_state(&self) -> InterpreterResult<Option<serde_json::Value>> {
+        save_state(self)
+    }
+
+    fn restore_state(
+        &mut self,
+        state: serde_json::Value,
+    ) -> InterpreterResult<()> {
+        restore_state(self, state)
+    }
+
     fn validate(&self, inputs: &[(calyx::ir::Id, &Value)]) {
         for (id, v) in inputs {
             match id.as_ref() {
@@ -111,7 +126,10 @@ impl<const SIGNED: bool> Primitive for StdMultPipe<SIGNED> {
                 )
             };
 
-            if overflow & crate::SETTINGS.read().unwrap().error_on_overflow {
+            let settings = crate::SETTINGS.read().unwrap();
+            if overflow
+                && (settings.error_on_overflow || settings.check_overflow)
+            {
                 return Err(InterpreterError::OverflowError());
             } else if overflow {
                 warn!("Computation has under/overflowed in multiplier");
@@ -162,6 +180,7 @@ impl<const SIGNED: bool> Primitive for StdMultPipe<SIGNED> {
 ///be output on the third [do_tick()].
 ///Note: Calling [execute] multiple times before [do_tick()] has no effect; only
 ///the last set of inputs prior to the [do_tick()] will be saved.
+#[derive(Serialize, Deserialize)]
 pub struct StdDivPipe<const SIGNED: bool> {
     pub width: u64,
     pub quotient: Value,
@@ -224,6 +243,17 @@ impl<const SIGNED: bool> Primitive for StdDivPipe<SIGNED> {
         false
     }
 
+    fn save_state(&self) -> InterpreterResult<Option<serde_json::Value>> {
+        save_state(self)
+    }
+
+    fn restore_state(
+        &mut self,
+        state: serde_json::Value,
+    ) -> InterpreterResult<()> {
+        restore_state(self, state)
+    }
+
     fn validate(&self, inputs: &[(calyx::ir::Id, &Value)]) {
         for (id, v) in inputs {
             match id.as_ref() {
@@ -319,6 +349,7 @@ impl<const SIGNED: bool> Primitive for StdDivPipe<SIGNED> {
 }
 
 /// A register.
+#[derive(Serialize, Deserialize)]
 pub struct StdReg {
     pub width: u64,
     pub data: [Value; 1],
@@ -376,6 +407,17 @@ impl Primitive for StdReg {
         false
     }
 
+    fn save_state(&self) -> InterpreterResult<Option<serde_json::Value>> {
+        save_state(self)
+    }
+
+    fn restore_state(
+        &mut self,
+        state: serde_json::Value,
+    ) -> InterpreterResult<()> {
+        restore_state(self, state)
+    }
+
     fn validate(&self, inputs: &[(calyx::ir::Id, &Value)]) {
         for (id, v) in inputs {
             match id.as_ref() {
@@ -429,6 +471,22 @@ impl Primitive for StdReg {
     }
 }
 
+/// Checks if the given index is valid for a memory with the given dimensions.
+/// Unless strict memory accesses are enabled, only the flattened address is
+/// checked, so an index that exceeds its dimension wraps around into the next
+/// one like it would in hardware.
+fn valid_access(access: &[u64], dims: &[u64]) -> bool {
+    if crate::SETTINGS.read().unwrap().strict_memory_access {
+        access.iter().zip(dims).all(|(idx, dim)| idx < dim)
+    } else {
+        let addr = access
+            .iter()
+            .zip(dims)
+            .fold(0, |addr, (idx, dim)| addr * dim + idx);
+        addr < dims.iter().product()
+    }
+}
+
 /// A one-dimensional memory. Initialized with
 /// StdMemD1.new(WIDTH, SIZE, IDX_SIZE) where:
 /// * WIDTH - Size of an individual memory slot.
@@ -447,7 +505,7 @@ impl Primitive for StdReg {
 ///              with respect to addr0.
 /// * done: 1 - The done signal for the memory. This signal goes high for one
 ///         cycle after finishing a write to the memory.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct StdMemD1 {
     pub width: u64,    // size of individual piece of mem
     pub size: u64,     // # slots of mem
@@ -514,7 +572,7 @@ impl Primitive for StdMemD1 {
     fn do_tick(&mut self) -> InterpreterResult<Vec<(ir::Id, Value)>> {
         //if there is an update, update and return along w/ a done
         //else this memory was used combinationally and there is nothing to tick
-        if self.last_index >= self.size {
+        if !valid_access(&[self.last_index], &[self.size]) {
             if crate::SETTINGS.read().unwrap().allow_invalid_memory_access {
                 if self.write_en {
                     return Ok(vec![
@@ -565,6 +623,17 @@ impl Primitive for StdMemD1 {
         false
     }
 
+    fn save_state(&self) -> InterpreterResult<Option<serde_json::Value>> {
+        save_state(self)
+    }
+
+    fn restore_state(
+        &mut self,
+        state: serde_json::Value,
+    ) -> InterpreterResult<()> {
+        restore_state(self, state)
+    }
+
     fn validate(&self, inputs: &[(ir::Id, &Value)]) {
         for (id, v) in inputs {
             match id.as_ref() {
@@ -668,7 +737,7 @@ impl Primitive for StdMemD1 {
 /// Outputs:
 /// read_data: WIDTH - The value stored at mem[addr0][addr1]. This value is combinational with respect to addr0 and addr1.
 /// done: 1: The done signal for the memory. This signal goes high for one cycle after finishing a write to the memory.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StdMemD2 {
     pub width: u64,   // size of individual piece of mem
     pub d0_size: u64, // # slots of mem
@@ -766,7 +835,8 @@ impl StdMemD2 {
 impl Primitive for StdMemD2 {
     //null-op for now
     fn do_tick(&mut self) -> InterpreterResult<Vec<(ir::Id, Value)>> {
-        if self.calc_addr(self.last_idx.0, self.last_idx.1) >= self.max_idx() {
+        let (addr0, addr1) = self.last_idx;
+        if !valid_access(&[addr0, addr1], &[self.d0_size, self.d1_size]) {
             if crate::SETTINGS.read().unwrap().allow_invalid_memory_access {
                 if self.write_en {
                     return Ok(vec![
@@ -811,6 +881,17 @@ impl Primitive for StdMemD2 {
         false
     }
 
+    fn save_state(&self) -> InterpreterResult<Option<serde_json::Value>> {
+        save_state(self)
+    }
+
+    fn restore_state(
+        &mut self,
+        state: serde_json::Value,
+    ) -> InterpreterResult<()> {
+        restore_state(self, state)
+    }
+
     fn validate(&self, inputs: &[(ir::Id, &Value)]) {
         for (id, v) in inputs {
             match id.as_ref() {
@@ -927,7 +1008,7 @@ impl Primitive for StdMemD2 {
 /// Outputs:
 /// read_data: WIDTH - The value stored at mem[addr0][addr1][addr2]. This value is combinational with respect to addr0, addr1, and addr2.
 /// done: 1: The done signal for the memory. This signal goes high for one cycle after finishing a write to the memory.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StdMemD3 {
     width: u64,
     d0_size: u64,
@@ -1039,7 +1120,10 @@ impl Primitive for StdMemD3 {
     //null-op for now
     fn do_tick(&mut self) -> InterpreterResult<Vec<(ir::Id, Value)>> {
         let (addr0, addr1, addr2) = self.last_idx;
-        if self.calc_addr(addr0, addr1, addr2) >= self.max_idx() {
+        if !valid_access(
+            &[addr0, addr1, addr2],
+            &[self.d0_size, self.d1_size, self.d2_size],
+        ) {
             if crate::SETTINGS.read().unwrap().allow_invalid_memory_access {
                 if self.write_en {
                     return Ok(vec![
@@ -1085,6 +1169,17 @@ impl Primitive for StdMemD3 {
         false
     }
 
+    fn save_state(&self) -> InterpreterResult<Option<serde_json::Value>> {
+        save_state(self)
+    }
+
+    fn restore_state(
+        &mut self,
+        state: serde_json::Value,
+    ) -> InterpreterResult<()> {
+        restore_state(self, state)
+    }
+
     fn validate(&self, inputs: &[(ir::Id, &Value)]) {
         for (id, v) in inputs {
             match id.as_ref() {
@@ -1218,7 +1313,7 @@ impl Primitive for StdMemD3 {
 /// Outputs:
 /// read_data: WIDTH - The value stored at mem[addr0][addr1][addr2][addr3]. This value is combinational with respect to addr0, addr1, addr2, and addr3.
 /// done: 1: The done signal for the memory. This signal goes high for one cycle after finishing a write to the memory.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StdMemD4 {
     width: u64,
     d0_size: u64,
@@ -1349,7 +1444,10 @@ impl Primitive for StdMemD4 {
     //null-op for now
     fn do_tick(&mut self) -> InterpreterResult<Vec<(ir::Id, Value)>> {
         let (addr0, addr1, addr2, addr3) = self.last_idx;
-        if self.calc_addr(addr0, addr1, addr2, addr3) >= self.max_idx() {
+        if !valid_access(
+            &[addr0, addr1, addr2, addr3],
+            &[self.d0_size, self.d1_size, self.d2_size, self.d3_size],
+        ) {
             if crate::SETTINGS.read().unwrap().allow_invalid_memory_access {
                 if self.write_en {
                     return Ok(vec![
@@ -1404,6 +1502,17 @@ impl Primitive for StdMemD4 {
         false
     }
 
+    fn save_state(&self) -> InterpreterResult<Option<serde_json::Value>> {
+        save_state(self)
+    }
+
+    fn restore_state(
+        &mut self,
+        state: serde_json::Value,
+    ) -> InterpreterResult<()> {
+        restore_state(self, state)
+    }
+
     fn validate(&self, inputs: &[(ir::Id, &Value)]) {
         for (id, v) in inputs {
             match id.as_ref() {
@@ -1521,6 +1630,7 @@ impl Primitive for StdMemD4 {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct StdFpMultPipe<const SIGNED: bool> {
     pub width: u64,
     pub int_width: u64,
@@ -1585,6 +1695,17 @@ impl<const SIGNED: bool> Primitive for StdFpMultPipe<SIGNED> {
         false
     }
 
+    fn save_state(&self) -> InterpreterResult<Option<serde_json::Value>> {
+        save_state(self)
+    }
+
+    fn restore_state(
+        &mut self,
+        state: serde_json::Value,
+    ) -> InterpreterResult<()> {
+        restore_state(self, state)
+    }
+
     fn validate(&self, inputs: &[(ir::Id, &Value)]) {
         validate![inputs;
             left: self.width,
@@ -1640,6 +1761,7 @@ impl<const SIGNED: bool> Primitive for StdFpMultPipe<SIGNED> {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct StdFpDivPipe<const SIGNED: bool> {
     pub width: u64,
     pub int_width: u64,
@@ -1706,6 +1828,17 @@ impl<const SIGNED: bool> Primitive for StdFpDivPipe<SIGNED> {
         false
     }
 
+    fn save_state(&self) -> InterpreterResult<Option<serde_json::Value>> {
+        save_state(self)
+    }
+
+    fn restore_state(
+        &mut self,
+        state: serde_json::Value,
+    ) -> InterpreterResult<()> {
+        restore_state(self, state)
+    }
+
     fn validate(&self, inputs: &[(ir::Id, &Value)]) {
         validate![inputs;
             left: self.width,
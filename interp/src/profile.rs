@@ -0,0 +1,148 @@
+//! Sampling profile of the groups that run during a run of the interpreter.
+//!
+//! When profiling is enabled, the interpreter records the groups that are
+//! active every `N` cycles, where `N` is the sample interval. Every sample
+//! stands for `N` cycles so the number of cycles each group ran for is
+//! approximated by its number of samples times the interval. Sampling every
+//! cycle gives an exact profile while larger intervals are cheaper for long
+//! simulations. Groups are identified by the instance of the component they
+//! belong to, so every instance of a component has its own samples, and
+//! cycles are counted by that instance.
+use crate::interpreter_ir as iir;
+use crate::names::{ComponentQIN, InstanceName};
+use calyx::ir;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+/// Profile collected by a simulation. It is shared by the environments of
+/// the simulation.
+pub type SharedProfile = Rc<RefCell<Profile>>;
+
+/// Samples of the groups of a program.
+pub struct Profile {
+    /// Number of cycles between two samples.
+    interval: u64,
+    /// Number of samples in which each group of each instance was active,
+    /// keyed by the address of the group.
+    samples: HashMap<ComponentQIN, HashMap<usize, u64>>,
+}
+
+fn addr(group: &ir::RRC<ir::Group>) -> usize {
+    group.as_ptr() as *const ir::Group as usize
+}
+
+impl Profile {
+    /// Profile of a new simulation that samples the groups every `interval`
+    /// cycles.
+    pub fn new(interval: u64) -> SharedProfile {
+        Rc::new(RefCell::new(Self {
+            interval,
+            samples: HashMap::new(),
+        }))
+    }
+
+    /// Record that `group` of the instance `qin` is active in `cycle` if the
+    /// cycle is sampled.
+    pub(crate) fn record(
+        &mut self,
+        qin: &ComponentQIN,
+        group: &ir::RRC<ir::Group>,
+        cycle: u64,
+    ) {
+        if cycle.is_multiple_of(self.interval) {
+            *self
+                .samples
+                .entry(qin.clone())
+                .or_default()
+                .entry(addr(group))
+                .or_default() += 1;
+        }
+    }
+
+    fn lookup(&self, qin: &ComponentQIN, group: &ir::RRC<ir::Group>) -> u64 {
+        self.samples
+            .get(qin)
+            .and_then(|groups| groups.get(&addr(group)))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Profile of a single group.
+#[derive(Serialize)]
+struct GroupProfile {
+    /// Number of samples in which the group was active.
+    samples: u64,
+    /// Approximate number of cycles the group was active for.
+    cycles: u64,
+}
+
+/// Profile of every group in a program.
+#[derive(Serialize)]
+pub struct ProfileReport {
+    /// Number of cycles between two samples.
+    sample_interval: u64,
+    /// Profile of the groups keyed by the hierarchical name of the instance
+    /// of their component, e.g., `main.dot0`, and by group name.
+    instances: BTreeMap<String, BTreeMap<String, GroupProfile>>,
+}
+
+impl ProfileReport {
+    /// Build the report for the instances of the components in `ctx`,
+    /// starting from `main`, from the samples collected so far.
+    pub fn new(
+        ctx: &iir::ComponentCtx,
+        main: &Rc<iir::Component>,
+        profile: &Profile,
+    ) -> Self {
+        let mut report = Self {
+            sample_interval: profile.interval,
+            instances: BTreeMap::new(),
+        };
+        let qin = ComponentQIN::new_single(main, &main.name);
+        report.add_instance(ctx, main, &qin, profile);
+        report
+    }
+
+    /// Add the profile of the instance `qin` of `comp` and of the instances
+    /// it contains.
+    fn add_instance(
+        &mut self,
+        ctx: &iir::ComponentCtx,
+        comp: &Rc<iir::Component>,
+        qin: &ComponentQIN,
+        profile: &Profile,
+    ) {
+        let groups = comp
+            .groups
+            .iter()
+            .map(|group| {
+                let count = profile.lookup(qin, group);
+                (
+                    group.borrow().name().to_string(),
+                    GroupProfile {
+                        samples: count,
+                        cycles: count * profile.interval,
+                    },
+                )
+            })
+            .collect();
+        self.instances.insert(qin.to_string(), groups);
+        for cell in comp.cells.iter() {
+            let cell = cell.borrow();
+            if let ir::CellType::Component { name } = &cell.prototype {
+                let inner = ctx.iter().find(|c| c.name == name).unwrap();
+                let inner_qin =
+                    qin.new_extend(InstanceName::new(inner, cell.name()));
+                self.add_instance(ctx, inner, &inner_qin, profile);
+            }
+        }
+    }
+
+    /// The report as a JSON object.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
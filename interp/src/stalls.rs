@@ -0,0 +1,229 @@
+//! Wait states for `@external` memories.
+//!
+//! The interpreter normally completes every write to a memory in a single
+//! cycle. A stall configuration delays the writes to selected `@external`
+//! memories by a number of wait states so that designs can be tested against
+//! slower memories. While a write is stalled, the memory ignores it and keeps
+//! its `done` signal low. The write takes effect in the cycle after the last
+//! wait state.
+//!
+//! The configuration is a JSON file that maps the hierarchical names of
+//! memories, like `main.p0.mem`, to the model used to generate the wait
+//! states of each write. Memories of the main component can also be named
+//! without the name of the component:
+//! - `{"fixed": n}` stalls every write for `n` cycles.
+//! - `{"random": {"max": n, "seed": s}}` stalls every write for a
+//!   pseudo-random number of cycles between `0` and `n`.
+//! - `{"script": [n0, n1, ...]}` stalls the writes for the given number of
+//!   cycles in order and starts over once the list is exhausted.
+use crate::errors::{InterpreterError, InterpreterResult};
+use crate::names::ComponentQIN;
+use crate::primitives::{Primitive, Serializeable};
+use crate::values::Value;
+use calyx::ir;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Model used to generate the wait states of a memory.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum StallModel {
+    /// The same number of wait states for every write.
+    Fixed(u64),
+    /// A pseudo-random number of wait states between `0` and `max`.
+    Random { max: u64, seed: u64 },
+    /// The given wait states for consecutive writes.
+    Script(Vec<u64>),
+}
+
+/// Generates the number of wait states of consecutive writes.
+#[derive(Debug)]
+struct WaitStates {
+    model: StallModel,
+    /// State of the random number generator or the index into the script.
+    state: u64,
+}
+
+impl WaitStates {
+    fn new(model: StallModel) -> Self {
+        let state = match &model {
+            // xorshift requires a non-zero state.
+            StallModel::Random { seed, .. } => (*seed).max(1),
+            StallModel::Fixed(_) | StallModel::Script(_) => 0,
+        };
+        Self { model, state }
+    }
+
+    fn next(&mut self) -> u64 {
+        match &self.model {
+            StallModel::Fixed(waits) => *waits,
+            StallModel::Random { max, .. } => {
+                self.state ^= self.state << 13;
+                self.state ^= self.state >> 7;
+                self.state ^= self.state << 17;
+                self.state % max.saturating_add(1)
+            }
+            StallModel::Script(waits) if waits.is_empty() => 0,
+            StallModel::Script(waits) => {
+                let idx = self.state as usize % waits.len();
+                self.state += 1;
+                waits[idx]
+            }
+        }
+    }
+}
+
+/// Models of the memories that are stalled in a simulation. Every instance
+/// of a memory has its own wait states.
+pub struct Stalls {
+    /// Models of the memories keyed by their hierarchical name.
+    models: HashMap<String, StallModel>,
+}
+
+impl Stalls {
+    /// Stall the memories named in the configuration file at `path`.
+    pub fn read(path: &Path) -> InterpreterResult<Rc<Self>> {
+        let to_err = |err: &dyn std::fmt::Display| {
+            InterpreterError::StallConfigError(format!(
+                "failed to read {}: {}",
+                path.to_string_lossy(),
+                err
+            ))
+        };
+        let buf = fs::read(path).map_err(|err| to_err(&err))?;
+        let models =
+            serde_json::from_slice(&buf).map_err(|err| to_err(&err))?;
+        Ok(Rc::new(Self { models }))
+    }
+
+    /// Model of the memory `cell` of the instance `qin`. Memories of the
+    /// main component can also be named without the name of the component.
+    fn model(&self, qin: &ComponentQIN, cell: &ir::Id) -> Option<&StallModel> {
+        self.models.get(qin.qualify(cell).as_ref()).or_else(|| {
+            if qin.len() == 1 {
+                self.models.get(cell.as_ref())
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Wraps the memory `prim`, a cell of the instance `qin`, if the
+/// configuration stalls the memory `cell`. Only `@external` memories can be
+/// stalled.
+pub(crate) fn wrap(
+    cell: &ir::Cell,
+    prim: Box<dyn Primitive>,
+    stalls: &Option<Rc<Stalls>>,
+    qin: &ComponentQIN,
+) -> Box<dyn Primitive> {
+    let stalls = match stalls {
+        Some(stalls) if cell.get_attribute("external").is_some() => stalls,
+        _ => return prim,
+    };
+    match stalls.model(qin, cell.name()) {
+        Some(model) => Box::new(StalledMemory::new(prim, model.clone())),
+        None => prim,
+    }
+}
+
+/// A memory whose writes are delayed by wait states.
+struct StalledMemory {
+    mem: Box<dyn Primitive>,
+    waits: WaitStates,
+    /// Wait states of the current write.
+    next_wait: u64,
+    /// Wait states that have passed for the current write.
+    waited: u64,
+    /// True if `write_en` was high in the current cycle.
+    requested: bool,
+}
+
+impl StalledMemory {
+    fn new(mem: Box<dyn Primitive>, model: StallModel) -> Self {
+        let mut waits = WaitStates::new(model);
+        let next_wait = waits.next();
+        Self {
+            mem,
+            waits,
+            next_wait,
+            waited: 0,
+            requested: false,
+        }
+    }
+}
+
+impl Primitive for StalledMemory {
+    fn do_tick(&mut self) -> InterpreterResult<Vec<(ir::Id, Value)>> {
+        let out = self.mem.do_tick()?;
+        if !self.requested {
+            self.waited = 0;
+        } else if self.waited < self.next_wait {
+            self.waited += 1;
+        } else {
+            self.waited = 0;
+            self.next_wait = self.waits.next();
+        }
+        Ok(out)
+    }
+
+    fn is_comb(&self) -> bool {
+        self.mem.is_comb()
+    }
+
+    fn validate(&self, inputs: &[(ir::Id, &Value)]) {
+        self.mem.validate(inputs)
+    }
+
+    fn execute(
+        &mut self,
+        inputs: &[(ir::Id, &Value)],
+    ) -> InterpreterResult<Vec<(ir::Id, Value)>> {
+        self.requested =
+            inputs.iter().any(|(id, v)| id == "write_en" && v.as_bool());
+        if !self.requested || self.waited >= self.next_wait {
+            return self.mem.execute(inputs);
+        }
+        // Hide the write from the memory until the wait states have passed.
+        let low = Value::bit_low();
+        let inputs = inputs
+            .iter()
+            .map(|(id, v)| {
+                (id.clone(), if id == "write_en" { &low } else { *v })
+            })
+            .collect::<Vec<_>>();
+        self.mem.execute(&inputs)
+    }
+
+    fn reset(
+        &mut self,
+        inputs: &[(ir::Id, &Value)],
+    ) -> InterpreterResult<Vec<(ir::Id, Value)>> {
+        self.waited = 0;
+        self.requested = false;
+        self.mem.reset(inputs)
+    }
+
+    fn serialize(&self, signed: bool) -> Serializeable {
+        self.mem.serialize(signed)
+    }
+
+    fn has_serializeable_state(&self) -> bool {
+        self.mem.has_serializeable_state()
+    }
+
+    fn save_state(&self) -> InterpreterResult<Option<serde_json::Value>> {
+        self.mem.save_state()
+    }
+
+    fn restore_state(
+        &mut self,
+        state: serde_json::Value,
+    ) -> InterpreterResult<()> {
+        self.mem.restore_state(state)
+    }
+}
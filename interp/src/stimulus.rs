@@ -0,0 +1,298 @@
+//! Scripted stimulus for the input ports of the main component.
+//!
+//! The interpreter normally leaves the input ports of the main component at
+//! zero. A stimulus file drives them over time so that designs with streaming
+//! or handshake-style interfaces can be tested. The file contains one command
+//! per line that are executed in order:
+//! - `N: p = v, q = w` sets the input ports `p` and `q` in cycle `N`.
+//! - `+N: p = v` sets `p` `N` cycles after the previous command completed.
+//! - `wait p == v` waits until the port `p` has the value `v` at the end of a
+//!   cycle. The wait completes in the following cycle.
+//!
+//! Inputs keep their value until they are set again. Everything after a `#`
+//! is a comment:
+//! ```text
+//! 0: data = 5, valid = 1
+//! wait ready == 1   # the design accepted the value
+//! +0: valid = 0
+//! +3: data = 7, valid = 1
+//! ```
+use crate::environment::InterpreterState;
+use crate::errors::{InterpreterError, InterpreterResult};
+use crate::names::ComponentQIN;
+use crate::values::Value;
+use calyx::ir;
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+/// When the assignments of a [Command::Set] happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Time {
+    /// The given cycle.
+    At(u64),
+    /// The given number of cycles after the previous command completed.
+    After(u64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    /// Set the given input ports to the given values.
+    Set(Time, Vec<(String, u64)>),
+    /// Wait until the port has the given value.
+    Wait(String, u64),
+}
+
+/// Stimulus driven by a simulation. It is shared by the environments of the
+/// simulation.
+pub type SharedStimulus = Rc<RefCell<Stimulus>>;
+
+/// A parsed stimulus file along with the progress of its execution.
+#[derive(Debug)]
+pub struct Stimulus {
+    commands: Vec<Command>,
+    /// Index of the next command to execute.
+    next: usize,
+    /// Cycle in which the previous command completed.
+    cycle: u64,
+    /// The instance whose ports are driven.
+    instance: Option<ComponentQIN>,
+}
+
+fn parse_err(line: usize, msg: &str) -> InterpreterError {
+    InterpreterError::StimulusError(format!("line {}: {}", line + 1, msg))
+}
+
+fn parse_num(line: usize, s: &str) -> InterpreterResult<u64> {
+    s.trim().parse().map_err(|_| {
+        parse_err(line, &format!("expected a number, found `{}'", s.trim()))
+    })
+}
+
+fn parse_binding(
+    line: usize,
+    s: &str,
+    op: &str,
+) -> InterpreterResult<(String, u64)> {
+    let (port, val) = s.split_once(op).ok_or_else(|| {
+        parse_err(line, &format!("expected `port {} value'", op))
+    })?;
+    let port = port.trim();
+    if port.is_empty() || port.contains(char::is_whitespace) {
+        return Err(parse_err(line, &format!("invalid port name `{}'", port)));
+    }
+    Ok((port.to_string(), parse_num(line, val)?))
+}
+
+fn parse(src: &str) -> InterpreterResult<Vec<Command>> {
+    let mut commands = vec![];
+    let mut last_cycle = 0;
+    for (line, text) in src.lines().enumerate() {
+        let text = text.split('#').next().unwrap().trim();
+        if text.is_empty() {
+            continue;
+        }
+        if let Some(cond) = text.strip_prefix("wait ") {
+            let (port, val) = parse_binding(line, cond, "==")?;
+            commands.push(Command::Wait(port, val));
+            continue;
+        }
+        let (time, assigns) = text
+            .split_once(':')
+            .ok_or_else(|| parse_err(line, "expected `cycle: assignments'"))?;
+        let time = match time.trim().strip_prefix('+') {
+            Some(delay) => Time::After(parse_num(line, delay)?),
+            None => {
+                let cycle = parse_num(line, time)?;
+                if cycle < last_cycle {
+                    return Err(parse_err(
+                        line,
+                        &format!(
+                            "cycle {} is before the previous cycle {}",
+                            cycle, last_cycle
+                        ),
+                    ));
+                }
+                last_cycle = cycle;
+                Time::At(cycle)
+            }
+        };
+        let assigns = assigns
+            .split(',')
+            .map(|assign| parse_binding(line, assign, "="))
+            .collect::<InterpreterResult<_>>()?;
+        commands.push(Command::Set(time, assigns));
+    }
+    Ok(commands)
+}
+
+impl Stimulus {
+    /// Drive the inputs of the main component using the stimulus file at
+    /// `path`.
+    pub fn read(path: &Path) -> InterpreterResult<SharedStimulus> {
+        let src = fs::read_to_string(path).map_err(|err| {
+            InterpreterError::StimulusError(format!(
+                "failed to read {}: {}",
+                path.to_string_lossy(),
+                err
+            ))
+        })?;
+        Ok(Rc::new(RefCell::new(Stimulus {
+            commands: parse(&src)?,
+            next: 0,
+            cycle: 0,
+            instance: None,
+        })))
+    }
+}
+
+/// Returns the port `name` of the signature of the component of `env`.
+fn find_port(
+    env: &InterpreterState,
+    name: &str,
+) -> InterpreterResult<ir::RRC<ir::Port>> {
+    env.component.signature.borrow().find(name).ok_or_else(|| {
+        InterpreterError::StimulusError(format!(
+            "component `{}' does not have a port named `{}'",
+            env.component.name, name
+        ))
+    })
+}
+
+/// Check the stimulus against the signature of the main component in `env`
+/// and apply the assignments of cycle `0`.
+pub fn init(env: &mut InterpreterState) -> InterpreterResult<()> {
+    let stimulus = match env.instruments.stimulus.clone() {
+        Some(stimulus) => stimulus,
+        None => return Ok(()),
+    };
+    let mut stim = stimulus.borrow_mut();
+    for cmd in &stim.commands {
+        match cmd {
+            Command::Set(_, assigns) => {
+                for (name, _) in assigns {
+                    // Inputs of the component are outputs of its signature.
+                    let port = find_port(env, name)?;
+                    let port = port.borrow();
+                    if port.direction != ir::Direction::Output
+                        || port.attributes.has("clk")
+                        || port.attributes.has("reset")
+                    {
+                        return Err(InterpreterError::StimulusError(format!(
+                            "`{}' is not an input of component `{}' that can be driven",
+                            name, env.component.name
+                        )));
+                    }
+                }
+            }
+            Command::Wait(name, _) => {
+                find_port(env, name)?;
+            }
+        }
+    }
+    stim.instance = Some(env.qin.clone());
+    let cycle = env.clk;
+    stim.advance(env, Some(cycle), false)
+}
+
+/// Runs `f` on the stimulus if `env` is the instance of the main component.
+fn with_main(
+    env: &mut InterpreterState,
+    f: impl FnOnce(&mut Stimulus, &mut InterpreterState) -> InterpreterResult<()>,
+) -> InterpreterResult<()> {
+    let stimulus = match env.instruments.stimulus.clone() {
+        Some(stimulus) => stimulus,
+        None => return Ok(()),
+    };
+    let mut stim = stimulus.borrow_mut();
+    if stim.instance.as_ref() == Some(&env.qin) {
+        f(&mut stim, env)
+    } else {
+        Ok(())
+    }
+}
+
+/// Called whenever the combinational logic of a component has converged to
+/// check the pending `wait` commands.
+pub(crate) fn observe(env: &mut InterpreterState) -> InterpreterResult<()> {
+    with_main(env, |stim, env| stim.advance(env, None, true))
+}
+
+/// Called at the end of every cycle of a component to apply the assignments
+/// of the next cycle.
+pub(crate) fn step(env: &mut InterpreterState) -> InterpreterResult<()> {
+    with_main(env, |stim, env| {
+        let next_cycle = env.clk + 1;
+        stim.advance(env, Some(next_cycle), true)
+    })
+}
+
+impl Stimulus {
+    /// Execute the commands that complete before `until`. Assignments are
+    /// only applied if `until` is given and `wait` commands are only checked
+    /// if `observe` is true.
+    fn advance(
+        &mut self,
+        env: &mut InterpreterState,
+        until: Option<u64>,
+        observe: bool,
+    ) -> InterpreterResult<()> {
+        while let Some(cmd) = self.commands.get(self.next) {
+            match cmd {
+                Command::Wait(name, val) => {
+                    let port = find_port(env, name)?;
+                    if !observe || env.get_from_port(&port).as_u64() != *val {
+                        break;
+                    }
+                    self.cycle = env.clk + 1;
+                }
+                Command::Set(time, assigns) => {
+                    let cycle = match time {
+                        Time::At(cycle) => (*cycle).max(self.cycle),
+                        Time::After(delay) => self.cycle + delay,
+                    };
+                    match until {
+                        Some(until) if cycle <= until => {}
+                        _ => break,
+                    }
+                    for (name, val) in assigns {
+                        let port = find_port(env, name)?;
+                        let width = port.borrow().width;
+                        env.insert(&port, Value::from(*val, width));
+                    }
+                    self.cycle = cycle;
+                }
+            }
+            self.next += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        let src = "# comment\n0: a = 1, b = 2\nwait c == 1 # done\n+2: a = 0\n";
+        assert_eq!(
+            parse(src).unwrap(),
+            vec![
+                Command::Set(
+                    Time::At(0),
+                    vec![("a".to_string(), 1), ("b".to_string(), 2)]
+                ),
+                Command::Wait("c".to_string(), 1),
+                Command::Set(Time::After(2), vec![("a".to_string(), 0)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_decreasing_cycles() {
+        assert!(parse("3: a = 1\n1: a = 0").is_err());
+        assert!(parse("3: a b = 1").is_err());
+    }
+}
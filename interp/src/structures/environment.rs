@@ -2,12 +2,21 @@
 
 use super::names::{ComponentQIN, GroupQIN, InstanceName};
 use super::stk_env::Smoosher;
+use crate::activity::SharedActivity;
+use crate::checkpoint::{ComponentState, SharedRequest};
+use crate::coverage::SharedCoverage;
 use crate::errors::{InterpreterError, InterpreterResult};
+use crate::ffi::{self, SharedCalls};
 use crate::interpreter::ComponentInterpreter;
 use crate::interpreter_ir as iir;
 use crate::primitives::{
     combinational, stateful, Entry, Primitive, Serializeable,
 };
+use crate::profile::SharedProfile;
+use crate::stalls::{self, Stalls};
+use crate::stimulus::SharedStimulus;
+use crate::taint::{self, SharedTaint};
+use crate::trace::SharedTrace;
 use crate::utils::AsRaw;
 use crate::utils::MemoryMap;
 use crate::values::Value;
@@ -56,6 +65,37 @@ pub struct InterpreterState {
     pub component: Rc<iir::Component>,
 
     pub sub_comp_set: Rc<HashSet<ConstCell>>,
+
+    /// The hierarchical name of the instance this environment is for.
+    pub qin: ComponentQIN,
+
+    /// Information collected by the simulation.
+    pub instruments: Instruments,
+}
+
+/// Information collected while simulating a program. It is shared by every
+/// environment of the simulation, and each kind of information is only
+/// collected if it was requested.
+#[derive(Clone, Default)]
+pub struct Instruments {
+    /// Coverage of the control statements.
+    pub coverage: Option<SharedCoverage>,
+    /// Switching activity of the ports.
+    pub activity: Option<SharedActivity>,
+    /// Labels of the inputs the values were computed from.
+    pub taint: Option<SharedTaint>,
+    /// Changes to the selected groups and cells.
+    pub trace: Option<SharedTrace>,
+    /// Samples of the active groups.
+    pub profile: Option<SharedProfile>,
+    /// Wait states of the stalled memories.
+    pub stalls: Option<Rc<Stalls>>,
+    /// Values driven on the inputs of the main component.
+    pub stimulus: Option<SharedStimulus>,
+    /// Values returned to the cells of foreign primitives.
+    pub calls: Option<SharedCalls>,
+    /// Checkpoint to save once the simulation reaches its cycle.
+    pub checkpoint: Option<SharedRequest>,
 }
 
 /// Helper functions for the environment.
@@ -66,28 +106,22 @@ impl InterpreterState {
         ctx: &iir::ComponentCtx,
         target: &Rc<iir::Component>,
         mems: &Option<MemoryMap>,
+        instruments: &Instruments,
     ) -> InterpreterResult<Self> {
         // only for the main component
         let qin = ComponentQIN::new_single(target, &target.name);
-        let (map, set) = Self::construct_cell_map(target, ctx, mems, &qin)?;
-
-        Ok(Self {
-            context: Rc::clone(ctx),
-            clk: 0,
-            port_map: InterpreterState::construct_port_map(&*target),
-            cell_map: map,
-            component: target.clone(),
-            sub_comp_set: Rc::new(set),
-        })
+        Self::init(ctx, target, mems, instruments, &qin)
     }
 
     pub fn init(
         ctx: &iir::ComponentCtx,
         target: &Rc<iir::Component>,
         mems: &Option<MemoryMap>,
+        instruments: &Instruments,
         qin: &ComponentQIN,
     ) -> InterpreterResult<Self> {
-        let (map, set) = Self::construct_cell_map(target, ctx, mems, qin)?;
+        let (map, set) =
+            Self::construct_cell_map(target, ctx, mems, instruments, qin)?;
 
         Ok(Self {
             context: Rc::clone(ctx),
@@ -96,29 +130,45 @@ impl InterpreterState {
             cell_map: map,
             component: target.clone(),
             sub_comp_set: Rc::new(set),
+            qin: qin.clone(),
+            instruments: instruments.clone(),
         })
     }
 
     /// Insert a new value for the given constant port into the environment
     pub fn insert<P: AsRaw<ir::Port>>(&mut self, port: P, value: Value) {
-        self.port_map.set(port.as_raw(), value);
+        let port = port.as_raw();
+        if let Some(activity) = &self.instruments.activity {
+            if let Some(old) = self.port_map.get(&port) {
+                activity.borrow_mut().record(&self.qin, port, old, &value);
+            }
+        }
+        if let Some(trace) = &self.instruments.trace {
+            if let Some(old) = self.port_map.get(&port) {
+                if *old != value {
+                    trace
+                        .borrow_mut()
+                        .record(&self.qin, port, self.clk, old, &value);
+                }
+            }
+        }
+        self.port_map.set(port, value);
     }
 
-    fn make_primitive(
+    pub(crate) fn make_primitive(
         prim_name: &ir::Id,
         params: &ir::Binding,
+        string_params: &ir::StringBinding,
         cell_name: Option<&ir::Id>,
         mems: &Option<MemoryMap>,
     ) -> InterpreterResult<Box<dyn Primitive>> {
         Ok(match prim_name.as_ref() {
             "std_const" => Box::new(combinational::StdConst::new(params)),
             // unsigned and signed basic arith
-            "std_add" | "std_sadd" => {
-                Box::new(combinational::StdAdd::new(params))
-            }
-            "std_sub" | "std_ssub" => {
-                Box::new(combinational::StdSub::new(params))
-            }
+            "std_add" => Box::new(combinational::StdAdd::new(params)),
+            "std_sub" => Box::new(combinational::StdSub::new(params)),
+            "std_sadd" => Box::new(combinational::StdSadd::new(params)),
+            "std_ssub" => Box::new(combinational::StdSsub::new(params)),
             // fp basic arith
             "std_fp_sadd" | "std_fp_add" => {
                 Box::new(combinational::StdFpAdd::new(params))
@@ -157,11 +207,15 @@ impl InterpreterState {
             // unsigned shifts
             "std_lsh" => Box::new(combinational::StdLsh::new(params)),
             "std_rsh" => Box::new(combinational::StdRsh::new(params)),
+            // signed shifts
+            "std_slsh" => Box::new(combinational::StdSlsh::new(params)),
+            "std_srsh" => Box::new(combinational::StdSrsh::new(params)),
             // Logical operators
             "std_and" => Box::new(combinational::StdAnd::new(params)),
             "std_or" => Box::new(combinational::StdOr::new(params)),
             "std_xor" => Box::new(combinational::StdXor::new(params)),
             "std_not" => Box::new(combinational::StdNot::new(params)),
+            "std_orr" => Box::new(combinational::StdOrr::new(params)),
             // Unsigned Comparsion
             "std_ge" => Box::new(combinational::StdGe::new(params)),
             "std_le" => Box::new(combinational::StdLe::new(params)),
@@ -184,8 +238,12 @@ impl InterpreterState {
             // Resizing ops
             "std_slice" => Box::new(combinational::StdSlice::new(params)),
             "std_pad" => Box::new(combinational::StdPad::new(params)),
+            "std_wire" => Box::new(combinational::StdWire::new(params)),
             // State components
             "std_reg" => Box::new(stateful::StdReg::new(params)),
+            "std_rom" => {
+                Box::new(combinational::StdRom::new(params, string_params)?)
+            }
             "std_mem_d1" => {
                 let mut prim = Box::new(stateful::StdMemD1::new(params));
 
@@ -243,6 +301,7 @@ impl InterpreterState {
         comp: &Rc<iir::Component>,
         ctx: &iir::ComponentCtx,
         mems: &Option<MemoryMap>,
+        instruments: &Instruments,
         qin_name: &ComponentQIN,
     ) -> InterpreterResult<(PrimitiveMap, HashSet<ConstCell>)> {
         let mut map = HashMap::new();
@@ -254,34 +313,90 @@ impl InterpreterState {
                 ir::CellType::Primitive {
                     name,
                     param_binding,
+                    string_binding,
                     is_comb: _,
                 } => {
-                    let cell_name = match name.as_ref() {
-                        "std_mem_d1" | "std_mem_d2" | "std_mem_d3"
-                        | "std_mem_d4" => Some(cl.name()),
-                        _ => None,
+                    // Memories are the cells that must be given an address
+                    // to be read from.
+                    let is_memory = cl.ports.iter().any(|port| {
+                        let port = port.borrow();
+                        port.direction == ir::Direction::Input
+                            && port.attributes.has("read_together")
+                    });
+                    let cell_name =
+                        if is_memory { Some(cl.name()) } else { None };
+
+                    // Memories with initial contents in the program are
+                    // loaded with them.
+                    let contents = cl.contents.as_ref().map(|vals| {
+                        let width = cl.get_parameter("WIDTH").unwrap();
+                        once((
+                            cl.name().clone(),
+                            vals.iter()
+                                .map(|v| Value::from(*v, width))
+                                .collect(),
+                        ))
+                        .collect::<MemoryMap>()
+                    });
+                    let prim: Box<dyn Primitive> =
+                        if ffi::is_foreign(name.as_ref()) {
+                            Box::new(ffi::ForeignPrimitive::new(
+                                cl,
+                                qin_name.qualify(cl.name()),
+                                &instruments.calls,
+                            )?)
+                        } else {
+                            Self::make_primitive(
+                                name,
+                                param_binding,
+                                string_binding,
+                                cell_name,
+                                if contents.is_some() {
+                                    &contents
+                                } else {
+                                    mems
+                                },
+                            )
+                            .map_err(|err| {
+                                match err {
+                                    InterpreterError::UnknownPrimitive(
+                                        prim,
+                                    ) => ffi::missing_model(
+                                        prim,
+                                        qin_name.qualify(cl.name()),
+                                    ),
+                                    err => err,
+                                }
+                            })?
+                        };
+                    let prim = match cell_name {
+                        Some(_) => stalls::wrap(
+                            cl,
+                            prim,
+                            &instruments.stalls,
+                            qin_name,
+                        ),
+                        None => prim,
                     };
-
-                    map.insert(
-                        cl as ConstCell,
-                        Self::make_primitive(
-                            name,
-                            param_binding,
-                            cell_name,
-                            mems,
-                        )?,
-                    );
+                    let prim =
+                        taint::wrap(cl, prim, &instruments.taint, qin_name);
+                    map.insert(cl as ConstCell, prim);
                 }
                 ir::CellType::Component { name } => {
                     let inner_comp =
                         ctx.iter().find(|x| x.name == name).unwrap();
                     let qin = qin_name
                         .new_extend(InstanceName::new(inner_comp, cl.name()));
-                    let env = Self::init(ctx, inner_comp, mems, &qin)?;
-                    let comp_interp: Box<dyn Primitive> =
+                    let env =
+                        Self::init(ctx, inner_comp, mems, instruments, &qin)?;
+                    let comp_interp: Box<dyn Primitive> = taint::wrap(
+                        cl,
                         Box::new(ComponentInterpreter::from_component(
                             inner_comp, env, qin,
-                        ));
+                        )),
+                        &instruments.taint,
+                        qin_name,
+                    );
                     set.insert(cl.as_raw());
                     map.insert(cl as ConstCell, comp_interp);
                 }
@@ -384,6 +499,8 @@ impl InterpreterState {
             context: Rc::clone(&self.context),
             component: self.component.clone(),
             sub_comp_set: Rc::clone(&self.sub_comp_set),
+            qin: self.qin.clone(),
+            instruments: self.instruments.clone(),
         }
     }
     /// Creates a fork of the source environment which has the same clock and
@@ -398,6 +515,8 @@ impl InterpreterState {
             context: Rc::clone(&self.context),
             component: self.component.clone(),
             sub_comp_set: Rc::clone(&self.sub_comp_set),
+            qin: self.qin.clone(),
+            instruments: self.instruments.clone(),
         }
     }
 
@@ -502,6 +621,123 @@ impl InterpreterState {
             .flatten()
             .collect()
     }
+
+    /// The ports tracked by this environment keyed by a name that identifies
+    /// them across runs. Ports of constants are not included since their
+    /// values never change.
+    fn named_ports(&self) -> Vec<(String, RRC<ir::Port>)> {
+        let comp = &self.component;
+        let sig = comp.signature.borrow();
+        let mut ports = sig
+            .ports
+            .iter()
+            .map(|port| {
+                (
+                    format!("{}.{}", sig.name(), port.borrow().name),
+                    port.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        for group in comp.groups.iter() {
+            let grp = group.borrow();
+            ports.extend(grp.holes.iter().map(|hole| {
+                (
+                    format!("{}[{}]", grp.name(), hole.borrow().name),
+                    hole.clone(),
+                )
+            }));
+        }
+        for cell in comp.cells.iter() {
+            let cl = cell.borrow();
+            if let ir::CellType::Constant { .. } = cl.prototype {
+                continue;
+            }
+            ports.extend(cl.ports.iter().map(|port| {
+                (
+                    format!("{}.{}", cl.name(), port.borrow().name),
+                    port.clone(),
+                )
+            }));
+        }
+        ports
+    }
+
+    /// Save the values of all ports and the state of all stateful cells,
+    /// including sub-components.
+    pub fn save_state(&self) -> InterpreterResult<ComponentState> {
+        let ports = self
+            .named_ports()
+            .into_iter()
+            .map(|(name, port)| (name, self.get_from_port(&port).clone()))
+            .collect();
+
+        let cell_map = self.cell_map.borrow();
+        let mut cells = BTreeMap::new();
+        for cell in self.component.cells.iter() {
+            if let Some(prim) = cell_map.get(&cell.as_raw()) {
+                if let Some(state) = prim.save_state()? {
+                    cells.insert(cell.borrow().name().to_string(), state);
+                }
+            }
+        }
+
+        Ok(ComponentState {
+            clk: self.clk,
+            ports,
+            cells,
+        })
+    }
+
+    /// Restore the state saved by [InterpreterState::save_state].
+    pub fn restore_state(
+        &mut self,
+        state: ComponentState,
+    ) -> InterpreterResult<()> {
+        let mismatch = |msg: String| {
+            InterpreterError::CheckpointError(format!(
+                "{} in component `{}`",
+                msg, self.component.name
+            ))
+        };
+
+        let mut ports =
+            self.named_ports().into_iter().collect::<HashMap<_, _>>();
+        let mut values = vec![];
+        for (name, value) in state.ports {
+            let port = ports
+                .remove(&name)
+                .ok_or_else(|| mismatch(format!("unknown port `{}`", name)))?;
+            if port.borrow().width != value.width() {
+                return Err(mismatch(format!(
+                    "port `{}` has width {} but the saved value has width {}",
+                    name,
+                    port.borrow().width,
+                    value.width()
+                )));
+            }
+            values.push((port, value));
+        }
+
+        {
+            let mut cell_map = self.cell_map.borrow_mut();
+            for (name, cell_state) in state.cells {
+                let prim = self
+                    .component
+                    .find_cell(&name)
+                    .and_then(|cell| cell_map.get_mut(&cell.as_raw()))
+                    .ok_or_else(|| {
+                        mismatch(format!("unknown cell `{}`", name))
+                    })?;
+                prim.restore_state(cell_state)?;
+            }
+        }
+
+        for (port, value) in values {
+            self.insert(port.as_raw(), value);
+        }
+        self.clk = state.clk;
+        Ok(())
+    }
 }
 
 impl Serialize for InterpreterState {
@@ -585,6 +821,17 @@ impl<'a> StateView<'a> {
         }
     }
 
+    /// The clock cycle of the environment. The clock of a composite view is
+    /// the clock of its most advanced child.
+    pub fn clk(&self) -> u64 {
+        match self {
+            StateView::SingleView(sv) => sv.clk,
+            StateView::Composite(cv) => {
+                cv.1.iter().map(StateView::clk).max().unwrap_or(cv.0.clk)
+            }
+        }
+    }
+
     pub fn sub_component_currently_executing(&self) -> HashSet<GroupQIN> {
         match self {
             StateView::SingleView(sv) => sv.sub_component_currently_executing(),
@@ -64,6 +64,24 @@ impl ComponentQIN {
         let inst = InstanceName::new(component_id, instance);
         Self::from(inst)
     }
+
+    /// The hierarchical name of the cell or group `name` of this instance,
+    /// e.g., `main.dot0.mul`. Keeps the span of `name`.
+    pub fn qualify(&self, name: &Id) -> Id {
+        Id::new(format!("{}.{}", self, name), name.span().cloned())
+    }
+}
+
+/// Displays the names of the instances from the main component down, e.g.,
+/// `main.dot0`.
+impl Display for ComponentQIN {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self
+            .iter()
+            .map(|inst| inst.instance.id.as_str())
+            .collect::<Vec<_>>();
+        write!(f, "{}", names.join("."))
+    }
 }
 
 impl<T: Into<InstanceName>> From<T> for ComponentQIN {
@@ -108,6 +126,12 @@ impl GroupQIN {
     }
 }
 
+impl Display for GroupQIN {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.0.prefix, self.0.name)
+    }
+}
+
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 /// A qualified group name
 pub struct GroupQN(QualifiedName);
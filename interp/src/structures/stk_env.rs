@@ -201,22 +201,36 @@ impl<'a, K: Eq + std::hash::Hash, V: Eq> From<&'a Smoosher<K, V>>
     for HashMap<&'a K, &'a V>
 {
     fn from(item: &'a Smoosher<K, V>) -> Self {
-        //just add all bindings
-        let mut tr = HashMap::new();
-        //first add from head
-        for (k, v) in HashMap::iter(&item.head) {
-            tr.insert(k, v);
-        }
-        //then from tail
-        for nd in List::iter(&item.tail) {
-            for (k, v) in HashMap::iter(nd) {
-                //add, but only if the binding isn't yet in the HM (preserve scope)
-                if None == tr.get(k) {
-                    tr.insert(k, v);
+        item.iter().collect()
+    }
+}
+
+/// Iterator over the visible bindings of a [Smoosher], i.e., the binding of
+/// each key in the newest scope that binds it. Created by [Smoosher::iter].
+pub struct SmooshedIter<'a, K: Eq + std::hash::Hash, V: Eq> {
+    smoosher: &'a Smoosher<K, V>,
+    /// Bindings of the current scope that have not been visited yet.
+    bindings: std::collections::hash_map::Iter<'a, K, V>,
+    /// Scopes below the current scope.
+    scopes: Iter<'a, HashMap<K, V>>,
+    /// Number of scopes above the current scope.
+    depth: usize,
+}
+
+impl<'a, K: Eq + std::hash::Hash, V: Eq> Iterator for SmooshedIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (smoosher, depth) = (self.smoosher, self.depth);
+            for (k, v) in self.bindings.by_ref() {
+                if !smoosher.bound_above(k, depth) {
+                    return Some((k, v));
                 }
             }
+            self.bindings = self.scopes.next()?.iter();
+            self.depth += 1;
         }
-        tr
     }
 }
 
@@ -297,6 +311,77 @@ impl<K: Eq + std::hash::Hash, V: Eq> Smoosher<K, V> {
         }
     }
 
+    /// Returns true if [k] is bound in one of the newest [depth] scopes.
+    fn bound_above(&self, k: &K, depth: usize) -> bool {
+        std::iter::once(&self.head)
+            .chain(self.tail.iter())
+            .take(depth)
+            .any(|hm| hm.contains_key(k))
+    }
+
+    /// Returns true if [k] is bound in any scope.
+    ///
+    /// # Example
+    /// ```
+    /// use interp::stk_env::Smoosher;
+    /// let mut smoosher = Smoosher::new();
+    /// smoosher.set("hi!", 1);
+    /// smoosher.new_scope();
+    /// assert!(smoosher.contains_key(&"hi!"));
+    /// assert!(!smoosher.contains_key(&"hey"));
+    /// ```
+    pub fn contains_key(&self, k: &K) -> bool {
+        self.get(k).is_some()
+    }
+
+    /// Returns an iterator over the visible bindings of [self], i.e., the
+    /// binding of each key in the newest scope that binds it, without building
+    /// a merged HashMap. Each key is produced exactly once, in no particular
+    /// order.
+    ///
+    /// # Example
+    /// ```
+    /// use interp::stk_env::Smoosher;
+    /// let mut smoosher = Smoosher::new();
+    /// smoosher.set("hi!", 1);
+    /// smoosher.set("bye", 0);
+    /// smoosher.new_scope();
+    /// smoosher.set("hi!", 2);
+    /// let mut bindings: Vec<_> = smoosher.iter().collect();
+    /// bindings.sort();
+    /// assert_eq!(bindings, vec![(&"bye", &0), (&"hi!", &2)]);
+    /// ```
+    pub fn iter(&self) -> SmooshedIter<'_, K, V> {
+        SmooshedIter {
+            smoosher: self,
+            bindings: self.head.iter(),
+            scopes: self.tail.iter(),
+            depth: 0,
+        }
+    }
+
+    /// Returns the number of keys bound in [self]. Keys bound in multiple
+    /// scopes are only counted once.
+    ///
+    /// # Example
+    /// ```
+    /// use interp::stk_env::Smoosher;
+    /// let mut smoosher = Smoosher::new();
+    /// smoosher.set("hi!", 1);
+    /// smoosher.new_scope();
+    /// smoosher.set("hi!", 2);
+    /// smoosher.set("bye", 0);
+    /// assert_eq!(smoosher.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns true if no key is bound in [self].
+    pub fn is_empty(&self) -> bool {
+        self.head.is_empty() && self.tail.iter().all(HashMap::is_empty)
+    }
+
     /// ```text
     /// Sets a new binding of [k] to [v] in the highest scope.
     /// Guarantees that all following calls to [get(&k)] will return [&v],
@@ -973,22 +1058,7 @@ impl<K: Eq + std::hash::Hash, V: Eq> Smoosher<K, V> {
     /// current state of the environment, which is easily iterable.
     /// ```
     pub fn to_hm(&self) -> HashMap<&K, &V> {
-        //just add all bindings
-        let mut tr = HashMap::new();
-        //first add from head
-        for (k, v) in HashMap::iter(&self.head) {
-            tr.insert(k, v);
-        }
-        //then from tail
-        for nd in List::iter(&self.tail) {
-            for (k, v) in HashMap::iter(nd) {
-                //add, but only if the binding isn't yet in the HM (preserve scope)
-                if None == tr.get(k) {
-                    tr.insert(k, v);
-                }
-            }
-        }
-        tr
+        self.iter().collect()
     }
 
     /// ```text
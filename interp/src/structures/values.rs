@@ -7,6 +7,7 @@ use fraction::Fraction;
 use ibig::{ibig, ops::UnsignedAbs, IBig, UBig};
 use itertools::Itertools;
 use serde::de::{self, Deserialize, Visitor};
+use serde::Serialize;
 
 /// Retrieves the unsigned fixed point representation of `v`. This splits the representation into
 ///  integral and fractional bits. The width of the integral bits is described as:
@@ -776,6 +777,22 @@ impl PartialOrd for Value {
     }
 }
 
+/// Values are serialized as a bitstring with the most significant bit first,
+/// which is the format expected by the [Deserialize] implementation.
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let bits: String = self
+            .iter()
+            .rev()
+            .map(|b| if b { '1' } else { '0' })
+            .collect();
+        serializer.serialize_str(&bits)
+    }
+}
+
 impl<'de> Deserialize<'de> for Value {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -800,7 +817,16 @@ impl<'de> Deserialize<'de> for Value {
                 let mut vec = BitVec::<Lsb0, u64>::new();
                 let s = String::from(value);
                 for c in s.chars() {
-                    let bit: bool = c.to_digit(2).unwrap() == 1;
+                    let bit = match c {
+                        '0' => false,
+                        '1' => true,
+                        _ => {
+                            return Err(E::invalid_value(
+                                de::Unexpected::Str(value),
+                                &self,
+                            ))
+                        }
+                    };
                     vec.insert(0, bit)
                 }
                 Ok(vec)
@@ -0,0 +1,441 @@
+//! Taint tracking of the values read from selected inputs.
+//!
+//! A taint configuration marks memories and input ports of the main
+//! component with labels. While the program runs, every port carries the set
+//! of labels of the inputs its value was computed from:
+//! - An assignment taints its destination with the labels of its source and
+//!   of the ports read by its guard.
+//! - The outputs of combinational primitives carry the labels of all their
+//!   inputs.
+//! - Stateful primitives hold the labels of the values written to them. A
+//!   write replaces the labels held by a register but is added to the labels
+//!   held by a memory since other addresses keep their contents. The outputs
+//!   of a stateful primitive carry the labels it holds along with the labels
+//!   of its address ports.
+//! - Instances of components are treated as memories that hold the labels of
+//!   every input they have seen since they were reset.
+//!
+//! The labels of the outputs and memories of the main component at the end
+//! of the run show which outputs depend on which inputs, which can be used to
+//! check that logical channels sharing an accelerator stay isolated.
+//!
+//! The configuration is a JSON file that maps labels to the names of the
+//! memories and input ports they mark:
+//! ```json
+//! { "a": ["mem_a", "in_a"], "b": ["mem_b"] }
+//! ```
+use crate::environment::InterpreterState;
+use crate::errors::{InterpreterError, InterpreterResult};
+use crate::interpreter_ir as iir;
+use crate::names::ComponentQIN;
+use crate::primitives::{Primitive, Serializeable};
+use crate::values::Value;
+use calyx::ir;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Labels of the inputs a value was computed from.
+pub type Taint = BTreeSet<String>;
+
+/// Labels tracked by a simulation. It is shared by the environments of the
+/// simulation.
+pub type SharedTaint = Rc<RefCell<TaintState>>;
+
+/// Labels of the sources, ports, and stateful cells of a program. Ports and
+/// cells are identified by the instance of the component they belong to and
+/// their address, so every instance of a component carries its own labels.
+pub struct TaintState {
+    /// Labels of the memories and input ports of the main component keyed
+    /// by their name.
+    sources: HashMap<String, Taint>,
+    /// Labels carried by the ports of each instance.
+    ports: HashMap<ComponentQIN, HashMap<usize, Taint>>,
+    /// Labels held by the stateful cells of each instance.
+    cells: HashMap<ComponentQIN, HashMap<usize, Taint>>,
+}
+
+impl TaintState {
+    /// Track the labels of the memories and ports named in the
+    /// configuration file at `path`.
+    pub fn read(path: &Path) -> InterpreterResult<SharedTaint> {
+        let to_err = |err: &dyn std::fmt::Display| {
+            InterpreterError::TaintConfigError(format!(
+                "failed to read {}: {}",
+                path.to_string_lossy(),
+                err
+            ))
+        };
+        let buf = fs::read(path).map_err(|err| to_err(&err))?;
+        let config: HashMap<String, Vec<String>> =
+            serde_json::from_slice(&buf).map_err(|err| to_err(&err))?;
+        let mut sources: HashMap<String, Taint> = HashMap::new();
+        for (label, names) in config {
+            for name in names {
+                sources.entry(name).or_default().insert(label.clone());
+            }
+        }
+        Ok(Rc::new(RefCell::new(Self {
+            sources,
+            ports: HashMap::new(),
+            cells: HashMap::new(),
+        })))
+    }
+
+    fn lookup(&self, qin: &ComponentQIN, port: usize) -> Taint {
+        self.ports
+            .get(qin)
+            .and_then(|ports| ports.get(&port))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn set(&mut self, qin: &ComponentQIN, port: usize, taint: Taint) {
+        if taint.is_empty() {
+            if let Some(ports) = self.ports.get_mut(qin) {
+                ports.remove(&port);
+            }
+        } else {
+            self.ports
+                .entry(qin.clone())
+                .or_default()
+                .insert(port, taint);
+        }
+    }
+
+    fn hold(&mut self, qin: &ComponentQIN, cell: usize, taint: Taint) {
+        self.cells
+            .entry(qin.clone())
+            .or_default()
+            .insert(cell, taint);
+    }
+}
+
+fn addr(port: &ir::RRC<ir::Port>) -> usize {
+    port.as_ptr() as *const ir::Port as usize
+}
+
+/// Mark the input ports of the main component named in the configuration,
+/// if taint is tracked by the simulation of `env`.
+pub fn init(env: &InterpreterState) -> InterpreterResult<()> {
+    let state = match &env.instruments.taint {
+        Some(state) => state,
+        None => return Ok(()),
+    };
+    let mut state = state.borrow_mut();
+    let comp = &env.component;
+    let sig = comp.signature.borrow();
+    let sources = state.sources.clone();
+    for (name, taint) in sources {
+        // Inputs of the component are outputs of its signature.
+        let input = sig
+            .find(&name)
+            .filter(|port| port.borrow().direction == ir::Direction::Output);
+        let is_cell =
+            comp.cells.iter().any(|cell| cell.borrow().name() == &name);
+        if let Some(port) = input {
+            state.set(&env.qin, addr(&port), taint);
+        } else if !is_cell {
+            return Err(InterpreterError::TaintConfigError(format!(
+                "`{}` is neither an input nor a cell of component `{}`",
+                name, comp.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Taint the destination of `assign` in the instance of `env`, or clear its
+/// labels if no assignment to `dst` is active.
+pub(crate) fn assign(
+    env: &InterpreterState,
+    dst: &ir::RRC<ir::Port>,
+    assign: Option<&ir::Assignment>,
+) {
+    let mut state = match &env.instruments.taint {
+        Some(state) => state.borrow_mut(),
+        None => return,
+    };
+    let taint = match assign {
+        Some(assign) => {
+            let mut taint = state.lookup(&env.qin, addr(&assign.src));
+            for port in assign.guard.all_ports() {
+                taint.extend(state.lookup(&env.qin, addr(&port)));
+            }
+            taint
+        }
+        None => Taint::new(),
+    };
+    state.set(&env.qin, addr(dst), taint);
+}
+
+/// Wraps `prim`, a cell of the instance `qin`, so that it propagates the
+/// labels of its inputs to its outputs if taint is tracked.
+pub(crate) fn wrap(
+    cell: &ir::Cell,
+    prim: Box<dyn Primitive>,
+    taint: &Option<SharedTaint>,
+    qin: &ComponentQIN,
+) -> Box<dyn Primitive> {
+    let state = match taint {
+        Some(state) => state,
+        None => return prim,
+    };
+    // Inputs that must be driven to read the cell are the addresses of a
+    // memory.
+    let address = cell
+        .ports
+        .iter()
+        .filter(|port| {
+            let port = port.borrow();
+            port.direction == ir::Direction::Input
+                && port.attributes.has("read_together")
+        })
+        .map(|port| port.borrow().name.clone())
+        .collect::<HashSet<_>>();
+    let accumulate = match &cell.prototype {
+        ir::CellType::Primitive { .. } => !address.is_empty(),
+        _ => true,
+    };
+    // Only the cells of the main component are marked.
+    let source = if qin.len() == 1 {
+        state
+            .borrow()
+            .sources
+            .get(cell.name().as_ref())
+            .cloned()
+            .unwrap_or_default()
+    } else {
+        Taint::new()
+    };
+    let addr_cell = cell as *const ir::Cell as usize;
+    // The outputs of a marked cell are tainted before it is first executed
+    // so that readers evaluated earlier in the same cycle see its labels.
+    if !prim.is_comb() {
+        let mut state = state.borrow_mut();
+        state.hold(qin, addr_cell, source.clone());
+        for port in &cell.ports {
+            if port.borrow().direction == ir::Direction::Output {
+                state.set(qin, addr(port), source.clone());
+            }
+        }
+    }
+    Box::new(TaintedPrimitive {
+        prim,
+        state: Rc::clone(state),
+        qin: qin.clone(),
+        cell: addr_cell,
+        ports: cell
+            .ports
+            .iter()
+            .map(|port| (port.borrow().name.clone(), addr(port)))
+            .collect(),
+        accumulate,
+        address_ports: address,
+        held: source.clone(),
+        source,
+        pending: None,
+        address: Taint::new(),
+    })
+}
+
+/// A primitive whose outputs carry the labels of its inputs.
+struct TaintedPrimitive {
+    prim: Box<dyn Primitive>,
+    /// Labels tracked by the simulation.
+    state: SharedTaint,
+    /// The instance the cell belongs to.
+    qin: ComponentQIN,
+    /// Address of the cell.
+    cell: usize,
+    /// Addresses of the ports of the cell keyed by their name.
+    ports: HashMap<ir::Id, usize>,
+    /// Writes add to the labels held by the cell instead of replacing them.
+    accumulate: bool,
+    /// Names of the address ports of a memory.
+    address_ports: HashSet<ir::Id>,
+    /// Labels the cell was marked with in the configuration.
+    source: Taint,
+    /// Labels of the values held by the cell.
+    held: Taint,
+    /// Labels of the value being written in the current cycle.
+    pending: Option<Taint>,
+    /// Labels of the address ports in the current cycle.
+    address: Taint,
+}
+
+impl TaintedPrimitive {
+    fn input_taint<'a, I>(&self, inputs: I) -> Taint
+    where
+        I: Iterator<Item = &'a ir::Id>,
+    {
+        let state = self.state.borrow();
+        inputs
+            .filter_map(|id| self.ports.get(id))
+            .flat_map(|port| state.lookup(&self.qin, *port))
+            .collect()
+    }
+
+    /// Taint the ports in `outputs` with `taint`.
+    fn taint_outputs(&self, outputs: &[(ir::Id, Value)], taint: &Taint) {
+        let mut state = self.state.borrow_mut();
+        for (id, _) in outputs {
+            if let Some(port) = self.ports.get(id) {
+                state.set(&self.qin, *port, taint.clone());
+            }
+        }
+    }
+
+    fn held_outputs(&self) -> Taint {
+        self.held.union(&self.address).cloned().collect()
+    }
+
+    fn update_held(&mut self) {
+        if let Some(taint) = self.pending.take() {
+            self.held = if self.accumulate {
+                self.held.union(&taint).cloned().collect()
+            } else {
+                self.source.union(&taint).cloned().collect()
+            };
+        }
+        self.state
+            .borrow_mut()
+            .hold(&self.qin, self.cell, self.held.clone());
+    }
+}
+
+impl Primitive for TaintedPrimitive {
+    fn do_tick(&mut self) -> InterpreterResult<Vec<(ir::Id, Value)>> {
+        let out = self.prim.do_tick()?;
+        if !self.prim.is_comb() {
+            self.update_held();
+            self.taint_outputs(&out, &self.held_outputs());
+        }
+        Ok(out)
+    }
+
+    fn is_comb(&self) -> bool {
+        self.prim.is_comb()
+    }
+
+    fn validate(&self, inputs: &[(ir::Id, &Value)]) {
+        self.prim.validate(inputs)
+    }
+
+    fn execute(
+        &mut self,
+        inputs: &[(ir::Id, &Value)],
+    ) -> InterpreterResult<Vec<(ir::Id, Value)>> {
+        let out = self.prim.execute(inputs)?;
+        let taint = self.input_taint(inputs.iter().map(|(id, _)| id));
+        if self.prim.is_comb() {
+            self.taint_outputs(&out, &taint);
+            return Ok(out);
+        }
+        self.address = self.input_taint(
+            inputs
+                .iter()
+                .map(|(id, _)| id)
+                .filter(|id| self.address_ports.contains(*id)),
+        );
+        let writing = inputs
+            .iter()
+            .any(|(id, v)| (id == "write_en" || id == "go") && v.as_bool());
+        self.pending = if writing { Some(taint) } else { None };
+        self.taint_outputs(&out, &self.held_outputs());
+        Ok(out)
+    }
+
+    fn reset(
+        &mut self,
+        inputs: &[(ir::Id, &Value)],
+    ) -> InterpreterResult<Vec<(ir::Id, Value)>> {
+        self.pending = None;
+        self.address.clear();
+        if !self.accumulate {
+            self.held = self.source.clone();
+        }
+        self.prim.reset(inputs)
+    }
+
+    fn serialize(&self, signed: bool) -> Serializeable {
+        Primitive::serialize(self.prim.as_ref(), signed)
+    }
+
+    fn has_serializeable_state(&self) -> bool {
+        self.prim.has_serializeable_state()
+    }
+
+    fn get_state(&self) -> Option<crate::environment::StateView<'_>> {
+        self.prim.get_state()
+    }
+
+    fn get_comp_interpreter(
+        &self,
+    ) -> Option<&crate::interpreter::ComponentInterpreter> {
+        self.prim.get_comp_interpreter()
+    }
+
+    fn save_state(&self) -> InterpreterResult<Option<serde_json::Value>> {
+        self.prim.save_state()
+    }
+
+    fn restore_state(
+        &mut self,
+        state: serde_json::Value,
+    ) -> InterpreterResult<()> {
+        self.prim.restore_state(state)
+    }
+}
+
+/// Labels of the outputs and stateful cells of the main component.
+#[derive(Serialize)]
+pub struct TaintReport {
+    /// Labels of the output ports.
+    outputs: BTreeMap<String, Taint>,
+    /// Labels held by the memories, registers, and component instances.
+    cells: BTreeMap<String, Taint>,
+}
+
+impl TaintReport {
+    /// Build the report for the instance of `main` from the labels collected
+    /// so far.
+    pub fn new(main: &Rc<iir::Component>, state: &TaintState) -> Self {
+        let qin = ComponentQIN::new_single(main, &main.name);
+        let outputs = main
+            .signature
+            .borrow()
+            .ports
+            .iter()
+            // Outputs of the component are inputs of its signature.
+            .filter(|port| port.borrow().direction == ir::Direction::Input)
+            .map(|port| {
+                (
+                    port.borrow().name.to_string(),
+                    state.lookup(&qin, addr(port)),
+                )
+            })
+            .collect();
+        let cells = match state.cells.get(&qin) {
+            Some(held) => main
+                .cells
+                .iter()
+                .filter_map(|cell| {
+                    let taint =
+                        held.get(&(cell.as_ptr() as *const ir::Cell as usize))?;
+                    Some((cell.borrow().name().to_string(), taint.clone()))
+                })
+                .collect(),
+            None => BTreeMap::new(),
+        };
+        Self { outputs, cells }
+    }
+
+    /// The report as a JSON object.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
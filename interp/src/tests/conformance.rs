@@ -0,0 +1,325 @@
+//! Conformance tests that compare the interpreter models of the combinational
+//! primitives with their Verilog implementations.
+//!
+//! Every primitive is driven with corner cases and random inputs at several
+//! widths. The Verilog implementations are simulated with Icarus Verilog and
+//! the tests are skipped if `iverilog` is not installed. Stateful primitives
+//! are not covered since their models are cycle-approximate.
+//!
+//! Run them with `cargo test --features conformance`.
+use crate::environment::InterpreterState;
+use crate::values::Value;
+use calyx::ir;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Number of random input vectors for each primitive and width.
+const VECTORS: usize = 200;
+
+/// Widths at which every primitive is tested.
+const WIDTHS: &[u64] = &[1, 3, 8, 32, 64];
+
+/// Verilog implementations of the primitives, relative to the interpreter.
+const LIBRARIES: &[&str] =
+    &["../primitives/core.sv", "../primitives/binary_operators.sv"];
+
+/// Primitives with two inputs of the same width.
+const BINARY: &[(&str, bool)] = &[
+    // (name, has a single bit output)
+    ("std_add", false),
+    ("std_sub", false),
+    ("std_and", false),
+    ("std_or", false),
+    ("std_xor", false),
+    ("std_lsh", false),
+    ("std_rsh", false),
+    ("std_gt", true),
+    ("std_lt", true),
+    ("std_ge", true),
+    ("std_le", true),
+    ("std_eq", true),
+    ("std_neq", true),
+    ("std_sadd", false),
+    ("std_ssub", false),
+    ("std_slsh", false),
+    ("std_srsh", false),
+    ("std_sgt", true),
+    ("std_slt", true),
+    ("std_sge", true),
+    ("std_sle", true),
+    ("std_seq", true),
+    ("std_sneq", true),
+];
+
+/// Primitives with a single input of the same width as their output.
+const UNARY: &[&str] = &["std_not", "std_wire"];
+
+/// A primitive instantiated with concrete parameters.
+struct Case {
+    name: &'static str,
+    params: Vec<(&'static str, u64)>,
+    inputs: Vec<(&'static str, u64)>,
+    out_width: u64,
+}
+
+impl Case {
+    /// Every primitive at every width.
+    fn all() -> Vec<Case> {
+        let mut cases = vec![];
+        for &width in WIDTHS {
+            for &(name, is_bit) in BINARY {
+                cases.push(Case {
+                    name,
+                    params: vec![("WIDTH", width)],
+                    inputs: vec![("left", width), ("right", width)],
+                    out_width: if is_bit { 1 } else { width },
+                });
+            }
+            for &name in UNARY {
+                cases.push(Case {
+                    name,
+                    params: vec![("WIDTH", width)],
+                    inputs: vec![("in", width)],
+                    out_width: width,
+                });
+            }
+            let half = (width / 2).max(1);
+            cases.push(Case {
+                name: "std_slice",
+                params: vec![("IN_WIDTH", width), ("OUT_WIDTH", half)],
+                inputs: vec![("in", width)],
+                out_width: half,
+            });
+            cases.push(Case {
+                name: "std_pad",
+                params: vec![("IN_WIDTH", half), ("OUT_WIDTH", width)],
+                inputs: vec![("in", half)],
+                out_width: width,
+            });
+        }
+        cases
+    }
+
+    fn label(&self) -> String {
+        let params = self
+            .params
+            .iter()
+            .map(|(_, val)| val.to_string())
+            .collect::<Vec<_>>();
+        format!("{}({})", self.name, params.join(", "))
+    }
+
+    /// The output of the interpreter model for each vector.
+    fn model(&self, vectors: &[Vec<u64>]) -> Vec<u64> {
+        let params: ir::Binding = self
+            .params
+            .iter()
+            .map(|(name, val)| (ir::Id::from(*name), *val))
+            .collect();
+        let mut prim = InterpreterState::make_primitive(
+            &self.name.into(),
+            &params,
+            &ir::StringBinding::default(),
+            None,
+            &None,
+        )
+        .unwrap_or_else(|err| panic!("{}: {}", self.label(), err));
+        vectors
+            .iter()
+            .map(|vector| {
+                let values = self
+                    .inputs
+                    .iter()
+                    .zip(vector)
+                    .map(|((_, width), val)| Value::from(*val, *width))
+                    .collect::<Vec<_>>();
+                let inputs = self
+                    .inputs
+                    .iter()
+                    .zip(&values)
+                    .map(|((name, _), val)| (ir::Id::from(*name), val))
+                    .collect::<Vec<_>>();
+                let outputs = prim
+                    .execute(&inputs)
+                    .unwrap_or_else(|err| panic!("{}: {}", self.label(), err));
+                outputs
+                    .into_iter()
+                    .find(|(name, _)| name == "out")
+                    .map(|(_, val)| val.as_u64() & mask(self.out_width))
+                    .unwrap_or_else(|| {
+                        panic!("{}: no output `out`", self.label())
+                    })
+            })
+            .collect()
+    }
+
+    /// A testbench that applies every vector and prints the output in hex.
+    fn testbench(&self, vectors: &[Vec<u64>]) -> String {
+        let mut tb = String::new();
+        writeln!(tb, "module tb;").unwrap();
+        for (name, width) in &self.inputs {
+            writeln!(tb, "  logic [{}:0] {};", width - 1, name).unwrap();
+        }
+        writeln!(tb, "  logic [{}:0] out;", self.out_width - 1).unwrap();
+        let params = self
+            .params
+            .iter()
+            .map(|(name, val)| format!(".{}({})", name, val))
+            .collect::<Vec<_>>();
+        let ports = self
+            .inputs
+            .iter()
+            .map(|(name, _)| name)
+            .chain(std::iter::once(&"out"))
+            .map(|name| format!(".{}({})", name, name))
+            .collect::<Vec<_>>();
+        writeln!(
+            tb,
+            "  {} #({}) dut({});",
+            self.name,
+            params.join(", "),
+            ports.join(", ")
+        )
+        .unwrap();
+        writeln!(tb, "  initial begin").unwrap();
+        for vector in vectors {
+            for ((name, width), val) in self.inputs.iter().zip(vector) {
+                write!(tb, "    {} = {}'h{:x};", name, width, val).unwrap();
+            }
+            writeln!(tb, " #1 $display(\"%h\", out);").unwrap();
+        }
+        writeln!(tb, "    $finish;\n  end\nendmodule").unwrap();
+        tb
+    }
+
+    /// The output of the Verilog implementation for each vector.
+    fn simulate(&self, vectors: &[Vec<u64>], dir: &Path) -> Vec<Option<u64>> {
+        let stem = self
+            .params
+            .iter()
+            .fold(self.name.to_string(), |stem, (_, val)| {
+                format!("{}_{}", stem, val)
+            });
+        let tb = dir.join(format!("{}_tb.sv", stem));
+        let exe = dir.join(format!("{}_tb", stem));
+        fs::write(&tb, self.testbench(vectors)).unwrap();
+        let status = Command::new("iverilog")
+            .args(&["-g2012", "-s", "tb", "-o"])
+            .arg(&exe)
+            .arg(&tb)
+            .args(LIBRARIES)
+            .status()
+            .unwrap();
+        assert!(status.success(), "{}: iverilog failed", self.label());
+        let output = Command::new("vvp").arg("-n").arg(&exe).output().unwrap();
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| u64::from_str_radix(line.trim(), 16).ok())
+            .collect()
+    }
+}
+
+fn mask(width: u64) -> u64 {
+    u64::MAX >> (64 - width)
+}
+
+/// Deterministic xorshift generator so that failures can be reproduced.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A value of `width` bits. One in four values is small so that shift
+    /// amounts are often in range.
+    fn value(&mut self, width: u64) -> u64 {
+        let val = self.next();
+        if val % 4 == 0 {
+            (val >> 2) % (width + 2) & mask(width)
+        } else {
+            val & mask(width)
+        }
+    }
+}
+
+/// Corner cases followed by random vectors for the inputs of `case`.
+fn vectors(case: &Case, rng: &mut Rng) -> Vec<Vec<u64>> {
+    let corners = |width: u64| {
+        let mut vals = vec![0, 1, mask(width), mask(width) >> 1];
+        vals.push(1 << (width - 1));
+        vals.into_iter().map(move |val| val & mask(width))
+    };
+    let mut vectors: Vec<Vec<u64>> = vec![vec![]];
+    for (_, width) in &case.inputs {
+        vectors = vectors
+            .into_iter()
+            .flat_map(|vector| {
+                corners(*width).map(move |val| {
+                    let mut vector = vector.clone();
+                    vector.push(val);
+                    vector
+                })
+            })
+            .collect();
+    }
+    for _ in 0..VECTORS {
+        vectors.push(
+            case.inputs
+                .iter()
+                .map(|(_, width)| rng.value(*width))
+                .collect(),
+        );
+    }
+    vectors
+}
+
+#[test]
+fn combinational_primitives() {
+    if Command::new("iverilog").arg("-V").output().is_err() {
+        eprintln!("Skipping primitive conformance tests: iverilog not found");
+        return;
+    }
+    let dir: PathBuf = std::env::temp_dir()
+        .join(format!("calyx-conformance-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let mut failures = vec![];
+    for case in Case::all() {
+        let vectors = vectors(&case, &mut rng);
+        let expected = case.simulate(&vectors, &dir);
+        let actual = case.model(&vectors);
+        assert_eq!(
+            expected.len(),
+            vectors.len(),
+            "{}: simulation printed the wrong number of outputs",
+            case.label()
+        );
+        for ((vector, verilog), model) in
+            vectors.iter().zip(expected).zip(actual)
+        {
+            if verilog != Some(model) {
+                failures.push(format!(
+                    "{} on inputs {:?}: Verilog gives {}, interpreter gives {}",
+                    case.label(),
+                    vector,
+                    verilog.map_or("x".to_string(), |val| val.to_string()),
+                    model
+                ));
+            }
+        }
+    }
+    fs::remove_dir_all(&dir).ok();
+    assert!(
+        failures.is_empty(),
+        "{} mismatches between the interpreter and Verilog:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
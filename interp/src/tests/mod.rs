@@ -1,3 +1,6 @@
 mod primitives;
 mod stk_env;
 mod values;
+
+#[cfg(feature = "conformance")]
+mod conformance;
@@ -840,6 +840,19 @@ fn test_std_pad_panic() {
         .validate_and_execute(&[("in".into(), &to_pad)])
         .unwrap();
 }
+#[test]
+fn test_std_wire() {
+    let val = Value::from(42, 8);
+    let mut std_wire = comb::StdWire::from_constants(8);
+    let res_wire = std_wire
+        .validate_and_execute(&[("in".into(), &val)])
+        .unwrap()
+        .into_iter()
+        .next()
+        .map(|(_, v)| v)
+        .unwrap();
+    assert_eq!(res_wire.as_u64(), 42);
+}
 /// Logical Operators
 #[test]
 fn test_std_not() {
@@ -881,6 +894,22 @@ fn test_std_not_panic() {
         .unwrap();
 }
 
+#[test]
+fn test_std_orr() {
+    let mut std_orr = comb::StdOrr::from_constants(4);
+    for (val, expected) in [(0, 0), (1, 1), (8, 1), (15, 1)] {
+        let input = Value::from(val, 4);
+        let res_orr = std_orr
+            .validate_and_execute(&[("in".into(), &input)])
+            .unwrap()
+            .into_iter()
+            .next()
+            .map(|(_, v)| v)
+            .unwrap();
+        assert_eq!(res_orr.as_u64(), expected);
+    }
+}
+
 #[test]
 fn test_std_and() {
     //101: [1100101], 78: [1001110] & -> [1000100] which is 68
@@ -208,6 +208,68 @@ fn smoosher_diff_other() {
     assert!(!diff_2.contains_key(&"joseph"));
 }
 
+#[test]
+fn smoosher_iter() {
+    let mut smoosher = Smoosher::new();
+    smoosher.set("alma", 18);
+    smoosher.set("joseph", 19);
+    smoosher.new_scope();
+    smoosher.set("jonathan", 14);
+    smoosher.new_scope();
+    smoosher.new_scope();
+    smoosher.set("joseph", 436);
+    smoosher.set("ari", 12);
+    let mut bindings: Vec<_> = smoosher.iter().collect();
+    bindings.sort();
+    assert_eq!(
+        bindings,
+        vec![
+            (&"alma", &18),
+            (&"ari", &12),
+            (&"jonathan", &14),
+            (&"joseph", &436)
+        ]
+    );
+    assert_eq!(smoosher.iter().collect::<HashMap<_, _>>(), smoosher.to_hm());
+}
+
+#[test]
+fn smoosher_iter_fork() {
+    let mut smoosher = Smoosher::new();
+    smoosher.set("alma", 18);
+    smoosher.set("jonathan", 14);
+    let mut smoosher2 = smoosher.fork();
+    smoosher.set("alma", 19);
+    smoosher2.set("jonathan", 15);
+    smoosher2.set("ari", 12);
+    let hm = smoosher.iter().collect::<HashMap<_, _>>();
+    assert_eq!(hm.len(), 2);
+    assert_eq!(**hm.get(&"alma").unwrap(), 19);
+    assert_eq!(**hm.get(&"jonathan").unwrap(), 14);
+    let hm2 = smoosher2.iter().collect::<HashMap<_, _>>();
+    assert_eq!(hm2.len(), 3);
+    assert_eq!(**hm2.get(&"alma").unwrap(), 18);
+    assert_eq!(**hm2.get(&"jonathan").unwrap(), 15);
+    assert_eq!(**hm2.get(&"ari").unwrap(), 12);
+}
+
+#[test]
+fn smoosher_len_contains_key() {
+    let mut smoosher = Smoosher::new();
+    assert_eq!(smoosher.len(), 0);
+    assert!(smoosher.is_empty());
+    smoosher.set("alma", 18);
+    smoosher.new_scope();
+    smoosher.new_scope();
+    assert!(!smoosher.is_empty());
+    smoosher.set("alma", 19);
+    smoosher.set("ari", 12);
+    assert_eq!(smoosher.len(), 2);
+    assert!(smoosher.contains_key(&"alma"));
+    assert!(smoosher.contains_key(&"ari"));
+    assert!(!smoosher.contains_key(&"jonathan"));
+}
+
 mod values_stk_env_test {
     #[allow(unused)]
     use crate::structures::stk_env::Smoosher;
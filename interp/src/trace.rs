@@ -0,0 +1,181 @@
+//! Human-readable trace of the changes to selected groups and cells.
+//!
+//! A trace filter is a comma-separated list of terms that select the groups
+//! and cells to trace:
+//! - `group=RE` and `cell=RE` trace the groups and cells whose name matches
+//!   the regular expression `RE`.
+//! - `group!=RE` and `cell!=RE` exclude the groups and cells whose name
+//!   matches `RE`. If a kind of entity is only excluded, every other entity
+//!   of that kind is traced.
+//!
+//! Names are either plain names like `acc` or qualified with their component
+//! like `main::acc`, and the regular expressions must match one of them
+//! completely:
+//! ```text
+//! group=main::do_add,cell=acc|sum,cell!=main::.*_tmp
+//! ```
+//! Every change to the value of a port of a traced cell, or to the `go` and
+//! `done` holes of a traced group, is printed to stderr under the cycle in
+//! which it happened. Changes are printed with the hierarchical name of the
+//! instance they happened in, like `main.acc0.sum.out`, and cycles are
+//! counted by that instance.
+use crate::errors::{InterpreterError, InterpreterResult};
+use crate::interpreter_ir as iir;
+use crate::names::ComponentQIN;
+use crate::values::Value;
+use calyx::ir;
+use regex::Regex;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// The entities of one kind selected by a trace filter.
+#[derive(Default)]
+struct Selection {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl Selection {
+    /// Returns true if the entity called `name` in `comp` is traced.
+    fn selects(&self, comp: &ir::Id, name: &ir::Id) -> bool {
+        if self.include.is_empty() && self.exclude.is_empty() {
+            return false;
+        }
+        let qualified = format!("{}::{}", comp, name);
+        let matches =
+            |re: &Regex| re.is_match(name.as_ref()) || re.is_match(&qualified);
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+/// The groups and cells selected by a trace filter.
+#[derive(Default)]
+struct Filter {
+    groups: Selection,
+    cells: Selection,
+}
+
+impl Filter {
+    fn parse(spec: &str) -> InterpreterResult<Self> {
+        let mut filter = Filter::default();
+        for term in spec.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            let (kind, pattern, exclude) = match term.split_once("!=") {
+                Some((kind, pattern)) => (kind, pattern, true),
+                None => match term.split_once('=') {
+                    Some((kind, pattern)) => (kind, pattern, false),
+                    None => {
+                        return Err(InterpreterError::TraceError(format!(
+                            "expected `KIND=RE` or `KIND!=RE`, found `{}`",
+                            term
+                        )))
+                    }
+                },
+            };
+            let selection = match kind.trim() {
+                "group" => &mut filter.groups,
+                "cell" => &mut filter.cells,
+                kind => {
+                    return Err(InterpreterError::TraceError(format!(
+                        "unknown kind `{}` in `{}`, expected `group` or `cell`",
+                        kind, term
+                    )))
+                }
+            };
+            let re = Regex::new(&format!("^(?:{})$", pattern.trim())).map_err(
+                |err| {
+                    InterpreterError::TraceError(format!(
+                        "invalid regular expression in `{}`: {}",
+                        term, err
+                    ))
+                },
+            )?;
+            if exclude {
+                selection.exclude.push(re);
+            } else {
+                selection.include.push(re);
+            }
+        }
+        Ok(filter)
+    }
+}
+
+/// Trace printed by a simulation. It is shared by the environments of the
+/// simulation.
+pub type SharedTrace = Rc<RefCell<Trace>>;
+
+/// The traced ports of a program.
+pub struct Trace {
+    /// Names of the traced ports within their component keyed by their
+    /// address.
+    ports: HashMap<usize, String>,
+    /// Cycle of the last change that was printed.
+    cycle: Option<u64>,
+}
+
+fn addr(port: &ir::RRC<ir::Port>) -> usize {
+    port.as_ptr() as *const ir::Port as usize
+}
+
+impl Trace {
+    /// Trace the groups and cells of `components` selected by the filter
+    /// `spec`.
+    pub fn new(
+        spec: &str,
+        components: &iir::ComponentCtx,
+    ) -> InterpreterResult<SharedTrace> {
+        let filter = Filter::parse(spec)?;
+        let mut ports = HashMap::new();
+        for comp in components.iter() {
+            for group in comp.groups.iter() {
+                let group = group.borrow();
+                if filter.groups.selects(&comp.name, group.name()) {
+                    for hole in &group.holes {
+                        let name =
+                            format!("{}[{}]", group.name(), hole.borrow().name);
+                        ports.insert(addr(hole), name);
+                    }
+                }
+            }
+            for cell in comp.cells.iter() {
+                let cell = cell.borrow();
+                if filter.cells.selects(&comp.name, cell.name()) {
+                    for port in &cell.ports {
+                        let name =
+                            format!("{}.{}", cell.name(), port.borrow().name);
+                        ports.insert(addr(port), name);
+                    }
+                }
+            }
+        }
+        Ok(Rc::new(RefCell::new(Self { ports, cycle: None })))
+    }
+
+    /// Print the change of `port` of the instance `qin` from `old` to `new`
+    /// in `cycle` if the port is traced.
+    pub(crate) fn record(
+        &mut self,
+        qin: &ComponentQIN,
+        port: *const ir::Port,
+        cycle: u64,
+        old: &Value,
+        new: &Value,
+    ) {
+        let name = match self.ports.get(&(port as usize)) {
+            Some(name) => name,
+            None => return,
+        };
+        if self.cycle != Some(cycle) {
+            eprintln!("cycle {}:", cycle);
+            self.cycle = Some(cycle);
+        }
+        eprintln!(
+            "  {}.{}: {} -> {}",
+            qin,
+            name,
+            old.as_unsigned(),
+            new.as_unsigned()
+        );
+    }
+}
@@ -1,49 +1,15 @@
 use crate::values::Value;
 use calyx::errors::Error;
-use calyx::ir::{self, Assignment, Binding, Id, Port, RRC};
+use calyx::ir::{self, Binding, Id, RRC};
 use serde::Deserialize;
 use std::cell::Ref;
 use std::collections::HashMap;
 use std::fs;
-use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
 use std::ops::{Deref, DerefMut};
 use std::path::PathBuf;
 use std::rc::Rc;
 
-/// A wrapper to enable hashing of assignments by their destination port.
-pub(super) struct PortAssignment<'a>(*const Port, &'a Assignment);
-
-impl<'a> Hash for PortAssignment<'a> {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.0.hash(state);
-    }
-}
-
-impl<'a> PartialEq for PortAssignment<'a> {
-    fn eq(&self, other: &Self) -> bool {
-        std::ptr::eq(self.0, other.0)
-    }
-}
-
-impl<'a> Eq for PortAssignment<'a> {}
-
-impl<'a, 'b> PortAssignment<'a> {
-    /// Construct a new PortAssignment.
-    pub fn new(a_ref: &'a Assignment) -> Self {
-        Self(a_ref.dst.as_raw(), a_ref)
-    }
-
-    /// Get the associated port.
-    pub fn get_port(&self) -> *const Port {
-        self.0
-    }
-
-    /// Get the associated assignment.
-    pub fn get_assignment(&self) -> &Assignment {
-        self.1
-    }
-}
-
 /// A map representing all the identifiers and its associated values in a
 /// Futil program.
 #[derive(Debug, Deserialize)]
@@ -63,6 +29,12 @@ impl MemoryMap {
     }
 }
 
+impl FromIterator<(Id, Vec<Value>)> for MemoryMap {
+    fn from_iter<T: IntoIterator<Item = (Id, Vec<Value>)>>(iter: T) -> Self {
+        MemoryMap(iter.into_iter().collect())
+    }
+}
+
 impl Deref for MemoryMap {
     type Target = HashMap<Id, Vec<Value>>;
 
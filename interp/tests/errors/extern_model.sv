@@ -0,0 +1,20 @@
+module my_accel #(
+    parameter WIDTH = 32
+) (
+   input wire logic go,
+   input wire logic [WIDTH-1:0] in,
+   input wire logic clk,
+   input wire logic reset,
+   output logic [WIDTH-1:0] out,
+   output logic done
+);
+  always_ff @(posedge clk) begin
+    if (reset) begin
+      out <= 0;
+      done <= 0;
+    end else begin
+      out <= in + 1;
+      done <= go;
+    end
+  end
+endmodule
@@ -0,0 +1,428 @@
+//! Analysis of a single open document.
+use calyx::{
+    errors::{CalyxResult, Error, Span},
+    frontend::{self, ast, LibraryConf},
+    ir,
+    pass_manager::PassManager,
+};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, Location, Position, Range, Url,
+};
+use std::path::{Path, PathBuf};
+
+/// A Calyx program opened in the editor along with the information needed to
+/// answer queries about it.
+pub struct Document {
+    path: PathBuf,
+    src: String,
+    /// Components of the program and the files it imports. Empty if the
+    /// program could not be parsed.
+    components: Vec<ast::ComponentDef>,
+    /// Primitives defined by the imported libraries.
+    primitives: Vec<ir::Primitive>,
+    /// The IR of the program if it could be built.
+    ctx: Option<ir::Context>,
+    /// The first error in the program.
+    error: Option<Error>,
+}
+
+/// Name under the cursor. `qualifier` is the cell or group before the name
+/// if the name refers to one of its ports, such as `r` in `r.in`.
+struct Reference<'a> {
+    name: &'a str,
+    qualifier: Option<&'a str>,
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+impl Document {
+    /// Analyze the source `src` of the program stored at `path`. The
+    /// imports are found using `conf`.
+    pub fn new(path: PathBuf, src: String, conf: &LibraryConf) -> Self {
+        let mut doc = Document {
+            path,
+            src,
+            components: vec![],
+            primitives: vec![],
+            ctx: None,
+            error: None,
+        };
+        if let Err(err) = doc.analyze(conf) {
+            doc.error = Some(err);
+        }
+        doc
+    }
+
+    /// Build the IR of the program and run the lints on it.
+    fn analyze(&mut self, conf: &LibraryConf) -> CalyxResult<()> {
+        let ws = frontend::Workspace::construct_from_source(
+            &self.src, &self.path, conf,
+        )?;
+        self.components = ws.components.clone();
+        self.primitives = ws
+            .externs
+            .iter()
+            .flat_map(|(_, prims)| prims.iter().cloned())
+            .collect();
+        let mut ctx = ir::from_ast::ast_to_ir(ws, ir::BackendConf::default())?;
+        let result = PassManager::default_passes()?.execute_analyses(
+            &mut ctx,
+            &["lint".to_string()],
+            &[],
+        );
+        self.ctx = Some(ctx);
+        result
+    }
+
+    /// Byte offset of `pos` in the source.
+    fn offset(&self, pos: Position) -> Option<usize> {
+        let mut offset = 0;
+        for (idx, line) in self.src.split('\n').enumerate() {
+            if idx == pos.line as usize {
+                let col = line
+                    .char_indices()
+                    .nth(pos.character as usize)
+                    .map_or(line.len(), |(col, _)| col);
+                return Some(offset + col);
+            }
+            offset += line.len() + 1;
+        }
+        None
+    }
+
+    /// Position of the byte offset `offset` in `src`.
+    fn position(src: &str, offset: usize) -> Position {
+        let before = &src[..offset.min(src.len())];
+        let line = before.matches('\n').count();
+        let start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        Position::new(line as u32, before[start..].chars().count() as u32)
+    }
+
+    /// Location of the span in the editor.
+    fn location(span: &Span) -> Option<Location> {
+        let (start, end) = span.range();
+        let src = span.input();
+        let path = Path::new(span.file());
+        let uri = if path.is_absolute() {
+            Url::from_file_path(path)
+        } else {
+            Url::from_file_path(path.canonicalize().ok()?)
+        }
+        .ok()?;
+        Some(Location::new(
+            uri,
+            Range::new(Self::position(src, start), Self::position(src, end)),
+        ))
+    }
+
+    /// The name at `pos`.
+    fn reference(&self, pos: Position) -> Option<Reference<'_>> {
+        let offset = self.offset(pos)?;
+        let start = self.src[..offset]
+            .rfind(|c| !is_ident_char(c))
+            .map_or(0, |idx| idx + 1);
+        let end = self.src[offset..]
+            .find(|c| !is_ident_char(c))
+            .map_or(self.src.len(), |idx| offset + idx);
+        if start == end {
+            return None;
+        }
+        let qualifier = self.src[..start].strip_suffix('.').map(|before| {
+            let start =
+                before.rfind(|c| !is_ident_char(c)).map_or(0, |idx| idx + 1);
+            &before[start..]
+        });
+        Some(Reference {
+            name: &self.src[start..end],
+            qualifier,
+        })
+    }
+
+    /// The component of this document that contains `pos`.
+    fn component_at(&self, pos: Position) -> Option<&ast::ComponentDef> {
+        let offset = self.offset(pos)?;
+        let path = self.path.to_string_lossy();
+        self.components
+            .iter()
+            .filter_map(|comp| {
+                let span = comp.name.span()?;
+                let (start, _) = span.range();
+                (span.file() == path && start <= offset)
+                    .then_some((start, comp))
+            })
+            .max_by_key(|(start, _)| *start)
+            .map(|(_, comp)| comp)
+    }
+
+    /// Diagnostics for the errors in the program.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let err = match &self.error {
+            Some(err) => err,
+            None => return vec![],
+        };
+        let diag = err.diagnostic();
+        let path = self.path.to_string_lossy();
+        let range = match &diag.location {
+            Some(loc) if loc.file.as_deref().is_none_or(|f| f == path) => {
+                let start = Position::new(
+                    loc.line.saturating_sub(1) as u32,
+                    loc.column.saturating_sub(1) as u32,
+                );
+                // Highlight the name at the location.
+                let len = self
+                    .offset(start)
+                    .map(|offset| {
+                        self.src[offset..]
+                            .find(|c| !is_ident_char(c))
+                            .unwrap_or(self.src.len() - offset)
+                    })
+                    .unwrap_or(0)
+                    .max(1);
+                Range::new(
+                    start,
+                    Position::new(start.line, start.character + len as u32),
+                )
+            }
+            // Errors in other files are shown at the start of the document.
+            _ => Range::default(),
+        };
+        let mut message = diag.message;
+        if let Some(file) = diag.location.and_then(|loc| loc.file) {
+            if file != path {
+                message = format!("{}: {}", file, message);
+            }
+        }
+        vec![Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(lsp_types::NumberOrString::String(
+                diag.code.to_string(),
+            )),
+            source: Some("calyx".to_string()),
+            message,
+            ..Default::default()
+        }]
+    }
+
+    /// Location of the definition of the cell, group, component, or
+    /// primitive at `pos`.
+    pub fn definition(&self, pos: Position) -> Option<Location> {
+        let reference = self.reference(pos)?;
+        if reference.qualifier.is_some() {
+            return None;
+        }
+        let name = reference.name;
+        let local = self.component_at(pos).and_then(|comp| {
+            comp.cells
+                .iter()
+                .map(|cell| &cell.name)
+                .chain(comp.groups.iter().map(|group| &group.name))
+                .find(|id| *id == name)
+        });
+        let id = local
+            .or_else(|| {
+                self.components
+                    .iter()
+                    .map(|comp| &comp.name)
+                    .find(|id| *id == name)
+            })
+            .or_else(|| {
+                self.primitives
+                    .iter()
+                    .map(|prim| &prim.name)
+                    .find(|id| *id == name)
+            })?;
+        Self::location(id.span()?)
+    }
+
+    /// Description of the port, cell, component, or primitive at `pos`.
+    pub fn hover(&self, pos: Position) -> Option<String> {
+        let reference = self.reference(pos)?;
+        let ctx = self.ctx.as_ref()?;
+        let comp = self.component_at(pos).and_then(|comp| {
+            ctx.components.iter().find(|c| c.name == comp.name)
+        });
+
+        // Port of a cell or of the component.
+        if let Some(qualifier) = reference.qualifier {
+            let cell = comp?.find_cell(&qualifier)?;
+            let port = cell.borrow().find(reference.name)?;
+            let port = port.borrow();
+            return Some(format!(
+                "{}.{}: {} ({})",
+                qualifier,
+                port.name,
+                port.width,
+                direction(&port.direction)
+            ));
+        }
+
+        if let Some(comp) = comp {
+            if let Some(cell) = comp.find_cell(&reference.name) {
+                return Some(describe_cell(&cell.borrow()));
+            }
+            if let Some(port) = comp.signature.borrow().find(reference.name) {
+                let port = port.borrow();
+                // Signature ports are reversed inside the component.
+                return Some(format!(
+                    "{}: {} ({})",
+                    port.name,
+                    port.width,
+                    direction(&port.direction.reverse())
+                ));
+            }
+        }
+        if let Some(comp) =
+            ctx.components.iter().find(|c| c.name == reference.name)
+        {
+            return Some(describe_component(comp));
+        }
+        let prim = ctx.lib.find_primitive(reference.name)?;
+        let mut out = vec![];
+        ir::IRPrinter::write_primitive(prim, 0, &mut out).ok()?;
+        String::from_utf8(out).ok().map(|s| s.trim().to_string())
+    }
+}
+
+fn direction(dir: &ir::Direction) -> &'static str {
+    match dir {
+        ir::Direction::Input => "input",
+        ir::Direction::Output => "output",
+        ir::Direction::Inout => "inout",
+    }
+}
+
+/// Signature of `ports` where the ports with direction `input` are listed
+/// first.
+fn describe_ports<'a>(
+    ports: impl Iterator<Item = &'a ir::RRC<ir::Port>> + Clone,
+    input: ir::Direction,
+) -> String {
+    let list = |inputs: bool| {
+        ports
+            .clone()
+            .filter(|port| (port.borrow().direction == input) == inputs)
+            .map(|port| {
+                let port = port.borrow();
+                format!("{}: {}", port.name, port.width)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!("({}) -> ({})", list(true), list(false))
+}
+
+/// The prototype of `cell` and the widths of its ports.
+fn describe_cell(cell: &ir::Cell) -> String {
+    let proto = match &cell.prototype {
+        ir::CellType::Primitive {
+            name,
+            param_binding,
+            ..
+        } => format!(
+            "{}({})",
+            name,
+            param_binding
+                .iter()
+                .map(|(_, val)| val.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        ir::CellType::Component { name } => format!("{}()", name),
+        ir::CellType::ThisComponent => "this".to_string(),
+        ir::CellType::Constant { val, width } => format!("{}'d{}", width, val),
+    };
+    format!(
+        "{} = {}\n{}",
+        cell.name(),
+        proto,
+        describe_ports(cell.ports.iter(), ir::Direction::Input)
+    )
+}
+
+/// The signature of `comp`.
+fn describe_component(comp: &ir::Component) -> String {
+    // Signature ports are reversed inside the component.
+    format!(
+        "component {}{}",
+        comp.name,
+        describe_ports(
+            comp.signature.borrow().ports.iter(),
+            ir::Direction::Output
+        )
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(src: &str) -> Document {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let root = root.canonicalize().unwrap();
+        Document::new(
+            root.join("tests/check/lsp.futil"),
+            src.to_string(),
+            &root.as_path().into(),
+        )
+    }
+
+    const SRC: &str = "import \"primitives/core.futil\";
+component main() -> () {
+  cells {
+    r = std_reg(32);
+  }
+  wires {
+    group write {
+      r.in = 32'd1;
+      r.write_en = 1'd1;
+      write[done] = r.done;
+    }
+  }
+  control {
+    write;
+  }
+}
+";
+
+    #[test]
+    fn definition() {
+        let doc = document(SRC);
+        assert!(doc.diagnostics().is_empty());
+        let cell = doc.definition(Position::new(7, 6)).unwrap();
+        assert_eq!(
+            cell.range,
+            Range::new(Position::new(3, 4), Position::new(3, 5))
+        );
+        let group = doc.definition(Position::new(13, 5)).unwrap();
+        assert_eq!(
+            group.range,
+            Range::new(Position::new(6, 10), Position::new(6, 15))
+        );
+        let prim = doc.definition(Position::new(3, 10)).unwrap();
+        assert!(prim.uri.path().ends_with("primitives/core.futil"));
+    }
+
+    #[test]
+    fn hover() {
+        let doc = document(SRC);
+        assert_eq!(doc.hover(Position::new(7, 8)).unwrap(), "r.in: 32 (input)");
+        assert!(doc
+            .hover(Position::new(7, 6))
+            .unwrap()
+            .starts_with("r = std_reg(32)"));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let doc = document(&SRC.replace("      r.write_en = 1'd1;\n", ""));
+        let diags = doc.diagnostics();
+        assert_eq!(diags.len(), 1);
+        assert_eq!(
+            diags[0].range,
+            Range::new(Position::new(6, 10), Position::new(6, 15))
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Language server for Calyx programs.
+//!
+//! The server reports the errors found by the parser, the IR construction,
+//! and the `lint` passes as diagnostics, jumps to the definitions of cells,
+//! groups, components, and primitives, and shows the widths of ports and the
+//! signatures of cells, components, and primitives on hover. It communicates
+//! with the editor over stdin and stdout.
+//!
+//! Imports are searched in the directories given by the `libPaths`
+//! initialization option, which default to the root of the workspace.
+mod document;
+
+use calyx::frontend::LibraryConf;
+use document::Document;
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{GotoDefinition, HoverRequest, Request as _},
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, InitializeParams, MarkedString,
+    OneOf, PublishDiagnosticsParams, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+
+type ServerResult<T> = Result<T, Box<dyn Error + Sync + Send>>;
+
+/// State of the server.
+struct Server {
+    connection: Connection,
+    conf: LibraryConf,
+    /// Documents that are open in the editor.
+    documents: HashMap<Url, Document>,
+}
+
+fn main() -> ServerResult<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = serde_json::to_value(ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::FULL,
+        )),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..Default::default()
+    })?;
+    let params: InitializeParams =
+        serde_json::from_value(connection.initialize(capabilities)?)?;
+    let mut server = Server {
+        connection,
+        conf: library_conf(&params),
+        documents: HashMap::new(),
+    };
+    server.run()?;
+    // The writer thread exits once the connection is dropped.
+    drop(server);
+    io_threads.join()?;
+    Ok(())
+}
+
+/// Directories used to find the imports of programs.
+fn library_conf(params: &InitializeParams) -> LibraryConf {
+    let lib_paths = params
+        .initialization_options
+        .as_ref()
+        .and_then(|opts| opts.get("libPaths"))
+        .and_then(|paths| {
+            serde_json::from_value::<Vec<PathBuf>>(paths.clone()).ok()
+        })
+        .or_else(|| {
+            #[allow(deprecated)]
+            let root = params.root_uri.as_ref()?.to_file_path().ok()?;
+            Some(vec![root])
+        })
+        .unwrap_or_else(|| vec![PathBuf::from(".")]);
+    LibraryConf {
+        lib_paths,
+        cache_dir: None,
+    }
+}
+
+impl Server {
+    fn run(&mut self) -> ServerResult<()> {
+        while let Ok(msg) = self.connection.receiver.recv() {
+            match msg {
+                Message::Request(req) => {
+                    if self.connection.handle_shutdown(&req)? {
+                        return Ok(());
+                    }
+                    self.handle_request(req)?;
+                }
+                Message::Notification(not) => self.handle_notification(not)?,
+                Message::Response(_) => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, req: Request) -> ServerResult<()> {
+        let result = match req.method.as_str() {
+            GotoDefinition::METHOD => {
+                let params: GotoDefinitionParams =
+                    serde_json::from_value(req.params)?;
+                let pos = params.text_document_position_params;
+                let location = self
+                    .documents
+                    .get(&pos.text_document.uri)
+                    .and_then(|doc| doc.definition(pos.position));
+                serde_json::to_value(
+                    location.map(GotoDefinitionResponse::Scalar),
+                )?
+            }
+            HoverRequest::METHOD => {
+                let params: HoverParams = serde_json::from_value(req.params)?;
+                let pos = params.text_document_position_params;
+                let hover = self
+                    .documents
+                    .get(&pos.text_document.uri)
+                    .and_then(|doc| doc.hover(pos.position))
+                    .map(|text| Hover {
+                        contents: HoverContents::Scalar(
+                            MarkedString::LanguageString(
+                                lsp_types::LanguageString {
+                                    language: "calyx".to_string(),
+                                    value: text,
+                                },
+                            ),
+                        ),
+                        range: None,
+                    });
+                serde_json::to_value(hover)?
+            }
+            _ => serde_json::Value::Null,
+        };
+        self.connection
+            .sender
+            .send(Message::Response(Response::new_ok(req.id, result)))?;
+        Ok(())
+    }
+
+    fn handle_notification(&mut self, not: Notification) -> ServerResult<()> {
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    serde_json::from_value(not.params)?;
+                let doc = params.text_document;
+                self.update(doc.uri, doc.text)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    serde_json::from_value(not.params)?;
+                // The server asks for the full text on every change.
+                match params.content_changes.into_iter().last() {
+                    Some(change) => {
+                        self.update(params.text_document.uri, change.text)
+                    }
+                    None => Ok(()),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    serde_json::from_value(not.params)?;
+                self.documents.remove(&params.text_document.uri);
+                self.publish(params.text_document.uri, vec![])
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Analyze the new text of the document and publish its diagnostics.
+    fn update(&mut self, uri: Url, text: String) -> ServerResult<()> {
+        let path = match uri.to_file_path() {
+            Ok(path) => path,
+            // Only documents stored in files can import libraries.
+            Err(()) => return Ok(()),
+        };
+        let doc = Document::new(path, text, &self.conf);
+        let diagnostics = doc.diagnostics();
+        self.documents.insert(uri.clone(), doc);
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(
+        &self,
+        uri: Url,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+    ) -> ServerResult<()> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        self.connection.sender.send(Message::Notification(
+            Notification::new(PublishDiagnostics::METHOD.to_string(), params),
+        ))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+// SystemC models of the primitives in core.futil used by the SystemC backend.
+// Every value is an `sc_uint` so widths are limited to 64 bits.
+#ifndef CALYX_CORE_H
+#define CALYX_CORE_H
+
+#include <systemc.h>
+
+/// Primitives
+
+template <int IN_WIDTH, int OUT_WIDTH>
+struct std_slice : sc_module {
+  sc_in<sc_uint<IN_WIDTH> > in;
+  sc_out<sc_uint<OUT_WIDTH> > out;
+
+  SC_HAS_PROCESS(std_slice);
+  std_slice(sc_module_name name) : sc_module(name) {
+    SC_METHOD(eval);
+    sensitive << in;
+  }
+
+  void eval() { out.write(sc_uint<OUT_WIDTH>(in.read())); }
+};
+
+template <int IN_WIDTH, int OUT_WIDTH>
+struct std_pad : sc_module {
+  sc_in<sc_uint<IN_WIDTH> > in;
+  sc_out<sc_uint<OUT_WIDTH> > out;
+
+  SC_HAS_PROCESS(std_pad);
+  std_pad(sc_module_name name) : sc_module(name) {
+    SC_METHOD(eval);
+    sensitive << in;
+  }
+
+  void eval() { out.write(sc_uint<OUT_WIDTH>(in.read())); }
+};
+
+template <int WIDTH>
+struct std_wire : sc_module {
+  sc_in<sc_uint<WIDTH> > in;
+  sc_out<sc_uint<WIDTH> > out;
+
+  SC_HAS_PROCESS(std_wire);
+  std_wire(sc_module_name name) : sc_module(name) {
+    SC_METHOD(eval);
+    sensitive << in;
+  }
+
+  void eval() { out.write(in.read()); }
+};
+
+template <int WIDTH>
+struct std_not : sc_module {
+  sc_in<sc_uint<WIDTH> > in;
+  sc_out<sc_uint<WIDTH> > out;
+
+  SC_HAS_PROCESS(std_not);
+  std_not(sc_module_name name) : sc_module(name) {
+    SC_METHOD(eval);
+    sensitive << in;
+  }
+
+  void eval() { out.write(~in.read()); }
+};
+
+// Combinational primitives with `left` and `right` inputs.
+#define CALYX_BINARY_PRIMITIVE(NAME, OUT_WIDTH, EXPR)        \
+  template <int WIDTH>                                        \
+  struct NAME : sc_module {                                   \
+    sc_in<sc_uint<WIDTH> > left;                              \
+    sc_in<sc_uint<WIDTH> > right;                             \
+    sc_out<sc_uint<OUT_WIDTH> > out;                          \
+                                                              \
+    SC_HAS_PROCESS(NAME);                                     \
+    NAME(sc_module_name name) : sc_module(name) {             \
+      SC_METHOD(eval);                                        \
+      sensitive << left << right;                             \
+    }                                                         \
+                                                              \
+    void eval() {                                             \
+      sc_uint<WIDTH> l = left.read();                         \
+      sc_uint<WIDTH> r = right.read();                        \
+      out.write(sc_uint<OUT_WIDTH>(EXPR));                    \
+    }                                                         \
+  };
+
+/// Logical operators
+CALYX_BINARY_PRIMITIVE(std_and, WIDTH, l & r)
+CALYX_BINARY_PRIMITIVE(std_or, WIDTH, l | r)
+CALYX_BINARY_PRIMITIVE(std_xor, WIDTH, l ^ r)
+
+template <int WIDTH>
+struct std_orr : sc_module {
+  sc_in<sc_uint<WIDTH> > in;
+  sc_out<sc_uint<1> > out;
+
+  SC_HAS_PROCESS(std_orr);
+  std_orr(sc_module_name name) : sc_module(name) {
+    SC_METHOD(eval);
+    sensitive << in;
+  }
+
+  void eval() { out.write(in.read().or_reduce()); }
+};
+
+/// Numerical operators
+CALYX_BINARY_PRIMITIVE(std_add, WIDTH, l + r)
+CALYX_BINARY_PRIMITIVE(std_sub, WIDTH, l - r)
+CALYX_BINARY_PRIMITIVE(std_gt, 1, l > r)
+CALYX_BINARY_PRIMITIVE(std_lt, 1, l < r)
+CALYX_BINARY_PRIMITIVE(std_eq, 1, l == r)
+CALYX_BINARY_PRIMITIVE(std_neq, 1, l != r)
+CALYX_BINARY_PRIMITIVE(std_ge, 1, l >= r)
+CALYX_BINARY_PRIMITIVE(std_le, 1, l <= r)
+CALYX_BINARY_PRIMITIVE(std_lsh, WIDTH,
+                       r >= WIDTH ? 0 : l.to_uint64() << r.to_uint64())
+CALYX_BINARY_PRIMITIVE(std_rsh, WIDTH,
+                       r >= WIDTH ? 0 : l.to_uint64() >> r.to_uint64())
+
+template <int WIDTH>
+struct std_mux : sc_module {
+  sc_in<sc_uint<1> > cond;
+  sc_in<sc_uint<WIDTH> > tru;
+  sc_in<sc_uint<WIDTH> > fal;
+  sc_out<sc_uint<WIDTH> > out;
+
+  SC_HAS_PROCESS(std_mux);
+  std_mux(sc_module_name name) : sc_module(name) {
+    SC_METHOD(eval);
+    sensitive << cond << tru << fal;
+  }
+
+  void eval() { out.write(cond.read() ? tru.read() : fal.read()); }
+};
+
+/// Memories
+
+template <int WIDTH>
+struct std_reg : sc_module {
+  sc_in<sc_uint<WIDTH> > in;
+  sc_in<sc_uint<1> > write_en;
+  sc_in<bool> clk;
+  sc_in<sc_uint<1> > reset;
+  sc_out<sc_uint<WIDTH> > out;
+  sc_out<sc_uint<1> > done;
+
+  SC_HAS_PROCESS(std_reg);
+  std_reg(sc_module_name name) : sc_module(name) {
+    SC_METHOD(tick);
+    sensitive << clk.pos();
+    dont_initialize();
+  }
+
+  void tick() {
+    if (reset.read()) {
+      out.write(0);
+      done.write(0);
+    } else if (write_en.read()) {
+      out.write(in.read());
+      done.write(1);
+    } else {
+      done.write(0);
+    }
+  }
+};
+
+// Memories store their contents in `mem` so that test harnesses can
+// initialize and inspect them. Accesses outside the memory read zero and
+// writes to them are dropped.
+template <int WIDTH, int SIZE>
+struct std_mem_base : sc_module {
+  sc_in<sc_uint<WIDTH> > write_data;
+  sc_in<sc_uint<1> > write_en;
+  sc_in<bool> clk;
+  sc_out<sc_uint<WIDTH> > read_data;
+  sc_out<sc_uint<1> > done;
+
+  sc_uint<WIDTH> mem[SIZE];
+
+  std_mem_base(sc_module_name name) : sc_module(name) {
+    for (int i = 0; i < SIZE; i++) mem[i] = 0;
+  }
+
+ protected:
+  // Notified whenever `mem` changes so that `read_data` is updated.
+  sc_event written;
+
+  // Index of the addressed element or `SIZE` when it is out of bounds.
+  virtual int index() = 0;
+
+  void read() {
+    int idx = index();
+    read_data.write(idx < SIZE ? mem[idx] : sc_uint<WIDTH>(0));
+  }
+
+  void write() {
+    if (write_en.read()) {
+      int idx = index();
+      if (idx < SIZE) mem[idx] = write_data.read();
+      done.write(1);
+      written.notify(SC_ZERO_TIME);
+    } else {
+      done.write(0);
+    }
+  }
+};
+
+// Row-major index from the address `addr` into a dimension of size `size`.
+#define CALYX_MEM_INDEX(idx, addr, size)             \
+  if ((int)addr.read() >= size) return SIZE;         \
+  idx = idx * size + (int)addr.read();
+
+template <int WIDTH, int SIZE, int IDX_SIZE>
+struct std_mem_d1 : std_mem_base<WIDTH, SIZE> {
+  sc_in<sc_uint<IDX_SIZE> > addr0;
+
+  SC_HAS_PROCESS(std_mem_d1);
+  std_mem_d1(sc_module_name name) : std_mem_base<WIDTH, SIZE>(name) {
+    SC_METHOD(read);
+    this->sensitive << addr0 << this->written;
+    SC_METHOD(write);
+    this->sensitive << this->clk.pos();
+    this->dont_initialize();
+  }
+
+  int index() {
+    int idx = 0;
+    CALYX_MEM_INDEX(idx, addr0, SIZE)
+    return idx;
+  }
+};
+
+template <int WIDTH, int D0_SIZE, int D1_SIZE, int D0_IDX_SIZE,
+          int D1_IDX_SIZE>
+struct std_mem_d2 : std_mem_base<WIDTH, D0_SIZE * D1_SIZE> {
+  static const int SIZE = D0_SIZE * D1_SIZE;
+  sc_in<sc_uint<D0_IDX_SIZE> > addr0;
+  sc_in<sc_uint<D1_IDX_SIZE> > addr1;
+
+  SC_HAS_PROCESS(std_mem_d2);
+  std_mem_d2(sc_module_name name) : std_mem_base<WIDTH, SIZE>(name) {
+    SC_METHOD(read);
+    this->sensitive << addr0 << addr1 << this->written;
+    SC_METHOD(write);
+    this->sensitive << this->clk.pos();
+    this->dont_initialize();
+  }
+
+  int index() {
+    int idx = 0;
+    CALYX_MEM_INDEX(idx, addr0, D0_SIZE)
+    CALYX_MEM_INDEX(idx, addr1, D1_SIZE)
+    return idx;
+  }
+};
+
+template <int WIDTH, int D0_SIZE, int D1_SIZE, int D2_SIZE, int D0_IDX_SIZE,
+          int D1_IDX_SIZE, int D2_IDX_SIZE>
+struct std_mem_d3 : std_mem_base<WIDTH, D0_SIZE * D1_SIZE * D2_SIZE> {
+  static const int SIZE = D0_SIZE * D1_SIZE * D2_SIZE;
+  sc_in<sc_uint<D0_IDX_SIZE> > addr0;
+  sc_in<sc_uint<D1_IDX_SIZE> > addr1;
+  sc_in<sc_uint<D2_IDX_SIZE> > addr2;
+
+  SC_HAS_PROCESS(std_mem_d3);
+  std_mem_d3(sc_module_name name) : std_mem_base<WIDTH, SIZE>(name) {
+    SC_METHOD(read);
+    this->sensitive << addr0 << addr1 << addr2 << this->written;
+    SC_METHOD(write);
+    this->sensitive << this->clk.pos();
+    this->dont_initialize();
+  }
+
+  int index() {
+    int idx = 0;
+    CALYX_MEM_INDEX(idx, addr0, D0_SIZE)
+    CALYX_MEM_INDEX(idx, addr1, D1_SIZE)
+    CALYX_MEM_INDEX(idx, addr2, D2_SIZE)
+    return idx;
+  }
+};
+
+template <int WIDTH, int D0_SIZE, int D1_SIZE, int D2_SIZE, int D3_SIZE,
+          int D0_IDX_SIZE, int D1_IDX_SIZE, int D2_IDX_SIZE, int D3_IDX_SIZE>
+struct std_mem_d4
+    : std_mem_base<WIDTH, D0_SIZE * D1_SIZE * D2_SIZE * D3_SIZE> {
+  static const int SIZE = D0_SIZE * D1_SIZE * D2_SIZE * D3_SIZE;
+  sc_in<sc_uint<D0_IDX_SIZE> > addr0;
+  sc_in<sc_uint<D1_IDX_SIZE> > addr1;
+  sc_in<sc_uint<D2_IDX_SIZE> > addr2;
+  sc_in<sc_uint<D3_IDX_SIZE> > addr3;
+
+  SC_HAS_PROCESS(std_mem_d4);
+  std_mem_d4(sc_module_name name) : std_mem_base<WIDTH, SIZE>(name) {
+    SC_METHOD(read);
+    this->sensitive << addr0 << addr1 << addr2 << addr3 << this->written;
+    SC_METHOD(write);
+    this->sensitive << this->clk.pos();
+    this->dont_initialize();
+  }
+
+  int index() {
+    int idx = 0;
+    CALYX_MEM_INDEX(idx, addr0, D0_SIZE)
+    CALYX_MEM_INDEX(idx, addr1, D1_SIZE)
+    CALYX_MEM_INDEX(idx, addr2, D2_SIZE)
+    CALYX_MEM_INDEX(idx, addr3, D3_SIZE)
+    return idx;
+  }
+};
+
+#endif  // CALYX_CORE_H
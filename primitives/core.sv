@@ -60,6 +60,15 @@ module std_pad #(
   `endif
 endmodule
 
+module std_wire #(
+    parameter WIDTH = 32
+) (
+   input wire logic [WIDTH-1:0] in,
+   output logic     [WIDTH-1:0] out
+);
+  assign out = in;
+endmodule
+
 module std_not #(
     parameter WIDTH = 32
 ) (
@@ -99,6 +108,15 @@ module std_xor #(
   assign out = left ^ right;
 endmodule
 
+module std_orr #(
+    parameter WIDTH = 32
+) (
+   input wire   logic [WIDTH-1:0] in,
+   output logic out
+);
+  assign out = |in;
+endmodule
+
 module std_add #(
     parameter WIDTH = 32
 ) (
@@ -0,0 +1,17 @@
+module std_rom #(
+    parameter WIDTH = 32,
+    parameter SIZE = 16,
+    parameter IDX_SIZE = 4,
+    parameter INIT_FILE = ""
+) (
+   input wire                logic [IDX_SIZE-1:0] addr0,
+   output logic [ WIDTH-1:0] read_data
+);
+
+  logic [WIDTH-1:0] mem[SIZE-1:0];
+
+  initial $readmemh(INIT_FILE, mem);
+
+  /* verilator lint_off WIDTH */
+  assign read_data = mem[addr0];
+endmodule
@@ -0,0 +1,38 @@
+module std_elastic_buffer #(
+    parameter WIDTH = 32
+) (
+   input wire logic [WIDTH-1:0] in,
+   input wire logic in_valid,
+   output logic in_ready,
+   output logic [WIDTH-1:0] out,
+   output logic out_valid,
+   input wire logic out_ready,
+   input wire logic clk,
+   input wire logic reset
+);
+
+  // Holds a value accepted while the receiver was not ready.
+  logic [WIDTH-1:0] skid;
+  logic skid_valid;
+
+  assign in_ready = !skid_valid;
+
+  always_ff @(posedge clk) begin
+    if (reset) begin
+      out_valid <= 0;
+      skid_valid <= 0;
+    end else if (out_ready || !out_valid) begin
+      if (skid_valid) begin
+        out <= skid;
+        out_valid <= 1;
+        skid_valid <= 0;
+      end else begin
+        out <= in;
+        out_valid <= in_valid;
+      end
+    end else if (in_valid && in_ready) begin
+      skid <= in;
+      skid_valid <= 1;
+    end
+  end
+endmodule
@@ -0,0 +1,28 @@
+module std_syscall #(
+    parameter IN_WIDTH = 32,
+    parameter OUT_WIDTH = 32,
+    parameter CALL = ""
+) (
+   input wire logic go,
+   input wire logic [IN_WIDTH-1:0] arg,
+   input wire logic clk,
+   input wire logic reset,
+   output logic [OUT_WIDTH-1:0] out,
+   output logic done
+);
+
+  // Calls are handled by the interpreter. In simulation, they are printed and
+  // return zero.
+  always_ff @(posedge clk) begin
+    if (reset) begin
+      out <= 0;
+      done <= 0;
+    end else if (go) begin
+      $display("%s(%0d)", CALL, arg);
+      out <= 0;
+      done <= 1;
+    end else begin
+      done <= 0;
+    end
+  end
+endmodule
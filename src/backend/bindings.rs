@@ -0,0 +1,156 @@
+//! Resource bindings that map Calyx primitives to vendor IP modules.
+//!
+//! A binding file is a JSON object keyed by primitive name:
+//! ```json
+//! {
+//!   "std_mult_pipe": {
+//!     "module": "mult_gen_0",
+//!     "params": { "WIDTH": "A_WIDTH" },
+//!     "ports": {
+//!       "left": "A", "right": "B", "out": "P", "clk": "CLK",
+//!       "go": null, "done": null, "reset": null
+//!     },
+//!     "tie": { "CE": 1 }
+//!   }
+//! }
+//! ```
+//! Instances of a bound primitive are emitted as instances of `module`.
+//! Every port of the primitive must be listed in `ports`, either with the
+//! name of the vendor port it connects to or with `null` if it is left
+//! unconnected. The vendor ports listed in `tie` are connected to constants.
+//! Only the parameters listed in `params` are passed to the vendor module.
+use calyx::errors::{CalyxResult, Error};
+use calyx::ir;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The vendor module used to implement a primitive.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Binding {
+    /// Name of the vendor module.
+    pub module: String,
+    /// Map from primitive parameter names to vendor parameter names.
+    #[serde(default)]
+    pub params: HashMap<String, String>,
+    /// Map from primitive port names to vendor port names. Ports mapped to
+    /// `None` are not connected.
+    #[serde(default)]
+    pub ports: HashMap<String, Option<String>>,
+    /// Map from vendor port names to the constants they are tied to.
+    #[serde(default)]
+    pub tie: HashMap<String, i32>,
+}
+
+impl Binding {
+    /// Name of the vendor port connected to the primitive port `port`, or
+    /// `None` if the port is not connected.
+    pub fn port(&self, port: &str) -> Option<&str> {
+        self.ports.get(port).and_then(|p| p.as_deref())
+    }
+
+    /// Name of the vendor parameter bound to the primitive parameter `param`
+    /// if it is passed to the vendor module.
+    pub fn param(&self, param: &str) -> Option<&str> {
+        self.params.get(param).map(String::as_str)
+    }
+
+    /// Check that the binding maps every port of `prim` and only mentions
+    /// the ports and parameters of `prim`.
+    fn validate(&self, prim: &ir::Primitive) -> Result<(), String> {
+        if let Some(param) = self.params.keys().find(|p| {
+            !prim
+                .params
+                .iter()
+                .chain(&prim.string_params)
+                .any(|x| x == *p)
+        }) {
+            return Err(format!("unknown parameter `{}`", param));
+        }
+        if let Some(port) = self
+            .ports
+            .keys()
+            .find(|p| !prim.signature.iter().any(|pd| pd.name == *p))
+        {
+            return Err(format!("unknown port `{}`", port));
+        }
+        if let Some(pd) = prim
+            .signature
+            .iter()
+            .find(|pd| !self.ports.contains_key(pd.name.as_ref()))
+        {
+            return Err(format!(
+                "port `{}` is not mapped. Map it to `null` to leave it unconnected",
+                pd.name
+            ));
+        }
+        if let Some(port) = self
+            .tie
+            .keys()
+            .find(|p| self.ports.values().any(|v| v.as_ref() == Some(*p)))
+        {
+            return Err(format!(
+                "vendor port `{}` is both tied and connected",
+                port
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Bindings for all primitives mentioned in a binding file.
+#[derive(Default, Debug)]
+pub struct Bindings {
+    /// File the bindings were read from.
+    path: PathBuf,
+    bindings: HashMap<String, Binding>,
+}
+
+impl Bindings {
+    /// Read bindings from the JSON file at `path`.
+    pub fn from_file(path: &Path) -> CalyxResult<Self> {
+        let content = fs::read_to_string(path).map_err(|err| {
+            Error::InvalidFile(format!(
+                "Failed to read {}: {}",
+                path.to_string_lossy(),
+                err
+            ))
+        })?;
+        let bindings = serde_json::from_str(&content).map_err(|err| {
+            Error::InvalidFile(format!(
+                "Malformed binding file {}: {}",
+                path.to_string_lossy(),
+                err
+            ))
+        })?;
+        Ok(Bindings {
+            path: path.to_path_buf(),
+            bindings,
+        })
+    }
+
+    /// Check the bindings against the signatures of the primitives in `lib`.
+    /// Bindings for primitives that the program does not define are ignored.
+    pub fn validate(&self, lib: &ir::LibrarySignatures) -> CalyxResult<()> {
+        for (name, binding) in &self.bindings {
+            if let Some(prim) = lib.find_primitive(name.as_str()) {
+                binding.validate(prim).map_err(|msg| {
+                    Error::InvalidFile(format!(
+                        "Invalid binding for `{}` in {}: {}",
+                        name,
+                        self.path.to_string_lossy(),
+                        msg
+                    ))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The binding for the primitive `prim`, if there is one.
+    pub fn get(&self, prim: &str) -> Option<&Binding> {
+        self.bindings.get(prim)
+    }
+}
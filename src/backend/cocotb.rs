@@ -0,0 +1,291 @@
+//! Backend that generates a cocotb testbench skeleton for the entrypoint.
+//!
+//! The testbench starts the clock, resets the design, loads the `@external`
+//! memories, and runs the design until it asserts its `@done` port. It is
+//! meant to be filled in with directed tests for the Verilog output.
+use crate::backend::traits::Backend;
+use calyx::{
+    errors::{CalyxResult, Error},
+    ir,
+    utils::OutputFile,
+};
+use std::io::Write;
+
+#[derive(Default)]
+pub struct CocotbBackend;
+
+/// Names that cannot be used as attributes in Python.
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break",
+    "class", "continue", "def", "del", "elif", "else", "except", "finally",
+    "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal",
+    "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+/// Helpers shared by every testbench.
+const HELPERS: &str = r#"
+
+def start_clock(dut):
+    """Drive the clock of the design."""
+    cocotb.start_soon(Clock(CLK(dut), CLOCK_PERIOD, units="ns").start())
+
+
+async def reset(dut, cycles=RESET_CYCLES):
+    """Hold the design in reset for `cycles` cycles."""
+    GO(dut).value = 0
+    RESET(dut).value = 1
+    for _ in range(cycles):
+        await FallingEdge(CLK(dut))
+    RESET(dut).value = 0
+
+
+async def run(dut, timeout=TIMEOUT):
+    """Assert `go` until the design asserts `done` and return the number of
+    cycles it took."""
+    GO(dut).value = 1
+    for cycles in range(1, timeout + 1):
+        await FallingEdge(CLK(dut))
+        if DONE(dut).value == 1:
+            GO(dut).value = 0
+            return cycles
+    raise TimeoutError(f"`done` was not asserted within {timeout} cycles")
+
+
+def _element(dut, name, addr):
+    """The element at the flattened address `addr` of the memory `name`."""
+    _, dims = MEMORIES[name]
+    if not 0 <= addr < math.prod(dims):
+        raise IndexError(f"address {addr} is out of bounds for `{name}`")
+    handle = getattr(dut, name).mem
+    for dim in range(len(dims)):
+        stride = math.prod(dims[dim + 1:])
+        handle = handle[addr // stride % dims[dim]]
+    return handle
+
+
+def load_memory(dut, name, values):
+    """Write `values` to the memory `name` in row-major order, starting at
+    the first element."""
+    width, _ = MEMORIES[name]
+    for addr, val in enumerate(values):
+        _element(dut, name, addr).value = val % (1 << width)
+
+
+def read_memory(dut, name):
+    """The elements of the memory `name` in row-major order."""
+    _, dims = MEMORIES[name]
+    return [
+        _element(dut, name, addr).value.integer
+        for addr in range(math.prod(dims))
+    ]
+"#;
+
+/// Python expression for the handle of the signal `name` of the design.
+fn handle(name: &str) -> String {
+    let is_ident = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_ident && !PYTHON_KEYWORDS.contains(&name) {
+        format!("dut.{}", name)
+    } else {
+        format!("getattr(dut, \"{}\")", name)
+    }
+}
+
+/// The width and dimensions of `cell` if it is an `@external` memory.
+fn memory(cell: &ir::Cell) -> Option<(u64, Vec<u64>)> {
+    cell.get_attribute("external")?;
+    match &cell.prototype {
+        ir::CellType::Primitive {
+            name,
+            param_binding,
+            ..
+        } if name.id.starts_with("std_mem_d") => {
+            let param = |param: &str| {
+                param_binding
+                    .iter()
+                    .find(|(name, _)| name == param)
+                    .map(|(_, val)| *val)
+            };
+            let dims = match param("SIZE") {
+                Some(size) => vec![size],
+                None => (0..4)
+                    .filter_map(|dim| param(&format!("D{}_SIZE", dim)))
+                    .collect(),
+            };
+            Some((param("WIDTH")?, dims))
+        }
+        _ => None,
+    }
+}
+
+/// Name of the port of `comp` with the attribute `attr`.
+fn interface_port(comp: &ir::Component, attr: &str) -> CalyxResult<String> {
+    comp.signature
+        .borrow()
+        .find_with_attr(attr)
+        .map(|port| port.borrow().name.to_string())
+        .ok_or_else(|| {
+            Error::MalformedStructure(format!(
+                "Component `{}` has no @{} port, which the cocotb testbench needs to run it",
+                comp.name, attr
+            ))
+        })
+}
+
+impl Backend for CocotbBackend {
+    fn name(&self) -> &'static str {
+        "cocotb"
+    }
+
+    fn validate(_prog: &ir::Context) -> CalyxResult<()> {
+        Ok(())
+    }
+
+    fn link_externs(
+        _prog: &ir::Context,
+        _write: &mut OutputFile,
+    ) -> CalyxResult<()> {
+        Ok(())
+    }
+
+    fn emit(prog: &ir::Context, file: &mut OutputFile) -> CalyxResult<()> {
+        let comp = prog
+            .components
+            .iter()
+            .find(|comp| comp.name == prog.entrypoint)
+            .ok_or_else(|| {
+                Error::Misc(format!(
+                    "No entrypoint component `{}`",
+                    prog.entrypoint
+                ))
+            })?;
+        let interface = ["go", "done", "clk", "reset"]
+            .iter()
+            .map(|attr| interface_port(comp, attr))
+            .collect::<CalyxResult<Vec<_>>>()?;
+
+        // The ports of the signature have the opposite direction inside the
+        // component.
+        let (mut inputs, mut outputs) = (vec![], vec![]);
+        for port in &comp.signature.borrow().ports {
+            let port = port.borrow();
+            if interface.contains(&port.name.to_string()) {
+                continue;
+            }
+            match port.direction {
+                ir::Direction::Output => {
+                    inputs.push((port.name.clone(), port.width))
+                }
+                ir::Direction::Input => {
+                    outputs.push((port.name.clone(), port.width))
+                }
+                ir::Direction::Inout => {}
+            }
+        }
+        let memories = comp
+            .cells
+            .iter()
+            .filter_map(|cell| {
+                let cell = cell.borrow();
+                memory(&cell).map(|mem| (cell.name().clone(), mem))
+            })
+            .collect::<Vec<_>>();
+
+        let out = &mut file.get_write();
+        writeln!(
+            out,
+            "# Testbench for the `{name}` component generated by the Calyx compiler.
+# Run it with cocotb by setting `TOPLEVEL={name}` and `MODULE` to the name of
+# this file in a Makefile that builds the Verilog output of the compiler.
+import math
+
+import cocotb
+from cocotb.clock import Clock
+from cocotb.triggers import FallingEdge
+
+# Period of the clock in nanoseconds.
+CLOCK_PERIOD = 10
+# Number of cycles the design is held in reset.
+RESET_CYCLES = 2
+# Number of cycles after which `run` gives up on the design.
+TIMEOUT = 100000
+",
+            name = comp.name
+        )?;
+        let ports = ["GO", "DONE", "CLK", "RESET"];
+        for (var, port) in ports.iter().zip(&interface) {
+            writeln!(out, "{} = lambda dut: {}", var, handle(port))?;
+        }
+        writeln!(out)?;
+        writeln!(out, "# Data ports of `{}` and their widths.", comp.name)?;
+        for (var, ports) in &[("INPUTS", &inputs), ("OUTPUTS", &outputs)] {
+            writeln!(out, "{} = {{", var)?;
+            for (name, width) in ports.iter() {
+                writeln!(out, "    \"{}\": {},", name, width)?;
+            }
+            writeln!(out, "}}")?;
+        }
+        writeln!(
+            out,
+            "# @external memories of `{}` and their widths and dimensions.",
+            comp.name
+        )?;
+        writeln!(out, "MEMORIES = {{")?;
+        for (name, (width, dims)) in &memories {
+            let dims = dims.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+            writeln!(
+                out,
+                "    \"{}\": ({}, [{}]),",
+                name,
+                width,
+                dims.join(", ")
+            )?;
+        }
+        writeln!(out, "}}")?;
+        write!(out, "{}", HELPERS)?;
+
+        writeln!(
+            out,
+            "
+
+@cocotb.test()
+async def test_{name}(dut):
+    \"\"\"Run `{name}` once.\"\"\"
+    start_clock(dut)
+    await reset(dut)",
+            name = comp.name
+        )?;
+        for (name, (_, dims)) in &memories {
+            let size: u64 = dims.iter().product();
+            writeln!(
+                out,
+                "    load_memory(dut, \"{}\", [0] * {})",
+                name, size
+            )?;
+        }
+        for (name, _) in &inputs {
+            writeln!(out, "    {}.value = 0", handle(name.id.as_str()))?;
+        }
+        writeln!(out, "    cycles = await run(dut)")?;
+        writeln!(
+            out,
+            "    dut._log.info(\"`{}` finished in %d cycles\", cycles)",
+            comp.name
+        )?;
+        for (name, _) in &outputs {
+            writeln!(
+                out,
+                "    # assert {}.value == 0",
+                handle(name.id.as_str())
+            )?;
+        }
+        for (name, (_, dims)) in &memories {
+            let size: u64 = dims.iter().product();
+            writeln!(
+                out,
+                "    # assert read_memory(dut, \"{}\") == [0] * {}",
+                name, size
+            )?;
+        }
+        Ok(())
+    }
+}
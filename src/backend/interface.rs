@@ -0,0 +1,152 @@
+//! Backend that describes the interface of each component as JSON.
+//!
+//! The description lists the ports of each component along with their
+//! widths, directions, and attributes, and the `@external` memories of the
+//! component. It is meant for generating host code and testbenches for the
+//! output of the Verilog backend.
+use crate::backend::traits::Backend;
+use calyx::{
+    errors::{CalyxResult, Error},
+    ir,
+    utils::OutputFile,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+#[derive(Default)]
+pub struct InterfaceBackend;
+
+#[derive(Serialize)]
+struct Interface<'a> {
+    entrypoint: &'a str,
+    components: Vec<Component<'a>>,
+}
+
+#[derive(Serialize)]
+struct Component<'a> {
+    name: &'a str,
+    attributes: BTreeMap<&'a str, u64>,
+    ports: Vec<Port>,
+    memories: Vec<Memory>,
+}
+
+#[derive(Serialize)]
+struct Port {
+    name: String,
+    width: u64,
+    direction: &'static str,
+    attributes: BTreeMap<String, u64>,
+}
+
+#[derive(Serialize)]
+struct Memory {
+    name: String,
+    #[serde(rename = "type")]
+    typ: String,
+    parameters: BTreeMap<String, u64>,
+}
+
+impl Port {
+    /// Port of the component with the signature port `port`.
+    fn new(port: &ir::Port) -> Self {
+        // The directions of the signature ports are reversed inside the
+        // component.
+        let direction = match port.direction {
+            ir::Direction::Input => "output",
+            ir::Direction::Output => "input",
+            ir::Direction::Inout => "inout",
+        };
+        Port {
+            name: port.name.to_string(),
+            width: port.width,
+            direction,
+            attributes: port
+                .attributes
+                .iter()
+                .map(|(attr, val)| (attr.clone(), *val))
+                .collect(),
+        }
+    }
+}
+
+impl Memory {
+    /// The memory implemented by `cell` if it is marked `@external`.
+    fn from_cell(cell: &ir::Cell) -> Option<Self> {
+        cell.get_attribute("external")?;
+        match &cell.prototype {
+            ir::CellType::Primitive {
+                name,
+                param_binding,
+                ..
+            } => Some(Memory {
+                name: cell.name().to_string(),
+                typ: name.to_string(),
+                parameters: param_binding
+                    .iter()
+                    .map(|(param, val)| (param.to_string(), *val))
+                    .collect(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Component<'a> {
+    fn new(comp: &'a ir::Component) -> Self {
+        Component {
+            name: comp.name.as_ref(),
+            attributes: comp
+                .attributes
+                .iter()
+                .map(|(attr, val)| (attr.as_str(), *val))
+                .collect(),
+            ports: comp
+                .signature
+                .borrow()
+                .ports
+                .iter()
+                .map(|port| Port::new(&port.borrow()))
+                .collect(),
+            memories: comp
+                .cells
+                .iter()
+                .filter_map(|cell| Memory::from_cell(&cell.borrow()))
+                .collect(),
+        }
+    }
+}
+
+impl Backend for InterfaceBackend {
+    fn name(&self) -> &'static str {
+        "interface"
+    }
+
+    fn validate(_prog: &ir::Context) -> CalyxResult<()> {
+        Ok(())
+    }
+
+    fn link_externs(
+        _prog: &ir::Context,
+        _write: &mut OutputFile,
+    ) -> CalyxResult<()> {
+        Ok(())
+    }
+
+    fn emit(prog: &ir::Context, file: &mut OutputFile) -> CalyxResult<()> {
+        let interface = Interface {
+            entrypoint: prog.entrypoint.as_ref(),
+            components: prog.components.iter().map(Component::new).collect(),
+        };
+        let mut out = file.get_write();
+        serde_json::to_writer_pretty(&mut out, &interface).map_err(|err| {
+            Error::WriteError(format!(
+                "Failed to write interface to {}: {}",
+                file.as_path_string(),
+                err
+            ))
+        })?;
+        writeln!(out)?;
+        Ok(())
+    }
+}
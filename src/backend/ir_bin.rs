@@ -0,0 +1,30 @@
+//! Backend that emits the program in the compact binary format read by the
+//! frontend.
+use super::traits::Backend;
+use calyx::{errors::CalyxResult, frontend::ir_bin, ir, utils::OutputFile};
+
+#[derive(Default)]
+pub struct IrBinBackend;
+
+impl Backend for IrBinBackend {
+    fn name(&self) -> &'static str {
+        "ir-bin"
+    }
+
+    fn validate(_prog: &ir::Context) -> CalyxResult<()> {
+        Ok(())
+    }
+
+    fn emit(prog: &ir::Context, file: &mut OutputFile) -> CalyxResult<()> {
+        let ns = ir::to_ast::context_to_ast(prog)?;
+        ir_bin::write(&ns, &mut file.get_write())
+    }
+
+    /// The primitive definitions are embedded in the emitted program.
+    fn link_externs(
+        _prog: &ir::Context,
+        _write: &mut OutputFile,
+    ) -> CalyxResult<()> {
+        Ok(())
+    }
+}
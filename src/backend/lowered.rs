@@ -0,0 +1,187 @@
+//! Checks that a program has been lowered enough to be emitted by a backend
+//! that generates hardware, such as the Verilog and SystemC backends.
+//!
+//! These backends only accept components with no groups, no holes, an empty
+//! control program, and assignments between ports of the same width. Each
+//! violation is reported with the pass that removes it.
+
+use calyx::{
+    errors::{CalyxResult, Error},
+    ir,
+};
+use ir::{Control, Guard};
+
+/// Suffix of the error messages that tells users how to run all the passes.
+const RUN_ALL: &str = "or use `-p all` to run all the lowering passes";
+
+/// The pass that compiles away the first statement in `con` that a backend
+/// cannot emit, if any.
+fn missing_pass(con: &Control) -> Option<&'static str> {
+    match con {
+        Control::Empty(_) => None,
+        Control::Debug(_) => Some("compile-debug"),
+        Control::Invoke(_) => Some("compile-invoke"),
+        Control::Enable(_) => Some("tdcc"),
+        Control::Seq(ir::Seq { stmts, .. })
+        | Control::Par(ir::Par { stmts, .. }) => {
+            stmts.iter().find_map(missing_pass).or(Some("tdcc"))
+        }
+        Control::If(ir::If {
+            tbranch, fbranch, ..
+        }) => missing_pass(tbranch)
+            .or_else(|| missing_pass(fbranch))
+            .or(Some("tdcc")),
+        Control::While(ir::While { body, .. }) => {
+            missing_pass(body).or(Some("tdcc"))
+        }
+    }
+}
+
+/// The name of the group and the hole if `port` is a hole.
+fn hole_name(port: &ir::RRC<ir::Port>) -> Option<(ir::Id, ir::Id)> {
+    let port = port.borrow();
+    if port.is_hole() {
+        Some(port.canonical())
+    } else {
+        None
+    }
+}
+
+/// Returns the name of the first hole used by `guard`, if any.
+fn guard_hole(guard: &Guard) -> Option<(ir::Id, ir::Id)> {
+    match guard {
+        Guard::Or(left, right) | Guard::And(left, right) => {
+            guard_hole(left).or_else(|| guard_hole(right))
+        }
+        Guard::Eq(left, right)
+        | Guard::Neq(left, right)
+        | Guard::Gt(left, right)
+        | Guard::Lt(left, right)
+        | Guard::Geq(left, right)
+        | Guard::Leq(left, right) => {
+            hole_name(left).or_else(|| hole_name(right))
+        }
+        Guard::Not(inner) => guard_hole(inner),
+        Guard::Port(port) => hole_name(port),
+        Guard::True => None,
+    }
+}
+
+/// Check a continuous assignment of `comp`.
+fn validate_assignment(
+    assign: &ir::Assignment,
+    comp: &ir::Component,
+    backend: &str,
+) -> CalyxResult<()> {
+    let hole = hole_name(&assign.dst)
+        .or_else(|| hole_name(&assign.src))
+        .or_else(|| guard_hole(&assign.guard));
+    if let Some((group, name)) = hole {
+        return Err(Error::MalformedStructure(format!(
+            "Component `{}` uses the hole `{}[{}]`, which the {} backend cannot emit. Run the `go-insertion` and `hole-inliner` passes {}.",
+            comp.name, group, name, backend, RUN_ALL
+        )));
+    }
+    let (dst, src) = (assign.dst.borrow(), assign.src.borrow());
+    if dst.width != src.width {
+        let (dst_cell, dst_name) = dst.canonical();
+        let (src_cell, src_name) = src.canonical();
+        return Err(Error::MalformedStructure(format!(
+            "Component `{}` assigns `{}.{}` with width {} to `{}.{}` with width {}. Widths must be resolved before using the {} backend; this is usually caused by a pass that does not preserve widths.",
+            comp.name,
+            src_cell,
+            src_name,
+            src.width,
+            dst_cell,
+            dst_name,
+            dst.width,
+            backend
+        )));
+    }
+    Ok(())
+}
+
+/// Check that `comp` has been lowered enough to be emitted by `backend`.
+fn validate_component(comp: &ir::Component, backend: &str) -> CalyxResult<()> {
+    let pass = match &*comp.control.borrow() {
+        // The group generated by `tdcc` is inlined by `hole-inliner`.
+        Control::Enable(_) => Some("hole-inliner"),
+        con => missing_pass(con),
+    };
+    if let Some(pass) = pass {
+        return Err(Error::MalformedControl(format!(
+            "Component `{}` has a control program, which the {} backend cannot emit. Run the `{}` pass {}.",
+            comp.name, backend, pass, RUN_ALL
+        )));
+    }
+    if let Some(group) = comp.comb_groups.iter().next() {
+        return Err(Error::MalformedStructure(format!(
+            "Component `{}` contains the combinational group `{}`, which the {} backend cannot emit. Run the `remove-comb-groups` pass {}.",
+            comp.name,
+            group.borrow().name(),
+            backend,
+            RUN_ALL
+        )));
+    }
+    if let Some(group) = comp.groups.iter().next() {
+        return Err(Error::MalformedStructure(format!(
+            "Component `{}` contains the group `{}`, which the {} backend cannot emit. Run the `hole-inliner` pass {}.",
+            comp.name,
+            group.borrow().name(),
+            backend,
+            RUN_ALL
+        )));
+    }
+    comp.continuous_assignments
+        .iter()
+        .try_for_each(|assign| validate_assignment(assign, comp, backend))
+}
+
+/// Check that every component of `ctx` has been lowered enough to be emitted
+/// by `backend`.
+pub fn validate_lowered(ctx: &ir::Context, backend: &str) -> CalyxResult<()> {
+    ctx.components
+        .iter()
+        .try_for_each(|comp| validate_component(comp, backend))
+}
+
+/// Check that the guard of `assign` is either a port or `true`.
+fn validate_guard(
+    assign: &ir::Assignment,
+    comp: &ir::Component,
+    backend: &str,
+) -> CalyxResult<()> {
+    if matches!(&*assign.guard, Guard::True | Guard::Port(_)) {
+        return Ok(());
+    }
+    Err(Error::MalformedStructure(format!(
+        "Component `{}` uses the guard `{}`, which the {} backend cannot emit. Run the `lower-guards` pass.",
+        comp.name,
+        ir::IRPrinter::guard_str(&assign.guard),
+        backend
+    )))
+}
+
+/// Check that every guard of `ctx` is either a port or `true`, which is the
+/// case after the `lower-guards` pass.
+pub fn validate_lowered_guards(
+    ctx: &ir::Context,
+    backend: &str,
+) -> CalyxResult<()> {
+    for comp in &ctx.components {
+        for group in comp.groups.iter() {
+            for assign in &group.borrow().assignments {
+                validate_guard(assign, comp, backend)?;
+            }
+        }
+        for group in comp.comb_groups.iter() {
+            for assign in &group.borrow().assignments {
+                validate_guard(assign, comp, backend)?;
+            }
+        }
+        for assign in &comp.continuous_assignments {
+            validate_guard(assign, comp, backend)?;
+        }
+    }
+    Ok(())
+}
@@ -1,11 +1,12 @@
 use calyx::errors::Error;
 use calyx::ir::{GetAttributes, IRPrinter};
 
-use crate::ir::{self, RRC};
+use calyx::ir::{self, RRC};
 use std::collections::HashMap;
 use std::io;
 use std::rc::Rc;
 
+use super::lowered;
 use super::traits::Backend;
 
 #[derive(Default)]
@@ -16,8 +17,8 @@ impl Backend for MlirBackend {
         "mlir"
     }
 
-    fn validate(_prog: &ir::Context) -> calyx::errors::CalyxResult<()> {
-        Ok(())
+    fn validate(prog: &ir::Context) -> calyx::errors::CalyxResult<()> {
+        lowered::validate_lowered_guards(prog, "mlir")
     }
 
     fn emit(
@@ -58,7 +59,7 @@ impl MlirBackend {
             format!(
                 " {{{}}}",
                 attrs
-                    .iter()
+                    .iter_values()
                     .map(|(k, v)| { format!("{}={}", k, v) })
                     .collect::<Vec<_>>()
                     .join(", ")
@@ -381,7 +382,9 @@ impl MlirBackend {
                 Self::write_control(body, indent_level + 2, f)?;
                 write!(f, "{}}}", " ".repeat(indent_level))
             }
-            ir::Control::Empty(_) => writeln!(f),
+            // The Calyx dialect cannot print values so `@debug` statements
+            // are dropped.
+            ir::Control::Empty(_) | ir::Control::Debug(_) => writeln!(f),
         }?;
         if let Some(attr) = control.get_attributes() {
             write!(f, "{}", Self::format_attributes(attr))?;
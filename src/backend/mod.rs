@@ -1,5 +1,186 @@
 //! Backends for the Calyx compiler.
+pub mod bindings;
+pub mod cocotb;
+pub mod interface;
+pub mod ir_bin;
+pub mod lowered;
 pub mod mlir;
+pub mod synth_tcl;
+pub mod systemc;
 pub mod traits;
+pub mod verilator;
 pub mod verilog;
 pub mod xilinx;
+
+use calyx::{errors::CalyxResult, ir, utils::OutputFile};
+use cocotb::CocotbBackend;
+use interface::InterfaceBackend;
+use ir_bin::IrBinBackend;
+use itertools::Itertools;
+use mlir::MlirBackend;
+use std::str::FromStr;
+use synth_tcl::SynthTclBackend;
+use systemc::SystemCBackend;
+use traits::Backend;
+use verilog::VerilogBackend;
+use xilinx::{XilinxInterfaceBackend, XilinxXmlBackend};
+
+// ================== Backend Variant and Parsing ===================== //
+
+/// Enumeration of valid backends
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackendOpt {
+    Verilog,
+    Xilinx,
+    XilinxXml,
+    Calyx,
+    Mlir,
+    IrBin,
+    SystemC,
+    Interface,
+    Cocotb,
+    SynthTcl,
+    None,
+}
+
+/// Return a vector that maps strings to Backends.
+#[inline(always)]
+fn backends() -> Vec<(&'static str, BackendOpt)> {
+    vec![
+        ("verilog", BackendOpt::Verilog),
+        ("xilinx", BackendOpt::Xilinx),
+        ("xilinx-xml", BackendOpt::XilinxXml),
+        ("futil", BackendOpt::Calyx),
+        ("calyx", BackendOpt::Calyx),
+        ("mlir", BackendOpt::Mlir),
+        ("ir-bin", BackendOpt::IrBin),
+        ("systemc", BackendOpt::SystemC),
+        ("interface", BackendOpt::Interface),
+        ("cocotb", BackendOpt::Cocotb),
+        ("synth-tcl", BackendOpt::SynthTcl),
+        ("none", BackendOpt::None),
+    ]
+}
+
+impl Default for BackendOpt {
+    fn default() -> Self {
+        BackendOpt::Calyx
+    }
+}
+
+/// Command line parsing for the Backend enum
+impl FromStr for BackendOpt {
+    type Err = String;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        // allocate a vector for the list of backends
+        let backends = backends();
+        // see if there is a backend for the string that we receive
+        let found_backend = backends
+            .iter()
+            .find(|(backend_name, _)| &input == backend_name);
+        if let Some((_, opt)) = found_backend {
+            // return the BackendOpt if we found one
+            Ok(opt.clone())
+        } else {
+            // build list of backends for error message
+            let backend_str = backends
+                .iter()
+                .map(|(name, _)| (*name).to_string())
+                .join(", ");
+            Err(format!(
+                "`{}` is not a valid backend.\nValid backends: {}",
+                input, backend_str
+            ))
+        }
+    }
+}
+
+/// Convert `BackendOpt` to a string
+impl ToString for BackendOpt {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Mlir => "mlir",
+            Self::Verilog => "verilog",
+            Self::Xilinx => "xilinx",
+            Self::XilinxXml => "xilinx-xml",
+            Self::Calyx => "calyx",
+            Self::IrBin => "ir-bin",
+            Self::SystemC => "systemc",
+            Self::Interface => "interface",
+            Self::Cocotb => "cocotb",
+            Self::SynthTcl => "synth-tcl",
+            Self::None => "none",
+        }
+        .to_string()
+    }
+}
+
+impl BackendOpt {
+    /// Run the backend on `context` and write the output to `output`.
+    pub fn run(
+        &self,
+        context: &ir::Context,
+        output: OutputFile,
+    ) -> CalyxResult<()> {
+        match self {
+            BackendOpt::Mlir => {
+                let backend = MlirBackend::default();
+                backend.run(context, output)
+            }
+            BackendOpt::IrBin => {
+                let backend = IrBinBackend;
+                backend.run(context, output)
+            }
+            BackendOpt::Verilog => {
+                let backend = VerilogBackend::default();
+                backend.run(context, output)
+            }
+            BackendOpt::SystemC => {
+                let backend = SystemCBackend;
+                backend.run(context, output)
+            }
+            BackendOpt::Xilinx => {
+                let backend = XilinxInterfaceBackend::default();
+                backend.run(context, output)
+            }
+            BackendOpt::Interface => {
+                let backend = InterfaceBackend;
+                backend.run(context, output)
+            }
+            BackendOpt::Cocotb => {
+                let backend = CocotbBackend;
+                backend.run(context, output)
+            }
+            BackendOpt::SynthTcl => {
+                let backend = SynthTclBackend;
+                backend.run(context, output)
+            }
+            BackendOpt::XilinxXml => {
+                let backend = XilinxXmlBackend::default();
+                backend.run(context, output)
+            }
+            BackendOpt::Calyx => {
+                for (path, prims) in context.lib.extern_defs() {
+                    ir::IRPrinter::write_extern(
+                        (path, &prims.cloned().collect_vec()),
+                        &mut output.get_write(),
+                    )?;
+                }
+                for comp in &context.components {
+                    ir::IRPrinter::write_component_with_comments(
+                        comp,
+                        &context.comments,
+                        &mut output.get_write(),
+                    )?;
+                    writeln!(&mut output.get_write())?
+                }
+                ir::IRPrinter::write_metadata(
+                    &context.metadata,
+                    &mut output.get_write(),
+                )?;
+                Ok(())
+            }
+            BackendOpt::None => Ok(()),
+        }
+    }
+}
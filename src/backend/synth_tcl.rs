@@ -0,0 +1,280 @@
+//! Backend that generates a Tcl script to synthesize the design with Vivado
+//! or Quartus.
+//!
+//! The script creates a project, adds the Verilog design, sets the
+//! entrypoint as the toplevel module, constrains the clock if the entrypoint
+//! has a `@clock_period(<ns>)` attribute, and runs synthesis. When the script
+//! is written to a file, the Verilog design is written next to it with the
+//! `.sv` extension. Otherwise, the script expects the design in
+//! `<entrypoint>.sv` next to the script.
+//!
+//! The script is configured using `-x synth-tcl:<key>=<value>`:
+//! - `tool`: `vivado` (default) or `quartus`.
+//! - `device`: the part number of the target device.
+use crate::backend::lowered;
+use crate::backend::traits::Backend;
+use crate::backend::verilog::VerilogBackend;
+use calyx::{
+    errors::{CalyxResult, Error},
+    ir,
+    utils::OutputFile,
+};
+use std::io::Write;
+
+/// Name of the backend, also used as the prefix of its options.
+const NAME: &str = "synth-tcl";
+
+#[derive(Default)]
+pub struct SynthTclBackend;
+
+/// Synthesis tools that the script can drive.
+#[derive(Clone, Copy, PartialEq)]
+enum Tool {
+    Vivado,
+    Quartus,
+}
+
+impl Tool {
+    /// Part number used when no device is given. The Vivado part matches
+    /// the one used by `fud/synth/synth.tcl`.
+    fn default_device(self) -> &'static str {
+        match self {
+            Tool::Vivado => "xczu3eg-sbva484-1-e",
+            Tool::Quartus => "10AX115N2F40E2LG",
+        }
+    }
+}
+
+/// Options of the script given using `-x synth-tcl:<key>=<value>`.
+struct Options {
+    tool: Tool,
+    device: Option<String>,
+}
+
+impl Options {
+    fn from(ctx: &ir::Context) -> CalyxResult<Self> {
+        let mut opts = Options {
+            tool: Tool::Vivado,
+            device: None,
+        };
+        for opt in &ctx.extra_opts {
+            let mut splits = opt.splitn(2, ':');
+            if splits.next() != Some(NAME) {
+                continue;
+            }
+            let malformed = || {
+                Error::Misc(format!(
+                    "Malformed option `{}`. `{}` expects `{}:tool=<vivado|quartus>` or `{}:device=<part>`",
+                    opt, NAME, NAME, NAME
+                ))
+            };
+            let (key, val) = splits
+                .next()
+                .and_then(|opt| opt.split_once('='))
+                .ok_or_else(malformed)?;
+            match (key, val) {
+                ("tool", "vivado") => opts.tool = Tool::Vivado,
+                ("tool", "quartus") => opts.tool = Tool::Quartus,
+                ("device", part) if !part.is_empty() => {
+                    opts.device = Some(part.to_string())
+                }
+                _ => return Err(malformed()),
+            }
+        }
+        Ok(opts)
+    }
+
+    fn device(&self) -> &str {
+        self.device
+            .as_deref()
+            .unwrap_or_else(|| self.tool.default_device())
+    }
+}
+
+/// Name of the clock port of `comp` and the period of the clock in
+/// nanoseconds, if the component has a `@clock_period` attribute.
+fn clock(comp: &ir::Component) -> CalyxResult<Option<(ir::Id, u64)>> {
+    let period = match comp.attributes.get("clock_period") {
+        Some(period) => *period,
+        None => return Ok(None),
+    };
+    let port = comp.signature.borrow().find_with_attr("clk").ok_or_else(|| {
+        Error::MalformedStructure(format!(
+            "Component `{}` has a @clock_period attribute but no @clk port to constrain",
+            comp.name
+        ))
+    })?;
+    let name = port.borrow().name.clone();
+    Ok(Some((name, period)))
+}
+
+/// Tcl commands that write `lines` to the file `path`, relative to the
+/// directory of the script.
+fn write_file(
+    out: &mut dyn Write,
+    path: &str,
+    lines: &[String],
+) -> std::io::Result<()> {
+    writeln!(out, "set fd [open [file join $dir {}] w]", path)?;
+    for line in lines {
+        writeln!(out, "puts $fd {{{}}}", line)?;
+    }
+    writeln!(out, "close $fd")
+}
+
+fn emit_vivado(
+    out: &mut dyn Write,
+    top: &ir::Id,
+    design: &str,
+    device: &str,
+    clock: Option<(ir::Id, u64)>,
+) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "# Run this script using `vivado -mode batch -source <script>`.
+set dir [file dirname [info script]]
+
+create_project -force -part {device} {top} [file join $dir {top}_vivado]
+add_files -norecurse [file join $dir {design}]
+set_property top {top} [current_fileset]",
+        device = device,
+        top = top,
+        design = design
+    )?;
+    if let Some((port, period)) = clock {
+        writeln!(out)?;
+        write_file(
+            out,
+            &format!("{}.xdc", top),
+            &[format!(
+                "create_clock -period {} -name {} [get_ports {}]",
+                period, port, port
+            )],
+        )?;
+        writeln!(
+            out,
+            "add_files -fileset constrs_1 [file join $dir {}.xdc]",
+            top
+        )?;
+    }
+    writeln!(
+        out,
+        "
+# The design is synthesized out of context so that its ports do not need to
+# be connected to the pins of the device.
+set_property \\
+    -name {{STEPS.SYNTH_DESIGN.ARGS.MORE OPTIONS}} \\
+    -value {{-mode out_of_context}} \\
+    -objects [get_runs synth_1]
+
+launch_runs synth_1
+wait_on_run synth_1"
+    )
+}
+
+fn emit_quartus(
+    out: &mut dyn Write,
+    top: &ir::Id,
+    design: &str,
+    device: &str,
+    clock: Option<(ir::Id, u64)>,
+) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "# Run this script using `quartus_sh -t <script>`.
+package require ::quartus::project
+package require ::quartus::flow
+set dir [file dirname [info script]]
+
+project_new [file join $dir {top}] -overwrite
+set_global_assignment -name DEVICE {device}
+set_global_assignment -name SYSTEMVERILOG_FILE [file join $dir {design}]
+set_global_assignment -name TOP_LEVEL_ENTITY {top}",
+        device = device,
+        top = top,
+        design = design
+    )?;
+    if let Some((port, period)) = clock {
+        writeln!(out)?;
+        write_file(
+            out,
+            &format!("{}.sdc", top),
+            &[format!(
+                "create_clock -period {} -name {} [get_ports {}]",
+                period, port, port
+            )],
+        )?;
+        writeln!(
+            out,
+            "set_global_assignment -name SDC_FILE [file join $dir {}.sdc]",
+            top
+        )?;
+    }
+    writeln!(
+        out,
+        "
+execute_module -tool map
+project_close"
+    )
+}
+
+impl Backend for SynthTclBackend {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn validate(ctx: &ir::Context) -> CalyxResult<()> {
+        lowered::validate_lowered(ctx, NAME)?;
+        Options::from(ctx).map(|_| ())
+    }
+
+    fn link_externs(
+        _ctx: &ir::Context,
+        _file: &mut OutputFile,
+    ) -> CalyxResult<()> {
+        Ok(())
+    }
+
+    fn emit(ctx: &ir::Context, file: &mut OutputFile) -> CalyxResult<()> {
+        let opts = Options::from(ctx)?;
+        let top = ctx
+            .components
+            .iter()
+            .find(|comp| comp.name == ctx.entrypoint)
+            .ok_or_else(|| {
+                Error::Misc(format!(
+                    "No entrypoint component `{}`",
+                    ctx.entrypoint
+                ))
+            })?;
+        let clock = clock(top)?;
+
+        // Write the design next to the script.
+        let design = match file {
+            OutputFile::File(path) => {
+                let sv = path.with_extension("sv");
+                VerilogBackend.run(ctx, OutputFile::File(sv.clone()))?;
+                sv.file_name().unwrap().to_string_lossy().to_string()
+            }
+            OutputFile::Stdout | OutputFile::Buffer(_) => {
+                format!("{}.sv", top.name)
+            }
+        };
+
+        let out = &mut file.get_write();
+        writeln!(
+            out,
+            "# Synthesis script for the `{}` component generated by the Calyx compiler.",
+            top.name
+        )?;
+        match opts.tool {
+            Tool::Vivado => {
+                emit_vivado(out, &top.name, &design, opts.device(), clock)?
+            }
+            Tool::Quartus => {
+                emit_quartus(out, &top.name, &design, opts.device(), clock)?
+            }
+        }
+        Ok(())
+    }
+}
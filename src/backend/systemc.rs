@@ -0,0 +1,499 @@
+//! SystemC backend for the Calyx compiler.
+//!
+//! Emits a cycle-approximate SystemC module for every component so that
+//! Calyx designs can be used in SystemC-based architectural simulators.
+//! Like the Verilog backend, it only accepts programs with no control and no
+//! groups.
+
+use crate::backend::lowered;
+use crate::backend::traits::Backend;
+use calyx::{
+    errors::{CalyxResult, Error},
+    ir,
+    utils::OutputFile,
+};
+use ir::{Guard, RRC};
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// Emits a `SC_MODULE` for every component. The combinational logic driving
+/// each port is an `SC_METHOD` that is sensitive to the ports read by its
+/// assignments.
+#[derive(Default)]
+pub struct SystemCBackend;
+
+/// Widest port supported by the SystemC models since all values are
+/// represented using `sc_uint`.
+const MAX_WIDTH: u64 = 64;
+
+/// Returns `Ok` if the continuous assignments of `comp` only use ports that
+/// the SystemC models can represent.
+fn validate_component(comp: &ir::Component) -> CalyxResult<()> {
+    let has_clk = comp.signature.borrow().find_with_attr("clk").is_some();
+    if !has_clk && !comp.debug_logs.is_empty() {
+        return Err(Error::MalformedStructure(format!(
+            "Component `{}` prints values but has no @clk port",
+            comp.name
+        )));
+    }
+    for cell in comp.cells.iter() {
+        let cell = cell.borrow();
+        if let ir::CellType::Primitive { string_binding, .. } = &cell.prototype
+        {
+            if !string_binding.is_empty() {
+                return Err(Error::MalformedStructure(format!(
+                    "Cell `{}` uses string parameters which are not supported by the SystemC models",
+                    cell.name()
+                )));
+            }
+        }
+        if cell.contents.is_some() {
+            return Err(Error::MalformedStructure(format!(
+                "Memory `{}` has initial contents, which are not supported by the SystemC models",
+                cell.name()
+            )));
+        }
+    }
+    for cell in comp.cells.iter().chain(std::iter::once(&comp.signature)) {
+        for port in &cell.borrow().ports {
+            let port = port.borrow();
+            let (cell, name) = port.canonical();
+            if port.width > MAX_WIDTH {
+                return Err(Error::MalformedStructure(format!(
+                    "Port `{}.{}` is {} bits wide but SystemC models support at most {} bits",
+                    cell, name, port.width, MAX_WIDTH
+                )));
+            }
+            if port.direction == ir::Direction::Inout {
+                return Err(Error::MalformedStructure(format!(
+                    "`inout` port `{}.{}` can not be turned into SystemC",
+                    cell, name
+                )));
+            }
+            if !has_clk && port.attributes.has("clk") {
+                return Err(Error::MalformedStructure(format!(
+                    "Cell `{}` requires a clock but component `{}` has no @clk port",
+                    cell, comp.name
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Backend for SystemCBackend {
+    fn name(&self) -> &'static str {
+        "systemc"
+    }
+
+    fn validate(ctx: &ir::Context) -> CalyxResult<()> {
+        lowered::validate_lowered(ctx, "systemc")?;
+        ctx.components.iter().try_for_each(validate_component)
+    }
+
+    /// Copy the SystemC models of the primitives, which are stored next to
+    /// the extern files with the `.h` extension.
+    fn link_externs(
+        ctx: &ir::Context,
+        file: &mut OutputFile,
+    ) -> CalyxResult<()> {
+        let write_err = |err: io::Error| {
+            let std::io::Error { .. } = err;
+            Error::WriteError(format!(
+                "File not found: {}",
+                file.as_path_string()
+            ))
+        };
+        writeln!(file.get_write(), "#include <systemc.h>")
+            .map_err(write_err)?;
+        for extern_path in ctx.lib.extern_paths() {
+            let model = extern_path.with_extension("h");
+            let mut ext = File::open(&model).map_err(|_| {
+                Error::InvalidFile(format!(
+                    "No SystemC model for the primitives in {}. Expected {}",
+                    extern_path.to_string_lossy(),
+                    model.to_string_lossy()
+                ))
+            })?;
+            io::copy(&mut ext, &mut file.get_write()).map_err(write_err)?;
+        }
+        Ok(())
+    }
+
+    fn emit(ctx: &ir::Context, file: &mut OutputFile) -> CalyxResult<()> {
+        let res = {
+            let f = &mut file.get_write();
+            definition_order(ctx).into_iter().try_for_each(|comp| {
+                writeln!(f)?;
+                emit_component(comp, ctx.bc.synthesis_mode, f)
+            })
+        };
+        res.map_err(|err| {
+            let std::io::Error { .. } = err;
+            Error::WriteError(format!(
+                "File not found: {}",
+                file.as_path_string()
+            ))
+        })
+    }
+}
+
+/// Components in an order where every component is defined before the
+/// components that instantiate it, as required by C++.
+fn definition_order(ctx: &ir::Context) -> Vec<&ir::Component> {
+    fn visit<'a>(
+        comp: &'a ir::Component,
+        ctx: &'a ir::Context,
+        visited: &mut HashSet<ir::Id>,
+        order: &mut Vec<&'a ir::Component>,
+    ) {
+        if !visited.insert(comp.name.clone()) {
+            return;
+        }
+        for cell in comp.cells.iter() {
+            if let ir::CellType::Component { name } = &cell.borrow().prototype {
+                if let Some(sub) =
+                    ctx.components.iter().find(|c| c.name == name)
+                {
+                    visit(sub, ctx, visited, order);
+                }
+            }
+        }
+        order.push(comp);
+    }
+
+    let mut visited = HashSet::new();
+    let mut order = Vec::with_capacity(ctx.components.len());
+    for comp in &ctx.components {
+        visit(comp, ctx, &mut visited, &mut order);
+    }
+    order
+}
+
+fn is_clk(port: &ir::Port) -> bool {
+    port.attributes.has("clk")
+}
+
+/// The type carried by `port`. Clocks are `bool`s so that processes can be
+/// sensitive to their edges.
+fn port_type(port: &ir::Port) -> String {
+    if is_clk(port) {
+        "bool".to_string()
+    } else {
+        format!("sc_uint<{}> ", port.width)
+    }
+}
+
+/// Name of the port or signal that carries the value of `port`.
+fn port_name(port: &ir::Port) -> String {
+    match &port.parent {
+        ir::PortParent::Cell(cell) => {
+            let parent_ref = cell.upgrade();
+            let parent = parent_ref.borrow();
+            match parent.prototype {
+                ir::CellType::ThisComponent => port.name.to_string(),
+                _ => format!("{}_{}", parent.name(), port.name),
+            }
+        }
+        ir::PortParent::Group(_) => unreachable!(),
+    }
+}
+
+/// Expression that reads the current value of `port`.
+fn port_to_expr(port_ref: &RRC<ir::Port>) -> String {
+    let port = port_ref.borrow();
+    if let ir::PortParent::Cell(cell) = &port.parent {
+        if let ir::CellType::Constant { val, width } =
+            cell.upgrade().borrow().prototype
+        {
+            // Decimal literals without a suffix are signed.
+            let suffix = if val > i64::MAX as u64 { "ull" } else { "" };
+            return format!("sc_uint<{}>({}{})", width, val, suffix);
+        }
+    }
+    format!("{}.read()", port_name(&port))
+}
+
+/// Returns true if `port` is defined by a constant.
+fn is_constant(port: &RRC<ir::Port>) -> bool {
+    match &port.borrow().parent {
+        ir::PortParent::Cell(cell) => matches!(
+            cell.upgrade().borrow().prototype,
+            ir::CellType::Constant { .. }
+        ),
+        ir::PortParent::Group(_) => false,
+    }
+}
+
+/// Transforms `guard` into a C++ expression. Operands of other operators are
+/// parenthesized.
+fn guard_to_expr(guard: &Guard, nested: bool) -> String {
+    let expr = match guard {
+        Guard::And(l, r) => {
+            format!("{} && {}", guard_to_expr(l, true), guard_to_expr(r, true))
+        }
+        Guard::Or(l, r) => {
+            format!("{} || {}", guard_to_expr(l, true), guard_to_expr(r, true))
+        }
+        Guard::Eq(l, r)
+        | Guard::Neq(l, r)
+        | Guard::Gt(l, r)
+        | Guard::Lt(l, r)
+        | Guard::Geq(l, r)
+        | Guard::Leq(l, r) => format!(
+            "{} {} {}",
+            port_to_expr(l),
+            guard.op_str(),
+            port_to_expr(r)
+        ),
+        Guard::Not(g) => return format!("!{}", guard_to_expr(g, true)),
+        Guard::Port(p) => return port_to_expr(p),
+        Guard::True => return "true".to_string(),
+    };
+    if nested {
+        format!("({})", expr)
+    } else {
+        expr
+    }
+}
+
+/// Escapes `s` so that it can be used in a C++ string literal.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn emit_component(
+    comp: &ir::Component,
+    synthesis_mode: bool,
+    f: &mut dyn io::Write,
+) -> io::Result<()> {
+    let sig = comp.signature.borrow();
+    let clk = sig.find_with_attr("clk").map(|p| p.borrow().name.clone());
+    // Members that are named in the constructor.
+    let mut members = vec![];
+
+    writeln!(f, "SC_MODULE({}) {{", comp.name)?;
+    for port_ref in &sig.ports {
+        let port = port_ref.borrow();
+        // NOTE: The signature port definitions are reversed inside the component.
+        let dir = match port.direction {
+            ir::Direction::Input => "sc_out",
+            ir::Direction::Output => "sc_in",
+            ir::Direction::Inout => unreachable!(),
+        };
+        writeln!(f, "  {}<{}> {};", dir, port_type(&port), port.name)?;
+        members.push(port.name.to_string());
+    }
+
+    let instances = comp
+        .cells
+        .iter()
+        .filter(|cell| {
+            matches!(
+                cell.borrow().prototype,
+                ir::CellType::Primitive { .. } | ir::CellType::Component { .. }
+            )
+        })
+        .collect_vec();
+
+    // Signals connected to the ports of cell instances. Clocks are connected
+    // directly to the clock of the component.
+    for cell in &instances {
+        for port_ref in &cell.borrow().ports {
+            let port = port_ref.borrow();
+            if !is_clk(&port) {
+                let name = port_name(&port);
+                writeln!(f, "  sc_signal<{}> {};", port_type(&port), name)?;
+                members.push(name);
+            }
+        }
+    }
+
+    for cell_ref in &instances {
+        let cell = cell_ref.borrow();
+        match &cell.prototype {
+            ir::CellType::Primitive {
+                name,
+                param_binding,
+                ..
+            } => writeln!(
+                f,
+                "  {}<{}> {};",
+                name,
+                param_binding.iter().map(|(_, v)| v).join(", "),
+                cell.name()
+            )?,
+            ir::CellType::Component { name } => {
+                writeln!(f, "  {} {};", name, cell.name())?
+            }
+            _ => unreachable!(),
+        }
+        members.push(cell.name().to_string());
+    }
+
+    // gather assignments keyed by destination
+    let mut map: HashMap<_, (RRC<ir::Port>, Vec<_>)> = HashMap::new();
+    for asgn in &comp.continuous_assignments {
+        // Clocks are connected directly.
+        if is_clk(&asgn.dst.borrow()) {
+            continue;
+        }
+        map.entry(asgn.dst.borrow().canonical())
+            .and_modify(|(_, v)| v.push(asgn))
+            .or_insert((Rc::clone(&asgn.dst), vec![asgn]));
+    }
+    let assigns = map
+        .into_values()
+        .sorted_by_key(|(port, _)| port.borrow().canonical())
+        .collect_vec();
+    let print_debug = !synthesis_mode && !comp.debug_logs.is_empty();
+
+    // Constructor
+    writeln!(f)?;
+    writeln!(f, "  SC_CTOR({})", comp.name)?;
+    writeln!(
+        f,
+        "      : {} {{",
+        members
+            .iter()
+            .map(|name| format!("{}(\"{}\")", name, name))
+            .join(",\n        ")
+    )?;
+    for cell_ref in &instances {
+        let cell = cell_ref.borrow();
+        for port_ref in &cell.ports {
+            let port = port_ref.borrow();
+            let signal = if is_clk(&port) {
+                clk.as_ref().unwrap().to_string()
+            } else {
+                port_name(&port)
+            };
+            writeln!(f, "    {}.{}({});", cell.name(), port.name, signal)?;
+        }
+    }
+    for (dst, asgns) in &assigns {
+        writeln!(f, "    SC_METHOD(assign_{});", port_name(&dst.borrow()))?;
+        let reads = asgns
+            .iter()
+            .flat_map(|asgn| {
+                asgn.guard
+                    .all_ports()
+                    .into_iter()
+                    .chain(std::iter::once(Rc::clone(&asgn.src)))
+            })
+            .filter(|port| !is_constant(port))
+            .map(|port| port_name(&port.borrow()))
+            .unique()
+            .collect_vec();
+        if !reads.is_empty() {
+            writeln!(f, "    sensitive << {};", reads.join(" << "))?;
+        }
+    }
+    if print_debug {
+        writeln!(f, "    SC_METHOD(print_debug);")?;
+        writeln!(f, "    sensitive << {}.pos();", clk.as_ref().unwrap())?;
+        writeln!(f, "    dont_initialize();")?;
+    }
+    writeln!(f, "  }}")?;
+
+    for asgns in &assigns {
+        writeln!(f)?;
+        emit_assignment(asgns, f)?;
+    }
+    if print_debug {
+        writeln!(f)?;
+        emit_debug_logs(&comp.debug_logs, f)?;
+    }
+    writeln!(f, "}};")
+}
+
+/// Generates a method that writes the source of the first assignment whose
+/// guard is true and 0 when none of the guards are active.
+///
+/// Example:
+/// ```
+/// // Input Calyx code
+/// a.in = foo ? 2'd0;
+/// a.in = bar ? 2'd1;
+/// ```
+/// Into:
+/// ```
+/// void assign_a_in() {
+///   if (foo_out.read()) {
+///     a_in.write(sc_uint<2>(0));
+///   } else if (bar_out.read()) {
+///     a_in.write(sc_uint<2>(1));
+///   } else {
+///     a_in.write(sc_uint<2>(0));
+///   }
+/// }
+/// ```
+fn emit_assignment(
+    (dst_ref, assignments): &(RRC<ir::Port>, Vec<&ir::Assignment>),
+    f: &mut dyn io::Write,
+) -> io::Result<()> {
+    let dst = dst_ref.borrow();
+    let name = port_name(&dst);
+    writeln!(f, "  void assign_{}() {{", name)?;
+    let mut branches = vec![];
+    let mut default = format!("sc_uint<{}>(0)", dst.width);
+    for asgn in assignments {
+        if asgn.guard.is_true() {
+            default = port_to_expr(&asgn.src);
+            break;
+        }
+        branches
+            .push((guard_to_expr(&asgn.guard, false), port_to_expr(&asgn.src)));
+    }
+    if branches.is_empty() {
+        writeln!(f, "    {}.write({});", name, default)?;
+    } else {
+        for (idx, (guard, src)) in branches.iter().enumerate() {
+            let kw = if idx == 0 { "if" } else { "} else if" };
+            writeln!(f, "    {} ({}) {{", kw, guard)?;
+            writeln!(f, "      {}.write({});", name, src)?;
+        }
+        writeln!(f, "    }} else {{")?;
+        writeln!(f, "      {}.write({});", name, default)?;
+        writeln!(f, "    }}")?;
+    }
+    writeln!(f, "  }}")
+}
+
+/// Generates a method that prints the values of compiled `@debug` statements
+/// on the clock edges they are enabled in:
+/// ```
+/// void print_debug() {
+///   if (debug_en_out.read()) {
+///     std::cout << "i = " << debug_arg_out.read() << std::endl;
+///   }
+/// }
+/// ```
+fn emit_debug_logs(
+    logs: &[ir::DebugLog],
+    f: &mut dyn io::Write,
+) -> io::Result<()> {
+    writeln!(f, "  void print_debug() {{")?;
+    for log in logs {
+        let segments = log
+            .debug
+            .segments()
+            .map(|seg| format!("\"{}\"", escape(seg)))
+            .collect_vec();
+        let args = log.debug.ports.iter().map(port_to_expr);
+        let mut parts = segments
+            .into_iter()
+            .interleave(args)
+            .filter(|part| part != "\"\"")
+            .chain(std::iter::once("std::endl".to_string()));
+        writeln!(f, "    if ({}) {{", port_to_expr(&log.enable))?;
+        writeln!(f, "      std::cout << {};", parts.join(" << "))?;
+        writeln!(f, "    }}")?;
+    }
+    writeln!(f, "  }}")
+}
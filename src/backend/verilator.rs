@@ -0,0 +1,326 @@
+//! C++ driver for simulating the output of the Verilog backend with
+//! Verilator.
+//!
+//! The driver is specialized to the `@external` memories of the top-level
+//! component. It loads their contents from `<DATA>/<name>.dat` before the
+//! simulation starts and writes them to `<DATA>/<name>.out` once the
+//! simulation ends, where `<DATA>` is given using the `+DATA=<dir>`
+//! argument. The files contain one hexadecimal value per line, which is the
+//! format used by `$readmemh` and `$writememh`.
+//!
+//! Since the driver accesses the memories directly, the design has to be
+//! built with `--public-flat-rw`.
+
+use calyx::{
+    errors::{CalyxResult, Error},
+    ir,
+};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Widest memory supported by the driver since the values are stored in
+/// 64-bit integers.
+const MAX_WIDTH: u64 = 64;
+
+/// An `@external` memory of the top-level component.
+struct Memory {
+    name: ir::Id,
+    width: u64,
+    /// Size of each dimension.
+    dims: Vec<u64>,
+}
+
+impl Memory {
+    /// The memory implemented by `cell`. Returns `None` if `cell` is not an
+    /// `@external` memory.
+    fn from_cell(cell: &ir::Cell) -> CalyxResult<Option<Self>> {
+        if cell.get_attribute("external").is_none() {
+            return Ok(None);
+        }
+        let dims = match cell.type_name() {
+            Some(name) if name == "std_mem_d1" => vec!["SIZE"],
+            Some(name) if name == "std_mem_d2" => vec!["D0_SIZE", "D1_SIZE"],
+            Some(name) if name == "std_mem_d3" => {
+                vec!["D0_SIZE", "D1_SIZE", "D2_SIZE"]
+            }
+            Some(name) if name == "std_mem_d4" => {
+                vec!["D0_SIZE", "D1_SIZE", "D2_SIZE", "D3_SIZE"]
+            }
+            Some(name) if name.as_ref().contains("mem") => {
+                return Err(Error::MalformedStructure(format!(
+                    "External memory `{}` uses `{}` but the Verilator driver only supports `std_mem_d1` to `std_mem_d4`",
+                    cell.name(),
+                    name
+                )))
+            }
+            _ => return Ok(None),
+        };
+        let width = cell.get_parameter("WIDTH").unwrap();
+        if width > MAX_WIDTH {
+            return Err(Error::MalformedStructure(format!(
+                "External memory `{}` is {} bits wide but the Verilator driver supports at most {} bits",
+                cell.name(),
+                width,
+                MAX_WIDTH
+            )));
+        }
+        Ok(Some(Memory {
+            name: cell.name().clone(),
+            width,
+            dims: dims
+                .into_iter()
+                .map(|dim| cell.get_parameter(dim).unwrap())
+                .collect(),
+        }))
+    }
+
+    fn size(&self) -> u64 {
+        self.dims.iter().product()
+    }
+}
+
+/// Write the driver for the component `comp` to `path`.
+pub fn write_harness(comp: &ir::Component, path: &Path) -> CalyxResult<()> {
+    let memories = comp
+        .cells
+        .iter()
+        .filter_map(|cell| Memory::from_cell(&cell.borrow()).transpose())
+        .collect::<CalyxResult<Vec<_>>>()?;
+    fs::write(path, harness(comp.name.as_ref(), &memories)).map_err(|err| {
+        Error::WriteError(format!(
+            "Failed to write Verilator driver to {}: {}",
+            path.to_string_lossy(),
+            err
+        ))
+    })
+}
+
+/// Visits the elements of `mem` in the order used by `$readmemh`.
+fn emit_each(top: &str, mem: &Memory, out: &mut String) -> std::fmt::Result {
+    writeln!(
+        out,
+        "// {}: {} bits x {}",
+        mem.name,
+        mem.width,
+        mem.dims
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>()
+            .join(" x ")
+    )?;
+    writeln!(
+        out,
+        "template <typename F> void each_{}(V{} *top, F f) {{",
+        mem.name, top
+    )?;
+    writeln!(
+        out,
+        "  auto &mem = top->rootp->{}__DOT__{}__DOT__mem;",
+        top, mem.name
+    )?;
+    let mut idx = String::new();
+    for (dim, size) in mem.dims.iter().enumerate() {
+        writeln!(
+            out,
+            "{:indent$}for (size_t i{dim} = 0; i{dim} < {size}; ++i{dim})",
+            "",
+            indent = 2 * (dim + 1),
+            dim = dim,
+            size = size
+        )?;
+        write!(idx, "[i{}]", dim)?;
+    }
+    writeln!(
+        out,
+        "{:indent$}f(mem{});",
+        "",
+        idx,
+        indent = 2 * (mem.dims.len() + 1)
+    )?;
+    writeln!(out, "}}")
+}
+
+/// Source of the driver for the top-level module `top` with the given
+/// memories.
+fn harness(top: &str, memories: &[Memory]) -> String {
+    let mut out = String::new();
+    emit_harness(top, memories, &mut out).unwrap();
+    out
+}
+
+fn emit_harness(
+    top: &str,
+    memories: &[Memory],
+    out: &mut String,
+) -> std::fmt::Result {
+    writeln!(
+        out,
+        "// Verilator driver for `{top}` generated by the Calyx compiler.
+// Build with:
+//   verilator --cc --exe --build --public-flat-rw --top-module {top} <design>.sv <driver>.cpp
+// Usage: V{top} <trace.vcd> [cycles] [--trace] +DATA=<dir>
+#include \"V{top}.h\"
+#include \"V{top}___024root.h\"
+#include \"verilated.h\"
+#include \"verilated_vcd_c.h\"
+#include <cstdint>
+#include <cstdlib>
+#include <cstring>
+#include <fstream>
+#include <iostream>
+#include <memory>
+#include <string>
+#include <vector>
+
+vluint64_t GLOBAL_sc_time = 0;
+double sc_time_stamp() {{ return GLOBAL_sc_time; }}
+
+// Reads `size` hexadecimal values from `path`. Lines starting with `//` are
+// comments and missing values are zero.
+std::vector<uint64_t> read_dat(const std::string &path, size_t size) {{
+  std::vector<uint64_t> values;
+  std::ifstream in(path);
+  if (!in) {{
+    std::cerr << \"[Verilator] ERROR: Failed to read \" << path << \"\\n\";
+    std::exit(1);
+  }}
+  std::string line;
+  while (values.size() < size && std::getline(in, line)) {{
+    if (line.empty() || line.rfind(\"//\", 0) == 0)
+      continue;
+    values.push_back(std::stoull(line, nullptr, 16));
+  }}
+  values.resize(size, 0);
+  return values;
+}}
+
+void write_dat(const std::string &path, const std::vector<uint64_t> &values,
+               int digits) {{
+  std::ofstream out(path);
+  out << std::hex;
+  for (uint64_t value : values) {{
+    out.width(digits);
+    out.fill('0');
+    out << value << \"\\n\";
+  }}
+}}
+",
+        top = top
+    )?;
+
+    for mem in memories {
+        writeln!(out)?;
+        emit_each(top, mem, out)?;
+    }
+
+    writeln!(
+        out,
+        "\nvoid load_memories(V{} *top, const std::string &data) {{",
+        top
+    )?;
+    for mem in memories {
+        let mask = if mem.width == MAX_WIDTH {
+            "~0ULL".to_string()
+        } else {
+            format!("0x{:x}ULL", (1u64 << mem.width) - 1)
+        };
+        writeln!(out, "  {{")?;
+        writeln!(
+            out,
+            "    auto values = read_dat(data + \"/{}.dat\", {});",
+            mem.name,
+            mem.size()
+        )?;
+        writeln!(out, "    size_t idx = 0;")?;
+        writeln!(
+            out,
+            "    each_{}(top, [&](auto &v) {{ v = values[idx++] & {}; }});",
+            mem.name, mask
+        )?;
+        writeln!(out, "  }}")?;
+    }
+    writeln!(out, "}}")?;
+
+    writeln!(
+        out,
+        "\nvoid dump_memories(V{} *top, const std::string &data) {{",
+        top
+    )?;
+    for mem in memories {
+        writeln!(out, "  {{")?;
+        writeln!(out, "    std::vector<uint64_t> values;")?;
+        writeln!(
+            out,
+            "    each_{}(top, [&](auto &v) {{ values.push_back(v); }});",
+            mem.name
+        )?;
+        writeln!(
+            out,
+            "    write_dat(data + \"/{}.out\", values, {});",
+            mem.name,
+            mem.width.div_ceil(4)
+        )?;
+        writeln!(out, "  }}")?;
+    }
+    writeln!(out, "}}")?;
+
+    write!(
+        out,
+        "
+int main(int argc, char **argv) {{
+  Verilated::commandArgs(argc, argv);
+  auto top = std::make_unique<V{top}>();
+
+  // Number of cycles after which the simulation times out.
+  const uint64_t n_cycles = argc >= 3 ? std::stoull(argv[2]) : 500000000;
+  const bool trace_requested =
+      argc >= 4 && std::strcmp(argv[3], \"--trace\") == 0;
+  std::unique_ptr<VerilatedVcdC> tfp;
+  if (trace_requested) {{
+    Verilated::traceEverOn(true);
+    tfp = std::make_unique<VerilatedVcdC>();
+    top->trace(tfp.get(), 99);
+    tfp->open(argv[1]);
+  }}
+
+  // Directory containing the contents of the memories.
+  std::string data = Verilated::commandArgsPlusMatch(\"DATA=\");
+  data = data.empty() ? \".\" : data.substr(std::strlen(\"+DATA=\"));
+
+  std::cout << \"[Verilator] Simulation begin\\n\";
+  top->go = 0;
+  top->clk = 0;
+  top->reset = 1;
+  top->eval();
+  load_memories(top.get(), data);
+
+  top->reset = 0;
+  top->go = 1;
+  uint64_t cycles = 0;
+  for (; cycles < n_cycles && top->done == 0; ++cycles, ++GLOBAL_sc_time) {{
+    if (trace_requested)
+      tfp->dump(2 * cycles);
+    top->clk = 1;
+    top->eval();
+    if (trace_requested)
+      tfp->dump(2 * cycles + 1);
+    top->clk = 0;
+    top->eval();
+  }}
+
+  const bool timed_out = cycles == n_cycles && top->done == 0;
+  std::cout << \"[Verilator]\"
+            << (timed_out ? \" ERROR: Program reached limit of \"
+                          : \" Simulated \")
+            << cycles << \" cycles\\n\";
+  dump_memories(top.get(), data);
+  top->final();
+  if (trace_requested)
+    tfp->close();
+  return timed_out ? 2 : 0;
+}}
+",
+        top = top
+    )
+}
@@ -3,16 +3,21 @@
 //! Transforms an [`ir::Context`](crate::ir::Context) into a formatted string that represents a
 //! valid SystemVerilog program.
 
+use crate::backend::bindings::Bindings;
+use crate::backend::lowered;
 use crate::backend::traits::Backend;
+use crate::backend::verilator;
 use calyx::{
+    analysis::GuardDisjointness,
     errors::{CalyxResult, Error},
     ir,
-    utils::OutputFile,
+    utils::{self, OutputFile},
 };
-use ir::{Control, Group, Guard, RRC};
+use ir::{Guard, RRC};
 use itertools::Itertools;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io;
+use std::path::Path;
 use std::{collections::HashMap, rc::Rc};
 use vast::v17::ast as v;
 
@@ -21,71 +26,24 @@ use vast::v17::ast as v;
 #[derive(Default)]
 pub struct VerilogBackend;
 
-/// Checks to make sure that there are no holes being
-/// used in a guard.
-fn validate_guard(guard: &ir::Guard) -> bool {
-    match guard {
-        Guard::Or(left, right) | Guard::And(left, right) => {
-            validate_guard(left) && validate_guard(right)
-        }
-        Guard::Eq(left, right)
-        | Guard::Neq(left, right)
-        | Guard::Gt(left, right)
-        | Guard::Lt(left, right)
-        | Guard::Geq(left, right)
-        | Guard::Leq(left, right) => {
-            !left.borrow().is_hole() && !right.borrow().is_hole()
-        }
-        Guard::Not(inner) => validate_guard(inner),
-        Guard::Port(port) => !port.borrow().is_hole(),
-        Guard::True => true,
-    }
-}
-
-/// Returns `Ok` if there are no groups defined.
-fn validate_structure<'a, I>(groups: I) -> CalyxResult<()>
-where
-    I: Iterator<Item = &'a RRC<Group>>,
-{
-    for group in groups {
-        for asgn in &group.borrow().assignments {
-            let port = asgn.dst.borrow();
-            // check if port is a hole
-            if port.is_hole() {
-                return Err(Error::MalformedStructure(
-                    "Groups / Holes can not be turned into Verilog".to_string(),
-                ));
-            }
-
-            // validate guard
-            if !validate_guard(&asgn.guard) {
-                return Err(Error::MalformedStructure(
-                    "Groups / Holes can not be turned into Verilog".to_string(),
-                ));
-            };
-        }
-    }
-    Ok(())
-}
-
-/// Returns `Ok` if the control for `comp` is either a single `enable`
-/// or `empty`.
-fn validate_control(ctrl: &ir::Control) -> CalyxResult<()> {
-    match ctrl {
-        Control::Empty(_) => Ok(()),
-        _ => Err(Error::MalformedControl("Control must be empty".to_string())),
-    }
-}
-
 impl Backend for VerilogBackend {
     fn name(&self) -> &'static str {
         "verilog"
     }
 
     fn validate(ctx: &ir::Context) -> CalyxResult<()> {
+        lowered::validate_lowered(ctx, "verilog")?;
+        if let Some(path) = &ctx.bc.bindings {
+            Bindings::from_file(path)?.validate(&ctx.lib)?;
+        }
         for component in &ctx.components {
-            validate_structure(component.groups.iter())?;
-            validate_control(&component.control.borrow())?;
+            // Report malformed ROM contents before anything is emitted.
+            for cell in component.cells.iter() {
+                let cell = cell.borrow();
+                if is_rom(&cell) {
+                    rom_contents(&cell)?;
+                }
+            }
         }
         Ok(())
     }
@@ -97,6 +55,11 @@ impl Backend for VerilogBackend {
         ctx: &ir::Context,
         file: &mut OutputFile,
     ) -> CalyxResult<()> {
+        // The file list of the per-module output refers to the extern files
+        // instead.
+        if ctx.bc.per_module_dir.is_some() {
+            return Ok(());
+        }
         for extern_path in ctx.lib.extern_paths() {
             // The extern file is guaranteed to exist by the frontend.
             let mut ext = File::open(extern_path).unwrap();
@@ -112,6 +75,10 @@ impl Backend for VerilogBackend {
     }
 
     fn emit(ctx: &ir::Context, file: &mut OutputFile) -> CalyxResult<()> {
+        let bindings = match &ctx.bc.bindings {
+            Some(path) => Bindings::from_file(path)?,
+            None => Bindings::default(),
+        };
         let modules = &ctx
             .components
             .iter()
@@ -121,10 +88,31 @@ impl Backend for VerilogBackend {
                     ctx.bc.synthesis_mode,
                     ctx.bc.enable_verification,
                     ctx.bc.initialize_inputs,
+                    ctx.bc.verilator_harness.is_none(),
+                    &bindings,
                 )
-                .to_string()
             })
-            .collect::<Vec<_>>();
+            .collect::<CalyxResult<Vec<_>>>()?;
+
+        // The driver loads and dumps the memories of the top-level component
+        // instead of the Verilog design.
+        if let Some(path) = &ctx.bc.verilator_harness {
+            let top = ctx
+                .components
+                .iter()
+                .find(|comp| comp.name == ctx.entrypoint)
+                .ok_or_else(|| {
+                    Error::Misc(format!(
+                        "No top-level component `{}` to generate a Verilator driver for",
+                        ctx.entrypoint
+                    ))
+                })?;
+            verilator::write_harness(top, path)?;
+        }
+
+        if let Some(dir) = &ctx.bc.per_module_dir {
+            return write_per_module(ctx, dir, modules);
+        }
 
         write!(file.get_write(), "{}", modules.join("\n")).map_err(|err| {
             let std::io::Error { .. } = err;
@@ -137,32 +125,128 @@ impl Backend for VerilogBackend {
     }
 }
 
+/// Name of the file list written by [`write_per_module`].
+const FILE_LIST: &str = "files.f";
+
+/// Write each of the emitted `modules` to `<dir>/<component>.sv` along with
+/// a file list that names the extern files followed by the module files in
+/// the order the components are defined.
+fn write_per_module(
+    ctx: &ir::Context,
+    dir: &Path,
+    modules: &[String],
+) -> CalyxResult<()> {
+    let write_err = |path: &Path, err: io::Error| {
+        Error::WriteError(format!(
+            "Failed to write {}: {}",
+            path.to_string_lossy(),
+            err
+        ))
+    };
+    fs::create_dir_all(dir).map_err(|err| write_err(dir, err))?;
+    let mut files = ctx
+        .lib
+        .extern_paths()
+        .into_iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect_vec();
+    for (comp, module) in ctx.components.iter().zip(modules) {
+        let path = dir.join(format!("{}.sv", comp.name));
+        fs::write(&path, module).map_err(|err| write_err(&path, err))?;
+        files.push(path.to_string_lossy().to_string());
+    }
+    let list = dir.join(FILE_LIST);
+    let mut contents = files.join("\n");
+    contents.push('\n');
+    fs::write(&list, contents).map_err(|err| write_err(&list, err))
+}
+
+/// Calyx attributes on components and cells that are emitted as synthesis
+/// attributes on the corresponding Verilog modules and instances, along with
+/// the value of the synthesis attribute. These allow resources in the
+/// synthesized design to be mapped back to Calyx components and cells.
+const SYNTHESIS_ATTRIBUTES: [(&str, &str); 2] =
+    [("keep_hierarchy", "yes"), ("dont_touch", "true")];
+
+/// Synthesis attributes for a module or instance with the given attributes.
+fn synthesis_attributes(attrs: &ir::Attributes) -> Option<v::Attribute> {
+    let mut attr = v::Attribute::default();
+    for (name, value) in SYNTHESIS_ATTRIBUTES {
+        if attrs.has(name) {
+            attr.add_stmt(name, value);
+        }
+    }
+    if attr.attrs().is_empty() {
+        None
+    } else {
+        Some(attr)
+    }
+}
+
+/// Attributes of the interface ports, which are implied by the port names
+/// in the generated module.
+const INTERFACE_ATTRIBUTES: [&str; 4] = ["go", "done", "clk", "reset"];
+
+/// Verilog attribute for a signature port with the given attributes. All
+/// attributes other than the interface attributes are emitted with their
+/// values so that downstream tools can use them.
+fn port_attributes(attrs: &ir::Attributes) -> Option<String> {
+    let stmts = attrs
+        .iter_values()
+        .filter(|(name, _)| !INTERFACE_ATTRIBUTES.contains(&name.as_str()))
+        .map(|(name, value)| format!("{} = {}", name, value))
+        .collect_vec();
+    if stmts.is_empty() {
+        None
+    } else {
+        Some(format!("(*{}*)", stmts.join(", ")))
+    }
+}
+
+/// Print the header of the module for `comp`, which declares its ports. vast
+/// can represent neither `inout` ports nor attributes on ports, so the header
+/// is printed here instead.
+fn emit_header(comp: &ir::Component) -> String {
+    let ports = comp
+        .signature
+        .borrow()
+        .ports
+        .iter()
+        .map(|port_ref| {
+            let port = port_ref.borrow();
+            let decl = v::Decl::new_logic(port.name.as_ref(), port.width);
+            // NOTE: The signature port definitions are reversed inside the
+            // component.
+            let dir = match port.direction {
+                ir::Direction::Input => "output",
+                ir::Direction::Output => "input",
+                ir::Direction::Inout => "inout wire",
+            };
+            match port_attributes(&port.attributes) {
+                Some(attr) => format!("    {} {} {}", attr, dir, decl),
+                None => format!("    {} {}", dir, decl),
+            }
+        })
+        .join(",\n");
+    format!("module {} (\n{}\n);", comp.name, ports)
+}
+
 fn emit_component(
     comp: &ir::Component,
     synthesis_mode: bool,
     enable_verification: bool,
     initialize_inputs: bool,
-) -> v::Module {
+    memory_io: bool,
+    bindings: &Bindings,
+) -> CalyxResult<String> {
+    // The ports of the module are printed by [emit_header].
     let mut module = v::Module::new(comp.name.as_ref());
-    let sig = comp.signature.borrow();
-    for port_ref in &sig.ports {
-        let port = port_ref.borrow();
-        // NOTE: The signature port definitions are reversed inside the component.
-        match port.direction {
-            ir::Direction::Input => {
-                module.add_output(port.name.as_ref(), port.width);
-            }
-            ir::Direction::Output => {
-                module.add_input(port.name.as_ref(), port.width);
-            }
-            ir::Direction::Inout => {
-                panic!("Unexpected Inout port on Component: {}", port.name)
-            }
-        }
+    if let Some(attr) = synthesis_attributes(&comp.attributes) {
+        module.set_attr(attr);
     }
 
     // Add memory initial and final blocks
-    if !synthesis_mode {
+    if !synthesis_mode && memory_io {
         memory_read_write(comp).into_iter().for_each(|stmt| {
             module.add_stmt(stmt);
         });
@@ -194,12 +278,17 @@ fn emit_component(
     }
 
     // cell instances
-    comp.cells
-        .iter()
-        .filter_map(|cell| cell_instance(&cell.borrow()))
-        .for_each(|instance| {
+    for cell in comp.cells.iter() {
+        let cell = cell.borrow();
+        if is_rom(&cell) {
+            module.add_process(emit_rom(&cell)?);
+        } else if let Some(instance) = cell_instance(&cell, bindings) {
             module.add_instance(instance);
-        });
+            if let Some(contents) = &cell.contents {
+                module.add_process(memory_initial(&cell, contents));
+            }
+        }
+    }
 
     // gather assignments keyed by destination
     let mut map: HashMap<_, (RRC<ir::Port>, Vec<_>)> = HashMap::new();
@@ -226,8 +315,22 @@ fn emit_component(
 
     if !synthesis_mode {
         module.add_process(checks);
+        if !comp.debug_logs.is_empty() {
+            module.add_process(emit_debug_logs(&comp.debug_logs));
+        }
     }
-    module
+
+    // vast prints the attributes of the module followed by its header, which
+    // ends at the first `;`, and its body. Only the header is replaced.
+    let out = module.to_string();
+    let start = out.find("module ").unwrap_or(0);
+    let end = out.find(';').map_or(start, |idx| idx + 1);
+    Ok(format!(
+        "{}{}{}",
+        &out[..start],
+        emit_header(comp),
+        &out[end..]
+    ))
 }
 
 fn wire_decls(cell: &ir::Cell) -> Vec<(String, u64, ir::Direction)> {
@@ -256,28 +359,149 @@ fn wire_decls(cell: &ir::Cell) -> Vec<(String, u64, ir::Direction)> {
         .collect()
 }
 
-fn cell_instance(cell: &ir::Cell) -> Option<v::Instance> {
+/// Returns true if `cell` is a `std_rom`.
+fn is_rom(cell: &ir::Cell) -> bool {
+    matches!(&cell.prototype, ir::CellType::Primitive { name, .. } if name == "std_rom")
+}
+
+/// Contents of the `std_rom` cell `cell`.
+fn rom_contents(cell: &ir::Cell) -> CalyxResult<Vec<u128>> {
+    utils::read_rom_file(
+        cell.get_string_parameter("INIT_FILE").unwrap(),
+        cell.get_parameter("WIDTH").unwrap(),
+        cell.get_parameter("SIZE").unwrap(),
+    )
+}
+
+/// Implement the `std_rom` cell `cell` using a case statement over the
+/// contents of its `INIT_FILE`. The file is read when the program is compiled
+/// so the generated design does not depend on it.
+fn emit_rom(cell: &ir::Cell) -> CalyxResult<v::ParallelProcess> {
+    let width = cell.get_parameter("WIDTH").unwrap();
+    let idx_size = cell.get_parameter("IDX_SIZE").unwrap();
+    let contents = rom_contents(cell)?;
+
+    let name = cell.name();
+    let read_data = format!("{}_read_data", name);
+    let mut case = v::Case::new(v::Expr::new_ref(format!("{}_addr0", name)));
+    for (idx, val) in contents.iter().enumerate() {
+        let mut branch = v::CaseBranch::new(v::Expr::new_ulit_dec(
+            idx_size as u32,
+            &idx.to_string(),
+        ));
+        branch.add_seq(v::Sequential::new_blk_assign(
+            v::Expr::new_ref(&read_data),
+            v::Expr::new_ulit_hex(width as u32, &format!("{:x}", val)),
+        ));
+        case.add_branch(branch);
+    }
+    // Addresses outside of the ROM read zero.
+    let mut default = v::CaseDefault::default();
+    default.add_seq(v::Sequential::new_blk_assign(
+        v::Expr::new_ref(&read_data),
+        v::Expr::new_ulit_dec(width as u32, "0"),
+    ));
+    case.set_default(default);
+
+    let mut process = v::ParallelProcess::new_always_comb();
+    process.add_case(case);
+    Ok(process)
+}
+
+/// Initialize the elements of the memory `cell` with `contents`:
+/// ```
+/// initial begin
+///   <mem_name>.mem[0][0] = 32'h1;
+///   ...
+/// end
+/// ```
+fn memory_initial(cell: &ir::Cell, contents: &[u128]) -> v::ParallelProcess {
+    let width = cell.get_parameter("WIDTH").unwrap();
+    let dims = cell.memory_dimensions().unwrap();
+    let mut initial = v::ParallelProcess::new_initial();
+    for (idx, val) in contents.iter().enumerate() {
+        // Indices of the element in each dimension, outermost first.
+        let mut rest = idx as u64;
+        let mut indices = vec![];
+        for dim in dims.iter().rev() {
+            indices.push(format!("[{}]", rest % dim));
+            rest /= dim;
+        }
+        indices.reverse();
+        initial.add_seq(v::Sequential::new_blk_assign(
+            v::Expr::new_ipath(&format!(
+                "{}.mem{}",
+                cell.name(),
+                indices.join("")
+            )),
+            v::Expr::new_ulit_hex(width as u32, &format!("{:x}", val)),
+        ));
+    }
+    initial
+}
+
+/// Instantiate a cell. Primitives with a vendor binding are instantiated
+/// using the vendor module and its parameter and port names.
+fn cell_instance(cell: &ir::Cell, bindings: &Bindings) -> Option<v::Instance> {
     match cell.type_name() {
         Some(ty_name) => {
-            let mut inst =
-                v::Instance::new(cell.name().as_ref(), ty_name.as_ref());
+            let binding = match &cell.prototype {
+                ir::CellType::Primitive { .. } => {
+                    bindings.get(ty_name.as_ref())
+                }
+                _ => None,
+            };
+            let module =
+                binding.map_or(ty_name.as_ref(), |b| b.module.as_str());
+            let mut inst = v::Instance::new(cell.name().as_ref(), module);
+            if let Some(attr) = synthesis_attributes(&cell.attributes) {
+                inst.set_attr(attr);
+            }
 
-            if let ir::CellType::Primitive { param_binding, .. } =
-                &cell.prototype
+            if let ir::CellType::Primitive {
+                param_binding,
+                string_binding,
+                ..
+            } = &cell.prototype
             {
-                param_binding.iter().for_each(|(name, width)| {
-                    inst.add_param(
-                        name.as_ref(),
-                        v::Expr::new_int(*width as i32),
+                param_binding
+                    .iter()
+                    .map(|(name, width)| {
+                        (name, v::Expr::new_int(*width as i32))
+                    })
+                    .chain(
+                        string_binding.iter().map(|(name, value)| {
+                            (name, v::Expr::new_str(value))
+                        }),
                     )
-                })
+                    .for_each(|(name, value)| {
+                        let name = match binding {
+                            Some(b) => b.param(name.as_ref()),
+                            None => Some(name.as_ref()),
+                        };
+                        if let Some(name) = name {
+                            inst.add_param(name, value)
+                        }
+                    })
             }
 
             for port in &cell.ports {
-                inst.connect(
-                    port.borrow().name.as_ref(),
-                    port_to_ref(Rc::clone(port)),
-                );
+                let port_ref = port.borrow();
+                let name = port_ref.name.as_ref();
+                let name = match binding {
+                    Some(b) => b.port(name),
+                    None => Some(name),
+                };
+                if let Some(name) = name {
+                    inst.connect(name, port_to_ref(Rc::clone(port)));
+                }
+            }
+            if let Some(b) = binding {
+                let mut tied = b.tie.iter().collect::<Vec<_>>();
+                tied.sort();
+                for (name, value) in tied {
+                    inst.connect(name, v::Expr::new_int(*value));
+                }
             }
             Some(inst)
         }
@@ -286,7 +510,8 @@ fn cell_instance(cell: &ir::Cell) -> Option<v::Instance> {
 }
 
 /// Generates an always block that checks of the guards are disjoint when the
-/// length of assignments is greater than 1:
+/// length of assignments is greater than 1 and the guards are not statically
+/// known to be disjoint:
 /// ```verilog
 /// always_ff @(posedge clk) begin
 ///   if (!$onehot0({fsm_out < 1'd1 & go, fsm_out < 1'd1 & go})) begin
@@ -300,6 +525,13 @@ fn emit_guard_disjoint_check(
     if assignments.len() < 2 {
         return None;
     }
+    let guards = assignments
+        .iter()
+        .map(|assign| &*assign.guard)
+        .collect_vec();
+    if GuardDisjointness::pairwise_disjoint(&guards) {
+        return None;
+    }
     // Construct concat with all guards.
     let mut concat = v::ExprConcat::default();
     assignments.iter().for_each(|assign| {
@@ -320,9 +552,43 @@ fn emit_guard_disjoint_check(
     Some(v::Sequential::If(check))
 }
 
+/// Generates an always block that prints the values of compiled `@debug`
+/// statements in the cycles they are enabled in:
+/// ```verilog
+/// always_ff @(posedge clk) begin
+///   if (debug_en_out) begin
+///     $display("i = %0d", debug_arg_out);
+///   end
+/// end
+/// ```
+fn emit_debug_logs(logs: &[ir::DebugLog]) -> v::ParallelProcess {
+    let mut always = v::ParallelProcess::new_always_ff();
+    always.set_event(v::Sequential::Event(
+        v::EventTy::Posedge,
+        v::Expr::new_ref("clk"),
+    ));
+    for log in logs {
+        let format = log
+            .debug
+            .segments()
+            .map(|seg| seg.replace('\\', "\\\\").replace('%', "%%"))
+            .join("%0d");
+        let args = std::iter::once(v::Expr::new_str(&format))
+            .chain(log.debug.ports.iter().map(|p| port_to_ref(Rc::clone(p))))
+            .collect();
+        let mut check =
+            v::SequentialIfElse::new(port_to_ref(Rc::clone(&log.enable)));
+        check.add_seq(v::Sequential::new_seqexpr(v::Expr::new_call(
+            "$display", args,
+        )));
+        always.add_seq(v::Sequential::If(check));
+    }
+    always
+}
+
 /// Generates an assign statement that uses ternaries to select the correct
 /// assignment to enable and adds a default assignment to 0 when none of the
-/// guards are active.
+/// guards are active. Assignments to `inout` ports default to `'z` instead.
 ///
 /// Example:
 /// ```
@@ -338,7 +604,13 @@ fn emit_assignment(
     (dst_ref, assignments): &(RRC<ir::Port>, Vec<&ir::Assignment>),
 ) -> v::Parallel {
     let dst = dst_ref.borrow();
-    let init = v::Expr::new_ulit_dec(dst.width as u32, &0.to_string());
+    // `inout` ports are tri-state buffers that release the pin when none of
+    // the guards are active.
+    let init = if dst.direction == ir::Direction::Inout {
+        v::Expr::new_ulit_bin(dst.width as u32, "z")
+    } else {
+        v::Expr::new_ulit_dec(dst.width as u32, &0.to_string())
+    };
     let rhs = assignments.iter().rfold(init, |acc, e| {
         let guard = guard_to_expr(&e.guard);
         let asgn = port_to_ref(Rc::clone(&e.src));
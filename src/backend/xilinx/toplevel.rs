@@ -47,7 +47,7 @@ impl Backend for XilinxInterfaceBackend {
         }
 
         let mut modules = vec![
-            top_level(12, 32, &memories),
+            top_level(12, 32, &prog.entrypoint, &memories),
             bram(32, 32, 5),
             axi::AxiInterface::control_module("Control_axi", 12, 32, &memories),
         ];
@@ -93,6 +93,7 @@ fn external_memories(comp: &ir::Component) -> Vec<String> {
 fn top_level(
     address_width: u64,
     data_width: u64,
+    kernel: &ir::Id,
     memories: &[String],
 ) -> v::Module {
     let mut module = v::Module::new("Toplevel");
@@ -204,7 +205,7 @@ fn top_level(
     }
 
     // instantiate kernel
-    let mut kernel_instance = v::Instance::new("kernel_inst", "main");
+    let mut kernel_instance = v::Instance::new("kernel_inst", kernel.as_ref());
     module.add_decl(v::Decl::new_wire("kernel_start", 1));
     module.add_decl(v::Decl::new_wire("kernel_done", 1));
     kernel_instance.connect_ref("clk", "ap_clk");
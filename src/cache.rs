@@ -0,0 +1,196 @@
+//! On-disk cache of compiled programs.
+use crate::cmdline::Opts;
+use calyx::{
+    errors::{CalyxResult, Error},
+    frontend::Workspace,
+    utils::OutputFile,
+};
+use futil::backend::BackendOpt;
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io::Write,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Changed whenever the format of the cached entries changes so that stale
+/// entries are ignored.
+const CACHE_VERSION: u32 = 1;
+
+/// Extension of the files storing cached outputs.
+const EXTENSION: &str = "out";
+
+/// Caches the output of compilations so that compiling an unchanged program
+/// with the same options is instant.
+///
+/// Entries are keyed by the parsed program and the libraries it imports, the
+/// contents of the extern files and of the files named by string
+/// parameters, the pass pipeline, and the options that change the output.
+/// Once the cache grows beyond its size limit, the oldest entries are
+/// removed.
+pub struct OutputCache {
+    dir: PathBuf,
+    /// Maximum size of all entries in bytes.
+    limit: u64,
+}
+
+/// Hashes the contents of the file at `path`. Files that cannot be read only
+/// contribute their name.
+fn hash_file(path: &Path, hasher: &mut DefaultHasher) {
+    path.hash(hasher);
+    if let Ok(content) = fs::read(path) {
+        content.hash(hasher);
+    }
+}
+
+impl OutputCache {
+    /// Use `dir` to store the cached entries. The directory is created if it
+    /// does not exist.
+    pub fn new(dir: &Path, limit: u64) -> CalyxResult<Self> {
+        fs::create_dir_all(dir).map_err(|err| {
+            Error::InvalidFile(format!(
+                "Failed to create cache directory `{}`: {}",
+                dir.to_string_lossy(),
+                err
+            ))
+        })?;
+        Ok(Self {
+            dir: dir.into(),
+            limit,
+        })
+    }
+
+    /// Key of the output of compiling `ws` with `opts`. Returns `None` if
+    /// the program cannot be hashed or the compilation writes other files.
+    pub fn key(&self, ws: &Workspace, opts: &Opts) -> Option<u64> {
+        // Outputs written to other files and reports are not cached.
+        if opts.verilator_harness.is_some()
+            || opts.emit_per_module.is_some()
+            || opts.report.is_some()
+            || opts.backend == BackendOpt::SynthTcl
+        {
+            return None;
+        }
+        let mut hasher = DefaultHasher::new();
+        CACHE_VERSION.hash(&mut hasher);
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+
+        // The program and the signatures of the libraries it imports.
+        let program = (
+            &ws.components,
+            &ws.declarations,
+            &ws.original_imports,
+            &ws.original_lib_paths,
+            &ws.metadata,
+        );
+        serde_json::to_vec(&program).ok()?.hash(&mut hasher);
+        for (path, prims) in &ws.externs {
+            hash_file(path, &mut hasher);
+            serde_json::to_vec(prims).ok()?.hash(&mut hasher);
+        }
+        // String parameters can name files whose contents are used by the
+        // backends.
+        let string_params = ws
+            .components
+            .iter()
+            .chain(&ws.declarations)
+            .flat_map(|comp| &comp.cells)
+            .flat_map(|cell| &cell.prototype.string_params);
+        for param in string_params {
+            let path = Path::new(param);
+            if path.is_file() {
+                hash_file(path, &mut hasher);
+            }
+        }
+
+        opts.pass.hash(&mut hasher);
+        opts.disable_pass.hash(&mut hasher);
+        opts.extra_opts.hash(&mut hasher);
+        opts.toplevel.hash(&mut hasher);
+        opts.backend.to_string().hash(&mut hasher);
+        (opts.compile_mode == crate::cmdline::CompileMode::File)
+            .hash(&mut hasher);
+        opts.enable_synthesis.hash(&mut hasher);
+        opts.disable_verify.hash(&mut hasher);
+        opts.disable_init.hash(&mut hasher);
+        opts.check_ir.hash(&mut hasher);
+        if let Some(bindings) = &opts.bindings {
+            hash_file(bindings, &mut hasher);
+        }
+        Some(hasher.finish())
+    }
+
+    fn entry_path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{:016x}.{}", key, EXTENSION))
+    }
+
+    /// Location where the output for `key` is written before it is stored.
+    pub fn pending_path(&self, key: u64) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}.{}.{}", key, std::process::id(), "tmp"))
+    }
+
+    /// Write the cached output for `key` to `output`. Returns false if there
+    /// is no entry for `key`.
+    pub fn restore(&self, key: u64, output: &OutputFile) -> CalyxResult<bool> {
+        match fs::read(self.entry_path(key)) {
+            Ok(buf) => {
+                output.get_write().write_all(&buf)?;
+                Ok(true)
+            }
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Store the output written to [OutputCache::pending_path] for `key` and
+    /// copy it to `output`.
+    pub fn store(&self, key: u64, output: &OutputFile) -> CalyxResult<()> {
+        let pending = self.pending_path(key);
+        let buf = fs::read(&pending)?;
+        // Failing to store the entry only means that the program will be
+        // compiled again next time.
+        if fs::rename(&pending, self.entry_path(key)).is_err() {
+            let _ = fs::remove_file(&pending);
+        }
+        output.get_write().write_all(&buf)?;
+        self.collect_garbage();
+        Ok(())
+    }
+
+    /// Discard the output written to [OutputCache::pending_path] for `key`.
+    pub fn discard(&self, key: u64) {
+        let _ = fs::remove_file(self.pending_path(key));
+    }
+
+    /// Remove the oldest entries until the cache fits in its size limit.
+    fn collect_garbage(&self) {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        let mut entries = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != EXTENSION {
+                    return None;
+                }
+                let meta = fs::metadata(&path).ok()?;
+                let modified =
+                    meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                Some((modified, meta.len(), path))
+            })
+            .collect::<Vec<_>>();
+        let mut size: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort();
+        for (_, len, path) in entries {
+            if size <= self.limit {
+                break;
+            }
+            if fs::remove_file(path).is_ok() {
+                size -= len;
+            }
+        }
+    }
+}
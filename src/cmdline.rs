@@ -1,17 +1,40 @@
-use crate::backend::traits::Backend;
-use crate::backend::{
-    mlir::MlirBackend, verilog::VerilogBackend, xilinx::XilinxInterfaceBackend,
-    xilinx::XilinxXmlBackend,
-};
 use argh::FromArgs;
-use calyx::{errors::CalyxResult, ir, utils::OutputFile};
-use itertools::Itertools;
+use calyx::{
+    errors::CalyxResult, frontend::LibraryConf, ir, utils::OutputFile,
+};
+use futil::backend::BackendOpt;
 use std::path::Path;
 use std::path::PathBuf;
 use std::str::FromStr;
 
 #[derive(FromArgs)]
 /// The Calyx compiler
+pub struct Cli {
+    #[argh(subcommand)]
+    pub command: Command,
+}
+
+/// Subcommands supported by the compiler.
+#[allow(clippy::large_enum_variant)]
+#[derive(FromArgs)]
+#[argh(subcommand)]
+pub enum Command {
+    Compile(Opts),
+    Check(CheckOpts),
+    Passes(PassesOpts),
+    Fmt(FmtOpts),
+    Interp(InterpOpts),
+    Schedule(ScheduleOpts),
+}
+
+/// Names of all the subcommands. Invocations that do not start with one of
+/// these are treated as `compile` invocations.
+const SUBCOMMANDS: &[&str] =
+    &["compile", "check", "passes", "fmt", "interp", "schedule"];
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "compile")]
+/// Compile a Calyx program (default when no subcommand is given)
 pub struct Opts {
     /// input calyx program
     #[argh(positional, from_str_fn(read_path))]
@@ -21,14 +44,37 @@ pub struct Opts {
     #[argh(option, short = 'o', default = "OutputFile::default()")]
     pub output: OutputFile,
 
-    /// path to the primitives library
-    #[argh(option, short = 'l', default = "Path::new(\".\").into()")]
-    pub lib_path: PathBuf,
+    /// path to search for imported libraries. Can be given multiple times
+    /// and defaults to the current directory
+    #[argh(option, short = 'l')]
+    pub lib_path: Vec<PathBuf>,
+
+    /// directory used to cache the signatures of parsed primitive libraries
+    #[argh(option, long = "lib-cache")]
+    pub lib_cache: Option<PathBuf>,
+
+    /// directory used to cache compiled programs. Defaults to the
+    /// `FUTIL_CACHE_DIR` environment variable
+    #[argh(option)]
+    pub cache: Option<PathBuf>,
+
+    /// do not use the cache of compiled programs
+    #[argh(switch)]
+    pub no_cache: bool,
+
+    /// maximum size of the cache of compiled programs in MiB
+    #[argh(option, default = "256")]
+    pub cache_limit: u64,
 
     /// compilation mode
     #[argh(option, short = 'm', default = "CompileMode::default()")]
     pub compile_mode: CompileMode,
 
+    /// component to compile as the toplevel. Defaults to the component with
+    /// the "toplevel" attribute or `main`
+    #[argh(option)]
+    pub toplevel: Option<String>,
+
     /// enable synthesis mode
     #[argh(switch, long = "synthesis")]
     pub enable_synthesis: bool,
@@ -41,10 +87,38 @@ pub struct Opts {
     #[argh(switch)]
     pub disable_init: bool,
 
+    /// check the invariants of the IR after every pass and report the first
+    /// pass that breaks them
+    #[argh(switch, long = "check-ir")]
+    pub check_ir: bool,
+
+    /// JSON file mapping primitives to vendor modules (used by -b verilog)
+    #[argh(option, long = "bindings", from_str_fn(read_path))]
+    pub bindings: Option<PathBuf>,
+
+    /// write a C++ Verilator driver for the design to this file (used by
+    /// -b verilog)
+    #[argh(option, long = "verilator-harness")]
+    pub verilator_harness: Option<PathBuf>,
+
+    /// write one Verilog file per component and a file list to this
+    /// directory instead of the output file (used by -b verilog)
+    #[argh(option, long = "emit-per-module")]
+    pub emit_per_module: Option<PathBuf>,
+
     /// select a backend
     #[argh(option, short = 'b', default = "BackendOpt::default()")]
     pub backend: BackendOpt,
 
+    /// report statistics about the changes made by the passes in this
+    /// format (json)
+    #[argh(option)]
+    pub report: Option<ReportFormat>,
+
+    /// file to write the report to. Defaults to stderr
+    #[argh(option)]
+    pub report_file: Option<PathBuf>,
+
     /// run this pass during execution
     #[argh(option, short = 'p')]
     pub pass: Vec<String>,
@@ -60,6 +134,98 @@ pub struct Opts {
     /// list all avaliable pass options
     #[argh(switch, long = "list-passes")]
     pub list_passes: bool,
+    /// print a detailed explanation of an error code, such as E0009
+    #[argh(option)]
+    pub explain: Option<String>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "check")]
+/// Parse and validate a Calyx program without compiling it
+pub struct CheckOpts {
+    /// input calyx program
+    #[argh(positional, from_str_fn(read_path))]
+    pub file: Option<PathBuf>,
+
+    /// path to search for imported libraries. Can be given multiple times
+    /// and defaults to the current directory
+    #[argh(option, short = 'l')]
+    pub lib_path: Vec<PathBuf>,
+
+    /// directory used to cache the signatures of parsed primitive libraries
+    #[argh(option, long = "lib-cache")]
+    pub lib_cache: Option<PathBuf>,
+
+    /// component to check as the toplevel. Defaults to the component with
+    /// the "toplevel" attribute or `main`
+    #[argh(option)]
+    pub toplevel: Option<String>,
+
+    /// analysis pass to run. Can be given multiple times and defaults to the
+    /// `lint` alias
+    #[argh(option, short = 'p')]
+    pub pass: Vec<String>,
+
+    /// disable analysis pass
+    #[argh(option, short = 'd', long = "disable-pass")]
+    pub disable_pass: Vec<String>,
+
+    /// format of the reported errors (text or json)
+    #[argh(option, default = "MessageFormat::Text")]
+    pub message_format: MessageFormat,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "passes")]
+/// List all the available passes and aliases
+pub struct PassesOpts {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "fmt")]
+/// Format a Calyx program while preserving comments
+pub struct FmtOpts {
+    /// input calyx program
+    #[argh(positional, from_str_fn(read_path))]
+    pub file: Option<PathBuf>,
+
+    /// output file
+    #[argh(option, short = 'o', default = "OutputFile::default()")]
+    pub output: OutputFile,
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "interp",
+    note = "This subcommand runs the `interp` binary found next to this binary \
+or on the PATH and fails if it is missing. Build it with \
+`cargo build -p interp`. Run `{command_name} -- --help` for the options of \
+the interpreter."
+)]
+/// Run a Calyx program using the interpreter binary `interp`
+pub struct InterpOpts {
+    /// arguments passed to the interpreter
+    #[argh(positional, greedy)]
+    pub args: Vec<String>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "schedule")]
+/// Estimate the number of cycles taken by each component and print its
+/// critical control path
+pub struct ScheduleOpts {
+    /// input calyx program
+    #[argh(positional, from_str_fn(read_path))]
+    pub file: Option<PathBuf>,
+
+    /// path to search for imported libraries. Can be given multiple times
+    /// and defaults to the current directory
+    #[argh(option, short = 'l')]
+    pub lib_path: Vec<PathBuf>,
+
+    /// output file
+    #[argh(option, short = 'o', default = "OutputFile::default()")]
+    pub output: OutputFile,
 }
 
 fn read_path(path: &str) -> Result<PathBuf, String> {
@@ -93,133 +259,173 @@ impl FromStr for CompileMode {
     }
 }
 
-// ================== Backend Variant and Parsing ===================== //
+/// Format of the errors reported by `check`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// Errors are printed for humans on stderr.
+    Text,
+    /// Errors are printed as JSON on stdout.
+    Json,
+}
+
+impl FromStr for MessageFormat {
+    type Err = String;
 
-/// Enumeration of valid backends
-#[derive(Debug, Clone, PartialEq)]
-pub enum BackendOpt {
-    Verilog,
-    Xilinx,
-    XilinxXml,
-    Calyx,
-    Mlir,
-    None,
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(MessageFormat::Text),
+            "json" => Ok(MessageFormat::Json),
+            s => Err(format!(
+                "Unknown message format: {}. Valid options are `text` or `json`",
+                s
+            )),
+        }
+    }
 }
 
-/// Return a vector that maps strings to Backends.
-#[inline(always)]
-fn backends() -> Vec<(&'static str, BackendOpt)> {
-    vec![
-        ("verilog", BackendOpt::Verilog),
-        ("xilinx", BackendOpt::Xilinx),
-        ("xilinx-xml", BackendOpt::XilinxXml),
-        ("futil", BackendOpt::Calyx),
-        ("calyx", BackendOpt::Calyx),
-        ("mlir", BackendOpt::Mlir),
-        ("none", BackendOpt::None),
-    ]
+/// Format of the statistics reported by `--report`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
 }
 
-impl Default for BackendOpt {
-    fn default() -> Self {
-        BackendOpt::Calyx
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ReportFormat::Json),
+            s => Err(format!(
+                "Unknown report format: {}. Valid options are `json`",
+                s
+            )),
+        }
     }
 }
 
-/// Command line parsing for the Backend enum
-impl FromStr for BackendOpt {
-    type Err = String;
-    fn from_str(input: &str) -> Result<Self, Self::Err> {
-        // allocate a vector for the list of backends
-        let backends = backends();
-        // see if there is a backend for the string that we receive
-        let found_backend = backends
-            .iter()
-            .find(|(backend_name, _)| &input == backend_name);
-        if let Some((_, opt)) = found_backend {
-            // return the BackendOpt if we found one
-            Ok(opt.clone())
-        } else {
-            // build list of backends for error message
-            let backend_str = backends
-                .iter()
-                .map(|(name, _)| (*name).to_string())
-                .join(", ");
-            Err(format!(
-                "`{}` is not a valid backend.\nValid backends: {}",
-                input, backend_str
-            ))
+impl Opts {
+    /// Given a context, calls the backend corresponding to the `BackendOpt` variant
+    pub fn run_backend(self, context: ir::Context) -> CalyxResult<()> {
+        self.backend.run(&context, self.output)
+    }
+
+    /// Fill in defaults that argh cannot express.
+    fn with_defaults(mut self) -> Self {
+        // argh doesn't allow us to specify a default for this so we fill it
+        // in manually.
+        if self.pass.is_empty() {
+            self.pass = vec!["all".into()];
+        }
+        self
+    }
+
+    /// Where to find the libraries imported by the program.
+    pub fn library_conf(&self) -> LibraryConf {
+        LibraryConf {
+            lib_paths: lib_paths(&self.lib_path),
+            cache_dir: self.lib_cache.clone(),
+        }
+    }
+
+    /// Directory used to cache compiled programs, if any.
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        if self.no_cache {
+            return None;
         }
+        self.cache
+            .clone()
+            .or_else(|| std::env::var_os("FUTIL_CACHE_DIR").map(PathBuf::from))
     }
 }
 
-/// Convert `BackendOpt` to a string
-impl ToString for BackendOpt {
-    fn to_string(&self) -> String {
-        match self {
-            Self::Mlir => "mlir",
-            Self::Verilog => "verilog",
-            Self::Xilinx => "xilinx",
-            Self::XilinxXml => "xilinx-xml",
-            Self::Calyx => "calyx",
-            Self::None => "none",
+impl CheckOpts {
+    /// Where to find the libraries imported by the program.
+    pub fn library_conf(&self) -> LibraryConf {
+        LibraryConf {
+            lib_paths: lib_paths(&self.lib_path),
+            cache_dir: self.lib_cache.clone(),
+        }
+    }
+
+    /// The analysis passes to run.
+    pub fn passes(&self) -> Vec<String> {
+        if self.pass.is_empty() {
+            vec!["lint".to_string()]
+        } else {
+            self.pass.clone()
         }
-        .to_string()
     }
 }
 
-impl Opts {
-    /// Given a context, calls the backend corresponding to the `BackendOpt` variant
-    pub fn run_backend(self, context: ir::Context) -> CalyxResult<()> {
-        match self.backend {
-            BackendOpt::Mlir => {
-                let backend = MlirBackend::default();
-                backend.run(context, self.output)
-            }
-            BackendOpt::Verilog => {
-                let backend = VerilogBackend::default();
-                backend.run(context, self.output)
-            }
-            BackendOpt::Xilinx => {
-                let backend = XilinxInterfaceBackend::default();
-                backend.run(context, self.output)
-            }
-            BackendOpt::XilinxXml => {
-                let backend = XilinxXmlBackend::default();
-                backend.run(context, self.output)
-            }
-            BackendOpt::Calyx => {
-                for (path, prims) in context.lib.externs() {
-                    ir::IRPrinter::write_extern(
-                        (
-                            &path,
-                            &prims.into_iter().map(|(_, v)| v).collect_vec(),
-                        ),
-                        &mut self.output.get_write(),
-                    )?;
-                }
-                for comp in &context.components {
-                    ir::IRPrinter::write_component(
-                        comp,
-                        &mut self.output.get_write(),
-                    )?;
-                    writeln!(&mut self.output.get_write())?
-                }
-                Ok(())
-            }
-            BackendOpt::None => Ok(()),
+impl ScheduleOpts {
+    /// Where to find the libraries imported by the program.
+    pub fn library_conf(&self) -> LibraryConf {
+        LibraryConf {
+            lib_paths: lib_paths(&self.lib_path),
+            cache_dir: None,
         }
     }
+}
 
-    /// Get the current set of options from the command line invocation.
-    pub fn get_opts() -> Opts {
-        let mut opts: Opts = argh::from_env();
+/// The library paths given on the command line or the current directory if
+/// there are none.
+fn lib_paths(paths: &[PathBuf]) -> Vec<PathBuf> {
+    if paths.is_empty() {
+        vec![Path::new(".").into()]
+    } else {
+        paths.to_vec()
+    }
+}
 
-        // argh doesn't allow us to specify a default for this so we fill it
-        // in manually.
-        if opts.pass.is_empty() {
-            opts.pass = vec!["all".into()];
+impl Cli {
+    /// Get the subcommand from the command line invocation.
+    /// Invocations using the flags of the `compile` subcommand without naming
+    /// a subcommand are supported for compatibility.
+    pub fn get_command() -> Command {
+        let strings: Vec<String> = std::env::args().collect();
+        let cmd = Path::new(&strings[0])
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&strings[0]);
+        // Accept `--option=value` in addition to `--option value`.
+        let mut args: Vec<&str> = strings[1..]
+            .iter()
+            .flat_map(|arg| match arg.split_once('=') {
+                Some((opt, value)) if opt.starts_with("--") => {
+                    vec![opt, value]
+                }
+                _ => vec![arg.as_str()],
+            })
+            .collect();
+        let is_command = args
+            .first()
+            .filter(|arg| {
+                SUBCOMMANDS.contains(arg) || matches!(**arg, "help" | "--help")
+            })
+            .is_some();
+        if !is_command {
+            args.insert(0, "compile");
+        }
+
+        match Cli::from_args(&[cmd], &args) {
+            Ok(Cli {
+                command: Command::Compile(opts),
+            }) => Command::Compile(opts.with_defaults()),
+            Ok(cli) => cli.command,
+            Err(exit) => match exit.status {
+                Ok(()) => {
+                    println!("{}", exit.output);
+                    std::process::exit(0)
+                }
+                Err(()) => {
+                    eprintln!(
+                        "{}\nRun {} --help for more information.",
+                        exit.output.trim_end(),
+                        cmd
+                    );
+                    std::process::exit(1)
+                }
+            },
         }
-        opts
     }
 }
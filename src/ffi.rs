@@ -0,0 +1,455 @@
+//! C API for calling the compiler and the interpreter in-process.
+//!
+//! The API is built when the `ffi` feature is enabled. The shared library is
+//! produced with:
+//!
+//! ```text
+//! cargo rustc --release --lib --features ffi --crate-type cdylib
+//! ```
+//!
+//! Programs and simulations are opaque handles that must be released with
+//! [`calyx_free_program`] and [`calyx_sim_free`], and strings returned by the
+//! API must be released with [`calyx_free_string`]. The handles are not
+//! thread-safe and must only be used on the thread that created them.
+//!
+//! Functions that return a pointer return NULL when they fail, and functions
+//! that return an `int` return a negative value. The message of the last
+//! error on the current thread is returned by [`calyx_last_error`].
+//! `calyx-py/calyx/ffi.py` wraps the API for Python.
+use crate::backend::BackendOpt;
+use calyx::{
+    errors::Error,
+    frontend::{LibraryConf, Workspace},
+    ir,
+    pass_manager::PassManager,
+    utils::OutputFile,
+};
+use interp::{
+    environment::{Instruments, InterpreterState},
+    errors::InterpreterError,
+    interpreter::{ComponentInterpreter, Interpreter},
+    interpreter_ir as iir,
+    names::ComponentQIN,
+    MemoryMap,
+};
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    ptr,
+    rc::Rc,
+    str::FromStr,
+};
+
+thread_local! {
+    /// Message of the last error on this thread.
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+/// A program that has been parsed and, optionally, compiled.
+pub struct CalyxProgram {
+    ctx: ir::Context,
+}
+
+/// A simulation of the `main` component of a program.
+pub struct CalyxSim {
+    interp: ComponentInterpreter,
+}
+
+fn set_error(msg: String) {
+    let msg = CString::new(msg.replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|err| *err.borrow_mut() = Some(msg));
+}
+
+/// Run `f` and record its error, if any. Panics are reported as errors
+/// instead of unwinding into the caller.
+fn call<T>(default: T, f: impl FnOnce() -> Result<T, String>) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(val)) => val,
+        Ok(Err(msg)) => {
+            set_error(msg);
+            default
+        }
+        Err(panic) => {
+            let msg = panic
+                .downcast_ref::<&str>()
+                .map(|msg| msg.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown error".to_string());
+            set_error(format!("Internal error: {}", msg));
+            default
+        }
+    }
+}
+
+fn calyx_err(err: Error) -> String {
+    format!("{:?}", err)
+}
+
+fn interp_err(err: InterpreterError) -> String {
+    err.to_string()
+}
+
+/// The string pointed to by `ptr`, or `None` if it is NULL.
+unsafe fn str_arg<'a>(ptr: *const c_char) -> Result<Option<&'a str>, String> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map(Some)
+        .map_err(|err| format!("Argument is not valid UTF-8: {}", err))
+}
+
+/// Comma-separated list of names.
+fn names(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn into_c_string(s: String) -> Result<*mut c_char, String> {
+    CString::new(s)
+        .map(CString::into_raw)
+        .map_err(|_| "Output contains a NUL byte".to_string())
+}
+
+/// Message of the last error on the current thread, or NULL if no error has
+/// occurred. The string is owned by the library and is valid until the next
+/// call that fails.
+#[no_mangle]
+pub extern "C" fn calyx_last_error() -> *const c_char {
+    LAST_ERROR.with(|err| {
+        err.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |msg| msg.as_ptr())
+    })
+}
+
+/// Parse the program in `src`. `file` is the name used in error messages and
+/// the directory of `file` is used to resolve relative imports; it may be
+/// NULL. `lib_path` is the directory searched for library imports.
+///
+/// # Safety
+/// The arguments must be NULL or point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn calyx_parse(
+    src: *const c_char,
+    file: *const c_char,
+    lib_path: *const c_char,
+) -> *mut CalyxProgram {
+    call(ptr::null_mut(), || {
+        let src = str_arg(src)?.ok_or("No source given")?;
+        let file = Path::new(str_arg(file)?.unwrap_or("<source>"));
+        let lib_path = Path::new(str_arg(lib_path)?.unwrap_or("."));
+        let ws = Workspace::construct_from_source(
+            src,
+            file,
+            &LibraryConf::from(lib_path),
+        )
+        .map_err(calyx_err)?;
+        let bc = ir::BackendConf {
+            enable_verification: true,
+            initialize_inputs: true,
+            ..Default::default()
+        };
+        let ctx = ir::from_ast::ast_to_ir(ws, bc).map_err(calyx_err)?;
+        Ok(Box::into_raw(Box::new(CalyxProgram { ctx })))
+    })
+}
+
+/// Run passes on the program. `passes` and `disabled` are comma-separated
+/// lists of passes and aliases to run and to skip, like the `-p` and `-d`
+/// options of the compiler; `passes` runs `all` when it is NULL. `opts` is a
+/// comma-separated list of pass options like the `-x` option and may be
+/// NULL. Returns 0 on success.
+///
+/// # Safety
+/// `prog` must be a program returned by [`calyx_parse`] and the other
+/// arguments must be NULL or point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn calyx_compile(
+    prog: *mut CalyxProgram,
+    passes: *const c_char,
+    disabled: *const c_char,
+    opts: *const c_char,
+) -> c_int {
+    call(-1, || {
+        let prog = prog.as_mut().ok_or("No program given")?;
+        let passes = str_arg(passes)?.map_or_else(|| vec!["all".into()], names);
+        let disabled = str_arg(disabled)?.map(names).unwrap_or_default();
+        prog.ctx.extra_opts = str_arg(opts)?.map(names).unwrap_or_default();
+        let pm = PassManager::default_passes().map_err(calyx_err)?;
+        pm.execute_plan(&mut prog.ctx, &passes, &disabled)
+            .map_err(calyx_err)?;
+        Ok(0)
+    })
+}
+
+/// Generate the output of `backend`, named like the `-b` option of the
+/// compiler, for the program. The string must be released with
+/// [`calyx_free_string`].
+///
+/// # Safety
+/// `prog` must be a program returned by [`calyx_parse`] and `backend` must
+/// point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn calyx_emit(
+    prog: *const CalyxProgram,
+    backend: *const c_char,
+) -> *mut c_char {
+    call(ptr::null_mut(), || {
+        let prog = prog.as_ref().ok_or("No program given")?;
+        let backend = BackendOpt::from_str(str_arg(backend)?.unwrap_or(""))?;
+        let buf = Rc::new(RefCell::new(Vec::new()));
+        backend
+            .run(&prog.ctx, OutputFile::Buffer(Rc::clone(&buf)))
+            .map_err(calyx_err)?;
+        let out = String::from_utf8(buf.take())
+            .map_err(|err| format!("Output is not valid UTF-8: {}", err))?;
+        into_c_string(out)
+    })
+}
+
+/// Release a program.
+///
+/// # Safety
+/// `prog` must be NULL or a program returned by [`calyx_parse`] that has not
+/// been released or passed to [`calyx_sim_new`].
+#[no_mangle]
+pub unsafe extern "C" fn calyx_free_program(prog: *mut CalyxProgram) {
+    if !prog.is_null() {
+        drop(Box::from_raw(prog));
+    }
+}
+
+/// Release a string returned by the API.
+///
+/// # Safety
+/// `s` must be NULL or a string returned by the API that has not been
+/// released.
+#[no_mangle]
+pub unsafe extern "C" fn calyx_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Start a simulation of the entrypoint of the program. The program is
+/// consumed, even if the simulation cannot be started, and must not be used
+/// afterwards. `data` is a JSON object with the initial values of the
+/// memories, in the format of the `--data` option of the interpreter, and
+/// may be NULL.
+///
+/// # Safety
+/// `prog` must be a program returned by [`calyx_parse`] and `data` must be
+/// NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn calyx_sim_new(
+    prog: *mut CalyxProgram,
+    data: *const c_char,
+) -> *mut CalyxSim {
+    call(ptr::null_mut(), || {
+        if prog.is_null() {
+            return Err("No program given".to_string());
+        }
+        let mut ctx = Box::from_raw(prog).ctx;
+        let mems = str_arg(data)?
+            .map(serde_json::from_str::<MemoryMap>)
+            .transpose()
+            .map_err(|err| format!("Malformed data: {}", err))?;
+        // Registers with an `@init` attribute are initialized by the control
+        // program.
+        PassManager::default_passes()
+            .and_then(|pm| {
+                pm.execute_plan(&mut ctx, &["init-insertion".to_string()], &[])
+            })
+            .map_err(calyx_err)?;
+        let entrypoint = ctx.entrypoint;
+        let components: iir::ComponentCtx = Rc::new(
+            ctx.components
+                .into_iter()
+                .map(|comp| Rc::new(comp.into()))
+                .collect(),
+        );
+        let main = components
+            .iter()
+            .find(|comp| comp.name == entrypoint)
+            .ok_or_else(|| {
+                interp_err(InterpreterError::MissingMainComponent)
+            })?;
+        let env = InterpreterState::init_top_level(
+            &components,
+            main,
+            &mems,
+            &Instruments::default(),
+        )
+        .map_err(interp_err)?;
+        let qin = ComponentQIN::new_single(main, &main.name);
+        let mut interp = ComponentInterpreter::from_component(main, env, qin);
+        interp.set_go_high();
+        Ok(Box::into_raw(Box::new(CalyxSim { interp })))
+    })
+}
+
+/// Simulate one cycle. Does nothing once the simulation is done. Returns 0
+/// on success.
+///
+/// # Safety
+/// `sim` must be a simulation returned by [`calyx_sim_new`].
+#[no_mangle]
+pub unsafe extern "C" fn calyx_sim_step(sim: *mut CalyxSim) -> c_int {
+    call(-1, || {
+        let sim = sim.as_mut().ok_or("No simulation given")?;
+        if !sim.interp.is_done() {
+            sim.interp.step().map_err(interp_err)?;
+        }
+        Ok(0)
+    })
+}
+
+/// Simulate until the program is done. Returns 0 on success.
+///
+/// # Safety
+/// `sim` must be a simulation returned by [`calyx_sim_new`].
+#[no_mangle]
+pub unsafe extern "C" fn calyx_sim_run(sim: *mut CalyxSim) -> c_int {
+    call(-1, || {
+        let sim = sim.as_mut().ok_or("No simulation given")?;
+        sim.interp.run().map_err(interp_err)?;
+        Ok(0)
+    })
+}
+
+/// Returns 1 if the program is done, 0 if it is not.
+///
+/// # Safety
+/// `sim` must be a simulation returned by [`calyx_sim_new`].
+#[no_mangle]
+pub unsafe extern "C" fn calyx_sim_is_done(sim: *const CalyxSim) -> c_int {
+    call(-1, || {
+        let sim = sim.as_ref().ok_or("No simulation given")?;
+        Ok(sim.interp.is_done() as c_int)
+    })
+}
+
+/// The values of the ports and memories of the program as a JSON object, in
+/// the format printed by the `display` command of the debugger. The string must be released with
+/// [`calyx_free_string`].
+///
+/// # Safety
+/// `sim` must be a simulation returned by [`calyx_sim_new`].
+#[no_mangle]
+pub unsafe extern "C" fn calyx_sim_state(sim: *const CalyxSim) -> *mut c_char {
+    call(ptr::null_mut(), || {
+        let sim = sim.as_ref().ok_or("No simulation given")?;
+        into_c_string(sim.interp.get_env().state_as_str())
+    })
+}
+
+/// Release a simulation.
+///
+/// # Safety
+/// `sim` must be NULL or a simulation returned by [`calyx_sim_new`] that has
+/// not been released.
+#[no_mangle]
+pub unsafe extern "C" fn calyx_sim_free(sim: *mut CalyxSim) {
+    if !sim.is_null() {
+        drop(Box::from_raw(sim));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = r#"
+import "primitives/core.futil";
+component main() -> () {
+  cells {
+    @external m = std_mem_d1(32, 1, 1);
+  }
+  wires {
+    group write {
+      m.addr0 = 1'd0;
+      m.write_data = 32'd5;
+      m.write_en = 1'd1;
+      write[done] = m.done;
+    }
+  }
+  control { write; }
+}
+"#;
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    unsafe fn take_string(s: *mut c_char) -> String {
+        assert!(!s.is_null());
+        let out = CStr::from_ptr(s).to_str().unwrap().to_string();
+        calyx_free_string(s);
+        out
+    }
+
+    unsafe fn parse(src: &str) -> *mut CalyxProgram {
+        let lib = c(env!("CARGO_MANIFEST_DIR"));
+        calyx_parse(c(src).as_ptr(), ptr::null(), lib.as_ptr())
+    }
+
+    #[test]
+    fn compile_and_emit() {
+        unsafe {
+            let prog = parse(PROGRAM);
+            assert!(!prog.is_null());
+            assert_eq!(
+                calyx_compile(prog, ptr::null(), ptr::null(), ptr::null()),
+                0
+            );
+            let verilog = take_string(calyx_emit(prog, c("verilog").as_ptr()));
+            assert!(verilog.contains("module main"));
+            // The program can be emitted more than once.
+            let calyx = take_string(calyx_emit(prog, c("calyx").as_ptr()));
+            assert!(calyx.contains("component main"));
+            calyx_free_program(prog);
+        }
+    }
+
+    #[test]
+    fn report_errors() {
+        unsafe {
+            assert!(parse("component main() -> () {").is_null());
+            assert!(!calyx_last_error().is_null());
+            let prog = parse(PROGRAM);
+            let passes = c("no-such-pass");
+            assert_eq!(
+                calyx_compile(prog, passes.as_ptr(), ptr::null(), ptr::null()),
+                -1
+            );
+            let msg = CStr::from_ptr(calyx_last_error()).to_str().unwrap();
+            assert!(msg.contains("no-such-pass"));
+            calyx_free_program(prog);
+        }
+    }
+
+    #[test]
+    fn simulate() {
+        unsafe {
+            let sim = calyx_sim_new(parse(PROGRAM), ptr::null());
+            assert!(!sim.is_null());
+            assert_eq!(calyx_sim_is_done(sim), 0);
+            assert_eq!(calyx_sim_step(sim), 0);
+            assert_eq!(calyx_sim_run(sim), 0);
+            assert_eq!(calyx_sim_is_done(sim), 1);
+            let state: serde_json::Value =
+                serde_json::from_str(&take_string(calyx_sim_state(sim)))
+                    .unwrap();
+            assert_eq!(state["memories"]["main"]["m"], serde_json::json!([5]));
+            calyx_sim_free(sim);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+//! Library interface of the Calyx compiler driver.
+//!
+//! The backends are shared by the `futil` binary and, when the `ffi` feature
+//! is enabled, by the C API used to call the compiler in-process.
+pub mod backend;
+#[cfg(feature = "ffi")]
+pub mod ffi;
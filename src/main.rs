@@ -1,19 +1,54 @@
-mod backend;
+mod cache;
 mod cmdline;
 
 use calyx::{
-    errors::{CalyxResult, Error},
+    analysis::ScheduleLength,
+    errors::{self, CalyxResult, Error},
     frontend, ir,
     pass_manager::PassManager,
+    utils::OutputFile,
 };
-use cmdline::{BackendOpt, CompileMode, Opts};
+use cmdline::{
+    CheckOpts, Cli, Command, CompileMode, FmtOpts, InterpOpts, MessageFormat,
+    Opts, ReportFormat, ScheduleOpts,
+};
+use futil::backend::BackendOpt;
 use itertools::Itertools;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process;
 
 fn main() -> CalyxResult<()> {
-    let pm = PassManager::default_passes()?;
+    match Cli::get_command() {
+        Command::Compile(opts) => compile(opts),
+        Command::Check(opts) => check(opts),
+        Command::Passes(_) => {
+            println!("{}", PassManager::default_passes()?.show_names());
+            Ok(())
+        }
+        Command::Fmt(opts) => fmt(opts),
+        Command::Interp(opts) => interp(opts),
+        Command::Schedule(opts) => schedule(opts),
+    }
+}
 
-    // parse the command line arguments into Opts struct
-    let mut opts = Opts::get_opts();
+/// Compile the program using the passes and backend given in `opts`.
+fn compile(mut opts: Opts) -> CalyxResult<()> {
+    if let Some(code) = &opts.explain {
+        return match errors::explain(code) {
+            Some(explanation) => {
+                println!("{}", explanation);
+                Ok(())
+            }
+            None => Err(Error::Misc(format!("Unknown error code `{}`", code))),
+        };
+    }
+
+    let mut pm = PassManager::default_passes()?;
+    if opts.check_ir {
+        pm.enable_ir_checks();
+    }
 
     // list all the avaliable pass options when flag --list-passes is enabled
     if opts.list_passes {
@@ -22,20 +57,70 @@ fn main() -> CalyxResult<()> {
     }
 
     // Construct the namespace.
-    let mut ws = frontend::Workspace::construct(&opts.file, &opts.lib_path)?;
+    let ws = frontend::Workspace::construct_with_conf(
+        &opts.file,
+        &opts.library_conf(),
+    )?;
 
+    let cache = match opts.cache_dir() {
+        Some(dir) => {
+            Some(cache::OutputCache::new(&dir, opts.cache_limit << 20)?)
+        }
+        None => None,
+    };
+    let key = cache.as_ref().and_then(|cache| cache.key(&ws, &opts));
+    let (cache, key) = match (cache, key) {
+        (Some(cache), Some(key)) => (cache, key),
+        _ => return emit(opts, ws, pm),
+    };
+    if cache.restore(key, &opts.output)? {
+        return Ok(());
+    }
+
+    // Write the output to the cache and copy it to the requested output once
+    // compilation succeeds.
+    let output = std::mem::replace(
+        &mut opts.output,
+        OutputFile::File(cache.pending_path(key)),
+    );
+    match emit(opts, ws, pm) {
+        Ok(()) => cache.store(key, &output),
+        Err(err) => {
+            cache.discard(key);
+            Err(err)
+        }
+    }
+}
+
+/// Run the passes and the backend on the program in `ws`.
+fn emit(
+    mut opts: Opts,
+    mut ws: frontend::Workspace,
+    pm: PassManager,
+) -> CalyxResult<()> {
+    let lib_paths = ws.original_lib_paths.drain(..).collect_vec();
     let imports = ws.original_imports.drain(..).collect_vec();
     let bc = ir::BackendConf {
         synthesis_mode: opts.enable_synthesis,
         enable_verification: !opts.disable_verify,
         initialize_inputs: !opts.disable_init,
+        bindings: opts.bindings.take(),
+        verilator_harness: opts.verilator_harness.take(),
+        per_module_dir: opts.emit_per_module.take(),
+        toplevel: opts.toplevel.take().map(ir::Id::from),
     };
     // Build the IR representation
     let mut ctx = ir::from_ast::ast_to_ir(ws, bc)?;
     ctx.extra_opts = opts.extra_opts.drain(..).collect();
 
-    // Run all passes specified by the command line
-    pm.execute_plan(&mut ctx, &opts.pass, &opts.disable_pass)?;
+    // Run all passes specified by the command line. Errors point to the
+    // frontend program when the definitions they are about have positions.
+    let source_map = ctx.metadata.source_map(&ctx.components);
+    pm.execute_plan(&mut ctx, &opts.pass, &opts.disable_pass)
+        .map_err(|err| source_map.annotate(err))?;
+    if let Some(format) = opts.report {
+        write_report(&ctx.stats, format, &opts.report_file)?;
+    }
 
     if opts.compile_mode == CompileMode::File
         && !matches!(opts.backend, BackendOpt::Calyx | BackendOpt::None)
@@ -58,16 +143,167 @@ fn main() -> CalyxResult<()> {
             }
         } else {
             // Print out the original imports for this file.
+            for lib_path in lib_paths {
+                writeln!(out, "lib_path \"{}\";", lib_path)?;
+            }
             for import in imports {
                 writeln!(out, "import \"{}\";", import)?;
             }
         }
         for comp in &ctx.components {
-            ir::IRPrinter::write_component(comp, out)?;
+            ir::IRPrinter::write_component_with_comments(
+                comp,
+                &ctx.comments,
+                out,
+            )?;
             writeln!(out)?
         }
+        ir::IRPrinter::write_metadata(&ctx.metadata, out)?;
         Ok(())
     } else {
+        // Backends reject definitions that passes should have compiled away,
+        // so their errors point to the passes that created them.
+        let provenance = ir::ProvenanceMap::new(&ctx.components);
         opts.run_backend(ctx)
+            .map_err(|err| provenance.annotate(err))
+    }
+}
+
+/// Write the statistics recorded by the passes to `path` or to stderr.
+fn write_report(
+    stats: &ir::PassStats,
+    format: ReportFormat,
+    path: &Option<PathBuf>,
+) -> CalyxResult<()> {
+    let report = match format {
+        ReportFormat::Json => serde_json::to_string_pretty(stats)
+            .map_err(|err| Error::Misc(err.to_string()))?,
+    };
+    match path {
+        Some(path) => fs::write(path, report + "\n").map_err(|err| {
+            Error::WriteError(format!(
+                "Failed to write report to {}: {}",
+                path.to_string_lossy(),
+                err
+            ))
+        }),
+        None => {
+            eprintln!("{}", report);
+            Ok(())
+        }
+    }
+}
+
+/// Parse and validate the program without generating any output. Only
+/// analysis passes are run so that the program is never transformed.
+fn run_analyses(opts: &CheckOpts) -> CalyxResult<()> {
+    let pm = PassManager::default_passes()?;
+    let ws = frontend::Workspace::construct_with_conf(
+        &opts.file,
+        &opts.library_conf(),
+    )?;
+    let bc = ir::BackendConf {
+        toplevel: opts.toplevel.clone().map(ir::Id::from),
+        ..Default::default()
+    };
+    let mut ctx = ir::from_ast::ast_to_ir(ws, bc)?;
+    let source_map = ctx.metadata.source_map(&ctx.components);
+    pm.execute_analyses(&mut ctx, &opts.passes(), &opts.disable_pass)
+        .map_err(|err| source_map.annotate(err))
+}
+
+/// Check the program and report the error, if any, in the requested format.
+fn check(opts: CheckOpts) -> CalyxResult<()> {
+    match (run_analyses(&opts), opts.message_format) {
+        (Err(err), MessageFormat::Json) => {
+            let mut diag = err.diagnostic();
+            // Errors from the parser do not always know the file.
+            if let (Some(loc), Some(file)) = (&mut diag.location, &opts.file) {
+                if loc.file.is_none() {
+                    loc.file = Some(file.to_string_lossy().to_string());
+                }
+            }
+            let json = serde_json::to_string(&[diag])
+                .map_err(|err| Error::Misc(err.to_string()))?;
+            println!("{}", json);
+            process::exit(1)
+        }
+        (Ok(()), MessageFormat::Json) => {
+            println!("[]");
+            Ok(())
+        }
+        (result, MessageFormat::Text) => result,
+    }
+}
+
+/// Print the estimated number of cycles and the critical control path of each
+/// component. Latencies of groups are inferred from the primitives they use.
+fn schedule(opts: ScheduleOpts) -> CalyxResult<()> {
+    let pm = PassManager::default_passes()?;
+    let ws = frontend::Workspace::construct_with_conf(
+        &opts.file,
+        &opts.library_conf(),
+    )?;
+    let mut ctx = ir::from_ast::ast_to_ir(ws, ir::BackendConf::default())?;
+    pm.execute_plan(&mut ctx, &["infer-static-timing".to_string()], &[])?;
+    let out = &mut opts.output.get_write();
+    for comp in &ctx.components {
+        let schedule = ScheduleLength::from(&*comp.control.borrow());
+        writeln!(
+            out,
+            "component {}: {} cycles",
+            comp.name,
+            schedule.estimate()
+        )?;
+        write!(out, "{}", schedule)?;
+    }
+    Ok(())
+}
+
+/// Print the program in canonical form.
+fn fmt(opts: FmtOpts) -> CalyxResult<()> {
+    let (src, path) = match opts.file {
+        Some(path) => (fs::read_to_string(&path)?, path),
+        None => {
+            let mut src = String::new();
+            io::stdin().read_to_string(&mut src)?;
+            (src, PathBuf::from("<stdin>"))
+        }
+    };
+    write!(
+        opts.output.get_write(),
+        "{}",
+        frontend::format(&src, &path)?
+    )?;
+    Ok(())
+}
+
+/// Run the interpreter binary installed next to this binary, falling back to
+/// the one on the `PATH`. The interpreter is a separate crate, so the binary
+/// is not available unless it was built.
+fn interp(opts: InterpOpts) -> CalyxResult<()> {
+    let sibling = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join("interp")))
+        .filter(|path| path.exists());
+    let bin = sibling.unwrap_or_else(|| PathBuf::from("interp"));
+    let status = process::Command::new(&bin)
+        .args(&opts.args)
+        .status()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => Error::Misc(
+                "The interpreter binary `interp` was not found next to this \
+                 binary or on the PATH. Build it with `cargo build -p interp`."
+                    .to_string(),
+            ),
+            _ => Error::Misc(format!(
+                "Failed to run interpreter `{}`: {}",
+                bin.to_string_lossy(),
+                err
+            )),
+        })?;
+    if !status.success() {
+        process::exit(status.code().unwrap_or(1));
     }
+    Ok(())
 }